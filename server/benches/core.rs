@@ -0,0 +1,134 @@
+//! Criterion benchmarks for the hot paths: directory scan, parallel grep, fuzzy ranking,
+//! and budget allocation. Synthetic repositories come from the integration-test
+//! `FixtureBuilder`, so inputs are identical across runs and machines.
+//!
+//! Run with `cargo bench --bench core`. See CONTRIBUTING.md for the baseline workflow.
+
+#[path = "../tests/helpers/mod.rs"]
+mod helpers;
+
+use codescope_server::budget::{allocate_budget, BudgetUnit};
+use codescope_server::fuzzy::run_search;
+use codescope_server::scan::{build_search_index, scan_files};
+use codescope_server::types::ScanConfig;
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use dashmap::DashMap;
+use helpers::{Fixture, FixtureBuilder};
+use serde_json::json;
+
+/// Synthetic repo sizes (files per language) used across benchmark groups.
+const SIZES: &[usize] = &[200, 1000];
+
+fn synthetic_repo(files_per_lang: usize) -> Fixture {
+    FixtureBuilder::new()
+        .generated(files_per_lang, "src/core", "rs")
+        .generated(files_per_lang, "web/app", "ts")
+        .generated(files_per_lang, "tools/py", "py")
+        .build()
+}
+
+fn bench_scan(c: &mut Criterion) {
+    let mut group = c.benchmark_group("scan_files");
+    group.sample_size(20);
+    for &n in SIZES {
+        let fx = synthetic_repo(n);
+        let config = ScanConfig::new(fx.root.clone());
+        group.throughput(Throughput::Elements((n * 3) as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(n * 3), &config, |b, config| {
+            b.iter(|| scan_files(config))
+        });
+    }
+    group.finish();
+}
+
+fn bench_grep(c: &mut Criterion) {
+    let mut group = c.benchmark_group("cs_grep");
+    group.sample_size(20);
+    for &n in SIZES {
+        let fx = synthetic_repo(n);
+        let state = fx.state();
+        group.throughput(Throughput::Elements((n * 3) as u64));
+        for (label, query) in
+            [("common", "return"), ("rare", "gen_symbol_7"), ("regex", "gen_\\w+_1\\d")]
+        {
+            group.bench_with_input(BenchmarkId::new(label, n * 3), &query, |b, query| {
+                b.iter(|| fx.call(&state, "cs_grep", json!({ "query": query, "limit": 50 })))
+            });
+        }
+    }
+    group.finish();
+}
+
+fn bench_fuzzy(c: &mut Criterion) {
+    let mut group = c.benchmark_group("fuzzy_search");
+    for &n in SIZES {
+        let fx = synthetic_repo(n);
+        let config = ScanConfig::new(fx.root.clone());
+        let (_, manifest) = scan_files(&config);
+        let (files, modules) = build_search_index(&manifest);
+        group.throughput(Throughput::Elements(files.len() as u64));
+        for query in ["gen_12", "srccoregen", "app gen"] {
+            group.bench_with_input(BenchmarkId::new(query, files.len()), &query, |b, query| {
+                b.iter(|| run_search(&files, &modules, query, 50, 20))
+            });
+        }
+    }
+    group.finish();
+}
+
+fn bench_budget(c: &mut Criterion) {
+    let mut group = c.benchmark_group("allocate_budget");
+    group.sample_size(20);
+    let fx = synthetic_repo(200);
+    let repo = fx.scan("bench");
+    let paths: Vec<String> = repo.all_files.iter().take(300).map(|f| f.rel_path.clone()).collect();
+    let tok = codescope_server::tokenizer::create_tokenizer("bytes-estimate");
+    for budget in [2_000usize, 20_000] {
+        // Cold: fresh stub cache every iteration, so disk reads and stub extraction are measured.
+        group.bench_with_input(BenchmarkId::new("cold", budget), &budget, |b, &budget| {
+            b.iter_batched(
+                DashMap::new,
+                |cache| {
+                    allocate_budget(
+                        &repo.root,
+                        &paths,
+                        &repo.all_files,
+                        budget,
+                        &BudgetUnit::Tokens,
+                        Some("gen symbol"),
+                        None,
+                        None,
+                        &repo.deps,
+                        &cache,
+                        &*tok,
+                        &repo.config,
+                    )
+                },
+                BatchSize::PerIteration,
+            )
+        });
+        // Warm: the repo's shared stub cache is populated after the first iteration.
+        group.bench_with_input(BenchmarkId::new("warm", budget), &budget, |b, &budget| {
+            b.iter(|| {
+                allocate_budget(
+                    &repo.root,
+                    &paths,
+                    &repo.all_files,
+                    budget,
+                    &BudgetUnit::Tokens,
+                    Some("gen symbol"),
+                    None,
+                    None,
+                    &repo.deps,
+                    &repo.stub_cache,
+                    &*tok,
+                    &repo.config,
+                )
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_scan, bench_grep, bench_fuzzy, bench_budget);
+criterion_main!(benches);
@@ -0,0 +1,237 @@
+//! Requests and responses of the `/api/*` routes, shared with the server: it serializes
+//! these same types, so a field added there is a field here.
+//!
+//! Query structs serialize to the query string their route reads; `None` fields are left
+//! out and get the server's defaults.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// ---------------------------------------------------------------------------
+// Search (`GET /api/search`)
+// ---------------------------------------------------------------------------
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SearchQuery {
+    pub q: String,
+    #[serde(rename = "fileLimit")]
+    pub file_limit: Option<usize>,
+    #[serde(rename = "moduleLimit")]
+    pub module_limit: Option<usize>,
+}
+
+/// A scored file match from a fuzzy search query, with match position indices.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SearchFileResult {
+    pub path: String,
+    pub filename: String,
+    pub dir: String,
+    pub ext: String,
+    pub desc: String,
+    pub category: String,
+    pub score: f64,
+    #[serde(rename = "filenameIndices")]
+    pub filename_indices: Vec<usize>,
+    #[serde(rename = "pathIndices")]
+    pub path_indices: Vec<usize>,
+}
+
+/// A scored module match from a fuzzy search query, with match position indices.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SearchModuleResult {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "fileCount")]
+    pub file_count: usize,
+    pub score: f64,
+    #[serde(rename = "matchedIndices")]
+    pub matched_indices: Vec<usize>,
+}
+
+/// Combined search response containing ranked file and module results with timing metadata.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SearchResponse {
+    pub files: Vec<SearchFileResult>,
+    pub modules: Vec<SearchModuleResult>,
+    #[serde(rename = "queryTime")]
+    pub query_time: f64,
+    #[serde(rename = "totalFiles")]
+    pub total_files: usize,
+    #[serde(rename = "totalModules")]
+    pub total_modules: usize,
+}
+
+// ---------------------------------------------------------------------------
+// Grep (`GET /api/grep`)
+// ---------------------------------------------------------------------------
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct GrepQuery {
+    pub q: String,
+    /// Comma-separated extensions.
+    pub ext: Option<String>,
+    /// Module category prefix.
+    pub cat: Option<String>,
+    pub limit: Option<usize>,
+    #[serde(rename = "maxPerFile")]
+    pub max_per_file: Option<usize>,
+    /// `module` or `dir`: also aggregate hits per group.
+    #[serde(rename = "groupBy", alias = "group_by")]
+    pub group_by: Option<String>,
+    /// Candidate files scanned at most (default 50000).
+    #[serde(rename = "maxFiles")]
+    pub max_files: Option<usize>,
+    /// Wall-clock budget in milliseconds (default 10000, max 60000).
+    #[serde(rename = "timeoutMs")]
+    pub timeout_ms: Option<u64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GrepMatch {
+    pub line: String,
+    #[serde(rename = "lineNum")]
+    pub line_num: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GrepFileResult {
+    pub path: String,
+    pub desc: String,
+    pub matches: Vec<GrepMatch>,
+    pub score: f64,
+}
+
+/// Hits aggregated under one module or directory, with the best-scoring file's first match.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GrepGroupResult {
+    pub key: String,
+    pub files: usize,
+    pub matches: usize,
+    #[serde(rename = "topPath")]
+    pub top_path: String,
+    #[serde(rename = "topMatch")]
+    pub top_match: Option<GrepMatch>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GrepResponse {
+    pub results: Vec<GrepFileResult>,
+    /// Present when `groupBy` is set; groups cover all hits, not just the `limit` files.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub groups: Option<Vec<GrepGroupResult>>,
+    #[serde(rename = "totalMatches")]
+    pub total_matches: usize,
+    #[serde(rename = "searchedFiles")]
+    pub searched_files: usize,
+    /// Candidates left out by the `maxFiles` cap.
+    #[serde(rename = "skippedFiles")]
+    pub skipped_files: usize,
+    /// The time budget ran out; results cover only the files searched until then.
+    #[serde(rename = "timedOut")]
+    pub timed_out: bool,
+    #[serde(rename = "queryTime")]
+    pub query_time: u64,
+    /// Index generation the results were computed from.
+    pub generation: u64,
+}
+
+// ---------------------------------------------------------------------------
+// File reads (`GET /api/file`, `POST /api/files`)
+// ---------------------------------------------------------------------------
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct FileQuery {
+    pub path: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FileResponse {
+    pub content: String,
+    pub lines: usize,
+    pub size: u64,
+    pub path: String,
+    pub truncated: bool,
+    /// Secret values masked by `[redact]`.
+    pub redacted: usize,
+    /// Source encoding when not UTF-8; `content` is transcoded.
+    pub encoding: Option<String>,
+    /// Current path of the file when `path` is where it was before a move.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub renamed_to: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct BatchFilesRequest {
+    pub paths: Vec<String>,
+    /// `stubs` for signatures only; full content otherwise.
+    #[serde(default)]
+    pub mode: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum BatchFileEntry {
+    Ok {
+        content: String,
+        size: u64,
+        /// Current path of the file when the requested one is where it was before a move.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        renamed_to: Option<String>,
+    },
+    Err {
+        error: String,
+    },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BatchFilesResponse {
+    pub files: HashMap<String, BatchFileEntry>,
+}
+
+// ---------------------------------------------------------------------------
+// Imports (`GET /api/imports`)
+// ---------------------------------------------------------------------------
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ImportsQuery {
+    pub path: String,
+    /// `imports`, `imported_by`, or `both` (default).
+    pub direction: Option<String>,
+}
+
+/// How an import edge was found, from least to most certain.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportConfidence {
+    /// A computed module name (`require('./locales/' + lang)`, `import_module(name)`)
+    /// matched by its literal prefix or a string assigned to the variable.
+    Low,
+    /// A literal name in a dynamic import (`import('./x')`, `importlib.import_module`),
+    /// or a dependency between Bazel packages.
+    Medium,
+    /// A static import statement.
+    #[default]
+    High,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ImportsResponse {
+    pub path: String,
+    pub imports: Vec<String>,
+    #[serde(rename = "importedBy")]
+    pub imported_by: Vec<String>,
+    /// Metadata for every edge listed above.
+    pub edges: Vec<ImportEdgeEntry>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportEdgeEntry {
+    pub from: String,
+    pub to: String,
+    pub symbols: u32,
+    pub statements: u32,
+    pub type_only: bool,
+    pub confidence: ImportConfidence,
+    pub weight: u32,
+}
@@ -0,0 +1,86 @@
+//! Client for the JSON routes under `/api`.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::api::*;
+
+/// Client for a CodeScope server's `/api` routes. Cheap to clone; clones share one
+/// connection pool.
+#[derive(Clone, Debug)]
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+}
+
+impl Client {
+    /// Client for the server at `base_url` (`http://localhost:8432`).
+    pub fn new(base_url: &str) -> Self {
+        Self::with_http(reqwest::Client::new(), base_url)
+    }
+
+    /// Client sending its requests through `http`, for custom timeouts, proxies, or
+    /// default headers.
+    pub fn with_http(http: reqwest::Client, base_url: &str) -> Self {
+        Self { http, base_url: base_url.trim_end_matches('/').to_string() }
+    }
+
+    /// Fuzzy file and module search (`/api/search`).
+    pub async fn search(&self, query: &SearchQuery) -> Result<SearchResponse, String> {
+        self.get("search", query).await
+    }
+
+    /// Content search (`/api/grep`). Terms separated by spaces match any of them.
+    pub async fn grep(&self, query: &GrepQuery) -> Result<GrepResponse, String> {
+        self.get("grep", query).await
+    }
+
+    /// One file's content (`/api/file`).
+    pub async fn read(&self, path: &str) -> Result<FileResponse, String> {
+        self.get("file", &FileQuery { path: path.to_string() }).await
+    }
+
+    /// Several files at once (`/api/files`); a file that cannot be read gets an error
+    /// entry instead of failing the request.
+    pub async fn read_many(
+        &self,
+        request: &BatchFilesRequest,
+    ) -> Result<BatchFilesResponse, String> {
+        let response = self.http.post(self.url("files")).json(request).send().await;
+        decode(response).await
+    }
+
+    /// What a file imports and what imports it (`/api/imports`).
+    pub async fn imports(&self, query: &ImportsQuery) -> Result<ImportsResponse, String> {
+        self.get("imports", query).await
+    }
+
+    fn url(&self, route: &str) -> String {
+        format!("{}/api/{route}", self.base_url)
+    }
+
+    async fn get<Q: Serialize, T: DeserializeOwned>(
+        &self,
+        route: &str,
+        query: &Q,
+    ) -> Result<T, String> {
+        decode(self.http.get(self.url(route)).query(query).send().await).await
+    }
+}
+
+/// The response body as `T`, or the server's `error` message for a failed request.
+async fn decode<T: DeserializeOwned>(
+    response: reqwest::Result<reqwest::Response>,
+) -> Result<T, String> {
+    let response = response.map_err(|e| format!("Request failed: {e}"))?;
+    let status = response.status();
+    let body = response.text().await.map_err(|e| format!("Failed to read response: {e}"))?;
+    if !status.is_success() {
+        let message = serde_json::from_str::<serde_json::Value>(&body)
+            .ok()
+            .and_then(|v| v["error"].as_str().map(str::to_string))
+            .unwrap_or(body);
+        return Err(format!("HTTP {status}: {message}"));
+    }
+    serde_json::from_str(&body).map_err(|e| format!("Unexpected response: {e}"))
+}
@@ -0,0 +1,38 @@
+//! Typed async client for a running CodeScope server (`codescope web`).
+//!
+//! Two transports, one per server surface:
+//!
+//! - [`Client`] calls the JSON routes under `/api` (search, grep, file reads, imports) and
+//!   returns the [`api`] response types the server serializes.
+//! - [`McpClient`] speaks MCP Streamable HTTP at `/mcp` and calls the `cs_*` tools,
+//!   including `cs_git`, which has no `/api` route. Tool results are the text the server
+//!   gives agents.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), String> {
+//! use codescope_client::{api::GrepQuery, Client, GitArgs, McpClient};
+//!
+//! let api = Client::new("http://localhost:8432");
+//! let hits = api.grep(&GrepQuery { q: "parse_config".into(), ..Default::default() }).await?;
+//! println!("{} matches", hits.total_matches);
+//!
+//! let mcp = McpClient::connect("http://localhost:8432").await?;
+//! let history = GitArgs { action: "history".into(), path: Some("src/main.rs".into()), ..Default::default() };
+//! println!("{}", mcp.git(&history).await?);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! With `default-features = false` only [`api`] is built, which is how the server itself
+//! depends on this crate.
+
+pub mod api;
+#[cfg(feature = "http")]
+mod http;
+#[cfg(feature = "http")]
+mod mcp;
+
+#[cfg(feature = "http")]
+pub use http::Client;
+#[cfg(feature = "http")]
+pub use mcp::{GitArgs, GrepArgs, ImportsArgs, McpClient, ReadArgs, SearchArgs};
@@ -0,0 +1,254 @@
+//! Client for the MCP Streamable HTTP endpoint at `/mcp`.
+
+use serde::Serialize;
+use serde_json::json;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// MCP protocol version requested at `initialize`.
+const PROTOCOL_VERSION: &str = "2025-11-25";
+const SESSION_HEADER: &str = "mcp-session-id";
+const PROTOCOL_VERSION_HEADER: &str = "mcp-protocol-version";
+
+/// `cs_search` arguments.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SearchArgs {
+    pub query: String,
+    /// Comma-separated extensions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ext: Option<String>,
+    /// Path prefix.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+}
+
+/// `cs_grep` arguments.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct GrepArgs {
+    pub query: String,
+    /// `all` (default), `any`, `exact`, or `regex`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub match_mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ext: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_per_file: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+}
+
+/// `cs_read` arguments: `path` for one file, `paths` for a batch.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ReadArgs {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub paths: Option<Vec<String>>,
+    /// `full` (default), `stubs`, `outline`, or `smart`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_line: Option<usize>,
+    /// Token budget for a batch.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub budget: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+}
+
+/// `cs_imports` arguments.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ImportsArgs {
+    pub path: String,
+    /// `imports`, `imported_by`, or `both` (default).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub direction: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transitive: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+}
+
+/// `cs_git` arguments.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct GitArgs {
+    /// `blame`, `history`, `changed`, `compare`, `diffstat`, or `hotspots`.
+    pub action: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub head: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+}
+
+/// An MCP session with a CodeScope server. The session keeps the server-side state tools
+/// use across calls, such as the files already read for `cs_read` diffs.
+#[derive(Debug)]
+pub struct McpClient {
+    http: reqwest::Client,
+    endpoint: String,
+    session_id: String,
+    protocol_version: String,
+    next_id: AtomicU64,
+}
+
+impl McpClient {
+    /// Open a session with the server at `base_url` (`http://localhost:8432`).
+    pub async fn connect(base_url: &str) -> Result<Self, String> {
+        Self::connect_with(reqwest::Client::new(), base_url).await
+    }
+
+    /// Open a session sending requests through `http`, e.g. one with an `Authorization`
+    /// default header for servers started with `--auth-issuer`.
+    pub async fn connect_with(http: reqwest::Client, base_url: &str) -> Result<Self, String> {
+        let endpoint = format!("{}/mcp", base_url.trim_end_matches('/'));
+        let initialize = json!({
+            "jsonrpc": "2.0",
+            "id": 0,
+            "method": "initialize",
+            "params": {
+                "protocolVersion": PROTOCOL_VERSION,
+                "capabilities": {},
+                "clientInfo": { "name": "codescope-client", "version": env!("CARGO_PKG_VERSION") }
+            }
+        });
+        let response = http
+            .post(&endpoint)
+            .json(&initialize)
+            .send()
+            .await
+            .map_err(|e| format!("Request failed: {e}"))?;
+        let session_id = response
+            .headers()
+            .get(SESSION_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+            .ok_or_else(|| format!("No {SESSION_HEADER} header in the initialize response"))?;
+        let result = rpc_result(response).await?;
+        let protocol_version =
+            result["protocolVersion"].as_str().unwrap_or(PROTOCOL_VERSION).to_string();
+        let client =
+            Self { http, endpoint, session_id, protocol_version, next_id: AtomicU64::new(1) };
+        client.post(&json!({ "jsonrpc": "2.0", "method": "notifications/initialized" })).await?;
+        Ok(client)
+    }
+
+    /// Protocol version the server agreed to.
+    pub fn protocol_version(&self) -> &str {
+        &self.protocol_version
+    }
+
+    /// Call any tool; the text of its result, or of its error.
+    pub async fn call_tool(
+        &self,
+        name: &str,
+        arguments: serde_json::Value,
+    ) -> Result<String, String> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "tools/call",
+            "params": { "name": name, "arguments": arguments }
+        });
+        let result = rpc_result(self.post(&request).await?).await?;
+        let text = result["content"]
+            .as_array()
+            .map(|blocks| {
+                let texts: Vec<&str> = blocks.iter().filter_map(|b| b["text"].as_str()).collect();
+                texts.join("\n")
+            })
+            .unwrap_or_default();
+        // The server flags tool errors with a `⚠ Error: ` prefix rather than `isError`, which
+        // some agents treat as fatal to every call in flight
+        if let Some(rest) = text.strip_prefix("\u{26a0} ") {
+            return Err(rest.split_once(": ").map_or(rest, |(_, message)| message).to_string());
+        }
+        if result["isError"].as_bool() == Some(true) {
+            return Err(text);
+        }
+        Ok(text)
+    }
+
+    /// `cs_search`: files ranked by name, content, and (when enabled) semantic matches.
+    pub async fn search(&self, args: &SearchArgs) -> Result<String, String> {
+        self.call("cs_search", args).await
+    }
+
+    /// `cs_grep`: matching lines with context.
+    pub async fn grep(&self, args: &GrepArgs) -> Result<String, String> {
+        self.call("cs_grep", args).await
+    }
+
+    /// `cs_read`: file content, stubs, or an outline.
+    pub async fn read(&self, args: &ReadArgs) -> Result<String, String> {
+        self.call("cs_read", args).await
+    }
+
+    /// `cs_imports`: import relationships, or the files a change affects.
+    pub async fn imports(&self, args: &ImportsArgs) -> Result<String, String> {
+        self.call("cs_imports", args).await
+    }
+
+    /// `cs_git`: blame, history, changed files, and churn.
+    pub async fn git(&self, args: &GitArgs) -> Result<String, String> {
+        self.call("cs_git", args).await
+    }
+
+    /// End the session on the server.
+    pub async fn close(self) -> Result<(), String> {
+        self.http
+            .delete(&self.endpoint)
+            .header(SESSION_HEADER, &self.session_id)
+            .send()
+            .await
+            .map_err(|e| format!("Request failed: {e}"))?;
+        Ok(())
+    }
+
+    async fn call<A: Serialize>(&self, tool: &str, args: &A) -> Result<String, String> {
+        let arguments = serde_json::to_value(args).map_err(|e| e.to_string())?;
+        self.call_tool(tool, arguments).await
+    }
+
+    async fn post(&self, body: &serde_json::Value) -> Result<reqwest::Response, String> {
+        self.http
+            .post(&self.endpoint)
+            .header(SESSION_HEADER, &self.session_id)
+            .header(PROTOCOL_VERSION_HEADER, &self.protocol_version)
+            .json(body)
+            .send()
+            .await
+            .map_err(|e| format!("Request failed: {e}"))
+    }
+}
+
+/// The `result` of a JSON-RPC response, or its error message.
+async fn rpc_result(response: reqwest::Response) -> Result<serde_json::Value, String> {
+    let status = response.status();
+    let body: serde_json::Value =
+        response.json().await.map_err(|e| format!("HTTP {status}: unexpected response: {e}"))?;
+    if let Some(message) = body["error"]["message"].as_str() {
+        return Err(format!("HTTP {status}: {message}"));
+    }
+    if !status.is_success() {
+        return Err(format!("HTTP {status}"));
+    }
+    Ok(body["result"].clone())
+}
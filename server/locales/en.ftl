@@ -0,0 +1,87 @@
+# CodeScope message catalog — English (fallback for every other locale).
+# Message IDs are shared across catalogs; keep them in sync when adding strings.
+
+## Shared
+
+error-prefix = Error
+cwd-unknown = Could not determine current directory: { $error }
+path-not-found = Path '{ $path }' not found: { $error }
+repo-spec-invalid = expected NAME=PATH, got '{ $spec }'
+
+## codescope init
+
+init-title = codescope { $version } init
+init-project-root = Project root: { $path }
+init-detected-none = Detected: no recognized project type
+init-scan-everything = Will scan all files from project root
+init-detected-workspace = Detected: { $types } ({ $info })
+init-detected-project = Detected: { $types } project
+init-scan-dirs = Scan dirs: { $dirs }
+init-config-exists = .codescope.toml already exists, skipping
+init-config-write-failed = Failed to write .codescope.toml: { $error }
+init-config-created = Created .codescope.toml
+init-mcp-already-configured = codescope already configured in .mcp.json
+init-mcp-merged = Added codescope to existing .mcp.json
+init-mcp-created = Created .mcp.json
+init-global-added = Added '{ $name }' to ~/.codescope/repos.toml
+init-validated-many = Validated: 10,000+ source files found
+init-validated = Validated: { $count } source files found
+init-no-files = No source files found with current settings.
+init-no-files-hint = Try removing scan_dirs from .codescope.toml to scan everything.
+init-semantic-building = Building semantic index...
+init-semantic-built = Semantic index built: { $chunks } chunks in { $seconds }s (cached to ~/.cache/codescope/)
+init-semantic-failed = Semantic index build failed (non-fatal)
+init-done = Open Claude Code in { $path } -- CodeScope tools are now available.
+org-syncing = Syncing { $count } repos into { $path }
+org-cloned = Cloned '{ $name }'
+org-updated = Updated '{ $name }'
+org-failed = '{ $name }': { $error }
+org-registered = Registered { $count } repos in ~/.codescope/repos.toml
+org-indexing = Indexing { $count } repos in the background; the next server start loads them
+org-indexing-failed = Could not start background indexing: { $error }
+org-result-failed = { $count } repos failed -- fix the issues above and run the command again
+
+## codescope doctor
+
+doctor-title = codescope doctor
+doctor-binary = codescope v{ $version }
+doctor-config-valid = .codescope.toml exists and is valid TOML
+doctor-config-invalid = .codescope.toml exists but is invalid: { $error }
+doctor-config-missing = .codescope.toml not found (will use defaults)
+doctor-config-env = Environment overrides .codescope.toml: { $vars }
+doctor-mcp-ok = .mcp.json has codescope entry
+doctor-mcp-no-entry = .mcp.json exists but missing codescope entry
+doctor-mcp-invalid = .mcp.json exists but is invalid JSON: { $error }
+doctor-mcp-missing = .mcp.json not found (run: codescope init)
+doctor-scan-found = Test scan: found { $count } files in { $elapsed }
+doctor-scan-empty = Test scan: no files found
+doctor-estimated-total = Estimated total files: { $count }
+doctor-nested-git = Found { $count } subdirectories with .git -- root may be too broad
+doctor-cache-size = Disk cache: { $size } in { $count } entries ({ $path })
+doctor-cache-missing = { $count } cache entries ({ $size }) belong to repos that no longer exist -- run: codescope cache prune --missing
+doctor-repos-missing = { $count } registered repos have no root directory ({ $names }) -- they are served as unavailable until the path reappears
+doctor-noise-proposed = { $count } directories look like noise: { $dirs }. Paste this into .codescope.toml, or set auto_noise = true, to rank them lower:
+doctor-noise-auto = auto_noise scores { $count } directories as noise: { $dirs }. Add a [noise] entry (0 to exempt) to override
+doctor-result-fail = Result: FAIL -- fix the issues above
+doctor-result-warn = Result: PASS with warnings
+doctor-result-pass = Result: ALL PASS
+
+## codescope check
+
+check-no-rules = No [boundaries] rules in .codescope.toml -- nothing to check
+check-result-pass = Boundaries: { $edges } import edges checked against { $rules } rules, no violations
+check-result-fail = Boundaries: { $count } violations -- fix the imports above or update [boundaries]
+check-result-invalid = Boundaries: { $count } invalid [boundaries] entries in .codescope.toml
+
+## codescope test-impact
+
+test-impact-result = Test impact since { $since }: { $changed } changed files reach { $sources } source files and { $tests } tests
+
+## codescope cache
+
+cache-dir-unknown = Could not determine cache directory (HOME/LOCALAPPDATA not set)
+cache-repo-missing = (repo missing)
+cache-total = { $count } entries, { $size } total in { $path }
+cache-no-policy = Nothing to prune: pass --older-than, --max-size or --missing, or set [cache] in ~/.codescope/repos.toml
+cache-pruned = Removed { $count } entries ({ $size })
+cache-would-prune = Would remove { $count } entries ({ $size })
@@ -0,0 +1,87 @@
+# Catálogo de mensajes de CodeScope — español.
+# Los mensajes que falten aquí se muestran en inglés (en.ftl).
+
+## Compartido
+
+error-prefix = Error
+cwd-unknown = No se pudo determinar el directorio actual: { $error }
+path-not-found = No se encontró la ruta '{ $path }': { $error }
+repo-spec-invalid = se esperaba NAME=PATH, se recibió '{ $spec }'
+
+## codescope init
+
+init-title = codescope { $version } init
+init-project-root = Raíz del proyecto: { $path }
+init-detected-none = Detectado: ningún tipo de proyecto reconocido
+init-scan-everything = Se escanearán todos los archivos desde la raíz del proyecto
+init-detected-workspace = Detectado: { $types } ({ $info })
+init-detected-project = Detectado: proyecto { $types }
+init-scan-dirs = Directorios a escanear: { $dirs }
+init-config-exists = .codescope.toml ya existe, se omite
+init-config-write-failed = No se pudo escribir .codescope.toml: { $error }
+init-config-created = Se creó .codescope.toml
+init-mcp-already-configured = codescope ya está configurado en .mcp.json
+init-mcp-merged = Se añadió codescope al .mcp.json existente
+init-mcp-created = Se creó .mcp.json
+init-global-added = Se añadió '{ $name }' a ~/.codescope/repos.toml
+init-validated-many = Validado: más de 10.000 archivos fuente encontrados
+init-validated = Validado: { $count } archivos fuente encontrados
+init-no-files = No se encontraron archivos fuente con la configuración actual.
+init-no-files-hint = Pruebe a quitar scan_dirs de .codescope.toml para escanearlo todo.
+init-semantic-building = Construyendo el índice semántico...
+init-semantic-built = Índice semántico construido: { $chunks } fragmentos en { $seconds }s (en caché en ~/.cache/codescope/)
+init-semantic-failed = Falló la construcción del índice semántico (no es fatal)
+init-done = Abra Claude Code en { $path }: las herramientas de CodeScope ya están disponibles.
+org-syncing = Sincronizando { $count } repositorios en { $path }
+org-cloned = Clonado '{ $name }'
+org-updated = Actualizado '{ $name }'
+org-failed = '{ $name }': { $error }
+org-registered = { $count } repositorios registrados en ~/.codescope/repos.toml
+org-indexing = Indexando { $count } repositorios en segundo plano; el próximo inicio del servidor los carga
+org-indexing-failed = No se pudo iniciar la indexación en segundo plano: { $error }
+org-result-failed = { $count } repositorios fallaron -- corrija los problemas anteriores y vuelva a ejecutar el comando
+
+## codescope doctor
+
+doctor-title = codescope doctor
+doctor-binary = codescope v{ $version }
+doctor-config-valid = .codescope.toml existe y es TOML válido
+doctor-config-invalid = .codescope.toml existe pero no es válido: { $error }
+doctor-config-missing = No se encontró .codescope.toml (se usarán los valores predeterminados)
+doctor-config-env = El entorno reemplaza .codescope.toml: { $vars }
+doctor-mcp-ok = .mcp.json contiene la entrada de codescope
+doctor-mcp-no-entry = .mcp.json existe pero falta la entrada de codescope
+doctor-mcp-invalid = .mcp.json existe pero no es JSON válido: { $error }
+doctor-mcp-missing = No se encontró .mcp.json (ejecute: codescope init)
+doctor-scan-found = Escaneo de prueba: { $count } archivos encontrados en { $elapsed }
+doctor-scan-empty = Escaneo de prueba: no se encontraron archivos
+doctor-estimated-total = Total estimado de archivos: { $count }
+doctor-nested-git = Se encontraron { $count } subdirectorios con .git: la raíz puede ser demasiado amplia
+doctor-cache-size = Caché en disco: { $size } en { $count } entradas ({ $path })
+doctor-cache-missing = { $count } entradas de caché ({ $size }) pertenecen a repositorios que ya no existen; ejecute: codescope cache prune --missing
+doctor-repos-missing = { $count } repositorios registrados no tienen directorio raíz ({ $names }); se muestran como no disponibles hasta que la ruta vuelva a existir
+doctor-noise-proposed = { $count } directorios parecen ruido: { $dirs }. Pegue esto en .codescope.toml, o active auto_noise = true, para clasificarlos más abajo:
+doctor-noise-auto = auto_noise puntúa { $count } directorios como ruido: { $dirs }. Añada una entrada [noise] (0 para eximir) para cambiarlo
+doctor-result-fail = Resultado: FALLO -- corrija los problemas anteriores
+doctor-result-warn = Resultado: CORRECTO con advertencias
+doctor-result-pass = Resultado: TODO CORRECTO
+
+## codescope check
+
+check-no-rules = No hay reglas [boundaries] en .codescope.toml: nada que comprobar
+check-result-pass = Límites: { $edges } importaciones comprobadas con { $rules } reglas, sin infracciones
+check-result-fail = Límites: { $count } infracciones; corrija las importaciones anteriores o actualice [boundaries]
+check-result-invalid = Límites: { $count } entradas [boundaries] no válidas en .codescope.toml
+
+## codescope test-impact
+
+test-impact-result = Impacto en pruebas desde { $since }: { $changed } archivos modificados alcanzan { $sources } archivos fuente y { $tests } pruebas
+
+## codescope cache
+
+cache-dir-unknown = No se pudo determinar el directorio de caché (HOME/LOCALAPPDATA no definido)
+cache-repo-missing = (repositorio inexistente)
+cache-total = { $count } entradas, { $size } en total en { $path }
+cache-no-policy = Nada que eliminar: indique --older-than, --max-size o --missing, o defina [cache] en ~/.codescope/repos.toml
+cache-pruned = Se eliminaron { $count } entradas ({ $size })
+cache-would-prune = Se eliminarían { $count } entradas ({ $size })
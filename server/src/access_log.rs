@@ -0,0 +1,450 @@
+//! Sampled access log of tool calls and HTTP API requests (`--access-log PATH`).
+//!
+//! Each sampled request appends one JSON line: the MCP tool or HTTP endpoint, latency,
+//! result count when known, whether it failed, and the query as a keyed hash. Hashes let
+//! a team count repeated queries without storing what was searched.
+//!
+//! Queries are hashed with HMAC-SHA256 under a random 256-bit key created on first use in
+//! [`crate::data_dir`] (`access-log.key`, readable by the owner only), away from the log so
+//! that a copy of the log alone is not enough to test guesses against it. Anyone who can
+//! read the key can still confirm a guessed query by hashing it, and equal queries always
+//! hash alike, so the log shows how often each query recurs. Latency, result counts, and
+//! which tool was called are stored as-is. Deleting the key starts a new, unrelated set of
+//! hashes.
+//!
+//! `--access-log-plaintext` also keeps the query text. `--access-log-sample 0.1` records
+//! one request in ten. The log rotates at [`MAX_LOG_BYTES`] to `PATH.1`, `PATH.2`, ...,
+//! keeping [`KEEP_ROTATED`] old files, and `/api/metrics` summarizes all of them.
+
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Instant;
+use tracing::warn;
+
+use crate::types::AppContext;
+
+/// Size at which the log is rotated.
+pub const MAX_LOG_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Rotated files kept (`PATH.1` is the newest).
+pub const KEEP_ROTATED: usize = 3;
+
+/// Query hashes listed per tool or endpoint in [`AccessMetrics`].
+const TOP_QUERIES: usize = 10;
+
+/// URL query parameters recorded (hashed) as an HTTP request's query.
+const HTTP_QUERY_PARAMS: &[&str] = &["q", "query", "pattern"];
+
+/// Bytes of the query hash key.
+const KEY_LEN: usize = 32;
+
+/// Bytes of the HMAC kept as the logged hash.
+const HASH_LEN: usize = 16;
+
+/// One logged request.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AccessEntry {
+    /// Unix seconds.
+    pub ts: i64,
+    /// `tool` or `http`.
+    pub kind: String,
+    /// Tool name or request path.
+    pub name: String,
+    pub latency_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub results: Option<usize>,
+    pub error: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query_hash: Option<String>,
+    /// Only with `--access-log-plaintext`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+    /// Sample rate in effect, so summaries can estimate the full request count.
+    pub sample: f64,
+}
+
+/// An open access log.
+pub struct AccessLog {
+    path: PathBuf,
+    sample: f64,
+    plaintext: bool,
+    key: [u8; KEY_LEN],
+    /// The open file and its size.
+    file: Mutex<(File, u64)>,
+}
+
+impl AccessLog {
+    /// Open (appending to) the log at `path`. `sample` is clamped to 0–1.
+    pub fn open(path: &Path, sample: f64, plaintext: bool) -> Result<Self, String> {
+        let key_path = match crate::data_dir() {
+            Some(dir) => dir.join("access-log.key"),
+            None => {
+                let mut name = path.as_os_str().to_owned();
+                name.push(".key");
+                PathBuf::from(name)
+            }
+        };
+        Self::open_with_key(path, &key_path, sample, plaintext)
+    }
+
+    /// [`AccessLog::open`] with the query hash key at `key_path`.
+    pub fn open_with_key(
+        path: &Path,
+        key_path: &Path,
+        sample: f64,
+        plaintext: bool,
+    ) -> Result<Self, String> {
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Cannot create {}: {e}", dir.display()))?;
+        }
+        let file = open_append(path)?;
+        let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+        Ok(Self {
+            path: path.to_path_buf(),
+            sample: sample.clamp(0.0, 1.0),
+            plaintext,
+            key: load_key(key_path)?,
+            file: Mutex::new((file, size)),
+        })
+    }
+
+    pub fn sample_rate(&self) -> f64 {
+        self.sample
+    }
+
+    /// Keyed hash of a query, ignoring case and runs of whitespace.
+    pub fn hash_query(&self, query: &str) -> String {
+        let normalized = query.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC takes any key size");
+        mac.update(normalized.as_bytes());
+        mac.finalize().into_bytes()[..HASH_LEN].iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    /// Log one request if it falls in the sample.
+    pub fn record(
+        &self,
+        kind: &str,
+        name: &str,
+        latency_ms: u64,
+        results: Option<usize>,
+        error: bool,
+        query: Option<&str>,
+    ) {
+        if !self.sampled() {
+            return;
+        }
+        let query = query.map(str::trim).filter(|q| !q.is_empty());
+        let entry = AccessEntry {
+            ts: crate::ranking::now_secs(),
+            kind: kind.to_string(),
+            name: name.to_string(),
+            latency_ms,
+            results,
+            error,
+            query_hash: query.map(|q| self.hash_query(q)),
+            query: query.filter(|_| self.plaintext).map(String::from),
+            sample: self.sample,
+        };
+        let Ok(mut line) = serde_json::to_string(&entry) else { return };
+        line.push('\n');
+        if let Err(e) = self.append(line.as_bytes()) {
+            warn!(path = %self.path.display(), error = %e, "Could not write access log");
+        }
+    }
+
+    fn sampled(&self) -> bool {
+        if self.sample >= 1.0 {
+            return true;
+        }
+        let (random, _) = uuid::Uuid::new_v4().as_u64_pair();
+        (random as f64 / u64::MAX as f64) < self.sample
+    }
+
+    fn append(&self, line: &[u8]) -> Result<(), String> {
+        let mut guard = self.file.lock().map_err(|_| "access log lock poisoned".to_string())?;
+        let (file, size) = &mut *guard;
+        if *size > 0 && *size + line.len() as u64 > MAX_LOG_BYTES {
+            drop(std::mem::replace(file, rotate(&self.path)?));
+            *size = 0;
+        }
+        file.write_all(line).map_err(|e| e.to_string())?;
+        *size += line.len() as u64;
+        Ok(())
+    }
+
+    /// The log followed by its rotated files, oldest first, that exist.
+    pub fn files(&self) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> =
+            (1..=KEEP_ROTATED).rev().map(|i| rotated(&self.path, i)).collect();
+        files.push(self.path.clone());
+        files.into_iter().filter(|p| p.is_file()).collect()
+    }
+
+    /// Every entry still on disk, oldest first. Unparseable lines are skipped.
+    pub fn entries(&self) -> Vec<AccessEntry> {
+        let mut entries = Vec::new();
+        for path in self.files() {
+            let Ok(file) = File::open(&path) else { continue };
+            let lines = BufReader::new(file).lines().map_while(Result::ok);
+            entries.extend(lines.filter_map(|line| serde_json::from_str(&line).ok()));
+        }
+        entries
+    }
+}
+
+fn open_append(path: &Path) -> Result<File, String> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("Cannot open access log {}: {e}", path.display()))
+}
+
+fn rotated(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{n}"));
+    PathBuf::from(name)
+}
+
+/// Shift `PATH.N` to `PATH.N+1` (dropping the oldest), move the log to `PATH.1`, and
+/// open a fresh log.
+fn rotate(path: &Path) -> Result<File, String> {
+    let _ = std::fs::remove_file(rotated(path, KEEP_ROTATED));
+    for i in (1..KEEP_ROTATED).rev() {
+        let _ = std::fs::rename(rotated(path, i), rotated(path, i + 1));
+    }
+    std::fs::rename(path, rotated(path, 1))
+        .map_err(|e| format!("Cannot rotate {}: {e}", path.display()))?;
+    open_append(path)
+}
+
+/// The query hash key stored at `path`, created on first use with owner-only permissions.
+fn load_key(path: &Path) -> Result<[u8; KEY_LEN], String> {
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Cannot create {}: {e}", dir.display()))?;
+    }
+    let mut key = [0u8; KEY_LEN];
+    aes_gcm::aead::rand_core::RngCore::fill_bytes(&mut aes_gcm::aead::OsRng, &mut key);
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    match options.open(path) {
+        Ok(mut file) => {
+            file.write_all(&key)
+                .map_err(|e| format!("Cannot write access log key {}: {e}", path.display()))?;
+            return Ok(key);
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+        Err(e) => return Err(format!("Cannot create access log key {}: {e}", path.display())),
+    }
+    let existing = std::fs::read(path)
+        .map_err(|e| format!("Cannot read access log key {}: {e}", path.display()))?;
+    #[cfg(unix)]
+    if let Ok(meta) = std::fs::metadata(path) {
+        use std::os::unix::fs::PermissionsExt;
+        if meta.permissions().mode() & 0o077 != 0 {
+            warn!(path = %path.display(), "Access log key is readable by other users");
+        }
+    }
+    existing.try_into().map_err(|_| {
+        format!(
+            "Access log key {} is not {KEY_LEN} bytes; delete it to make a new one",
+            path.display()
+        )
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Summaries for /api/metrics
+// ---------------------------------------------------------------------------
+
+/// Access log summary served by `/api/metrics`; the default when no log is open.
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessMetrics {
+    pub enabled: bool,
+    pub sample_rate: f64,
+    /// Entries read from the log and its rotated files.
+    pub entries: usize,
+    /// Unix seconds of the oldest and newest entry.
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    /// Per tool (`tool:cs_search`) or endpoint (`http:/api/grep`).
+    pub by_name: BTreeMap<String, NameMetrics>,
+}
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct NameMetrics {
+    /// Sampled requests.
+    pub count: usize,
+    /// Requests the sample stands for.
+    pub estimated: f64,
+    pub errors: usize,
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+    pub max_ms: u64,
+    /// Mean result count over requests that reported one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mean_results: Option<f64>,
+    /// Requests that returned no results.
+    pub empty: usize,
+    /// Most repeated query hashes with their counts.
+    pub top_queries: Vec<(String, usize)>,
+}
+
+/// Aggregate `entries` per tool or endpoint.
+pub fn summarize(entries: &[AccessEntry], sample_rate: f64) -> AccessMetrics {
+    let mut groups: BTreeMap<String, Vec<&AccessEntry>> = BTreeMap::new();
+    for e in entries {
+        groups.entry(format!("{}:{}", e.kind, e.name)).or_default().push(e);
+    }
+    let by_name = groups
+        .into_iter()
+        .map(|(name, group)| {
+            let mut latencies: Vec<u64> = group.iter().map(|e| e.latency_ms).collect();
+            latencies.sort_unstable();
+            let percentile = |p: usize| latencies[(latencies.len() - 1) * p / 100];
+            let results: Vec<usize> = group.iter().filter_map(|e| e.results).collect();
+            let mut queries: BTreeMap<&str, usize> = BTreeMap::new();
+            for hash in group.iter().filter_map(|e| e.query_hash.as_deref()) {
+                *queries.entry(hash).or_default() += 1;
+            }
+            let mut top_queries: Vec<(String, usize)> =
+                queries.into_iter().map(|(h, n)| (h.to_string(), n)).collect();
+            top_queries.sort_by(|a, b| b.1.cmp(&a.1));
+            top_queries.truncate(TOP_QUERIES);
+            let metrics = NameMetrics {
+                count: group.len(),
+                estimated: group.iter().map(|e| 1.0 / e.sample.max(f64::EPSILON)).sum(),
+                errors: group.iter().filter(|e| e.error).count(),
+                p50_ms: percentile(50),
+                p95_ms: percentile(95),
+                max_ms: latencies[latencies.len() - 1],
+                mean_results: (!results.is_empty())
+                    .then(|| results.iter().sum::<usize>() as f64 / results.len() as f64),
+                empty: results.iter().filter(|&&n| n == 0).count(),
+                top_queries,
+            };
+            (name, metrics)
+        })
+        .collect();
+    AccessMetrics {
+        enabled: true,
+        sample_rate,
+        entries: entries.len(),
+        since: entries.iter().map(|e| e.ts).min(),
+        until: entries.iter().map(|e| e.ts).max(),
+        by_name,
+    }
+}
+
+/// Result count of a tool response: the length of a structured `results` list, else a
+/// count leading its first line (`12 matches in 3 files`, `Found 4 matches ...`).
+pub fn tool_result_count(text: &str, structured: Option<&serde_json::Value>) -> Option<usize> {
+    if let Some(results) = structured.and_then(|s| s["results"].as_array()) {
+        return Some(results.len());
+    }
+    let first = text.lines().next()?;
+    let first = first.strip_prefix("Found ").unwrap_or(first);
+    let digits: &str = &first[..first.find(|c: char| !c.is_ascii_digit()).unwrap_or(first.len())];
+    digits.parse().ok()
+}
+
+/// Axum middleware logging HTTP API requests to the access log, if one is open.
+pub async fn record_http(State(ctx): State<AppContext>, req: Request, next: Next) -> Response {
+    let Some(log) = ctx.state.snapshot().access_log.clone() else {
+        return next.run(req).await;
+    };
+    let path = req.uri().path().to_string();
+    let query = query_pairs(req.uri())
+        .into_iter()
+        .find(|(k, _)| HTTP_QUERY_PARAMS.contains(&k.as_str()))
+        .map(|(_, v)| v);
+    let start = Instant::now();
+    let response = next.run(req).await;
+    let failed = response.status().is_client_error() || response.status().is_server_error();
+    let latency = start.elapsed().as_millis() as u64;
+    log.record("http", &path, latency, None, failed, query.as_deref());
+    response
+}
+
+/// `key=value` pairs of a request's query string, percent-decoded.
+fn query_pairs(uri: &axum::http::Uri) -> Vec<(String, String)> {
+    axum::extract::Query::<Vec<(String, String)>>::try_from_uri(uri)
+        .map(|q| q.0)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_rotate_and_summarize_with_hashed_queries() {
+        let dir = std::env::temp_dir().join(format!("cs-access-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.join("logs/access.log");
+        let key = dir.join("keys/access-log.key");
+        let log = AccessLog::open_with_key(&path, &key, 1.0, false).unwrap();
+        log.record("tool", "cs_search", 12, Some(4), false, Some("Auth  Flow"));
+        log.record("tool", "cs_search", 30, Some(0), false, Some("auth flow"));
+        log.record("tool", "cs_grep", 5, None, true, None);
+
+        let entries = log.entries();
+        assert_eq!(entries.len(), 3);
+        assert!(entries.iter().all(|e| e.query.is_none()), "no plaintext by default");
+        assert_eq!(entries[0].query_hash, entries[1].query_hash, "normalized before hashing");
+        let reopened = AccessLog::open_with_key(&path, &key, 1.0, true).unwrap();
+        assert_eq!(reopened.hash_query("auth flow"), entries[0].query_hash.clone().unwrap());
+        assert_eq!(reopened.hash_query("auth flow").len(), 2 * HASH_LEN);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&key).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600, "key readable by the owner only");
+        }
+        let other_key = dir.join("keys/other.key");
+        let other = AccessLog::open_with_key(&path, &other_key, 1.0, false).unwrap();
+        assert_ne!(other.hash_query("auth flow"), reopened.hash_query("auth flow"));
+        std::fs::write(&other_key, b"short").unwrap();
+        assert!(AccessLog::open_with_key(&path, &other_key, 1.0, false).is_err());
+
+        let metrics = summarize(&entries, 1.0);
+        let search = &metrics.by_name["tool:cs_search"];
+        assert_eq!((search.count, search.p50_ms, search.max_ms), (2, 12, 30));
+        assert_eq!((search.mean_results, search.empty), (Some(2.0), 1));
+        assert_eq!(search.top_queries[0].1, 2);
+        assert_eq!(metrics.by_name["tool:cs_grep"].errors, 1);
+
+        let line = vec![b'x'; MAX_LOG_BYTES as usize];
+        reopened.append(&line).unwrap();
+        reopened.record("http", "/api/search", 1, None, false, Some("q"));
+        assert_eq!(reopened.files().len(), 3, "rotated before each oversized write");
+        assert_eq!(reopened.entries().len(), 4, "the oversized line is not an entry");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn result_counts_come_from_structured_results_or_the_first_line() {
+        let structured = serde_json::json!({ "results": [1, 2, 3] });
+        assert_eq!(tool_result_count("whatever", Some(&structured)), Some(3));
+        assert_eq!(tool_result_count("12 matches in 3 files\n...", None), Some(12));
+        assert_eq!(tool_result_count("Found 4 matches in 2 files", None), Some(4));
+        assert_eq!(tool_result_count("Impact analysis for x", None), None);
+    }
+}
@@ -0,0 +1,158 @@
+//! Query alias expansion from `[aliases]` in `.codescope.toml`.
+//!
+//! ```toml
+//! [aliases]
+//! ingestion = ["ing", "ingest"]
+//! billing = "blg"
+//! ```
+//!
+//! Each entry makes a group of interchangeable words. A `cs_search` or `cs_grep` query
+//! word from a group also matches the other words of that group, in either direction, so
+//! a search for "billing" finds `BlgInvoice` and one for "blg" finds `billing_run`. The
+//! typed word keeps matching anywhere in a line; its aliases only match as whole words or
+//! identifier parts (`ing_queue`, `IngWorker`, but not `string`), since short acronyms
+//! occur inside unrelated words all the time. `exact` and `regex` match modes are not
+//! expanded.
+
+use regex::{Regex, RegexBuilder};
+use std::collections::HashMap;
+use tracing::warn;
+
+/// `[aliases]` word groups.
+#[derive(Clone, Debug, Default)]
+pub struct AliasConfig {
+    /// Lowercase word → the other words of every group containing it.
+    words: HashMap<String, Vec<String>>,
+}
+
+/// One query word and the aliases it also matches.
+#[derive(Clone, Debug)]
+pub struct QueryTerm {
+    /// Lowercase query word.
+    pub term: String,
+    /// Lowercase aliases, excluding `term`.
+    pub aliases: Vec<String>,
+}
+
+impl AliasConfig {
+    /// Parse the `[aliases]` table. Values that are not a string or array of strings are
+    /// skipped with a warning.
+    pub fn from_table(table: &toml::Table) -> Self {
+        let mut config = Self::default();
+        for (key, value) in table {
+            let mut group = vec![key.to_lowercase()];
+            match value {
+                toml::Value::String(s) => group.push(s.to_lowercase()),
+                toml::Value::Array(a) => {
+                    group.extend(a.iter().filter_map(|v| v.as_str()).map(str::to_lowercase))
+                }
+                _ => {
+                    warn!(
+                        key = key.as_str(),
+                        "[aliases] values must be strings or arrays — ignoring"
+                    );
+                    continue;
+                }
+            }
+            group.retain(|w| !w.trim().is_empty());
+            for word in &group {
+                let others = config.words.entry(word.clone()).or_default();
+                for other in &group {
+                    if other != word && !others.contains(other) {
+                        others.push(other.clone());
+                    }
+                }
+            }
+        }
+        config
+    }
+
+    /// Query words with their aliases.
+    pub fn expand(&self, terms_lower: &[String]) -> Vec<QueryTerm> {
+        terms_lower
+            .iter()
+            .map(|t| QueryTerm {
+                term: t.clone(),
+                aliases: self.words.get(t).cloned().unwrap_or_default(),
+            })
+            .collect()
+    }
+}
+
+impl QueryTerm {
+    /// Whether a line contains the word, or one of its aliases as a whole word.
+    pub fn matches(&self, line: &str, line_lower: &str) -> bool {
+        line_lower.contains(self.term.as_str())
+            || self.aliases.iter().any(|a| contains_word(line, a))
+    }
+}
+
+/// Case-insensitive regex matching any query word or alias, for a quick per-line
+/// prefilter. `None` when no word has aliases.
+pub fn alias_pattern(terms: &[QueryTerm]) -> Option<Regex> {
+    if terms.iter().all(|t| t.aliases.is_empty()) {
+        return None;
+    }
+    let alternatives: Vec<String> = terms
+        .iter()
+        .flat_map(|t| std::iter::once(&t.term).chain(&t.aliases))
+        .map(|w| regex::escape(w))
+        .collect();
+    RegexBuilder::new(&alternatives.join("|")).case_insensitive(true).build().ok()
+}
+
+/// Whether `word` (lowercase) occurs in `line` as a whole word or identifier part: not
+/// preceded or followed by a letter, unless a lower-to-upper case change separates them.
+fn contains_word(line: &str, word: &str) -> bool {
+    // ASCII lowercasing keeps byte offsets aligned with `line`
+    let lower = line.to_ascii_lowercase();
+    let bytes = line.as_bytes();
+    let mut from = 0;
+    while let Some(pos) = lower[from..].find(word) {
+        let start = from + pos;
+        let end = start + word.len();
+        let starts_part = start == 0
+            || !bytes[start - 1].is_ascii_alphabetic()
+            || (!bytes[start - 1].is_ascii_uppercase() && bytes[start].is_ascii_uppercase());
+        let ends_part = end == bytes.len()
+            || !bytes[end].is_ascii_alphabetic()
+            || (bytes[end].is_ascii_uppercase() && !bytes[end - 1].is_ascii_uppercase());
+        if starts_part && ends_part {
+            return true;
+        }
+        from = start + lower[start..].chars().next().map_or(1, char::len_utf8);
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aliases_expand_both_ways_and_match_identifier_parts() {
+        let table: toml::Table = toml::from_str(
+            r#"
+            ingestion = ["ing", "Ingest"]
+            billing = "blg"
+            broken = 3
+            "#,
+        )
+        .unwrap();
+        let aliases = AliasConfig::from_table(&table);
+        let terms = aliases.expand(&["ingestion".to_string(), "blg".to_string(), "x".into()]);
+        assert_eq!(terms[0].aliases, ["ing", "ingest"]);
+        assert_eq!(terms[1].aliases, ["billing"]);
+        assert!(terms[2].aliases.is_empty());
+
+        let ing = &terms[0];
+        for line in ["let q = ing_queue();", "struct IngWorker;", "ING_BATCH = 3", "use ing;"] {
+            assert!(ing.matches(line, &line.to_lowercase()), "{line}");
+        }
+        for line in ["let s = String::new();", "thing.run()", "Ingress"] {
+            assert!(!ing.matches(line, &line.to_lowercase()), "{line}");
+        }
+        assert!(alias_pattern(&terms).unwrap().is_match("fn run_blg()"));
+        assert!(alias_pattern(&aliases.expand(&["x".to_string()])).is_none());
+    }
+}
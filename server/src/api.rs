@@ -1,7 +1,7 @@
 //! HTTP API handlers for the CodeScope web UI.
 //!
 //! Routes serve file trees, manifests, dependencies, grep results, search results,
-//! and import graphs as JSON. All endpoints are mounted under `/api/*` by the
+//! import graphs, and licenses as JSON. All endpoints are mounted under `/api/*` by the
 //! main HTTP server.
 
 use axum::{
@@ -9,43 +9,50 @@ use axum::{
     http::StatusCode,
     response::IntoResponse,
 };
-use regex::RegexBuilder;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::time::Instant;
 
+use crate::access_log::AccessMetrics;
 use crate::budget::{allocate_budget, ContextRequest, ContextResponse};
-use crate::fuzzy::{preprocess_search_query, run_search, SearchResponse};
+use crate::fuzzy::{
+    parse_query_operators, preprocess_search_query, run_search_ranked, SearchResponse,
+};
+use crate::manifests::Manifest;
 use crate::scan::get_category_path;
-use crate::stubs::extract_stubs;
+use crate::stubs::extract_file_stubs;
 use crate::types::*;
-
-/// Acquire read lock on server state, returning HTTP 500 if the lock is poisoned.
-fn read_state(
-    state: &std::sync::RwLock<ServerState>,
-) -> Result<std::sync::RwLockReadGuard<'_, ServerState>, (StatusCode, Json<serde_json::Value>)> {
-    state.read().map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": "Internal server error" })),
-        )
-    })
-}
+use codescope_client::api::*;
 
 // ---------------------------------------------------------------------------
 // Health check endpoint
 // ---------------------------------------------------------------------------
 
-/// Health check endpoint returning server status, version, repo count, and uptime.
+/// Health check endpoint returning server status, version, repo count, and uptime, plus
+/// the `cs_status` report components: per-repo memory and scan stages, repos still
+/// indexing, semantic index state, and watcher activity. `status` is `indexing` until
+/// every registered repo has finished its first scan.
 pub async fn api_health(State(ctx): State<AppContext>) -> impl IntoResponse {
-    let s = ctx.state.read().unwrap();
+    let s = ctx.state.snapshot();
     let uptime = ctx.start_time.elapsed().as_secs();
+    let repos = crate::status::repos_status(&s);
+    let memory = crate::status::memory_status(&s);
+    let scan: serde_json::Map<String, serde_json::Value> =
+        repos.indexed.iter().map(|r| (r.name.clone(), serde_json::json!(r.scan))).collect();
+    let ready = repos.indexing.iter().all(|p| p.state == "lazy");
     Json(serde_json::json!({
-        "status": "ok",
+        "status": if ready { "ok" } else { "indexing" },
         "version": env!("CARGO_PKG_VERSION"),
-        "repos": s.repos.len(),
+        "repos": repos.indexed.len(),
+        "generation": s.generation,
         "uptime_seconds": uptime,
+        "memory_bytes": memory.total_bytes,
+        "memory": memory.repos,
+        "scan": scan,
+        "indexing": repos.indexing,
+        "semantic": crate::status::semantic_status(&s),
+        "watch": s.watch.status(),
     }))
 }
 
@@ -72,39 +79,26 @@ pub async fn api_deps(State(ctx): State<AppContext>) -> impl IntoResponse {
 // Single file read
 // ---------------------------------------------------------------------------
 
-#[derive(Deserialize)]
-pub struct FileQuery {
-    path: String,
-}
-
-#[derive(Serialize)]
-pub struct FileResponse {
-    content: String,
-    lines: usize,
-    size: u64,
-    path: String,
-    truncated: bool,
-}
-
 /// Read a single file by path, with optional truncation for large files.
 pub async fn api_file(
     State(ctx): State<AppContext>,
     Query(q): Query<FileQuery>,
 ) -> Result<Json<FileResponse>, (StatusCode, Json<serde_json::Value>)> {
-    let s = read_state(&ctx.state)?;
-    let repo = s.default_repo();
+    let s = ctx.state.snapshot();
 
-    let full_path = validate_path(&repo.root, &q.path)
+    let resolved = crate::paths::resolve(&s, &q.path, s.default_repo())
         .map_err(|e| (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": e }))))?;
+    let full_path = &resolved.abs_path;
 
-    let metadata = fs::metadata(&full_path).map_err(|_| {
+    let metadata = fs::metadata(full_path).map_err(|_| {
         (StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": "File not found" })))
     })?;
 
     let file_size = metadata.len();
-    let raw = fs::read_to_string(&full_path).map_err(|_| {
+    let (raw, encoding) = crate::encoding::read_with_encoding(full_path).map_err(|_| {
         (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({ "error": "Read error" })))
     })?;
+    let (raw, redacted) = crate::redact::redact(&resolved.repo.config.redact, raw);
 
     let truncated = raw.len() > MAX_FILE_READ;
     let content = if truncated {
@@ -119,62 +113,58 @@ pub async fn api_file(
 
     let lines = content.lines().count();
 
-    Ok(Json(FileResponse { content, lines, size: file_size, path: q.path, truncated }))
+    Ok(Json(FileResponse {
+        content,
+        lines,
+        size: file_size,
+        path: q.path,
+        truncated,
+        redacted,
+        encoding: encoding.map(str::to_string),
+        renamed_to: resolved.renamed_from.is_some().then(|| resolved.rel_path.clone()),
+    }))
 }
 
 // ---------------------------------------------------------------------------
 // Batch file read
 // ---------------------------------------------------------------------------
 
-#[derive(Deserialize)]
-pub struct BatchFilesRequest {
-    paths: Vec<String>,
-    #[serde(default)]
-    mode: Option<String>,
-}
-
-#[derive(Serialize)]
-#[serde(untagged)]
-enum BatchFileEntry {
-    Ok { content: String, size: u64 },
-    Err { error: String },
-}
-
-#[derive(Serialize)]
-pub struct BatchFilesResponse {
-    files: HashMap<String, BatchFileEntry>,
-}
-
 /// Batch-read multiple files by path.
 pub async fn api_files(
     State(ctx): State<AppContext>,
     Json(body): Json<BatchFilesRequest>,
 ) -> Result<Json<BatchFilesResponse>, (StatusCode, Json<serde_json::Value>)> {
-    let s = read_state(&ctx.state)?;
-    let repo = s.default_repo();
+    let s = ctx.state.snapshot();
 
     let mut files = HashMap::new();
 
     for p in &body.paths {
-        match validate_path(&repo.root, p) {
-            Err(e) => {
-                files.insert(p.clone(), BatchFileEntry::Err { error: e.to_string() });
+        match crate::paths::resolve(&s, p, s.default_repo()) {
+            Err(error) => {
+                files.insert(p.clone(), BatchFileEntry::Err { error });
             }
-            Ok(full_path) => match fs::read_to_string(&full_path) {
+            Ok(resolved) => match crate::encoding::read_to_string(&resolved.abs_path) {
                 Err(_) => {
                     files
                         .insert(p.clone(), BatchFileEntry::Err { error: "Read error".to_string() });
                 }
                 Ok(raw) => {
+                    let (raw, _) = crate::redact::redact(&resolved.repo.config.redact, raw);
                     let use_stubs = body.mode.as_deref() == Some("stubs");
                     let content = if use_stubs {
-                        let ext = p.rsplit_once('.').map(|(_, e)| e).unwrap_or("");
-                        extract_stubs(&raw, ext)
+                        extract_file_stubs(
+                            &resolved.repo.config.languages,
+                            &resolved.rel_path,
+                            &raw,
+                            Default::default(),
+                        )
                     } else {
                         raw
                     };
                     let size = content.len() as u64;
-                    files.insert(p.clone(), BatchFileEntry::Ok { content, size });
+                    let renamed_to =
+                        resolved.renamed_from.is_some().then(|| resolved.rel_path.clone());
+                    files.insert(p.clone(), BatchFileEntry::Ok { content, size, renamed_to });
                 }
             },
         }
@@ -187,42 +177,6 @@ pub async fn api_files(
 // Grep
 // ---------------------------------------------------------------------------
 
-#[derive(Deserialize)]
-pub struct GrepQuery {
-    q: String,
-    ext: Option<String>,
-    cat: Option<String>,
-    limit: Option<usize>,
-    #[serde(rename = "maxPerFile")]
-    max_per_file: Option<usize>,
-}
-
-#[derive(Serialize)]
-struct GrepMatch {
-    line: String,
-    #[serde(rename = "lineNum")]
-    line_num: usize,
-}
-
-#[derive(Serialize)]
-struct GrepFileResult {
-    path: String,
-    desc: String,
-    matches: Vec<GrepMatch>,
-    score: f64,
-}
-
-#[derive(Serialize)]
-pub struct GrepResponse {
-    results: Vec<GrepFileResult>,
-    #[serde(rename = "totalMatches")]
-    total_matches: usize,
-    #[serde(rename = "searchedFiles")]
-    searched_files: usize,
-    #[serde(rename = "queryTime")]
-    query_time: u64,
-}
-
 /// Regex content search across indexed files with context lines.
 pub async fn api_grep(
     State(ctx): State<AppContext>,
@@ -253,24 +207,32 @@ pub async fn api_grep(
     // Multi-term OR: "cloud reconstruct" -> regex `cloud|reconstruct`
     let terms: Vec<String> = q.q.split_whitespace().map(|s| s.to_string()).collect();
     let pattern_str = terms.iter().map(|t| regex::escape(t)).collect::<Vec<_>>().join("|");
-    let pattern = RegexBuilder::new(&pattern_str).case_insensitive(true).build().map_err(|_| {
+    let pattern = grep_regex(&pattern_str).map_err(|_| {
         (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": "Invalid pattern" })))
     })?;
+    let group_by = q
+        .group_by
+        .as_deref()
+        .map(GrepGroupBy::parse)
+        .transpose()
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": e }))))?;
 
-    // Heavy file I/O — clone Arc, acquire read lock inside blocking closure.
-    // The read() call here is safe to unwrap: lock poisoning only occurs if a
-    // writer panics, and we never hold a write lock in request handlers.
-    let state = ctx.state.clone();
+    // Heavy file I/O — bind the request to the current index generation, then move the
+    // snapshot into the blocking closure.
+    let s = ctx.state.snapshot();
     let response = tokio::task::spawn_blocking(move || {
         use rayon::prelude::*;
 
-        let s = state.read().expect("state lock poisoned");
         let repo = s.default_repo();
+        let ranker = crate::ranking::Ranker::new(repo);
         let start = Instant::now();
+        let timeout = q.timeout_ms.unwrap_or(GREP_DEFAULT_TIMEOUT_MS).min(GREP_MAX_TIMEOUT_MS);
+        let deadline = GrepDeadline::after(std::time::Duration::from_millis(timeout));
 
-        let candidates: Vec<&ScannedFile> = repo
+        let mut candidates: Vec<&ScannedFile> = repo
             .all_files
             .iter()
+            .filter(|f| f.is_searchable() && repo.config.read_policy.permits(&f.rel_path))
             .filter(|f| {
                 if let Some(ref exts) = ext_filter {
                     if !exts.contains(&f.ext) {
@@ -286,6 +248,9 @@ pub async fn api_grep(
                 true
             })
             .collect();
+        let max_files = q.max_files.unwrap_or(GREP_DEFAULT_MAX_FILES).max(1);
+        let skipped_files = candidates.len().saturating_sub(max_files);
+        candidates.truncate(max_files);
 
         // Parallel grep: each file processed independently
         let terms_owned: Vec<String> = terms.iter().map(|t| t.to_lowercase()).collect();
@@ -293,13 +258,19 @@ pub async fn api_grep(
         let mut file_results: Vec<(GrepFileResult, usize)> = candidates
             .par_iter()
             .filter_map(|file| {
-                let content = fs::read_to_string(&file.abs_path).ok()?;
+                if deadline.expired() {
+                    return None;
+                }
+                let content = crate::encoding::read_to_string(&file.abs_path).ok()?;
                 let total_lines = content.lines().count().max(1);
                 let mut file_matches = Vec::new();
                 let mut total_match_count = 0usize;
                 let mut first_match_line_idx = usize::MAX;
                 let mut terms_seen: HashSet<usize> = HashSet::new();
                 for (i, line) in content.lines().enumerate() {
+                    if i % 4096 == 4095 && deadline.expired() {
+                        break;
+                    }
                     if pattern.is_match(line) {
                         total_match_count += 1;
                         if first_match_line_idx == usize::MAX {
@@ -335,7 +306,7 @@ pub async fn api_grep(
                         terms_seen.len(),
                         if first_match_line_idx == usize::MAX { 0 } else { first_match_line_idx },
                         &idf_weights,
-                    );
+                    ) * ranker.boost(&file.rel_path);
 
                     Some((
                         GrepFileResult {
@@ -355,6 +326,29 @@ pub async fn api_grep(
             .sort_by(|a, b| b.0.score.partial_cmp(&a.0.score).unwrap_or(std::cmp::Ordering::Equal));
 
         let searched_files = candidates.len();
+        let groups = group_by.map(|g| {
+            let keys: Vec<String> =
+                file_results.iter().map(|(r, _)| g.key(&r.path, &repo.config)).collect();
+            group_grep_hits(
+                file_results.iter().zip(&keys).map(|((r, n), k)| (k.as_str(), r.score, *n)),
+            )
+            .into_iter()
+            .take(limit)
+            .map(|grp| {
+                let top = &file_results[grp.top].0;
+                GrepGroupResult {
+                    key: grp.key,
+                    files: grp.files,
+                    matches: grp.matches,
+                    top_path: top.path.clone(),
+                    top_match: top
+                        .matches
+                        .first()
+                        .map(|m| GrepMatch { line: m.line.clone(), line_num: m.line_num }),
+                }
+            })
+            .collect()
+        });
         let mut results = Vec::new();
         let mut total_matches = 0usize;
         for (file_result, count) in file_results {
@@ -367,7 +361,16 @@ pub async fn api_grep(
 
         let query_time = start.elapsed().as_millis() as u64;
 
-        GrepResponse { results, total_matches, searched_files, query_time }
+        GrepResponse {
+            results,
+            groups,
+            total_matches,
+            searched_files,
+            skipped_files,
+            timed_out: deadline.timed_out(),
+            query_time,
+            generation: s.generation,
+        }
     })
     .await
     .unwrap();
@@ -379,26 +382,41 @@ pub async fn api_grep(
 // Search
 // ---------------------------------------------------------------------------
 
-#[derive(Deserialize)]
-pub struct SearchQuery {
-    q: String,
-    #[serde(rename = "fileLimit")]
-    file_limit: Option<usize>,
-    #[serde(rename = "moduleLimit")]
-    module_limit: Option<usize>,
-}
-
 /// Fuzzy search for files and modules by query string.
 pub async fn api_search(
     State(ctx): State<AppContext>,
     Query(q): Query<SearchQuery>,
 ) -> Result<Json<SearchResponse>, (StatusCode, Json<serde_json::Value>)> {
-    let s = read_state(&ctx.state)?;
+    let s = ctx.state.snapshot();
     let repo = s.default_repo();
     let file_limit = q.file_limit.unwrap_or(80);
     let module_limit = q.module_limit.unwrap_or(8);
     let query = preprocess_search_query(&q.q);
-    Ok(Json(run_search(&repo.search_files, &repo.search_modules, &query, file_limit, module_limit)))
+    let ops = parse_query_operators(&q.q);
+    let ranker = crate::ranking::Ranker::new(repo);
+    let boost = |f: &crate::types::SearchFileEntry| ranker.boost(&f.path);
+    let mut resp = if ops.any {
+        let terms: Vec<&str> = query.split_whitespace().collect();
+        crate::fuzzy::run_search_any_ranked(
+            &repo.search_files,
+            &repo.search_modules,
+            &terms,
+            file_limit,
+            module_limit,
+            &boost,
+        )
+    } else {
+        run_search_ranked(
+            &repo.search_files,
+            &repo.search_modules,
+            &query,
+            file_limit,
+            module_limit,
+            &boost,
+        )
+    };
+    resp.files.retain(|f| !ops.excludes(&f.path) && repo.config.read_policy.permits(&f.path));
+    Ok(Json(resp))
 }
 
 // ---------------------------------------------------------------------------
@@ -435,6 +453,9 @@ struct FindResultEntry {
     top_match: Option<String>,
     #[serde(rename = "topMatchLine")]
     top_match_line: Option<usize>,
+    /// Highlight ranges for each query term within `topMatch`.
+    #[serde(rename = "topMatchRanges")]
+    top_match_ranges: Vec<MatchRange>,
     #[serde(rename = "filenameIndices")]
     filename_indices: Vec<usize>,
     #[serde(rename = "termsMatched")]
@@ -452,6 +473,8 @@ pub struct FindResponse {
     ext_counts: HashMap<String, usize>,
     #[serde(rename = "catCounts")]
     cat_counts: HashMap<String, usize>,
+    /// Index generation the results were computed from.
+    generation: u64,
 }
 
 struct MergedFind {
@@ -499,11 +522,10 @@ pub async fn api_find(
     let cat_filter = q.cat.clone();
     let raw_query = q.q.clone();
 
-    let state = ctx.state.clone();
+    let s = ctx.state.snapshot();
     let response = tokio::task::spawn_blocking(move || {
         use rayon::prelude::*;
 
-        let s = state.read().expect("state lock poisoned");
         let repo = s.default_repo();
         let start = Instant::now();
 
@@ -511,9 +533,17 @@ pub async fn api_find(
 
         // 1. Fuzzy filename search
         let query = preprocess_search_query(&raw_query);
-        let search_resp = run_search(&repo.search_files, &repo.search_modules, &query, limit, 0);
+        let ops = parse_query_operators(&raw_query);
+        let ranker = crate::ranking::Ranker::new(repo);
+        let search_resp =
+            run_search_ranked(&repo.search_files, &repo.search_modules, &query, limit, 0, &|f| {
+                ranker.boost(&f.path)
+            });
 
         for f in &search_resp.files {
+            if ops.excludes(&f.path) || !repo.config.read_policy.permits(&f.path) {
+                continue;
+            }
             if let Some(ref exts) = ext_filter {
                 let ext = f.ext.trim_start_matches('.');
                 if !exts.contains(ext) {
@@ -547,15 +577,17 @@ pub async fn api_find(
         }
 
         // 2. Content grep (only if query is >= 2 chars)
-        if raw_query.len() >= 2 {
-            let terms: Vec<String> = raw_query.split_whitespace().map(|s| s.to_string()).collect();
+        if raw_query.len() >= 2 && !ops.terms.is_empty() {
+            let terms = &ops.terms;
             let terms_lower: Vec<String> = terms.iter().map(|t| t.to_lowercase()).collect();
             let pattern_str = terms.iter().map(|t| regex::escape(t)).collect::<Vec<_>>().join("|");
 
-            if let Ok(pattern) = RegexBuilder::new(&pattern_str).case_insensitive(true).build() {
+            if let Ok(pattern) = grep_regex(&pattern_str) {
                 let candidates: Vec<&ScannedFile> = repo
                     .all_files
                     .iter()
+                    .filter(|f| f.is_searchable() && !ops.excludes(&f.rel_path))
+                    .filter(|f| repo.config.read_policy.permits(&f.rel_path))
                     .filter(|f| {
                         if let Some(ref exts) = ext_filter {
                             if !exts.contains(&f.ext) {
@@ -589,21 +621,28 @@ pub async fn api_find(
                 )> = candidates
                     .par_iter()
                     .filter_map(|file| {
-                        let content = fs::read_to_string(&file.abs_path).ok()?;
-                        let total_lines = content.lines().count().max(1);
+                        let size = fs::metadata(&file.abs_path).ok()?.len();
+                        if size > repo.config.max_grep_file_size {
+                            return None;
+                        }
+                        let mut total_lines = 0usize;
                         let mut match_count = 0usize;
                         let mut best_snippet: Option<String> = None;
                         let mut best_snippet_line: Option<usize> = None;
                         let mut best_snippet_term_count: usize = 0;
                         let mut first_match_line_idx = usize::MAX;
                         let mut terms_seen: HashSet<usize> = HashSet::new();
-                        for (i, line) in content.lines().enumerate() {
+                        crate::encoding::for_each_line(&file.abs_path, |i, line| {
+                            total_lines = i + 1;
                             if pattern.is_match(line) {
+                                if ops.excludes_line(line) {
+                                    return;
+                                }
+                                let line_lower = line.to_lowercase();
                                 match_count += 1;
                                 if first_match_line_idx == usize::MAX {
                                     first_match_line_idx = i;
                                 }
-                                let line_lower = line.to_lowercase();
                                 let line_term_count = terms_lower
                                     .iter()
                                     .filter(|t| line_lower.contains(t.as_str()))
@@ -624,7 +663,9 @@ pub async fn api_find(
                                     best_snippet_line = Some(i + 1);
                                 }
                             }
-                        }
+                        })
+                        .ok()?;
+                        let total_lines = total_lines.max(1);
                         if match_count == 0 {
                             return None;
                         }
@@ -648,7 +689,7 @@ pub async fn api_find(
                                 first_match_line_idx
                             },
                             &idf_weights,
-                        );
+                        ) * ranker.boost(&file.rel_path);
 
                         let fname =
                             file.rel_path.rsplit('/').next().unwrap_or(&file.rel_path).to_string();
@@ -733,6 +774,8 @@ pub async fn api_find(
         ranked.truncate(limit);
 
         // 4. Build response
+        let highlight_terms: Vec<String> =
+            raw_query.split_whitespace().map(|t| t.to_lowercase()).collect();
         let mut ext_counts: HashMap<String, usize> = HashMap::new();
         let mut cat_counts: HashMap<String, usize> = HashMap::new();
         let results: Vec<FindResultEntry> = ranked
@@ -767,6 +810,11 @@ pub async fn api_find(
                     combined_score,
                     match_type,
                     grep_count: r.grep_count,
+                    top_match_ranges: r
+                        .top_match
+                        .as_deref()
+                        .map(|m| snippet_match_ranges(m, &highlight_terms))
+                        .unwrap_or_default(),
                     top_match: r.top_match,
                     top_match_line: r.top_match_line,
                     filename_indices: r.filename_indices,
@@ -778,7 +826,7 @@ pub async fn api_find(
 
         let query_time = start.elapsed().as_millis() as u64;
 
-        FindResponse { results, query_time, ext_counts, cat_counts }
+        FindResponse { results, query_time, ext_counts, cat_counts, generation: s.generation }
     })
     .await
     .unwrap();
@@ -790,26 +838,12 @@ pub async fn api_find(
 // Import graph
 // ---------------------------------------------------------------------------
 
-#[derive(Deserialize)]
-pub struct ImportsQuery {
-    path: String,
-    direction: Option<String>, // "imports" | "imported_by" | "both" (default)
-}
-
-#[derive(Serialize)]
-pub struct ImportsResponse {
-    path: String,
-    imports: Vec<String>,
-    #[serde(rename = "importedBy")]
-    imported_by: Vec<String>,
-}
-
 /// Query import/include relationships for a file.
 pub async fn api_imports(
     State(ctx): State<AppContext>,
     Query(q): Query<ImportsQuery>,
 ) -> Result<Json<ImportsResponse>, (StatusCode, Json<serde_json::Value>)> {
-    let s = read_state(&ctx.state)?;
+    let s = ctx.state.snapshot();
     let repo = s.default_repo();
     let direction = q.direction.as_deref().unwrap_or("both");
     let imports = if direction == "both" || direction == "imports" {
@@ -822,7 +856,255 @@ pub async fn api_imports(
     } else {
         vec![]
     };
-    Ok(Json(ImportsResponse { path: q.path, imports, imported_by }))
+    let graph = &repo.import_graph;
+    let edges = imports
+        .iter()
+        .map(|to| (q.path.as_str(), to.as_str()))
+        .chain(imported_by.iter().map(|from| (from.as_str(), q.path.as_str())))
+        .map(|(from, to)| {
+            let edge = graph.edge(from, to);
+            ImportEdgeEntry {
+                from: from.to_string(),
+                to: to.to_string(),
+                symbols: edge.symbols,
+                statements: edge.statements,
+                type_only: edge.type_only,
+                confidence: edge.confidence,
+                weight: edge.weight(),
+            }
+        })
+        .collect();
+    Ok(Json(ImportsResponse { path: q.path, imports, imported_by, edges }))
+}
+
+// ---------------------------------------------------------------------------
+// Licenses
+// ---------------------------------------------------------------------------
+
+#[derive(Serialize)]
+pub struct ModuleLicense {
+    license: String,
+    files: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LicensesResponse {
+    license_files: Vec<crate::licenses::DirLicense>,
+    headers: Vec<crate::licenses::FileLicense>,
+    /// Module → effective licenses of its files, most common first.
+    modules: HashMap<String, Vec<ModuleLicense>>,
+}
+
+/// License files, per-file license headers, and per-module license breakdown.
+pub async fn api_licenses(State(ctx): State<AppContext>) -> Json<LicensesResponse> {
+    let s = ctx.state.snapshot();
+    let repo = s.default_repo();
+    let modules = repo
+        .manifest
+        .iter()
+        .filter_map(|(cat, files)| {
+            let summary = repo.licenses.summarize(files.iter().map(|f| f.path.as_str()));
+            (!summary.is_empty()).then(|| {
+                let licenses = summary
+                    .into_iter()
+                    .map(|(license, files)| ModuleLicense { license: license.to_string(), files })
+                    .collect();
+                (cat.clone(), licenses)
+            })
+        })
+        .collect();
+    Json(LicensesResponse {
+        license_files: repo.licenses.dirs.values().cloned().collect(),
+        headers: repo.licenses.headers.clone(),
+        modules,
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Package manifests
+// ---------------------------------------------------------------------------
+
+pub async fn api_manifests(State(ctx): State<AppContext>) -> Json<Vec<Manifest>> {
+    let s = ctx.state.snapshot();
+    Json(crate::manifests::load_manifests(s.default_repo()))
+}
+
+// ---------------------------------------------------------------------------
+// Impact analysis
+// ---------------------------------------------------------------------------
+
+#[derive(Deserialize)]
+pub struct ImpactQuery {
+    path: String,
+    max_depth: Option<usize>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImpactResponse {
+    path: String,
+    /// Dependents at all depths, not counting the selected files.
+    total: usize,
+    max_depth: usize,
+    /// One root per selected file, each dependent under the file it was first reached from.
+    tree: Vec<crate::impact::ImpactNode>,
+}
+
+/// Everything that imports `path` directly or transitively, as nested JSON. `path` takes
+/// the same forms as `cs_imports` (file, glob, directory, or module).
+pub async fn api_impact(
+    State(ctx): State<AppContext>,
+    Query(q): Query<ImpactQuery>,
+) -> Result<Json<ImpactResponse>, (StatusCode, Json<serde_json::Value>)> {
+    let bad_request =
+        |e: String| (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": e })));
+    if q.path.is_empty() {
+        return Err(bad_request("path is required".to_string()));
+    }
+    let s = ctx.state.snapshot();
+    let repo = s.default_repo();
+    let targets = crate::impact::import_targets(repo, &q.path).map_err(bad_request)?;
+    let impact = crate::impact::analyze(&s, repo, &targets, q.max_depth.unwrap_or(5).min(20));
+    Ok(Json(ImpactResponse {
+        path: q.path,
+        total: impact.total(),
+        max_depth: impact.max_depth(),
+        tree: impact.tree(),
+    }))
+}
+
+// ---------------------------------------------------------------------------
+// Duplicates
+// ---------------------------------------------------------------------------
+
+#[derive(Deserialize)]
+pub struct DuplicatesQuery {
+    path: Option<String>,
+    min_lines: Option<u32>,
+}
+
+/// Clusters of identical and near-identical files, most redundant lines first.
+pub async fn api_duplicates(
+    State(ctx): State<AppContext>,
+    Query(q): Query<DuplicatesQuery>,
+) -> Json<crate::duplicates::DuplicateReport> {
+    let s = ctx.state.snapshot();
+    let min_lines = q.min_lines.unwrap_or(crate::duplicates::DEFAULT_MIN_LINES);
+    Json(crate::duplicates::find(s.default_repo(), q.path.as_deref().unwrap_or(""), min_lines))
+}
+
+// ---------------------------------------------------------------------------
+// Module graph
+// ---------------------------------------------------------------------------
+
+#[derive(Deserialize)]
+pub struct ModuleGraphQuery {
+    depth: Option<usize>,
+    prefix: Option<String>,
+    /// `dot` for Graphviz source instead of JSON.
+    format: Option<String>,
+}
+
+/// Module-to-module dependencies aggregated from file import edges, as JSON or DOT.
+pub async fn api_module_graph(
+    State(ctx): State<AppContext>,
+    Query(q): Query<ModuleGraphQuery>,
+) -> axum::response::Response {
+    let s = ctx.state.snapshot();
+    let depth = q.depth.unwrap_or(crate::modgraph::DEFAULT_DEPTH).clamp(1, 5);
+    let graph = crate::modgraph::build(s.default_repo(), depth, q.prefix.as_deref().unwrap_or(""));
+    if q.format.as_deref() == Some("dot") {
+        return ([("content-type", "text/vnd.graphviz")], crate::modgraph::to_dot(&graph))
+            .into_response();
+    }
+    Json(graph).into_response()
+}
+
+// ---------------------------------------------------------------------------
+// File statistics
+// ---------------------------------------------------------------------------
+
+/// Lines, file counts, and sizes of the repo, per extension, language, and module.
+pub async fn api_stats(State(ctx): State<AppContext>) -> Json<crate::scan::RepoStats> {
+    let s = ctx.state.snapshot();
+    Json(crate::scan::compute_stats(s.default_repo()))
+}
+
+// ---------------------------------------------------------------------------
+// Code owners
+// ---------------------------------------------------------------------------
+
+#[derive(Deserialize)]
+pub struct OwnersQuery {
+    path: Option<String>,
+    /// List the files owned by this user or team.
+    owner: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OwnersResponse {
+    #[serde(flatten)]
+    report: crate::owners::OwnersReport,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    owned_files: Option<Vec<String>>,
+}
+
+/// Ownership of a file or directory from `CODEOWNERS`, or the files an owner owns.
+pub async fn api_owners(
+    State(ctx): State<AppContext>,
+    Query(q): Query<OwnersQuery>,
+) -> Json<OwnersResponse> {
+    let s = ctx.state.snapshot();
+    let repo = s.default_repo();
+    let report = crate::owners::owners_report(repo, q.path.as_deref().unwrap_or(""));
+    let owned_files = q.owner.as_deref().map(|owner| {
+        crate::owners::files_owned_by(repo, owner).into_iter().map(String::from).collect()
+    });
+    Json(OwnersResponse { report, owned_files })
+}
+
+// ---------------------------------------------------------------------------
+// Access metrics
+// ---------------------------------------------------------------------------
+
+/// Summary of the access log (`--access-log`) per tool and endpoint.
+pub async fn api_metrics(State(ctx): State<AppContext>) -> Json<AccessMetrics> {
+    let Some(log) = ctx.state.snapshot().access_log.clone() else {
+        return Json(AccessMetrics::default());
+    };
+    let metrics = tokio::task::spawn_blocking(move || {
+        crate::access_log::summarize(&log.entries(), log.sample_rate())
+    })
+    .await;
+    Json(metrics.unwrap_or_default())
+}
+
+// ---------------------------------------------------------------------------
+// Boundary violations
+// ---------------------------------------------------------------------------
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ViolationsResponse {
+    rules: Vec<String>,
+    edges_checked: usize,
+    violations: Vec<crate::boundaries::Violation>,
+    problems: Vec<String>,
+}
+
+/// Import edges that break `[boundaries]` layering rules.
+pub async fn api_violations(State(ctx): State<AppContext>) -> Json<ViolationsResponse> {
+    let s = ctx.state.snapshot();
+    let repo = s.default_repo();
+    let config = &repo.config.boundaries;
+    Json(ViolationsResponse {
+        rules: config.rules.iter().map(|r| r.to_string()).collect(),
+        edges_checked: crate::boundaries::edge_count(&repo.import_graph),
+        violations: crate::boundaries::check(config, &repo.import_graph),
+        problems: config.problems.clone(),
+    })
 }
 
 // ---------------------------------------------------------------------------
@@ -830,30 +1112,45 @@ pub async fn api_imports(
 // ---------------------------------------------------------------------------
 
 /// Budget-aware batch file read with importance-weighted compression.
+///
+/// Takes the options of `cs_read` budget mode (`query`, `ordering`, `unit`, `repo`, and
+/// `include_seen`, with the caller's earlier reads passed as `seen` since HTTP has no
+/// session) and returns each file's tier and cost plus a per-tier breakdown, and with
+/// `ordering=interleaved` the packed content cut into definitions in presentation order.
 pub async fn api_context(
     State(ctx): State<AppContext>,
     Json(body): Json<ContextRequest>,
-) -> Json<ContextResponse> {
-    let state = ctx.state.clone();
+) -> Result<Json<ContextResponse>, (StatusCode, Json<serde_json::Value>)> {
+    let bad_request =
+        |e: String| (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": e })));
+    if body.paths.is_empty() {
+        return Err(bad_request("paths array is empty".to_string()));
+    }
+    let s = ctx.state.snapshot();
     let result = tokio::task::spawn_blocking(move || {
-        let s = state.read().expect("state lock poisoned");
-        let repo = s.default_repo();
-        allocate_budget(
+        let (repo, paths) =
+            crate::namespace::resolve_paths_repo(&s, &body.paths, body.repo.as_deref())?;
+        let repo = repo.unwrap_or_else(|| s.default_repo());
+        let seen: Option<HashSet<String>> = (!body.include_seen).then(|| {
+            body.seen.iter().map(|p| crate::paths::split_repo_prefix(p).1.to_string()).collect()
+        });
+        let resp = allocate_budget(
             &repo.root,
-            &body.paths,
+            &paths,
             &repo.all_files,
             body.budget,
             &body.unit,
             body.query.as_deref(),
             body.ordering.as_deref(),
-            None, // no session tracking in HTTP mode
+            seen.as_ref(),
             &repo.deps,
             &repo.stub_cache,
             &*s.tokenizer,
             &repo.config,
-        )
+        );
+        Ok(resp)
     })
     .await
     .unwrap();
-    Json(result)
+    result.map(Json).map_err(bad_request)
 }
@@ -0,0 +1,291 @@
+//! Crash-safe file writes: write a temp file next to the target, fsync it, then rename it
+//! over the target. Readers see either the old content or the new, never a partial file.
+//!
+//! A symlinked target stays a symlink: the write goes to the file it points to, whose
+//! permissions the new content keeps.
+//!
+//! Temp files are named `.<target name>.<pid>.<seq>.tmp`, with `seq` unique per write in
+//! the process, and are created exclusively, so concurrent writes of one target never share
+//! a temp file; the last rename wins. A process killed mid-write leaves one behind; the
+//! next write of the same target, and [`remove_stale_temps`] sweeps, delete any that are
+//! older than [`STALE_AFTER`].
+
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
+
+/// Temp files untouched for this long belong to a writer that is gone.
+pub const STALE_AFTER: Duration = Duration::from_secs(24 * 3600);
+
+/// Symlinks followed before giving up on a chain (the kernel's own limit on Linux).
+const MAX_SYMLINK_HOPS: usize = 40;
+
+/// Sequence number of the next temp file created by this process.
+static NEXT_TEMP: AtomicU64 = AtomicU64::new(0);
+
+/// Replace `path` with `contents` atomically, creating parent directories as needed.
+pub fn write(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let mut file = AtomicFile::create(path)?;
+    file.write_all(contents.as_ref())?;
+    file.commit()
+}
+
+/// [`write`] for a cache file, sealed when cache encryption is on ([`crate::crypt`]).
+pub fn write_cache(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let mut file = AtomicFile::create_cache(path)?;
+    file.write_all(contents.as_ref())?;
+    file.commit()
+}
+
+/// A buffered writer for a temp file that replaces its target on [`AtomicFile::commit`].
+/// Dropping it uncommitted deletes the temp file and leaves the target alone.
+pub struct AtomicFile {
+    writer: Option<BufWriter<File>>,
+    tmp: PathBuf,
+    path: PathBuf,
+    /// Key and plaintext of a cache file sealed on commit.
+    sealed: Option<(crate::crypt::CacheKey, Vec<u8>)>,
+}
+
+impl AtomicFile {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let path = write_target(path);
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        remove_stale_temps_of(&path);
+        let seq = NEXT_TEMP.fetch_add(1, Ordering::Relaxed);
+        let tmp = temp_path(&path, std::process::id(), seq);
+        let file = File::options().write(true).create_new(true).open(&tmp)?;
+        if let Ok(meta) = std::fs::metadata(&path) {
+            file.set_permissions(meta.permissions())?;
+        }
+        Ok(Self { writer: Some(BufWriter::new(file)), tmp, path, sealed: None })
+    }
+
+    /// [`AtomicFile::create`] for a cache file. With cache encryption on, the content is
+    /// buffered and sealed on commit; with encryption required but no key, this fails.
+    pub fn create_cache(path: &Path) -> io::Result<Self> {
+        let key = crate::crypt::cache_key()?;
+        let mut file = Self::create(path)?;
+        file.sealed = key.map(|k| (k.clone(), Vec::new()));
+        Ok(file)
+    }
+
+    /// Flush and fsync the temp file, then rename it over the target.
+    pub fn commit(&mut self) -> io::Result<()> {
+        let Some(mut writer) = self.writer.take() else {
+            return Err(io::Error::other("atomic write already committed"));
+        };
+        if let Some((key, plaintext)) = self.sealed.take() {
+            writer.write_all(&crate::crypt::seal(&key, &plaintext))?;
+        }
+        let file = writer.into_inner().map_err(io::IntoInnerError::into_error)?;
+        file.sync_all()?;
+        drop(file);
+        std::fs::rename(&self.tmp, &self.path)?;
+        sync_parent(&self.path);
+        Ok(())
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let (Some(_), Some((_, plaintext))) = (&self.writer, &mut self.sealed) {
+            plaintext.extend_from_slice(buf);
+            return Ok(buf.len());
+        }
+        match &mut self.writer {
+            Some(w) => w.write(buf),
+            None => Err(io::Error::other("atomic write already committed")),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.writer {
+            Some(w) => w.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if self.writer.take().is_some() {
+            let _ = std::fs::remove_file(&self.tmp);
+        }
+    }
+}
+
+/// The file a write to `path` replaces: the end of its symlink chain, resolved link by link
+/// so a dangling link gets its target created, or `path` itself.
+fn write_target(path: &Path) -> PathBuf {
+    let mut target = path.to_path_buf();
+    for _ in 0..MAX_SYMLINK_HOPS {
+        let Ok(link) = std::fs::read_link(&target) else { break };
+        // Relative links are relative to the directory holding them
+        target = match target.parent() {
+            Some(dir) => dir.join(link),
+            None => link,
+        };
+    }
+    target
+}
+
+fn temp_path(path: &Path, pid: u32, seq: u64) -> PathBuf {
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(format!(".{pid}.{seq}.tmp"));
+    path.with_file_name(name)
+}
+
+/// Whether `name` is a temp file of [`AtomicFile`], for any target and process. Temps from
+/// versions that named them `.<target name>.<pid>.tmp` match too.
+fn is_temp_name(name: &str) -> bool {
+    name.starts_with('.')
+        && name
+            .strip_suffix(".tmp")
+            .and_then(|s| s.rsplit_once('.'))
+            .is_some_and(|(_, n)| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+}
+
+fn is_stale(path: &Path, now: SystemTime) -> bool {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .is_ok_and(|t| now.duration_since(t).unwrap_or_default() > STALE_AFTER)
+}
+
+/// Delete stale temp files left by interrupted writes of `path`.
+fn remove_stale_temps_of(path: &Path) {
+    let (Some(dir), Some(target)) = (path.parent(), path.file_name()) else { return };
+    let prefix = format!(".{}.", target.to_string_lossy());
+    let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    let now = SystemTime::now();
+    for entry in std::fs::read_dir(dir).into_iter().flatten().flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with(&prefix) && is_temp_name(&name) && is_stale(&entry.path(), now) {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+}
+
+/// Delete stale temp files left by interrupted writes anywhere in `dir` (not recursive).
+/// Returns how many were removed.
+pub fn remove_stale_temps(dir: &Path) -> usize {
+    let now = SystemTime::now();
+    let mut removed = 0;
+    for entry in std::fs::read_dir(dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        if is_temp_name(&entry.file_name().to_string_lossy())
+            && path.is_file()
+            && is_stale(&path, now)
+            && std::fs::remove_file(&path).is_ok()
+        {
+            removed += 1;
+        }
+    }
+    removed
+}
+
+/// Persist the rename itself. Directories cannot be opened for syncing on Windows, where
+/// the rename is durable once it returns.
+fn sync_parent(path: &Path) {
+    #[cfg(unix)]
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        if let Ok(d) = File::open(dir) {
+            let _ = d.sync_all();
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_replace_the_target_and_clean_up_temps() {
+        let dir = std::env::temp_dir().join(format!("codescope-atomic-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let target = dir.join("repos.toml");
+        write(&target, "a = 1\n").unwrap();
+        write(&target, "a = 2\n").unwrap();
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "a = 2\n");
+
+        // An abandoned write leaves the target untouched and no temp file
+        let mut file = AtomicFile::create(&target).unwrap();
+        file.write_all(b"partial").unwrap();
+        drop(file);
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "a = 2\n");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        // Orphans from dead writers are swept once stale, fresh ones are left alone
+        let orphan = temp_path(&target, 4242, 0);
+        let fresh = temp_path(&dir.join("other.json"), 7, 3);
+        std::fs::write(&orphan, "x").unwrap();
+        std::fs::write(&fresh, "x").unwrap();
+        assert_eq!(remove_stale_temps(&dir), 0);
+        let old = SystemTime::now() - STALE_AFTER * 2;
+        File::options().append(true).open(&orphan).unwrap().set_modified(old).unwrap();
+        write(&target, "a = 3\n").unwrap();
+        assert!(!orphan.exists() && fresh.exists());
+        assert!(!is_temp_name(".hidden") && !is_temp_name(".repos.toml.tmp"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn concurrent_writes_of_one_target_use_their_own_temps() {
+        let dir =
+            std::env::temp_dir().join(format!("codescope-atomic-race-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let target = dir.join("repos.toml");
+        std::thread::scope(|s| {
+            for i in 0..8 {
+                let target = &target;
+                s.spawn(move || {
+                    for _ in 0..20 {
+                        write(target, format!("a = {i}\n")).unwrap();
+                    }
+                });
+            }
+        });
+        let content = std::fs::read_to_string(&target).unwrap();
+        assert!(content.starts_with("a = ") && content.lines().count() == 1, "{content}");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1, "no temp left");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_targets_stay_links_and_keep_permissions() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let dir =
+            std::env::temp_dir().join(format!("codescope-atomic-link-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("dotfiles")).unwrap();
+        let real = dir.join("dotfiles/repos.toml");
+        std::fs::write(&real, "a = 1\n").unwrap();
+        std::fs::set_permissions(&real, std::fs::Permissions::from_mode(0o600)).unwrap();
+        let link = dir.join("repos.toml");
+        symlink("dotfiles/repos.toml", &link).unwrap();
+
+        write(&link, "a = 2\n").unwrap();
+        assert!(std::fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(std::fs::read_to_string(&real).unwrap(), "a = 2\n");
+        assert_eq!(std::fs::metadata(&real).unwrap().permissions().mode() & 0o777, 0o600);
+        assert_eq!(std::fs::read_dir(dir.join("dotfiles")).unwrap().count(), 1, "no temp left");
+
+        // A dangling link gets its target created
+        let dangling = dir.join(".mcp.json");
+        symlink(dir.join("dotfiles/mcp.json"), &dangling).unwrap();
+        write(&dangling, "{}").unwrap();
+        assert!(std::fs::symlink_metadata(&dangling).unwrap().file_type().is_symlink());
+        assert_eq!(std::fs::read_to_string(dir.join("dotfiles/mcp.json")).unwrap(), "{}");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,272 @@
+//! Module boundary rules: named layers of files, and which layers may not import which.
+//!
+//! ```toml
+//! [boundaries]
+//! deny = ["ui -> db", "db -> ui"]
+//!
+//! [boundaries.layers]
+//! ui = ["src/ui/**", "src/components/**"]
+//! db = ["src/db/**"]
+//! ```
+//!
+//! A file may belong to several layers. An import edge violates a rule when the importing
+//! file is in the rule's source layer and the imported file in its target layer. Rules are
+//! checked on demand against the import graph by `cs_imports action=violations`,
+//! `/api/violations`, and `codescope check` (non-zero exit for CI).
+
+use crate::types::{ImportConfidence, ImportEdge, ImportGraph};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// A named set of files, matched by globs relative to the repo root.
+#[derive(Clone)]
+pub struct Layer {
+    pub name: String,
+    pub patterns: Vec<String>,
+    set: GlobSet,
+}
+
+/// `from -> to`: files in layer `from` must not import files in layer `to`.
+#[derive(Clone)]
+pub struct BoundaryRule {
+    pub from: String,
+    pub to: String,
+}
+
+impl std::fmt::Display for BoundaryRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} -> {}", self.from, self.to)
+    }
+}
+
+/// `[boundaries]` settings from `.codescope.toml`.
+#[derive(Clone, Default)]
+pub struct BoundaryConfig {
+    pub layers: Vec<Layer>,
+    pub rules: Vec<BoundaryRule>,
+    /// Invalid globs and rules naming unknown layers; those entries are skipped.
+    pub problems: Vec<String>,
+}
+
+impl BoundaryConfig {
+    /// Parse the `[boundaries]` table: `layers` (name → glob or globs) and `deny`
+    /// (`"from -> to"` strings).
+    pub fn from_table(table: &toml::Table) -> Self {
+        let mut config = Self::default();
+        if let Some(layers) = table.get("layers").and_then(|v| v.as_table()) {
+            for (name, value) in layers {
+                let patterns: Vec<String> = match value {
+                    toml::Value::String(s) => vec![s.clone()],
+                    toml::Value::Array(a) => {
+                        a.iter().filter_map(|v| v.as_str()).map(str::to_string).collect()
+                    }
+                    _ => {
+                        config.problems.push(format!("layer '{name}': expected glob or globs"));
+                        continue;
+                    }
+                };
+                let mut builder = GlobSetBuilder::new();
+                for pattern in &patterns {
+                    match Glob::new(pattern) {
+                        Ok(g) => {
+                            builder.add(g);
+                        }
+                        Err(e) => config.problems.push(format!("layer '{name}': {e}")),
+                    }
+                }
+                match builder.build() {
+                    Ok(set) => config.layers.push(Layer { name: name.clone(), patterns, set }),
+                    Err(e) => config.problems.push(format!("layer '{name}': {e}")),
+                }
+            }
+        }
+        let deny = table.get("deny").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+        for rule in deny.iter().filter_map(|v| v.as_str()) {
+            let Some((from, to)) = rule.split_once("->") else {
+                config.problems.push(format!("rule '{rule}': expected \"from -> to\""));
+                continue;
+            };
+            let (from, to) = (from.trim(), to.trim());
+            let unknown: Vec<&str> =
+                [from, to].into_iter().filter(|l| !config.has_layer(l)).collect();
+            if unknown.is_empty() {
+                config.rules.push(BoundaryRule { from: from.to_string(), to: to.to_string() });
+            } else {
+                config
+                    .problems
+                    .push(format!("rule '{rule}': unknown layer '{}'", unknown.join("', '")));
+            }
+        }
+        config
+    }
+
+    fn has_layer(&self, name: &str) -> bool {
+        self.layers.iter().any(|l| l.name == name)
+    }
+
+    /// Layers `path` belongs to.
+    pub fn layers_of(&self, path: &str) -> Vec<&str> {
+        self.layers.iter().filter(|l| l.set.is_match(path)).map(|l| l.name.as_str()).collect()
+    }
+}
+
+/// An import edge that crosses a denied layer boundary.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Violation {
+    pub from: String,
+    pub to: String,
+    /// The rule broken, as written in `deny`.
+    pub rule: String,
+    pub symbols: u32,
+    pub type_only: bool,
+}
+
+/// Every import edge that breaks a rule, sorted by importer then imported file. An edge
+/// matching several rules is reported under the first.
+pub fn check(config: &BoundaryConfig, graph: &ImportGraph) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    if config.rules.is_empty() {
+        return violations;
+    }
+    for (from, targets) in &graph.imports {
+        let from_layers = config.layers_of(from);
+        if from_layers.is_empty() {
+            continue;
+        }
+        for to in targets {
+            let to_layers = config.layers_of(to);
+            let broken = config.rules.iter().find(|r| {
+                from_layers.contains(&r.from.as_str()) && to_layers.contains(&r.to.as_str())
+            });
+            if let Some(rule) = broken {
+                let ImportEdge { symbols, type_only, .. } = graph.edge(from, to);
+                violations.push(Violation {
+                    from: from.clone(),
+                    to: to.clone(),
+                    rule: rule.to_string(),
+                    symbols,
+                    type_only,
+                });
+            }
+        }
+    }
+    violations
+}
+
+/// Import edges in the graph, for "N edges checked" summaries.
+pub fn edge_count(graph: &ImportGraph) -> usize {
+    graph.imports.values().map(Vec::len).sum()
+}
+
+/// Violations grouped by rule, at most `limit` edges listed, followed by config problems.
+pub fn format_violations(
+    config: &BoundaryConfig,
+    graph: &ImportGraph,
+    violations: &[Violation],
+    limit: usize,
+) -> String {
+    let mut out = String::new();
+    if config.rules.is_empty() {
+        out.push_str(
+            "No [boundaries] rules in .codescope.toml. Declare layers and denied imports:\n\n\
+             [boundaries]\ndeny = [\"ui -> db\"]\n\n[boundaries.layers]\n\
+             ui = [\"src/ui/**\"]\ndb = [\"src/db/**\"]\n",
+        );
+    } else if violations.is_empty() {
+        out.push_str(&format!(
+            "No boundary violations: {} import edges checked against {} rules\n",
+            edge_count(graph),
+            config.rules.len()
+        ));
+    } else {
+        out.push_str(&format!(
+            "{} boundary violation{} ({} rules, {} layers)\n",
+            violations.len(),
+            if violations.len() == 1 { "" } else { "s" },
+            config.rules.len(),
+            config.layers.len()
+        ));
+        let mut by_rule: BTreeMap<&str, Vec<&Violation>> = BTreeMap::new();
+        for v in violations {
+            by_rule.entry(&v.rule).or_default().push(v);
+        }
+        let mut shown = 0;
+        for (rule, edges) in &by_rule {
+            if shown >= limit {
+                break;
+            }
+            out.push_str(&format!("\n{rule} ({}):\n", edges.len()));
+            for v in edges.iter().take(limit - shown) {
+                let label = ImportEdge {
+                    symbols: v.symbols,
+                    type_only: v.type_only,
+                    ..ImportEdge::plain(ImportConfidence::High)
+                }
+                .label();
+                if label.is_empty() {
+                    out.push_str(&format!("  {} -> {}\n", v.from, v.to));
+                } else {
+                    out.push_str(&format!("  {} -> {}  [{label}]\n", v.from, v.to));
+                }
+                shown += 1;
+            }
+        }
+        if violations.len() > shown {
+            out.push_str(&format!("\n... {} more (raise 'limit')\n", violations.len() - shown));
+        }
+    }
+    if !config.problems.is_empty() {
+        out.push_str("\nConfig problems in [boundaries]:\n");
+        for problem in &config.problems {
+            out.push_str(&format!("  {problem}\n"));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn denied_edges_are_reported_and_bad_rules_flagged() {
+        let table: toml::Table = toml::from_str(
+            r#"
+            deny = ["ui -> db", "db -> nowhere", "ui"]
+            [layers]
+            ui = ["src/ui/**"]
+            db = "src/db/**"
+            "#,
+        )
+        .unwrap();
+        let config = BoundaryConfig::from_table(&table);
+        assert_eq!(config.rules.len(), 1);
+        assert_eq!(config.problems.len(), 2, "{:?}", config.problems);
+
+        let mut edges = BTreeMap::new();
+        edges.insert(
+            "src/ui/page.ts".to_string(),
+            BTreeMap::from([
+                (
+                    "src/db/query.ts".to_string(),
+                    ImportEdge { symbols: 2, ..ImportEdge::plain(ImportConfidence::High) },
+                ),
+                ("src/ui/button.ts".to_string(), ImportEdge::default()),
+            ]),
+        );
+        edges.insert(
+            "src/db/query.ts".to_string(),
+            BTreeMap::from([("src/ui/button.ts".to_string(), ImportEdge::default())]),
+        );
+        let graph = ImportGraph::from_edges(edges);
+
+        let violations = check(&config, &graph);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(
+            (violations[0].from.as_str(), violations[0].to.as_str(), violations[0].rule.as_str()),
+            ("src/ui/page.ts", "src/db/query.ts", "ui -> db")
+        );
+    }
+}
@@ -3,15 +3,22 @@
 //! (full → stubs → pruned → manifest-only) until the budget is satisfied.
 //!
 //! Used by `cs_read` in budget mode for context-window-aware batch file reads.
+//!
+//! With `ordering=interleaved` the packed files are also cut into definition chunks
+//! ([`crate::stubs::definition_chunks`]) and laid out by relevance across all files: the
+//! most relevant chunk first, the next one last, and so on inward, so the least relevant
+//! material lands in the middle of the context, where long-context models attend least.
 
+use crate::paths::resolve_in_repo;
 use crate::scan::get_category_path;
-use crate::stubs::{extract_stubs, extract_tier4, parse_blocks, BlockKind, StubBlock};
+use crate::stubs::{
+    definition_chunks, extract_stubs, extract_tier4, parse_blocks, BlockKind, StubBlock,
+};
 use crate::tokenizer::Tokenizer;
-use crate::types::{validate_path, CachedStub, DepEntry, ScanConfig, ScannedFile};
+use crate::types::{CachedStub, DepEntry, ScanConfig, ScannedFile, StubCache};
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashMap, HashSet};
-use std::fs;
 use std::path::Path;
 use std::sync::Arc;
 
@@ -38,7 +45,8 @@ pub fn importance_score(rel_path: &str, file_size: u64) -> f64 {
         // Primary source
         "rs" | "go" | "java" | "kt" | "scala" | "swift" | "ts" | "tsx" => 0.15,
         // Implementation / secondary source
-        "cpp" | "cxx" | "cc" | "c" | "js" | "jsx" | "mjs" | "cjs" | "py" | "rb" => 0.12,
+        "cpp" | "cxx" | "cc" | "c" | "js" | "jsx" | "mjs" | "cjs" | "py" | "rb" | "php" | "ex"
+        | "exs" => 0.12,
         // Shaders
         "usf" | "ush" | "hlsl" | "glsl" | "vert" | "frag" | "comp" | "wgsl" => 0.12,
         // Config
@@ -107,9 +115,19 @@ pub struct ContextRequest {
     #[serde(default)]
     pub query: Option<String>,
     /// Output ordering strategy: None or "importance" (default) = descending importance,
-    /// "attention" = primacy/recency optimized (high-importance at start and end, mid in middle)
+    /// "attention" = primacy/recency optimized (high-importance at start and end, mid in middle),
+    /// "interleaved" = the same at definition granularity, filling [`ContextResponse::chunks`]
     #[serde(default)]
     pub ordering: Option<String>,
+    /// Repo to read from; defaults to the `[repo]` prefix of the paths, then the default repo
+    #[serde(default)]
+    pub repo: Option<String>,
+    /// Paths the caller has already read; they are deprioritized like a session's reads
+    /// in `cs_read` unless `include_seen` is set
+    #[serde(default)]
+    pub seen: Vec<String>,
+    #[serde(default)]
+    pub include_seen: bool,
 }
 
 fn default_budget() -> usize {
@@ -120,11 +138,35 @@ fn default_budget() -> usize {
 pub struct ContextFileEntry {
     pub content: String,
     pub tier: u8,
+    #[serde(rename = "tierName")]
+    pub tier_name: &'static str,
     pub tokens: usize,
+    pub chars: usize,
     pub importance: f64,
     pub order: u32,
 }
 
+/// Files and cost of one tier in a [`ContextResponse`].
+#[derive(Serialize)]
+pub struct TierSummary {
+    pub tier: u8,
+    pub name: &'static str,
+    pub files: usize,
+    pub tokens: usize,
+    pub chars: usize,
+}
+
+/// Label of a budget tier: 1 full stubs, 2 pruned, 3 TOC, 4 manifest line, 0 error.
+pub fn tier_name(tier: u8) -> &'static str {
+    match tier {
+        1 => "full stubs",
+        2 => "pruned",
+        3 => "TOC",
+        4 => "manifest",
+        _ => "error",
+    }
+}
+
 #[derive(Serialize)]
 pub struct ContextSummary {
     #[serde(rename = "totalTokens")]
@@ -137,12 +179,30 @@ pub struct ContextSummary {
     pub tier_counts: HashMap<String, usize>,
     #[serde(rename = "totalFiles")]
     pub total_files: usize,
+    /// Per-tier file counts and costs, tiers in order, empty tiers left out.
+    pub tiers: Vec<TierSummary>,
+}
+
+/// A definition of a packed file, in `ordering=interleaved` output order.
+#[derive(Serialize)]
+pub struct ContextChunk {
+    pub path: String,
+    /// `kind name` of the definition; empty for imports and other statements.
+    pub symbol: String,
+    pub content: String,
+    pub tier: u8,
+    /// The file's importance plus the chunk's own relevance to the query.
+    pub score: f64,
 }
 
 #[derive(Serialize)]
 pub struct ContextResponse {
     pub files: HashMap<String, ContextFileEntry>,
     pub summary: ContextSummary,
+    /// With `ordering=interleaved`: the content of `files` cut into definitions, in the
+    /// order to present them.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub chunks: Vec<ContextChunk>,
 }
 
 struct BudgetFile {
@@ -183,6 +243,12 @@ enum LoadResult {
     Err(String, ContextFileEntry),
 }
 
+/// Extension stub extraction and block parsing use for `path`, from its detected language.
+fn stub_ext_for(config: &ScanConfig, path: &str, content: &str) -> String {
+    crate::lang::file_stub_ext(crate::lang::detect(&config.languages, path, content), path)
+        .to_string()
+}
+
 /// Compute importance from query terms + static heuristics.
 fn compute_importance(path: &str, raw: &str, file_size: u64, query_terms: &[String]) -> f64 {
     let path_lower = path.to_lowercase();
@@ -269,6 +335,42 @@ fn score_block(block: &StubBlock, query_terms: &[String]) -> f64 {
     base + query_bonus
 }
 
+/// Relevance of a definition chunk of its own: definitions over statements, plus query
+/// terms in its name or text, weighted as in [`score_block`].
+fn score_chunk(label: &str, text: &str, query_terms: &[String]) -> f64 {
+    let base = if label.is_empty() { 0.0 } else { 1.0 };
+    let (label, text) = (label.to_lowercase(), text.to_lowercase());
+    let bonus: f64 = query_terms
+        .iter()
+        .map(|term| {
+            if label.contains(term.as_str()) {
+                10.0
+            } else if text.contains(term.as_str()) {
+                3.0
+            } else {
+                0.0
+            }
+        })
+        .sum();
+    base + bonus
+}
+
+/// Lay out items ranked most relevant first so relevance falls toward the middle: ranks
+/// 0, 2, 4, … from the start, and 1, 3, 5, … from the end.
+fn interleave<T>(ranked: Vec<T>) -> Vec<T> {
+    let mut front = Vec::with_capacity(ranked.len());
+    let mut back = Vec::new();
+    for (i, item) in ranked.into_iter().enumerate() {
+        if i % 2 == 0 {
+            front.push(item);
+        } else {
+            back.push(item);
+        }
+    }
+    front.extend(back.into_iter().rev());
+    front
+}
+
 /// Water-fill budget allocation: distribute tokens proportionally by importance.
 /// Returns per-file total budgets (0 = manifest, >0 = stubs/pruned content budget).
 pub(crate) fn allocate_file_budgets(
@@ -402,12 +504,14 @@ pub fn allocate_budget(
     ordering: Option<&str>,
     seen_files: Option<&HashSet<String>>,
     deps: &BTreeMap<String, DepEntry>,
-    stub_cache: &dashmap::DashMap<String, CachedStub>,
+    stub_cache: &StubCache,
     tokenizer: &dyn Tokenizer,
     config: &ScanConfig,
 ) -> ContextResponse {
     let desc_map: HashMap<&str, &str> =
         all_files.iter().map(|f| (f.rel_path.as_str(), f.desc.as_str())).collect();
+    let hashes: HashMap<&str, u64> =
+        all_files.iter().map(|f| (f.rel_path.as_str(), f.content_hash)).collect();
 
     // Query terms are the primary relevance signal
     let query_terms: Vec<String> = query
@@ -423,12 +527,18 @@ pub fn allocate_budget(
     let load_results: Vec<LoadResult> = paths
         .par_iter()
         .map(|p| {
-            let ext = p.rsplit_once('.').map(|(_, e)| e).unwrap_or("").to_string();
             let desc = desc_map.get(p.as_str()).copied().unwrap_or("").to_string();
-
-            // Check cache first
-            if let Some(cached) = stub_cache.get(p.as_str()) {
+            // Unindexed files have no hash to check entries against, so they are not cached
+            let key = hashes.get(p.as_str()).map(|&hash| (p.clone(), hash));
+
+            // Check cache first (denied paths fall through so resolution reports the error)
+            if let Some(cached) = key
+                .as_ref()
+                .and_then(|key| stub_cache.get(key))
+                .filter(|_| config.read_policy.permits(p))
+            {
                 let file_size = cached.raw.len() as u64;
+                let ext = stub_ext_for(config, p, &cached.raw);
                 let cost = match unit {
                     BudgetUnit::Tokens => cached.fast_tokens,
                     BudgetUnit::Chars => cached.tier1.len(),
@@ -447,30 +557,37 @@ pub fn allocate_budget(
             }
 
             // Cache miss: read from disk, compute stubs, cache result
-            match validate_path(project_root, p) {
+            match resolve_in_repo(project_root, &config.read_policy, p) {
                 Err(e) => LoadResult::Err(
                     p.clone(),
                     ContextFileEntry {
                         content: format!("Error: {e}"),
                         tier: 0,
+                        tier_name: tier_name(0),
                         tokens: 0,
+                        chars: 0,
                         importance: 0.0,
                         order: u32::MAX,
                     },
                 ),
-                Ok(full_path) => match fs::read_to_string(&full_path) {
+                Ok(full_path) => match crate::encoding::read_to_string(&full_path) {
                     Err(_) => LoadResult::Err(
                         p.clone(),
                         ContextFileEntry {
                             content: "Error: could not read file".into(),
                             tier: 0,
+                            tier_name: tier_name(0),
                             tokens: 0,
+                            chars: 0,
                             importance: 0.0,
                             order: u32::MAX,
                         },
                     ),
                     Ok(raw) => {
+                        // Mask before caching so cache hits never hold plaintext secrets
+                        let (raw, _) = crate::redact::redact(&config.redact, raw);
                         let file_size = raw.len() as u64;
+                        let ext = stub_ext_for(config, p, &raw);
                         let tier1 = extract_stubs(&raw, &ext);
                         let fast_tokens = tokenizer.count_tokens(&tier1);
                         let cost = match unit {
@@ -481,15 +598,16 @@ pub fn allocate_budget(
                         let raw_arc: Arc<str> = Arc::from(raw.as_str());
                         let tier1_arc: Arc<str> = Arc::from(tier1.as_str());
 
-                        // Store in cache
-                        stub_cache.insert(
-                            p.clone(),
-                            CachedStub {
-                                raw: Arc::clone(&raw_arc),
-                                tier1: Arc::clone(&tier1_arc),
-                                fast_tokens,
-                            },
-                        );
+                        if let Some(key) = key {
+                            stub_cache.insert(
+                                key,
+                                CachedStub {
+                                    raw: Arc::clone(&raw_arc),
+                                    tier1: Arc::clone(&tier1_arc),
+                                    fast_tokens,
+                                },
+                            );
+                        }
 
                         let importance = compute_importance(p, &raw, file_size, &query_terms);
 
@@ -585,7 +703,15 @@ pub fn allocate_budget(
     // Phase 2: Check budget — if T1 fits, we're done
     let mut total: usize = files.iter().map(|f| f.current_cost).sum();
     if total <= budget {
-        return build_context_response(files, errors, budget, unit, ordering, tokenizer);
+        return build_context_response(
+            files,
+            errors,
+            budget,
+            unit,
+            ordering,
+            &query_terms,
+            tokenizer,
+        );
     }
 
     // Phase 3: Water-fill budget allocation — distribute tokens by importance
@@ -645,7 +771,7 @@ pub fn allocate_budget(
         }
     }
 
-    build_context_response(files, errors, budget, unit, ordering, tokenizer)
+    build_context_response(files, errors, budget, unit, ordering, &query_terms, tokenizer)
 }
 
 #[cfg(test)]
@@ -680,6 +806,13 @@ mod tests {
         assert!(total <= budget, "total allocated ({total}) should not exceed budget ({budget})");
     }
 
+    #[test]
+    fn interleaving_puts_the_least_relevant_in_the_middle() {
+        assert_eq!(interleave(vec![1, 2, 3, 4, 5, 6]), [1, 3, 5, 6, 4, 2]);
+        assert_eq!(interleave(vec![1]), [1]);
+        assert!(score_chunk("fn parse_args", "", &["parse".into()]) > score_chunk("", "", &[]));
+    }
+
     #[test]
     fn higher_importance_gets_more_tokens() {
         let files = vec![
@@ -703,6 +836,7 @@ fn build_context_response(
     budget: usize,
     unit: &BudgetUnit,
     ordering: Option<&str>,
+    query_terms: &[String],
     tokenizer: &dyn Tokenizer,
 ) -> ContextResponse {
     // Demote files whose current content is empty to tier 4 (manifest line)
@@ -760,8 +894,41 @@ fn build_context_response(
         }
     }
 
+    let mut chunks: Vec<ContextChunk> = Vec::new();
+    if ordering == Some("interleaved") {
+        for file in &files {
+            let pieces = if file.current_tier <= 2 {
+                definition_chunks(&file.current_content, &file.ext)
+            } else {
+                Vec::new()
+            };
+            if pieces.is_empty() {
+                chunks.push(ContextChunk {
+                    path: file.path.clone(),
+                    symbol: String::new(),
+                    content: file.current_content.clone(),
+                    tier: file.current_tier,
+                    score: file.importance,
+                });
+            }
+            for piece in pieces {
+                chunks.push(ContextChunk {
+                    path: file.path.clone(),
+                    score: file.importance + score_chunk(&piece.label, &piece.text, query_terms),
+                    symbol: piece.label,
+                    content: piece.text,
+                    tier: file.current_tier,
+                });
+            }
+        }
+        // Stable: equal scores keep file importance order, then position in the file
+        chunks.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+        chunks = interleave(chunks);
+    }
+
     let mut result_files: HashMap<String, ContextFileEntry> = HashMap::new();
     let mut tier_counts: HashMap<String, usize> = HashMap::new();
+    let mut tiers: Vec<TierSummary> = Vec::new();
     let mut total_tokens = 0usize;
     let mut total_chars = 0usize;
 
@@ -779,13 +946,29 @@ fn build_context_response(
         let tok = tokenizer.count_tokens(&content);
         total_tokens += tok;
         total_chars += chars;
+        match tiers.iter_mut().find(|t| t.tier == tier) {
+            Some(t) => {
+                t.files += 1;
+                t.tokens += tok;
+                t.chars += chars;
+            }
+            None => tiers.push(TierSummary {
+                tier,
+                name: tier_name(tier),
+                files: 1,
+                tokens: tok,
+                chars,
+            }),
+        }
 
         result_files.insert(
             file.path,
             ContextFileEntry {
                 content,
                 tier,
+                tier_name: tier_name(tier),
                 tokens: tok,
+                chars,
                 importance: file.importance,
                 order: order_map[idx],
             },
@@ -809,7 +992,9 @@ fn build_context_response(
             ContextFileEntry {
                 content: map_text,
                 tier: 0,
+                tier_name: "context map",
                 tokens: 0,
+                chars: 0,
                 importance: 0.0,
                 order: u32::MAX,
             },
@@ -821,6 +1006,7 @@ fn build_context_response(
         BudgetUnit::Chars => "chars",
     };
 
+    tiers.sort_by_key(|t| t.tier);
     ContextResponse {
         summary: ContextSummary {
             total_tokens,
@@ -829,7 +1015,9 @@ fn build_context_response(
             unit: unit_str.to_string(),
             tier_counts,
             total_files: result_files.len(),
+            tiers,
         },
         files: result_files,
+        chunks,
     }
 }
@@ -0,0 +1,455 @@
+//! Disk cache inventory and pruning: `codescope cache ls|prune` and the `[cache]` policy.
+//!
+//! Two kinds of per-repo entries live under [`crate::cache_dir`]: semantic caches
+//! (`semantic/<identity>/`, one directory per repo) and index handoffs
+//! (`handoff/<hash>.json`). Nothing else there is touched; downloaded models are shared by
+//! every repo. An entry's age is the time since a server last used it: the server
+//! refreshes the entries of the repos it indexes at startup. Entries of repos that a
+//! running server holds the lock for are never pruned.
+//!
+//! The automatic policy is read from `[cache]` in `~/.codescope/repos.toml`:
+//!
+//! ```toml
+//! [cache]
+//! max_age = "60d"        # drop entries unused for longer
+//! max_size = "5G"        # then drop the oldest until the total fits
+//! prune_missing = true   # drop entries whose repo directory no longer exists
+//! encrypt = true         # seal entries with a key (see crate::crypt)
+//! ```
+
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+use crate::memory::format_bytes;
+
+/// One per-repo cache entry.
+#[derive(Clone, Debug)]
+pub struct CacheEntry {
+    /// `"semantic"` or `"handoff"`.
+    pub kind: &'static str,
+    /// Directory (semantic) or file (handoff) to delete when pruning.
+    pub path: PathBuf,
+    /// Repo root the entry was built for, when recorded.
+    pub repo: Option<PathBuf>,
+    pub bytes: u64,
+    /// Newest modification time of the entry's files.
+    pub modified: SystemTime,
+}
+
+impl CacheEntry {
+    /// Whether the entry names a repo root that no longer exists.
+    pub fn repo_missing(&self) -> bool {
+        self.repo.as_ref().is_some_and(|r| !r.exists())
+    }
+
+    fn age(&self, now: SystemTime) -> Duration {
+        now.duration_since(self.modified).unwrap_or_default()
+    }
+}
+
+/// What `prune` removes. The default removes nothing.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PrunePolicy {
+    pub max_age: Option<Duration>,
+    pub max_size: Option<u64>,
+    pub prune_missing: bool,
+}
+
+impl PrunePolicy {
+    pub fn is_empty(&self) -> bool {
+        self.max_age.is_none() && self.max_size.is_none() && !self.prune_missing
+    }
+
+    /// Parse a `[cache]` table.
+    pub fn from_table(table: &toml::Table) -> Result<Self, String> {
+        let text = |key: &str| -> Result<Option<&str>, String> {
+            match table.get(key) {
+                None => Ok(None),
+                Some(v) => v.as_str().map(Some).ok_or(format!("[cache] {key} must be a string")),
+            }
+        };
+        Ok(Self {
+            max_age: text("max_age")?.map(parse_duration).transpose()?,
+            max_size: text("max_size")?.map(parse_size).transpose()?,
+            prune_missing: table.get("prune_missing").and_then(|v| v.as_bool()).unwrap_or(false),
+        })
+    }
+}
+
+/// Parse a duration like `60d`, `12h`, `2w`, `30m` or `90s`.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let n: u64 = num.parse().map_err(|_| format!("invalid duration '{s}' (expected e.g. 60d)"))?;
+    let secs = match unit.trim() {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        "w" => 7 * 86_400,
+        _ => return Err(format!("invalid duration '{s}' (units: s, m, h, d, w)")),
+    };
+    Ok(Duration::from_secs(n * secs))
+}
+
+/// Parse a size like `5G`, `500MB`, `64k` or a plain byte count. Units are binary.
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let n: f64 = num.parse().map_err(|_| format!("invalid size '{s}' (expected e.g. 5G)"))?;
+    let unit = unit.trim().to_ascii_uppercase();
+    let scale: u64 = match unit.trim_end_matches('B').trim_end_matches('I') {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        "T" => 1 << 40,
+        _ => return Err(format!("invalid size '{s}' (units: K, M, G, T)")),
+    };
+    Ok((n * scale as f64) as u64)
+}
+
+/// Short age for listings: `45m`, `7h`, `63d`.
+fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    if secs < 3600 {
+        format!("{}m", secs / 60)
+    } else if secs < 86_400 {
+        format!("{}h", secs / 3600)
+    } else {
+        format!("{}d", secs / 86_400)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Inventory
+// ---------------------------------------------------------------------------
+
+/// Every per-repo entry under `base`, oldest first.
+pub fn list(base: &Path) -> Vec<CacheEntry> {
+    let mut entries = Vec::new();
+    for dir in read_dir_paths(&base.join("semantic")).into_iter().filter(|p| p.is_dir()) {
+        let files: Vec<(u64, SystemTime)> = read_dir_paths(&dir)
+            .iter()
+            .filter_map(|p| std::fs::metadata(p).ok())
+            .filter(|m| m.is_file())
+            .map(|m| (m.len(), m.modified().unwrap_or(SystemTime::UNIX_EPOCH)))
+            .collect();
+        let repo = std::fs::read_to_string(dir.join("meta.json"))
+            .ok()
+            .and_then(|s| serde_json::from_str::<serde_json::Value>(&s).ok())
+            .and_then(|v| v.get("last_path").and_then(|p| p.as_str()).map(PathBuf::from));
+        entries.push(CacheEntry {
+            kind: "semantic",
+            path: dir,
+            repo,
+            bytes: files.iter().map(|f| f.0).sum(),
+            modified: files.iter().map(|f| f.1).max().unwrap_or(SystemTime::UNIX_EPOCH),
+        });
+    }
+    for file in read_dir_paths(&base.join("handoff")) {
+        let Ok(meta) = std::fs::metadata(&file) else { continue };
+        if !meta.is_file() || file.extension().is_none_or(|e| e != "json") {
+            continue;
+        }
+        entries.push(CacheEntry {
+            kind: "handoff",
+            repo: handoff_root(&file),
+            path: file,
+            bytes: meta.len(),
+            modified: meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+        });
+    }
+    entries.sort_by_key(|e| e.modified);
+    entries
+}
+
+fn read_dir_paths(dir: &Path) -> Vec<PathBuf> {
+    std::fs::read_dir(dir).map(|rd| rd.flatten().map(|e| e.path()).collect()).unwrap_or_default()
+}
+
+/// Repo root recorded in a handoff file.
+fn handoff_root(path: &Path) -> Option<PathBuf> {
+    #[derive(Deserialize)]
+    struct Header {
+        root: PathBuf,
+    }
+    let reader = crate::crypt::open(path).ok()?;
+    serde_json::from_reader::<_, Header>(reader).ok().map(|h| h.root)
+}
+
+// ---------------------------------------------------------------------------
+// Pruning
+// ---------------------------------------------------------------------------
+
+/// Indices into `entries` (oldest first) that `policy` removes. Entries of the repos in
+/// `keep` are never removed, but still count toward `max_size`.
+pub fn select(
+    entries: &[CacheEntry],
+    policy: &PrunePolicy,
+    keep: &[PathBuf],
+    now: SystemTime,
+) -> Vec<usize> {
+    let kept = |e: &CacheEntry| e.repo.as_ref().is_some_and(|r| keep.contains(r));
+    let mut remove: Vec<bool> = entries
+        .iter()
+        .map(|e| {
+            !kept(e)
+                && (policy.max_age.is_some_and(|max| e.age(now) > max)
+                    || (policy.prune_missing && e.repo_missing()))
+        })
+        .collect();
+    if let Some(max) = policy.max_size {
+        let mut total: u64 =
+            entries.iter().zip(&remove).filter(|(_, r)| !**r).map(|(e, _)| e.bytes).sum();
+        for (i, e) in entries.iter().enumerate() {
+            if total <= max {
+                break;
+            }
+            if !remove[i] && !kept(e) {
+                remove[i] = true;
+                total -= e.bytes;
+            }
+        }
+    }
+    (0..entries.len()).filter(|&i| remove[i]).collect()
+}
+
+fn remove_entry(entry: &CacheEntry) -> std::io::Result<()> {
+    if entry.path.is_dir() {
+        std::fs::remove_dir_all(&entry.path)
+    } else {
+        std::fs::remove_file(&entry.path)
+    }
+}
+
+/// `[cache]` from `~/.codescope/repos.toml`, if there is one.
+pub fn cache_table() -> Result<Option<toml::Table>, String> {
+    let Some(path) = crate::config_dir().map(|d| d.join("repos.toml")) else {
+        return Ok(None);
+    };
+    let Ok(content) = std::fs::read_to_string(&path) else {
+        return Ok(None);
+    };
+    let mut table: toml::Table =
+        content.parse().map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+    match table.remove("cache") {
+        Some(toml::Value::Table(t)) => Ok(Some(t)),
+        _ => Ok(None),
+    }
+}
+
+/// The `[cache]` policy; the empty policy when absent.
+pub fn load_policy() -> Result<PrunePolicy, String> {
+    match cache_table()? {
+        Some(t) => PrunePolicy::from_table(&t),
+        None => Ok(PrunePolicy::default()),
+    }
+}
+
+/// Server startup housekeeping: delete temp files of interrupted writes, mark the caches
+/// of `roots` as used, then apply the `[cache]` policy to everything else.
+pub fn auto_prune(roots: &[PathBuf]) {
+    let Some(base) = crate::cache_dir() else { return };
+    let policy = match load_policy() {
+        Ok(p) => p,
+        Err(e) => {
+            warn!(error = %e, "Ignoring [cache] policy");
+            PrunePolicy::default()
+        }
+    };
+    let mut orphans = crate::atomic::remove_stale_temps(&base.join("handoff"));
+    for dir in read_dir_paths(&base.join("semantic")) {
+        orphans += crate::atomic::remove_stale_temps(&dir);
+    }
+    if orphans > 0 {
+        info!(files = orphans, "Removed temp files left by interrupted cache writes");
+    }
+    let now = SystemTime::now();
+    let entries = list(&base);
+    for entry in entries.iter().filter(|e| e.repo.as_ref().is_some_and(|r| roots.contains(r))) {
+        touch(entry, now);
+    }
+    if policy.is_empty() {
+        return;
+    }
+    let mut keep = in_use_elsewhere(&entries);
+    keep.extend(roots.iter().cloned());
+    let mut freed = 0;
+    let mut removed = 0;
+    for i in select(&entries, &policy, &keep, now) {
+        match remove_entry(&entries[i]) {
+            Ok(()) => {
+                freed += entries[i].bytes;
+                removed += 1;
+            }
+            Err(e) => warn!(path = %entries[i].path.display(), error = %e, "Cache prune failed"),
+        }
+    }
+    if removed > 0 {
+        info!(entries = removed, freed = %format_bytes(freed as usize), "Pruned cache");
+    }
+}
+
+/// Repo roots of `entries` whose caches another running server holds (see
+/// [`crate::locks`]).
+fn in_use_elsewhere(entries: &[CacheEntry]) -> Vec<PathBuf> {
+    let mut roots: Vec<PathBuf> = entries.iter().filter_map(|e| e.repo.clone()).collect();
+    roots.sort();
+    roots.dedup();
+    roots.retain(|r| crate::locks::held_elsewhere(r));
+    roots
+}
+
+/// Bump the modification time of an entry's files to `now`.
+fn touch(entry: &CacheEntry, now: SystemTime) {
+    let files =
+        if entry.path.is_dir() { read_dir_paths(&entry.path) } else { vec![entry.path.clone()] };
+    for file in files {
+        if let Ok(f) = std::fs::File::options().append(true).open(&file) {
+            let _ = f.set_modified(now);
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// codescope cache
+// ---------------------------------------------------------------------------
+
+fn cache_base() -> Option<PathBuf> {
+    let base = crate::cache_dir();
+    if base.is_none() {
+        eprintln!("{}: {}", crate::tr!("error-prefix"), crate::tr!("cache-dir-unknown"));
+    }
+    base
+}
+
+/// `codescope cache ls`: one line per entry on stdout, totals on stderr.
+pub fn run_ls() -> i32 {
+    let Some(base) = cache_base() else { return 1 };
+    let entries = list(&base);
+    let now = SystemTime::now();
+    for e in &entries {
+        let repo = e.repo.as_ref().map_or("?".to_string(), |r| r.display().to_string());
+        let missing = if e.repo_missing() {
+            format!("  {}", crate::tr!("cache-repo-missing"))
+        } else {
+            String::new()
+        };
+        println!(
+            "{:>6}  {:>9}  {:<8}  {repo}{missing}",
+            format_age(e.age(now)),
+            format_bytes(e.bytes as usize),
+            e.kind
+        );
+    }
+    let total: u64 = entries.iter().map(|e| e.bytes).sum();
+    eprintln!(
+        "{}",
+        crate::tr!(
+            "cache-total",
+            count = entries.len(),
+            size = format_bytes(total as usize),
+            path = base.display().to_string()
+        )
+    );
+    0
+}
+
+/// `codescope cache prune`: apply `policy`, or the `[cache]` policy when it is empty.
+/// Caches of repos a running server holds are kept.
+pub fn run_prune(policy: PrunePolicy, dry_run: bool) -> i32 {
+    let Some(base) = cache_base() else { return 1 };
+    let policy = if policy.is_empty() {
+        match load_policy() {
+            Ok(p) => p,
+            Err(e) => {
+                eprintln!("{}: {e}", crate::tr!("error-prefix"));
+                return 1;
+            }
+        }
+    } else {
+        policy
+    };
+    if policy.is_empty() {
+        eprintln!("{}", crate::tr!("cache-no-policy"));
+        return 2;
+    }
+    let entries = list(&base);
+    let mut freed = 0;
+    let mut removed = 0;
+    let mut failed = false;
+    let keep = in_use_elsewhere(&entries);
+    for i in select(&entries, &policy, &keep, SystemTime::now()) {
+        let e = &entries[i];
+        let result = if dry_run { Ok(()) } else { remove_entry(e) };
+        match result {
+            Ok(()) => {
+                println!("{}", e.path.display());
+                freed += e.bytes;
+                removed += 1;
+            }
+            Err(err) => {
+                eprintln!("{}: {}: {err}", crate::tr!("error-prefix"), e.path.display());
+                failed = true;
+            }
+        }
+    }
+    let size = format_bytes(freed as usize);
+    if dry_run {
+        eprintln!("{}", crate::tr!("cache-would-prune", count = removed, size = size));
+    } else {
+        eprintln!("{}", crate::tr!("cache-pruned", count = removed, size = size));
+    }
+    i32::from(failed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prune_selects_by_age_missing_repo_and_size() {
+        let now = SystemTime::now();
+        let day = Duration::from_secs(86_400);
+        let entry = |repo: &str, days: u32, mb: u64| CacheEntry {
+            kind: "semantic",
+            path: PathBuf::from(repo),
+            repo: Some(PathBuf::from(repo)),
+            bytes: mb << 20,
+            modified: now - day * days,
+        };
+        let here = std::env::current_dir().unwrap();
+        let entries = vec![
+            entry("/nonexistent/old", 90, 10),
+            entry(here.to_str().unwrap(), 80, 10),
+            entry("/nonexistent/gone", 5, 10),
+            entry("/nonexistent/mid", 3, 30),
+            entry("/nonexistent/new", 1, 30),
+        ];
+
+        let policy = PrunePolicy::from_table(
+            &toml::from_str("max_age = \"60d\"\nmax_size = \"50M\"").unwrap(),
+        )
+        .unwrap();
+        assert_eq!(policy.max_age, Some(day * 60));
+        // age drops 0 and 1, leaving 70MB; then the oldest go until 50MB fits
+        assert_eq!(select(&entries, &policy, &[], now), [0, 1, 2, 3]);
+        // kept repos survive and still count toward the size limit
+        assert_eq!(select(&entries, &policy, &[here], now), [0, 2, 3]);
+
+        let missing = PrunePolicy { prune_missing: true, ..Default::default() };
+        assert_eq!(select(&entries, &missing, &[], now), [0, 2, 3, 4]);
+        assert!(select(&entries, &PrunePolicy::default(), &[], now).is_empty());
+
+        assert_eq!(parse_size("5G"), Ok(5 << 30));
+        assert_eq!(parse_size("1.5 MB"), Ok(3 << 19));
+        assert_eq!(parse_duration("2w"), Ok(day * 14));
+        assert!(parse_duration("60 days").is_err());
+        assert!(PrunePolicy::from_table(&toml::from_str("max_size = 5").unwrap()).is_err());
+    }
+}
@@ -0,0 +1,138 @@
+//! Listing order for module and file names (`sort_order` in `.codescope.toml`).
+//!
+//! The default `natural` order compares digit runs by value, so `File2.cs` comes before
+//! `File10.cs`, and letters case- and accent-insensitively, so `Zeta` no longer precedes
+//! `alpha` and `École` sorts with the other `e` names. Scripts keep their Unicode block
+//! order (Latin, Greek, Cyrillic), as in the common collation tables; within Cyrillic,
+//! `ё` sorts as `е` instead of after `я`. Names that compare equal this way fall back to
+//! byte order, so the result is stable. `bytes` keeps plain byte order.
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::iter::Peekable;
+use std::str::Chars;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    #[default]
+    Natural,
+    Bytes,
+}
+
+impl SortOrder {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "natural" => Ok(Self::Natural),
+            "bytes" => Ok(Self::Bytes),
+            other => Err(format!("Unknown sort_order '{other}'. Expected 'natural' or 'bytes'")),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Natural => "natural",
+            Self::Bytes => "bytes",
+        }
+    }
+
+    pub fn compare(self, a: &str, b: &str) -> Ordering {
+        match self {
+            Self::Natural => natural_cmp(a, b),
+            Self::Bytes => a.cmp(b),
+        }
+    }
+
+    /// Entries of a map keyed by name (a manifest's modules), in this order.
+    pub fn sorted<V>(self, map: &BTreeMap<String, V>) -> Vec<(&String, &V)> {
+        let mut entries: Vec<_> = map.iter().collect();
+        if self != Self::Bytes {
+            entries.sort_by(|a, b| self.compare(a.0, b.0));
+        }
+        entries
+    }
+}
+
+/// Natural, case- and accent-insensitive comparison, ties broken by byte order.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut x, mut y) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        let ord = match (x.peek().copied(), y.peek().copied()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(c), Some(d)) if c.is_ascii_digit() && d.is_ascii_digit() => {
+                let (m, n) = (digits(&mut x), digits(&mut y));
+                let (m, n) = (m.trim_start_matches('0'), n.trim_start_matches('0'));
+                m.len().cmp(&n.len()).then_with(|| m.cmp(n))
+            }
+            (Some(c), Some(d)) => {
+                x.next();
+                y.next();
+                fold(c).cmp(&fold(d))
+            }
+        };
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+}
+
+fn digits(chars: &mut Peekable<Chars>) -> String {
+    let mut run = String::new();
+    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+        run.push(c);
+    }
+    run
+}
+
+/// Lowercase base letter of `c`, for the accented letters of the Latin-1 and Latin
+/// Extended-A ranges in common use, and `ё`.
+fn fold(c: char) -> char {
+    let c = c.to_lowercase().next().unwrap_or(c);
+    match c {
+        'à'..='å' | 'ā' | 'ă' | 'ą' => 'a',
+        'ç' | 'ć' | 'č' => 'c',
+        'ď' | 'đ' => 'd',
+        'è'..='ë' | 'ē' | 'ė' | 'ę' | 'ě' => 'e',
+        'ğ' => 'g',
+        'ì'..='ï' | 'ī' | 'į' | 'ı' => 'i',
+        'ł' | 'ľ' => 'l',
+        'ñ' | 'ń' | 'ň' => 'n',
+        'ò'..='ö' | 'ø' | 'ō' | 'ő' => 'o',
+        'ř' => 'r',
+        'ś' | 'š' | 'ş' => 's',
+        'ť' | 'ţ' => 't',
+        'ù'..='ü' | 'ū' | 'ů' | 'ű' | 'ų' => 'u',
+        'ý' | 'ÿ' => 'y',
+        'ź' | 'ż' | 'ž' => 'z',
+        'ё' => 'е',
+        _ => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(order: SortOrder, names: &[&str]) -> Vec<String> {
+        let mut names: Vec<String> = names.iter().map(|s| s.to_string()).collect();
+        names.sort_by(|a, b| order.compare(a, b));
+        names
+    }
+
+    #[test]
+    fn natural_order_compares_numbers_by_value_and_ignores_case_and_accents() {
+        let names =
+            ["File10.cs", "file2.cs", "File1.cs", "Zeta", "alpha", "École", "ёж", "яд", "ель"];
+        assert_eq!(
+            sorted(SortOrder::Natural, &names),
+            ["alpha", "École", "File1.cs", "file2.cs", "File10.cs", "Zeta", "ёж", "ель", "яд"]
+        );
+        assert_eq!(
+            sorted(SortOrder::Bytes, &names),
+            ["File1.cs", "File10.cs", "Zeta", "alpha", "file2.cs", "École", "ель", "яд", "ёж"]
+        );
+        // Equal apart from case or zero padding: byte order decides
+        assert_eq!(sorted(SortOrder::Natural, &["a", "A", "v07", "v7"]), ["A", "a", "v07", "v7"]);
+    }
+}
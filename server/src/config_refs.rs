@@ -0,0 +1,260 @@
+//! Environment variable and config key references, indexed during scan for `cs_config_refs`.
+//!
+//! Reads are matched per language (`std::env::var("X")`, `process.env.X`,
+//! `os.environ["X"]`, `os.Getenv("X")`, `viper.GetString("key")`, ...). Assignments in
+//! `.env` files are recorded as definitions, so a lookup shows both where a variable is
+//! read and where it is set. Only names are indexed, never values. The file walk skips
+//! hidden files, so `.env*` files at the repo root are read separately.
+
+use rayon::prelude::*;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::types::{ScanConfig, ScannedFile};
+
+/// Files larger than this are skipped (likely generated or bundled).
+const MAX_REF_SCAN_BYTES: u64 = 256 * 1024;
+
+/// Whether a reference names an environment variable or an application config key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigRefKind {
+    Env,
+    Config,
+}
+
+impl ConfigRefKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Env => "env",
+            Self::Config => "config",
+        }
+    }
+}
+
+/// One reference to an environment variable or config key.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ConfigRef {
+    pub kind: ConfigRefKind,
+    pub name: String,
+    pub path: String,
+    /// 1-based line number.
+    pub line: usize,
+    /// The line assigns the value (`.env` files) rather than reading it.
+    pub definition: bool,
+}
+
+/// Compiled reference patterns. Build once per scan and reuse.
+pub struct ConfigRefDetector {
+    rust_env: Regex,
+    js_env: Regex,
+    js_config: Regex,
+    py_env: Regex,
+    py_config: Regex,
+    go_env: Regex,
+    go_config: Regex,
+    cs_env: Regex,
+    cs_config: Regex,
+    jvm_env: Regex,
+    jvm_config: Regex,
+    rb_env: Regex,
+    php_env: Regex,
+    dotenv_def: Regex,
+}
+
+impl Default for ConfigRefDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConfigRefDetector {
+    pub fn new() -> Self {
+        Self {
+            rust_env: Regex::new(r#"(?:env::var(?:_os)?|env!|option_env!)\(\s*"([^"]+)""#)
+                .unwrap(),
+            js_env: Regex::new(
+                r#"(?:process\.env|import\.meta\.env)(?:\.([A-Za-z_]\w*)|\[\s*['"`]([^'"`]+)['"`]\s*\])|Deno\.env\.get\(\s*['"`]([^'"`]+)"#,
+            )
+            .unwrap(),
+            js_config: Regex::new(r#"\b(?:config|nconf|convict)\.(?:get|has)\(\s*['"`]([\w.:-]+)['"`]"#)
+                .unwrap(),
+            py_env: Regex::new(
+                r#"(?:os\.environ(?:\.get|\.setdefault|\.pop)?\s*[\[(]|os\.getenv\(|getenv\()\s*['"]([^'"]+)['"]"#,
+            )
+            .unwrap(),
+            py_config: Regex::new(
+                r#"\b(?:config|settings|conf|cfg|app\.config)(?:\.get)?\s*[\[(]\s*['"]([\w.:-]+)['"]"#,
+            )
+            .unwrap(),
+            go_env: Regex::new(r#"os\.(?:Getenv|LookupEnv|Setenv)\(\s*"([^"]+)""#).unwrap(),
+            go_config: Regex::new(r#"viper\.(?:Get\w*|IsSet|SetDefault|BindEnv)\(\s*"([^"]+)""#)
+                .unwrap(),
+            cs_env: Regex::new(r#"Environment\.GetEnvironmentVariable\(\s*"([^"]+)""#).unwrap(),
+            cs_config: Regex::new(
+                r#"(?:[Cc]onfiguration|[Cc]onfig)(?:\[\s*"([^"]+)"\s*\]|\.(?:GetValue<[^>]+>|GetSection|GetConnectionString)\(\s*"([^"]+)")"#,
+            )
+            .unwrap(),
+            jvm_env: Regex::new(r#"System\.getenv\(\s*"([^"]+)""#).unwrap(),
+            jvm_config: Regex::new(
+                r#"(?:@Value\(\s*"\$\{([^}:]+)|System\.getProperty\(\s*"([^"]+)"|getProperty\(\s*"([^"]+)")"#,
+            )
+            .unwrap(),
+            rb_env: Regex::new(r#"ENV(?:\.fetch\(|\[)\s*['"]([^'"]+)['"]"#).unwrap(),
+            php_env: Regex::new(r#"(?:getenv|env)\(\s*['"]([^'"]+)['"]|\$_ENV\[\s*['"]([^'"]+)['"]"#)
+                .unwrap(),
+            dotenv_def: Regex::new(r"^\s*(?:export\s+)?([A-Za-z_][A-Za-z0-9_]*)\s*=").unwrap(),
+        }
+    }
+
+    /// Find references in one file's content.
+    pub fn detect(&self, rel_path: &str, ext: &str, content: &str) -> Vec<ConfigRef> {
+        let filename = rel_path.rsplit('/').next().unwrap_or(rel_path);
+        let is_dotenv = filename == ".env" || filename.starts_with(".env.") || ext == "env";
+        let patterns: &[(&Regex, ConfigRefKind)] = match ext {
+            _ if is_dotenv => &[],
+            "rs" => &[(&self.rust_env, ConfigRefKind::Env)],
+            "js" | "mjs" | "cjs" | "jsx" | "ts" | "tsx" | "vue" | "svelte" => {
+                &[(&self.js_env, ConfigRefKind::Env), (&self.js_config, ConfigRefKind::Config)]
+            }
+            "py" => &[(&self.py_env, ConfigRefKind::Env), (&self.py_config, ConfigRefKind::Config)],
+            "go" => &[(&self.go_env, ConfigRefKind::Env), (&self.go_config, ConfigRefKind::Config)],
+            "cs" => &[(&self.cs_env, ConfigRefKind::Env), (&self.cs_config, ConfigRefKind::Config)],
+            "java" | "kt" | "scala" => {
+                &[(&self.jvm_env, ConfigRefKind::Env), (&self.jvm_config, ConfigRefKind::Config)]
+            }
+            "rb" => &[(&self.rb_env, ConfigRefKind::Env)],
+            "php" => &[(&self.php_env, ConfigRefKind::Env)],
+            _ => return Vec::new(),
+        };
+
+        let mut out = Vec::new();
+        for (i, line) in content.lines().enumerate() {
+            if is_dotenv {
+                if let Some(c) = self.dotenv_def.captures(line) {
+                    out.push(ConfigRef {
+                        kind: ConfigRefKind::Env,
+                        name: c[1].to_string(),
+                        path: rel_path.to_string(),
+                        line: i + 1,
+                        definition: true,
+                    });
+                }
+                continue;
+            }
+            for (re, kind) in patterns {
+                for c in re.captures_iter(line) {
+                    // Alternations put the name in whichever group matched.
+                    let Some(name) = c.iter().skip(1).flatten().next() else {
+                        continue;
+                    };
+                    out.push(ConfigRef {
+                        kind: *kind,
+                        name: name.as_str().to_string(),
+                        path: rel_path.to_string(),
+                        line: i + 1,
+                        definition: false,
+                    });
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Read and scan one file. Large or unreadable files yield nothing.
+pub fn detect_file_config_refs(detector: &ConfigRefDetector, file: &ScannedFile) -> Vec<ConfigRef> {
+    if file.abs_path.metadata().map(|m| m.len()).unwrap_or(0) > MAX_REF_SCAN_BYTES {
+        return Vec::new();
+    }
+    match crate::encoding::read_to_string(&file.abs_path) {
+        Ok(content) => detector.detect(&file.rel_path, &file.ext, &content),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Index references across all scanned files plus root `.env*` files, sorted by name,
+/// then path and line.
+pub fn scan_config_refs(config: &ScanConfig, all_files: &[ScannedFile]) -> Vec<ConfigRef> {
+    let detector = ConfigRefDetector::new();
+    let mut refs: Vec<ConfigRef> =
+        all_files.par_iter().flat_map_iter(|f| detect_file_config_refs(&detector, f)).collect();
+    refs.extend(root_dotenv_refs(&detector, config));
+    sort_config_refs(&mut refs);
+    refs
+}
+
+/// Definitions in the `.env*` files at the repo root, which the file walk skips.
+pub fn root_dotenv_refs(detector: &ConfigRefDetector, config: &ScanConfig) -> Vec<ConfigRef> {
+    let mut refs = Vec::new();
+    if let Ok(entries) = std::fs::read_dir(&config.root) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !(name == ".env" || name.starts_with(".env.")) || !config.read_policy.permits(&name)
+            {
+                continue;
+            }
+            if let Ok(content) = std::fs::read_to_string(entry.path()) {
+                refs.extend(detector.detect(&name, "", &content));
+            }
+        }
+    }
+    refs
+}
+
+/// Canonical ordering of the reference table.
+pub fn sort_config_refs(refs: &mut [ConfigRef]) {
+    refs.sort_by(|a, b| a.name.cmp(&b.name).then(a.path.cmp(&b.path)).then(a.line.cmp(&b.line)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(refs: &[ConfigRef]) -> Vec<(&str, ConfigRefKind, usize, bool)> {
+        refs.iter().map(|r| (r.name.as_str(), r.kind, r.line, r.definition)).collect()
+    }
+
+    #[test]
+    fn detects_reads_per_language_and_dotenv_definitions() {
+        let d = ConfigRefDetector::new();
+        let rust =
+            "let url = std::env::var(\"DATABASE_URL\")?;\nlet v = env!(\"CARGO_PKG_VERSION\");\n";
+        assert_eq!(
+            names(&d.detect("src/db.rs", "rs", rust)),
+            vec![
+                ("DATABASE_URL", ConfigRefKind::Env, 1, false),
+                ("CARGO_PKG_VERSION", ConfigRefKind::Env, 2, false),
+            ]
+        );
+
+        let ts =
+            "const port = process.env.PORT ?? process.env['HTTP_PORT'];\nconfig.get('db.host');\n";
+        assert_eq!(
+            names(&d.detect("web/server.ts", "ts", ts)),
+            vec![
+                ("PORT", ConfigRefKind::Env, 1, false),
+                ("HTTP_PORT", ConfigRefKind::Env, 1, false),
+                ("db.host", ConfigRefKind::Config, 2, false),
+            ]
+        );
+
+        let py = "key = os.environ.get(\"API_KEY\")\nlevel = os.getenv('LOG_LEVEL', 'info')\n";
+        assert_eq!(
+            names(&d.detect("app/settings.py", "py", py)),
+            vec![
+                ("API_KEY", ConfigRefKind::Env, 1, false),
+                ("LOG_LEVEL", ConfigRefKind::Env, 2, false)
+            ]
+        );
+
+        let dotenv = "# local\nDATABASE_URL=postgres://localhost\nexport PORT=8080\n";
+        assert_eq!(
+            names(&d.detect(".env.example", "example", dotenv)),
+            vec![
+                ("DATABASE_URL", ConfigRefKind::Env, 2, true),
+                ("PORT", ConfigRefKind::Env, 3, true)
+            ]
+        );
+    }
+}
@@ -0,0 +1,517 @@
+//! Project conventions mined from tool configuration: formatter and linter settings
+//! (rustfmt, clippy, eslint, prettier, editorconfig) and the commands CI runs.
+//!
+//! Config files are read from the repo root and its top-level directories, so workspace
+//! members like `server/rustfmt.toml` are found. Nothing is executed: JavaScript configs
+//! (`eslint.config.js`, `prettier.config.js`) are reported by presence only.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::types::RepoState;
+
+/// Maximum CI commands kept per workflow file.
+const MAX_CI_COMMANDS: usize = 50;
+
+/// Leading words that mark a CI line as a build, lint, or test command worth reporting.
+const CI_TOOLS: &[&str] = &[
+    "cargo",
+    "rustup",
+    "npm",
+    "npx",
+    "pnpm",
+    "yarn",
+    "bun",
+    "deno",
+    "node",
+    "tsc",
+    "eslint",
+    "prettier",
+    "go",
+    "golangci-lint",
+    "python",
+    "python3",
+    "pip",
+    "pytest",
+    "uv",
+    "ruff",
+    "mypy",
+    "black",
+    "make",
+    "just",
+    "cmake",
+    "ctest",
+    "dotnet",
+    "gradle",
+    "./gradlew",
+    "mvn",
+    "bundle",
+    "rake",
+];
+
+/// Settings from one formatter or linter config file.
+#[derive(Serialize)]
+pub struct ToolConfig {
+    pub tool: String,
+    /// Repo-relative config path (with `#section` for configs embedded in another file).
+    pub source: String,
+    /// Flattened settings (`key` or `section.key` → value). Empty for unevaluated JS configs.
+    pub settings: BTreeMap<String, String>,
+}
+
+/// Triggers and tool commands from one CI workflow file.
+#[derive(Serialize)]
+pub struct CiWorkflow {
+    pub source: String,
+    pub name: Option<String>,
+    pub triggers: Vec<String>,
+    pub commands: Vec<String>,
+}
+
+/// Structured conventions report for a repo.
+#[derive(Serialize, Default)]
+pub struct ConventionsReport {
+    pub formatting: Vec<ToolConfig>,
+    pub linting: Vec<ToolConfig>,
+    pub ci: Vec<CiWorkflow>,
+}
+
+impl ConventionsReport {
+    pub fn is_empty(&self) -> bool {
+        self.formatting.is_empty() && self.linting.is_empty() && self.ci.is_empty()
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Mining
+// ---------------------------------------------------------------------------
+
+/// Mine formatter, linter, and CI conventions for a repo.
+pub fn mine_conventions(repo: &RepoState) -> ConventionsReport {
+    let root = &repo.root;
+    let mut report = ConventionsReport::default();
+
+    let mut dirs = vec![String::new()];
+    if let Ok(entries) = fs::read_dir(root) {
+        let mut children: Vec<String> = entries
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_ok_and(|t| t.is_dir()))
+            .filter_map(|e| e.file_name().to_str().map(String::from))
+            .filter(|n| !n.starts_with('.') && !repo.config.skips_dir(n))
+            .collect();
+        children.sort();
+        dirs.extend(children.into_iter().map(|n| format!("{n}/")));
+    }
+
+    for dir in &dirs {
+        let read = |name: &str| {
+            let rel = format!("{dir}{name}");
+            fs::read_to_string(root.join(&rel)).ok().map(|content| (rel, content))
+        };
+
+        for name in ["rustfmt.toml", ".rustfmt.toml"] {
+            if let Some((source, content)) = read(name) {
+                report.formatting.push(ToolConfig {
+                    tool: "rustfmt".into(),
+                    source,
+                    settings: toml_settings(&content),
+                });
+            }
+        }
+        if let Some((source, content)) = read(".editorconfig") {
+            report.formatting.push(ToolConfig {
+                tool: "editorconfig".into(),
+                source,
+                settings: editorconfig_settings(&content),
+            });
+        }
+        for name in [".prettierrc", ".prettierrc.json", ".prettierrc.yaml", ".prettierrc.yml"] {
+            if let Some((source, content)) = read(name) {
+                report.formatting.push(ToolConfig {
+                    tool: "prettier".into(),
+                    source,
+                    settings: json_or_yaml_settings(&content),
+                });
+            }
+        }
+
+        for name in ["clippy.toml", ".clippy.toml"] {
+            if let Some((source, content)) = read(name) {
+                report.linting.push(ToolConfig {
+                    tool: "clippy".into(),
+                    source,
+                    settings: toml_settings(&content),
+                });
+            }
+        }
+        if let Some((source, content)) = read("Cargo.toml") {
+            if let Ok(table) = content.parse::<toml::Table>() {
+                for (section, lints) in [
+                    ("lints", table.get("lints")),
+                    ("workspace.lints", table.get("workspace").and_then(|w| w.get("lints"))),
+                ] {
+                    let Some(lints) = lints.and_then(|l| l.as_table()) else { continue };
+                    for (tool, rules) in lints {
+                        let Some(rules) = rules.as_table() else { continue };
+                        let mut settings = BTreeMap::new();
+                        flatten_toml("", rules, &mut settings);
+                        report.linting.push(ToolConfig {
+                            tool: if tool == "rust" { "rustc".into() } else { tool.clone() },
+                            source: format!("{source}#{section}.{tool}"),
+                            settings,
+                        });
+                    }
+                }
+            }
+        }
+        for name in [".eslintrc", ".eslintrc.json"] {
+            if let Some((source, content)) = read(name) {
+                report.linting.push(ToolConfig {
+                    tool: "eslint".into(),
+                    source,
+                    settings: json_or_yaml_settings(&content),
+                });
+            }
+        }
+        for name in [
+            "eslint.config.js",
+            "eslint.config.mjs",
+            "eslint.config.cjs",
+            "eslint.config.ts",
+            ".eslintrc.js",
+            ".eslintrc.cjs",
+        ] {
+            if let Some((source, _)) = read(name) {
+                report.linting.push(ToolConfig {
+                    tool: "eslint".into(),
+                    source,
+                    settings: BTreeMap::new(),
+                });
+            }
+        }
+        for name in ["prettier.config.js", "prettier.config.mjs", ".prettierrc.js"] {
+            if let Some((source, _)) = read(name) {
+                report.formatting.push(ToolConfig {
+                    tool: "prettier".into(),
+                    source,
+                    settings: BTreeMap::new(),
+                });
+            }
+        }
+        if let Some((source, content)) = read("package.json") {
+            if let Ok(pkg) = serde_json::from_str::<serde_json::Value>(&content) {
+                if let Some(cfg) = pkg.get("prettier") {
+                    let mut settings = BTreeMap::new();
+                    flatten_json("", cfg, &mut settings);
+                    report.formatting.push(ToolConfig {
+                        tool: "prettier".into(),
+                        source: format!("{source}#prettier"),
+                        settings,
+                    });
+                }
+                if let Some(cfg) = pkg.get("eslintConfig") {
+                    let mut settings = BTreeMap::new();
+                    flatten_json("", cfg, &mut settings);
+                    report.linting.push(ToolConfig {
+                        tool: "eslint".into(),
+                        source: format!("{source}#eslintConfig"),
+                        settings,
+                    });
+                }
+            }
+        }
+    }
+
+    report.ci = ci_files(root)
+        .into_iter()
+        .filter_map(|rel| {
+            let content = fs::read_to_string(root.join(&rel)).ok()?;
+            Some(parse_ci_workflow(&rel, &content))
+        })
+        .collect();
+
+    report
+}
+
+/// Repo-relative paths of recognized CI configuration files.
+fn ci_files(root: &Path) -> Vec<String> {
+    let mut files = Vec::new();
+    for dir in [".github/workflows", ".gitea/workflows"] {
+        if let Ok(entries) = fs::read_dir(root.join(dir)) {
+            let mut names: Vec<String> = entries
+                .filter_map(|e| e.ok())
+                .filter_map(|e| e.file_name().to_str().map(String::from))
+                .filter(|n| n.ends_with(".yml") || n.ends_with(".yaml"))
+                .collect();
+            names.sort();
+            files.extend(names.into_iter().map(|n| format!("{dir}/{n}")));
+        }
+    }
+    for rel in [
+        ".gitlab-ci.yml",
+        ".circleci/config.yml",
+        "azure-pipelines.yml",
+        "bitbucket-pipelines.yml",
+        ".travis.yml",
+    ] {
+        if root.join(rel).is_file() {
+            files.push(rel.to_string());
+        }
+    }
+    files
+}
+
+// ---------------------------------------------------------------------------
+// Config parsers
+// ---------------------------------------------------------------------------
+
+fn toml_settings(content: &str) -> BTreeMap<String, String> {
+    let mut settings = BTreeMap::new();
+    if let Ok(table) = content.parse::<toml::Table>() {
+        flatten_toml("", &table, &mut settings);
+    }
+    settings
+}
+
+fn flatten_toml(prefix: &str, table: &toml::Table, out: &mut BTreeMap<String, String>) {
+    for (key, value) in table {
+        let key = if prefix.is_empty() { key.clone() } else { format!("{prefix}.{key}") };
+        match value {
+            toml::Value::Table(t) => flatten_toml(&key, t, out),
+            toml::Value::String(s) => {
+                out.insert(key, s.clone());
+            }
+            other => {
+                out.insert(key, other.to_string());
+            }
+        }
+    }
+}
+
+fn flatten_json(prefix: &str, value: &serde_json::Value, out: &mut BTreeMap<String, String>) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, v) in map {
+                let key = if prefix.is_empty() { key.clone() } else { format!("{prefix}.{key}") };
+                flatten_json(&key, v, out);
+            }
+        }
+        serde_json::Value::String(s) => {
+            out.insert(prefix.to_string(), s.clone());
+        }
+        other => {
+            out.insert(prefix.to_string(), other.to_string());
+        }
+    }
+}
+
+/// Parse a JSON config, falling back to flat `key: value` YAML (as `.prettierrc` allows).
+fn json_or_yaml_settings(content: &str) -> BTreeMap<String, String> {
+    let mut settings = BTreeMap::new();
+    if let Ok(value) = serde_json::from_str::<serde_json::Value>(content) {
+        flatten_json("", &value, &mut settings);
+        return settings;
+    }
+    for line in content.lines() {
+        if line.starts_with([' ', '\t', '#', '-']) {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            let value = value.trim().trim_matches(['"', '\'']);
+            if !key.trim().is_empty() && !value.is_empty() {
+                settings.insert(key.trim().to_string(), value.to_string());
+            }
+        }
+    }
+    settings
+}
+
+/// `.editorconfig` settings keyed as `[section] key`; keys before any section are bare.
+fn editorconfig_settings(content: &str) -> BTreeMap<String, String> {
+    let mut settings = BTreeMap::new();
+    let mut section = String::new();
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with(['#', ';']) {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = name.to_string();
+        } else if let Some((key, value)) = line.split_once('=') {
+            let key = key.trim();
+            let key =
+                if section.is_empty() { key.to_string() } else { format!("[{section}] {key}") };
+            settings.insert(key, value.trim().to_string());
+        }
+    }
+    settings
+}
+
+/// Line-based extraction of workflow name, triggers, and tool commands from CI YAML.
+///
+/// Commands come from `run:` values and blocks and from list items (GitLab `script:`),
+/// kept only when they start with a known build/lint/test tool.
+fn parse_ci_workflow(source: &str, content: &str) -> CiWorkflow {
+    let mut name = None;
+    let mut triggers = Vec::new();
+    let mut commands: Vec<String> = Vec::new();
+    let mut in_on = false;
+    let mut on_child_indent: Option<usize> = None;
+    let mut block_indent: Option<usize> = None;
+
+    let mut push_command = |cmd: &str| {
+        let cmd = cmd.trim();
+        let first = cmd.split_whitespace().next().unwrap_or("");
+        if CI_TOOLS.contains(&first)
+            && !commands.iter().any(|c| c == cmd)
+            && commands.len() < MAX_CI_COMMANDS
+        {
+            commands.push(cmd.to_string());
+        }
+    };
+
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        // Inside a `run: |` block: every more-indented line is a shell line.
+        if let Some(min) = block_indent {
+            if indent > min {
+                push_command(trimmed);
+                continue;
+            }
+            block_indent = None;
+        }
+
+        if indent == 0 {
+            in_on = false;
+            if let Some(value) = trimmed.strip_prefix("name:") {
+                name = Some(value.trim().trim_matches(['"', '\'']).to_string());
+            } else if let Some(value) =
+                trimmed.strip_prefix("on:").or_else(|| trimmed.strip_prefix("\"on\":"))
+            {
+                let value = value.trim().trim_start_matches('[').trim_end_matches(']');
+                if value.is_empty() {
+                    in_on = true;
+                    on_child_indent = None;
+                } else {
+                    triggers.extend(value.split(',').map(|t| t.trim().to_string()));
+                }
+            }
+            continue;
+        }
+
+        if in_on {
+            let child = *on_child_indent.get_or_insert(indent);
+            if indent == child {
+                if let Some((key, _)) = trimmed.split_once(':') {
+                    triggers.push(key.trim().to_string());
+                }
+            }
+            continue;
+        }
+
+        let item = trimmed.strip_prefix("- ").unwrap_or(trimmed);
+        if let Some(value) = item.strip_prefix("run:") {
+            let value = value.trim();
+            if value.starts_with(['|', '>']) {
+                block_indent = Some(indent);
+            } else {
+                push_command(value.trim_matches(['"', '\'']));
+            }
+        } else if trimmed.starts_with("- ") {
+            push_command(item.trim_matches(['"', '\'']));
+        }
+    }
+
+    CiWorkflow { source: source.to_string(), name, triggers, commands }
+}
+
+// ---------------------------------------------------------------------------
+// Rendering
+// ---------------------------------------------------------------------------
+
+/// Render a report as the `cs_conventions` text output.
+pub fn format_conventions(repo_name: &str, report: &ConventionsReport) -> String {
+    if report.is_empty() {
+        return format!("No formatter, linter, or CI configuration found in {repo_name}");
+    }
+    let mut out = format!("Conventions for {repo_name}\n");
+    for (title, configs) in [("Formatting", &report.formatting), ("Linting", &report.linting)] {
+        if configs.is_empty() {
+            continue;
+        }
+        out.push_str(&format!("\n{title}:\n"));
+        for cfg in configs {
+            out.push_str(&format!("  {} ({})\n", cfg.tool, cfg.source));
+            if cfg.settings.is_empty() {
+                out.push_str("    (JavaScript config, not evaluated)\n");
+            }
+            for (key, value) in &cfg.settings {
+                out.push_str(&format!("    {key} = {value}\n"));
+            }
+        }
+    }
+    if !report.ci.is_empty() {
+        out.push_str("\nCI:\n");
+        for wf in &report.ci {
+            let name = wf.name.as_deref().map(|n| format!(" \"{n}\"")).unwrap_or_default();
+            let on = if wf.triggers.is_empty() {
+                String::new()
+            } else {
+                format!(" on {}", wf.triggers.join(", "))
+            };
+            out.push_str(&format!("  {}{name}{on}\n", wf.source));
+            for cmd in &wf.commands {
+                out.push_str(&format!("    $ {cmd}\n"));
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn editorconfig_sections_prefix_keys() {
+        let settings = editorconfig_settings(
+            "root = true\n\n[*]\nindent_style = space\n; comment\n[*.rs]\nindent_size = 4\n",
+        );
+        assert_eq!(settings["root"], "true");
+        assert_eq!(settings["[*] indent_style"], "space");
+        assert_eq!(settings["[*.rs] indent_size"], "4");
+    }
+
+    #[test]
+    fn ci_workflow_extracts_triggers_and_tool_commands() {
+        let yaml = "name: CI
+on:
+  push:
+    branches: [main]
+  pull_request:
+jobs:
+  test:
+    steps:
+      - uses: actions/checkout@v4
+      - run: cargo fmt --check
+      - name: Test
+        run: |
+          echo starting
+          cargo test --workspace
+      - run: cargo fmt --check
+";
+        let wf = parse_ci_workflow(".github/workflows/ci.yml", yaml);
+        assert_eq!(wf.name.as_deref(), Some("CI"));
+        assert_eq!(wf.triggers, vec!["push", "pull_request"]);
+        assert_eq!(wf.commands, vec!["cargo fmt --check", "cargo test --workspace"]);
+
+        let inline = parse_ci_workflow("x.yml", "on: [push, workflow_dispatch]\n");
+        assert_eq!(inline.triggers, vec!["push", "workflow_dispatch"]);
+    }
+}
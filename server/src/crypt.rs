@@ -0,0 +1,195 @@
+//! Encryption at rest for the caches under [`crate::cache_dir`]: semantic caches and index
+//! handoffs, the files there that are derived from source code.
+//!
+//! Encryption is on when `CODESCOPE_CACHE_KEY` holds a key (64 hex digits), or when
+//! `~/.codescope/repos.toml` asks for it, in which case a key missing from the environment
+//! is looked up in the OS keychain (service `codescope`, account `cache-key`; `security`
+//! on macOS, `secret-tool` on Linux):
+//!
+//! ```toml
+//! [cache]
+//! encrypt = true
+//! ```
+//!
+//! With encryption required and no key found, caches are not written at all rather than
+//! written in plaintext. Each file is sealed whole with AES-256-GCM: [`MAGIC`], a random
+//! 96-bit nonce, then the ciphertext and its tag. [`read`] and [`open`] pass plaintext
+//! files through, so caches written before encryption was turned on still load; they are
+//! replaced by sealed ones on their next write.
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use std::io::{self, BufReader, Cursor, Read, Seek};
+use std::path::Path;
+use std::process::Command;
+use std::sync::OnceLock;
+
+/// Environment variable holding the cache key.
+pub const KEY_ENV: &str = "CODESCOPE_CACHE_KEY";
+
+/// First bytes of a sealed file.
+pub const MAGIC: &[u8; 4] = b"CSE1";
+
+const NONCE_LEN: usize = 12;
+
+/// A 256-bit cache key.
+#[derive(Clone)]
+pub struct CacheKey(Key<Aes256Gcm>);
+
+impl CacheKey {
+    /// Parse 64 hex digits, as printed by `openssl rand -hex 32`.
+    pub fn parse(hex: &str) -> Result<Self, String> {
+        let hex = hex.trim();
+        if hex.len() != 64 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err("cache key must be 64 hex digits (openssl rand -hex 32)".to_string());
+        }
+        let bytes: Vec<u8> =
+            (0..32).map(|i| u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap()).collect();
+        Ok(Self(*Key::<Aes256Gcm>::from_slice(&bytes)))
+    }
+}
+
+impl std::fmt::Debug for CacheKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("CacheKey(..)")
+    }
+}
+
+/// Cache encryption for this process, resolved once.
+enum Mode {
+    Off,
+    On(CacheKey),
+    /// Required, but no usable key: why.
+    Unavailable(String),
+}
+
+fn mode() -> &'static Mode {
+    static MODE: OnceLock<Mode> = OnceLock::new();
+    MODE.get_or_init(|| {
+        // An unreadable repos.toml may be the one asking for encryption: fail closed
+        let required = crate::cache::cache_table()
+            .map(|t| t.and_then(|t| t.get("encrypt")?.as_bool()).unwrap_or(false));
+        let key = match (std::env::var(KEY_ENV), required) {
+            (Ok(hex), _) => CacheKey::parse(&hex).map_err(|e| format!("{KEY_ENV}: {e}")),
+            (Err(_), Ok(true)) => keychain_key(),
+            (Err(_), Ok(false)) => return Mode::Off,
+            (Err(_), Err(e)) => Err(e),
+        };
+        match key {
+            Ok(key) => Mode::On(key),
+            Err(e) => {
+                tracing::warn!(error = %e, "Cache encryption is on but has no key; caches are not written");
+                Mode::Unavailable(e)
+            }
+        }
+    })
+}
+
+/// The key to seal caches with: `None` when encryption is off, an error when it is
+/// required but no key is available.
+pub fn cache_key() -> io::Result<Option<&'static CacheKey>> {
+    match mode() {
+        Mode::Off => Ok(None),
+        Mode::On(key) => Ok(Some(key)),
+        Mode::Unavailable(e) => Err(io::Error::other(format!("cache encryption: {e}"))),
+    }
+}
+
+/// The key stored in the OS keychain.
+fn keychain_key() -> Result<CacheKey, String> {
+    let mut cmd = if cfg!(target_os = "macos") {
+        let mut cmd = Command::new("security");
+        cmd.args(["find-generic-password", "-s", "codescope", "-a", "cache-key", "-w"]);
+        cmd
+    } else if cfg!(unix) {
+        let mut cmd = Command::new("secret-tool");
+        cmd.args(["lookup", "service", "codescope", "account", "cache-key"]);
+        cmd
+    } else {
+        return Err(format!("no OS keychain support on this platform; set {KEY_ENV}"));
+    };
+    let output = cmd
+        .stdin(std::process::Stdio::null())
+        .output()
+        .map_err(|e| format!("Failed to query the OS keychain: {e}"))?;
+    let hex = String::from_utf8_lossy(&output.stdout);
+    if !output.status.success() || hex.trim().is_empty() {
+        return Err(format!("no cache key in the OS keychain or {KEY_ENV}"));
+    }
+    CacheKey::parse(&hex).map_err(|e| format!("OS keychain: {e}"))
+}
+
+/// `plaintext` sealed with `key`, in the sealed file format.
+pub fn seal(key: &CacheKey, plaintext: &[u8]) -> Vec<u8> {
+    let cipher = Aes256Gcm::new(&key.0);
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher.encrypt(&nonce, plaintext).expect("AES-GCM encryption of a buffer");
+    let mut out = Vec::with_capacity(MAGIC.len() + NONCE_LEN + ciphertext.len());
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&ciphertext);
+    out
+}
+
+/// The plaintext of `bytes`: opened with `key` when sealed, as they are otherwise.
+pub fn unseal(key: Option<&CacheKey>, bytes: Vec<u8>) -> io::Result<Vec<u8>> {
+    let Some(sealed) = bytes.strip_prefix(MAGIC.as_slice()) else { return Ok(bytes) };
+    let Some(key) = key else {
+        return Err(io::Error::other(format!("encrypted cache, but no key in {KEY_ENV}")));
+    };
+    if sealed.len() < NONCE_LEN {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "truncated encrypted cache"));
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    Aes256Gcm::new(&key.0).decrypt(Nonce::from_slice(nonce), ciphertext).map_err(|_| {
+        io::Error::new(io::ErrorKind::InvalidData, "encrypted cache does not open with this key")
+    })
+}
+
+/// The plaintext of a cache file.
+pub fn read(path: &Path) -> io::Result<Vec<u8>> {
+    let bytes = std::fs::read(path)?;
+    if !bytes.starts_with(MAGIC) {
+        return Ok(bytes);
+    }
+    unseal(cache_key().ok().flatten(), bytes)
+}
+
+/// A reader of a cache file's plaintext. Plaintext files are streamed; sealed ones are
+/// read and opened whole.
+pub fn open(path: &Path) -> io::Result<Box<dyn Read>> {
+    let mut file = std::fs::File::open(path)?;
+    let mut magic = [0u8; 4];
+    let sealed = file.read_exact(&mut magic).is_ok() && &magic == MAGIC;
+    file.rewind()?;
+    if !sealed {
+        return Ok(Box::new(BufReader::new(file)));
+    }
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    Ok(Box::new(Cursor::new(unseal(cache_key().ok().flatten(), bytes)?)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sealed_caches_open_only_with_their_key() {
+        let key = CacheKey::parse(&"0f".repeat(32)).unwrap();
+        let other = CacheKey::parse(&"a1".repeat(32)).unwrap();
+        let sealed = seal(&key, b"fn parse(s: &str)");
+        assert!(sealed.starts_with(MAGIC));
+        assert!(!sealed.windows(5).any(|w| w == b"parse"));
+        assert_eq!(unseal(Some(&key), sealed.clone()).unwrap(), b"fn parse(s: &str)");
+        assert!(unseal(Some(&other), sealed.clone()).is_err());
+        assert!(unseal(None, sealed.clone()).is_err());
+
+        let mut tampered = sealed;
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(unseal(Some(&key), tampered).is_err());
+        // Caches written before encryption was turned on still load
+        assert_eq!(unseal(Some(&key), b"{}".to_vec()).unwrap(), b"{}");
+        assert!(CacheKey::parse("abc").is_err());
+    }
+}
@@ -0,0 +1,140 @@
+//! Duplicate files: clusters of indexed files with the same content, found from the hashes
+//! recorded at scan time ([`crate::scan::digest_file`]) without reading any file again.
+//!
+//! Files are grouped by their whitespace-insensitive hash. A group whose members also share
+//! the exact content hash is `identical`; otherwise its members differ only in indentation,
+//! line endings, or blank lines and the cluster is `near-identical`, the usual sign of a
+//! vendored or copy-pasted file that was reformatted on the way in.
+
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::types::RepoState;
+
+/// Files shorter than this are left out unless the caller asks otherwise; tiny files
+/// (empty `__init__.py`, one-line re-exports) repeat without being copies.
+pub const DEFAULT_MIN_LINES: u32 = 5;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum DuplicateKind {
+    Identical,
+    /// Same content apart from whitespace.
+    NearIdentical,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DuplicateFile {
+    pub path: String,
+    /// `"generated"` or `"vendored"`, as detected at scan time.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub origin: Option<&'static str>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Cluster {
+    pub kind: DuplicateKind,
+    /// Lines of the longest copy.
+    pub lines: u32,
+    /// Lines that would go away if one copy were kept.
+    pub redundant_lines: u64,
+    pub files: Vec<DuplicateFile>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateReport {
+    /// Directory the search was limited to; empty for the whole repo.
+    pub path: String,
+    pub min_lines: u32,
+    /// Files that have a copy.
+    pub files: usize,
+    pub redundant_lines: u64,
+    /// Most redundant lines first.
+    pub clusters: Vec<Cluster>,
+}
+
+/// Duplicate clusters among the files under `path` with at least `min_lines` lines.
+pub fn find(repo: &RepoState, path: &str, min_lines: u32) -> DuplicateReport {
+    let path = path.trim_matches('/');
+    let mut groups: HashMap<u64, Vec<&crate::types::ScannedFile>> = HashMap::new();
+    for f in &repo.all_files {
+        if f.normalized_hash != 0
+            && f.lines >= min_lines.max(1)
+            && crate::entries::path_in(&f.rel_path, path)
+        {
+            groups.entry(f.normalized_hash).or_default().push(f);
+        }
+    }
+    let mut clusters: Vec<Cluster> = groups
+        .into_values()
+        .filter(|files| files.len() > 1)
+        .map(|mut files| {
+            files.sort_by(|a, b| a.rel_path.cmp(&b.rel_path));
+            let identical = files.iter().all(|f| f.content_hash == files[0].content_hash);
+            let lines = files.iter().map(|f| f.lines).max().unwrap_or(0);
+            Cluster {
+                kind: if identical {
+                    DuplicateKind::Identical
+                } else {
+                    DuplicateKind::NearIdentical
+                },
+                lines,
+                redundant_lines: u64::from(lines) * (files.len() as u64 - 1),
+                files: files
+                    .iter()
+                    .map(|f| DuplicateFile { path: f.rel_path.clone(), origin: f.origin })
+                    .collect(),
+            }
+        })
+        .collect();
+    clusters.sort_by(|a, b| {
+        b.redundant_lines
+            .cmp(&a.redundant_lines)
+            .then_with(|| a.files[0].path.cmp(&b.files[0].path))
+    });
+    DuplicateReport {
+        path: path.to_string(),
+        min_lines,
+        files: clusters.iter().map(|c| c.files.len()).sum(),
+        redundant_lines: clusters.iter().map(|c| c.redundant_lines).sum(),
+        clusters,
+    }
+}
+
+/// `cs_duplicates` text output, listing up to `limit` clusters.
+pub fn format_report(repo: &str, report: &DuplicateReport, limit: usize) -> String {
+    let target = if report.path.is_empty() { repo } else { report.path.as_str() };
+    if report.clusters.is_empty() {
+        return format!(
+            "No duplicate files in {target} (files of {} or more lines compared)",
+            report.min_lines
+        );
+    }
+    let mut out = format!(
+        "{} duplicate clusters in {target} ({} files, {} redundant lines):\n",
+        report.clusters.len(),
+        report.files,
+        report.redundant_lines
+    );
+    for cluster in report.clusters.iter().take(limit) {
+        let (kind, each) = match cluster.kind {
+            DuplicateKind::Identical => ("identical", "each"),
+            DuplicateKind::NearIdentical => ("near-identical, whitespace differs", "at most"),
+        };
+        out.push_str(&format!(
+            "\n{kind} — {} files, {} lines {each}\n",
+            cluster.files.len(),
+            cluster.lines
+        ));
+        for f in &cluster.files {
+            let origin = f.origin.map(|o| format!(" [{o}]")).unwrap_or_default();
+            out.push_str(&format!("  {}{origin}\n", f.path));
+        }
+    }
+    if report.clusters.len() > limit {
+        out.push_str(&format!("\n... and {} more clusters\n", report.clusters.len() - limit));
+    }
+    out
+}
@@ -0,0 +1,143 @@
+//! Text decoding for source files that are not UTF-8.
+//!
+//! [`read_to_string`] replaces `fs::read_to_string` on every search and read path. A
+//! byte-order mark wins; otherwise valid UTF-8 is taken as is, and anything else is
+//! decoded from the encoding chardetng guesses (Shift_JIS, GBK, windows-1252 for
+//! Latin-1, ...). Files with NUL bytes and no UTF-16 BOM are still rejected as binary.
+//! The scan records the detected encoding on [`crate::types::ScannedFile`].
+//!
+//! Grep goes through [`for_each_line`], which streams files over [`STREAM_LINES_BYTES`]
+//! line by line instead of decoding them whole.
+
+use chardetng::EncodingDetector;
+use encoding_rs::Encoding;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Bytes sampled at scan time to detect a file's encoding.
+const SNIFF_BYTES: u64 = 8192;
+
+/// Files larger than this are read line by line by [`for_each_line`].
+pub const STREAM_LINES_BYTES: u64 = 8 * 1024 * 1024;
+
+/// Longest line kept when streaming; the rest of a longer line is dropped.
+const MAX_STREAMED_LINE: u64 = 64 * 1024;
+
+/// Decode file bytes to UTF-8. Returns the text and the source encoding, `None` for UTF-8.
+pub fn decode(bytes: Vec<u8>) -> io::Result<(String, Option<&'static str>)> {
+    if let Some((encoding, bom_len)) = Encoding::for_bom(&bytes) {
+        let (text, _) = encoding.decode_without_bom_handling(&bytes[bom_len..]);
+        let name = (encoding != encoding_rs::UTF_8).then(|| encoding.name());
+        return Ok((text.into_owned(), name));
+    }
+    let bytes = match String::from_utf8(bytes) {
+        Ok(text) => return Ok((text, None)),
+        Err(e) => e.into_bytes(),
+    };
+    if bytes.contains(&0) {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "binary file"));
+    }
+    let encoding = guess(&bytes, true);
+    let (text, _) = encoding.decode_without_bom_handling(&bytes);
+    Ok((text.into_owned(), Some(encoding.name())))
+}
+
+fn guess(bytes: &[u8], last: bool) -> &'static Encoding {
+    let mut detector = EncodingDetector::new();
+    detector.feed(bytes, last);
+    detector.guess(None, true)
+}
+
+/// Read a file as UTF-8 text, transcoding from its detected encoding.
+pub fn read_to_string(path: impl AsRef<Path>) -> io::Result<String> {
+    read_with_encoding(path).map(|(text, _)| text)
+}
+
+/// Like [`read_to_string`], also returning the source encoding (`None` for UTF-8).
+pub fn read_with_encoding(path: impl AsRef<Path>) -> io::Result<(String, Option<&'static str>)> {
+    decode(std::fs::read(path)?)
+}
+
+/// Call `f` with the index and text of each line of a file, without line endings. Files up
+/// to [`STREAM_LINES_BYTES`] are decoded whole by [`read_to_string`]. Larger ones go through
+/// a buffered reader, so memory stays bounded: their lines are taken as UTF-8 with invalid
+/// bytes replaced, and lines over 64KB are cut off. Returns whether the file was streamed.
+pub fn for_each_line(path: &Path, mut f: impl FnMut(usize, &str)) -> io::Result<bool> {
+    use std::io::BufRead;
+
+    let size = std::fs::metadata(path)?.len();
+    if size <= STREAM_LINES_BYTES {
+        read_to_string(path)?.lines().enumerate().for_each(|(i, line)| f(i, line));
+        return Ok(false);
+    }
+    let mut reader = io::BufReader::new(std::fs::File::open(path)?);
+    let mut buf = Vec::new();
+    for i in 0.. {
+        buf.clear();
+        let n = (&mut reader).take(MAX_STREAMED_LINE).read_until(b'\n', &mut buf)?;
+        if n == 0 {
+            break;
+        }
+        if buf.last() == Some(&b'\n') {
+            buf.pop();
+        } else if n as u64 == MAX_STREAMED_LINE {
+            reader.skip_until(b'\n')?;
+        }
+        if buf.last() == Some(&b'\r') {
+            buf.pop();
+        }
+        f(i, &String::from_utf8_lossy(&buf));
+    }
+    Ok(true)
+}
+
+/// Detect a file's encoding from its first bytes, `None` for UTF-8 (or unreadable).
+pub fn detect_file(path: &Path) -> Option<&'static str> {
+    let mut head = Vec::new();
+    std::fs::File::open(path).ok()?.take(SNIFF_BYTES).read_to_end(&mut head).ok()?;
+    if let Some((encoding, _)) = Encoding::for_bom(&head) {
+        return (encoding != encoding_rs::UTF_8).then(|| encoding.name());
+    }
+    match std::str::from_utf8(&head) {
+        Ok(_) => None,
+        // A multi-byte character cut off by the sample boundary is still UTF-8
+        Err(e) if e.error_len().is_none() => None,
+        Err(_) => {
+            let last = (head.len() as u64) < SNIFF_BYTES;
+            Some(guess(&head, last).name())
+        }
+    }
+}
+
+/// Whether `head` starts with a UTF-16 byte-order mark (text despite its NUL bytes).
+pub fn has_utf16_bom(head: &[u8]) -> bool {
+    head.starts_with(&[0xFF, 0xFE]) || head.starts_with(&[0xFE, 0xFF])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transcodes_legacy_encodings_and_keeps_utf8() {
+        let (text, enc) = decode("fn main() {}\n".as_bytes().to_vec()).unwrap();
+        assert_eq!((text.as_str(), enc), ("fn main() {}\n", None));
+
+        let source =
+            "// 設定ファイルを読み込んで、サーバーの起動オプションを返します。\nfn load() {}\n";
+        let (sjis, _, _) = encoding_rs::SHIFT_JIS.encode(source);
+        let (text, enc) = decode(sjis.into_owned()).unwrap();
+        assert_eq!(enc, Some("Shift_JIS"));
+        assert_eq!(text, source);
+
+        let latin1 = b"# Caf\xe9 cr\xe8me br\xfbl\xe9e\nprint('ok')\n".to_vec();
+        let (text, enc) = decode(latin1).unwrap();
+        assert_eq!(enc, Some("windows-1252"));
+        assert!(text.contains("Café crème brûlée"), "{text}");
+
+        let utf16 = vec![0xFF, 0xFE, b'x', 0];
+        assert_eq!(decode(utf16).unwrap(), ("x".to_string(), Some("UTF-16LE")));
+
+        assert!(decode(vec![0x7F, 0x45, 0x4C, 0x46, 0, 0, 0xC3]).is_err(), "binary rejected");
+    }
+}
@@ -0,0 +1,464 @@
+//! Program entry point detection: `main` functions, binary targets declared in manifests,
+//! HTTP route registrations, and CLI subcommand definitions.
+//!
+//! Detection is line-based and runs once per file during scan (and again for files the
+//! watcher re-indexes). `cs_modules action=entries` ranks the results by how much of the
+//! import graph each entry reaches, and adds import-graph roots that no pattern matched.
+
+use rayon::prelude::*;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+
+use crate::types::{ImportGraph, ScannedFile};
+
+/// Files larger than this are skipped (likely generated or bundled).
+const MAX_ENTRY_SCAN_BYTES: u64 = 256 * 1024;
+
+/// Lines searched after a CLI marker for the name it applies to.
+const CLI_LOOKAHEAD_LINES: usize = 6;
+
+/// Maximum length of an entry's detail text.
+const MAX_DETAIL_LEN: usize = 100;
+
+/// What kind of entry point was found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    /// A `main` function or script guard (`fn main`, `if __name__ == "__main__"`, ...).
+    Main,
+    /// A binary target declared in a manifest (`[[bin]]`, package.json `bin`, project scripts).
+    Bin,
+    /// An HTTP route registration.
+    Route,
+    /// A CLI subcommand definition.
+    Cli,
+}
+
+impl EntryKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Main => "main",
+            Self::Bin => "bin",
+            Self::Route => "route",
+            Self::Cli => "cli",
+        }
+    }
+}
+
+/// One detected entry point. `path` and `line` locate the declaration itself, so binary
+/// targets point at their manifest and name the target file in `detail`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EntryPoint {
+    pub kind: EntryKind,
+    pub path: String,
+    /// 1-based line number.
+    pub line: usize,
+    pub detail: String,
+}
+
+/// Compiled patterns for entry point detection. Build once per scan and reuse.
+pub struct EntryDetector {
+    rust_main: Regex,
+    go_main: Regex,
+    c_main: Regex,
+    jvm_main: Regex,
+    cs_main: Regex,
+    kotlin_main: Regex,
+    chained_route: Regex,
+    method_route: Regex,
+    attr_route: Regex,
+    spring_route: Regex,
+    aspnet_route: Regex,
+    argparse_cmd: Regex,
+    decorator_cmd: Regex,
+    js_cmd: Regex,
+    cobra_use: Regex,
+    toml_kv: Regex,
+}
+
+impl Default for EntryDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EntryDetector {
+    pub fn new() -> Self {
+        Self {
+            rust_main: Regex::new(r"^\s*(?:pub\s+)?(?:async\s+)?fn\s+main\s*\(").unwrap(),
+            go_main: Regex::new(r"^func\s+main\s*\(\s*\)").unwrap(),
+            c_main: Regex::new(r"^\s*(?:int|void)\s+w?main\s*\(").unwrap(),
+            jvm_main: Regex::new(r"\bstatic\s+void\s+main\s*\(").unwrap(),
+            cs_main: Regex::new(r"\bstatic\s+(?:async\s+)?(?:void|int|Task(?:<int>)?)\s+Main\s*\(")
+                .unwrap(),
+            kotlin_main: Regex::new(r"^fun\s+main\s*\(").unwrap(),
+            // axum/warp style: `.route("/path", get(handler))`, often on a chained line.
+            chained_route: Regex::new(r#"\.route\(\s*"(/[^"]*)""#).unwrap(),
+            // Express, Fastify, Flask, FastAPI, Gin, Echo, net/http: `app.get("/path", ...)`.
+            method_route: Regex::new(
+                r#"(?:^|[^\w.])@?(?:app|application|router|routes|server|srv|mux|http|r|e|g|bp|blueprint|group|engine|fastify|web|v1|v2)\.(get|post|put|delete|patch|head|options|all|route|api_route|websocket|GET|POST|PUT|DELETE|PATCH|HEAD|OPTIONS|Any|Handle|HandleFunc)\(\s*['"`](/[^'"`]*)['"`]"#,
+            )
+            .unwrap(),
+            // actix-web / Rocket attribute macros: `#[get("/path")]`.
+            attr_route: Regex::new(
+                r#"#\[(get|post|put|delete|patch|head|options)\(\s*"(/[^"]*)""#,
+            )
+            .unwrap(),
+            spring_route: Regex::new(
+                r#"@(Get|Post|Put|Delete|Patch|Request)Mapping\(\s*(?:(?:value|path)\s*=\s*)?\{?\s*"([^"]*)""#,
+            )
+            .unwrap(),
+            aspnet_route: Regex::new(r#"\[(?:Http(Get|Post|Put|Delete|Patch)|Route)\(\s*"([^"]*)""#)
+                .unwrap(),
+            argparse_cmd: Regex::new(r#"\.add_parser\(\s*['"]([\w:-]+)['"]"#).unwrap(),
+            decorator_cmd: Regex::new(r"^\s*@(?:\w+\.)*(command|group)\(").unwrap(),
+            js_cmd: Regex::new(r#"\.command\(\s*['"`]([\w:-]+)"#).unwrap(),
+            cobra_use: Regex::new(r#"Use:\s*"([^"\s]+)"#).unwrap(),
+            toml_kv: Regex::new(r#"^\s*([\w.-]+|"[^"]+")\s*=\s*"([^"]*)""#).unwrap(),
+        }
+    }
+
+    /// Detect entry points in one file's content.
+    pub fn detect(&self, rel_path: &str, ext: &str, content: &str) -> Vec<EntryPoint> {
+        let filename = rel_path.rsplit('/').next().unwrap_or(rel_path);
+        let mut out = Vec::new();
+        match filename {
+            "Cargo.toml" => self.cargo_bins(rel_path, content, &mut out),
+            "package.json" => package_json_bins(rel_path, content, &mut out),
+            "pyproject.toml" => self.pyproject_scripts(rel_path, content, &mut out),
+            _ => {}
+        }
+        if !out.is_empty() {
+            return out;
+        }
+
+        let lines: Vec<&str> = content.lines().collect();
+        let go_package_main = ext == "go" && content.contains("package main");
+        for (i, line) in lines.iter().enumerate() {
+            let mut push = |kind: EntryKind, detail: String| {
+                out.push(EntryPoint {
+                    kind,
+                    path: rel_path.to_string(),
+                    line: i + 1,
+                    detail: crate::format::truncate_line(&detail, MAX_DETAIL_LEN),
+                });
+            };
+
+            // Main functions and script guards
+            let is_main = match ext {
+                "rs" => self.rust_main.is_match(line),
+                "go" => go_package_main && self.go_main.is_match(line),
+                "c" | "cc" | "cpp" | "cxx" => self.c_main.is_match(line),
+                "java" => self.jvm_main.is_match(line),
+                "cs" => self.cs_main.is_match(line),
+                "kt" => self.kotlin_main.is_match(line),
+                "py" => {
+                    line.starts_with("if __name__")
+                        && line.contains("__main__")
+                        && line.contains("==")
+                }
+                "js" | "mjs" | "cjs" | "ts" => {
+                    (i == 0 && line.starts_with("#!") && line.contains("node"))
+                        || line.contains("require.main === module")
+                }
+                _ => false,
+            };
+            if is_main {
+                push(EntryKind::Main, line.trim().to_string());
+                continue;
+            }
+
+            // HTTP routes
+            let route = match ext {
+                "rs" => self
+                    .attr_route
+                    .captures(line)
+                    .map(|c| format!("{} {}", c[1].to_uppercase(), &c[2]))
+                    .or_else(|| self.chained_route.captures(line).map(|c| c[1].to_string())),
+                "java" | "kt" => self
+                    .spring_route
+                    .captures(line)
+                    .map(|c| route_detail(&c[1].replace("Request", ""), &c[2])),
+                "cs" => self
+                    .aspnet_route
+                    .captures(line)
+                    .map(|c| route_detail(c.get(1).map(|m| m.as_str()).unwrap_or(""), &c[2])),
+                "py" | "js" | "mjs" | "cjs" | "ts" | "go" | "rb" | "php" => {
+                    self.method_route.captures(line).map(|c| route_detail(&c[1], &c[2]))
+                }
+                _ => None,
+            };
+            if let Some(detail) = route {
+                push(EntryKind::Route, detail);
+                continue;
+            }
+
+            // CLI subcommands
+            let cli = match ext {
+                "rs" if line.trim_start().starts_with("#[derive(")
+                    && line.contains("Subcommand") =>
+                {
+                    Some(
+                        following_item(&lines, i, &["enum "])
+                            .map(|name| format!("clap subcommands {name}"))
+                            .unwrap_or_else(|| "clap subcommands".to_string()),
+                    )
+                }
+                "py" => {
+                    self.argparse_cmd.captures(line).map(|c| format!("argparse {}", &c[1])).or_else(
+                        || {
+                            let c = self.decorator_cmd.captures(line)?;
+                            let name = following_item(&lines, i, &["def ", "async def "])?;
+                            Some(format!("{} {name}", &c[1]))
+                        },
+                    )
+                }
+                "go" if line.contains("cobra.Command{") => {
+                    let name = lines[i..lines.len().min(i + CLI_LOOKAHEAD_LINES)]
+                        .iter()
+                        .find_map(|l| self.cobra_use.captures(l).map(|c| c[1].to_string()));
+                    Some(format!("cobra {}", name.unwrap_or_default()).trim_end().to_string())
+                }
+                "js" | "mjs" | "cjs" | "ts" => {
+                    self.js_cmd.captures(line).map(|c| format!("command {}", &c[1]))
+                }
+                _ => None,
+            };
+            if let Some(detail) = cli {
+                push(EntryKind::Cli, detail);
+            }
+        }
+        out
+    }
+
+    /// `[[bin]]` tables in a Cargo manifest.
+    fn cargo_bins(&self, rel_path: &str, content: &str, out: &mut Vec<EntryPoint>) {
+        let dir = parent_dir(rel_path);
+        let mut current: Option<(usize, Option<String>, Option<String>)> = None;
+        let mut flush = |bin: Option<(usize, Option<String>, Option<String>)>| {
+            if let Some((line, name, path)) = bin {
+                let name = name.unwrap_or_else(|| "?".to_string());
+                let target = path.unwrap_or_else(|| format!("src/bin/{name}.rs"));
+                out.push(EntryPoint {
+                    kind: EntryKind::Bin,
+                    path: rel_path.to_string(),
+                    line,
+                    detail: format!("cargo bin {name} -> {}", join_rel(dir, &target)),
+                });
+            }
+        };
+        for (i, line) in content.lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.starts_with('[') {
+                flush(current.take());
+                if trimmed == "[[bin]]" {
+                    current = Some((i + 1, None, None));
+                }
+                continue;
+            }
+            if let Some((_, name, path)) = current.as_mut() {
+                if let Some(c) = self.toml_kv.captures(line) {
+                    match &c[1] {
+                        "name" => *name = Some(c[2].to_string()),
+                        "path" => *path = Some(c[2].to_string()),
+                        _ => {}
+                    }
+                }
+            }
+        }
+        flush(current);
+    }
+
+    /// `[project.scripts]` and `[tool.poetry.scripts]` in a pyproject.toml.
+    fn pyproject_scripts(&self, rel_path: &str, content: &str, out: &mut Vec<EntryPoint>) {
+        let mut in_scripts = false;
+        for (i, line) in content.lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.starts_with('[') {
+                in_scripts = trimmed == "[project.scripts]" || trimmed == "[tool.poetry.scripts]";
+                continue;
+            }
+            if !in_scripts {
+                continue;
+            }
+            if let Some(c) = self.toml_kv.captures(line) {
+                out.push(EntryPoint {
+                    kind: EntryKind::Bin,
+                    path: rel_path.to_string(),
+                    line: i + 1,
+                    detail: format!("script {} -> {}", c[1].trim_matches('"'), &c[2]),
+                });
+            }
+        }
+    }
+}
+
+/// The `bin` field of a package.json, as a string or a name → path map.
+fn package_json_bins(rel_path: &str, content: &str, out: &mut Vec<EntryPoint>) {
+    let Ok(json) = serde_json::from_str::<serde_json::Value>(content) else {
+        return;
+    };
+    let dir = parent_dir(rel_path);
+    let line = content.lines().position(|l| l.contains("\"bin\"")).map(|i| i + 1).unwrap_or(1);
+    let mut push = |name: &str, target: &str| {
+        out.push(EntryPoint {
+            kind: EntryKind::Bin,
+            path: rel_path.to_string(),
+            line,
+            detail: format!("npm bin {name} -> {}", join_rel(dir, target)),
+        });
+    };
+    match &json["bin"] {
+        serde_json::Value::String(target) => push(json["name"].as_str().unwrap_or("?"), target),
+        serde_json::Value::Object(map) => {
+            for (name, target) in map {
+                if let Some(target) = target.as_str() {
+                    push(name, target);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// `GET /path`, or just the path when the registration does not name a method.
+fn route_detail(method: &str, path: &str) -> String {
+    match method.to_ascii_lowercase().as_str() {
+        "" | "route" | "api_route" | "handle" | "handlefunc" | "all" | "any" => path.to_string(),
+        m => format!("{} {path}", m.to_uppercase()),
+    }
+}
+
+/// Name of the first item starting with one of `prefixes` within the lookahead window.
+fn following_item(lines: &[&str], start: usize, prefixes: &[&str]) -> Option<String> {
+    lines[start + 1..lines.len().min(start + 1 + CLI_LOOKAHEAD_LINES)].iter().find_map(|l| {
+        let l = l.trim_start().trim_start_matches("pub ");
+        let rest = prefixes.iter().find_map(|p| l.strip_prefix(p))?;
+        let name: String = rest.chars().take_while(|c| c.is_alphanumeric() || *c == '_').collect();
+        (!name.is_empty()).then_some(name)
+    })
+}
+
+fn parent_dir(rel_path: &str) -> &str {
+    rel_path.rsplit_once('/').map(|(d, _)| d).unwrap_or("")
+}
+
+/// Join a manifest-relative path onto the manifest's directory.
+fn join_rel(dir: &str, target: &str) -> String {
+    let target = target.trim_start_matches("./");
+    if dir.is_empty() {
+        target.to_string()
+    } else {
+        format!("{dir}/{target}")
+    }
+}
+
+/// Read and scan one file. Large or unreadable files yield nothing.
+pub fn detect_file_entry_points(detector: &EntryDetector, file: &ScannedFile) -> Vec<EntryPoint> {
+    if file.abs_path.metadata().map(|m| m.len()).unwrap_or(0) > MAX_ENTRY_SCAN_BYTES {
+        return Vec::new();
+    }
+    match crate::encoding::read_to_string(&file.abs_path) {
+        Ok(content) => detector.detect(&file.rel_path, &file.ext, &content),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Detect entry points across all scanned files, sorted by path and line.
+pub fn detect_entry_points(all_files: &[ScannedFile]) -> Vec<EntryPoint> {
+    let detector = EntryDetector::new();
+    let mut entries: Vec<EntryPoint> =
+        all_files.par_iter().flat_map_iter(|f| detect_file_entry_points(&detector, f)).collect();
+    entries.sort_by(|a, b| a.path.cmp(&b.path).then(a.line.cmp(&b.line)));
+    entries
+}
+
+/// Number of files reachable from `path` by following imports (excluding itself).
+pub fn import_reach(graph: &ImportGraph, path: &str) -> usize {
+    let mut visited: HashSet<&str> = HashSet::from([path]);
+    let mut queue: VecDeque<&str> = VecDeque::from([path]);
+    while let Some(current) = queue.pop_front() {
+        for next in graph.imports.get(current).into_iter().flatten() {
+            if visited.insert(next.as_str()) {
+                queue.push_back(next.as_str());
+            }
+        }
+    }
+    visited.len() - 1
+}
+
+/// Files that import others but are imported by nothing, ranked by reach. These are
+/// likely entry points the patterns missed (scripts, test harnesses, plugin roots).
+pub fn import_roots(graph: &ImportGraph, exclude: &HashSet<&str>) -> Vec<(String, usize)> {
+    let mut roots: Vec<(String, usize)> = graph
+        .imports
+        .keys()
+        .filter(|p| !exclude.contains(p.as_str()))
+        .filter(|p| graph.imported_by.get(*p).is_none_or(|v| v.is_empty()))
+        .map(|p| (p.clone(), import_reach(graph, p)))
+        .filter(|(_, reach)| *reach >= 2)
+        .collect();
+    roots.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    roots
+}
+
+/// Whether `path` is `dir` or lies under it. An empty `dir` matches everything.
+pub fn path_in(path: &str, dir: &str) -> bool {
+    let dir = dir.trim_end_matches('/');
+    dir.is_empty()
+        || path == dir
+        || path.strip_prefix(dir).is_some_and(|rest| rest.starts_with('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(entries: &[EntryPoint]) -> Vec<(EntryKind, usize, &str)> {
+        entries.iter().map(|e| (e.kind, e.line, e.detail.as_str())).collect()
+    }
+
+    #[test]
+    fn detects_mains_routes_and_subcommands() {
+        let d = EntryDetector::new();
+        let rust = "#[derive(Parser)]\nstruct Cli;\n\n#[derive(Subcommand)]\nenum Commands {\n    Init,\n}\n\n#[tokio::main]\nasync fn main() {\n    let app = Router::new()\n        .route(\"/api/tree\", get(tree))\n        .route(\"/health\", get(ok));\n}\n";
+        assert_eq!(
+            kinds(&d.detect("src/main.rs", "rs", rust)),
+            vec![
+                (EntryKind::Cli, 4, "clap subcommands Commands"),
+                (EntryKind::Main, 10, "async fn main() {"),
+                (EntryKind::Route, 12, "/api/tree"),
+                (EntryKind::Route, 13, "/health"),
+            ]
+        );
+
+        let py = "@app.get(\"/items/{id}\")\ndef item(id):\n    return headers.get(\"/x\")\n\nif __name__ == \"__main__\":\n    main()\n";
+        assert_eq!(
+            kinds(&d.detect("app.py", "py", py)),
+            vec![
+                (EntryKind::Route, 1, "GET /items/{id}"),
+                (EntryKind::Main, 5, "if __name__ == \"__main__\":")
+            ]
+        );
+
+        let go = "package lib\n\nfunc main() {}\n";
+        assert!(d.detect("lib.go", "go", go).is_empty());
+    }
+
+    #[test]
+    fn detects_manifest_binaries() {
+        let d = EntryDetector::new();
+        let cargo = "[package]\nname = \"tool\"\n\n[[bin]]\nname = \"tool-cli\"\npath = \"src/cli.rs\"\n\n[dependencies]\n";
+        assert_eq!(
+            kinds(&d.detect("crates/tool/Cargo.toml", "toml", cargo)),
+            vec![(EntryKind::Bin, 4, "cargo bin tool-cli -> crates/tool/src/cli.rs")]
+        );
+
+        let pkg = "{\n  \"name\": \"web\",\n  \"bin\": { \"web-cli\": \"./bin/cli.js\" }\n}\n";
+        assert_eq!(
+            kinds(&d.detect("web/package.json", "json", pkg)),
+            vec![(EntryKind::Bin, 3, "npm bin web-cli -> web/bin/cli.js")]
+        );
+    }
+}
@@ -0,0 +1,308 @@
+//! Cargo feature map: `[features]` tables from every `Cargo.toml` in the repo, and the
+//! `#[cfg(feature = ...)]` gates on Rust files and items, for `cs_modules action=features`.
+//!
+//! A gated `mod name;` declaration gates the whole module file, as does an inner
+//! `#![cfg(...)]` attribute. Gates on other items are reported against the item line.
+//! Computed on demand; nothing is cached on the repo.
+
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+
+use crate::types::RepoState;
+
+/// Lines searched after an attribute for the item it applies to.
+const ITEM_LOOKAHEAD_LINES: usize = 5;
+
+/// Maximum length of an item's description.
+const MAX_ITEM_LEN: usize = 80;
+
+/// Files larger than this are skipped.
+const MAX_FEATURE_SCAN_BYTES: u64 = 512 * 1024;
+
+/// The `[features]` table of one Cargo manifest.
+#[derive(Serialize)]
+pub struct CrateFeatures {
+    pub manifest: String,
+    pub package: String,
+    /// Feature name → what it enables (other features, `dep:` crates, `crate/feature`).
+    pub features: BTreeMap<String, Vec<String>>,
+}
+
+/// A `cfg` attribute that mentions at least one feature.
+#[derive(Serialize)]
+pub struct FeatureGate {
+    pub path: String,
+    /// 1-based line of the attribute.
+    pub line: usize,
+    /// The `cfg(...)` predicate, e.g. `all(feature = "semantic", not(test))`.
+    pub predicate: String,
+    pub features: Vec<String>,
+    /// The gated item's first line, or `None` when the gate covers the whole file.
+    pub item: Option<String>,
+    /// For whole-file gates from a `mod` declaration: where the module is declared.
+    pub declared_at: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct FeatureMap {
+    pub crates: Vec<CrateFeatures>,
+    pub gates: Vec<FeatureGate>,
+}
+
+/// Parse all Cargo feature tables and Rust `cfg(feature)` gates in the repo.
+pub fn map_features(repo: &RepoState) -> FeatureMap {
+    let feature_re = Regex::new(r#"feature\s*=\s*"([^"]+)""#).unwrap();
+    let mod_re = Regex::new(r"^(?:pub(?:\([^)]*\))?\s+)?mod\s+([A-Za-z_]\w*)\s*;").unwrap();
+    let indexed: HashSet<&str> = repo.all_files.iter().map(|f| f.rel_path.as_str()).collect();
+
+    let mut crates = Vec::new();
+    let mut gates = Vec::new();
+    let mut module_gates = Vec::new();
+    for f in &repo.all_files {
+        let filename = f.rel_path.rsplit('/').next().unwrap_or(&f.rel_path);
+        if filename != "Cargo.toml" && f.ext != "rs" {
+            continue;
+        }
+        if f.abs_path.metadata().map(|m| m.len()).unwrap_or(0) > MAX_FEATURE_SCAN_BYTES {
+            continue;
+        }
+        let Ok(content) = crate::encoding::read_to_string(&f.abs_path) else {
+            continue;
+        };
+        if filename == "Cargo.toml" {
+            if let Some(c) = crate_features(&f.rel_path, &content) {
+                crates.push(c);
+            }
+            continue;
+        }
+
+        let lines: Vec<&str> = content.lines().collect();
+        for (i, line) in lines.iter().enumerate() {
+            let trimmed = line.trim_start();
+            let (inner, rest) = if let Some(rest) = trimmed.strip_prefix("#![cfg(") {
+                (true, rest)
+            } else if let Some(rest) = trimmed.strip_prefix("#[cfg(") {
+                (false, rest)
+            } else {
+                continue;
+            };
+            let predicate = balanced_prefix(rest).to_string();
+            let features: Vec<String> =
+                feature_re.captures_iter(&predicate).map(|c| c[1].to_string()).collect();
+            if features.is_empty() {
+                continue;
+            }
+            let mut gate = FeatureGate {
+                path: f.rel_path.clone(),
+                line: i + 1,
+                predicate,
+                features,
+                item: None,
+                declared_at: None,
+            };
+            if !inner {
+                let item = lines[i + 1..lines.len().min(i + 1 + ITEM_LOOKAHEAD_LINES)]
+                    .iter()
+                    .map(|l| l.trim())
+                    .find(|l| !l.is_empty() && !l.starts_with("#[") && !l.starts_with("//"));
+                if let Some(item) = item {
+                    if let Some(target) = mod_re
+                        .captures(item)
+                        .and_then(|c| module_file(&f.rel_path, &c[1], &indexed))
+                    {
+                        module_gates.push(FeatureGate {
+                            path: target,
+                            line: 1,
+                            predicate: gate.predicate.clone(),
+                            features: gate.features.clone(),
+                            item: None,
+                            declared_at: Some(format!("{}:{}", f.rel_path, i + 1)),
+                        });
+                    }
+                    gate.item = Some(crate::format::truncate_line(item, MAX_ITEM_LEN));
+                }
+            }
+            gates.push(gate);
+        }
+    }
+    gates.extend(module_gates);
+    crates.sort_by(|a, b| a.manifest.cmp(&b.manifest));
+    gates.sort_by(|a, b| a.path.cmp(&b.path).then(a.line.cmp(&b.line)));
+    FeatureMap { crates, gates }
+}
+
+/// Render the map for `cs_modules action=features`, optionally narrowed to one feature
+/// and to files under `prefix`. `limit` caps the items listed per feature.
+pub fn format_feature_map(
+    map: &FeatureMap,
+    feature: Option<&str>,
+    prefix: &str,
+    limit: usize,
+) -> String {
+    let wanted = |features: &[String]| feature.is_none_or(|f| features.iter().any(|x| x == f));
+    let mut out = String::new();
+
+    let crates: Vec<&CrateFeatures> = map
+        .crates
+        .iter()
+        .filter(|c| crate::entries::path_in(&c.manifest, prefix))
+        .filter(|c| feature.is_none_or(|f| c.features.contains_key(f)))
+        .collect();
+    out.push_str(&format!("Cargo features ({} crates)\n", crates.len()));
+    for c in &crates {
+        out.push_str(&format!("\n{} ({})\n", c.manifest, c.package));
+        for (name, enables) in &c.features {
+            if feature.is_some_and(|f| f != name) {
+                continue;
+            }
+            out.push_str(&format!("  {name} = [{}]\n", enables.join(", ")));
+            let enabled_by: Vec<&str> = c
+                .features
+                .iter()
+                .filter(|(_, list)| list.contains(name))
+                .map(|(n, _)| n.as_str())
+                .collect();
+            if feature.is_some() && !enabled_by.is_empty() {
+                out.push_str(&format!("    enabled by: {}\n", enabled_by.join(", ")));
+            }
+        }
+    }
+
+    let gates: Vec<&FeatureGate> = map
+        .gates
+        .iter()
+        .filter(|g| crate::entries::path_in(&g.path, prefix) && wanted(&g.features))
+        .collect();
+    let files: Vec<&&FeatureGate> = gates.iter().filter(|g| g.item.is_none()).collect();
+    if !files.is_empty() {
+        out.push_str(&format!("\nGated files ({})\n", files.len()));
+        for g in files {
+            out.push_str(&format!("  {}  cfg({})", g.path, g.predicate));
+            if let Some(at) = &g.declared_at {
+                out.push_str(&format!("  (mod declared at {at})"));
+            }
+            out.push('\n');
+        }
+    }
+
+    let mut by_feature: BTreeMap<&str, Vec<&FeatureGate>> = BTreeMap::new();
+    for g in gates.iter().filter(|g| g.item.is_some()) {
+        for f in &g.features {
+            if feature.is_none_or(|w| w == f) {
+                by_feature.entry(f.as_str()).or_default().push(g);
+            }
+        }
+    }
+    if !by_feature.is_empty() {
+        out.push_str("\nGated items by feature\n");
+    }
+    for (f, items) in &by_feature {
+        out.push_str(&format!("{f} ({}):\n", items.len()));
+        for g in items.iter().take(limit) {
+            out.push_str(&format!(
+                "  {}:{}  {}  cfg({})\n",
+                g.path,
+                g.line,
+                g.item.as_deref().unwrap_or(""),
+                g.predicate
+            ));
+        }
+        if items.len() > limit {
+            out.push_str(&format!("  ... and {} more\n", items.len() - limit));
+        }
+    }
+    if crates.is_empty() && gates.is_empty() {
+        out.push_str("\nNo feature definitions or cfg(feature) gates found.\n");
+    }
+    out
+}
+
+/// The `[features]` table of a manifest, or `None` when it has none.
+fn crate_features(rel_path: &str, content: &str) -> Option<CrateFeatures> {
+    let table: toml::Table = content.parse().ok()?;
+    let features = table.get("features")?.as_table()?;
+    let package = table
+        .get("package")
+        .and_then(|p| p.get("name"))
+        .and_then(|n| n.as_str())
+        .unwrap_or("")
+        .to_string();
+    let features = features
+        .iter()
+        .map(|(name, enables)| {
+            let list = enables
+                .as_array()
+                .map(|a| a.iter().filter_map(|v| v.as_str().map(str::to_string)).collect())
+                .unwrap_or_default();
+            (name.clone(), list)
+        })
+        .collect();
+    Some(CrateFeatures { manifest: rel_path.to_string(), package, features })
+}
+
+/// Text up to the `)` that closes an already-opened paren.
+fn balanced_prefix(s: &str) -> &str {
+    let mut depth = 1usize;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return &s[..i];
+                }
+            }
+            _ => {}
+        }
+    }
+    s
+}
+
+/// File implementing `mod name;` declared in `decl_path`, if it is indexed.
+fn module_file(decl_path: &str, name: &str, indexed: &HashSet<&str>) -> Option<String> {
+    let (dir, filename) = decl_path.rsplit_once('/').unwrap_or(("", decl_path));
+    let stem = filename.strip_suffix(".rs").unwrap_or(filename);
+    let base = match stem {
+        "lib" | "main" | "mod" => dir.to_string(),
+        _ if dir.is_empty() => stem.to_string(),
+        _ => format!("{dir}/{stem}"),
+    };
+    let prefix = if base.is_empty() { String::new() } else { format!("{base}/") };
+    [format!("{prefix}{name}.rs"), format!("{prefix}{name}/mod.rs")]
+        .into_iter()
+        .find(|p| indexed.contains(p.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn balanced_prefix_stops_at_closing_paren() {
+        assert_eq!(balanced_prefix(r#"feature = "semantic")]"#), r#"feature = "semantic""#);
+        assert_eq!(
+            balanced_prefix(r#"all(feature = "a", not(test)))] fn x() {}"#),
+            r#"all(feature = "a", not(test))"#
+        );
+    }
+
+    #[test]
+    fn module_files_resolve_relative_to_declaring_file() {
+        let indexed: HashSet<&str> =
+            ["src/semantic.rs", "src/net/http/mod.rs", "src/net/tls.rs"].into_iter().collect();
+        assert_eq!(
+            module_file("src/lib.rs", "semantic", &indexed).as_deref(),
+            Some("src/semantic.rs")
+        );
+        assert_eq!(
+            module_file("src/net.rs", "http", &indexed).as_deref(),
+            Some("src/net/http/mod.rs")
+        );
+        assert_eq!(
+            module_file("src/net/mod.rs", "tls", &indexed).as_deref(),
+            Some("src/net/tls.rs")
+        );
+        assert_eq!(module_file("src/lib.rs", "missing", &indexed), None);
+    }
+}
@@ -0,0 +1,190 @@
+//! Output profiles for MCP tool results.
+//!
+//! A profile (`compact`, `standard`, `verbose`) sets snippet length, default grep context,
+//! and how much detail result headers carry in `cs_search`, `cs_grep`, and `cs_read`.
+//! Clients pick a session default at `initialize` via
+//! `capabilities.experimental.codescope.outputProfile`, and override it per call with a
+//! `profile` argument.
+//!
+//! Independently of the profile, a result longer than `max_result_tokens` is cut at a line
+//! boundary by [`split_result`]; the rest is fetched with a continuation cursor.
+
+use crate::types::SessionState;
+
+/// Verbosity preset for tool output.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputProfile {
+    /// Paths and matched lines only: short snippets, no context, minimal headers.
+    Compact,
+    /// The historical output format.
+    #[default]
+    Standard,
+    /// Longer snippets, more context, and scores/sizes in headers.
+    Verbose,
+}
+
+impl OutputProfile {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "compact" => Some(Self::Compact),
+            "standard" => Some(Self::Standard),
+            "verbose" => Some(Self::Verbose),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Compact => "compact",
+            Self::Standard => "standard",
+            Self::Verbose => "verbose",
+        }
+    }
+
+    /// Maximum bytes of a search result snippet.
+    pub fn snippet_len(self) -> usize {
+        match self {
+            Self::Compact => 80,
+            Self::Standard => 120,
+            Self::Verbose => 240,
+        }
+    }
+
+    /// Maximum bytes of a single grep output line.
+    pub fn line_len(self) -> usize {
+        match self {
+            Self::Compact => 120,
+            Self::Standard => 200,
+            Self::Verbose => 400,
+        }
+    }
+
+    /// Grep context lines when the call does not pass `context`.
+    pub fn context_lines(self) -> usize {
+        match self {
+            Self::Compact => 0,
+            Self::Standard => 2,
+            Self::Verbose => 4,
+        }
+    }
+
+    /// Whether result headers include descriptions, timings, and match statistics.
+    pub fn detailed_headers(self) -> bool {
+        self != Self::Compact
+    }
+
+    /// Whether result headers include raw scores and byte sizes.
+    pub fn show_scores(self) -> bool {
+        self == Self::Verbose
+    }
+}
+
+/// Profile for one tool call: the `profile` argument, else the session default.
+pub fn resolve_profile(
+    args: &serde_json::Value,
+    session: &Option<SessionState>,
+) -> Result<OutputProfile, String> {
+    match args.get("profile").and_then(|v| v.as_str()) {
+        Some(name) => OutputProfile::parse(name).ok_or_else(|| {
+            format!("Unknown profile '{name}'. Use 'compact', 'standard', or 'verbose'")
+        }),
+        None => Ok(session.as_ref().map(|s| s.output_profile).unwrap_or_default()),
+    }
+}
+
+/// Session default requested in `initialize` params. Unknown names fall back to standard.
+pub fn initialize_profile(params: &crate::protocol::InitializeParams) -> OutputProfile {
+    let codescope = params.capabilities.experimental.codescope.as_ref();
+    codescope
+        .and_then(|c| c.output_profile.as_deref())
+        .and_then(OutputProfile::parse)
+        .unwrap_or_default()
+}
+
+/// Truncate `line` to at most `max` bytes on a char boundary, appending `...` when cut.
+pub fn truncate_line(line: &str, max: usize) -> String {
+    if line.len() > max {
+        format!("{}...", &line[..line.floor_char_boundary(max)])
+    } else {
+        line.to_string()
+    }
+}
+
+/// `# path` header for a file read. Compact omits the detail line; verbose adds size.
+pub fn file_header(profile: OutputProfile, path: &str, detail: &str, bytes: usize) -> String {
+    match profile {
+        OutputProfile::Compact => format!("# {path}\n\n"),
+        OutputProfile::Standard => format!("# {path}\n({detail})\n\n"),
+        OutputProfile::Verbose => {
+            format!("# {path}\n({detail}, {bytes} bytes, ~{} tokens)\n\n", bytes / 4)
+        }
+    }
+}
+
+/// Split a tool result that is over `max_tokens` into the part to send now and the rest.
+/// The cut falls after the last whole line that fits; a first line too long to fit on its
+/// own is cut on a char boundary. Returns `None` when the result fits.
+pub fn split_result(
+    text: &str,
+    max_tokens: usize,
+    tokenizer: &dyn crate::tokenizer::Tokenizer,
+) -> Option<(String, String)> {
+    if tokenizer.count_tokens(text) <= max_tokens {
+        return None;
+    }
+    let mut used = 0;
+    let mut cut = 0;
+    for line in text.split_inclusive('\n') {
+        used += tokenizer.count_tokens(line);
+        if used > max_tokens {
+            break;
+        }
+        cut += line.len();
+    }
+    if cut == 0 {
+        // Keep the estimate's ~3 bytes per token; the page must not come back empty
+        cut = text.floor_char_boundary((max_tokens * 3).max(1)).max(text.ceil_char_boundary(1));
+    }
+    Some((text[..cut].to_string(), text[cut..].to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn profile_resolution_prefers_call_then_session() {
+        let mut session = Some(SessionState::new());
+        assert_eq!(resolve_profile(&json!({}), &session), Ok(OutputProfile::Standard));
+
+        let params = serde_json::from_value(json!({
+            "capabilities": { "experimental": { "codescope": { "outputProfile": "compact" } } }
+        }))
+        .unwrap();
+        session.as_mut().unwrap().output_profile = initialize_profile(&params);
+        assert_eq!(resolve_profile(&json!({}), &session), Ok(OutputProfile::Compact));
+        assert_eq!(
+            resolve_profile(&json!({ "profile": "Verbose" }), &session),
+            Ok(OutputProfile::Verbose)
+        );
+        assert!(resolve_profile(&json!({ "profile": "loud" }), &None).is_err());
+    }
+
+    #[test]
+    fn truncate_line_respects_char_boundaries() {
+        assert_eq!(truncate_line("short", 10), "short");
+        assert_eq!(truncate_line("héllo world", 2), "h...");
+    }
+
+    #[test]
+    fn results_split_after_the_last_line_that_fits() {
+        let tokenizer = crate::tokenizer::BytesEstimateTokenizer;
+        let text = "alpha one\nbeta two\ngamma three\n";
+        assert_eq!(split_result(text, 100, &tokenizer), None);
+        let (page, rest) = split_result(text, 8, &tokenizer).unwrap();
+        assert_eq!((page.as_str(), rest.as_str()), ("alpha one\nbeta two\n", "gamma three\n"));
+        let (page, rest) = split_result("ééééé", 1, &tokenizer).unwrap();
+        assert_eq!((page.as_str(), rest.as_str()), ("é", "éééé"));
+    }
+}
@@ -6,9 +6,10 @@
 
 use crate::types::{SearchFileEntry, SearchModuleEntry};
 use rayon::prelude::*;
-use serde::Serialize;
 use std::time::Instant;
 
+pub use codescope_client::api::{SearchFileResult, SearchModuleResult, SearchResponse};
+
 // ---------------------------------------------------------------------------
 // Scoring constants (fzf v2)
 // ---------------------------------------------------------------------------
@@ -320,47 +321,6 @@ struct TokenInfo {
     mask: u64,
 }
 
-/// A scored file match from a fuzzy search query, with match position indices.
-#[derive(Serialize)]
-pub struct SearchFileResult {
-    pub path: String,
-    pub filename: String,
-    pub dir: String,
-    pub ext: String,
-    pub desc: String,
-    pub category: String,
-    pub score: f64,
-    #[serde(rename = "filenameIndices")]
-    pub filename_indices: Vec<usize>,
-    #[serde(rename = "pathIndices")]
-    pub path_indices: Vec<usize>,
-}
-
-/// A scored module match from a fuzzy search query, with match position indices.
-#[derive(Serialize)]
-pub struct SearchModuleResult {
-    pub id: String,
-    pub name: String,
-    #[serde(rename = "fileCount")]
-    pub file_count: usize,
-    pub score: f64,
-    #[serde(rename = "matchedIndices")]
-    pub matched_indices: Vec<usize>,
-}
-
-/// Combined search response containing ranked file and module results with timing metadata.
-#[derive(Serialize)]
-pub struct SearchResponse {
-    pub files: Vec<SearchFileResult>,
-    pub modules: Vec<SearchModuleResult>,
-    #[serde(rename = "queryTime")]
-    pub query_time: f64,
-    #[serde(rename = "totalFiles")]
-    pub total_files: usize,
-    #[serde(rename = "totalModules")]
-    pub total_modules: usize,
-}
-
 fn score_module(m: &SearchModuleEntry, tokens: &[TokenInfo]) -> Option<SearchModuleResult> {
     let mut total_score = 0.0;
     let mut all_indices = Vec::new();
@@ -489,6 +449,19 @@ pub fn run_search(
     query: &str,
     file_limit: usize,
     module_limit: usize,
+) -> SearchResponse {
+    run_search_ranked(search_files, search_modules, query, file_limit, module_limit, &|_| 1.0)
+}
+
+/// [`run_search`] with each file's score scaled by `boost` (see [`crate::ranking`]) before
+/// results are cut to `file_limit`.
+pub fn run_search_ranked(
+    search_files: &[SearchFileEntry],
+    search_modules: &[SearchModuleEntry],
+    query: &str,
+    file_limit: usize,
+    module_limit: usize,
+    boost: &(dyn Fn(&SearchFileEntry) -> f64 + Sync),
 ) -> SearchResponse {
     let start = Instant::now();
     let trimmed = query.trim();
@@ -520,8 +493,14 @@ pub fn run_search(
     });
     module_results.truncate(module_limit);
 
-    let mut file_results: Vec<SearchFileResult> =
-        search_files.par_iter().filter_map(|f| score_file(f, &tokens)).collect();
+    let mut file_results: Vec<SearchFileResult> = search_files
+        .par_iter()
+        .filter_map(|f| {
+            let mut result = score_file(f, &tokens)?;
+            result.score *= boost(f);
+            Some(result)
+        })
+        .collect();
 
     if file_results.len() > file_limit {
         file_results.select_nth_unstable_by(file_limit, |a, b| {
@@ -544,6 +523,56 @@ pub fn run_search(
     }
 }
 
+/// [`run_search_ranked`] for terms joined by `OR`: each term is searched on its own, and a
+/// file or module that several match keeps its best score.
+pub fn run_search_any_ranked(
+    search_files: &[SearchFileEntry],
+    search_modules: &[SearchModuleEntry],
+    terms: &[&str],
+    file_limit: usize,
+    module_limit: usize,
+    boost: &(dyn Fn(&SearchFileEntry) -> f64 + Sync),
+) -> SearchResponse {
+    let start = Instant::now();
+    let mut files: std::collections::HashMap<String, SearchFileResult> = Default::default();
+    let mut modules: std::collections::HashMap<String, SearchModuleResult> = Default::default();
+    for term in terms {
+        let resp =
+            run_search_ranked(search_files, search_modules, term, file_limit, module_limit, boost);
+        for f in resp.files {
+            match files.get(&f.path) {
+                Some(best) if best.score >= f.score => {}
+                _ => {
+                    files.insert(f.path.clone(), f);
+                }
+            }
+        }
+        for m in resp.modules {
+            match modules.get(&m.id) {
+                Some(best) if best.score >= m.score => {}
+                _ => {
+                    modules.insert(m.id.clone(), m);
+                }
+            }
+        }
+    }
+    let by_score = |a: f64, b: f64| b.partial_cmp(&a).unwrap_or(std::cmp::Ordering::Equal);
+    let mut files: Vec<SearchFileResult> = files.into_values().collect();
+    files.sort_unstable_by(|a, b| by_score(a.score, b.score).then_with(|| a.path.cmp(&b.path)));
+    files.truncate(file_limit);
+    let mut modules: Vec<SearchModuleResult> = modules.into_values().collect();
+    modules.sort_unstable_by(|a, b| by_score(a.score, b.score).then_with(|| a.id.cmp(&b.id)));
+    modules.truncate(module_limit);
+
+    SearchResponse {
+        files,
+        modules,
+        query_time: start.elapsed().as_secs_f64() * 1000.0,
+        total_files: search_files.len(),
+        total_modules: search_modules.len(),
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Query preprocessing
 // ---------------------------------------------------------------------------
@@ -554,14 +583,99 @@ const KNOWN_EXTS: &[&str] = &[
     "wgsl", "js", "ts", "jsx", "tsx", "mjs", "cjs", "rs", "go", "java", "kt", "scala", "swift",
     "css", "scss", "less", "sass", "html", "htm", "vue", "svelte", "sh", "bash", "zsh", "ps1",
     "psm1", "psd1", "bat", "cmd", "md", "rst", "txt", "adoc", "cmake", "make", "gradle", "csproj",
-    "sln",
+    "sln", "php", "ex", "exs",
 ];
 
+/// Boolean parts of a search query: terms joined by `AND` (the default) or `OR`, and
+/// `-term` / `!term` exclusions.
+#[derive(Debug, Default, PartialEq)]
+pub struct QueryOperators {
+    /// Terms to match, without operators or exclusions.
+    pub terms: Vec<String>,
+    /// An `OR` appeared between terms, so any one of them may match.
+    pub any: bool,
+    /// Lowercased excluded terms.
+    pub excluded: Vec<String>,
+}
+
+impl QueryOperators {
+    /// Whether an exclusion names `path`: a word of the path (split at punctuation and
+    /// camelCase humps) equals the term, ignoring a plural `s`, so `-tests` drops
+    /// `tests/budget.rs`, `budget_test.go`, and `BudgetTests.cs`. Terms with punctuation
+    /// match as substrings of the path.
+    pub fn excludes(&self, path: &str) -> bool {
+        self.names(path)
+    }
+
+    /// Whether an exclusion names a word or identifier part of `line`, matched as in
+    /// [`QueryOperators::excludes`], so `-tests` drops `run_tests()` but not `attests`.
+    pub fn excludes_line(&self, line: &str) -> bool {
+        self.names(line)
+    }
+
+    fn names(&self, text: &str) -> bool {
+        if self.excluded.is_empty() {
+            return false;
+        }
+        let words = path_words(text);
+        self.excluded.iter().any(|term| {
+            if !term.chars().all(char::is_alphanumeric) {
+                return text.to_lowercase().contains(term.as_str());
+            }
+            let singular = term.strip_suffix('s').unwrap_or(term);
+            words
+                .iter()
+                .any(|w| w == term || w == singular || w.strip_suffix('s') == Some(term.as_str()))
+        })
+    }
+}
+
+/// Split a search query into terms, `AND`/`OR` operators (uppercase, or `&&`/`||`), and
+/// exclusions. A leading `-` or `!` excludes a term when a letter, digit, or `_` follows it,
+/// so `--verbose` and `!=` stay literal.
+pub fn parse_query_operators(query: &str) -> QueryOperators {
+    let mut ops = QueryOperators::default();
+    for token in query.split_whitespace() {
+        match token {
+            "AND" | "&&" => {}
+            "OR" | "||" => ops.any = true,
+            _ => match token.strip_prefix(['-', '!']) {
+                Some(rest) if rest.starts_with(|c: char| c.is_alphanumeric() || c == '_') => {
+                    ops.excluded.push(rest.to_lowercase());
+                }
+                _ => ops.terms.push(token.to_string()),
+            },
+        }
+    }
+    ops
+}
+
+/// Lowercased words of a path or line, split at non-alphanumerics and lower-to-upper case changes.
+fn path_words(path: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    for part in path.split(|c: char| !c.is_alphanumeric()).filter(|p| !p.is_empty()) {
+        let mut word = String::new();
+        let mut prev_lower = false;
+        for c in part.chars() {
+            if c.is_uppercase() && prev_lower && !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            prev_lower = c.is_lowercase() || c.is_ascii_digit();
+            word.extend(c.to_lowercase());
+        }
+        words.push(word);
+    }
+    words
+}
+
 /// Strip known file extensions from search tokens so fuzzy search matches the stem.
 /// For example, "VolumetricCloudRendering.usf" becomes "VolumetricCloudRendering".
+/// Boolean operators and exclusions ([`parse_query_operators`]) are dropped.
 pub fn preprocess_search_query(query: &str) -> String {
-    query
-        .split_whitespace()
+    parse_query_operators(query)
+        .terms
+        .iter()
+        .map(String::as_str)
         .map(|token| {
             if let Some((stem, ext)) = token.rsplit_once('.') {
                 if KNOWN_EXTS.contains(&ext) && !stem.is_empty() {
@@ -662,4 +776,119 @@ mod tests {
         assert!(result.files.is_empty());
         assert!(result.modules.is_empty());
     }
+
+    fn file_entry(path: &str) -> SearchFileEntry {
+        let (dir, filename) = path.rsplit_once('/').unwrap_or(("", path));
+        let ext = filename.rsplit_once('.').map(|(_, e)| e).unwrap_or("");
+        SearchFileEntry {
+            path: path.into(),
+            path_lower: path.to_lowercase(),
+            filename: filename.into(),
+            filename_lower: filename.to_lowercase(),
+            dir: dir.into(),
+            ext: ext.into(),
+            desc: "".into(),
+            desc_lower: "".into(),
+            category: dir.into(),
+            filename_mask: char_bitmask(&filename.to_lowercase()),
+            path_mask: char_bitmask(&path.to_lowercase()),
+            desc_mask: 0,
+        }
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn fuzzy_score_never_panics(text in "\\PC{0,80}", pattern in "\\PC{0,12}", cs: bool) {
+            let _ = fuzzy_score_v2(&text, &pattern, cs);
+        }
+
+        #[test]
+        fn fuzzy_indices_are_valid(text in "\\PC{0,80}", pattern in "\\PC{1,12}", cs: bool) {
+            if let Some((_, indices)) = fuzzy_score_v2(&text, &pattern, cs) {
+                let (tb, pb) = (text.as_bytes(), pattern.as_bytes());
+                proptest::prop_assert_eq!(indices.len(), pb.len());
+                proptest::prop_assert!(indices.windows(2).all(|w| w[0] < w[1]));
+                for (k, &i) in indices.iter().enumerate() {
+                    proptest::prop_assert!(chars_match(tb[i], pb[k], cs));
+                }
+            }
+        }
+
+        #[test]
+        fn substring_always_matches(
+            prefix in "[a-z_/. ]{0,20}",
+            pattern in "[a-zA-Z0-9_]{1,12}",
+            suffix in "[a-z_/. ]{0,20}",
+        ) {
+            let text = format!("{prefix}{pattern}{suffix}");
+            proptest::prop_assert!(fuzzy_score_v2(&text, &pattern, true).is_some());
+            proptest::prop_assert!(fuzzy_score_v2(&text, &pattern, false).is_some());
+        }
+
+        // Delimiters and camelCase in the surrounding text legitimately earn boundary
+        // bonuses, so monotonicity is only expected against plain word characters.
+        #[test]
+        fn exact_match_scores_at_least_embedded(
+            prefix in "[a-z]{0,20}",
+            pattern in "[a-z0-9_]{1,12}",
+            suffix in "[a-z]{0,20}",
+        ) {
+            let embedded = format!("{prefix}{pattern}{suffix}");
+            let (exact, _) = fuzzy_score_v2(&pattern, &pattern, false).unwrap();
+            let (other, _) = fuzzy_score_v2(&embedded, &pattern, false).unwrap();
+            proptest::prop_assert!(exact >= other, "exact {} < embedded {}", exact, other);
+        }
+
+        #[test]
+        fn run_search_never_panics(
+            paths in proptest::collection::vec("\\PC{1,40}", 0..8),
+            query in "\\PC{0,24}",
+        ) {
+            let files: Vec<SearchFileEntry> = paths.iter().map(|p| file_entry(p)).collect();
+            let result = run_search(&files, &[], &query, 10, 10);
+            proptest::prop_assert!(result.files.len() <= files.len());
+        }
+
+        #[test]
+        fn exact_stem_ranks_first(stem in "[a-z]{3,10}", noise in "[a-z]{1,6}") {
+            let exact = file_entry(&format!("src/{stem}.rs"));
+            let longer = file_entry(&format!("src/{noise}{stem}{noise}.rs"));
+            let result = run_search(&[longer, exact], &[], &stem, 10, 10);
+            proptest::prop_assert_eq!(result.files[0].filename.clone(), format!("{stem}.rs"));
+        }
+    }
+
+    #[test]
+    fn query_operators_split_terms_and_exclusions() {
+        let ops = parse_query_operators("budget OR quota -tests !Spec --verbose !=");
+        assert_eq!(ops.terms, vec!["budget", "quota", "--verbose", "!="]);
+        assert!(ops.any);
+        assert_eq!(ops.excluded, vec!["tests", "spec"]);
+        assert!(!parse_query_operators("budget AND quota").any);
+        assert_eq!(preprocess_search_query("budget.rs AND planner -tests"), "budget planner");
+
+        for path in
+            ["tests/budget.rs", "src/budget_test.go", "src/BudgetTests.cs", "web/budget.spec.ts"]
+        {
+            assert!(ops.excludes(path), "{path} should be excluded");
+        }
+        for path in ["src/budget.rs", "src/latest.rs", "src/inspector.rs"] {
+            assert!(!ops.excludes(path), "{path} should be kept");
+        }
+        assert!(ops.excludes_line("// covered by tests below"));
+        assert!(ops.excludes_line("    run_tests(&cases);") && ops.excludes_line("impl FooSpec {"));
+        assert!(!ops.excludes_line("// the audit attests to the budget"));
+    }
+
+    #[test]
+    fn any_search_keeps_files_matching_either_term() {
+        let files = [file_entry("src/budget.rs"), file_entry("src/quota.rs")];
+        assert!(run_search(&files, &[], "budget quota", 10, 10).files.is_empty());
+        let resp = run_search_any_ranked(&files, &[], &["budget", "quota"], 10, 10, &|_| 1.0);
+        let mut paths: Vec<&str> = resp.files.iter().map(|f| f.path.as_str()).collect();
+        paths.sort();
+        assert_eq!(paths, ["src/budget.rs", "src/quota.rs"]);
+        let one = run_search_any_ranked(&files, &[], &["budget", "quota"], 1, 10, &|_| 1.0);
+        assert_eq!(one.files.len(), 1);
+    }
 }
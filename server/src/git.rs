@@ -1,9 +1,16 @@
-//! Git-aware intelligence: blame, file history, changed files, and churn analysis.
-
-use git2::{BlameOptions, Repository, Sort, Time};
+//! Git-aware intelligence: blame, file history, changed files, branch comparison,
+//! per-module diffstats, and churn analysis.
+//!
+//! Comparisons default to the repo's default branch: `[git] default_branch` in
+//! `.codescope.toml`, else `origin/HEAD`, else the first of `main`, `master`, `trunk`,
+//! `develop` that exists. `[git] protected` globs name refs that must not be rewritten
+//! (the default branch alone when unset); results flag them.
+
+use git2::{BlameOptions, BranchType, Oid, Repository, Sort, Time};
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use serde::Serialize;
-use std::collections::HashMap;
-use std::path::Path;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
 
 // ---------------------------------------------------------------------------
 // Types
@@ -39,6 +46,104 @@ pub struct HotFile {
     pub commits: usize,
 }
 
+/// `head` relative to `base`, diffed from their merge base as `git diff base...head` does.
+#[derive(Serialize)]
+pub struct Comparison {
+    pub base: String,
+    pub head: String,
+    /// Short hash of the merge base.
+    pub merge_base: String,
+    /// Commits on `head` but not `base`, newest first, up to the requested limit.
+    pub ahead: Vec<CommitInfo>,
+    pub ahead_count: usize,
+    pub behind_count: usize,
+    pub files: Vec<ChangedFile>,
+}
+
+/// Added and removed lines for one file between two refs.
+#[derive(Serialize)]
+pub struct FileDiffStat {
+    pub path: String,
+    pub status: String,
+    pub added: usize,
+    pub removed: usize,
+    pub binary: bool,
+}
+
+/// Line counts for `head` since its merge base with `base`.
+#[derive(Serialize)]
+pub struct DiffStat {
+    pub base: String,
+    pub head: String,
+    /// Short hash of the merge base.
+    pub merge_base: String,
+    pub files: Vec<FileDiffStat>,
+}
+
+/// [`FileDiffStat`]s summed per module, files sorted by churn.
+#[derive(Serialize)]
+pub struct ModuleDiffStat {
+    pub module: String,
+    pub added: usize,
+    pub removed: usize,
+    pub files: Vec<FileDiffStat>,
+}
+
+/// Branch names tried, in order, when neither config nor `origin/HEAD` names the default.
+const DEFAULT_BRANCH_CANDIDATES: &[&str] = &["main", "master", "trunk", "develop"];
+
+/// `[git]` settings from `.codescope.toml`.
+#[derive(Clone, Default)]
+pub struct GitConfig {
+    /// Branch that `changed` and `compare` diff against when no ref is given.
+    pub default_branch: Option<String>,
+    protected: Vec<String>,
+    protected_set: Option<GlobSet>,
+}
+
+impl GitConfig {
+    /// Parse the `[git]` table: `default_branch` (string) and `protected` (array of globs).
+    /// Invalid globs are skipped with a warning.
+    pub fn from_table(table: &toml::Table) -> Self {
+        let default_branch =
+            table.get("default_branch").and_then(|v| v.as_str()).map(str::to_string);
+        let protected: Vec<String> = table
+            .get("protected")
+            .and_then(|v| v.as_array())
+            .map(|a| a.iter().filter_map(|v| v.as_str()).map(str::to_string).collect())
+            .unwrap_or_default();
+        let mut builder = GlobSetBuilder::new();
+        for pattern in &protected {
+            match Glob::new(pattern) {
+                Ok(g) => {
+                    builder.add(g);
+                }
+                Err(e) => tracing::warn!(pattern, error = %e, "Invalid [git] protected glob"),
+            }
+        }
+        let protected_set = if protected.is_empty() { None } else { builder.build().ok() };
+        Self { default_branch, protected, protected_set }
+    }
+
+    /// Configured protected globs; empty means only the default branch is protected.
+    pub fn protected_patterns(&self) -> &[String] {
+        &self.protected
+    }
+
+    /// Whether `name` (a short ref such as `main`, `origin/main`, or `v1.2.0`) is protected.
+    /// Remote-tracking names also match by their branch part.
+    pub fn is_protected(&self, name: &str, default_branch: &str) -> bool {
+        let branch = name.split_once('/').map(|(_, b)| b);
+        match &self.protected_set {
+            Some(set) => set.is_match(name) || branch.is_some_and(|b| set.is_match(b)),
+            None => {
+                let default = default_branch.split_once('/').map_or(default_branch, |(_, b)| b);
+                name == default_branch || name == default || branch == Some(default)
+            }
+        }
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Helpers
 // ---------------------------------------------------------------------------
@@ -130,8 +235,8 @@ pub fn blame(
 
     // Read file content for line text
     let file_path = repo_root.join(rel_path);
-    let content =
-        std::fs::read_to_string(&file_path).map_err(|e| format!("Failed to read file: {e}"))?;
+    let content = crate::encoding::read_to_string(&file_path)
+        .map_err(|e| format!("Failed to read file: {e}"))?;
     let lines: Vec<&str> = content.lines().collect();
 
     let mut result = Vec::new();
@@ -241,26 +346,51 @@ pub fn file_history(
     Ok(results)
 }
 
-/// Files changed since a given commit, branch, or tag.
-pub fn changed_since(repo_root: &Path, since: &str) -> Result<Vec<ChangedFile>, String> {
+/// The branch comparisons default to. See the module docs for the lookup order.
+pub fn default_branch(repo_root: &Path, config: &GitConfig) -> Result<String, String> {
+    if let Some(branch) = &config.default_branch {
+        return Ok(branch.clone());
+    }
     let repo = Repository::open(repo_root).map_err(|e| format!("Failed to open repo: {e}"))?;
+    if let Ok(origin_head) = repo.find_reference("refs/remotes/origin/HEAD") {
+        if let Some(target) = origin_head.symbolic_target() {
+            return Ok(target.trim_start_matches("refs/remotes/").to_string());
+        }
+    }
+    DEFAULT_BRANCH_CANDIDATES
+        .iter()
+        .find(|name| repo.find_branch(name, BranchType::Local).is_ok())
+        .map(|name| name.to_string())
+        .ok_or_else(|| {
+            "No default branch found; set [git] default_branch in .codescope.toml".to_string()
+        })
+}
 
-    // Resolve the "since" reference to a commit
-    let base_obj =
-        repo.revparse_single(since).map_err(|e| format!("Cannot resolve '{since}': {e}"))?;
-    let base_commit =
-        base_obj.peel_to_commit().map_err(|e| format!("'{since}' is not a commit: {e}"))?;
-    let base_tree = base_commit.tree().map_err(|e| format!("Failed to get tree: {e}"))?;
-
-    // Get HEAD tree
-    let head = repo.head().map_err(|e| format!("Failed to get HEAD: {e}"))?;
-    let head_commit = head.peel_to_commit().map_err(|e| format!("HEAD is not a commit: {e}"))?;
-    let head_tree = head_commit.tree().map_err(|e| format!("Failed to get HEAD tree: {e}"))?;
+/// The checked-out branch, or `None` for a detached or unborn HEAD.
+pub fn current_branch(repo_root: &Path) -> Option<String> {
+    let repo = Repository::open(repo_root).ok()?;
+    let head = repo.head().ok()?;
+    if head.is_branch() {
+        head.shorthand().map(str::to_string)
+    } else {
+        None
+    }
+}
 
-    let diff = repo
-        .diff_tree_to_tree(Some(&base_tree), Some(&head_tree), None)
-        .map_err(|e| format!("Diff failed: {e}"))?;
+fn resolve_commit<'r>(repo: &'r Repository, rev: &str) -> Result<git2::Commit<'r>, String> {
+    repo.revparse_single(rev)
+        .map_err(|e| format!("Cannot resolve '{rev}': {e}"))?
+        .peel_to_commit()
+        .map_err(|e| format!("'{rev}' is not a commit: {e}"))
+}
 
+/// Paths and statuses that differ between two trees.
+fn diff_files(
+    repo: &Repository,
+    old: Option<&git2::Tree>,
+    new: Option<&git2::Tree>,
+) -> Result<Vec<ChangedFile>, String> {
+    let diff = repo.diff_tree_to_tree(old, new, None).map_err(|e| format!("Diff failed: {e}"))?;
     let mut results = Vec::new();
     diff.foreach(
         &mut |delta, _| {
@@ -279,10 +409,267 @@ pub fn changed_since(repo_root: &Path, since: &str) -> Result<Vec<ChangedFile>,
         None,
     )
     .map_err(|e| format!("Diff iteration failed: {e}"))?;
-
     Ok(results)
 }
 
+/// Commits reachable from `from` but not from `hide`, newest first.
+fn commits_between(repo: &Repository, from: Oid, hide: Oid) -> Result<Vec<Oid>, String> {
+    let mut revwalk = repo.revwalk().map_err(|e| format!("Revwalk failed: {e}"))?;
+    revwalk.push(from).map_err(|e| format!("Revwalk push failed: {e}"))?;
+    revwalk.hide(hide).map_err(|e| format!("Revwalk hide failed: {e}"))?;
+    revwalk.set_sorting(Sort::TIME).map_err(|e| format!("set_sorting failed: {e}"))?;
+    Ok(revwalk.filter_map(Result::ok).collect())
+}
+
+/// Merge base of two commits, its tree, and the tree of `head`.
+fn merge_base_trees<'r>(
+    repo: &'r Repository,
+    base: &git2::Commit,
+    head: &git2::Commit<'r>,
+) -> Result<(Oid, git2::Tree<'r>, git2::Tree<'r>), String> {
+    let merge_base = repo.merge_base(base.id(), head.id()).map_err(|e| {
+        format!(
+            "No common history between {} and {}: {e}",
+            short_id(base.id()),
+            short_id(head.id())
+        )
+    })?;
+    let merge_tree = repo
+        .find_commit(merge_base)
+        .and_then(|c| c.tree())
+        .map_err(|e| format!("Failed to get merge-base tree: {e}"))?;
+    let head_tree = head.tree().map_err(|e| format!("Failed to get tree: {e}"))?;
+    Ok((merge_base, merge_tree, head_tree))
+}
+
+fn short_id(oid: Oid) -> String {
+    oid.to_string()[..8].to_string()
+}
+
+/// Compare `head` against `base`: commits ahead and behind, and files changed since
+/// their merge base. At most `limit` ahead commits are listed.
+pub fn compare(
+    repo_root: &Path,
+    base: &str,
+    head: &str,
+    limit: usize,
+) -> Result<Comparison, String> {
+    let repo = Repository::open(repo_root).map_err(|e| format!("Failed to open repo: {e}"))?;
+    let base_commit = resolve_commit(&repo, base)?;
+    let head_commit = resolve_commit(&repo, head)?;
+    let (merge_base, merge_tree, head_tree) = merge_base_trees(&repo, &base_commit, &head_commit)?;
+    let files = diff_files(&repo, Some(&merge_tree), Some(&head_tree))?;
+
+    let ahead_oids = commits_between(&repo, head_commit.id(), base_commit.id())?;
+    let behind_count = commits_between(&repo, base_commit.id(), head_commit.id())?.len();
+    let mut ahead = Vec::new();
+    for oid in ahead_oids.iter().take(limit) {
+        let Ok(commit) = repo.find_commit(*oid) else { continue };
+        let parent_tree = commit.parent(0).ok().and_then(|p| p.tree().ok());
+        let files_changed = commit
+            .tree()
+            .ok()
+            .and_then(|t| diff_files(&repo, parent_tree.as_ref(), Some(&t)).ok())
+            .map(|files| files.into_iter().map(|f| f.path).collect())
+            .unwrap_or_default();
+        let sig = commit.author();
+        ahead.push(CommitInfo {
+            hash: short_id(*oid),
+            author: sig.name().unwrap_or("unknown").to_string(),
+            date: format_git_time(sig.when()),
+            message: commit.message().unwrap_or("").lines().next().unwrap_or("").to_string(),
+            files_changed,
+        });
+    }
+
+    Ok(Comparison {
+        base: base.to_string(),
+        head: head.to_string(),
+        merge_base: short_id(merge_base),
+        ahead,
+        ahead_count: ahead_oids.len(),
+        behind_count,
+        files,
+    })
+}
+
+/// Added/removed lines per file for `head` since its merge base with `base`
+/// (`git diff --numstat base...head`). Binary files report zero lines.
+pub fn diffstat(repo_root: &Path, base: &str, head: &str) -> Result<DiffStat, String> {
+    let repo = Repository::open(repo_root).map_err(|e| format!("Failed to open repo: {e}"))?;
+    let base_commit = resolve_commit(&repo, base)?;
+    let head_commit = resolve_commit(&repo, head)?;
+    let (merge_base, merge_tree, head_tree) = merge_base_trees(&repo, &base_commit, &head_commit)?;
+    let diff = repo
+        .diff_tree_to_tree(Some(&merge_tree), Some(&head_tree), None)
+        .map_err(|e| format!("Diff failed: {e}"))?;
+
+    let mut files = Vec::new();
+    for (i, delta) in diff.deltas().enumerate() {
+        let path = delta
+            .new_file()
+            .path()
+            .or_else(|| delta.old_file().path())
+            .and_then(|p| p.to_str())
+            .unwrap_or("")
+            .to_string();
+        let patch = git2::Patch::from_diff(&diff, i).map_err(|e| format!("Diff failed: {e}"))?;
+        let (added, removed) = match &patch {
+            Some(p) if !delta.flags().is_binary() => {
+                let (_, added, removed) =
+                    p.line_stats().map_err(|e| format!("Diff failed: {e}"))?;
+                (added, removed)
+            }
+            _ => (0, 0),
+        };
+        files.push(FileDiffStat {
+            path,
+            status: status_char(delta.status()).to_string(),
+            added,
+            removed,
+            binary: delta.flags().is_binary() || patch.is_none(),
+        });
+    }
+
+    Ok(DiffStat {
+        base: base.to_string(),
+        head: head.to_string(),
+        merge_base: short_id(merge_base),
+        files,
+    })
+}
+
+/// Group file stats by `module_of(path)`, modules and their files sorted by churn
+/// (added + removed), then name.
+pub fn by_module(
+    files: Vec<FileDiffStat>,
+    module_of: impl Fn(&str) -> String,
+) -> Vec<ModuleDiffStat> {
+    let mut modules: HashMap<String, ModuleDiffStat> = HashMap::new();
+    for file in files {
+        let module = module_of(&file.path);
+        let entry = modules.entry(module.clone()).or_insert_with(|| ModuleDiffStat {
+            module,
+            added: 0,
+            removed: 0,
+            files: Vec::new(),
+        });
+        entry.added += file.added;
+        entry.removed += file.removed;
+        entry.files.push(file);
+    }
+    let churn = |added: usize, removed: usize| added + removed;
+    let mut result: Vec<ModuleDiffStat> = modules.into_values().collect();
+    for m in &mut result {
+        m.files.sort_by(|a, b| {
+            churn(b.added, b.removed).cmp(&churn(a.added, a.removed)).then(a.path.cmp(&b.path))
+        });
+    }
+    result.sort_by(|a, b| {
+        churn(b.added, b.removed).cmp(&churn(a.added, a.removed)).then(a.module.cmp(&b.module))
+    });
+    result
+}
+
+/// Files changed since a given commit, branch, or tag.
+pub fn changed_since(repo_root: &Path, since: &str) -> Result<Vec<ChangedFile>, String> {
+    let repo = Repository::open(repo_root).map_err(|e| format!("Failed to open repo: {e}"))?;
+    let base_tree =
+        resolve_commit(&repo, since)?.tree().map_err(|e| format!("Failed to get tree: {e}"))?;
+
+    // Get HEAD tree
+    let head = repo.head().map_err(|e| format!("Failed to get HEAD: {e}"))?;
+    let head_commit = head.peel_to_commit().map_err(|e| format!("HEAD is not a commit: {e}"))?;
+    let head_tree = head_commit.tree().map_err(|e| format!("Failed to get HEAD tree: {e}"))?;
+
+    diff_files(&repo, Some(&base_tree), Some(&head_tree))
+}
+
+/// HEAD commit id of the git repository containing `repo_root`.
+pub fn head_commit(repo_root: &Path) -> Option<String> {
+    let repo = Repository::discover(repo_root).ok()?;
+    let head = repo.head().ok()?.peel_to_commit().ok()?;
+    Some(head.id().to_string())
+}
+
+/// Files that may differ from an index built at commit `since`: uncommitted changes
+/// (staged, unstaged, and untracked but not ignored) plus files changed between `since`
+/// and HEAD. Absolute paths, deleted files included.
+pub fn changed_paths(repo_root: &Path, since: Option<&str>) -> Result<Vec<PathBuf>, String> {
+    let repo = Repository::discover(repo_root).map_err(|e| format!("Not a git repo: {e}"))?;
+    let workdir = repo.workdir().ok_or("Bare repository has no working tree")?.to_path_buf();
+    let mut paths: BTreeSet<String> = BTreeSet::new();
+
+    let mut opts = git2::StatusOptions::new();
+    opts.include_untracked(true).recurse_untracked_dirs(true).include_ignored(false);
+    let statuses = repo.statuses(Some(&mut opts)).map_err(|e| format!("git status failed: {e}"))?;
+    paths.extend(statuses.iter().filter_map(|s| s.path().map(String::from)));
+
+    let head = repo.head().and_then(|h| h.peel_to_commit()).ok();
+    if let (Some(since), Some(head)) = (since, head) {
+        if head.id().to_string() != since {
+            let base_tree = resolve_commit(&repo, since)?
+                .tree()
+                .map_err(|e| format!("Failed to get tree: {e}"))?;
+            let head_tree = head.tree().map_err(|e| format!("Failed to get HEAD tree: {e}"))?;
+            let changed = diff_files(&repo, Some(&base_tree), Some(&head_tree))?;
+            paths.extend(changed.into_iter().map(|f| f.path));
+        }
+    }
+    Ok(paths.into_iter().map(|p| workdir.join(p)).collect())
+}
+
+/// Unified diff (hunks only, 3 lines of context) between two versions of a text, with
+/// the numbers of added and removed lines. `None` if the texts are equal.
+pub fn diff_text(old: &str, new: &str) -> Option<(String, usize, usize)> {
+    let mut opts = git2::DiffOptions::new();
+    opts.context_lines(3);
+    let mut patch =
+        git2::Patch::from_buffers(old.as_bytes(), None, new.as_bytes(), None, Some(&mut opts))
+            .ok()?;
+    let (_, added, removed) = patch.line_stats().ok()?;
+    let buf = patch.to_buf().ok()?;
+    let text = buf.as_str()?;
+    let hunks = &text[text.find("@@")?..];
+    Some((hunks.to_string(), added, removed))
+}
+
+/// Files that exist on HEAD and changed since its merge base with `base`, for selecting
+/// what to re-test. Deleted files are left out.
+pub fn changed_on_branch(repo_root: &Path, base: &str) -> Result<Vec<String>, String> {
+    let cmp = compare(repo_root, base, "HEAD", 0)?;
+    Ok(cmp.files.into_iter().filter(|f| f.status != "deleted").map(|f| f.path).collect())
+}
+
+/// Time of the last commit touching each file, from the newest `max_commits` commits
+/// reachable from HEAD. Files not changed in that window are absent.
+pub fn last_commit_times(
+    repo_root: &Path,
+    max_commits: usize,
+) -> Result<HashMap<String, i64>, String> {
+    let repo = Repository::open(repo_root).map_err(|e| format!("Failed to open repo: {e}"))?;
+    let mut revwalk = repo.revwalk().map_err(|e| format!("Revwalk failed: {e}"))?;
+    revwalk.push_head().map_err(|e| format!("push_head failed: {e}"))?;
+    revwalk.set_sorting(Sort::TIME).map_err(|e| format!("set_sorting failed: {e}"))?;
+
+    let mut times: HashMap<String, i64> = HashMap::new();
+    for oid in revwalk.take(max_commits).flatten() {
+        let Ok(commit) = repo.find_commit(oid) else { continue };
+        let Ok(tree) = commit.tree() else { continue };
+        let parent_tree = commit.parent(0).ok().and_then(|p| p.tree().ok());
+        let Ok(diff) = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None) else {
+            continue;
+        };
+        let time = commit.time().seconds();
+        for delta in diff.deltas() {
+            if let Some(path) = delta.new_file().path().and_then(|p| p.to_str()) {
+                times.entry(path.to_string()).or_insert(time);
+            }
+        }
+    }
+    Ok(times)
+}
+
 /// Most frequently changed files (churn ranking) within recent N days.
 pub fn hot_files(repo_root: &Path, limit: usize, days: usize) -> Result<Vec<HotFile>, String> {
     let repo = Repository::open(repo_root).map_err(|e| format!("Failed to open repo: {e}"))?;
@@ -374,4 +761,47 @@ mod tests {
         let (y, m, d) = days_to_ymd(11017);
         assert_eq!((y, m, d), (2000, 3, 1), "day 11017 should be 2000-03-01");
     }
+
+    #[test]
+    fn protected_refs_default_to_the_default_branch() {
+        let unset = GitConfig::default();
+        assert!(unset.is_protected("main", "main"));
+        assert!(unset.is_protected("origin/main", "origin/main"));
+        assert!(unset.is_protected("main", "origin/main"));
+        assert!(!unset.is_protected("feature/x", "main"));
+
+        let table: toml::Table = toml::from_str(
+            "default_branch = \"develop\"\nprotected = [\"develop\", \"release/*\", \"v*\"]",
+        )
+        .unwrap();
+        let config = GitConfig::from_table(&table);
+        assert_eq!(config.default_branch.as_deref(), Some("develop"));
+        assert!(config.is_protected("release/1.2", "develop"));
+        assert!(config.is_protected("origin/develop", "develop"));
+        assert!(config.is_protected("v1.0.0", "develop"));
+        assert!(!config.is_protected("main", "develop"), "explicit list replaces the default");
+    }
+
+    #[test]
+    fn diffstats_aggregate_per_module_by_churn() {
+        let stat = |path: &str, added, removed| FileDiffStat {
+            path: path.to_string(),
+            status: "modified".to_string(),
+            added,
+            removed,
+            binary: false,
+        };
+        let files = vec![
+            stat("core/a.rs", 3, 1),
+            stat("web/app.ts", 2, 0),
+            stat("core/b.rs", 10, 2),
+            stat("web/ui.ts", 1, 1),
+        ];
+        let modules = by_module(files, |p| p.split('/').next().unwrap().to_string());
+        let summary: Vec<(&str, usize, usize)> =
+            modules.iter().map(|m| (m.module.as_str(), m.added, m.removed)).collect();
+        assert_eq!(summary, [("core", 13, 3), ("web", 3, 1)]);
+        assert_eq!(modules[0].files[0].path, "core/b.rs");
+        assert_eq!(modules[1].files[0].path, "web/app.ts", "ties break by path");
+    }
 }
@@ -0,0 +1,362 @@
+//! Index handoff between server processes.
+//!
+//! On a clean shutdown the server writes each indexed repo's derived data (import graph,
+//! entry points, config references, license headers, schema cross-references, term
+//! frequencies) to a handoff file, together with a content hash of every file it covers.
+//! The next process, typically a freshly upgraded binary, loads that file instead of
+//! rescanning: the file walk still runs, but only files whose hash no longer matches are
+//! parsed again.
+//!
+//! A handoff is consumed when loaded. It is discarded, and the repo scanned from scratch,
+//! when the format version or `.codescope.toml` differs, or when too much of the repo
+//! changed for an incremental catch-up to pay off.
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+#[cfg(feature = "semantic")]
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::config_refs::{self, ConfigRef, ConfigRefDetector};
+use crate::entries::{self, EntryDetector, EntryPoint};
+use crate::licenses::{self, FileLicense, LicenseIndex};
+use crate::scan::{build_search_index, collect_module_docs, scan_deps, scan_files, scan_imports};
+use crate::schemas::{self, SchemaIndex, SchemaRef, SchemaSymbol};
+use crate::symbols::{self, SymbolEntry};
+use crate::types::{
+    ImportEdge, ImportGraph, RepoState, ScanProfile, ScannedFile, ServerState, TermDocFreq,
+};
+
+/// Bumped whenever the handoff layout or the meaning of a derived index changes.
+const HANDOFF_VERSION: u32 = 9;
+
+/// Files modified this recently when the handoff is written may not have reached the
+/// index yet (the watcher debounces), so they are recorded as unverified.
+const UNSETTLED_MTIME: Duration = Duration::from_secs(2);
+
+/// Hash recorded for files that must be re-parsed regardless of content.
+const UNVERIFIED_HASH: u64 = 0;
+
+/// Share of files that may differ before a full scan is cheaper than catching up.
+const MAX_CHANGED_FRACTION: f64 = 0.5;
+
+#[derive(Serialize, Deserialize)]
+struct RepoHandoff {
+    version: u32,
+    root: PathBuf,
+    /// Hash of `.codescope.toml` (empty content when absent).
+    config_hash: u64,
+    /// Scan profile the index was built with.
+    profile: Option<String>,
+    /// rel_path → content hash.
+    files: BTreeMap<String, u64>,
+    /// importer → imported file → edge metadata; both adjacency maps derive from it.
+    import_edges: BTreeMap<String, BTreeMap<String, ImportEdge>>,
+    entry_points: Vec<EntryPoint>,
+    symbols: Vec<SymbolEntry>,
+    config_refs: Vec<ConfigRef>,
+    license_headers: Vec<FileLicense>,
+    schema_symbols: Vec<SchemaSymbol>,
+    schema_refs: Vec<SchemaRef>,
+    term_doc_freq: TermDocFreq,
+}
+
+/// 64-bit FNV-1a. Stable across builds, unlike `std`'s `DefaultHasher`, so two
+/// different binaries agree on it.
+pub fn content_hash(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &b in bytes {
+        hash ^= u64::from(b);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+/// Default handoff file for a repo root: `<cache_dir>/handoff/<hash of root>.json`.
+pub fn handoff_path(root: &Path) -> Option<PathBuf> {
+    let key = content_hash(root.to_string_lossy().as_bytes());
+    crate::cache_dir().map(|d| d.join("handoff").join(format!("{key:016x}.json")))
+}
+
+/// Hash of the repo's config: `.codescope.toml` and the environment variables overriding it.
+fn config_hash(root: &Path) -> u64 {
+    let mut bytes = std::fs::read(root.join(".codescope.toml")).unwrap_or_default();
+    for (key, value) in crate::env_overrides(|name| std::env::var(name).ok()) {
+        bytes.extend(format!("\n{key} = {value}").bytes());
+    }
+    content_hash(&bytes)
+}
+
+fn file_hash(path: &Path) -> Option<u64> {
+    std::fs::read(path).ok().map(|bytes| content_hash(&bytes))
+}
+
+/// Write a repo's handoff to `path`, replacing any previous one.
+pub fn save(repo: &RepoState, path: &Path) -> Result<(), String> {
+    let settled_before = SystemTime::now() - UNSETTLED_MTIME;
+    let files: BTreeMap<String, u64> = repo
+        .all_files
+        .par_iter()
+        .map(|f| {
+            let settled = f
+                .abs_path
+                .metadata()
+                .and_then(|m| m.modified())
+                .is_ok_and(|mtime| mtime <= settled_before);
+            let hash = if settled { file_hash(&f.abs_path) } else { None };
+            (f.rel_path.clone(), hash.unwrap_or(UNVERIFIED_HASH))
+        })
+        .collect();
+    let handoff = RepoHandoff {
+        version: HANDOFF_VERSION,
+        root: repo.root.clone(),
+        config_hash: config_hash(&repo.root),
+        profile: repo.config.profile.clone(),
+        files,
+        import_edges: repo.import_graph.edges.clone(),
+        entry_points: repo.entry_points.clone(),
+        symbols: repo.symbols.clone(),
+        config_refs: repo.config_refs.clone(),
+        license_headers: repo.licenses.headers.clone(),
+        schema_symbols: repo.schemas.symbols.clone(),
+        schema_refs: repo.schemas.refs.clone(),
+        term_doc_freq: repo.term_doc_freq.clone(),
+    };
+
+    let json = serde_json::to_vec(&handoff)
+        .map_err(|e| format!("Failed to serialize handoff for '{}': {}", repo.name, e))?;
+    // Atomic, so a concurrently starting process never reads a partial file
+    crate::atomic::write_cache(path, json)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Write handoffs for every indexed repo to their default paths. Repos still waiting for
+/// their initial scan have nothing to hand off and are skipped, as are repos whose caches
+/// another running server writes (see [`crate::locks`]); that server hands off on exit.
+pub fn save_all(state: &ServerState) {
+    for repo in state.repos.values() {
+        let Some(path) = handoff_path(&repo.root) else {
+            continue;
+        };
+        if !crate::locks::is_writer(&repo.root) {
+            tracing::debug!(repo = repo.name.as_str(), "Another server writes this handoff");
+            continue;
+        }
+        match save(repo, &path) {
+            Ok(()) => tracing::info!(repo = repo.name.as_str(), "Wrote index handoff"),
+            Err(e) => tracing::warn!(repo = repo.name.as_str(), error = %e, "Index handoff failed"),
+        }
+    }
+}
+
+/// Load and consume the handoff at `path`, bringing it up to date with the files on disk.
+/// Returns `None` when there is no usable handoff and the repo needs a full scan.
+pub fn load(path: &Path, name: &str, root: &Path, profile: Option<&str>) -> Option<RepoState> {
+    let bytes = crate::crypt::read(path);
+    let _ = std::fs::remove_file(path);
+    let bytes = match bytes {
+        Ok(b) => b,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+        Err(e) => {
+            tracing::warn!(repo = name, error = %e, "Ignoring unreadable index handoff");
+            return None;
+        }
+    };
+    let handoff: RepoHandoff = match serde_json::from_slice(&bytes) {
+        Ok(h) => h,
+        Err(e) => {
+            tracing::warn!(repo = name, error = %e, "Ignoring unreadable index handoff");
+            return None;
+        }
+    };
+    if handoff.version != HANDOFF_VERSION || handoff.root != root {
+        tracing::info!(repo = name, "Index handoff is from another version or root; rescanning");
+        return None;
+    }
+    if handoff.config_hash != config_hash(root) {
+        tracing::info!(repo = name, "Config changed since index handoff; rescanning");
+        return None;
+    }
+    if handoff.profile.as_deref() != profile {
+        tracing::info!(repo = name, "Index handoff is for another scan profile; rescanning");
+        return None;
+    }
+    catch_up(handoff, name, root)
+}
+
+/// Load the handoff for `root` from its default path, if one exists.
+pub fn take(name: &str, root: &Path, profile: Option<&str>) -> Option<RepoState> {
+    load(&handoff_path(root)?, name, root, profile)
+}
+
+/// Rebuild a [`RepoState`] from a handoff, re-parsing only files whose content changed.
+fn catch_up(handoff: RepoHandoff, name: &str, root: &Path) -> Option<RepoState> {
+    let start = Instant::now();
+    let synced_at = crate::ranking::now_secs();
+    let synced_head = crate::git::head_commit(root);
+    let mut profile = ScanProfile::new();
+    let config = crate::load_codescope_profile(root, handoff.profile.as_deref());
+    let (all_files, manifest) = profile.stage("walk", || scan_files(&config));
+
+    let changed: Vec<ScannedFile> = profile.stage("verify", || {
+        all_files
+            .par_iter()
+            .filter(|f| {
+                let recorded = handoff.files.get(&f.rel_path).copied();
+                recorded.is_none_or(|h| h == UNVERIFIED_HASH || file_hash(&f.abs_path) != Some(h))
+            })
+            .cloned()
+            .collect()
+    });
+    let current: HashSet<&str> = all_files.iter().map(|f| f.rel_path.as_str()).collect();
+    let removed = handoff.files.keys().filter(|p| !current.contains(p.as_str())).count();
+    if (changed.len() + removed) as f64 > all_files.len() as f64 * MAX_CHANGED_FRACTION {
+        tracing::info!(
+            repo = name,
+            changed = changed.len(),
+            removed,
+            "Too many changes; rescanning"
+        );
+        return None;
+    }
+    let stale: HashSet<&str> = changed.iter().map(|f| f.rel_path.as_str()).collect();
+    let reused = |path: &str| current.contains(path) && !stale.contains(path);
+
+    // Added or removed files can change how unchanged files' imports resolve
+    let import_graph = profile.stage("imports", || {
+        if changed.iter().any(|f| !handoff.files.contains_key(&f.rel_path)) || removed > 0 {
+            scan_imports(&all_files)
+        } else {
+            let mut edges = handoff.import_edges;
+            edges.retain(|path, _| reused(path));
+            edges.extend(crate::scan::scan_imports_from(&changed, &all_files).edges);
+            ImportGraph::from_edges(edges)
+        }
+    });
+
+    let mut entry_points = handoff.entry_points;
+    profile.stage("entries", || {
+        let detector = EntryDetector::new();
+        entry_points.retain(|e| reused(&e.path));
+        entry_points.par_extend(
+            changed.par_iter().flat_map_iter(|f| entries::detect_file_entry_points(&detector, f)),
+        );
+        entry_points.sort_by(|a, b| a.path.cmp(&b.path).then(a.line.cmp(&b.line)));
+    });
+
+    let mut symbols = handoff.symbols;
+    profile.stage("symbols", || {
+        symbols.retain(|s| reused(&s.path));
+        symbols.par_extend(
+            changed.par_iter().flat_map_iter(|f| symbols::detect_file_symbols(f, &config)),
+        );
+        symbols::sort_symbols(&mut symbols);
+    });
+
+    // Root `.env*` files are outside the walk and cheap, so they are always re-read
+    let mut refs = handoff.config_refs;
+    profile.stage("config_refs", || {
+        let detector = ConfigRefDetector::new();
+        refs.retain(|r| reused(&r.path));
+        refs.par_extend(
+            changed
+                .par_iter()
+                .flat_map_iter(|f| config_refs::detect_file_config_refs(&detector, f)),
+        );
+        refs.extend(config_refs::root_dotenv_refs(&detector, &config));
+        config_refs::sort_config_refs(&mut refs);
+    });
+
+    // License files sit outside the walk too; listing their directories is cheap
+    let mut license_headers = handoff.license_headers;
+    let licenses = profile.stage("licenses", || {
+        license_headers.retain(|h| reused(&h.path));
+        license_headers.par_extend(changed.par_iter().filter_map(licenses::detect_file_license));
+        licenses::sort_headers(&mut license_headers);
+        LicenseIndex {
+            dirs: licenses::scan_license_dirs(&config, &all_files),
+            headers: license_headers,
+        }
+    });
+
+    // An edited schema can add or rename symbols, which every file may reference
+    let schemas = profile.stage("schemas", || {
+        if removed > 0 || changed.iter().any(|f| schemas::is_schema_candidate(&f.ext)) {
+            schemas::scan_schemas(&all_files)
+        } else {
+            let mut refs = handoff.schema_refs;
+            refs.retain(|r| reused(&r.path));
+            refs.extend(schemas::scan_refs(&handoff.schema_symbols, &changed));
+            schemas::sort_refs(&mut refs);
+            SchemaIndex { symbols: handoff.schema_symbols, refs }
+        }
+    });
+
+    let mut term_doc_freq = handoff.term_doc_freq;
+    profile.stage("tdf", || {
+        term_doc_freq.retain_docs(|p| reused(p));
+        crate::scan::add_term_docs(&mut term_doc_freq, &changed);
+    });
+
+    let commit_times = profile
+        .stage("recency", || crate::ranking::scan_commit_times(&config.root, &config.ranking));
+    let deps = profile.stage("deps", || scan_deps(&config));
+    let (search_files, search_modules) = profile.stage("index", || build_search_index(&manifest));
+    let module_docs = collect_module_docs(&manifest);
+    let scan_time_ms = start.elapsed().as_millis() as u64;
+    tracing::info!(
+        repo = name,
+        files = all_files.len(),
+        reparsed = changed.len(),
+        removed,
+        time_ms = scan_time_ms,
+        "Warm-started from index handoff"
+    );
+
+    let mut repo = RepoState {
+        name: name.to_string(),
+        root: root.to_path_buf(),
+        config,
+        all_files,
+        manifest,
+        module_docs,
+        entry_points,
+        symbols,
+        config_refs: refs,
+        licenses,
+        schemas,
+        commit_times,
+        deps,
+        search_files,
+        search_modules,
+        import_graph,
+        stub_cache: dashmap::DashMap::new(),
+        term_doc_freq,
+        index_memory: Default::default(),
+        scan_time_ms,
+        scan_profile: profile,
+        synced_at,
+        synced_head,
+        renames: Default::default(),
+        #[cfg(feature = "semantic")]
+        semantic_index: Arc::new(std::sync::RwLock::new(None)),
+        #[cfg(feature = "semantic")]
+        semantic_progress: Arc::new(crate::types::SemanticProgress::new()),
+    };
+    crate::memory::measure_index(&mut repo);
+    Some(repo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_hash_is_stable_fnv1a() {
+        assert_eq!(content_hash(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(content_hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_ne!(content_hash(b"fn main() {}"), content_hash(b"fn main() { }"));
+    }
+}
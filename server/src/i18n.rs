@@ -0,0 +1,175 @@
+//! Localized CLI and MCP messages backed by Fluent catalogs in `server/locales/`.
+//!
+//! The locale comes from `--lang`, then `CODESCOPE_LANG`, then the POSIX `LC_ALL` /
+//! `LC_MESSAGES` / `LANG` variables. English is the fallback both for unsupported locales
+//! and for message IDs missing from a translated catalog.
+
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource};
+use std::sync::OnceLock;
+use unic_langid::LanguageIdentifier;
+
+pub use fluent_bundle::FluentValue;
+
+/// Compiled-in catalogs, keyed by primary language subtag. `en` must come first.
+const CATALOGS: &[(&str, &str)] =
+    &[("en", include_str!("../locales/en.ftl")), ("es", include_str!("../locales/es.ftl"))];
+
+/// Environment variables consulted (in order) when no explicit locale is given.
+const LOCALE_ENV_VARS: &[&str] = &["CODESCOPE_LANG", "LC_ALL", "LC_MESSAGES", "LANG"];
+
+static LOCALIZER: OnceLock<Localizer> = OnceLock::new();
+
+struct Localizer {
+    locale: &'static str,
+    primary: Option<FluentBundle<FluentResource>>,
+    fallback: FluentBundle<FluentResource>,
+}
+
+impl Localizer {
+    fn new(requested: Option<&str>) -> Self {
+        let (en_lang, en_source) = CATALOGS[0];
+        let fallback = build_bundle(en_lang, en_source);
+        let catalog = requested
+            .and_then(|r| CATALOGS.iter().find(|(lang, _)| *lang == primary_subtag(r)))
+            .filter(|(lang, _)| *lang != en_lang);
+        match catalog {
+            Some((lang, source)) => {
+                Self { locale: lang, primary: Some(build_bundle(lang, source)), fallback }
+            }
+            None => Self { locale: en_lang, primary: None, fallback },
+        }
+    }
+
+    fn format(&self, id: &str, args: &[(&str, FluentValue<'_>)]) -> String {
+        let mut fargs = FluentArgs::new();
+        for (key, value) in args {
+            fargs.set(*key, value.clone());
+        }
+        self.primary
+            .as_ref()
+            .and_then(|b| format_message(b, id, &fargs))
+            .or_else(|| format_message(&self.fallback, id, &fargs))
+            .unwrap_or_else(|| id.to_string())
+    }
+}
+
+fn build_bundle(lang: &str, source: &str) -> FluentBundle<FluentResource> {
+    let langid: LanguageIdentifier = lang.parse().expect("catalog language tag is valid");
+    let mut bundle = FluentBundle::new_concurrent(vec![langid]);
+    // Terminal output, not bidi-mixed UI — isolation marks would show up as garbage.
+    bundle.set_use_isolating(false);
+    let resource = FluentResource::try_new(source.to_string()).unwrap_or_else(|(res, _)| res);
+    let _ = bundle.add_resource(resource);
+    bundle
+}
+
+fn format_message(
+    bundle: &FluentBundle<FluentResource>,
+    id: &str,
+    args: &FluentArgs,
+) -> Option<String> {
+    let pattern = bundle.get_message(id)?.value()?;
+    let mut errors = Vec::new();
+    Some(bundle.format_pattern(pattern, Some(args), &mut errors).into_owned())
+}
+
+/// Primary language subtag of a locale string: `es_ES.UTF-8` → `es`, `pt-BR` → `pt`.
+fn primary_subtag(locale: &str) -> String {
+    locale.split(['_', '-', '.', '@']).next().unwrap_or("").to_ascii_lowercase()
+}
+
+/// First locale from [`LOCALE_ENV_VARS`] that is set and is not `C`/`POSIX`.
+fn locale_from_env() -> Option<String> {
+    LOCALE_ENV_VARS
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|v| !v.is_empty() && v != "C" && v != "POSIX" && !v.starts_with("C."))
+}
+
+/// Select the message locale. Call once at startup; later calls are ignored.
+///
+/// `explicit` (from `--lang`) wins over the environment. Without a call, the first
+/// message lookup initializes from the environment.
+pub fn init(explicit: Option<&str>) {
+    let requested = explicit.map(str::to_string).or_else(locale_from_env);
+    let _ = LOCALIZER.set(Localizer::new(requested.as_deref()));
+}
+
+/// Language subtag of the active catalog (`en` when falling back).
+pub fn current_locale() -> &'static str {
+    localizer().locale
+}
+
+fn localizer() -> &'static Localizer {
+    LOCALIZER.get_or_init(|| Localizer::new(locale_from_env().as_deref()))
+}
+
+/// Format message `id` with named arguments. Unknown IDs render as the ID itself.
+pub fn message(id: &str, args: &[(&str, FluentValue<'_>)]) -> String {
+    localizer().format(id, args)
+}
+
+/// Look up a localized message: `tr!("doctor-title")` or `tr!("init-validated", count = n)`.
+#[macro_export]
+macro_rules! tr {
+    ($id:literal) => {
+        $crate::i18n::message($id, &[])
+    };
+    ($id:literal, $($key:ident = $val:expr),+ $(,)?) => {
+        $crate::i18n::message(
+            $id,
+            &[$((stringify!($key), $crate::i18n::FluentValue::from($val))),+],
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn catalogs_parse_and_translations_are_known_ids() {
+        let en = FluentResource::try_new(CATALOGS[0].1.to_string()).expect("en.ftl parses");
+        let en_ids: Vec<&str> = en
+            .entries()
+            .filter_map(|e| match e {
+                fluent_syntax::ast::Entry::Message(m) => Some(m.id.name),
+                _ => None,
+            })
+            .collect();
+        for (lang, source) in &CATALOGS[1..] {
+            let res = FluentResource::try_new(source.to_string())
+                .unwrap_or_else(|(_, errs)| panic!("{lang}.ftl has errors: {errs:?}"));
+            for entry in res.entries() {
+                if let fluent_syntax::ast::Entry::Message(m) = entry {
+                    assert!(
+                        en_ids.contains(&m.id.name),
+                        "{lang}.ftl defines unknown id {}",
+                        m.id.name
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn locale_selection_and_fallback() {
+        let es = Localizer::new(Some("es_ES.UTF-8"));
+        assert_eq!(es.locale, "es");
+        assert_eq!(es.format("doctor-result-pass", &[]), "Resultado: TODO CORRECTO");
+        assert_eq!(
+            es.format("init-validated", &[("count", FluentValue::from(42))]),
+            "Validado: 42 archivos fuente encontrados"
+        );
+        assert_eq!(
+            es.format("repo-spec-invalid", &[("spec", FluentValue::from("api"))]),
+            "se esperaba NAME=PATH, se recibió 'api'"
+        );
+
+        let unknown = Localizer::new(Some("xx-YY"));
+        assert_eq!(unknown.locale, "en");
+        assert_eq!(unknown.format("doctor-result-pass", &[]), "Result: ALL PASS");
+        assert_eq!(unknown.format("no-such-message", &[]), "no-such-message");
+    }
+}
@@ -0,0 +1,197 @@
+//! Import impact analysis: everything that depends on a set of files, directly or
+//! transitively, found by a breadth-first walk up the `imported_by` graph.
+//!
+//! Shared by `cs_imports transitive=true`, which lists dependents by depth, and
+//! `/api/impact`, which returns them as a tree: each dependent sits under the file it was
+//! first reached from, so following a branch down shows one chain of imports that carries
+//! a change outward. Files in other repos that import a selected file through
+//! `cross_repo_edges` are included as `[repo] path`.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+use crate::types::{ImportConfidence, ImportEdge, RepoState, ServerState};
+
+/// Files a `cs_imports` path selects: a glob over indexed paths, an indexed file, every
+/// file under a directory, or every file of a module (manifest category, with submodules).
+/// An unmatched plain path is returned as is, so the caller reports it has no edges.
+pub fn import_targets(repo: &RepoState, spec: &str) -> Result<Vec<String>, String> {
+    if crate::scan::is_glob(spec) {
+        let glob = globset::GlobBuilder::new(spec.trim_start_matches("./"))
+            .literal_separator(true)
+            .build()
+            .map_err(|e| format!("Invalid glob '{spec}': {e}"))?
+            .compile_matcher();
+        let files: Vec<String> = repo
+            .all_files
+            .iter()
+            .filter(|f| glob.is_match(&f.rel_path))
+            .map(|f| f.rel_path.clone())
+            .collect();
+        if files.is_empty() {
+            return Err(format!("No indexed files match '{spec}'"));
+        }
+        return Ok(files);
+    }
+    if repo.all_files.iter().any(|f| f.rel_path == spec) {
+        return Ok(vec![spec.to_string()]);
+    }
+    let dir = format!("{}/", spec.trim_end_matches('/'));
+    let files: Vec<String> = repo
+        .all_files
+        .iter()
+        .filter(|f| f.rel_path.starts_with(&dir))
+        .map(|f| f.rel_path.clone())
+        .collect();
+    if !files.is_empty() {
+        return Ok(files);
+    }
+    let child_prefix = format!("{spec} > ");
+    let mut files: Vec<String> = repo
+        .manifest
+        .iter()
+        .filter(|(cat, _)| *cat == spec || cat.starts_with(&child_prefix))
+        .flat_map(|(_, entries)| entries.iter().map(|e| e.path.clone()))
+        .collect();
+    if files.is_empty() {
+        return Ok(vec![spec.to_string()]);
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Dependents of a set of files, by depth.
+pub struct Impact {
+    /// The selected files.
+    pub targets: Vec<String>,
+    /// Depth → dependents first reached at that depth, strongest first.
+    pub by_depth: BTreeMap<usize, Vec<String>>,
+    /// Edge each dependent was first reached through. For direct dependents of several
+    /// selected files, the merged edges to all of them.
+    pub via: HashMap<String, ImportEdge>,
+    /// File each dependent was first reached from.
+    pub parent: HashMap<String, String>,
+}
+
+impl Impact {
+    /// Number of dependents at all depths.
+    pub fn total(&self) -> usize {
+        self.by_depth.values().map(|v| v.len()).sum()
+    }
+
+    /// Deepest level reached; 0 when nothing depends on the targets.
+    pub fn max_depth(&self) -> usize {
+        self.by_depth.keys().max().copied().unwrap_or(0)
+    }
+
+    /// Edge a dependent was reached through (the default edge for cross-repo importers).
+    pub fn strength(&self, file: &str) -> ImportEdge {
+        self.via.get(file).copied().unwrap_or_default()
+    }
+
+    /// The dependents as a tree under each selected file.
+    pub fn tree(&self) -> Vec<ImpactNode> {
+        let mut children: HashMap<&str, Vec<(usize, &str)>> = HashMap::new();
+        for (&depth, files) in &self.by_depth {
+            for f in files {
+                if let Some(parent) = self.parent.get(f) {
+                    children.entry(parent.as_str()).or_default().push((depth, f.as_str()));
+                }
+            }
+        }
+        self.targets.iter().map(|t| self.node(t, 0, &children)).collect()
+    }
+
+    fn node(
+        &self,
+        path: &str,
+        depth: usize,
+        children: &HashMap<&str, Vec<(usize, &str)>>,
+    ) -> ImpactNode {
+        let edge = (depth > 0).then(|| self.strength(path));
+        let dependents = children.get(path).map(Vec::as_slice).unwrap_or_default();
+        ImpactNode {
+            path: path.to_string(),
+            depth,
+            symbols: edge.map_or(0, |e| e.symbols),
+            type_only: edge.is_some_and(|e| e.type_only),
+            confidence: edge.map(|e| e.confidence),
+            dependents: dependents.iter().map(|&(d, f)| self.node(f, d, children)).collect(),
+        }
+    }
+}
+
+/// A file in the impact tree, with the files that reach it first listed under it.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ImpactNode {
+    pub path: String,
+    /// 0 for a selected file, 1 for its direct dependents, and so on.
+    pub depth: usize,
+    /// Named symbols imported from the parent node.
+    pub symbols: u32,
+    pub type_only: bool,
+    /// Confidence of the import from the parent node; `None` for a selected file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<ImportConfidence>,
+    pub dependents: Vec<ImpactNode>,
+}
+
+/// Walk up to `max_depth` levels of importers of `targets`.
+pub fn analyze(
+    state: &ServerState,
+    repo: &RepoState,
+    targets: &[String],
+    max_depth: usize,
+) -> Impact {
+    let mut visited: HashSet<String> = HashSet::new();
+    let mut queue: VecDeque<(String, usize)> = VecDeque::new();
+    let mut by_depth: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+    let mut via: HashMap<String, ImportEdge> = HashMap::new();
+    let mut parent: HashMap<String, String> = HashMap::new();
+
+    for target in targets {
+        visited.insert(target.clone());
+        queue.push_back((target.clone(), 0));
+    }
+
+    while let Some((current, depth)) = queue.pop_front() {
+        if depth > 0 {
+            by_depth.entry(depth).or_default().push(current.clone());
+        }
+        if depth >= max_depth {
+            continue;
+        }
+        if let Some(dependents) = repo.import_graph.imported_by.get(&current) {
+            for dep in dependents {
+                let edge = repo.import_graph.edge(dep, &current);
+                if visited.insert(dep.clone()) {
+                    via.insert(dep.clone(), edge);
+                    parent.insert(dep.clone(), current.clone());
+                    queue.push_back((dep.clone(), depth + 1));
+                } else if depth == 0 {
+                    // A direct dependent of several selected files
+                    if let Some(first) = via.get_mut(dep) {
+                        first.merge(edge);
+                    }
+                }
+            }
+        }
+        for edge in &state.cross_repo_edges {
+            if edge.to_repo == repo.name && edge.to_file == current {
+                let key = format!("[{}] {}", edge.from_repo, edge.from_file);
+                if visited.insert(key.clone()) {
+                    parent.insert(key.clone(), current.clone());
+                    by_depth.entry(depth + 1).or_default().push(key);
+                }
+            }
+        }
+    }
+
+    // Strongest dependents first within each depth; type-only imports last
+    let strength = |f: &String| via.get(f).copied().unwrap_or_default();
+    for files in by_depth.values_mut() {
+        files.sort_by(|a, b| ImportEdge::strength_order(&strength(a), &strength(b)));
+    }
+    Impact { targets: targets.to_vec(), by_depth, via, parent }
+}
@@ -0,0 +1,544 @@
+//! Background initial indexing.
+//!
+//! The MCP server answers `initialize` as soon as it starts. Registered repos are scanned
+//! one at a time in priority order and each is published as a new index generation the
+//! moment its scan finishes. A tool call that needs a repo still in the queue gets an
+//! "indexing, N% done" response, and that repo moves to the front of the queue.
+//! `cs_status` answers throughout, showing the stage and file counts of the running scan.
+//!
+//! Repos registered with `lazy = true` are left out of the startup queue. They stay
+//! addressable, and the first tool call that targets one starts its scan (see
+//! [`serve_lazy`]); until then they do not count towards progress or partial-results notes.
+//!
+//! A repo whose root is missing (an unmounted drive, a deleted checkout) is kept registered
+//! but unavailable: it is not scanned, tools that target it say why, and
+//! [`watch_missing_roots`] indexes it as soon as the path reappears. An indexed repo whose
+//! root disappears is unpublished the same way and re-queued as it was registered: with
+//! its profile, lazy flag, and place in the queue.
+//!
+//! Each pending repo is scanned by whichever thread claims it first, so the startup queue,
+//! [`serve_lazy`], and [`watch_missing_roots`] never scan the same repo twice.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::types::{ScanProgress, ServerState, SharedState};
+
+/// Repo names listed in the partial-results note before it is truncated.
+const MAX_LISTED_PENDING: usize = 5;
+
+/// How often [`serve_lazy`] checks for tool calls targeting a lazy repo.
+const LAZY_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// How often [`watch_missing_roots`] checks whether repo roots are present.
+const ROOT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A registered repo: from `--repo`, `repos.toml`, or the working directory.
+#[derive(Clone, Debug)]
+pub struct RepoSpec {
+    pub name: String,
+    pub root: PathBuf,
+    /// `priority` in `repos.toml`; higher scans earlier. Default 0.
+    pub priority: i64,
+    /// `lazy = true` in `repos.toml`: scan on first use instead of at startup.
+    pub lazy: bool,
+    /// `[profile.<name>]` of the repo's `.codescope.toml` to scan with: `profile` in
+    /// `repos.toml`, otherwise `--profile`.
+    pub profile: Option<String>,
+}
+
+impl RepoSpec {
+    pub fn new(name: &str, root: PathBuf) -> Self {
+        Self { name: name.to_string(), root, priority: 0, lazy: false, profile: None }
+    }
+}
+
+/// A registered repo whose initial scan has not been published yet.
+pub struct PendingRepo {
+    pub name: String,
+    pub root: PathBuf,
+    /// Position in the startup order; lower scans first.
+    pub priority: usize,
+    /// Scanned only once a tool targets it.
+    pub lazy: bool,
+    /// Scan profile, from [`RepoSpec::profile`].
+    pub profile: Option<String>,
+    /// A tool asked for this repo, so it jumps the queue.
+    requested: AtomicBool,
+    /// The root directory is missing; not scanned until it reappears.
+    missing: AtomicBool,
+    /// A thread has started scanning this repo.
+    claimed: AtomicBool,
+    /// Re-queued after its root disappeared, so [`check_roots`] scans it once it is queued
+    /// again, even after the startup queue and [`serve_lazy`] have finished.
+    recovered: bool,
+    /// Stage and file counts of the scan, once it starts; shown by `cs_status`.
+    pub progress: Arc<ScanProgress>,
+}
+
+impl PendingRepo {
+    pub fn new(name: &str, root: &Path, priority: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            root: root.to_path_buf(),
+            priority,
+            lazy: false,
+            profile: None,
+            requested: AtomicBool::new(false),
+            missing: AtomicBool::new(false),
+            claimed: AtomicBool::new(false),
+            recovered: false,
+            progress: Arc::new(ScanProgress::new()),
+        }
+    }
+
+    /// Pending entry for `spec` at `position` in the scan order.
+    fn from_spec(spec: &RepoSpec, position: usize) -> Self {
+        let mut pending = Self::new(&spec.name, &spec.root, position);
+        pending.lazy = spec.lazy;
+        pending.profile = spec.profile.clone();
+        pending
+    }
+
+    /// Move this repo to the front of the queue.
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::Relaxed)
+    }
+
+    pub fn is_missing(&self) -> bool {
+        self.missing.load(Ordering::Relaxed)
+    }
+
+    pub fn set_missing(&self, missing: bool) {
+        self.missing.store(missing, Ordering::Relaxed);
+    }
+
+    /// Take the scan of this repo for the calling thread. False when another thread
+    /// already has it.
+    fn claim(&self) -> bool {
+        !self.claimed.swap(true, Ordering::AcqRel)
+    }
+
+    fn is_claimed(&self) -> bool {
+        self.claimed.load(Ordering::Acquire)
+    }
+
+    /// Whether the repo is queued for scanning: when its root exists, and unless it is lazy
+    /// and nothing has asked for it yet.
+    pub fn is_queued(&self) -> bool {
+        !self.is_missing() && (!self.lazy || self.is_requested())
+    }
+}
+
+/// Startup scan order: the repo containing `cwd` first (usually the one the agent is
+/// working in), then by descending `priority`, then the order the repos were registered in.
+pub fn priority_order(mut specs: Vec<RepoSpec>, cwd: Option<&Path>) -> Vec<RepoSpec> {
+    specs.sort_by_key(|s| {
+        (!cwd.is_some_and(|cwd| cwd.starts_with(&s.root)), std::cmp::Reverse(s.priority))
+    });
+    specs
+}
+
+/// Pending entries for repos in scan order.
+pub fn pending_repos(specs: &[RepoSpec]) -> BTreeMap<String, Arc<PendingRepo>> {
+    specs
+        .iter()
+        .enumerate()
+        .map(|(i, spec)| {
+            let pending = PendingRepo::from_spec(spec, i);
+            if !spec.root.is_dir() {
+                tracing::warn!(
+                    repo = spec.name.as_str(),
+                    root = %spec.root.display(),
+                    "Repository root not found; serving it as unavailable until it appears"
+                );
+                pending.set_missing(true);
+            }
+            (spec.name.clone(), Arc::new(pending))
+        })
+        .collect()
+}
+
+/// Pending repos that are queued for scanning ([`PendingRepo::is_queued`]).
+fn queued(state: &ServerState) -> impl Iterator<Item = &PendingRepo> {
+    state.pending.values().map(|p| &**p).filter(|p| p.is_queued())
+}
+
+/// Share of queued repos that are indexed, as a whole percentage. Lazy repos nothing has
+/// asked for are left out.
+pub fn percent_done(state: &ServerState) -> usize {
+    let total = state.repos.len() + queued(state).count();
+    (state.repos.len() * 100).checked_div(total).unwrap_or(100)
+}
+
+/// Next startup repo to scan: a requested one first, otherwise the highest priority.
+/// Lazy repos are left to [`serve_lazy`].
+fn next_pending(state: &ServerState) -> Option<Arc<PendingRepo>> {
+    state
+        .pending
+        .values()
+        .filter(|p| !p.lazy && !p.is_missing() && !p.is_claimed())
+        .min_by_key(|p| (!p.is_requested(), p.priority))
+        .cloned()
+}
+
+/// Scan one pending repo and publish it, unless another thread has claimed it. Returns
+/// whether this call indexed it.
+fn index_one(
+    shared: &SharedState,
+    next: &PendingRepo,
+    enable_semantic: bool,
+    warm_start: bool,
+) -> bool {
+    if !next.claim() {
+        return false;
+    }
+    let tok = shared.snapshot().tokenizer.clone();
+    let repo = warm_start
+        .then(|| crate::handoff::take(&next.name, &next.root, next.profile.as_deref()))
+        .flatten()
+        .unwrap_or_else(|| {
+            crate::scan_repo_with_progress(
+                &next.name,
+                &next.root,
+                &tok,
+                enable_semantic,
+                next.profile.as_deref(),
+                &next.progress,
+            )
+        });
+    tracing::info!(
+        repo = next.name.as_str(),
+        files = repo.all_files.len(),
+        time_ms = repo.scan_time_ms,
+        "Repo indexed"
+    );
+    if !crate::locks::is_writer(&next.root) {
+        tracing::info!(
+            repo = next.name.as_str(),
+            pid = crate::locks::writer_pid(&next.root),
+            "Another codescope server writes this repo's caches; using them read-only"
+        );
+    }
+    shared.update(|state| {
+        state.pending.remove(&next.name);
+        state.repos.insert(next.name.clone(), Arc::new(repo));
+        state.cross_repo_edges = crate::scan::resolve_cross_repo_imports(&state.repos);
+    });
+    if let Some(repo) = shared.snapshot().repos.get(&next.name) {
+        crate::memory::enforce_memory_limit(repo);
+    }
+    true
+}
+
+/// Scan every pending startup repo, publishing each as soon as it is ready. Returns once
+/// only lazy repos are left.
+///
+/// With `warm_start`, a repo with an index handoff from a previous process is loaded from
+/// it (see [`crate::handoff`]) and only falls back to a full scan when that fails.
+pub fn index_pending(shared: &SharedState, enable_semantic: bool, warm_start: bool) {
+    while let Some(next) = next_pending(&shared.snapshot()) {
+        index_one(shared, &next, enable_semantic, warm_start);
+    }
+}
+
+/// Scan lazy repos as tool calls request them. Returns once no lazy repo is pending; run it
+/// on its own thread alongside [`index_pending`]. A lazily scanned repo also gets its
+/// semantic index, built on this thread after it is published.
+pub fn serve_lazy(shared: &SharedState, enable_semantic: bool, warm_start: bool) {
+    loop {
+        let snapshot = shared.snapshot();
+        if !snapshot.pending.values().any(|p| p.lazy) {
+            break;
+        }
+        let next =
+            snapshot.pending.values().find(|p| p.lazy && p.is_queued() && !p.is_claimed()).cloned();
+        drop(snapshot);
+        let Some(next) = next else {
+            std::thread::sleep(LAZY_POLL_INTERVAL);
+            continue;
+        };
+        tracing::info!(repo = next.name.as_str(), "Lazy repo requested; indexing");
+        if !index_one(shared, &next, enable_semantic, warm_start) {
+            continue;
+        }
+        #[cfg(feature = "semantic")]
+        if enable_semantic {
+            build_semantic_index_for(shared, &next.name);
+        }
+    }
+}
+
+/// Keep repo availability in step with the filesystem: a pending repo whose root reappears
+/// is watched again and indexed (or, if lazy, once a tool asks for it), and an indexed repo
+/// whose root disappears is unpublished and marked missing. Runs until the process exits;
+/// start it on its own thread.
+pub fn watch_missing_roots(shared: &SharedState, enable_semantic: bool, warm_start: bool) {
+    loop {
+        std::thread::sleep(ROOT_POLL_INTERVAL);
+        check_roots(shared, enable_semantic, warm_start);
+    }
+}
+
+/// One pass of [`watch_missing_roots`].
+pub fn check_roots(shared: &SharedState, enable_semantic: bool, warm_start: bool) {
+    let snapshot = shared.snapshot();
+    let vanished: Vec<(String, PathBuf, Option<String>)> = snapshot
+        .repos
+        .values()
+        .filter(|r| !r.root.is_dir())
+        .map(|r| (r.name.clone(), r.root.clone(), r.config.profile.clone()))
+        .collect();
+    let returned: Vec<Arc<PendingRepo>> =
+        snapshot.pending.values().filter(|p| p.is_missing() && p.root.is_dir()).cloned().collect();
+    drop(snapshot);
+
+    if !vanished.is_empty() {
+        shared.update(|state| {
+            for (name, root, profile) in vanished {
+                tracing::warn!(
+                    repo = name.as_str(),
+                    root = %root.display(),
+                    "Repository root disappeared; marking it unavailable"
+                );
+                state.repos.remove(&name);
+                // Repos added at runtime have no registration; they keep their scan profile
+                let registered = state.registered.iter().enumerate().find(|(_, s)| s.name == name);
+                let mut pending = match registered {
+                    Some((position, spec)) => PendingRepo::from_spec(spec, position),
+                    None => {
+                        let mut pending = PendingRepo::new(&name, &root, state.registered.len());
+                        pending.profile = profile;
+                        pending
+                    }
+                };
+                pending.recovered = true;
+                pending.set_missing(true);
+                state.pending.insert(name, Arc::new(pending));
+            }
+            state.cross_repo_edges = crate::scan::resolve_cross_repo_imports(&state.repos);
+        });
+    }
+    for pending in &returned {
+        tracing::info!(repo = pending.name.as_str(), "Repository root is back");
+        crate::watch::rewatch(&shared.snapshot(), &pending.root);
+        pending.set_missing(false);
+    }
+
+    // Returned startup repos, and re-queued ones the startup queue and serve_lazy may be
+    // done with; lazy ones wait until a tool asks for them
+    let snapshot = shared.snapshot();
+    let ready: Vec<Arc<PendingRepo>> = snapshot
+        .pending
+        .values()
+        .filter(|p| p.is_queued() && !p.is_claimed())
+        .filter(|p| p.recovered || (!p.lazy && returned.iter().any(|r| Arc::ptr_eq(r, p))))
+        .cloned()
+        .collect();
+    drop(snapshot);
+    for pending in ready {
+        if !index_one(shared, &pending, enable_semantic, warm_start) {
+            continue;
+        }
+        #[cfg(feature = "semantic")]
+        if enable_semantic {
+            build_semantic_index_for(shared, &pending.name);
+        }
+    }
+}
+
+/// Build and install the semantic index of one published repo.
+#[cfg(feature = "semantic")]
+fn build_semantic_index_for(shared: &SharedState, name: &str) {
+    let snapshot = shared.snapshot();
+    let Some(repo) = snapshot.repos.get(name).cloned() else {
+        return;
+    };
+    let model = snapshot.semantic_model.clone();
+    drop(snapshot);
+    if let Some(idx) = crate::semantic::build_semantic_index(
+        &repo.all_files,
+        &repo.config.noise,
+        model.as_deref(),
+        &repo.semantic_progress,
+        &repo.root,
+    ) {
+        tracing::info!(repo = name, chunks = idx.chunk_meta.len(), "Semantic index ready");
+        *repo.semantic_index.write().unwrap() = Some(idx);
+        crate::memory::enforce_memory_limit(&repo);
+    }
+}
+
+/// Scan stages of every indexed repo in Chrome trace event format, which speedscope,
+/// Perfetto, and `chrome://tracing` render as a flame chart. Each repo gets its own track,
+/// timed from the start of its scan.
+pub fn scan_profile_trace(state: &ServerState) -> serde_json::Value {
+    let mut events = Vec::new();
+    for (tid, repo) in state.repos.values().enumerate() {
+        events.push(serde_json::json!({
+            "name": "thread_name", "ph": "M", "pid": 1, "tid": tid,
+            "args": { "name": repo.name },
+        }));
+        events.push(serde_json::json!({
+            "name": "scan", "cat": "scan", "ph": "X", "pid": 1, "tid": tid,
+            "ts": 0, "dur": repo.scan_profile.total_us(),
+            "args": { "repo": repo.name, "files": repo.all_files.len() },
+        }));
+        for stage in &repo.scan_profile.stages {
+            events.push(serde_json::json!({
+                "name": stage.name, "cat": "scan", "ph": "X", "pid": 1, "tid": tid,
+                "ts": stage.start_us, "dur": stage.duration_us,
+                "args": { "repo": repo.name },
+            }));
+        }
+    }
+    serde_json::json!({ "traceEvents": events, "displayTimeUnit": "ms" })
+}
+
+/// Write [`scan_profile_trace`] to `path` (`--profile-scan`).
+pub fn write_scan_profile(state: &ServerState, path: &Path) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(&scan_profile_trace(state))
+        .map_err(|e| format!("Failed to serialize scan profile: {}", e))?;
+    crate::atomic::write(path, json)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Response for a tool call that needs a repo that is still indexing or whose root is
+/// missing, or `None` when the call can run. Requested repos are moved to the front of the
+/// queue.
+///
+/// The target is the `repo` argument, a `[repo] ` prefix on `path`/`paths`, or the default
+/// repo when nothing selects one.
+pub fn not_ready_message(state: &ServerState, args: &serde_json::Value) -> Option<String> {
+    if state.pending.is_empty() {
+        return None;
+    }
+    let mut targets: Vec<&str> = Vec::new();
+    if let Some(name) = args["repo"].as_str() {
+        targets.push(name);
+    }
+    let paths = args["path"]
+        .as_str()
+        .into_iter()
+        .chain(args["paths"].as_array().into_iter().flatten().filter_map(|v| v.as_str()));
+    targets.extend(paths.filter_map(|p| crate::paths::split_repo_prefix(p).0));
+
+    let ready = state.repos.len();
+    if targets.is_empty() && state.repos.is_empty() {
+        if queued(state).next().is_some() {
+            let total = ready + queued(state).count();
+            let pct = percent_done(state);
+            return Some(format!(
+                "Indexing repositories, {pct}% done ({ready} of {total} ready). Retry shortly."
+            ));
+        }
+        if state.default_repo.is_none() {
+            let (missing, lazy): (Vec<&PendingRepo>, Vec<&PendingRepo>) =
+                state.pending.values().map(|p| &**p).partition(|p| p.is_missing());
+            if lazy.is_empty() {
+                return Some(unavailable_message(&missing));
+            }
+            let lazy: Vec<&str> = lazy.iter().map(|p| p.name.as_str()).collect();
+            return Some(format!(
+                "No repository is indexed yet. Lazy repos ({}) are scanned the first time a \
+                 tool names one with 'repo'.",
+                lazy.join(", ")
+            ));
+        }
+    }
+    if targets.is_empty() {
+        targets.extend(state.default_repo.as_deref());
+    }
+
+    let waiting: Vec<&PendingRepo> =
+        targets.iter().filter_map(|name| state.pending.get(*name)).map(|p| &**p).collect();
+    if waiting.is_empty() {
+        return None;
+    }
+    let missing: Vec<&PendingRepo> = waiting.iter().copied().filter(|p| p.is_missing()).collect();
+    if !missing.is_empty() {
+        return Some(unavailable_message(&missing));
+    }
+    for p in &waiting {
+        p.request();
+    }
+    let total = ready + queued(state).count();
+    let pct = percent_done(state);
+    let names: Vec<&str> = waiting.iter().map(|p| p.name.as_str()).collect();
+    let (subject, verb) = if names.len() == 1 { ("Repo", "is") } else { ("Repos", "are") };
+    Some(format!(
+        "{subject} '{}' {verb} still indexing ({ready} of {total} repos ready, {pct}% done) \
+         and moved to the front of the queue; retry shortly.",
+        names.join("', '")
+    ))
+}
+
+/// Response for a tool call targeting repos whose root is missing.
+fn unavailable_message(missing: &[&PendingRepo]) -> String {
+    let roots: Vec<String> =
+        missing.iter().map(|p| format!("'{}' ({})", p.name, p.root.display())).collect();
+    let (subject, verb) = if missing.len() == 1 { ("Repo", "is") } else { ("Repos", "are") };
+    format!(
+        "{subject} {} {verb} unavailable: the root directory was not found. Other repos are \
+         still served, and indexing resumes automatically once the path reappears.",
+        roots.join(", ")
+    )
+}
+
+/// Note appended to results computed while other repos are still indexing.
+pub fn partial_results_note(state: &ServerState) -> Option<String> {
+    let mut waiting: Vec<&PendingRepo> = queued(state).collect();
+    if waiting.is_empty() {
+        return None;
+    }
+    waiting.sort_by_key(|p| p.priority);
+    let mut names: Vec<&str> =
+        waiting.iter().take(MAX_LISTED_PENDING).map(|p| p.name.as_str()).collect();
+    let more = waiting.len().saturating_sub(MAX_LISTED_PENDING);
+    let more_label = format!("+{more} more");
+    if more > 0 {
+        names.push(&more_label);
+    }
+    Some(format!(
+        "\n\n[indexing] {} of {} repos ready ({}% done); not yet covered: {}.",
+        state.repos.len(),
+        state.repos.len() + state.pending.len(),
+        percent_done(state),
+        names.join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repo_containing_cwd_is_indexed_first() {
+        let specs = vec![
+            RepoSpec::new("api", PathBuf::from("/src/api")),
+            RepoSpec::new("web", PathBuf::from("/src/web")),
+            RepoSpec::new("docs", PathBuf::from("/src/docs")),
+        ];
+        let ordered = priority_order(specs.clone(), Some(Path::new("/src/web/components")));
+        let names: Vec<&str> = ordered.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["web", "api", "docs"]);
+
+        let ordered = priority_order(specs.clone(), None);
+        let names: Vec<&str> = ordered.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["api", "web", "docs"]);
+
+        let mut specs = specs;
+        specs[2].priority = 5;
+        specs[0].priority = -1;
+        let ordered = priority_order(specs, Some(Path::new("/src/web")));
+        let names: Vec<&str> = ordered.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["web", "docs", "api"]);
+    }
+}
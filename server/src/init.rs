@@ -1,8 +1,9 @@
-//! CLI subcommands `init` and `doctor`.
+//! CLI subcommands `init`, `doctor`, and `check`.
 //!
 //! `init` auto-detects 8+ project ecosystems (Rust, Node.js, Go, Python, C/C++,
 //! .NET, Unreal Engine, pnpm/uv workspaces) and generates `.codescope.toml` and
-//! `.mcp.json` config files. `doctor` diagnoses setup issues.
+//! `.mcp.json` config files. `doctor` diagnoses setup issues. `check` enforces
+//! `[boundaries]` layering rules in CI.
 
 use std::collections::{BTreeSet, HashSet};
 use std::path::{Path, PathBuf};
@@ -837,7 +838,7 @@ fn write_or_merge_mcp_json(root: &Path) -> Result<(), String> {
         // Check if codescope already exists
         if let Some(servers) = data.get("mcpServers").and_then(|v| v.as_object()) {
             if servers.contains_key("codescope") {
-                eprintln!("  codescope already configured in .mcp.json");
+                eprintln!("  {}", crate::tr!("init-mcp-already-configured"));
                 return Ok(());
             }
         }
@@ -855,9 +856,9 @@ fn write_or_merge_mcp_json(root: &Path) -> Result<(), String> {
 
         let output = serde_json::to_string_pretty(&data)
             .map_err(|e| format!("Failed to serialize .mcp.json: {}", e))?;
-        std::fs::write(&mcp_path, format!("{}\n", output))
+        crate::atomic::write(&mcp_path, format!("{}\n", output))
             .map_err(|e| format!("Failed to write {}: {}", mcp_path.display(), e))?;
-        eprintln!("  Added codescope to existing .mcp.json");
+        eprintln!("  {}", crate::tr!("init-mcp-merged"));
     } else {
         let data = serde_json::json!({
             "mcpServers": {
@@ -866,9 +867,9 @@ fn write_or_merge_mcp_json(root: &Path) -> Result<(), String> {
         });
         let output = serde_json::to_string_pretty(&data)
             .map_err(|e| format!("Failed to serialize .mcp.json: {}", e))?;
-        std::fs::write(&mcp_path, format!("{}\n", output))
+        crate::atomic::write(&mcp_path, format!("{}\n", output))
             .map_err(|e| format!("Failed to write {}: {}", mcp_path.display(), e))?;
-        eprintln!("  Created .mcp.json");
+        eprintln!("  {}", crate::tr!("init-mcp-created"));
     }
 
     Ok(())
@@ -880,8 +881,8 @@ fn write_or_merge_mcp_json(root: &Path) -> Result<(), String> {
 
 fn merge_global_repos_toml(root: &Path) -> Result<(), String> {
     let repo_name = root.file_name().and_then(|n| n.to_str()).unwrap_or("default");
-    crate::merge_global_repos_toml(repo_name, root)?;
-    eprintln!("  Added '{}' to ~/.codescope/repos.toml", repo_name);
+    crate::merge_global_repos_toml(repo_name, root, None)?;
+    eprintln!("  {}", crate::tr!("init-global-added", name = repo_name));
     Ok(())
 }
 
@@ -891,6 +892,9 @@ fn merge_global_repos_toml(root: &Path) -> Result<(), String> {
 
 /// Auto-detect project ecosystem and generate `.codescope.toml` + `.mcp.json` config files.
 pub fn run_init(args: &[String]) -> i32 {
+    if let Some(org_file) = args.iter().find_map(|a| a.strip_prefix("--org-file=")) {
+        return run_org_init(Path::new(org_file), args);
+    }
     let global = args.iter().any(|a| a == "--global");
     #[cfg(feature = "semantic")]
     let build_semantic = args.iter().any(|a| a == "--semantic");
@@ -904,64 +908,86 @@ pub fn run_init(args: &[String]) -> i32 {
     let root = match path_arg {
         Some(p) => PathBuf::from(p),
         None => std::env::current_dir().unwrap_or_else(|e| {
-            eprintln!("Error: Could not determine current directory: {}", e);
+            eprintln!(
+                "{}: {}",
+                crate::tr!("error-prefix"),
+                crate::tr!("cwd-unknown", error = e.to_string())
+            );
             std::process::exit(1);
         }),
     };
 
     let root = root.canonicalize().unwrap_or_else(|e| {
-        eprintln!("Error: Path '{}' not found: {}", root.display(), e);
+        eprintln!(
+            "{}: {}",
+            crate::tr!("error-prefix"),
+            crate::tr!("path-not-found", path = root.display().to_string(), error = e.to_string())
+        );
         std::process::exit(1);
     });
 
     let version = env!("CARGO_PKG_VERSION");
-    eprintln!("codescope {} init", version);
-    eprintln!("  Project root: {}", root.display());
+    eprintln!("{}", crate::tr!("init-title", version = version));
+    eprintln!("  {}", crate::tr!("init-project-root", path = root.display().to_string()));
 
     // Detect project ecosystems and workspace structure
     let detection = detect_project(&root);
 
     // Report what was detected
     if detection.ecosystems.is_empty() {
-        eprintln!("  Detected: no recognized project type");
-        eprintln!("  Will scan all files from project root");
+        eprintln!("  {}", crate::tr!("init-detected-none"));
+        eprintln!("  {}", crate::tr!("init-scan-everything"));
     } else {
         let labels: Vec<&str> = detection.ecosystems.iter().map(|e| e.label()).collect();
         let type_str = labels.join(" + ");
         if let Some(ref info) = detection.workspace_info {
-            eprintln!("  Detected: {} ({info})", type_str);
+            eprintln!(
+                "  {}",
+                crate::tr!(
+                    "init-detected-workspace",
+                    types = type_str.as_str(),
+                    info = info.as_str()
+                )
+            );
         } else {
-            eprintln!("  Detected: {} project", type_str);
+            eprintln!("  {}", crate::tr!("init-detected-project", types = type_str));
         }
     }
 
     if !detection.scan_dirs.is_empty() {
-        eprintln!("  Scan dirs: {:?}", detection.scan_dirs);
+        eprintln!(
+            "  {}",
+            crate::tr!("init-scan-dirs", dirs = format!("{:?}", detection.scan_dirs))
+        );
     }
 
     // Generate .codescope.toml
     let config_path = root.join(".codescope.toml");
     if config_path.exists() {
-        eprintln!("  .codescope.toml already exists, skipping");
+        eprintln!("  {}", crate::tr!("init-config-exists"));
     } else {
         let toml_content = generate_codescope_toml(&detection);
-        if let Err(e) = std::fs::write(&config_path, &toml_content) {
-            eprintln!("Error: Failed to write .codescope.toml: {}", e);
+        if let Err(e) = crate::atomic::write(&config_path, &toml_content) {
+            eprintln!(
+                "{}: {}",
+                crate::tr!("error-prefix"),
+                crate::tr!("init-config-write-failed", error = e.to_string())
+            );
             return 1;
         }
-        eprintln!("  Created .codescope.toml");
+        eprintln!("  {}", crate::tr!("init-config-created"));
     }
 
     // Generate or merge .mcp.json
     if let Err(e) = write_or_merge_mcp_json(&root) {
-        eprintln!("Error: {}", e);
+        eprintln!("{}: {}", crate::tr!("error-prefix"), e);
         return 1;
     }
 
     // Global repos.toml
     if global {
         if let Err(e) = merge_global_repos_toml(&root) {
-            eprintln!("Error: {}", e);
+            eprintln!("{}: {}", crate::tr!("error-prefix"), e);
             return 1;
         }
     }
@@ -970,19 +996,19 @@ pub fn run_init(args: &[String]) -> i32 {
     let file_count = validate_scan(&root, &detection.scan_dirs, &detection.extensions);
     if file_count > 0 {
         if file_count >= 10_000 {
-            eprintln!("  Validated: 10,000+ source files found");
+            eprintln!("  {}", crate::tr!("init-validated-many"));
         } else {
-            eprintln!("  Validated: {} source files found", file_count);
+            eprintln!("  {}", crate::tr!("init-validated", count = file_count));
         }
     } else {
-        eprintln!("  [WARN] No source files found with current settings.");
-        eprintln!("         Try removing scan_dirs from .codescope.toml to scan everything.");
+        eprintln!("  [WARN] {}", crate::tr!("init-no-files"));
+        eprintln!("         {}", crate::tr!("init-no-files-hint"));
     }
 
     // Build semantic index if requested (pre-populates centralized cache)
     #[cfg(feature = "semantic")]
     if build_semantic {
-        eprintln!("  Building semantic index...");
+        eprintln!("  {}", crate::tr!("init-semantic-building"));
         let config = crate::load_codescope_config(&root);
         let (all_files, _categories) = crate::scan::scan_files(&config);
         let progress = crate::types::SemanticProgress::new();
@@ -990,6 +1016,7 @@ pub fn run_init(args: &[String]) -> i32 {
         let start = std::time::Instant::now();
         match crate::semantic::build_semantic_index(
             &all_files,
+            &config.noise,
             sem_model.as_deref(),
             &progress,
             &root,
@@ -997,72 +1024,255 @@ pub fn run_init(args: &[String]) -> i32 {
             Some(idx) => {
                 let chunks: usize = idx.chunk_meta.len();
                 eprintln!(
-                    "  Semantic index built: {} chunks in {:.1}s (cached to ~/.cache/codescope/)",
-                    chunks,
-                    start.elapsed().as_secs_f64()
+                    "  {}",
+                    crate::tr!(
+                        "init-semantic-built",
+                        chunks = chunks,
+                        seconds = format!("{:.1}", start.elapsed().as_secs_f64())
+                    )
                 );
             }
             None => {
-                eprintln!("  [WARN] Semantic index build failed (non-fatal)");
+                eprintln!("  [WARN] {}", crate::tr!("init-semantic-failed"));
             }
         }
     }
 
     eprintln!();
-    eprintln!("  Open Claude Code in {} -- CodeScope tools are now available.", root.display());
+    eprintln!("  {}", crate::tr!("init-done", path = root.display().to_string()));
+    0
+}
+
+/// `codescope init --org-file=<repos.yaml>`: clone or update every repo of the org file
+/// (see [`crate::org`]) under the workspace directory (`--workspace=<dir>` overrides the
+/// file's), give each a `.codescope.toml`, register them in `~/.codescope/repos.toml` with
+/// their group, and index them in a background process (unless `--no-index`) so the next
+/// server start loads them from handoffs. Exits 1 if any repo failed.
+fn run_org_init(org_file: &Path, args: &[String]) -> i32 {
+    let fail = |error: String| {
+        eprintln!("{}: {}", crate::tr!("error-prefix"), error);
+        1
+    };
+    let content = match std::fs::read_to_string(org_file) {
+        Ok(c) => c,
+        Err(e) => return fail(format!("Failed to read {}: {}", org_file.display(), e)),
+    };
+    let file = match crate::org::parse(&content) {
+        Ok(f) => f,
+        Err(e) => return fail(format!("{}: {}", org_file.display(), e)),
+    };
+    let org_file = org_file.canonicalize().unwrap_or_else(|_| org_file.to_path_buf());
+    let workspace = match args.iter().find_map(|a| a.strip_prefix("--workspace=")) {
+        Some(dir) => PathBuf::from(dir),
+        None => crate::org::workspace_dir(&file, &org_file),
+    };
+    if let Err(e) = std::fs::create_dir_all(&workspace) {
+        return fail(format!("Failed to create {}: {}", workspace.display(), e));
+    }
+
+    eprintln!("{}", crate::tr!("init-title", version = env!("CARGO_PKG_VERSION")));
+    eprintln!(
+        "  {}",
+        crate::tr!("org-syncing", count = file.repos.len(), path = workspace.display().to_string())
+    );
+    let mut ready: Vec<(String, PathBuf)> = Vec::new();
+    let mut failed = 0;
+    for repo in &file.repos {
+        let dir = workspace.join(&repo.name);
+        let outcome = crate::org::sync(repo, &dir).and_then(|outcome| {
+            let dir = dir.canonicalize().unwrap_or_else(|_| dir.clone());
+            let config_path = dir.join(".codescope.toml");
+            if !config_path.exists() {
+                let toml_content = generate_codescope_toml(&detect_project(&dir));
+                crate::atomic::write(&config_path, &toml_content)
+                    .map_err(|e| format!("Failed to write {}: {}", config_path.display(), e))?;
+            }
+            crate::merge_global_repos_toml(&repo.name, &dir, repo.group.as_deref())?;
+            ready.push((repo.name.clone(), dir));
+            Ok(outcome)
+        });
+        let name = repo.name.as_str();
+        match outcome {
+            Ok(crate::org::SyncOutcome::Cloned) => {
+                eprintln!("  [PASS] {}", crate::tr!("org-cloned", name = name))
+            }
+            Ok(crate::org::SyncOutcome::Updated) => {
+                eprintln!("  [PASS] {}", crate::tr!("org-updated", name = name))
+            }
+            Err(e) => {
+                eprintln!("  [FAIL] {}", crate::tr!("org-failed", name = name, error = e));
+                failed += 1;
+            }
+        }
+    }
+
+    if !ready.is_empty() {
+        eprintln!("  {}", crate::tr!("org-registered", count = ready.len()));
+        if !args.iter().any(|a| a == "--no-index") {
+            let spawned = std::env::current_exe().and_then(|exe| {
+                std::process::Command::new(exe)
+                    .arg("warm")
+                    .args(ready.iter().map(|(name, dir)| format!("{name}={}", dir.display())))
+                    .stdin(std::process::Stdio::null())
+                    .stdout(std::process::Stdio::null())
+                    .stderr(std::process::Stdio::null())
+                    .spawn()
+            });
+            match spawned {
+                Ok(_) => eprintln!("  {}", crate::tr!("org-indexing", count = ready.len())),
+                Err(e) => eprintln!(
+                    "  [WARN] {}",
+                    crate::tr!("org-indexing-failed", error = e.to_string())
+                ),
+            }
+        }
+    }
+    if failed > 0 {
+        eprintln!();
+        eprintln!("  {}", crate::tr!("org-result-failed", count = failed));
+        return 1;
+    }
     0
 }
 
+/// `codescope warm NAME=PATH...`: scan each repo and write its index handoff, so the next
+/// server start loads it instead of scanning. Run in the background by `init --org-file`.
+pub fn run_warm(args: &[String]) -> i32 {
+    let tok = crate::tokenizer::create_tokenizer("bytes-estimate");
+    let mut code = 0;
+    for spec in args.iter().skip(1) {
+        let Some((name, root)) = spec.split_once('=') else {
+            eprintln!(
+                "{}: {}",
+                crate::tr!("error-prefix"),
+                crate::tr!("repo-spec-invalid", spec = spec.as_str())
+            );
+            code = 1;
+            continue;
+        };
+        let root = Path::new(root);
+        let repo = crate::scan_repo(name, root, &tok);
+        let saved = crate::handoff::handoff_path(root)
+            .ok_or_else(|| "Could not determine cache directory".to_string())
+            .and_then(|path| crate::handoff::save(&repo, &path));
+        if let Err(e) = saved {
+            eprintln!("{}: {e}", crate::tr!("error-prefix"));
+            code = 1;
+        }
+    }
+    code
+}
+
 // ---------------------------------------------------------------------------
 // codescope doctor
 // ---------------------------------------------------------------------------
 
-/// Diagnose CodeScope setup issues: check config files, binary location, and MCP integration.
-pub fn run_doctor(args: &[String]) -> i32 {
-    // Find the path argument (skip "doctor", skip flags)
-    let path_arg = args
-        .iter()
-        .skip(1) // skip "doctor"
-        .find(|a| !a.starts_with('-'));
+/// Project root from the first non-flag argument after the subcommand name, else the
+/// current directory. Exits on an unknown path.
+fn project_root(args: &[String]) -> PathBuf {
+    let path_arg = args.iter().skip(1).find(|a| !a.starts_with('-'));
 
     let root = match path_arg {
         Some(p) => PathBuf::from(p),
         None => std::env::current_dir().unwrap_or_else(|e| {
-            eprintln!("Error: Could not determine current directory: {}", e);
+            eprintln!(
+                "{}: {}",
+                crate::tr!("error-prefix"),
+                crate::tr!("cwd-unknown", error = e.to_string())
+            );
             std::process::exit(1);
         }),
     };
 
-    let root = root.canonicalize().unwrap_or_else(|e| {
-        eprintln!("Error: Path '{}' not found: {}", root.display(), e);
+    root.canonicalize().unwrap_or_else(|e| {
+        eprintln!(
+            "{}: {}",
+            crate::tr!("error-prefix"),
+            crate::tr!("path-not-found", path = root.display().to_string(), error = e.to_string())
+        );
         std::process::exit(1);
-    });
+    })
+}
+
+/// Print the tests to run for the changes on HEAD since `--since=<ref>` (default: the
+/// default branch), one path per line, so CI can run only those. The summary goes to
+/// stderr.
+pub fn run_test_impact(args: &[String]) -> i32 {
+    let root = project_root(args);
+    let name = root.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let tok = crate::tokenizer::create_tokenizer("bytes-estimate");
+    let repo = crate::scan_repo(&name, &root, &tok);
+
+    let since = match args.iter().find_map(|a| a.strip_prefix("--since=")) {
+        Some(since) => Ok(since.to_string()),
+        None => crate::git::default_branch(&root, &repo.config.git),
+    };
+    let changed = since
+        .and_then(|since| crate::git::changed_on_branch(&root, &since).map(|files| (since, files)));
+    let (since, changed) = match changed {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("{}: {e}", crate::tr!("error-prefix"));
+            return 1;
+        }
+    };
+
+    let impact = crate::testmap::test_impact(&repo, &changed, usize::MAX);
+    for test in &impact.tests {
+        println!("{}", test.path);
+    }
+    eprintln!(
+        "{}",
+        crate::tr!(
+            "test-impact-result",
+            since = since,
+            changed = changed.len(),
+            sources = impact.sources.len(),
+            tests = impact.tests.len()
+        )
+    );
+    0
+}
+
+/// Diagnose CodeScope setup issues: check config files, binary location, and MCP integration.
+pub fn run_doctor(args: &[String]) -> i32 {
+    let root = project_root(args);
 
     let version = env!("CARGO_PKG_VERSION");
     let mut has_warn = false;
     let mut has_fail = false;
 
-    eprintln!("codescope doctor");
+    eprintln!("{}", crate::tr!("doctor-title"));
     eprintln!();
 
     // 1. Binary version
-    eprintln!("  [PASS] codescope v{}", version);
+    eprintln!("  [PASS] {}", crate::tr!("doctor-binary", version = version));
 
     // 2. Check .codescope.toml
     let config_path = root.join(".codescope.toml");
     if config_path.exists() {
         let content = std::fs::read_to_string(&config_path).unwrap_or_default();
         match content.parse::<toml::Table>() {
-            Ok(_) => eprintln!("  [PASS] .codescope.toml exists and is valid TOML"),
+            Ok(_) => eprintln!("  [PASS] {}", crate::tr!("doctor-config-valid")),
             Err(e) => {
-                eprintln!("  [FAIL] .codescope.toml exists but is invalid: {}", e);
+                eprintln!(
+                    "  [FAIL] {}",
+                    crate::tr!("doctor-config-invalid", error = e.to_string())
+                );
                 has_fail = true;
             }
         }
     } else {
-        eprintln!("  [WARN] .codescope.toml not found (will use defaults)");
+        eprintln!("  [WARN] {}", crate::tr!("doctor-config-missing"));
         has_warn = true;
     }
+    let env_vars: Vec<String> = crate::env_overrides(|name| std::env::var(name).ok())
+        .iter()
+        .map(|(key, _)| crate::config_env_var(key))
+        .collect();
+    if !env_vars.is_empty() {
+        eprintln!("  [PASS] {}", crate::tr!("doctor-config-env", vars = env_vars.join(", ")));
+    }
 
     // 3. Check .mcp.json
     let mcp_path = root.join(".mcp.json");
@@ -1071,19 +1281,19 @@ pub fn run_doctor(args: &[String]) -> i32 {
         match serde_json::from_str::<serde_json::Value>(&content) {
             Ok(data) => {
                 if data.get("mcpServers").and_then(|v| v.get("codescope")).is_some() {
-                    eprintln!("  [PASS] .mcp.json has codescope entry");
+                    eprintln!("  [PASS] {}", crate::tr!("doctor-mcp-ok"));
                 } else {
-                    eprintln!("  [WARN] .mcp.json exists but missing codescope entry");
+                    eprintln!("  [WARN] {}", crate::tr!("doctor-mcp-no-entry"));
                     has_warn = true;
                 }
             }
             Err(e) => {
-                eprintln!("  [FAIL] .mcp.json exists but is invalid JSON: {}", e);
+                eprintln!("  [FAIL] {}", crate::tr!("doctor-mcp-invalid", error = e.to_string()));
                 has_fail = true;
             }
         }
     } else {
-        eprintln!("  [FAIL] .mcp.json not found (run: codescope init)");
+        eprintln!("  [FAIL] {}", crate::tr!("doctor-mcp-missing"));
         has_fail = true;
     }
 
@@ -1112,34 +1322,16 @@ pub fn run_doctor(args: &[String]) -> i32 {
             }
 
             let path = entry.path();
+            let rel_path =
+                path.strip_prefix(&root).unwrap_or(path).to_string_lossy().replace('\\', "/");
 
-            // Apply skip_dirs
-            let mut skip = false;
-            for component in path.components() {
-                if let std::path::Component::Normal(name) = component {
-                    if let Some(name_str) = name.to_str() {
-                        if config.skip_dirs.contains(name_str) {
-                            skip = true;
-                            break;
-                        }
-                    }
-                }
-            }
-            if skip {
+            // Apply skip_dirs and the extension filter
+            if config.in_skipped_dir(&rel_path)
+                || !config.matches_extensions(&entry.file_name().to_string_lossy())
+            {
                 continue;
             }
 
-            // Apply extension filter
-            if !config.extensions.is_empty() {
-                if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-                    if !config.extensions.contains(ext) {
-                        continue;
-                    }
-                } else {
-                    continue;
-                }
-            }
-
             estimated_total += 1;
             if file_count < scan_limit {
                 file_count += 1;
@@ -1149,14 +1341,17 @@ pub fn run_doctor(args: &[String]) -> i32 {
     let elapsed = start.elapsed();
 
     if file_count > 0 {
-        eprintln!("  [PASS] Test scan: found {} files in {:.0?}", file_count, elapsed);
+        eprintln!(
+            "  [PASS] {}",
+            crate::tr!("doctor-scan-found", count = file_count, elapsed = format!("{elapsed:.0?}"))
+        );
     } else {
-        eprintln!("  [WARN] Test scan: no files found");
+        eprintln!("  [WARN] {}", crate::tr!("doctor-scan-empty"));
         has_warn = true;
     }
 
     // 5. Total estimated file count
-    eprintln!("  [INFO] Estimated total files: {}", estimated_total);
+    eprintln!("  [INFO] {}", crate::tr!("doctor-estimated-total", count = estimated_total));
 
     // 6. Check for nested .git dirs (too-broad root)
     let mut git_dirs = 0;
@@ -1169,20 +1364,139 @@ pub fn run_doctor(args: &[String]) -> i32 {
         }
     }
     if git_dirs > 1 {
-        eprintln!("  [WARN] Found {} subdirectories with .git -- root may be too broad", git_dirs);
+        eprintln!("  [WARN] {}", crate::tr!("doctor-nested-git", count = git_dirs));
         has_warn = true;
     }
 
+    // 7. Disk cache usage
+    if let Some(base) = crate::cache_dir() {
+        let entries = crate::cache::list(&base);
+        let total: u64 = entries.iter().map(|e| e.bytes).sum();
+        eprintln!(
+            "  [INFO] {}",
+            crate::tr!(
+                "doctor-cache-size",
+                size = crate::memory::format_bytes(total as usize),
+                count = entries.len(),
+                path = base.display().to_string()
+            )
+        );
+        let missing: Vec<_> = entries.iter().filter(|e| e.repo_missing()).collect();
+        if !missing.is_empty() {
+            let bytes: u64 = missing.iter().map(|e| e.bytes).sum();
+            eprintln!(
+                "  [WARN] {}",
+                crate::tr!(
+                    "doctor-cache-missing",
+                    count = missing.len(),
+                    size = crate::memory::format_bytes(bytes as usize)
+                )
+            );
+            has_warn = true;
+        }
+    }
+
+    // 8. Registered repos whose root is missing (served as unavailable)
+    let repos_toml = crate::config_dir().map(|d| d.join("repos.toml"));
+    let registered: Option<toml::Table> = repos_toml
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|content| content.parse().ok());
+    if let Some(repos) = registered.as_ref().and_then(|t| t.get("repos")?.as_table().cloned()) {
+        let missing: Vec<&str> = repos
+            .iter()
+            .filter(|(_, v)| {
+                v.get("root").and_then(|r| r.as_str()).is_some_and(|r| !Path::new(r).is_dir())
+            })
+            .map(|(name, _)| name.as_str())
+            .collect();
+        if !missing.is_empty() {
+            eprintln!(
+                "  [WARN] {}",
+                crate::tr!(
+                    "doctor-repos-missing",
+                    count = missing.len(),
+                    names = missing.join(", ")
+                )
+            );
+            has_warn = true;
+        }
+    }
+
+    // 9. Directories that look like noise but have no [noise] entry
+    let (files, _) = crate::scan::scan_files(&config);
+    let graph = crate::scan::scan_imports(&files);
+    let candidates = crate::noise::detect(&files, &graph, &config.noise);
+    if !candidates.is_empty() {
+        let dirs: Vec<String> =
+            candidates.iter().map(|c| format!("{} ({})", c.dir, c.reasons.join("; "))).collect();
+        let (count, dirs) = (candidates.len(), dirs.join(", "));
+        if config.auto_noise {
+            eprintln!("  [INFO] {}", crate::tr!("doctor-noise-auto", count = count, dirs = dirs));
+        } else {
+            eprintln!(
+                "  [INFO] {}",
+                crate::tr!("doctor-noise-proposed", count = count, dirs = dirs)
+            );
+            for line in crate::noise::format_toml(&candidates).lines() {
+                eprintln!("         {line}");
+            }
+        }
+    }
+
     // Summary
     eprintln!();
     if has_fail {
-        eprintln!("  Result: FAIL -- fix the issues above");
+        eprintln!("  {}", crate::tr!("doctor-result-fail"));
         1
     } else if has_warn {
-        eprintln!("  Result: PASS with warnings");
+        eprintln!("  {}", crate::tr!("doctor-result-warn"));
+        0
+    } else {
+        eprintln!("  {}", crate::tr!("doctor-result-pass"));
         0
+    }
+}
+
+// ---------------------------------------------------------------------------
+// codescope check
+// ---------------------------------------------------------------------------
+
+/// Check the import graph against `[boundaries]` rules. Violations are printed to stdout;
+/// exits 1 when there are any or the rules are invalid, so CI can gate on it.
+pub fn run_check(args: &[String]) -> i32 {
+    let root = project_root(args);
+    let config = crate::load_codescope_config(&root);
+    let boundaries = &config.boundaries;
+    if boundaries.rules.is_empty() && boundaries.problems.is_empty() {
+        eprintln!("{}", crate::tr!("check-no-rules"));
+        return 0;
+    }
+
+    let (files, _) = crate::scan::scan_files(&config);
+    let graph = crate::scan::scan_imports(&files);
+    let violations = crate::boundaries::check(boundaries, &graph);
+    if !violations.is_empty() || !boundaries.problems.is_empty() {
+        print!(
+            "{}",
+            crate::boundaries::format_violations(boundaries, &graph, &violations, usize::MAX)
+        );
+    }
+
+    if !boundaries.problems.is_empty() {
+        eprintln!("{}", crate::tr!("check-result-invalid", count = boundaries.problems.len()));
+        1
+    } else if !violations.is_empty() {
+        eprintln!("{}", crate::tr!("check-result-fail", count = violations.len()));
+        1
     } else {
-        eprintln!("  Result: ALL PASS");
+        eprintln!(
+            "{}",
+            crate::tr!(
+                "check-result-pass",
+                edges = crate::boundaries::edge_count(&graph),
+                rules = boundaries.rules.len()
+            )
+        );
         0
     }
 }
@@ -0,0 +1,344 @@
+//! Language detection for stubs, semantic chunking, and language stats.
+//!
+//! A file's language comes from, in order:
+//! 1. A `[languages]` override in `.codescope.toml`, keyed by extension or glob.
+//! 2. Its extension, when the extension names exactly one language.
+//! 3. For ambiguous (`.h`, `.m`), unknown, or missing extensions, the file's first lines:
+//!    a vim/emacs modeline, then content markers for the ambiguous extensions, then a
+//!    `#!` shebang.
+//!
+//! Files with an unambiguous extension are never read, so detection during the scan walk
+//! and at read time always agree.
+
+use globset::{Glob, GlobMatcher};
+use regex::Regex;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+use std::sync::OnceLock;
+use tracing::warn;
+
+/// Bytes read from the start of a file for modelines, shebangs, and content markers.
+const HEAD_BYTES: usize = 2048;
+
+/// Lines searched for a modeline.
+const MODELINE_LINES: usize = 5;
+
+/// Known languages: name, the extension stub extraction understands for it, and the
+/// extensions that map to it unambiguously.
+const LANGUAGES: &[(&str, &str, &[&str])] = &[
+    ("c", "c", &["c"]),
+    ("cpp", "cpp", &["cpp", "cc", "cxx", "c++", "hpp", "hxx", "hh", "h++", "inl"]),
+    ("objc", "c", &["mm"]),
+    ("matlab", "", &[]),
+    ("csharp", "cs", &["cs"]),
+    ("java", "java", &["java"]),
+    ("kotlin", "kt", &["kt", "kts"]),
+    ("scala", "scala", &["scala", "sc"]),
+    ("rust", "rs", &["rs"]),
+    ("go", "go", &["go"]),
+    ("javascript", "js", &["js", "mjs", "cjs", "jsx"]),
+    ("typescript", "ts", &["ts", "tsx", "mts", "cts"]),
+    ("swift", "swift", &["swift"]),
+    ("d", "d", &["d"]),
+    ("python", "py", &["py", "pyi", "pyw"]),
+    ("ruby", "rb", &["rb", "rake", "gemspec"]),
+    ("php", "php", &["php"]),
+    ("elixir", "ex", &["ex", "exs"]),
+    ("perl", "pl", &["pl", "pm"]),
+    ("lua", "lua", &["lua"]),
+    ("shell", "sh", &["sh", "bash", "zsh", "ksh"]),
+    ("powershell", "ps1", &["ps1", "psm1", "psd1"]),
+    ("hlsl", "hlsl", &["hlsl", "usf", "ush"]),
+    ("glsl", "glsl", &["glsl", "vert", "frag", "comp"]),
+    ("wgsl", "wgsl", &["wgsl"]),
+    ("sql", "sql", &["sql"]),
+    ("jupyter", "ipynb", &["ipynb"]),
+    ("html", "html", &["html", "htm"]),
+    ("css", "css", &["css", "scss", "less"]),
+    ("markdown", "md", &["md", "markdown", "mdx"]),
+    ("asciidoc", "adoc", &["adoc", "asciidoc"]),
+    ("json", "json", &["json"]),
+    ("yaml", "yaml", &["yaml", "yml"]),
+    ("toml", "toml", &["toml"]),
+    ("xml", "xml", &["xml"]),
+    ("ini", "ini", &["ini", "cfg", "conf"]),
+];
+
+/// Modeline, shebang, and common alternative names for languages.
+const ALIASES: &[(&str, &str)] = &[
+    ("c++", "cpp"),
+    ("objective-c", "objc"),
+    ("objcpp", "objc"),
+    ("cs", "csharp"),
+    ("js", "javascript"),
+    ("node", "javascript"),
+    ("nodejs", "javascript"),
+    ("deno", "javascript"),
+    ("bun", "javascript"),
+    ("ts", "typescript"),
+    ("ts-node", "typescript"),
+    ("tsx", "typescript"),
+    ("py", "python"),
+    ("sh", "shell"),
+    ("bash", "shell"),
+    ("zsh", "shell"),
+    ("dash", "shell"),
+    ("ksh", "shell"),
+    ("pwsh", "powershell"),
+    ("octave", "matlab"),
+    ("rb", "ruby"),
+];
+
+/// Per-repo `[languages]` overrides from `.codescope.toml`:
+///
+/// ```toml
+/// [languages]
+/// h = "cpp"                   # every .h file is C++
+/// "legacy/**/*.h" = "c"       # except these
+/// "scripts/*" = "python"
+/// ```
+///
+/// Globs are checked before extensions, in file order.
+#[derive(Clone, Default)]
+pub struct LanguageOverrides {
+    exts: HashMap<String, &'static str>,
+    globs: Vec<(GlobMatcher, &'static str)>,
+}
+
+impl LanguageOverrides {
+    /// Parse the `[languages]` table. Unknown language names and invalid globs are skipped
+    /// with a warning.
+    pub fn from_table(table: &toml::Table) -> Self {
+        let mut out = Self::default();
+        for (key, value) in table {
+            let Some(lang) = value.as_str().and_then(by_name) else {
+                warn!(key = key.as_str(), value = %value, "Unknown language in [languages] — ignoring");
+                continue;
+            };
+            if key.contains(['*', '?', '[', '/']) {
+                match Glob::new(key) {
+                    Ok(glob) => out.globs.push((glob.compile_matcher(), lang)),
+                    Err(e) => warn!(pattern = key.as_str(), "Invalid glob in [languages]: {e}"),
+                }
+            } else {
+                out.exts.insert(key.trim_start_matches('.').to_lowercase(), lang);
+            }
+        }
+        out
+    }
+
+    fn lookup(&self, rel_path: &str, ext: &str) -> Option<&'static str> {
+        self.globs
+            .iter()
+            .find(|(glob, _)| glob.is_match(rel_path))
+            .map(|(_, lang)| *lang)
+            .or_else(|| self.exts.get(ext).copied())
+    }
+}
+
+/// Canonical language name for a name, alias, or extension (`c++`, `bash`, `py`).
+pub fn by_name(name: &str) -> Option<&'static str> {
+    let name = name.trim().to_lowercase();
+    LANGUAGES
+        .iter()
+        .find(|(lang, _, _)| *lang == name)
+        .map(|(lang, _, _)| *lang)
+        .or_else(|| ALIASES.iter().find(|(alias, _)| *alias == name).map(|(_, lang)| *lang))
+        .or_else(|| by_extension(&name))
+}
+
+/// The language of an extension: a built-in one, else the extension itself when a stub
+/// extractor is registered for it ([`crate::stubs::register_extractor`]).
+fn by_extension(ext: &str) -> Option<&'static str> {
+    LANGUAGES
+        .iter()
+        .find(|(_, _, exts)| exts.contains(&ext))
+        .map(|(lang, _, _)| *lang)
+        .or_else(|| crate::stubs::registered_ext(ext))
+}
+
+/// Extension that [`crate::stubs::extract_stubs`] and semantic chunking understand for
+/// `lang`; empty for languages with no dedicated stub strategy.
+pub fn stub_ext(lang: Option<&str>) -> &'static str {
+    let Some(lang) = lang else { return "" };
+    LANGUAGES
+        .iter()
+        .find(|(name, _, _)| *name == lang)
+        .map(|(_, ext, _)| *ext)
+        .or_else(|| crate::stubs::registered_ext(lang))
+        .unwrap_or("")
+}
+
+/// Extension to extract stubs and outlines of `rel_path` with: its own extension when an
+/// extractor is registered for it, so one registered for `tsx` handles `.tsx` files even
+/// though TypeScript's stub extension is `ts`; otherwise [`stub_ext`] of its language.
+pub fn file_stub_ext(lang: Option<&str>, rel_path: &str) -> &'static str {
+    crate::stubs::registered_ext(&extension(rel_path)).unwrap_or_else(|| stub_ext(lang))
+}
+
+fn extension(rel_path: &str) -> String {
+    let filename = rel_path.rsplit('/').next().unwrap_or(rel_path);
+    match filename.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => ext.to_lowercase(),
+        _ => String::new(),
+    }
+}
+
+/// Whether detecting this file's language needs its first lines.
+pub fn needs_head(overrides: &LanguageOverrides, rel_path: &str) -> bool {
+    let ext = extension(rel_path);
+    overrides.lookup(rel_path, &ext).is_none() && by_extension(&ext).is_none()
+}
+
+/// The first [`HEAD_BYTES`] of a file, lossily decoded.
+pub fn read_head(path: &Path) -> String {
+    let mut buf = vec![0u8; HEAD_BYTES];
+    let n = std::fs::File::open(path).and_then(|mut f| f.read(&mut buf)).unwrap_or(0);
+    String::from_utf8_lossy(&buf[..n]).into_owned()
+}
+
+/// Detect the language of `rel_path`. `head` is the start of the file's content (or all of
+/// it); it is only consulted when [`needs_head`] says so. Returns `None` for unrecognized
+/// files.
+pub fn detect(overrides: &LanguageOverrides, rel_path: &str, head: &str) -> Option<&'static str> {
+    let ext = extension(rel_path);
+    if let Some(lang) = overrides.lookup(rel_path, &ext).or_else(|| by_extension(&ext)) {
+        return Some(lang);
+    }
+    // Look at no more than the scan walk reads, so both see the same markers
+    let mut end = head.len().min(HEAD_BYTES);
+    while !head.is_char_boundary(end) {
+        end -= 1;
+    }
+    let head = &head[..end];
+    if let Some(lang) = modeline(head) {
+        return Some(lang);
+    }
+    match ext.as_str() {
+        "h" => return Some(disambiguate_header(head)),
+        "m" => return Some(disambiguate_m(head)),
+        _ => {}
+    }
+    shebang(head)
+}
+
+/// Detect the language of a file on disk, reading its first lines only when needed.
+pub fn detect_file(
+    overrides: &LanguageOverrides,
+    rel_path: &str,
+    abs_path: &Path,
+) -> Option<&'static str> {
+    let head = if needs_head(overrides, rel_path) { read_head(abs_path) } else { String::new() };
+    detect(overrides, rel_path, &head)
+}
+
+/// `vim: set ft=python:`, `vi: filetype=sh`, `-*- mode: c++ -*-`, or `-*- python -*-`.
+fn modeline(head: &str) -> Option<&'static str> {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| {
+        Regex::new(
+            r"(?:\b(?:vim?|ex):.*\b(?:ft|filetype|syntax)=([\w+-]+)|-\*-.*\bmode:\s*([\w+-]+).*-\*-|-\*-\s*([\w+-]+)\s*-\*-)",
+        )
+        .unwrap()
+    });
+    head.lines().take(MODELINE_LINES).find_map(|line| {
+        let caps = re.captures(line)?;
+        by_name(caps.iter().skip(1).flatten().next()?.as_str())
+    })
+}
+
+/// `#!/usr/bin/env python3` → `python`. Version suffixes and `env` flags are ignored.
+fn shebang(head: &str) -> Option<&'static str> {
+    let line = head.lines().next()?.strip_prefix("#!")?;
+    let mut words = line.split_whitespace();
+    let mut interpreter = words.next()?.rsplit('/').next()?;
+    if interpreter == "env" {
+        interpreter = words.find(|w| !w.starts_with('-'))?;
+    }
+    let name = interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    by_name(name)
+}
+
+/// `.h`: Objective-C or C++ when their markers appear, otherwise C.
+fn disambiguate_header(head: &str) -> &'static str {
+    static CPP: OnceLock<Regex> = OnceLock::new();
+    let cpp = CPP.get_or_init(|| {
+        Regex::new(
+            r"(?m)^\s*(?:class\s+\w+|namespace\b|template\s*<|(?:public|private|protected)\s*:)|std::|#include\s*<(?:iostream|string|vector|memory|map|cstdint|cstddef)>",
+        )
+        .unwrap()
+    });
+    if has_objc_markers(head) {
+        "objc"
+    } else if cpp.is_match(head) {
+        "cpp"
+    } else {
+        "c"
+    }
+}
+
+/// `.m`: MATLAB when it reads like MATLAB and has no Objective-C markers.
+fn disambiguate_m(head: &str) -> &'static str {
+    static MATLAB: OnceLock<Regex> = OnceLock::new();
+    let matlab = MATLAB
+        .get_or_init(|| Regex::new(r"(?m)^\s*(?:%|function\b|end\s*$|classdef\b|disp\()").unwrap());
+    if !has_objc_markers(head) && matlab.is_match(head) {
+        "matlab"
+    } else {
+        "objc"
+    }
+}
+
+fn has_objc_markers(head: &str) -> bool {
+    ["@interface", "@implementation", "@protocol", "@property", "#import"]
+        .iter()
+        .any(|m| head.contains(m))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_from_extension_content_modeline_and_shebang() {
+        let none = LanguageOverrides::default();
+        assert_eq!(detect(&none, "src/main.rs", ""), Some("rust"));
+        assert_eq!(detect(&none, "inc/list.h", "struct list { int n; };\n"), Some("c"));
+        assert_eq!(detect(&none, "inc/list.h", "namespace util {\nclass List;\n}\n"), Some("cpp"));
+        assert_eq!(detect(&none, "Foo.h", "#import <Foundation/Foundation.h>\n"), Some("objc"));
+        assert_eq!(
+            detect(&none, "fit.m", "% fit a curve\nfunction y = fit(x)\nend\n"),
+            Some("matlab")
+        );
+        assert_eq!(detect(&none, "App.m", "@implementation App\n@end\n"), Some("objc"));
+        assert_eq!(
+            detect(&none, "bin/deploy", "#!/usr/bin/env python3\nprint(1)\n"),
+            Some("python")
+        );
+        assert_eq!(detect(&none, "bin/run", "#!/bin/bash -e\n"), Some("shell"));
+        assert_eq!(detect(&none, "Jakefile", "// vim: set ft=javascript:\n"), Some("javascript"));
+        assert_eq!(detect(&none, "x.h", "/* -*- mode: c++ -*- */\nint f();\n"), Some("cpp"));
+        assert_eq!(detect(&none, "notes.txt", "hello\n"), None);
+        assert_eq!(detect(&none, ".bashrc", ""), None);
+    }
+
+    #[test]
+    fn overrides_win_over_detection() {
+        let table: toml::Table = r#"
+            h = "cpp"
+            "legacy/**/*.h" = "c"
+            "tools/*" = "python"
+            bogus = "klingon"
+        "#
+        .parse()
+        .unwrap();
+        let overrides = LanguageOverrides::from_table(&table);
+        assert_eq!(detect(&overrides, "inc/list.h", "struct list;\n"), Some("cpp"));
+        assert_eq!(detect(&overrides, "legacy/old/list.h", "class X {};\n"), Some("c"));
+        assert_eq!(detect(&overrides, "tools/gen", "#!/bin/sh\n"), Some("python"));
+        assert!(!needs_head(&overrides, "inc/list.h"));
+        assert_eq!(stub_ext(Some("objc")), "c");
+        assert_eq!(stub_ext(None), "");
+    }
+}
@@ -8,34 +8,122 @@
 //!
 //! - [`scan`] — File discovery, module detection, import graph building
 //! - [`types`] — Core types shared across the codebase
-//! - [`stubs`] — Language-aware stub extraction (signatures without bodies)
+//! - [`stubs`] — Language-aware stub extraction (signatures without bodies), with extractor plugins
+//! - [`format`] — Output profiles (compact/standard/verbose) for MCP tool results
+//! - [`features`] — Cargo feature definitions and `cfg(feature)` gates per file and item
 //! - [`fuzzy`] — FZF v2 fuzzy matching with Smith-Waterman scoring
 //! - [`budget`] — Token budget allocation via water-fill algorithm
+//! - [`encoding`] — Encoding detection and transcoding to UTF-8 for non-UTF-8 source files
+//! - [`entries`] — Entry point detection (mains, bin targets, HTTP routes, CLI subcommands)
+//! - [`config_refs`] — Environment variable and config key references
+//! - [`conventions`] — Formatter, linter, and CI conventions mined from tool configs
 //! - [`mcp`] — MCP JSON-RPC server (stdio transport)
+//! - [`lsp`] — Language Server Protocol facade (`codescope lsp`) for symbols and references
 //! - [`mcp_http`] — MCP Streamable HTTP transport
+//! - [`protocol`] — Typed JSON-RPC envelopes and MCP request/result structs
 //! - [`api`] — HTTP API handlers for the web UI
-//! - [`git`] — Git operations (blame, history, changed files, churn)
+//! - [`git`] — Git operations (blame, history, changed files, branch compare, diffstat, churn)
 //! - [`watch`] — File watcher for incremental live re-indexing
+//! - [`indexing`] — Background initial indexing in priority order, serving requests meanwhile
+//! - [`handoff`] — Index handoff files so a restarted server warm-starts instead of rescanning
 //! - [`init`] — CLI subcommands: `init` and `doctor`
+//! - [`i18n`] — Fluent message catalogs for localized CLI and tool output
 //! - [`auth`] — OAuth discovery and origin validation
 //! - [`tokenizer`] — Pluggable token counting backends
 //! - [`semantic`] — BERT-based semantic code search (feature-gated)
-
+//! - [`memory`] — Approximate index memory accounting and `max_memory_mb` enforcement
+//! - [`testmap`] — Test-to-source mapping by naming, import edges, and symbol references
+//! - [`namespace`] — Repo-qualified module ids (`repo/module`) and collision handling
+//! - [`licenses`] — SPDX license and copyright detection from file headers and LICENSE files
+//! - [`lang`] — Language detection from extensions, content markers, modelines, and shebangs, with overrides
+//! - [`paths`] — `[repo] path` resolution and `read_allow`/`read_deny` enforcement for file reads
+//! - [`redact`] — Opt-in masking of credentials in file reads (`[redact]`)
+//! - [`boundaries`] — Layering rules (`[boundaries]`) and import edges that violate them
+//! - [`ranking`] — `[ranking]` extension, path, and recency boosts for search scores
+//! - [`scopes`] — Named search scopes (`[scopes.<name>]`) for `cs_search`/`cs_grep` and `--scope`
+//! - [`schemas`] — Proto, OpenAPI, and GraphQL definitions linked to the code implementing or calling them
+//! - [`aliases`] — `[aliases]` word groups expanded in `cs_search`/`cs_grep` queries
+//! - [`spelling`] — Did-you-mean corrections for queries with no hits, from the indexed vocabulary
+//! - [`atomic`] — Crash-safe write-temp-fsync-rename file writes and orphaned temp cleanup
+//! - [`locks`] — Per-repo lock files electing one cache writer among servers sharing a repo
+//! - [`cache`] — Per-repo disk cache inventory, `codescope cache ls|prune`, and the `[cache]` policy
+//! - [`noise`] — `[noise]` directory scores that down-weight search results and skip embedding, and noise detection
+//! - [`workspaces`] — Cargo, npm/yarn/pnpm, and Go workspace members as module boundaries
+//! - [`tsconfig`] — `tsconfig.json` `paths`/`baseUrl` aliases (with `extends`) for JS/TS imports
+//! - [`prompts`] — User-defined MCP prompts (`[prompts.<name>]`, `~/.codescope/prompts/`)
+//! - [`manifests`] — Parsed `Cargo.toml`, `package.json`, and `go.mod` files as MCP resources
+//! - [`status`] — Typed server status shared by `cs_status` and `/health`
+//! - [`owners`] — `CODEOWNERS` rules, per-file owners, and ownership summaries for `cs_owners`
+//! - [`access_log`] — Sampled request log with hashed queries, summarized by `/api/metrics`
+//! - [`impact`] — Transitive importers of a file set, by depth and as a tree (`/api/impact`)
+//! - [`selfcheck`] — `--check` startup smoke test: a query battery per repo, reported as JSON
+//! - [`renames`] — files moved between scan generations, so old paths resolve to new ones
+//! - [`duplicates`] — clusters of identical or whitespace-only-different files (`cs_duplicates`)
+//! - [`modgraph`] — module dependency graph aggregated from file imports, as text or DOT
+//! - [`collate`] — natural, case- and accent-insensitive order for module and file listings
+//! - [`org`] — org files of git URLs cloned and registered by `codescope init --org-file`
+//! - [`crypt`] — AES-GCM encryption at rest for semantic caches and index handoffs
+//! - [`symbols`] — per-file function and type definitions, matched by name in `cs_search`
+
+pub mod access_log;
+pub mod aliases;
 pub mod api;
+pub mod atomic;
 pub mod auth;
+pub mod boundaries;
 pub mod budget;
+pub mod cache;
+pub mod collate;
+pub mod config_refs;
+pub mod conventions;
+pub mod crypt;
+pub mod duplicates;
+pub mod encoding;
+pub mod entries;
+pub mod features;
+pub mod format;
 pub mod fuzzy;
 pub mod git;
+pub mod handoff;
+pub mod i18n;
+pub mod impact;
+pub mod indexing;
 pub mod init;
+pub mod lang;
+pub mod licenses;
+pub mod locks;
+pub mod lsp;
+pub mod manifests;
 pub mod mcp;
 pub mod mcp_http;
+pub mod memory;
+pub mod modgraph;
+pub mod namespace;
+pub mod noise;
+pub mod org;
+pub mod owners;
+pub mod paths;
+pub mod prompts;
+pub mod protocol;
+pub mod ranking;
+pub mod redact;
+pub mod renames;
 pub mod scan;
+pub mod schemas;
+pub mod scopes;
+pub mod selfcheck;
 #[cfg(feature = "semantic")]
 pub mod semantic;
+pub mod spelling;
+pub mod status;
 pub mod stubs;
+pub mod symbols;
+pub mod testmap;
 pub mod tokenizer;
+pub mod tsconfig;
 pub mod types;
 pub mod watch;
+pub mod workspaces;
 
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -96,11 +184,35 @@ pub fn cache_dir() -> Option<PathBuf> {
 // ---------------------------------------------------------------------------
 
 /// Known keys in `.codescope.toml` for config validation.
-const KNOWN_CONFIG_KEYS: &[&str] =
-    &["scan_dirs", "skip_dirs", "extensions", "noise_dirs", "semantic_model"];
+const KNOWN_CONFIG_KEYS: &[&str] = &[
+    "scan_dirs",
+    "skip_dirs",
+    "extensions",
+    "noise_dirs",
+    "auto_noise",
+    "respect_gitignore",
+    "follow_symlinks",
+    "semantic_model",
+    "max_memory_mb",
+    "max_grep_file_size",
+    "max_result_tokens",
+    "sort_order",
+    "read_allow",
+    "read_deny",
+    "languages",
+    "redact",
+    "git",
+    "boundaries",
+    "ranking",
+    "scopes",
+    "aliases",
+    "noise",
+    "prompts",
+    "profile",
+];
 
 /// Simple Levenshtein edit distance for typo suggestions.
-fn edit_distance(a: &str, b: &str) -> usize {
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
     let (a, b) = (a.as_bytes(), b.as_bytes());
     let mut prev: Vec<usize> = (0..=b.len()).collect();
     let mut curr = vec![0; b.len() + 1];
@@ -115,85 +227,329 @@ fn edit_distance(a: &str, b: &str) -> usize {
     prev[b.len()]
 }
 
+/// A positive size in bytes: an integer, or a string with a `KB`, `MB`, or `GB` suffix.
+fn parse_byte_size(value: &toml::Value) -> Option<u64> {
+    if let Some(bytes) = value.as_integer() {
+        return u64::try_from(bytes).ok().filter(|&b| b > 0);
+    }
+    let text = value.as_str()?.trim().to_ascii_uppercase();
+    let (number, unit) = match text.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => text.split_at(i),
+        None => (text.as_str(), ""),
+    };
+    let unit = match unit.trim().trim_end_matches('B') {
+        "" => 1,
+        "K" => 1024,
+        "M" => 1024 * 1024,
+        "G" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+    number.parse::<u64>().ok().filter(|&n| n > 0).map(|n| n * unit)
+}
+
+/// Keys whose `CODESCOPE_<KEY>` value may also be a comma-separated list.
+const LIST_CONFIG_KEYS: &[&str] =
+    &["scan_dirs", "skip_dirs", "extensions", "noise_dirs", "read_allow", "read_deny"];
+
+/// Environment variable that overrides a `.codescope.toml` key: `CODESCOPE_SCAN_DIRS` for
+/// `scan_dirs`.
+pub fn config_env_var(key: &str) -> String {
+    format!("CODESCOPE_{}", key.to_ascii_uppercase())
+}
+
+/// Config keys set by `CODESCOPE_<KEY>` environment variables, looked up with `var`, as the
+/// TOML values they stand for. A value is read as TOML when it parses as one (`true`,
+/// `20000`, `["src", "lib"]`, or an inline table for a section such as
+/// `CODESCOPE_RANKING='{ recency = 0.2 }'`) and as a string otherwise; list keys also take
+/// `src,lib`. Empty variables are ignored.
+pub fn env_overrides(var: impl Fn(&str) -> Option<String>) -> Vec<(String, toml::Value)> {
+    KNOWN_CONFIG_KEYS
+        .iter()
+        .filter(|key| **key != "profile")
+        .filter_map(|key| {
+            let raw = var(&config_env_var(key))?;
+            let raw = raw.trim();
+            if raw.is_empty() {
+                return None;
+            }
+            let value = if LIST_CONFIG_KEYS.contains(key) && !raw.starts_with('[') {
+                let items = raw.split(',').map(str::trim).filter(|s| !s.is_empty());
+                toml::Value::Array(items.map(|s| toml::Value::String(s.to_string())).collect())
+            } else {
+                format!("v = {raw}")
+                    .parse::<toml::Table>()
+                    .ok()
+                    .and_then(|mut t| t.remove("v"))
+                    .unwrap_or_else(|| toml::Value::String(raw.to_string()))
+            };
+            Some((key.to_string(), value))
+        })
+        .collect()
+}
+
 /// Load scan configuration from `.codescope.toml` in the given project root.
 ///
-/// Returns a [`ScanConfig`] with defaults merged with any overrides from the config file.
-/// If the file doesn't exist or can't be parsed, returns defaults with a warning.
+/// Returns a [`ScanConfig`] with defaults merged with any overrides from the config file,
+/// and over those, any `CODESCOPE_<KEY>` environment variables ([`env_overrides`]).
+/// If the file doesn't exist or can't be parsed, the defaults are used with a warning.
 /// Unknown keys trigger a warning with a typo suggestion.
 pub fn load_codescope_config(project_root: &std::path::Path) -> ScanConfig {
+    load_codescope_profile(project_root, None)
+}
+
+/// [`load_codescope_config`] with the keys of `[profile.<name>]` replacing the top-level
+/// ones they set, so a profile can index e.g. only `docs/` with its own `extensions`. An
+/// unknown profile is warned about and the top-level settings are used.
+pub fn load_codescope_profile(project_root: &std::path::Path, profile: Option<&str>) -> ScanConfig {
     let mut config = ScanConfig::new(project_root.to_path_buf());
     let config_path = project_root.join(".codescope.toml");
 
+    let mut table = toml::Table::new();
     if config_path.exists() {
         debug!("Loading .codescope.toml");
         if let Ok(content) = std::fs::read_to_string(&config_path) {
-            if let Ok(table) = content.parse::<toml::Table>() {
-                // Validate keys — warn on unknown
-                for key in table.keys() {
-                    if !KNOWN_CONFIG_KEYS.contains(&key.as_str()) {
-                        let suggestion =
-                            KNOWN_CONFIG_KEYS.iter().min_by_key(|k| edit_distance(key, k)).unwrap();
-                        let dist = edit_distance(key, suggestion);
-                        if dist <= 3 {
-                            warn!(
-                                key = key.as_str(),
-                                suggestion = *suggestion,
-                                "Unknown key in .codescope.toml — did you mean '{suggestion}'?"
-                            );
-                        } else {
-                            warn!(
-                                key = key.as_str(),
-                                "Unknown key in .codescope.toml (known keys: {})",
-                                KNOWN_CONFIG_KEYS.join(", ")
-                            );
-                        }
-                    }
-                }
-
-                // scan_dirs
-                if let Some(dirs) = table.get("scan_dirs").and_then(|v| v.as_array()) {
-                    config.scan_dirs =
-                        dirs.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect();
-                }
-
-                // skip_dirs — merge with defaults
-                if let Some(dirs) = table.get("skip_dirs").and_then(|v| v.as_array()) {
-                    for d in dirs {
-                        if let Some(s) = d.as_str() {
-                            config.skip_dirs.insert(s.to_string());
-                        }
-                    }
-                }
-
-                // extensions
-                if let Some(exts) = table.get("extensions").and_then(|v| v.as_array()) {
-                    config.extensions =
-                        exts.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect();
-                }
-
-                // noise_dirs — merge with defaults
-                if let Some(dirs) = table.get("noise_dirs").and_then(|v| v.as_array()) {
-                    for d in dirs {
-                        if let Some(s) = d.as_str() {
-                            config.noise_dirs.insert(s.to_string());
-                        }
-                    }
-                }
-
-                // semantic_model
-                #[cfg(feature = "semantic")]
-                if let Some(model) = table.get("semantic_model").and_then(|v| v.as_str()) {
-                    config.semantic_model = Some(model.to_string());
-                }
+            match content.parse::<toml::Table>() {
+                Ok(t) => table = t,
+                Err(_) => warn!("Failed to parse .codescope.toml"),
+            }
+        }
+    }
+
+    // [profile.<name>] overrides, validated with the top-level keys below
+    let overrides = profile
+        .and_then(|name| table.get("profile")?.get(name)?.as_table().cloned().map(|t| (name, t)));
+    if let Some((name, overrides)) = overrides {
+        table.extend(overrides);
+        config.profile = Some(name.to_string());
+    }
+
+    // Validate keys — warn on unknown
+    for key in table.keys() {
+        if !KNOWN_CONFIG_KEYS.contains(&key.as_str()) {
+            let suggestion =
+                KNOWN_CONFIG_KEYS.iter().min_by_key(|k| edit_distance(key, k)).unwrap();
+            let dist = edit_distance(key, suggestion);
+            if dist <= 3 {
+                warn!(
+                    key = key.as_str(),
+                    suggestion = *suggestion,
+                    "Unknown key in .codescope.toml — did you mean '{suggestion}'?"
+                );
+            } else {
+                warn!(
+                    key = key.as_str(),
+                    "Unknown key in .codescope.toml (known keys: {})",
+                    KNOWN_CONFIG_KEYS.join(", ")
+                );
+            }
+        }
+    }
+
+    // CODESCOPE_<KEY> environment variables win over the file and its profile
+    for (key, value) in env_overrides(|name| std::env::var(name).ok()) {
+        debug!(key = key.as_str(), "Config key set from the environment");
+        config.env_overrides.push(key.clone());
+        table.insert(key, value);
+    }
+
+    // skip_dirs — merge with defaults. Globs and paths match relative paths.
+    if let Some(dirs) = table.get("skip_dirs").and_then(|v| v.as_array()) {
+        let mut patterns = Vec::new();
+        for s in dirs.iter().filter_map(|d| d.as_str()) {
+            let s = s.trim_matches('/');
+            if s.contains('/') {
+                patterns.push(s.to_string());
+            } else if scan::is_glob(s) {
+                patterns.push(format!("**/{s}"));
+            } else {
+                config.skip_dirs.insert(s.to_string());
+            }
+        }
+        config.skip_globs = scan::compile_config_globs(&patterns, "skip_dirs");
+    }
+
+    // scan_dirs — glob entries expanded against the tree now; absolute and `../`
+    // entries add directories outside the root
+    if let Some(dirs) = table.get("scan_dirs").and_then(|v| v.as_array()) {
+        let (external, mut entries): (Vec<String>, Vec<String>) = dirs
+            .iter()
+            .filter_map(|v| v.as_str().map(|s| s.to_string()))
+            .partition(|e| scan::is_external_entry(e));
+        for dir in external.iter().filter_map(|e| scan::external_dir(project_root, e)) {
+            if dir.prefix.starts_with("..") {
+                config.external_dirs.push(dir);
             } else {
-                warn!("Failed to parse .codescope.toml");
+                entries.push(dir.prefix);
+            }
+        }
+        // Only external entries: they add to the root instead of replacing it
+        if !config.external_dirs.is_empty() && entries.is_empty() {
+            entries.push(".".to_string());
+        }
+        config.scan_dir_prefixes = entries
+            .iter()
+            .map(|e| scan::literal_prefix(e.trim_matches('/')))
+            .filter(|p| !p.is_empty())
+            .collect();
+        config.scan_dirs = scan::expand_scan_dirs(&config, &entries);
+        config.scan_dirs.extend(config.external_dirs.iter().map(|d| d.prefix.clone()));
+    }
+
+    // extensions — globs match whole file names
+    if let Some(exts) = table.get("extensions").and_then(|v| v.as_array()) {
+        let (globs, exts): (Vec<String>, Vec<String>) = exts
+            .iter()
+            .filter_map(|v| v.as_str().map(|s| s.to_string()))
+            .partition(|s| scan::is_glob(s));
+        config.extensions = exts.into_iter().collect();
+        config.extension_globs = scan::compile_config_globs(&globs, "extensions");
+    }
+
+    // noise_dirs — merge with defaults
+    if let Some(dirs) = table.get("noise_dirs").and_then(|v| v.as_array()) {
+        for d in dirs {
+            if let Some(s) = d.as_str() {
+                config.noise_dirs.insert(s.to_string());
+            }
+        }
+    }
+
+    // respect_gitignore
+    if let Some(b) = table.get("respect_gitignore").and_then(|v| v.as_bool()) {
+        config.respect_gitignore = b;
+    }
+
+    // auto_noise
+    if let Some(b) = table.get("auto_noise").and_then(|v| v.as_bool()) {
+        config.auto_noise = b;
+    }
+
+    // follow_symlinks
+    if let Some(b) = table.get("follow_symlinks").and_then(|v| v.as_bool()) {
+        config.follow_symlinks = b;
+    }
+
+    // max_memory_mb
+    if let Some(mb) = table.get("max_memory_mb").and_then(|v| v.as_integer()) {
+        if mb > 0 {
+            config.max_memory_mb = Some(mb as usize);
+        } else {
+            warn!(value = mb, "max_memory_mb must be positive — ignoring");
+        }
+    }
+
+    // max_grep_file_size: bytes, or a string like "64MB"
+    if let Some(value) = table.get("max_grep_file_size") {
+        match parse_byte_size(value) {
+            Some(bytes) => config.max_grep_file_size = bytes,
+            None => warn!(
+                value = %value,
+                "max_grep_file_size must be a positive size like \"64MB\" — ignoring"
+            ),
+        }
+    }
+
+    // max_result_tokens
+    if let Some(n) = table.get("max_result_tokens").and_then(|v| v.as_integer()) {
+        if n > 0 {
+            config.max_result_tokens = Some(n as usize);
+        } else {
+            warn!(value = n, "max_result_tokens must be positive — ignoring");
+        }
+    }
+
+    // sort_order: "natural" (default) or "bytes"
+    if let Some(order) = table.get("sort_order").and_then(|v| v.as_str()) {
+        match collate::SortOrder::parse(order) {
+            Ok(order) => config.sort_order = order,
+            Err(_) => {
+                warn!(value = order, "sort_order must be \"natural\" or \"bytes\" — ignoring")
             }
         }
     }
 
+    // read_allow / read_deny
+    let globs = |key: &str| -> Vec<String> {
+        table
+            .get(key)
+            .and_then(|v| v.as_array())
+            .map(|a| a.iter().filter_map(|v| v.as_str().map(String::from)).collect())
+            .unwrap_or_default()
+    };
+    config.read_policy = paths::ReadPolicy::new(&globs("read_allow"), &globs("read_deny"))
+        .with_follow_symlinks(config.follow_symlinks)
+        .with_external_dirs(&config.external_dirs);
+
+    // [languages] overrides
+    if let Some(languages) = table.get("languages").and_then(|v| v.as_table()) {
+        config.languages = lang::LanguageOverrides::from_table(languages);
+    }
+
+    // [redact] secret masking
+    if let Some(redact) = table.get("redact").and_then(|v| v.as_table()) {
+        config.redact = redact::RedactConfig::from_table(redact);
+    }
+
+    // [git] default branch and protected refs
+    if let Some(git_table) = table.get("git").and_then(|v| v.as_table()) {
+        config.git = git::GitConfig::from_table(git_table);
+    }
+
+    // [boundaries] layering rules
+    if let Some(b) = table.get("boundaries").and_then(|v| v.as_table()) {
+        config.boundaries = boundaries::BoundaryConfig::from_table(b);
+        for problem in &config.boundaries.problems {
+            tracing::warn!(problem, "Ignoring invalid [boundaries] entry");
+        }
+    }
+
+    // [ranking] search boosts
+    if let Some(ranking) = table.get("ranking").and_then(|v| v.as_table()) {
+        config.ranking = ranking::RankingConfig::from_table(ranking);
+    }
+
+    // [scopes.<name>] saved search filters
+    if let Some(s) = table.get("scopes").and_then(|v| v.as_table()) {
+        config.scopes = scopes::parse_scopes(s);
+    }
+
+    // [aliases] query word groups
+    if let Some(a) = table.get("aliases").and_then(|v| v.as_table()) {
+        config.aliases = aliases::AliasConfig::from_table(a);
+    }
+
+    // [noise] directory scores
+    if let Some(n) = table.get("noise").and_then(|v| v.as_table()) {
+        config.noise = noise::NoiseConfig::from_table(n);
+    }
+
+    // [prompts.<name>] MCP prompt templates
+    if let Some(p) = table.get("prompts").and_then(|v| v.as_table()) {
+        config.prompts = prompts::parse_prompts(p);
+    }
+
+    // semantic_model
+    #[cfg(feature = "semantic")]
+    if let Some(model) = table.get("semantic_model").and_then(|v| v.as_str()) {
+        config.semantic_model = Some(model.to_string());
+    }
+
+    if let Some(name) = profile.filter(|_| config.profile.is_none()) {
+        warn!(profile = name, "Unknown scan profile in .codescope.toml — using the defaults");
+    }
+    config.workspace_members = workspaces::detect(project_root);
+    config.codeowners = owners::CodeOwners::load(project_root);
     config
 }
 
+/// Names of the `[profile.<name>]` sections in the repo's `.codescope.toml`, sorted.
+pub fn config_profiles(project_root: &std::path::Path) -> Vec<String> {
+    let content = std::fs::read_to_string(project_root.join(".codescope.toml")).unwrap_or_default();
+    let table = content.parse::<toml::Table>().unwrap_or_default();
+    let profiles = table.get("profile").and_then(|p| p.as_table());
+    profiles.map(|p| p.keys().cloned().collect()).unwrap_or_default()
+}
+
 // ---------------------------------------------------------------------------
 // Scan a single repo and return RepoState
 // ---------------------------------------------------------------------------
@@ -220,7 +576,21 @@ pub fn scan_repo_with_options(
     _tok: &Arc<dyn tokenizer::Tokenizer>,
     _enable_semantic: bool,
 ) -> RepoState {
-    let config = load_codescope_config(root);
+    scan_repo_with_progress(name, root, _tok, _enable_semantic, None, &types::ScanProgress::new())
+}
+
+/// [`scan_repo_with_options`] under the scan `profile` (see [`load_codescope_profile`]),
+/// reporting the running stage, files walked, and files whose imports are parsed to
+/// `progress` as the scan goes.
+pub fn scan_repo_with_progress(
+    name: &str,
+    root: &std::path::Path,
+    _tok: &Arc<dyn tokenizer::Tokenizer>,
+    _enable_semantic: bool,
+    profile: Option<&str>,
+    progress: &types::ScanProgress,
+) -> RepoState {
+    let mut config = load_codescope_profile(root, profile);
 
     info!(repo = name, root = %root.display(), "Scanning codebase");
     if !config.scan_dirs.is_empty() {
@@ -231,14 +601,46 @@ pub fn scan_repo_with_options(
     }
 
     let start = Instant::now();
+    let synced_at = ranking::now_secs();
+    let synced_head = git::head_commit(root);
+    let mut profile = types::ScanProfile::new();
 
-    let (all_files, manifest) = scan_files(&config);
+    let (all_files, manifest, symlinks) =
+        profile.tracked_stage(progress, "walk", || scan_files_with_progress(&config, progress));
     let file_count = all_files.len();
+    progress.set_files_total(file_count);
     let module_count = manifest.len();
-    let deps = scan_deps(&config);
-    let (search_files, search_modules) = build_search_index(&manifest);
-    let import_graph = scan_imports(&all_files);
-    let term_doc_freq = build_term_doc_freq(&all_files);
+    let deps = profile.tracked_stage(progress, "deps", || scan_deps(&config));
+    let (search_files, search_modules) =
+        profile.tracked_stage(progress, "index", || build_search_index(&manifest));
+    let module_docs = collect_module_docs(&manifest);
+    let import_graph = profile
+        .tracked_stage(progress, "imports", || scan_imports_with_progress(&all_files, progress));
+    if config.auto_noise {
+        let found = noise::detect(&all_files, &import_graph, &config.noise);
+        if !found.is_empty() {
+            info!(
+                repo = name,
+                dirs = ?found.iter().map(|c| (c.dir.as_str(), c.score)).collect::<Vec<_>>(),
+                "auto_noise: scoring detected noise directories"
+            );
+        }
+        config.noise.set_auto(&found);
+    }
+    let entry_points =
+        profile.tracked_stage(progress, "entries", || entries::detect_entry_points(&all_files));
+    let symbols =
+        profile.tracked_stage(progress, "symbols", || symbols::scan_symbols(&all_files, &config));
+    let config_refs = profile.tracked_stage(progress, "config_refs", || {
+        config_refs::scan_config_refs(&config, &all_files)
+    });
+    let licenses = profile
+        .tracked_stage(progress, "licenses", || licenses::scan_licenses(&config, &all_files));
+    let schemas = profile.tracked_stage(progress, "schemas", || schemas::scan_schemas(&all_files));
+    let commit_times = profile.tracked_stage(progress, "recency", || {
+        ranking::scan_commit_times(&config.root, &config.ranking)
+    });
+    let term_doc_freq = profile.tracked_stage(progress, "tdf", || build_term_doc_freq(&all_files));
 
     #[cfg(feature = "semantic")]
     let semantic_index = std::sync::Arc::new(std::sync::RwLock::new(None));
@@ -246,6 +648,7 @@ pub fn scan_repo_with_options(
     let semantic_progress = std::sync::Arc::new(types::SemanticProgress::new());
 
     let scan_time_ms = start.elapsed().as_millis() as u64;
+    progress.finish();
 
     info!(
         repo = name,
@@ -253,70 +656,120 @@ pub fn scan_repo_with_options(
         modules = module_count,
         dep_modules = deps.len(),
         import_edges = import_graph.imports.len(),
+        skipped_symlinks = symlinks.skipped.len(),
+        symlink_cycles = symlinks.cycles.len(),
         time_ms = scan_time_ms,
         "Scan complete"
     );
+    if !symlinks.skipped.is_empty() {
+        info!(
+            repo = name,
+            examples = ?symlinks.skipped.iter().take(5).collect::<Vec<_>>(),
+            "Symlinks not followed; set follow_symlinks = true in .codescope.toml to index them"
+        );
+    }
+    if !symlinks.cycles.is_empty() {
+        warn!(repo = name, symlinks = ?symlinks.cycles, "Symlink cycles skipped");
+    }
+    debug!(repo = name, stages = profile.summary().as_str(), "Scan stages");
 
-    RepoState {
+    let mut repo = RepoState {
         name: name.to_string(),
         root: root.to_path_buf(),
         config,
         all_files,
         manifest,
+        module_docs,
+        entry_points,
+        symbols,
+        config_refs,
+        licenses,
+        schemas,
+        commit_times,
         deps,
         search_files,
         search_modules,
         import_graph,
         stub_cache: DashMap::new(),
         term_doc_freq,
+        index_memory: Default::default(),
         scan_time_ms,
+        scan_profile: profile,
+        synced_at,
+        synced_head,
+        renames: Default::default(),
         #[cfg(feature = "semantic")]
         semantic_index,
         #[cfg(feature = "semantic")]
         semantic_progress,
-    }
+    };
+    memory::measure_index(&mut repo);
+    repo
 }
 
 /// Merge a repo entry into the global `~/.codescope/repos.toml` registry.
 ///
-/// If the repo name already exists in the file, this is a no-op.
+/// If the repo name already exists in the file, only its `group` is updated (when given).
 /// Creates `~/.codescope/` and `repos.toml` if they don't exist.
-pub fn merge_global_repos_toml(name: &str, root: &std::path::Path) -> Result<(), String> {
+pub fn merge_global_repos_toml(
+    name: &str,
+    root: &std::path::Path,
+    group: Option<&str>,
+) -> Result<(), String> {
     let dir = config_dir()
         .ok_or_else(|| "Could not determine config directory (HOME/APPDATA not set)".to_string())?;
     let toml_path = dir.join("repos.toml");
-
-    let mut table: toml::Table = if toml_path.exists() {
-        let content = std::fs::read_to_string(&toml_path)
-            .map_err(|e| format!("Failed to read {}: {}", toml_path.display(), e))?;
-        content.parse().map_err(|e| format!("Failed to parse {}: {}", toml_path.display(), e))?
-    } else {
-        toml::Table::new()
-    };
-
-    let repos = table.entry("repos").or_insert_with(|| toml::Value::Table(toml::Table::new()));
-    let repos = repos.as_table_mut().ok_or("repos is not a table in repos.toml")?;
-
-    if repos.contains_key(name) {
-        return Ok(());
-    }
-
-    let mut entry = toml::Table::new();
-    entry.insert("root".to_string(), toml::Value::String(root.to_string_lossy().to_string()));
-    repos.insert(name.to_string(), toml::Value::Table(entry));
-
     std::fs::create_dir_all(&dir)
         .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
-    let output = toml::to_string_pretty(&table)
-        .map_err(|e| format!("Failed to serialize repos.toml: {}", e))?;
-    std::fs::write(&toml_path, output)
-        .map_err(|e| format!("Failed to write {}: {}", toml_path.display(), e))?;
 
-    Ok(())
+    // Another process (a second server, `codescope init --global`) may be merging too
+    locks::with_file_lock(&toml_path, || -> Result<(), String> {
+        let mut table: toml::Table = if toml_path.exists() {
+            let content = std::fs::read_to_string(&toml_path)
+                .map_err(|e| format!("Failed to read {}: {}", toml_path.display(), e))?;
+            content
+                .parse()
+                .map_err(|e| format!("Failed to parse {}: {}", toml_path.display(), e))?
+        } else {
+            toml::Table::new()
+        };
+
+        let repos = table.entry("repos").or_insert_with(|| toml::Value::Table(toml::Table::new()));
+        let repos = repos.as_table_mut().ok_or("repos is not a table in repos.toml")?;
+
+        if let Some(entry) = repos.get_mut(name) {
+            let (Some(group), Some(entry)) = (group, entry.as_table_mut()) else {
+                return Ok(());
+            };
+            if entry.get("group").and_then(|v| v.as_str()) == Some(group) {
+                return Ok(());
+            }
+            entry.insert("group".to_string(), toml::Value::String(group.to_string()));
+        } else {
+            let mut entry = toml::Table::new();
+            let root = root.to_string_lossy().to_string();
+            entry.insert("root".to_string(), toml::Value::String(root));
+            if let Some(group) = group {
+                entry.insert("group".to_string(), toml::Value::String(group.to_string()));
+            }
+            repos.insert(name.to_string(), toml::Value::Table(entry));
+        }
+
+        let output = toml::to_string_pretty(&table)
+            .map_err(|e| format!("Failed to serialize repos.toml: {}", e))?;
+        atomic::write(&toml_path, output)
+            .map_err(|e| format!("Failed to write {}: {}", toml_path.display(), e))?;
+
+        Ok(())
+    })
 }
 
-/// Parse a `repos.toml` config file and return a list of `(name, root_path)` pairs.
-pub fn parse_repos_toml(path: &std::path::Path) -> Vec<(String, PathBuf)> {
+/// Parse a `repos.toml` config file into repo specs: `root` (required), plus optional
+/// `priority` (higher scans earlier), `lazy` (scan on first use), and `profile` (a
+/// `[profile.<name>]` of the repo's `.codescope.toml`). A `group` (set by
+/// `codescope init --org-file`) only labels the entry. Roots need not exist yet; see
+/// [`indexing::watch_missing_roots`].
+pub fn parse_repos_toml(path: &std::path::Path) -> Vec<indexing::RepoSpec> {
     let content = match std::fs::read_to_string(path) {
         Ok(c) => c,
         Err(e) => {
@@ -346,11 +799,14 @@ pub fn parse_repos_toml(path: &std::path::Path) -> Vec<(String, PathBuf)> {
             error!(repo = name.as_str(), "Missing 'root' field in repos config");
             std::process::exit(1);
         });
-        let root = PathBuf::from(root).canonicalize().unwrap_or_else(|e| {
-            error!(repo = name.as_str(), path = root, error = %e, "Repository root not found");
-            std::process::exit(1);
-        });
-        repos.push((name.clone(), root));
+        // A missing root (say, an unmounted drive) leaves the repo registered but unavailable
+        let root = PathBuf::from(root);
+        let root = root.canonicalize().unwrap_or(root);
+        let mut spec = indexing::RepoSpec::new(name, root);
+        spec.priority = value.get("priority").and_then(|v| v.as_integer()).unwrap_or(0);
+        spec.lazy = value.get("lazy").and_then(|v| v.as_bool()).unwrap_or(false);
+        spec.profile = value.get("profile").and_then(|v| v.as_str()).map(String::from);
+        repos.push(spec);
     }
     repos
 }
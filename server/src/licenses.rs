@@ -0,0 +1,481 @@
+//! SPDX license and copyright detection, indexed during scan for `cs_modules
+//! action=licenses` and `/api/licenses`.
+//!
+//! A file's header comment block is checked for an `SPDX-License-Identifier:` tag or a
+//! recognizable license notice, plus its copyright line. `LICENSE`/`COPYING` files apply
+//! to their directory subtree, nearest first; several in one directory (`LICENSE-MIT`,
+//! `LICENSE-APACHE`) combine as `A OR B`. A file's effective license is its own header's
+//! when present, otherwise the governing license file's. The file walk usually filters
+//! license files out by extension, so the directories of scanned files are listed
+//! separately, like root `.env*` files in [`crate::config_refs`].
+
+use rayon::prelude::*;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Read;
+use std::sync::OnceLock;
+
+use crate::types::{RepoState, ScanConfig, ScannedFile};
+
+/// Bytes read from the top of each file when looking for a header.
+const HEADER_SCAN_BYTES: u64 = 4096;
+
+/// License files larger than this are not read.
+const MAX_LICENSE_FILE_BYTES: u64 = 256 * 1024;
+
+/// Maximum length of a recorded copyright line.
+const MAX_COPYRIGHT_LEN: usize = 100;
+
+/// SPDX value for a license file whose text was not recognized.
+pub const NOASSERTION: &str = "NOASSERTION";
+
+/// A license declared in a file's own header.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FileLicense {
+    pub path: String,
+    /// SPDX expression, e.g. `MIT` or `Apache-2.0 OR MIT`.
+    pub license: String,
+    /// 1-based line of the tag or notice.
+    pub line: usize,
+    /// Copyright holder text after `Copyright (c)`.
+    pub copyright: Option<String>,
+}
+
+/// The license files in one directory, governing its subtree.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DirLicense {
+    /// Directory relative to the repo root; empty for the root.
+    pub dir: String,
+    pub license: String,
+    /// License file paths, sorted.
+    pub files: Vec<String>,
+    pub copyright: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct LicenseIndex {
+    /// Directory → its license files.
+    pub dirs: BTreeMap<String, DirLicense>,
+    /// File headers with a license, sorted by path.
+    pub headers: Vec<FileLicense>,
+}
+
+impl LicenseIndex {
+    pub fn header(&self, path: &str) -> Option<&FileLicense> {
+        let i = self.headers.binary_search_by(|h| h.path.as_str().cmp(path)).ok()?;
+        Some(&self.headers[i])
+    }
+
+    /// The nearest license files in `path`'s directory or an ancestor.
+    pub fn governing(&self, path: &str) -> Option<&DirLicense> {
+        let mut dir = path;
+        loop {
+            dir = dir.rfind('/').map_or("", |i| &dir[..i]);
+            if let Some(d) = self.dirs.get(dir) {
+                return Some(d);
+            }
+            if dir.is_empty() {
+                return None;
+            }
+        }
+    }
+
+    /// Effective license of a file: its header's, else the governing license file's.
+    pub fn effective(&self, path: &str) -> Option<&str> {
+        match self.header(path) {
+            Some(h) => Some(&h.license),
+            None => self.governing(path).map(|d| d.license.as_str()),
+        }
+    }
+
+    /// Effective licenses across `paths` with file counts, most common first.
+    pub fn summarize<'a>(
+        &'a self,
+        paths: impl IntoIterator<Item = &'a str>,
+    ) -> Vec<(&'a str, usize)> {
+        let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+        for p in paths {
+            if let Some(l) = self.effective(p) {
+                *counts.entry(l).or_default() += 1;
+            }
+        }
+        let mut out: Vec<(&str, usize)> = counts.into_iter().collect();
+        out.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        out
+    }
+
+    /// Headers whose license differs from the license file governing them.
+    pub fn divergent_headers(&self) -> impl Iterator<Item = &FileLicense> {
+        self.headers
+            .iter()
+            .filter(|h| self.governing(&h.path).is_none_or(|d| d.license != h.license))
+    }
+}
+
+/// `MIT` for a single license, `MIT (40), GPL-2.0-only (1)` for several.
+pub fn format_summary(summary: &[(&str, usize)]) -> String {
+    match summary {
+        [(license, _)] => license.to_string(),
+        _ => summary.iter().map(|(l, n)| format!("{l} ({n})")).collect::<Vec<_>>().join(", "),
+    }
+}
+
+fn spdx_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"SPDX-License-Identifier:\s*([^\s*].*)").unwrap())
+}
+
+fn copyright_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"(?i)\bcopyright\b\s*(?:(?:\(c\)|©)\s*(\S.*)|(\d{4}.*))").unwrap()
+    })
+}
+
+/// Strip comment closers and whitespace from the end of a captured value.
+fn trim_value(s: &str) -> &str {
+    s.trim().trim_end_matches("*/").trim_end_matches("-->").trim_end_matches("#}").trim()
+}
+
+/// Identify a license notice or full license text by its distinctive wording.
+pub fn identify_text(text: &str) -> Option<String> {
+    let t = text.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    let later = t.contains("any later version");
+    let gnu = |base: &str, v3: bool, v2: &str| {
+        let version = if v3 { "3.0" } else { v2 };
+        format!("{base}-{version}-{}", if later { "or-later" } else { "only" })
+    };
+    let id = if t.contains("apache license") && t.contains("version 2.0") {
+        "Apache-2.0".to_string()
+    } else if t.contains("gnu affero general public license") {
+        gnu("AGPL", true, "3.0")
+    } else if t.contains("gnu lesser general public license") {
+        gnu("LGPL", t.contains("version 3"), "2.1")
+    } else if t.contains("gnu general public license") {
+        gnu("GPL", t.contains("version 3"), "2.0")
+    } else if t.contains("mozilla public license") && t.contains("2.0") {
+        "MPL-2.0".to_string()
+    } else if t.contains("eclipse public license") && t.contains("2.0") {
+        "EPL-2.0".to_string()
+    } else if t.contains("boost software license") {
+        "BSL-1.0".to_string()
+    } else if t.contains("free and unencumbered software released into the public domain") {
+        "Unlicense".to_string()
+    } else if t.contains("cc0 1.0 universal") {
+        "CC0-1.0".to_string()
+    } else if t.contains("permission is hereby granted, free of charge") {
+        "MIT".to_string()
+    } else if t.contains("redistribution and use in source and binary forms") {
+        if t.contains("neither the name") || t.contains("names of its contributors") {
+            "BSD-3-Clause".to_string()
+        } else {
+            "BSD-2-Clause".to_string()
+        }
+    } else if t.contains("permission to use, copy, modify, and/or distribute this software")
+        || t.contains("permission to use, copy, modify, and distribute this software for any purpose with or without fee")
+    {
+        "ISC".to_string()
+    } else if t.contains("this software is provided 'as-is'") && t.contains("altered source versions") {
+        "Zlib".to_string()
+    } else {
+        return None;
+    };
+    Some(id)
+}
+
+fn copyright(text: &str) -> Option<String> {
+    text.lines().find_map(|line| {
+        let c = copyright_re().captures(line)?;
+        let value = trim_value(c.get(1).or(c.get(2))?.as_str());
+        (!value.is_empty()).then(|| value.chars().take(MAX_COPYRIGHT_LEN).collect())
+    })
+}
+
+fn is_comment(trimmed: &str) -> bool {
+    ["//", "#", "/*", "*", "--", ";", "<!--", "%", "\"\"\"", "'''", "{-", "(*", "rem ", "REM "]
+        .iter()
+        .any(|p| trimmed.starts_with(p))
+}
+
+/// Detect the license in a file's header: the leading comment block, after any shebang
+/// or `<?php`/`<?xml` line.
+pub fn detect_header(rel_path: &str, head: &str) -> Option<FileLicense> {
+    let mut block = String::new();
+    let mut first_line = 0;
+    let mut in_docstring = false;
+    let mut spdx: Option<(String, usize)> = None;
+    for (i, line) in head.lines().enumerate() {
+        let trimmed = line.trim();
+        if i == 0 && (trimmed.starts_with("#!") || trimmed.starts_with("<?")) {
+            continue;
+        }
+        if trimmed.is_empty() {
+            continue;
+        }
+        // Block comments and docstrings continue until their closer
+        let delimiter = trimmed.starts_with("\"\"\"") || trimmed.starts_with("'''");
+        if !(in_docstring || is_comment(trimmed)) {
+            break;
+        }
+        if delimiter
+            && !(trimmed.len() > 3 && (trimmed.ends_with("\"\"\"") || trimmed.ends_with("'''")))
+        {
+            in_docstring = !in_docstring;
+        } else if trimmed.starts_with("/*") && !trimmed.contains("*/") {
+            in_docstring = true;
+        } else if in_docstring && (trimmed.contains("*/") || trimmed.ends_with("-->")) {
+            in_docstring = false;
+        }
+        if spdx.is_none() {
+            spdx = spdx_re().captures(line).map(|c| (trim_value(&c[1]).to_string(), i + 1));
+        }
+        if block.is_empty() {
+            first_line = i + 1;
+        }
+        block.push_str(line);
+        block.push('\n');
+    }
+    let (license, line) = match spdx {
+        Some(tag) => tag,
+        None => (identify_text(&block)?, first_line),
+    };
+    Some(FileLicense { path: rel_path.to_string(), license, line, copyright: copyright(&block) })
+}
+
+/// Read the head of one file and detect its header license.
+pub fn detect_file_license(file: &ScannedFile) -> Option<FileLicense> {
+    let mut head = Vec::new();
+    std::fs::File::open(&file.abs_path)
+        .ok()?
+        .take(HEADER_SCAN_BYTES)
+        .read_to_end(&mut head)
+        .ok()?;
+    detect_header(&file.rel_path, &String::from_utf8_lossy(&head))
+}
+
+/// `LICENSE`, `LICENCE.md`, `COPYING`, `LICENSE-MIT`, `UNLICENSE`, ... but not
+/// source files such as `license.rs`.
+pub fn is_license_file(name: &str) -> bool {
+    let upper = name.to_uppercase();
+    let (stem, ext) = match upper.split_once('.') {
+        Some((stem, _)) => (stem, upper.rsplit('.').next().unwrap_or("")),
+        None => (upper.as_str(), ""),
+    };
+    let named = matches!(stem, "LICENSE" | "LICENCE" | "COPYING" | "UNLICENSE")
+        || stem.starts_with("LICENSE-")
+        || stem.starts_with("LICENCE-");
+    named
+        && matches!(
+            ext,
+            "" | "MD" | "TXT" | "RST" | "MARKDOWN" | "LESSER" | "MIT" | "APACHE" | "BSD"
+        )
+}
+
+/// License files in the root and in every directory holding a scanned file.
+pub fn scan_license_dirs(
+    config: &ScanConfig,
+    all_files: &[ScannedFile],
+) -> BTreeMap<String, DirLicense> {
+    let mut dirs: BTreeSet<&str> = BTreeSet::new();
+    dirs.insert("");
+    for f in all_files {
+        let mut path = f.rel_path.as_str();
+        while let Some(i) = path.rfind('/') {
+            path = &path[..i];
+            if !dirs.insert(path) {
+                break;
+            }
+        }
+    }
+    dirs.into_par_iter()
+        .filter_map(|dir| {
+            let entries = std::fs::read_dir(config.root.join(dir)).ok()?;
+            let mut files: Vec<(String, String)> = entries
+                .flatten()
+                .filter(|e| e.file_type().is_ok_and(|t| t.is_file()))
+                .filter_map(|e| {
+                    let name = e.file_name().to_string_lossy().to_string();
+                    let rel = if dir.is_empty() { name.clone() } else { format!("{dir}/{name}") };
+                    if !is_license_file(&name)
+                        || !config.read_policy.permits(&rel)
+                        || e.metadata().map_or(true, |m| m.len() > MAX_LICENSE_FILE_BYTES)
+                    {
+                        return None;
+                    }
+                    Some((rel, std::fs::read_to_string(e.path()).ok()?))
+                })
+                .collect();
+            if files.is_empty() {
+                return None;
+            }
+            files.sort();
+            let licenses: BTreeSet<String> = files
+                .iter()
+                .map(|(_, text)| {
+                    spdx_re()
+                        .captures(text)
+                        .map(|c| trim_value(&c[1]).to_string())
+                        .or_else(|| identify_text(text))
+                        .unwrap_or_else(|| NOASSERTION.to_string())
+                })
+                .collect();
+            let license = licenses.into_iter().collect::<Vec<_>>().join(" OR ");
+            let copyright = files.iter().find_map(|(_, text)| copyright(text));
+            let files = files.into_iter().map(|(p, _)| p).collect();
+            Some((dir.to_string(), DirLicense { dir: dir.to_string(), license, files, copyright }))
+        })
+        .collect()
+}
+
+/// Index license files and file headers across the repo.
+pub fn scan_licenses(config: &ScanConfig, all_files: &[ScannedFile]) -> LicenseIndex {
+    let mut headers: Vec<FileLicense> =
+        all_files.par_iter().filter_map(detect_file_license).collect();
+    sort_headers(&mut headers);
+    LicenseIndex { dirs: scan_license_dirs(config, all_files), headers }
+}
+
+/// Canonical ordering of the header table, required by [`LicenseIndex::header`].
+pub fn sort_headers(headers: &mut [FileLicense]) {
+    headers.sort_by(|a, b| a.path.cmp(&b.path));
+}
+
+/// Render the `cs_modules action=licenses` report. `prefix` filters modules and paths.
+pub fn format_licenses(repo: &RepoState, prefix: &str, limit: usize) -> String {
+    let index = &repo.licenses;
+    let holder = |c: &Option<String>| c.as_ref().map(|c| format!("  © {c}")).unwrap_or_default();
+    let plural =
+        |n: usize, one: &str, many: &str| format!("{n} {}", if n == 1 { one } else { many });
+    let mut out = format!(
+        "{}, {}\n",
+        plural(index.dirs.values().map(|d| d.files.len()).sum(), "license file", "license files"),
+        plural(index.headers.len(), "file with a license header", "files with license headers")
+    );
+
+    let dirs: Vec<&DirLicense> = index
+        .dirs
+        .values()
+        .filter(|d| crate::entries::path_in(&d.dir, prefix) || d.dir.is_empty())
+        .collect();
+    if !dirs.is_empty() {
+        out.push_str("\nLicense files:\n");
+        for d in dirs {
+            out.push_str(&format!(
+                "  {}  {}{}\n",
+                d.files.join(", "),
+                d.license,
+                holder(&d.copyright)
+            ));
+        }
+    }
+
+    let mut modules = String::new();
+    let mut shown = 0usize;
+    for (cat, files) in repo.manifest.iter().filter(|(cat, _)| cat.starts_with(prefix)) {
+        let summary = index.summarize(files.iter().map(|f| f.path.as_str()));
+        if summary.is_empty() {
+            continue;
+        }
+        if shown == limit {
+            modules.push_str("  ... (use prefix filter to narrow)\n");
+            break;
+        }
+        modules.push_str(&format!("  {cat}  {}\n", format_summary(&summary)));
+        shown += 1;
+    }
+    if !modules.is_empty() {
+        out.push_str(&format!("\nModules:\n{modules}"));
+    }
+
+    let divergent: Vec<&FileLicense> =
+        index.divergent_headers().filter(|h| crate::entries::path_in(&h.path, prefix)).collect();
+    if !divergent.is_empty() {
+        out.push_str("\nFile headers differing from their license file:\n");
+        for h in divergent.iter().take(limit) {
+            let inherited = index.governing(&h.path).map_or("none", |d| d.license.as_str());
+            out.push_str(&format!(
+                "  {}:{}  {} (directory: {inherited}){}\n",
+                h.path,
+                h.line,
+                h.license,
+                holder(&h.copyright)
+            ));
+        }
+        if divergent.len() > limit {
+            out.push_str(&format!("  ... and {} more\n", divergent.len() - limit));
+        }
+    }
+    if index.dirs.is_empty() && index.headers.is_empty() {
+        out.push_str("\nNo license files or license headers found.\n");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_spdx_tags_and_notices_in_headers_only() {
+        let rs = "// SPDX-License-Identifier: Apache-2.0 OR MIT\n// Copyright (c) 2023 Acme Corp.\n\nfn main() {}\n";
+        let h = detect_header("src/main.rs", rs).unwrap();
+        assert_eq!((h.license.as_str(), h.line), ("Apache-2.0 OR MIT", 1));
+        assert_eq!(h.copyright.as_deref(), Some("2023 Acme Corp."));
+
+        let c = "/*\n * Copyright 2019 Jane Doe\n *\n * This program is free software; you can redistribute it\n * under the terms of the GNU General Public License as published by the Free\n * Software Foundation; either version 2 of the License, or (at your option)\n * any later version.\n */\n#include <stdio.h>\n";
+        let h = detect_header("lib/util.c", c).unwrap();
+        assert_eq!((h.license.as_str(), h.line), ("GPL-2.0-or-later", 1));
+        assert_eq!(h.copyright.as_deref(), Some("2019 Jane Doe"));
+
+        let py = "#!/usr/bin/env python3\n\"\"\"Tool.\n\nLicensed under the Apache License, Version 2.0.\n\"\"\"\nimport os\n";
+        assert_eq!(detect_header("tool.py", py).unwrap().license, "Apache-2.0");
+
+        // Notice wording in code, not the header, is ignored
+        let code = "use std::fs;\n\nconst TEXT: &str = \"Permission is hereby granted, free of charge\";\n";
+        assert!(detect_header("src/gen.rs", code).is_none());
+    }
+
+    #[test]
+    fn governing_license_is_nearest_and_headers_override() {
+        let dir = |d: &str, l: &str| {
+            (
+                d.to_string(),
+                DirLicense { dir: d.into(), license: l.into(), files: vec![], copyright: None },
+            )
+        };
+        let index = LicenseIndex {
+            dirs: [dir("", "MIT"), dir("vendor/zlib", "Zlib")].into_iter().collect(),
+            headers: vec![FileLicense {
+                path: "src/gpl.c".into(),
+                license: "GPL-2.0-only".into(),
+                line: 1,
+                copyright: None,
+            }],
+        };
+        assert_eq!(index.effective("src/a.rs"), Some("MIT"));
+        assert_eq!(index.effective("vendor/zlib/inflate.c"), Some("Zlib"));
+        assert_eq!(index.effective("src/gpl.c"), Some("GPL-2.0-only"));
+        let summary = index.summarize(["src/a.rs", "src/b.rs", "src/gpl.c"]);
+        assert_eq!(format_summary(&summary), "MIT (2), GPL-2.0-only (1)");
+        assert_eq!(index.divergent_headers().count(), 1);
+    }
+
+    #[test]
+    fn recognizes_license_file_names() {
+        for name in [
+            "LICENSE",
+            "LICENSE.md",
+            "licence.txt",
+            "COPYING",
+            "COPYING.LESSER",
+            "LICENSE-MIT",
+            "UNLICENSE",
+        ] {
+            assert!(is_license_file(name), "{name}");
+        }
+        for name in ["license.rs", "licenses.ts", "LICENSE.json", "README.md"] {
+            assert!(!is_license_file(name), "{name}");
+        }
+    }
+}
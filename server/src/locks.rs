@@ -0,0 +1,120 @@
+//! Advisory lock files that let several servers on one machine share a repo's caches.
+//!
+//! The stdio MCP server and `codescope web` (or two editor sessions) may index the same
+//! repo at the same time. Each keeps its own in-memory index and file watcher, but the
+//! on-disk artifacts derived from the repo (semantic cache, index handoff) get a single
+//! writer: the first process to lock `<cache_dir>/locks/<hash of root>.lock`. The others
+//! read those caches and never write them. The OS drops the lock when the writer exits,
+//! and the next process that wants to write takes over. Cache pruning leaves alone repos
+//! that another process holds.
+//!
+//! Read-modify-write updates of shared config files (`repos.toml`) are serialized with
+//! [`with_file_lock`].
+
+use std::collections::BTreeMap;
+use std::fs::{File, TryLockError};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Repo roots this process is the cache writer for, with their open lock files.
+static HELD: Mutex<BTreeMap<PathBuf, File>> = Mutex::new(BTreeMap::new());
+
+/// Lock file for a repo root: `<cache_dir>/locks/<hash of root>.lock`.
+fn lock_path(root: &Path) -> Option<PathBuf> {
+    let key = crate::handoff::content_hash(root.to_string_lossy().as_bytes());
+    crate::cache_dir().map(|d| d.join("locks").join(format!("{key:016x}.lock")))
+}
+
+/// Take an exclusive lock on `path` without waiting. `Ok(None)` when another process
+/// (or another handle in this one) holds it.
+fn try_lock_file(path: &Path) -> std::io::Result<Option<File>> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let file = File::options().read(true).write(true).create(true).truncate(false).open(path)?;
+    match file.try_lock() {
+        Ok(()) => Ok(Some(file)),
+        Err(TryLockError::WouldBlock) => Ok(None),
+        Err(TryLockError::Error(e)) => Err(e),
+    }
+}
+
+/// Whether this process may write the caches of the repo at `root`, taking the repo's
+/// lock if nobody holds it. Without a usable lock directory there is nothing to
+/// coordinate with, so the answer is yes.
+pub fn is_writer(root: &Path) -> bool {
+    let mut held = HELD.lock().unwrap();
+    if held.contains_key(root) {
+        return true;
+    }
+    let Some(path) = lock_path(root) else { return true };
+    match try_lock_file(&path) {
+        Ok(Some(mut file)) => {
+            let _ = file.set_len(0);
+            let _ = write!(file, "{}", std::process::id());
+            held.insert(root.to_path_buf(), file);
+            true
+        }
+        Ok(None) => false,
+        Err(e) => {
+            tracing::debug!(path = %path.display(), error = %e, "Repo lock unavailable");
+            true
+        }
+    }
+}
+
+/// Whether another process currently holds the cache lock of the repo at `root`.
+pub fn held_elsewhere(root: &Path) -> bool {
+    if HELD.lock().unwrap().contains_key(root) {
+        return false;
+    }
+    let Some(path) = lock_path(root) else { return false };
+    // Probing takes the lock for a moment; dropping the file releases it
+    path.exists() && matches!(try_lock_file(&path), Ok(None))
+}
+
+/// Process id recorded by the current holder of a repo's lock, when it can be read.
+pub fn writer_pid(root: &Path) -> Option<u32> {
+    let mut content = String::new();
+    File::open(lock_path(root)?).ok()?.read_to_string(&mut content).ok()?;
+    content.trim().parse().ok()
+}
+
+/// Run `f` while holding an exclusive lock on `<path>.lock`, waiting for other processes
+/// to release it first. Runs `f` unlocked if the lock file cannot be opened.
+pub fn with_file_lock<T>(path: &Path, f: impl FnOnce() -> T) -> T {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".lock");
+    let lock = File::options()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path.with_file_name(name))
+        .ok()
+        .filter(|file| file.lock().is_ok());
+    let result = f();
+    drop(lock);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repo_lock_has_one_holder_until_released() {
+        let dir = std::env::temp_dir().join(format!("codescope-locks-{}", std::process::id()));
+        let path = dir.join("repo.lock");
+        let first = try_lock_file(&path).unwrap();
+        assert!(first.is_some());
+        assert!(try_lock_file(&path).unwrap().is_none(), "second holder while locked");
+        drop(first);
+        assert!(try_lock_file(&path).unwrap().is_some(), "lock released on drop");
+
+        let config = dir.join("repos.toml");
+        assert_eq!(with_file_lock(&config, || 7), 7);
+        assert!(dir.join("repos.toml.lock").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
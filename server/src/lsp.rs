@@ -0,0 +1,873 @@
+//! Language Server Protocol facade over the index (`codescope lsp`).
+//!
+//! Speaks LSP over stdio so editors can use the index directly. Four requests are
+//! answered from the same [`SharedState`] the MCP server uses:
+//!
+//! - `workspace/symbol` and `textDocument/documentSymbol`: the functions, types, and
+//!   sections of indexed files ([`crate::stubs::extract_outline`]). Workspace queries
+//!   search each repo's symbol index ([`crate::symbols`]), kept current by scans and the
+//!   file watcher and ranked as `cs_search` ranks definitions.
+//! - `textDocument/definition`: symbols named like the identifier under the cursor,
+//!   preferring the current file, then the files it imports (the import graph and
+//!   cross-repo edges), then the rest of its repo.
+//! - `textDocument/references`: whole-word uses of that identifier in the defining files
+//!   and in the files that import them.
+//!
+//! Like the rest of the index, this is name-based: there is no type checking, so
+//! same-named symbols of unrelated files can be listed together. Files are read from disk
+//! rather than from editor buffers, so the server asks for no document sync; the file
+//! watcher picks up saved edits. Reads follow each repo's `read_allow`/`read_deny`.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::protocol::{self, ErrorObject, Request, Response};
+use crate::symbols::{simple_name, Column, SymbolEntry, SymbolHit};
+use crate::types::{RepoState, ServerState, SharedState};
+
+/// Symbols returned for one `workspace/symbol` query.
+const MAX_WORKSPACE_SYMBOLS: usize = 200;
+
+/// Locations returned for one `textDocument/references` request.
+const MAX_REFERENCES: usize = 1000;
+
+// ---------------------------------------------------------------------------
+// Protocol types
+// ---------------------------------------------------------------------------
+
+/// Zero-based line and column, in the [`PositionEncoding`] agreed at `initialize`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Position {
+    pub line: u32,
+    pub character: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Location {
+    pub uri: String,
+    pub range: Range,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SymbolInformation {
+    pub name: String,
+    pub kind: u32,
+    pub location: Location,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container_name: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentSymbol {
+    pub name: String,
+    pub kind: u32,
+    pub range: Range,
+    pub selection_range: Range,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<DocumentSymbol>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TextDocumentIdentifier {
+    uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TextDocumentPositionParams {
+    text_document: TextDocumentIdentifier,
+    position: Position,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReferenceContext {
+    #[serde(default)]
+    include_declaration: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReferenceParams {
+    #[serde(flatten)]
+    at: TextDocumentPositionParams,
+    #[serde(default)]
+    context: ReferenceContext,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DocumentSymbolParams {
+    text_document: TextDocumentIdentifier,
+}
+
+#[derive(Debug, Deserialize)]
+struct WorkspaceSymbolParams {
+    #[serde(default)]
+    query: String,
+}
+
+/// How `character` offsets count: UTF-16 code units (the LSP default), or UTF-8 bytes
+/// when the client offers them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PositionEncoding {
+    Utf8,
+    Utf16,
+}
+
+impl PositionEncoding {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Utf8 => "utf-8",
+            Self::Utf16 => "utf-16",
+        }
+    }
+
+    /// A column stored in both encodings, in this one.
+    pub fn pick(self, column: Column) -> u32 {
+        match self {
+            Self::Utf8 => column.utf8,
+            Self::Utf16 => column.utf16,
+        }
+    }
+
+    /// Column of byte offset `byte` in `line`.
+    pub fn column(self, line: &str, byte: usize) -> u32 {
+        let prefix = &line[..byte.min(line.len())];
+        match self {
+            Self::Utf8 => prefix.len() as u32,
+            Self::Utf16 => prefix.encode_utf16().count() as u32,
+        }
+    }
+
+    /// Byte offset of `column` in `line`, clamped to the line's end.
+    pub fn byte(self, line: &str, column: u32) -> usize {
+        match self {
+            Self::Utf8 => {
+                let mut byte = (column as usize).min(line.len());
+                while !line.is_char_boundary(byte) {
+                    byte -= 1;
+                }
+                byte
+            }
+            Self::Utf16 => {
+                let mut units = 0;
+                for (byte, c) in line.char_indices() {
+                    if units >= column as usize {
+                        return byte;
+                    }
+                    units += c.len_utf16();
+                }
+                line.len()
+            }
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Symbol index
+// ---------------------------------------------------------------------------
+
+/// One definition of an outlined file, with its ranges in LSP coordinates.
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    /// `fn`, `class`, `struct`, `##`, and so on, as in
+    /// [`OutlineEntry::kind`](crate::stubs::OutlineEntry::kind).
+    pub keyword: String,
+    /// LSP `SymbolKind`.
+    pub kind: u32,
+    /// Name of the enclosing definition.
+    pub container: Option<String>,
+    pub depth: usize,
+    /// From the start of the first line to the end of the last.
+    pub range: Range,
+    /// The name on the first line.
+    pub selection_range: Range,
+}
+
+#[derive(Clone, Copy)]
+struct Definition<'a> {
+    repo: &'a RepoState,
+    symbol: &'a SymbolEntry,
+}
+
+impl Definition<'_> {
+    fn path(&self) -> &str {
+        &self.symbol.path
+    }
+}
+
+/// LSP `SymbolKind` for an outline keyword. Functions inside a type are methods.
+fn symbol_kind(keyword: &str, parent: Option<&str>) -> u32 {
+    match keyword {
+        "fn" if parent.is_some_and(|p| p != "fn") => 6, // Method
+        "fn" => 12,                                     // Function
+        "class" | "impl" | "extension" => 5,            // Class
+        "struct" | "union" | "record" => 23,            // Struct
+        "enum" => 10,                                   // Enum
+        "trait" | "interface" | "protocol" => 11,       // Interface
+        "module" | "defmodule" => 2,                    // Module
+        "namespace" => 3,                               // Namespace
+        "object" => 19,                                 // Object
+        _ if keyword.starts_with(['#', '=']) => 15,     // String, as for document headings
+        _ => 12,
+    }
+}
+
+/// An indexed symbol as an LSP [`Symbol`], with positions in `encoding`.
+fn to_symbol(entry: &SymbolEntry, encoding: PositionEncoding) -> Symbol {
+    let (parent_keyword, container) = match entry.parent.as_deref().map(|p| p.split_once(' ')) {
+        Some(Some((keyword, name))) => (Some(keyword), Some(name.to_string())),
+        _ => (None, None),
+    };
+    let first = entry.line.saturating_sub(1) as u32;
+    let last = entry.end_line.saturating_sub(1) as u32;
+    Symbol {
+        name: entry.name.clone(),
+        keyword: entry.kind.clone(),
+        kind: symbol_kind(&entry.kind, parent_keyword),
+        container,
+        depth: entry.depth,
+        range: Range {
+            start: Position { line: first, character: 0 },
+            end: Position { line: last, character: encoding.pick(entry.end) },
+        },
+        selection_range: Range {
+            start: Position { line: first, character: encoding.pick(entry.name_start) },
+            end: Position { line: first, character: encoding.pick(entry.name_end) },
+        },
+    }
+}
+
+/// The outline of `content` as [`Symbol`]s, each after the one enclosing it.
+pub fn outline_symbols(content: &str, ext: &str, encoding: PositionEncoding) -> Vec<Symbol> {
+    crate::symbols::outline(content, ext, "").iter().map(|e| to_symbol(e, encoding)).collect()
+}
+
+/// Nest symbols listed each after its enclosing one into a [`DocumentSymbol`] tree.
+fn document_symbols(symbols: Vec<Symbol>) -> Vec<DocumentSymbol> {
+    fn close(open: &mut Vec<(usize, DocumentSymbol)>, roots: &mut Vec<DocumentSymbol>) {
+        if let Some((_, done)) = open.pop() {
+            match open.last_mut() {
+                Some((_, parent)) => parent.children.push(done),
+                None => roots.push(done),
+            }
+        }
+    }
+    let mut roots = Vec::new();
+    let mut open: Vec<(usize, DocumentSymbol)> = Vec::new();
+    for s in symbols {
+        while open.last().is_some_and(|(depth, _)| *depth >= s.depth) {
+            close(&mut open, &mut roots);
+        }
+        let symbol = DocumentSymbol {
+            name: s.name,
+            kind: s.kind,
+            range: s.range,
+            selection_range: s.selection_range,
+            children: Vec::new(),
+        };
+        open.push((s.depth, symbol));
+    }
+    while !open.is_empty() {
+        close(&mut open, &mut roots);
+    }
+    roots
+}
+
+// ---------------------------------------------------------------------------
+// Identifiers and URIs
+// ---------------------------------------------------------------------------
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+/// Byte range of the identifier at or just before byte `at` of `line`.
+pub fn word_at(line: &str, at: usize) -> Option<(usize, usize)> {
+    let at = at.min(line.len());
+    let ident_at = |i: usize| line[i..].chars().next().is_some_and(is_ident_char);
+    let at = if ident_at(at) {
+        at
+    } else {
+        let before = line[..at].char_indices().next_back().map(|(i, _)| i)?;
+        if !ident_at(before) {
+            return None;
+        }
+        before
+    };
+    let start = line[..at]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_ident_char(*c))
+        .last()
+        .map_or(at, |(i, _)| i);
+    let end = line[at..].find(|c: char| !is_ident_char(c)).map_or(line.len(), |i| at + i);
+    Some((start, end))
+}
+
+/// Byte offsets of whole-word occurrences of `word` in `line`.
+pub(crate) fn word_occurrences<'a>(
+    line: &'a str,
+    word: &'a str,
+) -> impl Iterator<Item = usize> + 'a {
+    line.match_indices(word).map(|(i, _)| i).filter(move |&i| {
+        !word.is_empty()
+            && !line[..i].chars().next_back().is_some_and(is_ident_char)
+            && !line[i + word.len()..].chars().next().is_some_and(is_ident_char)
+    })
+}
+
+/// `file://` URI of an absolute path, percent-encoding all but unreserved characters.
+pub fn file_uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::from("file://");
+    // Windows paths (`C:/src`) still need the slash before the drive letter
+    if !path.starts_with('/') {
+        uri.push('/');
+    }
+    for b in path.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(b as char)
+            }
+            _ => uri.push_str(&format!("%{b:02X}")),
+        }
+    }
+    uri
+}
+
+/// Path of a `file://` URI, or `None` for other schemes and remote hosts.
+pub fn uri_path(uri: &str) -> Option<PathBuf> {
+    let rest = uri.strip_prefix("file://")?;
+    let rest = rest.strip_prefix("localhost").unwrap_or(rest);
+    if !rest.starts_with('/') {
+        return None;
+    }
+    let bytes = rest.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = if bytes[i] == b'%' { rest.get(i + 1..i + 3) } else { None };
+        match hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+            Some(b) => {
+                decoded.push(b);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    let path = String::from_utf8(decoded).ok()?;
+    // `/C:/src` is `C:/src`
+    let drive = path.len() > 2 && path.as_bytes()[2] == b':';
+    Some(PathBuf::from(if drive { &path[1..] } else { &path }))
+}
+
+/// `path` without `.` components, which scanned paths can carry (`root/./src/a.rs`).
+fn clean_path(path: &Path) -> PathBuf {
+    path.components().collect()
+}
+
+/// The indexed repo holding `path` and the path relative to it. Nested repos resolve to
+/// the innermost; files of external `scan_dirs` are matched by their absolute path.
+fn locate<'a>(state: &'a ServerState, path: &Path) -> Option<(&'a RepoState, String)> {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let inner = state
+        .repos
+        .values()
+        .filter(|repo| path.starts_with(&repo.root))
+        .max_by_key(|repo| repo.root.components().count());
+    if let Some(repo) = inner {
+        let rel = path.strip_prefix(&repo.root).ok()?;
+        return Some((repo, rel.to_string_lossy().replace('\\', "/")));
+    }
+    state.repos.values().find_map(|repo| {
+        let f = repo.all_files.iter().find(|f| clean_path(&f.abs_path) == path)?;
+        Some((repo.as_ref(), f.rel_path.clone()))
+    })
+}
+
+/// Absolute path and content of a repo file, if the read policy lets it be read.
+fn read_file(repo: &RepoState, rel_path: &str) -> Option<(PathBuf, String)> {
+    let abs = crate::paths::resolve_in_repo(&repo.root, &repo.config.read_policy, rel_path).ok()?;
+    let content = crate::encoding::read_to_string(&abs).ok()?;
+    Some((abs, content))
+}
+
+// ---------------------------------------------------------------------------
+// Server
+// ---------------------------------------------------------------------------
+
+/// An LSP session over the shared index.
+pub struct LspServer {
+    state: Arc<SharedState>,
+    encoding: PositionEncoding,
+    hierarchical_symbols: bool,
+    initialized: bool,
+    shutdown: bool,
+    exit_code: Option<i32>,
+}
+
+impl LspServer {
+    pub fn new(state: Arc<SharedState>) -> Self {
+        Self {
+            state,
+            encoding: PositionEncoding::Utf16,
+            hierarchical_symbols: false,
+            initialized: false,
+            shutdown: false,
+            exit_code: None,
+        }
+    }
+
+    /// Set once `exit` arrives: 0 after a `shutdown` request, 1 without one.
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    /// Handle one message; the response for requests, `None` for notifications.
+    pub fn handle(&mut self, msg: &Value) -> Option<Value> {
+        let request: Request = match serde_json::from_value(msg.clone()) {
+            Ok(r) => r,
+            Err(e) => {
+                let error =
+                    ErrorObject::new(protocol::INVALID_REQUEST, format!("Invalid request: {e}"));
+                return Some(Response::error(None, error).to_value());
+            }
+        };
+        let Some(id) = request.id.clone() else {
+            if request.method == "exit" {
+                self.exit_code = Some(if self.shutdown { 0 } else { 1 });
+            }
+            return None;
+        };
+        let outcome = if self.shutdown {
+            Err(ErrorObject::new(protocol::INVALID_REQUEST, "Server is shutting down"))
+        } else if !self.initialized && request.method != "initialize" {
+            Err(ErrorObject::new(protocol::NOT_INITIALIZED, "Server not initialized"))
+        } else {
+            self.dispatch(&request)
+        };
+        let response = match outcome {
+            Ok(result) => Response::result(Some(id), result),
+            Err(error) => Response::error(Some(id), error),
+        };
+        Some(response.to_value())
+    }
+
+    fn dispatch(&mut self, request: &Request) -> Result<Value, ErrorObject> {
+        let invalid = |e: String| ErrorObject::new(protocol::INVALID_PARAMS, e);
+        match request.method.as_str() {
+            "initialize" => Ok(self.initialize(&request.params)),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "workspace/symbol" => {
+                let params: WorkspaceSymbolParams = request.params().map_err(invalid)?;
+                Ok(json!(self.workspace_symbols(&params.query)))
+            }
+            "textDocument/definition" => {
+                let params: TextDocumentPositionParams = request.params().map_err(invalid)?;
+                Ok(json!(self.definition(&params)))
+            }
+            "textDocument/references" => {
+                let params: ReferenceParams = request.params().map_err(invalid)?;
+                Ok(json!(self.references(&params)))
+            }
+            "textDocument/documentSymbol" => {
+                let params: DocumentSymbolParams = request.params().map_err(invalid)?;
+                Ok(self.document_symbol(&params.text_document.uri))
+            }
+            method => Err(ErrorObject::new(
+                protocol::METHOD_NOT_FOUND,
+                format!("Method not found: {method}"),
+            )),
+        }
+    }
+
+    fn initialize(&mut self, params: &Value) -> Value {
+        let offers_utf8 = params
+            .pointer("/capabilities/general/positionEncodings")
+            .and_then(Value::as_array)
+            .is_some_and(|encodings| encodings.iter().any(|e| e == "utf-8"));
+        self.encoding = if offers_utf8 { PositionEncoding::Utf8 } else { PositionEncoding::Utf16 };
+        self.hierarchical_symbols = params
+            .pointer("/capabilities/textDocument/documentSymbol/hierarchicalDocumentSymbolSupport")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        self.initialized = true;
+        json!({
+            "capabilities": {
+                "positionEncoding": self.encoding.name(),
+                "workspaceSymbolProvider": true,
+                "definitionProvider": true,
+                "referencesProvider": true,
+                "documentSymbolProvider": true,
+            },
+            "serverInfo": { "name": "codescope", "version": env!("CARGO_PKG_VERSION") },
+        })
+    }
+
+    /// Symbols matching `query`, ranked by [`crate::symbols::search_symbols`]. An empty
+    /// query lists symbols in path order.
+    fn workspace_symbols(&mut self, query: &str) -> Vec<SymbolInformation> {
+        let state = self.state.snapshot();
+        let mut hits: Vec<(&RepoState, SymbolHit)> = if query.trim().is_empty() {
+            let all = state.repos.values().flat_map(|repo| {
+                repo.symbols
+                    .iter()
+                    .map(move |symbol| (repo.as_ref(), SymbolHit { symbol, score: 0 }))
+            });
+            all.take(MAX_WORKSPACE_SYMBOLS).collect()
+        } else {
+            state
+                .repos
+                .values()
+                .flat_map(|repo| {
+                    let hits = crate::symbols::search_symbols(&repo.symbols, query, |_| true);
+                    hits.into_iter().map(move |hit| (repo.as_ref(), hit))
+                })
+                .collect()
+        };
+        hits.sort_by(|a, b| a.1.rank(&b.1));
+        hits.truncate(MAX_WORKSPACE_SYMBOLS);
+        let paths = abs_paths(hits.iter().map(|(repo, hit)| (*repo, hit.symbol.path.as_str())));
+        hits.into_iter()
+            .filter_map(|(repo, hit)| {
+                let abs = paths.get(&(repo.name.as_str(), hit.symbol.path.as_str()))?;
+                let symbol = to_symbol(hit.symbol, self.encoding);
+                Some(SymbolInformation {
+                    name: symbol.name,
+                    kind: symbol.kind,
+                    location: Location { uri: file_uri(abs), range: symbol.range },
+                    container_name: symbol.container,
+                })
+            })
+            .collect()
+    }
+
+    fn definition(&mut self, params: &TextDocumentPositionParams) -> Vec<Location> {
+        let state = self.state.snapshot();
+        let Some((repo, rel_path, word)) = self.word_under_cursor(&state, params) else {
+            return Vec::new();
+        };
+        let defs = definitions(&state, repo, &rel_path, &word);
+        let paths = abs_paths(defs.iter().map(|d| (d.repo, d.path())));
+        defs.iter()
+            .filter_map(|d| {
+                let abs = paths.get(&(d.repo.name.as_str(), d.path()))?;
+                let range = to_symbol(d.symbol, self.encoding).selection_range;
+                Some(Location { uri: file_uri(abs), range })
+            })
+            .collect()
+    }
+
+    /// Uses of the identifier under the cursor in the files defining it, the files
+    /// importing those, and the current file.
+    fn references(&mut self, params: &ReferenceParams) -> Vec<Location> {
+        let state = self.state.snapshot();
+        let Some((repo, rel_path, word)) = self.word_under_cursor(&state, &params.at) else {
+            return Vec::new();
+        };
+        let defs = definitions(&state, repo, &rel_path, &word);
+        let is_declaration = |file_repo: &str, path: &str, start: Position| {
+            defs.iter().any(|d| {
+                d.repo.name == file_repo
+                    && d.path() == path
+                    && start.line + 1 == d.symbol.line as u32
+                    && start.character == self.encoding.pick(d.symbol.name_start)
+            })
+        };
+
+        let mut files: BTreeSet<(&str, &str)> = BTreeSet::new();
+        files.insert((&repo.name, &rel_path));
+        let defined_in: BTreeSet<(&str, &str)> =
+            defs.iter().map(|d| (d.repo.name.as_str(), d.path())).collect();
+        for (def_repo, def_path) in defined_in {
+            files.insert((def_repo, def_path));
+            if let Some(r) = state.repos.get(def_repo) {
+                let importers = r.import_graph.imported_by.get(def_path).into_iter().flatten();
+                files.extend(importers.map(|p| (def_repo, p.as_str())));
+            }
+            files.extend(
+                state
+                    .cross_repo_edges
+                    .iter()
+                    .filter(|e| e.to_repo == def_repo && e.to_file == def_path)
+                    .map(|e| (e.from_repo.as_str(), e.from_file.as_str())),
+            );
+        }
+
+        let mut locations = Vec::new();
+        for (file_repo, path) in files {
+            let Some(r) = state.repos.get(file_repo) else { continue };
+            let Some((abs, content)) = read_file(r, path) else { continue };
+            let uri = file_uri(&abs);
+            for (i, line) in content.lines().enumerate() {
+                for at in word_occurrences(line, &word) {
+                    let start =
+                        Position { line: i as u32, character: self.encoding.column(line, at) };
+                    if !params.context.include_declaration && is_declaration(file_repo, path, start)
+                    {
+                        continue;
+                    }
+                    let end = Position {
+                        line: i as u32,
+                        character: self.encoding.column(line, at + word.len()),
+                    };
+                    locations.push(Location { uri: uri.clone(), range: Range { start, end } });
+                    if locations.len() >= MAX_REFERENCES {
+                        return locations;
+                    }
+                }
+            }
+        }
+        locations
+    }
+
+    /// The outline of a file as read now, nested when the client supports it.
+    fn document_symbol(&self, uri: &str) -> Value {
+        let state = self.state.snapshot();
+        let Some((repo, rel_path)) = uri_path(uri).and_then(|p| locate(&state, &p)) else {
+            return json!([]);
+        };
+        let Some((abs, content)) = read_file(repo, &rel_path) else { return json!([]) };
+        let lang = crate::lang::detect(&repo.config.languages, &rel_path, &content);
+        let ext = crate::lang::file_stub_ext(lang, &rel_path);
+        let symbols = outline_symbols(&content, ext, self.encoding);
+        if self.hierarchical_symbols {
+            return json!(document_symbols(symbols));
+        }
+        let uri = file_uri(&abs);
+        let flat: Vec<SymbolInformation> = symbols
+            .into_iter()
+            .map(|s| SymbolInformation {
+                name: s.name,
+                kind: s.kind,
+                location: Location { uri: uri.clone(), range: s.range },
+                container_name: s.container,
+            })
+            .collect();
+        json!(flat)
+    }
+
+    /// Repo, path, and identifier at the requested position.
+    fn word_under_cursor<'a>(
+        &self,
+        state: &'a ServerState,
+        params: &TextDocumentPositionParams,
+    ) -> Option<(&'a RepoState, String, String)> {
+        let path = uri_path(&params.text_document.uri)?;
+        let (repo, rel_path) = locate(state, &path)?;
+        let (_, content) = read_file(repo, &rel_path)?;
+        let line = content.lines().nth(params.position.line as usize)?;
+        let at = self.encoding.byte(line, params.position.character);
+        let (start, end) = word_at(line, at)?;
+        Some((repo, rel_path, line[start..end].to_string()))
+    }
+}
+
+/// Absolute paths of repo files, from one pass over each repo that holds any of them.
+fn abs_paths<'a>(
+    files: impl Iterator<Item = (&'a RepoState, &'a str)>,
+) -> HashMap<(&'a str, &'a str), PathBuf> {
+    let mut wanted: HashMap<&str, (&RepoState, HashSet<&str>)> = HashMap::new();
+    for (repo, path) in files {
+        wanted.entry(&repo.name).or_insert_with(|| (repo, HashSet::new())).1.insert(path);
+    }
+    let mut found = HashMap::new();
+    for (repo, paths) in wanted.into_values() {
+        for f in repo.all_files.iter() {
+            if let Some(path) = paths.get(f.rel_path.as_str()) {
+                found.insert((repo.name.as_str(), *path), clean_path(&f.abs_path));
+            }
+        }
+    }
+    found
+}
+
+/// Definitions of `word` as seen from `rel_path`: those in the file itself, else those in
+/// the files it imports, else those elsewhere in its repo, else anywhere. An `impl` or
+/// `extension` block only counts when the type itself is not indexed.
+fn definitions<'a>(
+    state: &'a ServerState,
+    repo: &RepoState,
+    rel_path: &str,
+    word: &str,
+) -> Vec<Definition<'a>> {
+    let mut defs: Vec<Definition<'a>> = state
+        .repos
+        .values()
+        .flat_map(|r| {
+            r.symbols
+                .iter()
+                .filter(|s| !s.is_heading() && simple_name(&s.name) == word)
+                .map(|symbol| Definition { repo: r, symbol })
+        })
+        .collect();
+    let is_block = |d: &Definition| matches!(d.symbol.kind.as_str(), "impl" | "extension");
+    if defs.iter().any(|d| !is_block(d)) {
+        defs.retain(|d| !is_block(d));
+    }
+    let mut imported: HashSet<(&str, &str)> = repo
+        .import_graph
+        .imports
+        .get(rel_path)
+        .into_iter()
+        .flatten()
+        .map(|p| (repo.name.as_str(), p.as_str()))
+        .collect();
+    imported.extend(
+        state
+            .cross_repo_edges
+            .iter()
+            .filter(|e| e.from_repo == repo.name && e.from_file == rel_path)
+            .map(|e| (e.to_repo.as_str(), e.to_file.as_str())),
+    );
+    let tier = |d: &Definition| {
+        if d.repo.name == repo.name && d.path() == rel_path {
+            0
+        } else if imported.contains(&(d.repo.name.as_str(), d.path())) {
+            1
+        } else if d.repo.name == repo.name {
+            2
+        } else {
+            3
+        }
+    };
+    let best = defs.iter().map(tier).min();
+    defs.retain(|d| Some(tier(d)) == best);
+    defs
+}
+
+// ---------------------------------------------------------------------------
+// Transport
+// ---------------------------------------------------------------------------
+
+/// Read one `Content-Length`-framed message body; `None` at end of input.
+pub fn read_message(reader: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut length: Option<usize> = None;
+    let mut header = String::new();
+    loop {
+        header.clear();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let line = header.trim_end();
+        if line.is_empty() {
+            if length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().ok();
+            }
+        }
+    }
+    let mut body = vec![0; length.unwrap_or(0)];
+    reader.read_exact(&mut body)?;
+    Ok(Some(String::from_utf8_lossy(&body).into_owned()))
+}
+
+/// Write one message with its `Content-Length` header.
+pub fn write_message(out: &mut impl Write, msg: &Value) -> io::Result<()> {
+    let body = serde_json::to_string(msg).unwrap_or_default();
+    write!(out, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    out.flush()
+}
+
+/// Serve LSP on stdin/stdout until `exit` or end of input. Returns the process exit code.
+pub fn run_lsp(state: Arc<SharedState>) -> i32 {
+    let stdin = io::stdin();
+    let mut reader = stdin.lock();
+    let stdout = io::stdout();
+    let mut server = LspServer::new(state);
+    tracing::info!("LSP server ready");
+
+    loop {
+        let body = match read_message(&mut reader) {
+            Ok(Some(body)) => body,
+            Ok(None) => return 1,
+            Err(e) => {
+                tracing::warn!(error = %e, "Could not read LSP message");
+                return 1;
+            }
+        };
+        let response = match serde_json::from_str::<Value>(&body) {
+            Ok(msg) => server.handle(&msg),
+            Err(_) => Some(
+                Response::error(None, ErrorObject::new(protocol::PARSE_ERROR, "Parse error"))
+                    .to_value(),
+            ),
+        };
+        if let Some(response) = response {
+            if write_message(&mut stdout.lock(), &response).is_err() {
+                return 1;
+            }
+        }
+        if let Some(code) = server.exit_code() {
+            return code;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_round_trip_through_content_length_framing() {
+        let mut out = Vec::new();
+        write_message(&mut out, &json!({"jsonrpc": "2.0", "method": "exit"})).unwrap();
+        write_message(&mut out, &json!({"id": 1, "text": "héllo"})).unwrap();
+        let mut reader = io::BufReader::new(out.as_slice());
+        let first = read_message(&mut reader).unwrap().unwrap();
+        assert_eq!(first, r#"{"jsonrpc":"2.0","method":"exit"}"#);
+        let second: Value =
+            serde_json::from_str(&read_message(&mut reader).unwrap().unwrap()).unwrap();
+        assert_eq!(second["text"], "héllo");
+        assert!(read_message(&mut reader).unwrap().is_none());
+    }
+
+    #[test]
+    fn uris_and_positions_convert_both_ways() {
+        let path = Path::new("/work/my repo/src/ü.rs");
+        let uri = file_uri(path);
+        assert_eq!(uri, "file:///work/my%20repo/src/%C3%BC.rs");
+        assert_eq!(uri_path(&uri).as_deref(), Some(path));
+        assert_eq!(uri_path("file:///c%3A/src/a.rs"), Some(PathBuf::from("c:/src/a.rs")));
+        assert_eq!(uri_path("untitled:Untitled-1"), None);
+
+        // `𝕏` is two UTF-16 units and four bytes
+        let line = "let 𝕏 = parse_config(x);";
+        let at = PositionEncoding::Utf16.byte(line, 9);
+        assert_eq!(&line[at..at + 5], "parse");
+        assert_eq!(PositionEncoding::Utf16.column(line, at), 9);
+        assert_eq!(PositionEncoding::Utf8.column(line, at), 11);
+        assert_eq!(word_at(line, at).map(|(s, e)| &line[s..e]), Some("parse_config"));
+        // Just past the end of a word still selects it
+        let end = line.find('(').unwrap();
+        assert_eq!(word_at(line, end).map(|(s, e)| &line[s..e]), Some("parse_config"));
+        assert_eq!(word_at(line, 3), Some((0, 3)));
+        assert_eq!(word_at("a + b", 2), None);
+        assert_eq!(word_occurrences("config = configure(config)", "config").count(), 2);
+    }
+}
@@ -6,10 +6,9 @@ use axum::{
 };
 use clap::{CommandFactory, Parser, Subcommand};
 use dashmap::DashMap;
-use rayon::prelude::*;
 use std::collections::BTreeMap;
 use std::path::PathBuf;
-use std::sync::{Arc, RwLock};
+use std::sync::Arc;
 use tracing::{debug, error, info, warn};
 
 use tower_http::compression::CompressionLayer;
@@ -17,11 +16,13 @@ use tower_http::cors::CorsLayer;
 use tower_http::services::{ServeDir, ServeFile};
 use tower_http::trace::TraceLayer;
 
+use codescope_server::access_log::AccessLog;
 use codescope_server::api::*;
 use codescope_server::mcp::run_mcp;
-use codescope_server::scan::*;
 use codescope_server::types::*;
-use codescope_server::{config_dir, data_dir, parse_repos_toml, scan_repo_with_options, tokenizer};
+use codescope_server::{
+    config_dir, data_dir, handoff, indexing, namespace, parse_repos_toml, tokenizer,
+};
 
 // ---------------------------------------------------------------------------
 // CLI definition (clap derive)
@@ -81,6 +82,47 @@ struct Cli {
     /// Bind to 0.0.0.0 instead of 127.0.0.1 (localhost)
     #[arg(long)]
     bind_all: bool,
+
+    /// Write per-stage scan timings to PATH as Chrome trace JSON (open in speedscope or
+    /// Perfetto) once the initial scan finishes
+    #[arg(long, value_name = "PATH")]
+    profile_scan: Option<PathBuf>,
+
+    /// Default `[scopes.<name>]` search scope for cs_search and cs_grep calls that name none
+    #[arg(long, value_name = "NAME")]
+    scope: Option<String>,
+
+    /// Scan every repo with the `[profile.<name>]` settings of its `.codescope.toml`
+    /// (repos with their own `profile` in repos.toml keep it)
+    #[arg(long, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// Scan the configured repos, run a battery of queries against each, print a JSON
+    /// report, and exit non-zero if any check fails (a deployment smoke test)
+    #[arg(long)]
+    check: bool,
+
+    /// Neither load an index handoff from a previous process at startup nor write one on
+    /// shutdown
+    #[arg(long)]
+    no_handoff: bool,
+
+    /// Append a JSON line per tool call and HTTP API request to PATH, with queries hashed
+    /// (rotated at 10 MB; summarized by /api/metrics)
+    #[arg(long, value_name = "PATH")]
+    access_log: Option<PathBuf>,
+
+    /// Share of requests written to the access log, from 0 to 1
+    #[arg(long, value_name = "RATE", default_value_t = 1.0)]
+    access_log_sample: f64,
+
+    /// Keep query text in the access log alongside its hash
+    #[arg(long)]
+    access_log_plaintext: bool,
+
+    /// Message language (e.g. en, es). Default: CODESCOPE_LANG, then LC_ALL/LC_MESSAGES/LANG
+    #[arg(long, global = true)]
+    lang: Option<String>,
 }
 
 #[derive(Subcommand)]
@@ -97,17 +139,59 @@ enum Commands {
         /// Pre-build semantic index cache during init
         #[arg(long)]
         semantic: bool,
+
+        /// Clone or update every repo listed in a YAML org file, register them all in
+        /// ~/.codescope/repos.toml, and index them in the background
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["path", "global", "semantic"])]
+        org_file: Option<PathBuf>,
+
+        /// Directory to clone --org-file repos into (default: the file's `workspace`)
+        #[arg(long, value_name = "DIR", requires = "org_file")]
+        workspace: Option<PathBuf>,
+
+        /// With --org-file, skip the background indexing
+        #[arg(long, requires = "org_file")]
+        no_index: bool,
     },
     /// Check project setup and diagnose issues
     Doctor {
         /// Project path (default: current directory)
         path: Option<PathBuf>,
     },
+    /// Check imports against [boundaries] rules; exits non-zero on violations (for CI)
+    Check {
+        /// Project path (default: current directory)
+        path: Option<PathBuf>,
+    },
+    /// Print the test files affected by changes since a git ref (for CI test selection)
+    TestImpact {
+        /// Project path (default: current directory)
+        path: Option<PathBuf>,
+
+        /// Git ref to compare HEAD against from their merge base (default: the default branch)
+        #[arg(long)]
+        since: Option<String>,
+    },
+    /// List or prune per-repo disk caches (semantic caches, index handoffs)
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+    /// Serve the index over the Language Server Protocol on stdio (for editors): workspace
+    /// and document symbols, go to definition, and find references
+    Lsp,
     /// Launch the web UI in a browser
     Web {
         /// Project path (default: current directory)
         path: Option<PathBuf>,
     },
+    /// Scan repos and write index handoffs for the next server start (run by init --org-file)
+    #[command(hide = true)]
+    Warm {
+        /// Repos to index
+        #[arg(value_name = "NAME=PATH")]
+        repos: Vec<String>,
+    },
     /// Generate shell completions
     Completions {
         /// Shell to generate completions for
@@ -116,6 +200,30 @@ enum Commands {
     },
 }
 
+#[derive(Subcommand)]
+enum CacheAction {
+    /// List cache entries with age, size, and repo
+    Ls,
+    /// Delete cache entries; without options, applies [cache] from ~/.codescope/repos.toml
+    Prune {
+        /// Delete entries unused for longer than this (e.g. 60d, 12h, 2w)
+        #[arg(long, value_name = "AGE", value_parser = codescope_server::cache::parse_duration)]
+        older_than: Option<std::time::Duration>,
+
+        /// Then delete the oldest entries until the total fits (e.g. 5G, 500M)
+        #[arg(long, value_name = "SIZE", value_parser = codescope_server::cache::parse_size)]
+        max_size: Option<u64>,
+
+        /// Delete entries whose repo directory no longer exists
+        #[arg(long)]
+        missing: bool,
+
+        /// Print what would be deleted without deleting it
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
 // ---------------------------------------------------------------------------
 // Graceful shutdown signal
 // ---------------------------------------------------------------------------
@@ -146,21 +254,32 @@ async fn shutdown_signal() {
 
 #[tokio::main]
 async fn main() {
-    // Initialize structured logging
+    let cli = Cli::parse();
+
+    // Initialize structured logging; --check keeps stdout for its JSON report, and `lsp`
+    // for protocol messages
+    let lsp = matches!(cli.command, Some(Commands::Lsp));
+    let log_to_stderr = cli.check || lsp;
     tracing_subscriber::fmt()
         .with_env_filter(
             tracing_subscriber::EnvFilter::from_default_env()
                 .add_directive("codescope=info".parse().unwrap()),
         )
         .with_target(false)
+        .with_writer(move || -> Box<dyn std::io::Write> {
+            if log_to_stderr {
+                Box::new(std::io::stderr())
+            } else {
+                Box::new(std::io::stdout())
+            }
+        })
         .init();
-
-    let cli = Cli::parse();
+    codescope_server::i18n::init(cli.lang.as_deref());
 
     // Handle subcommands
     if let Some(command) = &cli.command {
         match command {
-            Commands::Init { path, global, semantic } => {
+            Commands::Init { path, global, semantic, org_file, workspace, no_index } => {
                 // Build args vector matching init::run_init's expected format
                 let mut args = vec!["init".to_string()];
                 if let Some(p) = path {
@@ -172,8 +291,22 @@ async fn main() {
                 if *semantic {
                     args.push("--semantic".to_string());
                 }
+                if let Some(file) = org_file {
+                    args.push(format!("--org-file={}", file.display()));
+                }
+                if let Some(dir) = workspace {
+                    args.push(format!("--workspace={}", dir.display()));
+                }
+                if *no_index {
+                    args.push("--no-index".to_string());
+                }
                 std::process::exit(codescope_server::init::run_init(&args));
             }
+            Commands::Warm { repos } => {
+                let mut args = vec!["warm".to_string()];
+                args.extend(repos.iter().cloned());
+                std::process::exit(codescope_server::init::run_warm(&args));
+            }
             Commands::Doctor { path } => {
                 let mut args = vec!["doctor".to_string()];
                 if let Some(p) = path {
@@ -181,10 +314,49 @@ async fn main() {
                 }
                 std::process::exit(codescope_server::init::run_doctor(&args));
             }
+            Commands::Check { path } => {
+                let mut args = vec!["check".to_string()];
+                if let Some(p) = path {
+                    args.push(p.display().to_string());
+                }
+                std::process::exit(codescope_server::init::run_check(&args));
+            }
+            Commands::TestImpact { path, since } => {
+                let mut args = vec!["test-impact".to_string()];
+                if let Some(p) = path {
+                    args.push(p.display().to_string());
+                }
+                if let Some(since) = since {
+                    args.push(format!("--since={since}"));
+                }
+                std::process::exit(codescope_server::init::run_test_impact(&args));
+            }
+            Commands::Cache { action } => {
+                let code = match action {
+                    CacheAction::Ls => codescope_server::cache::run_ls(),
+                    CacheAction::Prune { older_than, max_size, missing, dry_run } => {
+                        let policy = codescope_server::cache::PrunePolicy {
+                            max_age: *older_than,
+                            max_size: *max_size,
+                            prune_missing: *missing,
+                        };
+                        codescope_server::cache::run_prune(policy, *dry_run)
+                    }
+                };
+                std::process::exit(code);
+            }
             Commands::Web { path } => {
                 let root = path.clone().unwrap_or_else(|| std::env::current_dir().unwrap());
                 let root = root.canonicalize().unwrap_or_else(|e| {
-                    eprintln!("Error: Path '{}' not found: {}", root.display(), e);
+                    eprintln!(
+                        "{}: {}",
+                        codescope_server::tr!("error-prefix"),
+                        codescope_server::tr!(
+                            "path-not-found",
+                            path = root.display().to_string(),
+                            error = e.to_string()
+                        )
+                    );
                     std::process::exit(1);
                 });
 
@@ -214,6 +386,8 @@ async fn main() {
                     });
                 std::process::exit(status.code().unwrap_or(1));
             }
+            // Served once the repos are registered, like --mcp
+            Commands::Lsp => {}
             Commands::Completions { shell } => {
                 clap_complete::generate(
                     *shell,
@@ -234,16 +408,14 @@ async fn main() {
     // Determine repo list from CLI args
     // ---------------------------------------------------------------------------
 
-    let mut repo_specs: Vec<(String, PathBuf)> = Vec::new();
+    let mut repo_specs: Vec<indexing::RepoSpec> = Vec::new();
 
     // --repo name=/path flags (repeatable)
     for spec in &cli.repos {
         if let Some((name, path)) = spec.split_once('=') {
-            let root = PathBuf::from(path).canonicalize().unwrap_or_else(|e| {
-                error!(repo = name, path = path, error = %e, "Repository path not found");
-                std::process::exit(1);
-            });
-            repo_specs.push((name.to_string(), root));
+            let root = PathBuf::from(path);
+            let root = root.canonicalize().unwrap_or(root);
+            repo_specs.push(indexing::RepoSpec::new(name, root));
         } else {
             error!(spec = spec.as_str(), "Invalid --repo format, expected NAME=PATH");
             std::process::exit(1);
@@ -279,7 +451,7 @@ async fn main() {
             let project_root = project_root.canonicalize().unwrap_or(project_root);
             let name =
                 project_root.file_name().and_then(|n| n.to_str()).unwrap_or("default").to_string();
-            repo_specs.push((name, project_root));
+            repo_specs.push(indexing::RepoSpec::new(&name, project_root));
         }
     }
 
@@ -302,115 +474,150 @@ async fn main() {
     }
 
     // ---------------------------------------------------------------------------
-    // Scan all repos (parallel via rayon)
+    // Index repos in priority order. MCP stdio and LSP modes serve requests while indexing
+    // runs in the background; HTTP mode needs the full index for its pre-computed JSON cache.
     // ---------------------------------------------------------------------------
 
-    let tok_ref = &tok;
-    let repo_states: Vec<RepoState> = repo_specs
-        .par_iter()
-        .map(|(name, root)| scan_repo_with_options(name, root, tok_ref, enable_semantic))
-        .collect();
-
-    let mut repos = BTreeMap::new();
-    let default_repo =
-        if repo_states.len() == 1 { Some(repo_states[0].name.clone()) } else { None };
-    for repo in repo_states {
-        repos.insert(repo.name.clone(), repo);
+    let cwd = std::env::current_dir().ok();
+    if let Some(profile) = &cli.profile {
+        for spec in repo_specs.iter_mut().filter(|s| s.profile.is_none()) {
+            spec.profile = Some(profile.clone());
+        }
     }
-
-    // Build cross-repo import edges
-    let cross_repo_edges = codescope_server::scan::resolve_cross_repo_imports(&repos);
-
-    let total_files: usize = repos.values().map(|r| r.all_files.len()).sum();
-    let total_modules: usize = repos.values().map(|r| r.manifest.len()).sum();
-    info!(files = total_files, modules = total_modules, repos = repos.len(), "Scan complete");
+    // --check scans every repo up front, lazy ones included
+    if cli.check {
+        for spec in &mut repo_specs {
+            spec.lazy = false;
+        }
+    }
+    let repo_specs = indexing::priority_order(repo_specs, cwd.as_deref());
+    let default_repo = if repo_specs.len() == 1 { Some(repo_specs[0].name.clone()) } else { None };
+
+    let access_log = cli.access_log.as_ref().map(|path| {
+        let sample = cli.access_log_sample;
+        match AccessLog::open(path, sample, cli.access_log_plaintext) {
+            Ok(log) => {
+                info!(path = %path.display(), sample, "Writing access log");
+                Arc::new(log)
+            }
+            Err(e) => {
+                error!(error = %e, "Could not open access log");
+                std::process::exit(1);
+            }
+        }
+    });
 
     // Build unified ServerState (shared by MCP and HTTP modes)
     let server_state = ServerState {
-        repos,
+        repos: BTreeMap::new(),
+        pending: indexing::pending_repos(&repo_specs),
+        registered: repo_specs.clone(),
         default_repo,
-        cross_repo_edges,
+        default_scope: cli.scope.clone(),
+        generation: 0,
+        cross_repo_edges: Vec::new(),
         tokenizer: tok,
+        watch: Default::default(),
+        access_log,
         #[cfg(feature = "semantic")]
         semantic_enabled: enable_semantic,
         #[cfg(feature = "semantic")]
         semantic_model: semantic_model.clone(),
     };
-    let state = Arc::new(RwLock::new(server_state));
+    let state = Arc::new(SharedState::new(server_state));
 
-    // Spawn semantic indexing — background by default, blocking with --wait-semantic
+    if cli.check {
+        indexing::index_pending(&state, enable_semantic, false);
+        let report = codescope_server::selfcheck::run(&state.snapshot(), enable_semantic);
+        println!("{}", serde_json::to_string_pretty(&report).unwrap_or_default());
+        std::process::exit(if report.ok { 0 } else { 1 });
+    }
+
+    // Refresh this server's caches and apply the [cache] pruning policy to the rest
+    let roots: Vec<PathBuf> = repo_specs.iter().map(|s| s.root.clone()).collect();
+    std::thread::spawn(move || codescope_server::cache::auto_prune(&roots));
+
+    // Lazy repos are scanned on demand, alongside the startup queue
+    if repo_specs.iter().any(|s| s.lazy) {
+        let state_lazy = Arc::clone(&state);
+        let warm_start = !cli.no_handoff;
+        std::thread::spawn(move || indexing::serve_lazy(&state_lazy, enable_semantic, warm_start));
+    }
+
+    let background = (cli.mcp || lsp) && !cli.wait_semantic;
+    let state_bg = Arc::clone(&state);
     #[cfg(feature = "semantic")]
-    if enable_semantic {
-        let state_bg = Arc::clone(&state);
-        let sem_model = semantic_model.clone();
-        let wait = cli.wait_semantic;
-        let handle = std::thread::spawn(move || {
-            let s = state_bg.read().unwrap();
-            type SemWork = (
-                String,
-                PathBuf,
-                Vec<ScannedFile>,
-                std::sync::Arc<std::sync::RwLock<Option<SemanticIndex>>>,
-                std::sync::Arc<SemanticProgress>,
-            );
-            let work: Vec<SemWork> = s
-                .repos
-                .values()
-                .map(|r| {
-                    (
-                        r.name.clone(),
-                        r.root.clone(),
-                        r.all_files.clone(),
-                        std::sync::Arc::clone(&r.semantic_index),
-                        std::sync::Arc::clone(&r.semantic_progress),
-                    )
-                })
-                .collect();
-            drop(s);
-
-            for (name, root, files, sem_handle, progress) in work {
-                info!(repo = name.as_str(), "Building semantic index...");
-                let sem_start = std::time::Instant::now();
-                if let Some(idx) = codescope_server::semantic::build_semantic_index(
-                    &files,
-                    sem_model.as_deref(),
-                    &progress,
-                    &root,
-                ) {
-                    info!(
-                        repo = name.as_str(),
-                        chunks = idx.chunk_meta.len(),
-                        time_ms = sem_start.elapsed().as_millis() as u64,
-                        "Semantic index ready"
-                    );
-                    *sem_handle.write().unwrap() = Some(idx);
-                }
+    let sem_model = semantic_model.clone();
+    let wait_semantic = cli.wait_semantic;
+    let warm_start = !cli.no_handoff;
+    let profile_scan = cli.profile_scan.clone();
+    let index_all = move || {
+        let start = std::time::Instant::now();
+        indexing::index_pending(&state_bg, enable_semantic, warm_start);
+        let s = state_bg.snapshot();
+        let total_files: usize = s.repos.values().map(|r| r.all_files.len()).sum();
+        let total_modules: usize = s.repos.values().map(|r| r.manifest.len()).sum();
+        info!(
+            files = total_files,
+            modules = total_modules,
+            repos = s.repos.len(),
+            time_ms = start.elapsed().as_millis() as u64,
+            "Scan complete"
+        );
+        if let Some(path) = &profile_scan {
+            match indexing::write_scan_profile(&s, path) {
+                Ok(()) => info!(path = %path.display(), "Wrote scan profile"),
+                Err(e) => warn!(error = %e, "Could not write scan profile"),
             }
-        });
-        if wait {
-            info!("--wait-semantic: blocking until semantic index is ready");
-            handle.join().expect("semantic indexing thread panicked");
-            info!("Semantic index loaded — starting server");
         }
+        drop(s);
+
+        // Spawn semantic indexing — background by default, blocking with --wait-semantic
+        #[cfg(feature = "semantic")]
+        if enable_semantic {
+            build_semantic_indexes(state_bg, sem_model, wait_semantic);
+        }
+        #[cfg(not(feature = "semantic"))]
+        let _ = wait_semantic;
+    };
+    if background {
+        std::thread::spawn(index_all);
+    } else {
+        index_all();
     }
 
+    // Index repos whose root reappears; unpublish those whose root disappears
+    let state_roots = Arc::clone(&state);
+    std::thread::spawn(move || {
+        indexing::watch_missing_roots(&state_roots, enable_semantic, warm_start)
+    });
+
     // Start file watcher for incremental live re-indexing
-    let _watcher = codescope_server::watch::start_watcher(Arc::clone(&state));
+    codescope_server::watch::start_watcher(Arc::clone(&state));
+
+    if lsp {
+        let code = codescope_server::lsp::run_lsp(Arc::clone(&state));
+        if warm_start {
+            handoff::save_all(&state.snapshot());
+        }
+        std::process::exit(code);
+    }
 
     if cli.mcp {
-        run_mcp(state);
+        run_mcp(Arc::clone(&state));
+        if warm_start {
+            handoff::save_all(&state.snapshot());
+        }
         return;
     }
 
-    // HTTP mode — build pre-computed JSON cache from default repo
+    // HTTP mode — build pre-computed JSON cache (repo-qualified when several repos are indexed)
     let cache = {
-        let s = state.read().unwrap();
-        let repo = s.default_repo();
-        let tree = build_tree(&repo.manifest);
+        let s = state.snapshot();
         Arc::new(HttpCache {
-            tree_json: serde_json::to_string(&tree).unwrap(),
-            manifest_json: serde_json::to_string(&repo.manifest).unwrap(),
-            deps_json: serde_json::to_string(&repo.deps).unwrap(),
+            tree_json: serde_json::to_string(&namespace::unified_tree(&s)).unwrap(),
+            manifest_json: serde_json::to_string(&namespace::unified_manifest(&s)).unwrap(),
+            deps_json: serde_json::to_string(&namespace::unified_deps(&s)).unwrap(),
         })
     };
 
@@ -486,7 +693,11 @@ async fn main() {
     };
 
     let sessions: Arc<DashMap<String, McpSession>> = Arc::new(DashMap::new());
-    let mcp_ctx = McpAppContext { state, sessions: sessions.clone(), config: Arc::new(mcp_config) };
+    let mcp_ctx = McpAppContext {
+        state: Arc::clone(&state),
+        sessions: sessions.clone(),
+        config: Arc::new(mcp_config),
+    };
 
     // MCP HTTP transport routes (with origin validation middleware)
     let mcp_router = Router::new()
@@ -519,6 +730,19 @@ async fn main() {
         .route("/api/find", get(api_find))
         .route("/api/context", post(api_context))
         .route("/api/imports", get(api_imports))
+        .route("/api/licenses", get(api_licenses))
+        .route("/api/manifests", get(api_manifests))
+        .route("/api/owners", get(api_owners))
+        .route("/api/impact", get(api_impact))
+        .route("/api/stats", get(api_stats))
+        .route("/api/duplicates", get(api_duplicates))
+        .route("/api/module-graph", get(api_module_graph))
+        .route("/api/violations", get(api_violations))
+        .route("/api/metrics", get(api_metrics))
+        .route_layer(axum::middleware::from_fn_with_state(
+            ctx.clone(),
+            codescope_server::access_log::record_http,
+        ))
         .merge(mcp_router)
         .fallback_service(ServeDir::new(&dist_dir).not_found_service(ServeFile::new(&index_html)))
         .layer(TraceLayer::new_for_http())
@@ -548,4 +772,65 @@ async fn main() {
     eprintln!("CODESCOPE_PORT={port}");
 
     axum::serve(listener, app).with_graceful_shutdown(shutdown_signal()).await.unwrap();
+    if warm_start {
+        handoff::save_all(&state.snapshot());
+    }
+}
+
+/// Build semantic indexes for every indexed repo on a background thread, joining it when
+/// `wait` is set (`--wait-semantic`).
+#[cfg(feature = "semantic")]
+fn build_semantic_indexes(state: Arc<SharedState>, sem_model: Option<String>, wait: bool) {
+    let handle = std::thread::spawn(move || {
+        let s = state.snapshot();
+        type SemWork = (
+            String,
+            PathBuf,
+            Vec<ScannedFile>,
+            codescope_server::noise::NoiseConfig,
+            std::sync::Arc<std::sync::RwLock<Option<SemanticIndex>>>,
+            std::sync::Arc<SemanticProgress>,
+        );
+        let work: Vec<SemWork> = s
+            .repos
+            .values()
+            .map(|r| {
+                (
+                    r.name.clone(),
+                    r.root.clone(),
+                    r.all_files.clone(),
+                    r.config.noise.clone(),
+                    std::sync::Arc::clone(&r.semantic_index),
+                    std::sync::Arc::clone(&r.semantic_progress),
+                )
+            })
+            .collect();
+        drop(s);
+
+        for (name, root, files, noise, sem_handle, progress) in work {
+            info!(repo = name.as_str(), "Building semantic index...");
+            let sem_start = std::time::Instant::now();
+            if let Some(idx) = codescope_server::semantic::build_semantic_index(
+                &files,
+                &noise,
+                sem_model.as_deref(),
+                &progress,
+                &root,
+            ) {
+                info!(
+                    repo = name.as_str(),
+                    chunks = idx.chunk_meta.len(),
+                    time_ms = sem_start.elapsed().as_millis() as u64,
+                    "Semantic index ready"
+                );
+                *sem_handle.write().unwrap() = Some(idx);
+            }
+        }
+        codescope_server::memory::enforce_memory_limits(&state.snapshot());
+    });
+    if wait {
+        info!("--wait-semantic: blocking until semantic index is ready");
+        handle.join().expect("semantic indexing thread panicked");
+        info!("Semantic index loaded — starting server");
+    }
 }
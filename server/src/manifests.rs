@@ -0,0 +1,258 @@
+//! Parsed package manifests (`Cargo.toml`, `package.json`, `go.mod`), served as the
+//! `codescope://{repo}/manifests` resource and `/api/manifests`.
+//!
+//! The manifests are the repo-root ones plus those the dependency scan found for
+//! `cs_modules`, re-read on each request so edits show without a rescan. Each is reported
+//! with its package name and version, a few metadata fields (edition, license, Go version,
+//! ...), `package.json` scripts, workspace members, and its dependency sections as written:
+//! `dependencies`, `dev-dependencies`, `devDependencies`, `require`, and so on, each
+//! mapping a package to its version requirement (or `path:`, `git:`, `workspace`).
+
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::types::RepoState;
+
+/// Manifest file names understood, with their kind.
+const MANIFEST_KINDS: &[(&str, &str)] =
+    &[("Cargo.toml", "cargo"), ("package.json", "npm"), ("go.mod", "go")];
+
+/// `[package]` keys of a `Cargo.toml` reported as metadata.
+const CARGO_METADATA: &[&str] =
+    &["edition", "rust-version", "license", "description", "repository", "publish"];
+
+/// `package.json` keys reported as metadata.
+const NPM_METADATA: &[&str] =
+    &["description", "license", "private", "type", "main", "module", "types", "packageManager"];
+
+/// `package.json` dependency sections.
+const NPM_SECTIONS: &[&str] =
+    &["dependencies", "devDependencies", "peerDependencies", "optionalDependencies"];
+
+/// One parsed manifest.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    /// Path relative to the repo root.
+    pub path: String,
+    /// `cargo`, `npm`, or `go`.
+    pub kind: &'static str,
+    /// Package name (the module path for `go.mod`); `None` for a virtual workspace root.
+    pub name: Option<String>,
+    pub version: Option<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub scripts: BTreeMap<String, String>,
+    /// Workspace member patterns declared by this manifest.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub workspace: Vec<String>,
+    /// Section name → package → version requirement.
+    pub dependencies: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+/// Every manifest of a repo, sorted by path. Files that fail to parse are left out.
+pub fn load_manifests(repo: &RepoState) -> Vec<Manifest> {
+    let mut paths: BTreeSet<String> = repo.deps.values().map(|d| d.manifest.clone()).collect();
+    for (file, _) in MANIFEST_KINDS {
+        if repo.root.join(file).is_file() {
+            paths.insert(file.to_string());
+        }
+    }
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let file = path.rsplit('/').next().unwrap_or(&path);
+            let kind = MANIFEST_KINDS.iter().find(|(name, _)| *name == file)?.1;
+            let full = crate::paths::resolve_in_repo(&repo.root, &repo.config.read_policy, &path);
+            let content = crate::encoding::read_to_string(&full.ok()?).ok()?;
+            parse_manifest(path, kind, &content)
+        })
+        .collect()
+}
+
+/// Parse manifest `content` of the given kind.
+pub fn parse_manifest(path: String, kind: &'static str, content: &str) -> Option<Manifest> {
+    let mut manifest = Manifest {
+        path,
+        kind,
+        name: None,
+        version: None,
+        metadata: BTreeMap::new(),
+        scripts: BTreeMap::new(),
+        workspace: Vec::new(),
+        dependencies: BTreeMap::new(),
+    };
+    match kind {
+        "cargo" => parse_cargo(content, &mut manifest)?,
+        "npm" => parse_package_json(content, &mut manifest)?,
+        _ => parse_go_mod(content, &mut manifest),
+    }
+    Some(manifest)
+}
+
+fn parse_cargo(content: &str, m: &mut Manifest) -> Option<()> {
+    let table: toml::Table = content.parse().ok()?;
+    if let Some(package) = table.get("package").and_then(|p| p.as_table()) {
+        let text = |key: &str| package.get(key).and_then(toml_text);
+        m.name = text("name");
+        m.version = text("version");
+        for key in CARGO_METADATA {
+            if let Some(value) = text(key) {
+                m.metadata.insert(key.to_string(), value);
+            }
+        }
+    }
+    if let Some(members) = table.get("workspace").and_then(|w| w.get("members")) {
+        let members = members.as_array().into_iter().flatten().filter_map(|v| v.as_str());
+        m.workspace = members.map(String::from).collect();
+    }
+    for section in ["dependencies", "dev-dependencies", "build-dependencies"] {
+        let deps = table.get(section).and_then(|d| d.as_table());
+        add_cargo_section(m, section, deps);
+    }
+    let shared = table.get("workspace").and_then(|w| w.get("dependencies"));
+    add_cargo_section(m, "workspace.dependencies", shared.and_then(|d| d.as_table()));
+    Some(())
+}
+
+fn add_cargo_section(m: &mut Manifest, section: &str, deps: Option<&toml::Table>) {
+    let Some(deps) = deps.filter(|d| !d.is_empty()) else { return };
+    let reqs = deps.iter().map(|(name, spec)| (name.clone(), cargo_requirement(spec))).collect();
+    m.dependencies.insert(section.to_string(), reqs);
+}
+
+/// `"1.0"`, `{ version = "1.0" }`, `{ path = ".." }`, `{ git = ".." }`, or
+/// `{ workspace = true }`.
+fn cargo_requirement(spec: &toml::Value) -> String {
+    if let Some(version) = spec.as_str() {
+        return version.to_string();
+    }
+    let field = |key: &str| spec.get(key).and_then(|v| v.as_str());
+    if let Some(version) = field("version") {
+        version.to_string()
+    } else if let Some(path) = field("path") {
+        format!("path:{path}")
+    } else if let Some(git) = field("git") {
+        format!("git:{git}")
+    } else if spec.get("workspace").and_then(|w| w.as_bool()) == Some(true) {
+        "workspace".to_string()
+    } else {
+        "*".to_string()
+    }
+}
+
+/// Strings as they are, other scalars in TOML syntax.
+fn toml_text(value: &toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(s) => Some(s.clone()),
+        toml::Value::Table(_) | toml::Value::Array(_) => None,
+        other => Some(other.to_string()),
+    }
+}
+
+fn parse_package_json(content: &str, m: &mut Manifest) -> Option<()> {
+    let data: serde_json::Value = serde_json::from_str(content).ok()?;
+    let text = |value: &serde_json::Value| match value {
+        serde_json::Value::String(s) => Some(s.clone()),
+        serde_json::Value::Bool(_) | serde_json::Value::Number(_) => Some(value.to_string()),
+        _ => None,
+    };
+    m.name = data.get("name").and_then(text);
+    m.version = data.get("version").and_then(text);
+    for key in NPM_METADATA {
+        if let Some(value) = data.get(*key).and_then(text) {
+            m.metadata.insert(key.to_string(), value);
+        }
+    }
+    if let Some(node) = data.get("engines").and_then(|e| e.get("node")).and_then(text) {
+        m.metadata.insert("engines.node".to_string(), node);
+    }
+    let strings = |value: Option<&serde_json::Value>| -> BTreeMap<String, String> {
+        let map = value.and_then(|v| v.as_object()).into_iter().flatten();
+        map.filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_string()))).collect()
+    };
+    m.scripts = strings(data.get("scripts"));
+    let workspaces = data.get("workspaces");
+    let members = workspaces.and_then(|w| w.as_array().or_else(|| w.get("packages")?.as_array()));
+    m.workspace = members.into_iter().flatten().filter_map(text).collect();
+    for section in NPM_SECTIONS {
+        let deps = strings(data.get(*section));
+        if !deps.is_empty() {
+            m.dependencies.insert(section.to_string(), deps);
+        }
+    }
+    Some(())
+}
+
+/// `module`, `go`, and `toolchain` lines plus `require` directives, single or in a block.
+/// Requirements marked `// indirect` go to a `require (indirect)` section.
+fn parse_go_mod(content: &str, m: &mut Manifest) {
+    let mut in_require = false;
+    for line in content.lines() {
+        let (code, comment) = line.split_once("//").unwrap_or((line, ""));
+        let code = code.trim();
+        if in_require {
+            if code == ")" {
+                in_require = false;
+            } else {
+                add_go_requirement(m, code, comment);
+            }
+        } else if code == "require (" {
+            in_require = true;
+        } else if let Some(req) = code.strip_prefix("require ") {
+            add_go_requirement(m, req, comment);
+        } else if let Some(module) = code.strip_prefix("module ") {
+            m.name = Some(module.trim().trim_matches('"').to_string());
+        } else if let Some((key @ ("go" | "toolchain"), value)) = code.split_once(' ') {
+            m.metadata.insert(key.to_string(), value.trim().to_string());
+        }
+    }
+}
+
+fn add_go_requirement(m: &mut Manifest, req: &str, comment: &str) {
+    let mut parts = req.split_whitespace();
+    let (Some(module), Some(version)) = (parts.next(), parts.next()) else { return };
+    let section = if comment.trim() == "indirect" { "require (indirect)" } else { "require" };
+    m.dependencies
+        .entry(section.to_string())
+        .or_default()
+        .insert(module.to_string(), version.to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cargo_and_go_manifests_keep_their_sections() {
+        let cargo = r#"
+            [package]
+            name = "acme"
+            version = "0.3.0"
+            edition = "2021"
+
+            [dependencies]
+            serde = { version = "1", features = ["derive"] }
+            util = { path = "../util" }
+            regex = "1.10"
+
+            [dev-dependencies]
+            tempfile = { workspace = true }
+        "#;
+        let m = parse_manifest("Cargo.toml".into(), "cargo", cargo).unwrap();
+        assert_eq!((m.name.as_deref(), m.version.as_deref()), (Some("acme"), Some("0.3.0")));
+        assert_eq!(m.metadata["edition"], "2021");
+        assert_eq!(m.dependencies["dependencies"]["serde"], "1");
+        assert_eq!(m.dependencies["dependencies"]["util"], "path:../util");
+        assert_eq!(m.dependencies["dev-dependencies"]["tempfile"], "workspace");
+
+        let go = "module example.com/acme\n\ngo 1.22\n\nrequire (\n\tgithub.com/a/b v1.2.0\n\
+                  \tgolang.org/x/sys v0.20.0 // indirect\n)\nrequire example.com/c v0.1.0\n";
+        let m = parse_manifest("go.mod".into(), "go", go).unwrap();
+        assert_eq!(m.name.as_deref(), Some("example.com/acme"));
+        assert_eq!(m.metadata["go"], "1.22");
+        assert_eq!(m.dependencies["require"].len(), 2);
+        assert_eq!(m.dependencies["require (indirect)"]["golang.org/x/sys"], "v0.20.0");
+    }
+}
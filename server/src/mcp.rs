@@ -1,19 +1,31 @@
 //! MCP JSON-RPC server implementing the Model Context Protocol.
 //!
-//! Handles tool dispatch for 9 consolidated tools (`cs_search`, `cs_grep`, `cs_read`,
-//! `cs_modules`, `cs_imports`, `cs_git`, `cs_status`, `cs_rescan`, `cs_add_repo`),
-//! protocol version negotiation, and legacy tool name translation for backward compatibility.
+//! Handles tool dispatch for 15 consolidated tools (`cs_search`, `cs_grep`, `cs_read`,
+//! `cs_modules`, `cs_imports`, `cs_tests`, `cs_config_refs`, `cs_symbols`, `cs_git`,
+//! `cs_conventions`, `cs_owners`, `cs_duplicates`, `cs_status`, `cs_rescan`, `cs_add_repo`),
+//! protocol version negotiation, per-tool schema versions, and the legacy tool shim registry
+//! that keeps pre-consolidation tool names working.
+//! Module docs, conventions, and package manifests are served as
+//! `codescope://{repo}/module/{name}/docs`, `codescope://{repo}/conventions`, and
+//! `codescope://{repo}/manifests` resources, and user-defined prompts as MCP prompts.
 
 use crate::budget::{allocate_budget, BudgetUnit, DEFAULT_TOKEN_BUDGET};
-use crate::fuzzy::run_search;
+use crate::entries::{self, EntryKind, EntryPoint};
+use crate::fuzzy::run_search_ranked;
+use crate::impact::import_targets;
+use crate::protocol::{
+    self, CallToolParams, CallToolResult, ContentBlock, EmptyResult, ErrorObject, GetPromptParams,
+    GetPromptResult, Implementation, InitializeParams, InitializeResult, ListPromptsResult,
+    ListResourceTemplatesResult, ListResourcesResult, ListToolsResult, PromptMessage,
+    PromptsCapability, ReadResourceParams, ReadResourceResult, Request, Resource, ResourceContents,
+    ResourceTemplate, ResourcesCapability, Response, ServerCapabilities, ToolsCapability,
+};
 use crate::scan::get_category_path;
-use crate::stubs::extract_stubs;
+use crate::stubs::extract_file_stubs;
 use crate::types::*;
-use regex::RegexBuilder;
-use std::collections::{BTreeMap, HashSet, VecDeque};
-use std::fs;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::io::{self, BufRead, Write as IoWrite};
-use std::sync::{Arc, RwLock};
+use std::sync::Arc;
 
 // ---------------------------------------------------------------------------
 // Repo resolution helper
@@ -24,7 +36,7 @@ fn resolve_repo<'a>(
     args: &serde_json::Value,
 ) -> Result<&'a RepoState, String> {
     match args.get("repo").and_then(|v| v.as_str()) {
-        Some(name) => state.repos.get(name).ok_or_else(|| {
+        Some(name) => state.repos.get(name).map(|r| &**r).ok_or_else(|| {
             let available: Vec<&str> = state.repos.keys().map(|k| k.as_str()).collect();
             format!("Unknown repo '{name}'. Available: {}", available.join(", "))
         }),
@@ -54,11 +66,223 @@ fn resolve_repos_for_search<'a>(
             None => vec![],
         },
         None if state.repos.len() == 1 => vec![state.repos.values().next().unwrap()],
-        None => state.repos.values().collect(),
+        None => state.repos.values().map(|r| &**r).collect(),
     }
 }
 
+/// Apply the `scope` argument, or the server's `--scope` default, to the repos searched.
+/// Repos that do not define the scope are dropped; it is an error if none do.
+fn resolve_scope<'a>(
+    state: &ServerState,
+    args: &serde_json::Value,
+    repos: Vec<&'a RepoState>,
+) -> Result<(Vec<&'a RepoState>, Option<String>), String> {
+    let Some(name) = args["scope"].as_str().map(str::to_string).or(state.default_scope.clone())
+    else {
+        return Ok((repos, None));
+    };
+    let defining: Vec<&RepoState> =
+        repos.iter().copied().filter(|r| r.config.scopes.contains_key(&name)).collect();
+    if defining.is_empty() {
+        let mut known: Vec<&str> =
+            repos.iter().flat_map(|r| r.config.scopes.keys().map(String::as_str)).collect();
+        known.sort_unstable();
+        known.dedup();
+        return Err(if known.is_empty() {
+            format!("Unknown scope '{name}': no [scopes] defined in .codescope.toml")
+        } else {
+            format!("Unknown scope '{name}'. Defined scopes: {}", known.join(", "))
+        });
+    }
+    Ok((defining, Some(name)))
+}
+
+/// Module name for output headers: qualified (`repo/module`) when several repos are indexed.
+fn module_label(state: &ServerState, repo: &RepoState, module: &str) -> String {
+    if state.repos.len() > 1 {
+        crate::namespace::qualify(&repo.name, module)
+    } else {
+        module.to_string()
+    }
+}
+
+/// Resolve a `cs_read` path, plain or `[repo] path`. A prefix overrides the `repo` argument.
+fn resolve_read_path<'a>(
+    state: &'a ServerState,
+    args: &serde_json::Value,
+    spec: &str,
+) -> Result<crate::paths::ResolvedPath<'a>, String> {
+    let fallback = match crate::paths::split_repo_prefix(spec) {
+        (Some(_), _) => state.default_repo(),
+        (None, _) => resolve_repo(state, args)?,
+    };
+    crate::paths::resolve(state, spec, fallback)
+}
+
+/// ` (N files)` after a `cs_imports` heading that selected more than one file.
+fn set_note(targets: &[String]) -> String {
+    if targets.len() > 1 {
+        format!(" ({} files)", targets.len())
+    } else {
+        String::new()
+    }
+}
+
+/// The selected files an aggregated `cs_imports` edge connects to, first few by name.
+fn via_note(via: &[&str]) -> String {
+    const SHOWN: usize = 3;
+    let more =
+        if via.len() > SHOWN { format!(", +{} more", via.len() - SHOWN) } else { String::new() };
+    let names: Vec<&str> = via.iter().take(SHOWN).copied().collect();
+    format!("  via {}{more}", names.join(", "))
+}
+
+/// Header detail and body for a whole-file re-read: a note when `content` is what the
+/// last read served (`hash`), else the diff against it (`old`) when that is shorter.
+fn reread_body(
+    hash: u64,
+    old: Option<&str>,
+    content: &str,
+    lines: usize,
+) -> Option<(String, String)> {
+    const FULL: &str = "pass diff=false for the full content";
+    if hash == crate::handoff::content_hash(content.as_bytes()) {
+        let note = format!("[unchanged since your last read; {FULL}]\n");
+        return Some((format!("{lines} lines, unchanged"), note));
+    }
+    let (hunks, added, removed) = crate::git::diff_text(old?, content)?;
+    (hunks.len() < content.len()).then(|| {
+        (
+            format!("{lines} lines, +{added} -{removed} since your last read"),
+            format!("[diff since your last read; {FULL}]\n{hunks}"),
+        )
+    })
+}
+
+/// Lines `start..=end` (1-based) as `42: text`. Lines where one of `marks` starts end in
+/// `  [fn name, lines 42-97]`, so a follow-up read can ask for exactly that range.
+fn numbered_lines(
+    lines: &[&str],
+    start: usize,
+    end: usize,
+    marks: &[crate::stubs::SymbolMark],
+) -> String {
+    let width = end.to_string().len();
+    let mut marks = marks.iter().peekable();
+    let mut out = String::new();
+    for i in start..=end {
+        out.push_str(&format!("{i:>width$}: {}", lines[i - 1]));
+        while let Some(mark) = marks.next_if(|m| m.start < i) {
+            if mark.start + 1 == i {
+                out.push_str(&format!("  [{}, lines {i}-{}]", mark.label, mark.end + 1));
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// `cs_read mode=outline` body: one `kind name  lines 42-97` line per definition,
+/// indented by nesting.
+fn outline_text(resolved: &crate::paths::ResolvedPath<'_>, raw: &str) -> (usize, String) {
+    let lang = crate::lang::detect(&resolved.repo.config.languages, &resolved.rel_path, raw);
+    let entries =
+        crate::stubs::extract_outline(raw, crate::lang::file_stub_ext(lang, &resolved.rel_path));
+    if entries.is_empty() {
+        return (0, "(no functions or types found)\n".to_string());
+    }
+    let mut out = String::new();
+    for e in &entries {
+        out.push_str(&format!(
+            "{}{} {}  lines {}-{}\n",
+            "  ".repeat(e.depth),
+            e.kind,
+            e.name,
+            e.start_line,
+            e.end_line
+        ));
+    }
+    (entries.len(), out)
+}
+
+/// `Related tests:` footer for a single-file read, using the cheap name and import signals.
+/// Omitted for test files, the compact profile, and files with no linked tests.
+fn related_tests_footer(
+    profile: crate::format::OutputProfile,
+    resolved: &crate::paths::ResolvedPath<'_>,
+) -> String {
+    if !profile.detailed_headers() || crate::testmap::is_test_file(&resolved.rel_path) {
+        return String::new();
+    }
+    let links: Vec<_> = crate::testmap::tests_for_source(resolved.repo, &resolved.rel_path, false)
+        .into_iter()
+        .filter(|l| !l.inline)
+        .collect();
+    if links.is_empty() {
+        return String::new();
+    }
+    let paths: Vec<&str> = links.iter().take(5).map(|l| l.path.as_str()).collect();
+    let more =
+        if links.len() > 5 { format!(" (+{} more)", links.len() - 5) } else { String::new() };
+    format!("\n\nRelated tests: {}{more}", paths.join(", "))
+}
+
 /// Format a path with repo prefix when multiple repos exist.
+/// Indexed files under each directory in `dirs` (display paths, `[repo] `-prefixed when
+/// `multi`), in order.
+fn directory_sizes(repos: &[&RepoState], dirs: &[String], multi: bool) -> Vec<usize> {
+    let mut counts: HashMap<(&str, &str), usize> = dirs
+        .iter()
+        .map(|d| {
+            let (repo, rel) = crate::paths::split_repo_prefix(d);
+            ((repo.unwrap_or(""), rel), 0)
+        })
+        .collect();
+    for repo in repos {
+        let name = if multi { repo.name.as_str() } else { "" };
+        if !counts.keys().any(|(r, _)| *r == name) {
+            continue;
+        }
+        for f in &repo.all_files {
+            for (i, _) in f.rel_path.match_indices('/') {
+                if let Some(count) = counts.get_mut(&(name, &f.rel_path[..i])) {
+                    *count += 1;
+                }
+            }
+        }
+    }
+    dirs.iter()
+        .map(|d| {
+            let (repo, rel) = crate::paths::split_repo_prefix(d);
+            counts[&(repo.unwrap_or(""), rel)]
+        })
+        .collect()
+}
+
+/// A directory line in `cs_search` output.
+fn format_dir_hit(d: &DirHit, profile: crate::format::OutputProfile) -> String {
+    if !profile.detailed_headers() {
+        format!("  {}/ [dir]\n", d.path)
+    } else if profile.show_scores() {
+        format!(
+            "  {}/ — {} of {} files match [dir] (score {:.3})\n",
+            d.path, d.matching, d.files, d.score
+        )
+    } else {
+        format!("  {}/ — {} of {} files match [dir]\n", d.path, d.matching, d.files)
+    }
+}
+
+fn dir_hit_json(d: &DirHit) -> serde_json::Value {
+    serde_json::json!({
+        "kind": "dir",
+        "path": format!("{}/", d.path),
+        "matchingFiles": d.matching,
+        "fileCount": d.files,
+        "score": d.score,
+    })
+}
+
 fn repo_path(repo: &RepoState, path: &str, multi: bool) -> String {
     if multi {
         format!("[{}] {}", repo.name, path)
@@ -67,8 +291,88 @@ fn repo_path(repo: &RepoState, path: &str, multi: bool) -> String {
     }
 }
 
+/// Query words with the repo's `[aliases]`, which `exact` and `regex` modes skip.
+fn query_terms_for(
+    config: &crate::types::ScanConfig,
+    match_mode: &str,
+    terms_lower: &[String],
+) -> Vec<crate::aliases::QueryTerm> {
+    if matches!(match_mode, "exact" | "regex") {
+        crate::aliases::AliasConfig::default().expand(terms_lower)
+    } else {
+        config.aliases.expand(terms_lower)
+    }
+}
+
+/// IDF weight per query word; a word with aliases weighs as its most common spelling.
+fn query_idf_weights(repo: &RepoState, terms: &[crate::aliases::QueryTerm]) -> Vec<f64> {
+    terms
+        .iter()
+        .map(|t| {
+            std::iter::once(&t.term)
+                .chain(&t.aliases)
+                .filter(|w| repo.term_doc_freq.doc_freq(w) > 0)
+                .map(|w| repo.term_doc_freq.idf(w))
+                .reduce(f64::min)
+                .unwrap_or_else(|| repo.term_doc_freq.idf(&t.term))
+        })
+        .collect()
+}
+
+/// Collapse vendored copies in a multi-repo result list: files with byte-identical
+/// content in different repos keep only their highest-ranked row. Returns, parallel to
+/// `display_paths`, `None` for rows folded into an earlier one and `Some(copies)` (the
+/// display paths of the folded rows) for the rest. Only files whose name appears in more
+/// than one repo's results are read and hashed.
+fn collapse_identical_copies(
+    repos: &[&RepoState],
+    display_paths: &[&str],
+) -> Vec<Option<Vec<String>>> {
+    let mut out: Vec<Option<Vec<String>>> = vec![Some(Vec::new()); display_paths.len()];
+    if repos.len() < 2 {
+        return out;
+    }
+    let located: Vec<Option<(&RepoState, &str)>> = display_paths
+        .iter()
+        .map(|p| {
+            let (name, rel) = p.strip_prefix('[')?.split_once("] ")?;
+            Some((*repos.iter().find(|r| r.name == name)?, rel))
+        })
+        .collect();
+    let filename = |rel: &str| rel.rsplit('/').next().unwrap_or(rel).to_string();
+    let mut name_repos: HashMap<String, HashSet<&str>> = HashMap::new();
+    for (repo, rel) in located.iter().flatten() {
+        name_repos.entry(filename(rel)).or_default().insert(repo.name.as_str());
+    }
+
+    // content hash → (kept row, repos already in the group)
+    let mut groups: HashMap<u64, (usize, Vec<&str>)> = HashMap::new();
+    for (i, loc) in located.iter().enumerate() {
+        let Some((repo, rel)) = loc else { continue };
+        if name_repos.get(&filename(rel)).is_none_or(|r| r.len() < 2) {
+            continue;
+        }
+        let Ok(bytes) = std::fs::read(repo.root.join(rel)) else { continue };
+        let hash = crate::handoff::content_hash(&bytes);
+        match groups.get_mut(&hash) {
+            Some((kept, group_repos)) if !group_repos.contains(&repo.name.as_str()) => {
+                group_repos.push(&repo.name);
+                if let Some(copies) = out[*kept].as_mut() {
+                    copies.push(display_paths[i].to_string());
+                }
+                out[i] = None;
+            }
+            Some(_) => {}
+            None => {
+                groups.insert(hash, (i, vec![repo.name.as_str()]));
+            }
+        }
+    }
+    out
+}
+
 // ---------------------------------------------------------------------------
-// Tool definitions (consolidated: 9 tools)
+// Tool definitions (consolidated: 13 tools)
 // ---------------------------------------------------------------------------
 
 fn tool_definitions() -> serde_json::Value {
@@ -96,7 +400,7 @@ fn tool_definitions() -> serde_json::Value {
         {
             "name": "cs_search",
             "annotations": ro,
-            "description": "YOUR PRIMARY DISCOVERY TOOL. Combined search: fuzzy filename + content grep + semantic search (when available) in one call. Returns a unified ranked list. Use this first for discovering files and modules.\n\nReturns files ranked by combined relevance. When semantic search is available, results are automatically fused with keyword matches for better accuracy. Use fileLimit/moduleLimit to control result counts.",
+            "description": "YOUR PRIMARY DISCOVERY TOOL. Combined search: fuzzy filename + content grep + semantic search (when available) in one call. Returns a unified ranked list. Use this first for discovering files and modules.\n\nReturns files ranked by combined relevance. When semantic search is available, results are automatically fused with keyword matches for better accuracy. Files defining a function or type whose name matches the query rank higher and show the definition's line. Terms may be joined with AND (the default) or OR, and -term or !term excludes files whose path names the term (-tests drops test files) and lines that mention it. Use fileLimit/moduleLimit to control result counts.",
             "inputSchema": {
                 "type": "object",
                 "properties": {
@@ -105,9 +409,15 @@ fn tool_definitions() -> serde_json::Value {
                     "ext": { "type": "string", "description": "Comma-separated extensions to filter (e.g. 'h,cpp' or 'rs,ts')" },
                     "path": { "type": "string", "description": "Path prefix to filter files (e.g. 'server/src' or 'src/components')" },
                     "category": { "type": "string", "description": "Module category prefix to filter" },
+                    "scope": { "type": "string", "description": "Named scope from [scopes.<name>] in .codescope.toml: saved path/ext/category filters, combined with the ones above" },
+                    "autocorrect": { "type": "boolean", "description": "When nothing matches and a query word looks misspelled, rerun with the closest indexed term (flagged in the output). Default true; false only suggests the correction." },
+                    "include_generated": { "type": "boolean", "description": "Also search generated files (@generated / DO NOT EDIT headers, .pb.go, *_generated.rs) and vendored trees (vendor/, third_party/). Default: false" },
                     "limit": { "type": "integer", "description": "Max file results (default: 20)" },
                     "fileLimit": { "type": "integer", "description": "Max file results (default: 30, alias for limit)" },
                     "moduleLimit": { "type": "integer", "description": "Max module results (default: 5)" },
+                    "dirLimit": { "type": "integer", "description": "Max directory results mixed into the ranking, labeled [dir] (default: 3, 0 to disable)" },
+                    "context": { "type": "string", "enum": ["symbol"], "description": "'symbol': show the signature of the function or type enclosing each top match above the snippet." },
+                    "profile": { "type": "string", "enum": ["compact", "standard", "verbose"], "description": "Output verbosity: snippet length, context lines, header detail. Defaults to the session profile (standard unless set at initialize)." },
                     "repo": { "type": "string", "description": "Repository name (searches all repos if omitted)" }
                 },
                 "required": ["query"]
@@ -125,10 +435,17 @@ fn tool_definitions() -> serde_json::Value {
                     "ext": { "type": "string", "description": "Comma-separated extensions to filter (e.g. 'h,cpp' or 'rs,go')" },
                     "path": { "type": "string", "description": "Path prefix to filter files (e.g. 'server/src' or 'src/components')" },
                     "category": { "type": "string", "description": "Module category prefix to filter" },
+                    "scope": { "type": "string", "description": "Named scope from [scopes.<name>] in .codescope.toml: saved path/ext/category filters, combined with the ones above" },
+                    "autocorrect": { "type": "boolean", "description": "When nothing matches and a query word looks misspelled, rerun with the closest indexed term (flagged in the output). Default true; false only suggests the correction." },
+                    "include_generated": { "type": "boolean", "description": "Also search generated files (@generated / DO NOT EDIT headers, .pb.go, *_generated.rs) and vendored trees (vendor/, third_party/). Default: false" },
                     "limit": { "type": "integer", "description": "Max files to return. Default: 50" },
                     "max_per_file": { "type": "integer", "description": "Max matching lines shown per file. Default: 8, max: 50" },
-                    "context": { "type": "integer", "description": "Lines of context before/after each match (0-10). Default: 2" },
+                    "max_files": { "type": "integer", "description": "Max candidate files to scan; the rest are skipped with a note. Default: 50000" },
+                    "timeout_ms": { "type": "integer", "description": "Wall-clock budget in milliseconds. When it runs out the partial results are returned, flagged timed_out. Default: 10000, max: 60000" },
+                    "context": { "type": ["integer", "string"], "description": "Lines of context before/after each match (0-10), or 'symbol' to show each match under the signature line of its enclosing function or type. Default: 2 (0 with the compact profile, 4 with verbose)" },
                     "output": { "type": "string", "enum": ["full", "files_only"], "description": "Output mode. 'full' (default): matching lines with context. 'files_only': just filenames and match counts." },
+                    "group_by": { "type": "string", "enum": ["module", "dir"], "description": "Aggregate hits per module or directory: match and file counts plus the top snippet per group. 'limit' then caps groups. Useful for broad queries." },
+                    "profile": { "type": "string", "enum": ["compact", "standard", "verbose"], "description": "Output verbosity: snippet length, context lines, header detail. Defaults to the session profile (standard unless set at initialize)." },
                     "repo": { "type": "string", "description": "Repository name (searches all repos if omitted)" }
                 },
                 "required": ["query"]
@@ -137,7 +454,7 @@ fn tool_definitions() -> serde_json::Value {
         {
             "name": "cs_read",
             "annotations": ro,
-            "description": "Read source files. Use 'path' for a single file, 'paths' for batch reads.\n\nModes:\n- stubs (recommended first): structural outline with class/function signatures, no bodies.\n- full: complete content. For large files, use start_line/end_line.\n\nWith 'paths' + 'budget': budget-aware batch read with importance-weighted allocation.",
+            "description": "Read source files. Use 'path' for a single file, 'paths' for batch reads.\n\nModes:\n- stubs (recommended first): structural outline with class/function signatures, no bodies.\n- full: complete content. For large files, use start_line/end_line.\n- smart: picks full, stubs, or a line range around 'query' matches automatically and says why.\n\nWith 'paths' + 'budget': budget-aware batch read with importance-weighted allocation.",
             "inputSchema": {
                 "type": "object",
                 "properties": {
@@ -147,12 +464,18 @@ fn tool_definitions() -> serde_json::Value {
                         "items": { "type": "string" },
                         "description": "Array of relative paths (batch read, max 50)"
                     },
-                    "mode": { "type": "string", "enum": ["full", "stubs"], "description": "full = complete file, stubs = structural outline only. Default: full" },
+                    "mode": { "type": "string", "enum": ["full", "stubs", "outline", "smart"], "description": "full = complete file, stubs = structural outline only, outline = each function and type with its kind, name, and line range (for precise start_line/end_line follow-ups), smart = pick full/stubs/line range from file size, query, and session history (single file only). Default: full" },
+                    "query": { "type": "string", "description": "Relevance hint: with mode='smart', jumps to the lines matching these terms; with 'budget', weights files and blocks." },
                     "start_line": { "type": "integer", "description": "First line to return (1-based). Single file + mode='full' only." },
                     "end_line": { "type": "integer", "description": "Last line to return (1-based, inclusive). Single file + mode='full' only." },
+                    "diff": { "type": "boolean", "description": "Re-reading a whole file already read in full this session returns only a unified diff against that read (or a note that it is unchanged). Set false for the full content. Default: true" },
+                    "include_docs": { "type": "boolean", "description": "With mode='stubs' (or a smart read that picks stubs): keep the doc comment or docstring of each signature (rustdoc, JSDoc, Javadoc, Python docstrings, Elixir @doc). false leaves out all comments for the shortest outline. Default: true" },
+                    "visibility": { "type": "string", "enum": ["all", "public"], "description": "With mode='stubs' (or a smart read that picks stubs): 'public' keeps only the public surface: no private or internal items, members, and fields (non-pub Rust items, non-public Java/C# members, unexported JS/TS declarations, lowercase Go names, Python _names, Elixir defp, Ruby and C++ private sections). Default: all" },
+                    "symbols": { "type": "boolean", "description": "Number the lines and mark each function/type definition with its kind, name, and line range ([fn run_search, lines 42-97]) for precise start_line/end_line follow-ups. Single file + mode='full' only. Default: false" },
                     "budget": { "type": "integer", "description": "Max token budget for batch reads. Triggers smart compression. Default: 50000" },
-                    "ordering": { "type": "string", "enum": ["importance", "attention"], "description": "Output ordering for budget mode. 'importance' (default): descending by relevance. 'attention': primacy/recency optimized." },
+                    "ordering": { "type": "string", "enum": ["importance", "attention", "interleaved"], "description": "Output ordering for budget mode. 'importance' (default): descending by relevance. 'attention': primacy/recency optimized. 'interleaved': the same per definition instead of per file, the most relevant functions and types of all files at the start and end." },
                     "include_seen": { "type": "boolean", "description": "If true, don't deprioritize previously-read files in budget mode. Default: false" },
+                    "profile": { "type": "string", "enum": ["compact", "standard", "verbose"], "description": "Output verbosity: snippet length, context lines, header detail. Defaults to the session profile (standard unless set at initialize)." },
                     "repo": { "type": "string", "description": "Repository name (optional if single repo)" }
                 }
             }
@@ -160,14 +483,18 @@ fn tool_definitions() -> serde_json::Value {
         {
             "name": "cs_modules",
             "annotations": ro,
-            "description": "Explore module/category structure. Actions:\n- list (default): list modules with file counts\n- files: get all files in a specific module\n- deps: get package-level dependencies from manifests (Cargo.toml, package.json, go.mod). For file-level import relationships, use cs_imports instead.",
+            "description": "Explore module/category structure. Actions:\n- list (default): list modules with file counts, README/docs files, and licenses\n- files: get all files in a specific module\n- deps: get package-level dependencies from manifests (Cargo.toml, package.json, go.mod). For file-level import relationships, use cs_imports instead.\n- graph: module-to-module dependencies computed from actual file imports, with import and file counts per edge and mutual dependencies flagged. depth sets how many category levels make a module; format='dot' returns Graphviz DOT.\n- entries: program entry points (main functions, bin targets, HTTP routes, CLI subcommands) ranked by how many files they reach through imports, plus import-graph roots. Start exploring here.\n- features: Cargo [features] definitions and the files/items gated by #[cfg(feature = ...)], so you know which code only builds with a feature enabled.\n- licenses: SPDX licenses per module from LICENSE/COPYING files and file headers, copyright holders, and files whose header license differs from their directory's. Check before copying code between modules.",
             "inputSchema": {
                 "type": "object",
                 "properties": {
-                    "action": { "type": "string", "enum": ["list", "files", "deps"], "description": "What to do. Default: list" },
-                    "module": { "type": "string", "description": "Module name (required for 'files' and 'deps' actions)" },
-                    "prefix": { "type": "string", "description": "Filter modules by prefix (for 'list' action)" },
-                    "limit": { "type": "integer", "description": "Max modules to return (for 'list' action). Default: 100" },
+                    "action": { "type": "string", "enum": ["list", "files", "deps", "graph", "entries", "features", "licenses"], "description": "What to do. Default: list" },
+                    "module": { "type": "string", "description": "Module name (required for 'files' and 'deps' actions). With several repos, qualify as repo/module when the name exists in more than one" },
+                    "prefix": { "type": "string", "description": "Filter modules by prefix (for 'list' action), or entry points / feature gates / licenses by directory (for 'entries', 'features', and 'licenses' actions)" },
+                    "kind": { "type": "string", "enum": ["main", "bin", "route", "cli"], "description": "Only show entry points of this kind (for 'entries' action)" },
+                    "feature": { "type": "string", "description": "Only show this Cargo feature (for 'features' action)" },
+                    "depth": { "type": "integer", "description": "Category levels per module node (for 'graph' action). Default: 1" },
+                    "format": { "type": "string", "enum": ["text", "dot"], "description": "Output format (for 'graph' action). Default: text" },
+                    "limit": { "type": "integer", "description": "Max modules to return (for 'list' action), or entries per kind / gated items per feature / licensed modules (for 'entries', 'features', and 'licenses' actions). Default: 100 / 50" },
                     "repo": { "type": "string", "description": "Repository name (optional if single repo)" }
                 }
             }
@@ -175,46 +502,136 @@ fn tool_definitions() -> serde_json::Value {
         {
             "name": "cs_imports",
             "annotations": ro,
-            "description": "Find import/include relationships for a file. Shows what a file imports and/or what imports it.\n\nSet transitive=true for impact analysis: finds everything that depends on the file (directly or transitively) via BFS over the import graph.",
+            "description": "Find import/include relationships for a file. Shows what a file imports and/or what imports it, strongest edges first, with the number of named symbols imported and whether an import is type-only.\n\npath may also be a glob (src/auth/*.ts), a directory (server/src/auth/), or a module name: the edges of all its files are combined, leaving out imports between them, so one call answers 'what imports anything under this directory'.\n\nSet transitive=true for impact analysis: finds everything that depends on the file (directly or transitively) via BFS over the import graph. Each depth lists the most strongly coupled dependents first.\n\nSet action=violations to list import edges that break the [boundaries] layering rules in .codescope.toml (no path needed).\n\nSet action=test_impact to select the tests to run for a change: the test files that depend on the changed files (transitively), or are named after or inline in an affected source file. The change is 'path' (file, glob, directory, or module), or else the files changed on HEAD since 'since' (default: the default branch).",
             "inputSchema": {
                 "type": "object",
                 "properties": {
-                    "path": { "type": "string", "description": "Relative path from project root" },
+                    "action": { "type": "string", "enum": ["imports", "violations", "test_impact"], "description": "imports (default): relationships for 'path'. violations: import edges that break [boundaries] rules. test_impact: tests to run for a change to 'path' or since 'since'" },
+                    "path": { "type": "string", "description": "Relative file path, glob, directory, or module name (required unless action=violations)" },
                     "direction": { "type": "string", "enum": ["imports", "imported_by", "both"], "description": "Which direction to query. Default: both" },
                     "transitive": { "type": "boolean", "description": "If true, perform full impact analysis (BFS traversal). Default: false" },
-                    "max_depth": { "type": "integer", "description": "Max traversal depth for impact analysis (default: 5)" },
-                    "limit": { "type": "integer", "description": "Max files to show in impact analysis (default: 50)" },
+                    "since": { "type": "string", "description": "For test_impact without path: git ref to diff HEAD against from their merge base (default: the default branch)" },
+                    "max_depth": { "type": "integer", "description": "Max traversal depth for impact analysis (default: 5; 20 for test_impact)" },
+                    "limit": { "type": "integer", "description": "Max files to show in impact analysis or affected sources in test_impact (default: 50), or violations to list (default: 100)" },
+                    "repo": { "type": "string", "description": "Repository name (optional if single repo)" }
+                }
+            }
+        },
+        {
+            "name": "cs_tests",
+            "annotations": ro,
+            "description": "Map tests to the code they cover. For a source file, lists test files linked by naming convention, import edges, or references to the file's symbols (plus inline #[cfg(test)] modules). For a test file, lists the source files it covers. Use before changing code so tests are updated alongside it.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "for": { "type": "string", "description": "Relative path of a source or test file" },
+                    "limit": { "type": "integer", "description": "Max files to return. Default: 20" },
                     "repo": { "type": "string", "description": "Repository name (optional if single repo)" }
                 },
-                "required": ["path"]
+                "required": ["for"]
+            }
+        },
+        {
+            "name": "cs_config_refs",
+            "annotations": ro,
+            "description": "Find where environment variables and config keys are read (std::env::var, process.env, os.environ, os.Getenv, viper, IConfiguration, System.getenv, ...) and where .env files define them. Without a name, lists every variable/key with reference counts. Answers 'what reads DATABASE_URL' without regex guessing.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string", "description": "Variable or key name (case-insensitive substring). Omit to list all." },
+                    "kind": { "type": "string", "enum": ["env", "config"], "description": "Only environment variables or only config keys" },
+                    "prefix": { "type": "string", "description": "Only references in files under this directory" },
+                    "limit": { "type": "integer", "description": "Max names to return. Default: 50" },
+                    "repo": { "type": "string", "description": "Repository name (optional if single repo)" }
+                }
+            }
+        },
+        {
+            "name": "cs_symbols",
+            "annotations": ro,
+            "description": "Cross-reference schema definitions with code: protobuf messages, services, and rpcs; OpenAPI operationIds and component schemas; GraphQL types and Query/Mutation/Subscription fields. With a name, lists the source lines that implement it (function, method, or type of the same name in snake_case, camelCase, or PascalCase) and that use it. Without a name, lists every definition with implementation and use counts.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string", "description": "Symbol name (case-insensitive substring). Omit to list all." },
+                    "kind": { "type": "string", "description": "Only this kind: message, enum, service, rpc, operation, schema, type, input, interface, union, scalar, query, mutation, subscription" },
+                    "format": { "type": "string", "enum": ["proto", "openapi", "graphql"], "description": "Only definitions from this schema language" },
+                    "prefix": { "type": "string", "description": "Only definitions in schema files under this directory" },
+                    "limit": { "type": "integer", "description": "Max symbols to return. Default: 50" },
+                    "repo": { "type": "string", "description": "Repository name (optional if single repo)" }
+                }
             }
         },
         {
             "name": "cs_git",
             "annotations": ro,
-            "description": "Git history analysis. Actions:\n- blame: who last modified each line of a file\n- history: recent commits that touched a file\n- changed: files changed since a commit/branch/tag\n- hotspots: most frequently changed files (churn ranking)",
+            "description": "Git history analysis. Actions:\n- blame: who last modified each line of a file\n- history: recent commits that touched a file\n- changed: files changed since a commit/branch/tag (default: since branching off the default branch)\n- compare: commits ahead/behind and files changed between two refs\n- diffstat: added/removed lines per module (and its top files) between two refs\n- hotspots: most frequently changed files (churn ranking)\nThe default branch and protected refs come from [git] in .codescope.toml.",
             "inputSchema": {
                 "type": "object",
                 "properties": {
-                    "action": { "type": "string", "enum": ["blame", "history", "changed", "hotspots"], "description": "What to do (required)" },
+                    "action": { "type": "string", "enum": ["blame", "history", "changed", "compare", "diffstat", "hotspots"], "description": "What to do (required)" },
                     "path": { "type": "string", "description": "File path (required for blame/history)" },
-                    "since": { "type": "string", "description": "Commit/branch/tag to diff against (required for 'changed')" },
+                    "since": { "type": "string", "description": "Commit/branch/tag to diff against for 'changed' (default: merge base with the default branch)" },
+                    "base": { "type": "string", "description": "Base ref for 'compare'/'diffstat' (default: the repo's default branch)" },
+                    "head": { "type": "string", "description": "Head ref for 'compare'/'diffstat' (default: HEAD)" },
                     "start_line": { "type": "integer", "description": "First line for blame (1-based, optional)" },
                     "end_line": { "type": "integer", "description": "Last line for blame (1-based, optional)" },
-                    "limit": { "type": "integer", "description": "Max results (default: 10 for history, 20 for hotspots)" },
+                    "limit": { "type": "integer", "description": "Max results (default: 10 for history, 20 for compare commits, diffstat modules, and hotspots)" },
                     "days": { "type": "integer", "description": "Look back N days for hotspots (default: 90)" },
                     "repo": { "type": "string", "description": "Repository name (optional if single repo)" }
                 },
                 "required": ["action"]
             }
         },
+        {
+            "name": "cs_conventions",
+            "annotations": ro,
+            "description": "Report project conventions from tool configs: formatter settings (rustfmt, prettier, editorconfig), lint rules (clippy, Cargo [lints], eslint), and the build/lint/test commands CI runs. Check before writing code so it passes the project's checks.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "repo": { "type": "string", "description": "Repository name (optional if single repo)" }
+                }
+            }
+        },
+        {
+            "name": "cs_owners",
+            "annotations": ro,
+            "description": "Show who owns a file or directory according to the repo's CODEOWNERS: owners with how many files each owns, unowned files, and for a single file the deciding CODEOWNERS rule. Pass owner instead to list the files a team or user owns.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "File or directory (default: whole repo)" },
+                    "owner": { "type": "string", "description": "List files owned by this user or team (e.g. @org/team) instead" },
+                    "limit": { "type": "integer", "description": "Max owners or files listed (default: 50)" },
+                    "repo": { "type": "string", "description": "Repository name (optional if single repo)" }
+                }
+            }
+        },
+        {
+            "name": "cs_duplicates",
+            "annotations": ro,
+            "description": "Find clusters of duplicate files: identical copies, and near-identical ones that differ only in whitespace. Useful for spotting vendored or copy-pasted code before editing one copy. Largest clusters (most redundant lines) first; generated and vendored copies are marked.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "Only files under this directory (default: whole repo)" },
+                    "min_lines": { "type": "integer", "description": "Ignore files shorter than this (default: 5)" },
+                    "limit": { "type": "integer", "description": "Max clusters listed (default: 20)" },
+                    "repo": { "type": "string", "description": "Repository name (optional if single repo)" }
+                }
+            }
+        },
         {
             "name": "cs_status",
             "annotations": ro,
-            "description": "Show indexed repositories, file counts, language breakdown, scan time, and session info (files read, tokens served).",
+            "description": "Show indexed repositories, file counts, language breakdown, scan time, memory, semantic index and file watcher state, and session info (files read, tokens served).",
             "inputSchema": {
                 "type": "object",
-                "properties": {},
+                "properties": {
+                    "format": { "type": "string", "enum": ["text", "json"], "description": "json returns the typed status report (also as structured content). Default: text" },
+                    "detail": { "type": "string", "description": "Only these components, separated by | or comma: repos, semantic, session, watch, memory. Default: all" }
+                },
                 "additionalProperties": false
             }
         },
@@ -225,7 +642,9 @@ fn tool_definitions() -> serde_json::Value {
             "inputSchema": {
                 "type": "object",
                 "properties": {
-                    "repo": { "type": "string", "description": "Specific repo to rescan (default: all)" }
+                    "repo": { "type": "string", "description": "Specific repo to rescan (default: all)" },
+                    "incremental": { "type": "boolean", "description": "Only re-index files that git status, commits since the last scan, or modification times show as changed. Falls back to a full rescan outside git, when switching profile, or when config or manifest files changed. Default: false" },
+                    "profile": { "type": "string", "description": "Switch to this [profile.<name>] of .codescope.toml (e.g. a docs-only index); \"default\" for the top-level settings. Default: keep each repo's current profile" }
                 }
             }
         },
@@ -246,74 +665,709 @@ fn tool_definitions() -> serde_json::Value {
 }
 
 // ---------------------------------------------------------------------------
-// Legacy tool name translation (backward compatibility)
+// Resources (module docs)
+// ---------------------------------------------------------------------------
+
+const MODULE_DOCS_URI_TEMPLATE: &str = "codescope://{repo}/module/{name}/docs";
+
+fn encode_uri_segment(s: &str) -> String {
+    s.replace('%', "%25").replace(' ', "%20")
+}
+
+fn decode_uri_segment(s: &str) -> String {
+    s.replace("%20", " ").replace("%25", "%")
+}
+
+/// Resource URI for a module's docs. Category separators (` > `) become `/`.
+pub fn module_docs_uri(repo: &str, module: &str) -> String {
+    let name: Vec<String> = module.split(" > ").map(encode_uri_segment).collect();
+    format!("codescope://{}/module/{}/docs", encode_uri_segment(repo), name.join("/"))
+}
+
+/// Parse a module docs URI back into `(repo, module)`.
+fn parse_module_docs_uri(uri: &str) -> Option<(String, String)> {
+    let rest = uri.strip_prefix("codescope://")?;
+    let (repo, rest) = rest.split_once('/')?;
+    let name = rest.strip_prefix("module/")?.strip_suffix("/docs")?;
+    if repo.is_empty() || name.is_empty() {
+        return None;
+    }
+    let module: Vec<String> = name.split('/').map(decode_uri_segment).collect();
+    Some((decode_uri_segment(repo), module.join(" > ")))
+}
+
+/// Resource URI for a repo's conventions report.
+pub fn conventions_uri(repo: &str) -> String {
+    format!("codescope://{}/conventions", encode_uri_segment(repo))
+}
+
+/// Resource URI for a repo's parsed package manifests.
+pub fn manifests_uri(repo: &str) -> String {
+    format!("codescope://{}/manifests", encode_uri_segment(repo))
+}
+
+fn resource_list(state: &ServerState) -> ListResourcesResult {
+    let multi = state.repos.len() > 1;
+    let mut resources: Vec<Resource> = state
+        .repos
+        .values()
+        .map(|repo| Resource {
+            uri: conventions_uri(&repo.name),
+            name: format!("{} conventions", repo.name),
+            description: Some("Formatter, linter, and CI conventions (JSON)".to_string()),
+            mime_type: Some("application/json".to_string()),
+        })
+        .collect();
+    resources.extend(state.repos.values().map(|repo| {
+        Resource {
+            uri: manifests_uri(&repo.name),
+            name: format!("{} manifests", repo.name),
+            description: Some(
+                "Cargo.toml, package.json, and go.mod metadata, scripts, and dependencies (JSON)"
+                    .to_string(),
+            ),
+            mime_type: Some("application/json".to_string()),
+        }
+    }));
+    resources.extend(state.repos.values().flat_map(|repo| {
+        repo.module_docs.iter().map(move |(module, docs)| Resource {
+            uri: module_docs_uri(&repo.name, module),
+            name: format!("{} docs", repo_path(repo, module, multi)),
+            description: Some(docs.join(", ")),
+            mime_type: Some("text/markdown".to_string()),
+        })
+    }));
+    ListResourcesResult { resources }
+}
+
+fn resource_templates() -> ListResourceTemplatesResult {
+    ListResourceTemplatesResult {
+        resource_templates: vec![
+            ResourceTemplate {
+                uri_template: "codescope://{repo}/conventions".to_string(),
+                name: "Conventions".to_string(),
+                description: Some(
+                    "Formatter, linter, and CI conventions mined from tool configs, as JSON."
+                        .to_string(),
+                ),
+                mime_type: Some("application/json".to_string()),
+            },
+            ResourceTemplate {
+                uri_template: "codescope://{repo}/manifests".to_string(),
+                name: "Manifests".to_string(),
+                description: Some(
+                    "Every Cargo.toml, package.json, and go.mod: package metadata, scripts, \
+                     workspace members, and dependency sections with version requirements, as \
+                     JSON."
+                        .to_string(),
+                ),
+                mime_type: Some("application/json".to_string()),
+            },
+            ResourceTemplate {
+                uri_template: MODULE_DOCS_URI_TEMPLATE.to_string(),
+                name: "Module docs".to_string(),
+                description: Some("README and docs/ files for a module, concatenated. {name} is the module path from cs_modules with ' > ' written as '/'.".to_string()),
+                mime_type: Some("text/markdown".to_string()),
+            },
+        ],
+    }
+}
+
+/// Read a resource by URI: a conventions report, a repo's manifests, or a module's docs.
+fn read_resource(state: &ServerState, uri: &str) -> Result<ReadResourceResult, String> {
+    let repo_resource = uri
+        .strip_prefix("codescope://")
+        .and_then(|rest| rest.split_once('/'))
+        .filter(|(_, kind)| matches!(*kind, "conventions" | "manifests"));
+    let Some((repo_name, kind)) = repo_resource else {
+        return read_module_docs(state, uri);
+    };
+    let repo_name = decode_uri_segment(repo_name);
+    let repo = state.repos.get(&repo_name).ok_or_else(|| format!("Unknown repo '{repo_name}'"))?;
+    let text = if kind == "manifests" {
+        serde_json::to_string_pretty(&crate::manifests::load_manifests(repo))
+    } else {
+        serde_json::to_string_pretty(&crate::conventions::mine_conventions(repo))
+    };
+    Ok(text_resource(uri, "application/json", text.map_err(|e| e.to_string())?))
+}
+
+/// Read a module docs resource: every doc file for the module, concatenated.
+fn read_module_docs(state: &ServerState, uri: &str) -> Result<ReadResourceResult, String> {
+    let (repo_name, module) =
+        parse_module_docs_uri(uri).ok_or_else(|| format!("Unknown resource URI: {uri}"))?;
+    let repo = state.repos.get(&repo_name).ok_or_else(|| format!("Unknown repo '{repo_name}'"))?;
+    let docs = repo
+        .module_docs
+        .get(&module)
+        .ok_or_else(|| format!("No docs found for module '{module}'"))?;
+
+    let mut text = String::new();
+    for doc in docs {
+        let Ok(full_path) =
+            crate::paths::resolve_in_repo(&repo.root, &repo.config.read_policy, doc)
+        else {
+            continue;
+        };
+        let Ok(raw) = crate::encoding::read_to_string(&full_path) else { continue };
+        if text.len() + raw.len() > MAX_FILE_READ {
+            text.push_str(&format!("[{doc} omitted: docs exceed 512KB]\n"));
+            continue;
+        }
+        text.push_str(&format!("<!-- {doc} -->\n{}\n\n", raw.trim_end()));
+    }
+    Ok(text_resource(uri, "text/markdown", text))
+}
+
+fn text_resource(uri: &str, mime_type: &str, text: String) -> ReadResourceResult {
+    ReadResourceResult {
+        contents: vec![ResourceContents {
+            uri: uri.to_string(),
+            mime_type: Some(mime_type.to_string()),
+            text,
+        }],
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Prompts
 // ---------------------------------------------------------------------------
 
+/// Every prompt on offer, with the repo defining it (`None` for global prompts). A repo's
+/// prompt hides a global one of the same name; between repos the first by name wins.
+fn available_prompts(
+    state: &ServerState,
+) -> BTreeMap<String, (Option<&RepoState>, crate::prompts::PromptDef)> {
+    let mut prompts: BTreeMap<_, _> =
+        crate::prompts::load_global().into_iter().map(|(name, p)| (name, (None, p))).collect();
+    for repo in state.repos.values().rev() {
+        for (name, prompt) in &repo.config.prompts {
+            prompts.insert(name.clone(), (Some(&**repo), prompt.clone()));
+        }
+    }
+    prompts
+}
+
+/// Render a prompt, running `cs_search` and `cs_conventions` for its directives.
+fn get_prompt(state: &ServerState, params: GetPromptParams) -> Result<GetPromptResult, String> {
+    let prompts = available_prompts(state);
+    let Some((repo, prompt)) = prompts.get(&params.name) else {
+        return Err(format!("Unknown prompt: {}", params.name));
+    };
+    let missing = prompt.missing_arguments(&params.arguments);
+    if !missing.is_empty() {
+        return Err(format!(
+            "Prompt '{}' requires argument(s): {}",
+            params.name,
+            missing.join(", ")
+        ));
+    }
+    let search_repo = repo.map(|r| r.name.as_str());
+    let conventions_repo = search_repo
+        .or_else(|| (!state.repos.is_empty()).then(|| state.default_repo().name.as_str()));
+    let text = prompt.render(&params.arguments, |kind, arg| {
+        let (tool, args) = match kind {
+            "search" => ("cs_search", serde_json::json!({ "query": arg, "repo": search_repo })),
+            _ => ("cs_conventions", serde_json::json!({ "repo": conventions_repo })),
+        };
+        handle_tool_call(state, tool, &args, &mut None).0
+    });
+    Ok(GetPromptResult {
+        description: prompt.description.clone(),
+        messages: vec![PromptMessage {
+            role: "user".to_string(),
+            content: ContentBlock::Text { text },
+        }],
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Tool schema versions, deprecation, and legacy shims (backward compatibility)
+// ---------------------------------------------------------------------------
+
+/// `_meta` key carrying a tool's input schema version in `tools/list`.
+const SCHEMA_VERSION_META: &str = "codescope/schemaVersion";
+
+/// `_meta` key marking a listed tool as deprecated, with its replacement.
+const DEPRECATED_META: &str = "codescope/deprecated";
+
+/// `_meta` key listing retired tool names that are shims for a listed tool.
+const REPLACES_META: &str = "codescope/replaces";
+
+/// `_meta` key on `tools/call` results: the index generation the result was computed from.
+const GENERATION_META: &str = "codescope/generation";
+
+/// Input schema version per listed tool. Bump a tool's version whenever its
+/// `inputSchema` changes in a way an agent built against the old schema would notice
+/// (renamed or removed parameters, changed defaults or semantics).
+const TOOL_SCHEMA_VERSIONS: &[(&str, u32)] = &[
+    ("cs_search", 1),
+    ("cs_grep", 1),
+    ("cs_read", 1),
+    ("cs_modules", 1),
+    ("cs_imports", 1),
+    ("cs_tests", 1),
+    ("cs_config_refs", 1),
+    ("cs_symbols", 1),
+    ("cs_git", 1),
+    ("cs_conventions", 1),
+    ("cs_owners", 1),
+    ("cs_duplicates", 1),
+    ("cs_status", 1),
+    ("cs_rescan", 1),
+    ("cs_add_repo", 1),
+];
+
+/// Listed tools that still work but are scheduled for removal: `(name, replacement hint)`.
+/// Deprecated tools get a `DEPRECATED` description prefix and a `codescope/deprecated`
+/// `_meta` entry. Once removed, move them to [`LEGACY_TOOLS`].
+const DEPRECATED_TOOLS: &[(&str, &str)] = &[];
+
+/// Argument value a legacy shim sets before forwarding.
+pub enum ShimArg {
+    Str(&'static str),
+    Bool(bool),
+}
+
+/// A retired tool name still accepted by `tools/call` and forwarded to its replacement.
+pub struct LegacyTool {
+    pub name: &'static str,
+    pub replacement: &'static str,
+    /// Argument set on the forwarded call so the replacement behaves like the old tool.
+    pub set_arg: Option<(&'static str, ShimArg)>,
+}
+
+/// Registry of retired tool names (pre-consolidation API). These are not listed in
+/// `tools/list`; calls are forwarded with a deprecation notice appended to the result.
+pub const LEGACY_TOOLS: &[LegacyTool] = &[
+    LegacyTool { name: "cs_find", replacement: "cs_search", set_arg: None },
+    LegacyTool { name: "cs_semantic_search", replacement: "cs_search", set_arg: None },
+    LegacyTool { name: "cs_read_file", replacement: "cs_read", set_arg: None },
+    LegacyTool { name: "cs_read_files", replacement: "cs_read", set_arg: None },
+    LegacyTool { name: "cs_read_context", replacement: "cs_read", set_arg: None },
+    LegacyTool {
+        name: "cs_list_modules",
+        replacement: "cs_modules",
+        set_arg: Some(("action", ShimArg::Str("list"))),
+    },
+    LegacyTool {
+        name: "cs_get_module_files",
+        replacement: "cs_modules",
+        set_arg: Some(("action", ShimArg::Str("files"))),
+    },
+    LegacyTool {
+        name: "cs_get_deps",
+        replacement: "cs_modules",
+        set_arg: Some(("action", ShimArg::Str("deps"))),
+    },
+    LegacyTool { name: "cs_find_imports", replacement: "cs_imports", set_arg: None },
+    LegacyTool {
+        name: "cs_impact",
+        replacement: "cs_imports",
+        set_arg: Some(("transitive", ShimArg::Bool(true))),
+    },
+    LegacyTool {
+        name: "cs_blame",
+        replacement: "cs_git",
+        set_arg: Some(("action", ShimArg::Str("blame"))),
+    },
+    LegacyTool {
+        name: "cs_file_history",
+        replacement: "cs_git",
+        set_arg: Some(("action", ShimArg::Str("history"))),
+    },
+    LegacyTool {
+        name: "cs_changed_since",
+        replacement: "cs_git",
+        set_arg: Some(("action", ShimArg::Str("changed"))),
+    },
+    LegacyTool {
+        name: "cs_hot_files",
+        replacement: "cs_git",
+        set_arg: Some(("action", ShimArg::Str("hotspots"))),
+    },
+    LegacyTool { name: "cs_session_info", replacement: "cs_status", set_arg: None },
+];
+
+impl LegacyTool {
+    /// Replacement hint, e.g. `cs_modules with action="list"`.
+    pub fn hint(&self) -> String {
+        match &self.set_arg {
+            Some((key, ShimArg::Str(v))) => format!("{} with {key}=\"{v}\"", self.replacement),
+            Some((key, ShimArg::Bool(v))) => format!("{} with {key}={v}", self.replacement),
+            None => self.replacement.to_string(),
+        }
+    }
+}
+
+fn legacy_tool(name: &str) -> Option<&'static LegacyTool> {
+    LEGACY_TOOLS.iter().find(|t| t.name == name)
+}
+
+/// Forward a legacy tool name to its replacement, applying the shim's argument.
 fn translate_legacy_tool<'a>(
     name: &'a str,
     args: &serde_json::Value,
 ) -> (&'a str, serde_json::Value) {
-    match name {
-        "cs_find" | "cs_semantic_search" => ("cs_search", args.clone()),
-        "cs_read_file" => ("cs_read", args.clone()),
-        "cs_read_files" => ("cs_read", args.clone()),
-        "cs_read_context" => ("cs_read", args.clone()),
-        "cs_list_modules" => {
-            let mut a = args.clone();
-            a.as_object_mut().map(|m| m.insert("action".to_string(), serde_json::json!("list")));
-            ("cs_modules", a)
-        }
-        "cs_get_module_files" => {
-            let mut a = args.clone();
-            a.as_object_mut().map(|m| m.insert("action".to_string(), serde_json::json!("files")));
-            ("cs_modules", a)
-        }
-        "cs_get_deps" => {
-            let mut a = args.clone();
-            a.as_object_mut().map(|m| m.insert("action".to_string(), serde_json::json!("deps")));
-            ("cs_modules", a)
+    let Some(legacy) = legacy_tool(name) else {
+        return (name, args.clone());
+    };
+    let mut a = args.clone();
+    if let (Some((key, value)), Some(m)) = (&legacy.set_arg, a.as_object_mut()) {
+        let value = match value {
+            ShimArg::Str(s) => serde_json::json!(s),
+            ShimArg::Bool(b) => serde_json::json!(b),
+        };
+        m.insert(key.to_string(), value);
+    }
+    (legacy.replacement, a)
+}
+
+/// Add schema version, deprecation, and legacy-name metadata to a tool definition.
+fn annotate_tool(def: &mut serde_json::Value, version: u32, deprecated: Option<&str>) {
+    let name = def["name"].as_str().unwrap_or("").to_string();
+    let mut meta = serde_json::json!({ (SCHEMA_VERSION_META): version });
+    let replaces: Vec<&str> =
+        LEGACY_TOOLS.iter().filter(|t| t.replacement == name).map(|t| t.name).collect();
+    if !replaces.is_empty() {
+        meta[REPLACES_META] = serde_json::json!(replaces);
+    }
+    if let Some(hint) = deprecated {
+        meta[DEPRECATED_META] = serde_json::json!({ "replacement": hint });
+        let desc = def["description"].as_str().unwrap_or("");
+        def["description"] = serde_json::json!(format!("DEPRECATED — use {hint} instead. {desc}"));
+    }
+    def["_meta"] = meta;
+}
+
+/// Tool definitions as served by `tools/list`, with versioning metadata.
+fn listed_tools() -> serde_json::Value {
+    let mut tools = tool_definitions();
+    for def in tools.as_array_mut().into_iter().flatten() {
+        let name = def["name"].as_str().unwrap_or("");
+        let version =
+            TOOL_SCHEMA_VERSIONS.iter().find(|(n, _)| *n == name).map(|(_, v)| *v).unwrap_or(1);
+        let deprecated = DEPRECATED_TOOLS.iter().find(|(n, _)| *n == name).map(|(_, hint)| *hint);
+        annotate_tool(def, version, deprecated);
+        add_paging_params(def);
+    }
+    tools
+}
+
+/// Arguments every read-only tool takes for results over `max_result_tokens`.
+fn add_paging_params(def: &mut serde_json::Value) {
+    if def["annotations"]["readOnlyHint"] != true {
+        return;
+    }
+    let Some(props) = def["inputSchema"]["properties"].as_object_mut() else { return };
+    props.insert(
+        "max_result_tokens".to_string(),
+        serde_json::json!({ "type": "integer", "description": "Cut the result after this many tokens at a line boundary and return a cursor for the rest. 0 sends it whole. Default: max_result_tokens from .codescope.toml, else no limit" }),
+    );
+    props.insert(
+        "cursor".to_string(),
+        serde_json::json!({ "type": "string", "description": "Cursor from a cut result: returns the next part of that result instead of running the tool. Other arguments except max_result_tokens are ignored" }),
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Smart read planning (cs_read mode=smart)
+// ---------------------------------------------------------------------------
+
+/// Files up to this many lines are returned in full by `mode=smart` on first read.
+const SMART_FULL_MAX_LINES: usize = 300;
+
+/// Lines of context kept above and below query matches in a smart range.
+const SMART_RANGE_CONTEXT: usize = 30;
+
+/// Longest line range a smart read returns.
+const SMART_RANGE_MAX_LINES: usize = 200;
+
+/// Stub options from `cs_read`'s `include_docs` and `visibility` arguments.
+fn stub_options(args: &serde_json::Value) -> Result<crate::stubs::StubOptions, String> {
+    let visibility = match args["visibility"].as_str() {
+        Some(v) => crate::stubs::Visibility::parse(v)?,
+        None => crate::stubs::Visibility::All,
+    };
+    Ok(crate::stubs::StubOptions {
+        include_docs: args["include_docs"].as_bool().unwrap_or(true),
+        visibility,
+    })
+}
+
+enum SmartRead {
+    Full,
+    Stubs,
+    /// Inclusive 1-based line range.
+    Range(usize, usize),
+}
+
+/// Choose how to read a file for `mode=smart`, with a note explaining the choice.
+///
+/// Large files with query matches get the window covering the most matching lines. Small
+/// files are returned in full unless already read this session; everything else gets stubs.
+fn plan_smart_read(raw: &str, query: Option<&str>, already_read: bool) -> (SmartRead, String) {
+    let total = raw.lines().count();
+    let terms: Vec<String> = query
+        .map(|q| q.split_whitespace().filter(|w| w.len() >= 2).map(|w| w.to_lowercase()).collect())
+        .unwrap_or_default();
+
+    if total > SMART_FULL_MAX_LINES && !terms.is_empty() {
+        let hits: Vec<usize> = raw
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| {
+                let lower = line.to_lowercase();
+                terms.iter().any(|t| lower.contains(t.as_str()))
+            })
+            .map(|(i, _)| i + 1)
+            .collect();
+        // Densest run of hits whose span (plus context) fits in the range limit.
+        let span = SMART_RANGE_MAX_LINES - 2 * SMART_RANGE_CONTEXT;
+        let mut best: Option<(usize, usize)> = None;
+        let mut j = 0;
+        for i in 0..hits.len() {
+            j = j.max(i);
+            while j + 1 < hits.len() && hits[j + 1] - hits[i] < span {
+                j += 1;
+            }
+            if best.is_none_or(|(bi, bj)| j - i > bj - bi) {
+                best = Some((i, j));
+            }
         }
-        "cs_find_imports" => ("cs_imports", args.clone()),
-        "cs_impact" => {
-            let mut a = args.clone();
-            a.as_object_mut().map(|m| m.insert("transitive".to_string(), serde_json::json!(true)));
-            ("cs_imports", a)
+        if let Some((i, j)) = best {
+            let start = hits[i].saturating_sub(SMART_RANGE_CONTEXT).max(1);
+            let end = (hits[j] + SMART_RANGE_CONTEXT).min(total);
+            let note = format!(
+                "{total} lines; showing {start}-{end}, which covers {} of {} lines matching '{}'",
+                j - i + 1,
+                hits.len(),
+                query.unwrap_or_default()
+            );
+            return (SmartRead::Range(start, end), note);
         }
-        "cs_blame" => {
-            let mut a = args.clone();
-            a.as_object_mut().map(|m| m.insert("action".to_string(), serde_json::json!("blame")));
-            ("cs_git", a)
+    }
+
+    if already_read {
+        let note = "already read this session, showing stubs; use mode='full' to re-read";
+        (SmartRead::Stubs, note.to_string())
+    } else if total <= SMART_FULL_MAX_LINES {
+        (SmartRead::Full, format!("{total} lines, small enough to read in full"))
+    } else {
+        let note =
+            format!("{total} lines, showing stubs; pass 'query' or start_line/end_line for a body");
+        (SmartRead::Stubs, note)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tool call handler (read-only, takes &ServerState)
+// ---------------------------------------------------------------------------
+
+/// Error result for a tool call, prefixed with the localized "Error" label.
+/// `base` and `head` refs for `cs_git compare`/`diffstat`: `base` defaults to the repo's
+/// default branch, `head` to HEAD.
+fn git_range<'a>(
+    repo: &RepoState,
+    args: &'a serde_json::Value,
+) -> Result<(String, &'a str), String> {
+    let base = match args["base"].as_str().filter(|b| !b.is_empty()) {
+        Some(b) => b.to_string(),
+        None => crate::git::default_branch(&repo.root, &repo.config.git)
+            .map_err(|e| format!("{e}, or pass 'base'"))?,
+    };
+    let head = args["head"].as_str().filter(|h| !h.is_empty()).unwrap_or("HEAD");
+    Ok((base, head))
+}
+
+/// Display name for a ref: the checked-out branch for `HEAD` when there is one.
+fn ref_label(repo: &RepoState, rev: &str) -> String {
+    match rev {
+        "HEAD" => crate::git::current_branch(&repo.root).unwrap_or_else(|| "HEAD".to_string()),
+        r => r.to_string(),
+    }
+}
+
+/// Append changed files grouped by status, as `cs_git changed` and `compare` print them.
+fn push_changed_files(out: &mut String, files: &[crate::git::ChangedFile]) {
+    let mut by_status: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for f in files {
+        by_status.entry(&f.status).or_default().push(&f.path);
+    }
+    for (status, paths) in &by_status {
+        out.push_str(&format!("{} ({}):\n", status, paths.len()));
+        for p in paths {
+            out.push_str(&format!("  {p}\n"));
         }
-        "cs_file_history" => {
-            let mut a = args.clone();
-            a.as_object_mut().map(|m| m.insert("action".to_string(), serde_json::json!("history")));
-            ("cs_git", a)
+        out.push('\n');
+    }
+}
+
+fn tool_error(msg: impl std::fmt::Display) -> (String, bool) {
+    (format!("{}: {msg}", crate::tr!("error-prefix")), true)
+}
+
+/// Execute a tool call against `state`. Returns `(text, is_error)`.
+///
+/// Public so integration tests can drive tools without the JSON-RPC transport.
+pub fn handle_tool_call(
+    state: &ServerState,
+    name: &str,
+    args: &serde_json::Value,
+    session: &mut Option<SessionState>,
+) -> (String, bool) {
+    let (text, is_error, _) = handle_tool_call_structured(state, name, args, session);
+    (text, is_error)
+}
+
+/// Like [`handle_tool_call`], also returning MCP `structuredContent` for tools that
+/// produce it (`cs_search`, and `cs_status` with `format=json`). A result cut at
+/// `max_result_tokens` carries no structured content.
+pub fn handle_tool_call_structured(
+    state: &ServerState,
+    name: &str,
+    args: &serde_json::Value,
+    session: &mut Option<SessionState>,
+) -> (String, bool, Option<serde_json::Value>) {
+    let limit = result_token_limit(state, args);
+    if let Some(cursor) = args["cursor"].as_str() {
+        let Some(rest) = session.as_mut().and_then(|s| s.take_continuation(cursor)) else {
+            let (msg, is_error) = tool_error(format!(
+                "Unknown or expired cursor '{cursor}'. A session keeps the {} newest cut \
+                 results; rerun the call without cursor",
+                crate::types::MAX_CONTINUATIONS
+            ));
+            return (msg, is_error, None);
+        };
+        let text = match limit {
+            Some(limit) => page_result(state, name, rest, limit, session).0,
+            None => rest,
+        };
+        return (text, false, None);
+    }
+    // Repos still in the startup queue answer with progress instead of results
+    if name != "cs_status" {
+        if let Some(msg) = crate::indexing::not_ready_message(state, args) {
+            return (msg, false, None);
         }
-        "cs_changed_since" => {
-            let mut a = args.clone();
-            a.as_object_mut().map(|m| m.insert("action".to_string(), serde_json::json!("changed")));
-            ("cs_git", a)
+    }
+    let mut structured = None;
+    let (mut text, is_error) = run_tool(state, name, args, session, &mut structured);
+    if args["repo"].is_null() && !is_error && name != "cs_status" {
+        if let Some(note) = crate::indexing::partial_results_note(state) {
+            text.push_str(&note);
         }
-        "cs_hot_files" => {
-            let mut a = args.clone();
-            a.as_object_mut()
-                .map(|m| m.insert("action".to_string(), serde_json::json!("hotspots")));
-            ("cs_git", a)
+    }
+    if let Some(legacy) = legacy_tool(name) {
+        text.push_str(&format!(
+            "\n\n[deprecated] {} is a retired tool name; call {} instead.",
+            legacy.name,
+            legacy.hint()
+        ));
+    }
+    if let (Some(limit), false) = (limit, is_error) {
+        let (page, cut) = page_result(state, name, text, limit, session);
+        return (page, false, structured.filter(|_| !cut));
+    }
+    (text, is_error, structured)
+}
+
+/// Token limit for a call's result: its `max_result_tokens` argument (0 for none), else
+/// the setting of the repo it names or of the default repo.
+fn result_token_limit(state: &ServerState, args: &serde_json::Value) -> Option<usize> {
+    if let Some(n) = args["max_result_tokens"].as_u64() {
+        return (n > 0).then_some(n as usize);
+    }
+    let repo = match args["repo"].as_str() {
+        Some(name) => state.repos.get(name).map(|r| &**r),
+        None => (!state.repos.is_empty()).then(|| state.default_repo()),
+    };
+    repo.and_then(|r| r.config.max_result_tokens)
+}
+
+/// Cut `text` to `limit` tokens, keeping the rest in the session under a cursor that the
+/// footer names. Without a session the rest is dropped. Returns whether `text` was cut.
+fn page_result(
+    state: &ServerState,
+    name: &str,
+    text: String,
+    limit: usize,
+    session: &mut Option<SessionState>,
+) -> (String, bool) {
+    let Some((mut page, rest)) = crate::format::split_result(&text, limit, &*state.tokenizer)
+    else {
+        return (text, false);
+    };
+    let more = rest.lines().count();
+    if !page.ends_with('\n') {
+        page.push('\n');
+    }
+    match session {
+        Some(s) => {
+            let cursor = s.stash_continuation(rest);
+            page.push_str(&format!(
+                "\n[cut at {limit} tokens, {more} more lines: call {name} with \
+                 cursor=\"{cursor}\" for the rest]"
+            ));
         }
-        "cs_session_info" => ("cs_status", args.clone()),
-        _ => (name, args.clone()),
+        None => page.push_str(&format!(
+            "\n[cut at {limit} tokens, {more} more lines not shown: narrow the call or raise \
+             max_result_tokens]"
+        )),
     }
+    (page, true)
 }
 
-// ---------------------------------------------------------------------------
-// Tool call handler (read-only, takes &ServerState)
-// ---------------------------------------------------------------------------
+/// Generated and vendored files of `repo`, which `cs_search` leaves out unless asked
+/// to include them.
+fn hidden_origins(repo: &RepoState, include_generated: bool) -> HashSet<&str> {
+    if include_generated {
+        return HashSet::new();
+    }
+    repo.all_files.iter().filter(|f| f.origin.is_some()).map(|f| f.rel_path.as_str()).collect()
+}
 
-fn handle_tool_call(
+/// Zero-hit fallback for `cs_search` and `cs_grep`: rerun the tool with a spelling-corrected
+/// query (unless `autocorrect: false`), flagging the substitution, or append a did-you-mean
+/// to `empty`. Returns `empty` unchanged when there is nothing to correct.
+#[allow(clippy::too_many_arguments)]
+fn spelling_fallback(
+    state: &ServerState,
+    name: &str,
+    args: &serde_json::Value,
+    session: &mut Option<SessionState>,
+    structured: &mut Option<serde_json::Value>,
+    repos: &[&RepoState],
+    query: &str,
+    empty: String,
+) -> (String, bool) {
+    let Some(corrected) = crate::spelling::correct_query(repos, query) else {
+        return (empty, false);
+    };
+    if args["autocorrect"].as_bool().unwrap_or(true) {
+        let mut retry = args.clone();
+        retry["query"] = serde_json::json!(corrected);
+        retry["autocorrect"] = serde_json::json!(false);
+        let (out, is_error) = run_tool(state, name, &retry, session, structured);
+        if !is_error {
+            return (
+                format!(
+                    "No results for \"{query}\"; showing results for \"{corrected}\" instead \
+                     (autocorrected).\n\n{out}"
+                ),
+                false,
+            );
+        }
+    }
+    (format!("{empty}\nDid you mean \"{corrected}\"?"), false)
+}
+
+fn run_tool(
     state: &ServerState,
     original_name: &str,
     original_args: &serde_json::Value,
     session: &mut Option<SessionState>,
+    structured: &mut Option<serde_json::Value>,
 ) -> (String, bool) {
     let (name, args) = translate_legacy_tool(original_name, original_args);
     match name {
@@ -327,49 +1381,89 @@ fn handle_tool_call(
             // - paths (array) without budget → simple batch read
             if let Some(path_val) = args.get("path").and_then(|v| v.as_str()) {
                 // Single file read (was cs_read_file)
-                let repo = match resolve_repo(state, &args) {
-                    Ok(r) => r,
-                    Err(e) => return (format!("Error: {e}"), true),
+                let profile = match crate::format::resolve_profile(&args, session) {
+                    Ok(p) => p,
+                    Err(e) => return tool_error(e),
                 };
                 let path = path_val;
                 let mode = args["mode"].as_str().unwrap_or("full");
                 let start_line = args["start_line"].as_u64().map(|n| n.max(1) as usize);
                 let end_line = args["end_line"].as_u64().map(|n| n as usize);
-                match validate_path(&repo.root, path) {
-                    Err(e) => (format!("Error: {e}"), true),
-                    Ok(full_path) => match fs::read_to_string(&full_path) {
-                        Err(_) => ("Error: Could not read file".to_string(), true),
-                        Ok(raw) => {
+                let symbols = args["symbols"].as_bool().unwrap_or(false);
+                let stub_options = match stub_options(&args) {
+                    Ok(o) => o,
+                    Err(e) => return tool_error(e),
+                };
+                let diff_rereads = args["diff"].as_bool().unwrap_or(true);
+                match resolve_read_path(state, &args, path) {
+                    Err(e) => tool_error(e),
+                    Ok(resolved) => match crate::encoding::read_with_encoding(&resolved.abs_path) {
+                        Err(_) => tool_error("Could not read file"),
+                        Ok((raw, encoding)) => {
+                            let (raw, redacted) =
+                                crate::redact::redact(&resolved.repo.config.redact, raw);
+                            let already_read =
+                                session.as_ref().is_some_and(|s| s.files_read.contains_key(path));
                             if let Some(ref mut s) = session {
                                 let approx_tokens = raw.len() / 4;
                                 s.record_read(path, approx_tokens);
                             }
-                            if mode == "stubs" {
-                                let ext = path.rsplit_once('.').map(|(_, e)| e).unwrap_or("");
-                                let content = extract_stubs(&raw, ext);
+                            let (mode, start_line, end_line, note) = if mode == "smart" {
+                                let (plan, note) =
+                                    plan_smart_read(&raw, args["query"].as_str(), already_read);
+                                match plan {
+                                    SmartRead::Full => ("full", None, None, Some(note)),
+                                    SmartRead::Stubs => ("stubs", None, None, Some(note)),
+                                    SmartRead::Range(s, e) => {
+                                        ("full", Some(s), Some(e), Some(note))
+                                    }
+                                }
+                            } else {
+                                (mode, start_line, end_line, None)
+                            };
+                            let (text, is_error) = if mode == "stubs" {
+                                let content = extract_file_stubs(
+                                    &resolved.repo.config.languages,
+                                    &resolved.rel_path,
+                                    &raw,
+                                    stub_options,
+                                );
                                 let lines = content.lines().count();
-                                (format!("# {path}\n({lines} lines, stubs)\n\n{content}"), false)
+                                let header = crate::format::file_header(
+                                    profile,
+                                    path,
+                                    &format!("{lines} lines, stubs"),
+                                    raw.len(),
+                                );
+                                (format!("{header}{content}"), false)
+                            } else if mode == "outline" {
+                                let (symbols, content) = outline_text(&resolved, &raw);
+                                let header = crate::format::file_header(
+                                    profile,
+                                    path,
+                                    &format!(
+                                        "{} lines, {symbols} symbols, outline",
+                                        raw.lines().count()
+                                    ),
+                                    raw.len(),
+                                );
+                                (format!("{header}{content}"), false)
                             } else if start_line.is_some() || end_line.is_some() {
                                 let all_lines: Vec<&str> = raw.lines().collect();
                                 let total = all_lines.len();
                                 let s = start_line.unwrap_or(1).min(total).max(1);
                                 let e = end_line.unwrap_or(total).min(total);
                                 if s > e {
-                                    return (
-                                        format!("Error: start_line ({s}) > end_line ({e})"),
-                                        true,
-                                    );
-                                }
-                                let width = format!("{}", e).len();
-                                let mut content = String::new();
-                                for i in s..=e {
-                                    content.push_str(&format!(
-                                        "{:>w$}: {}\n",
-                                        i,
-                                        all_lines[i - 1],
-                                        w = width
+                                    return tool_error(format!(
+                                        "start_line ({s}) > end_line ({e})"
                                     ));
                                 }
+                                let marks = if symbols {
+                                    crate::stubs::symbol_marks(&all_lines)
+                                } else {
+                                    Vec::new()
+                                };
+                                let content = numbered_lines(&all_lines, s, e, &marks);
                                 (format!("# {path} (lines {s}-{e} of {total})\n\n{content}"), false)
                             } else {
                                 let content = if raw.len() > MAX_FILE_READ {
@@ -382,8 +1476,74 @@ fn handle_tool_call(
                                     raw
                                 };
                                 let lines = content.lines().count();
-                                (format!("# {path}\n({lines} lines)\n\n{content}"), false)
-                            }
+                                // A whole-file re-read: only what changed since the last one
+                                let key = format!("[{}] {}", resolved.repo.name, resolved.rel_path);
+                                // A file read before it moved diffs against that read
+                                let old_key = resolved
+                                    .renamed_from
+                                    .as_ref()
+                                    .map(|p| format!("[{}] {p}", resolved.repo.name));
+                                let previous = session
+                                    .as_ref()
+                                    .and_then(|s| {
+                                        s.receipt(&key).or_else(|| s.receipt(old_key.as_deref()?))
+                                    })
+                                    .filter(|_| diff_rereads && !symbols)
+                                    .map(|r| (r.hash, r.content.clone()));
+                                if let Some(s) = session.as_mut() {
+                                    s.record_receipt(&key, &content);
+                                }
+                                let reread = previous.and_then(|(hash, old)| {
+                                    reread_body(hash, old.as_deref(), &content, lines)
+                                });
+                                let (detail, content) = if let Some(reread) = reread {
+                                    reread
+                                } else if symbols {
+                                    let all_lines: Vec<&str> = content.lines().collect();
+                                    let marks = crate::stubs::symbol_marks(&all_lines);
+                                    (
+                                        format!("{lines} lines, {} symbols", marks.len()),
+                                        numbered_lines(&all_lines, 1, lines, &marks),
+                                    )
+                                } else {
+                                    (format!("{lines} lines"), content)
+                                };
+                                let header = crate::format::file_header(
+                                    profile,
+                                    path,
+                                    &detail,
+                                    content.len(),
+                                );
+                                (format!("{header}{content}"), false)
+                            };
+                            let text = match note {
+                                Some(note) => {
+                                    text.replacen('\n', &format!("\n[smart: {note}]\n"), 1)
+                                }
+                                None => text,
+                            };
+                            let text = match encoding {
+                                Some(enc) => text.replacen(
+                                    '\n',
+                                    &format!("\n[encoding: {enc}, transcoded to UTF-8]\n"),
+                                    1,
+                                ),
+                                None => text,
+                            };
+                            let text = if redacted > 0 {
+                                text.replacen(
+                                    '\n',
+                                    &format!("\n{}\n", crate::redact::note(redacted)),
+                                    1,
+                                )
+                            } else {
+                                text
+                            };
+                            let text = match resolved.rename_note() {
+                                Some(note) => text.replacen('\n', &format!("\n{note}\n"), 1),
+                                None => text,
+                            };
+                            (text + &related_tests_footer(profile, &resolved), is_error)
                         }
                     },
                 }
@@ -392,14 +1552,24 @@ fn handle_tool_call(
                 let has_budget = args.get("budget").is_some();
                 if has_budget {
                     // Budget-aware batch read (was cs_read_context)
-                    let repo = match resolve_repo(state, &args) {
-                        Ok(r) => r,
-                        Err(e) => return (format!("Error: {e}"), true),
-                    };
                     let paths: Vec<String> = paths_arr
                         .iter()
                         .filter_map(|v| v.as_str().map(|s| s.to_string()))
                         .collect();
+                    // `[repo] path` prefixes select the repo, so dependency lookups stay
+                    // within the repo that owns the files.
+                    let (repo, paths) = match crate::namespace::resolve_paths_repo(
+                        state,
+                        &paths,
+                        args["repo"].as_str(),
+                    ) {
+                        Ok((Some(repo), paths)) => (repo, paths),
+                        Ok((None, paths)) => match resolve_repo(state, &args) {
+                            Ok(r) => (r, paths),
+                            Err(e) => return tool_error(e),
+                        },
+                        Err(e) => return tool_error(e),
+                    };
                     let budget =
                         args["budget"].as_u64().unwrap_or(DEFAULT_TOKEN_BUDGET as u64) as usize;
                     let unit = match args["unit"].as_str() {
@@ -408,8 +1578,20 @@ fn handle_tool_call(
                     };
 
                     if paths.is_empty() {
-                        return ("Error: paths array is empty".to_string(), true);
+                        return tool_error("paths array is empty");
                     }
+                    // Old paths of moved files are read from where the files are now
+                    let mut moved = Vec::new();
+                    let paths: Vec<String> = paths
+                        .into_iter()
+                        .map(|p| match repo.renames.resolve(&p) {
+                            Some(current) if !repo.root.join(&p).exists() => {
+                                moved.push(crate::renames::note(&p, current));
+                                current.to_string()
+                            }
+                            _ => p,
+                        })
+                        .collect();
 
                     let query = args["query"].as_str();
                     let ordering = args["ordering"].as_str();
@@ -439,14 +1621,6 @@ fn handle_tool_call(
                         }
                     }
 
-                    let tier_names = |t: u8| match t {
-                        1 => "full stubs",
-                        2 => "pruned",
-                        3 => "TOC",
-                        4 => "manifest",
-                        _ => "error",
-                    };
-
                     let mut out = format!(
                         "Context: {} files, ~{} tokens (budget: {})\n",
                         resp.summary.total_files, resp.summary.total_tokens, resp.summary.budget
@@ -457,16 +1631,32 @@ fn handle_tool_call(
                         let key = tier.to_string();
                         if let Some(&count) = resp.summary.tier_counts.get(&key) {
                             if count > 0 {
-                                tier_parts.push(format!("{} {}", count, tier_names(tier)));
+                                tier_parts.push(format!(
+                                    "{} {}",
+                                    count,
+                                    crate::budget::tier_name(tier)
+                                ));
                             }
                         }
                     }
                     if !tier_parts.is_empty() {
                         out.push_str(&format!("Tiers: {}\n\n", tier_parts.join(", ")));
                     }
+                    if !moved.is_empty() {
+                        out.push_str(&format!("{}\n\n", moved.join("\n")));
+                    }
+                    let redacted: usize =
+                        resp.files.values().map(|e| crate::redact::marker_count(&e.content)).sum();
+                    if redacted > 0 {
+                        out.push_str(&format!("{}\n\n", crate::redact::note(redacted)));
+                    }
 
                     let mut sorted_paths: Vec<&String> = resp.files.keys().collect();
                     sorted_paths.sort();
+                    if !resp.chunks.is_empty() {
+                        // Errors first, then definitions in the interleaved order
+                        sorted_paths.retain(|p| resp.files[*p].tier == 0);
+                    }
 
                     for path in sorted_paths {
                         if let Some(entry) = resp.files.get(path) {
@@ -474,7 +1664,7 @@ fn handle_tool_call(
                                 out.push_str(&format!("# {path}\n{}\n\n", entry.content));
                             } else {
                                 let tier_label = if entry.tier > 1 {
-                                    format!(" [{}]", tier_names(entry.tier))
+                                    format!(" [{}]", crate::budget::tier_name(entry.tier))
                                 } else {
                                     String::new()
                                 };
@@ -482,51 +1672,85 @@ fn handle_tool_call(
                             }
                         }
                     }
+                    for chunk in &resp.chunks {
+                        let symbol = if chunk.symbol.is_empty() {
+                            String::new()
+                        } else {
+                            format!(" :: {}", chunk.symbol)
+                        };
+                        out.push_str(&format!("# {}{symbol}\n{}\n", chunk.path, chunk.content));
+                    }
 
                     (out, false)
                 } else {
                     // Simple batch read (was cs_read_files)
-                    let repo = match resolve_repo(state, &args) {
-                        Ok(r) => r,
-                        Err(e) => return (format!("Error: {e}"), true),
-                    };
                     let paths: Vec<&str> = paths_arr.iter().filter_map(|v| v.as_str()).collect();
                     let mode = args["mode"].as_str().unwrap_or("full");
+                    let stub_options = match stub_options(&args) {
+                        Ok(o) => o,
+                        Err(e) => return tool_error(e),
+                    };
 
                     if paths.len() > 50 {
-                        return ("Error: Max 50 files per call".to_string(), true);
+                        return tool_error("Max 50 files per call");
                     }
 
                     let mut out = String::new();
                     for p in &paths {
-                        match validate_path(&repo.root, p) {
+                        match resolve_read_path(state, &args, p) {
                             Err(e) => {
-                                out.push_str(&format!("# {p}\nError: {e}\n\n"));
+                                out.push_str(&format!(
+                                    "# {p}\n{}: {e}\n\n",
+                                    crate::tr!("error-prefix")
+                                ));
                             }
-                            Ok(full_path) => match fs::read_to_string(&full_path) {
-                                Err(_) => {
-                                    out.push_str(&format!("# {p}\nError: Could not read file\n\n"));
-                                }
-                                Ok(raw) => {
-                                    if let Some(ref mut s) = session {
-                                        let approx_tokens = raw.len() / 4;
-                                        s.record_read(p, approx_tokens);
+                            Ok(resolved) => {
+                                match crate::encoding::read_to_string(&resolved.abs_path) {
+                                    Err(_) => {
+                                        out.push_str(&format!(
+                                            "# {p}\n{}: Could not read file\n\n",
+                                            crate::tr!("error-prefix")
+                                        ));
+                                    }
+                                    Ok(raw) => {
+                                        if let Some(ref mut s) = session {
+                                            let approx_tokens = raw.len() / 4;
+                                            s.record_read(p, approx_tokens);
+                                        }
+                                        let (raw, redacted) = crate::redact::redact(
+                                            &resolved.repo.config.redact,
+                                            raw,
+                                        );
+                                        out.push_str(&format!("# {p}\n"));
+                                        if let Some(note) = resolved.rename_note() {
+                                            out.push_str(&format!("{note}\n"));
+                                        }
+                                        if redacted > 0 {
+                                            out.push_str(&crate::redact::note(redacted));
+                                            out.push('\n');
+                                        }
+                                        let content = if mode == "stubs" {
+                                            extract_file_stubs(
+                                                &resolved.repo.config.languages,
+                                                &resolved.rel_path,
+                                                &raw,
+                                                stub_options,
+                                            )
+                                        } else if mode == "outline" {
+                                            outline_text(&resolved, &raw).1
+                                        } else {
+                                            raw
+                                        };
+                                        out.push_str(&format!("{content}\n\n"));
                                     }
-                                    let content = if mode == "stubs" {
-                                        let ext = p.rsplit_once('.').map(|(_, e)| e).unwrap_or("");
-                                        extract_stubs(&raw, ext)
-                                    } else {
-                                        raw
-                                    };
-                                    out.push_str(&format!("# {p}\n{content}\n\n"));
                                 }
-                            },
+                            }
                         }
                     }
                     (out, false)
                 }
             } else {
-                ("Error: Either 'path' (string) or 'paths' (array) is required".to_string(), true)
+                tool_error("Either 'path' (string) or 'paths' (array) is required")
             }
         }
 
@@ -536,67 +1760,134 @@ fn handle_tool_call(
         "cs_grep" => {
             let repos = resolve_repos_for_search(state, &args);
             if repos.is_empty() {
-                return ("Error: No matching repos found".to_string(), true);
+                return tool_error("No matching repos found");
             }
+            let (repos, scope_name) = match resolve_scope(state, &args, repos) {
+                Ok(r) => r,
+                Err(e) => return tool_error(e),
+            };
             let multi = repos.len() > 1;
 
             let query = args["query"].as_str().unwrap_or("");
             if query.is_empty() {
-                return ("Error: Query must not be empty".to_string(), true);
+                return tool_error("Query must not be empty");
             }
 
+            let profile = match crate::format::resolve_profile(&args, session) {
+                Ok(p) => p,
+                Err(e) => return tool_error(e),
+            };
             let limit = args["limit"].as_u64().unwrap_or(50).min(200) as usize;
             let max_per_file = args["max_per_file"].as_u64().unwrap_or(8).min(50) as usize;
-            let context_lines = args["context"].as_u64().unwrap_or(2).min(10) as usize;
+            let context = match SnippetContext::parse(&args["context"], profile.context_lines()) {
+                Ok(c) => c,
+                Err(e) => return tool_error(e),
+            };
             let ext_filter: Option<HashSet<String>> = args["ext"].as_str().map(|exts| {
                 exts.split(',').map(|e| e.trim().trim_start_matches('.').to_string()).collect()
             });
             let cat_filter = args["category"].as_str();
             let path_filter = args["path"].as_str();
             let match_mode = args["match_mode"].as_str().unwrap_or("all");
+            let include_generated = args["include_generated"].as_bool().unwrap_or(false);
             let output_mode = args["output"].as_str().unwrap_or("full");
+            let group_by = match args["group_by"].as_str().map(GrepGroupBy::parse).transpose() {
+                Ok(g) => g,
+                Err(e) => return tool_error(e),
+            };
 
             let terms: Vec<&str> = query.split_whitespace().collect();
             let terms_lower: Vec<String> = terms.iter().map(|t| t.to_lowercase()).collect();
             let require_all_terms = match_mode == "all" && terms.len() > 1;
 
             let pattern = match match_mode {
-                "exact" => RegexBuilder::new(&regex::escape(query)).case_insensitive(true).build(),
-                "regex" => RegexBuilder::new(query).case_insensitive(true).build(),
-                _ => {
-                    let pattern_str =
-                        terms.iter().map(|t| regex::escape(t)).collect::<Vec<_>>().join("|");
-                    RegexBuilder::new(&pattern_str).case_insensitive(true).build()
-                }
+                "exact" => grep_regex(&regex::escape(query)),
+                "regex" => grep_regex(query),
+                _ => grep_regex(
+                    &terms.iter().map(|t| regex::escape(t)).collect::<Vec<_>>().join("|"),
+                ),
             };
             let pattern = match pattern {
                 Ok(p) => p,
-                Err(e) => return (format!("Error: Invalid pattern: {e}"), true),
+                Err(regex::Error::CompiledTooBig(_)) => {
+                    return tool_error(
+                        "Pattern too large: its compiled form exceeds the grep size limit. \
+                         Use smaller repetition counts or split it into several searches",
+                    )
+                }
+                Err(e) => return tool_error(format!("Invalid pattern: {e}")),
             };
+            let timeout_ms = args["timeout_ms"]
+                .as_u64()
+                .unwrap_or(GREP_DEFAULT_TIMEOUT_MS)
+                .min(GREP_MAX_TIMEOUT_MS);
+            let max_files =
+                args["max_files"].as_u64().map_or(GREP_DEFAULT_MAX_FILES, |n| n.max(1) as usize);
 
             let start = std::time::Instant::now();
+            let deadline = GrepDeadline::after(std::time::Duration::from_millis(timeout_ms));
 
             struct GrepFileHit {
                 display_path: String,
                 desc: String,
                 match_indices: Vec<usize>,
                 total_match_count: usize,
-                lines: Vec<String>,
+                lines: GrepLines,
                 score: f64,
                 terms_matched: usize,
                 total_terms: usize,
+                /// Module or directory key when `group_by` is set.
+                group: String,
+            }
+
+            /// Lines kept for output: all of them, or for a streamed file only the shown
+            /// matches and their context lines.
+            enum GrepLines {
+                All(Vec<String>),
+                Sparse { total: usize, kept: BTreeMap<usize, String> },
+            }
+
+            impl GrepLines {
+                fn len(&self) -> usize {
+                    match self {
+                        GrepLines::All(lines) => lines.len(),
+                        GrepLines::Sparse { total, .. } => *total,
+                    }
+                }
+
+                fn get(&self, i: usize) -> &str {
+                    match self {
+                        GrepLines::All(lines) => &lines[i],
+                        GrepLines::Sparse { kept, .. } => kept.get(&i).map_or("", |l| l),
+                    }
+                }
             }
 
+            let context_keep = match context {
+                SnippetContext::Lines(n) => n,
+                _ => 0,
+            };
+            let oversized = std::sync::atomic::AtomicUsize::new(0);
             let mut file_hits: Vec<GrepFileHit> = Vec::new();
+            let (mut scanned, mut over_cap) = (0usize, 0usize);
 
             for repo in &repos {
+                let ranker = crate::ranking::Ranker::new(repo);
                 let config = &repo.config;
-                let idf_weights: Vec<f64> =
-                    terms_lower.iter().map(|t| repo.term_doc_freq.idf(t)).collect();
-                let candidates: Vec<&ScannedFile> = repo
+                let scope = scope_name.as_deref().map(|n| &config.scopes[n]);
+                let query_terms = query_terms_for(config, match_mode, &terms_lower);
+                let alias_pattern = crate::aliases::alias_pattern(&query_terms);
+                let pattern = alias_pattern.as_ref().unwrap_or(&pattern);
+                let idf_weights = query_idf_weights(repo, &query_terms);
+                let mut candidates: Vec<&ScannedFile> = repo
                     .all_files
                     .iter()
+                    .filter(|f| f.is_searchable() && (include_generated || f.origin.is_none()))
+                    .filter(|f| config.read_policy.permits(&f.rel_path))
                     .filter(|f| {
+                        if scope.is_some_and(|s| !s.matches(&f.rel_path, config)) {
+                            return false;
+                        }
                         if let Some(prefix) = path_filter {
                             if !f.rel_path.starts_with(prefix) {
                                 return false;
@@ -616,43 +1907,87 @@ fn handle_tool_call(
                         true
                     })
                     .collect();
+                let room = max_files.saturating_sub(scanned);
+                over_cap += candidates.len().saturating_sub(room);
+                candidates.truncate(room);
+                scanned += candidates.len();
 
                 use rayon::prelude::*;
                 let mut par_hits: Vec<GrepFileHit> = candidates
                     .par_iter()
                     .filter_map(|file| {
-                        let content = fs::read_to_string(&file.abs_path).ok()?;
-                        let lines: Vec<&str> = content.lines().collect();
-                        let total_lines = lines.len().max(1);
+                        if deadline.expired() {
+                            return None;
+                        }
+                        let size = std::fs::metadata(&file.abs_path).ok()?.len();
+                        if size > config.max_grep_file_size {
+                            oversized.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                            return None;
+                        }
+                        // Large files are streamed: only shown matches and context are kept
+                        let streamed = size > crate::encoding::STREAM_LINES_BYTES;
+                        let mut all_lines: Vec<String> = Vec::new();
+                        let mut kept: BTreeMap<usize, String> = BTreeMap::new();
+                        let mut recent: VecDeque<(usize, String)> = VecDeque::new();
+                        let mut after_left = 0usize;
+                        let mut total_lines = 0usize;
 
                         let mut match_indices: Vec<usize> = Vec::new();
                         let mut total_match_count = 0usize;
                         let mut first_match_line_idx = usize::MAX;
                         let mut terms_seen = std::collections::HashSet::new();
-                        for (i, line) in lines.iter().enumerate() {
+                        let mut line_matches = |i: usize, line: &str| -> bool {
+                            // Checked every few thousand lines so huge files stop on time too
+                            if i % 4096 == 4095 && deadline.expired() || deadline.timed_out() {
+                                return false;
+                            }
                             if !pattern.is_match(line) {
-                                continue;
+                                return false;
                             }
-                            if require_all_terms {
-                                let line_lower = line.to_lowercase();
-                                if !terms_lower.iter().all(|t| line_lower.contains(t.as_str())) {
-                                    continue;
-                                }
+                            let line_lower = line.to_lowercase();
+                            let line_terms: Vec<bool> =
+                                query_terms.iter().map(|t| t.matches(line, &line_lower)).collect();
+                            if require_all_terms && !line_terms.iter().all(|&m| m) {
+                                return false;
+                            }
+                            // The alias prefilter also hits aliases inside longer words
+                            if alias_pattern.is_some() && !line_terms.contains(&true) {
+                                return false;
                             }
                             total_match_count += 1;
                             if first_match_line_idx == usize::MAX {
                                 first_match_line_idx = i;
                             }
-                            let line_lower = line.to_lowercase();
-                            for (ti, term) in terms_lower.iter().enumerate() {
-                                if line_lower.contains(term.as_str()) {
-                                    terms_seen.insert(ti);
-                                }
+                            for (ti, _) in line_terms.iter().enumerate().filter(|(_, &m)| m) {
+                                terms_seen.insert(ti);
                             }
                             if match_indices.len() < max_per_file {
                                 match_indices.push(i);
+                                return true;
                             }
-                        }
+                            false
+                        };
+                        crate::encoding::for_each_line(&file.abs_path, |i, line| {
+                            total_lines = i + 1;
+                            let shown = line_matches(i, line);
+                            if !streamed {
+                                all_lines.push(line.to_string());
+                            } else if shown {
+                                kept.extend(recent.drain(..));
+                                kept.insert(i, line.to_string());
+                                after_left = context_keep;
+                            } else if after_left > 0 {
+                                kept.insert(i, line.to_string());
+                                after_left -= 1;
+                            } else if context_keep > 0 {
+                                recent.push_back((i, line.to_string()));
+                                if recent.len() > context_keep {
+                                    recent.pop_front();
+                                }
+                            }
+                        })
+                        .ok()?;
+                        let total_lines = total_lines.max(1);
 
                         if match_indices.is_empty() {
                             return None;
@@ -677,42 +2012,142 @@ fn handle_tool_call(
                                 first_match_line_idx
                             },
                             &idf_weights,
-                        );
+                        ) * ranker.boost(&file.rel_path);
 
                         Some(GrepFileHit {
                             display_path: repo_path(repo, &file.rel_path, multi),
                             desc: file.desc.clone(),
                             match_indices,
                             total_match_count,
-                            lines: lines.iter().map(|l| l.to_string()).collect(),
+                            lines: if streamed {
+                                GrepLines::Sparse { total: total_lines, kept }
+                            } else {
+                                GrepLines::All(all_lines)
+                            },
                             score,
                             terms_matched: terms_seen.len(),
                             total_terms: terms_lower.len(),
+                            group: group_by
+                                .map(|g| {
+                                    let key = g.key(&file.rel_path, config);
+                                    match (multi, g) {
+                                        (false, _) => key,
+                                        (true, GrepGroupBy::Module) => {
+                                            crate::namespace::qualify(&repo.name, &key)
+                                        }
+                                        (true, GrepGroupBy::Dir) => repo_path(repo, &key, true),
+                                    }
+                                })
+                                .unwrap_or_default(),
                         })
                     })
                     .collect();
                 file_hits.append(&mut par_hits);
             }
+            let oversized = oversized.into_inner();
+            let mut notes = if oversized > 0 {
+                format!(
+                    "\n\n{oversized} file{} over max_grep_file_size not searched",
+                    if oversized == 1 { "" } else { "s" }
+                )
+            } else {
+                String::new()
+            };
+            if over_cap > 0 {
+                notes.push_str(&format!(
+                    "\n\nmax_files cap reached: searched {scanned} of {} candidate files. \
+                     Narrow with path, ext, or category, or raise max_files",
+                    scanned + over_cap
+                ));
+            }
+            if deadline.timed_out() {
+                notes.push_str(&format!(
+                    "\n\ntimed_out: stopped after {timeout_ms}ms, results are partial. \
+                     Narrow with path, ext, or category, or raise timeout_ms"
+                ));
+            }
+            if file_hits.is_empty() {
+                let empty = format!("0 matches in 0 files\n{}", notes.trim_start());
+                if deadline.timed_out() {
+                    return (empty, false);
+                }
+                return spelling_fallback(
+                    state, name, &args, session, structured, &repos, query, empty,
+                );
+            }
 
             file_hits
                 .sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+            let copies = if multi {
+                let paths: Vec<&str> = file_hits.iter().map(|h| h.display_path.as_str()).collect();
+                let copies = collapse_identical_copies(&repos, &paths);
+                let mut keep = copies.iter().map(Option::is_some);
+                file_hits.retain(|_| keep.next().unwrap_or(true));
+                copies.into_iter().flatten().collect()
+            } else {
+                vec![Vec::new(); file_hits.len()]
+            };
+
+            let truncate = |line: &str| crate::format::truncate_line(line, profile.line_len());
+
+            if let Some(group_by) = group_by {
+                let groups = group_grep_hits(
+                    file_hits.iter().map(|h| (h.group.as_str(), h.score, h.total_match_count)),
+                );
+                let total_matches: usize = file_hits.iter().map(|h| h.total_match_count).sum();
+                let mut out = format!(
+                    "{total_matches} matches in {} files across {} {}\n",
+                    file_hits.len(),
+                    groups.len(),
+                    group_by.noun()
+                );
+                for g in groups.iter().take(limit) {
+                    out.push_str(&format!(
+                        "\n{}  ({} matches in {} files)\n",
+                        g.key, g.matches, g.files
+                    ));
+                    if output_mode == "files_only" {
+                        continue;
+                    }
+                    let top = &file_hits[g.top];
+                    let i = top.match_indices[0];
+                    out.push_str(&format!(
+                        "  {}:L{}: {}\n",
+                        top.display_path,
+                        i + 1,
+                        truncate(top.lines.get(i).trim())
+                    ));
+                }
+                if groups.len() > limit {
+                    out.push_str(&format!("\n... and {} more\n", groups.len() - limit));
+                }
+                out.push_str(&notes);
+                return (out, false);
+            }
 
             let mut results = Vec::new();
             let mut total_matches: usize = 0;
-
-            let truncate = |line: &str| -> String {
-                if line.len() > 200 {
-                    format!("{}...", &line[..line.floor_char_boundary(200)])
+            let file_header = |hit: &GrepFileHit, term_info: &str, also_in: &str| {
+                if profile.detailed_headers() {
+                    format!(
+                        "{}  ({}, score {:.0}{term_info}){also_in}",
+                        hit.display_path, hit.desc, hit.score
+                    )
                 } else {
-                    line.to_string()
+                    format!("{}{also_in}", hit.display_path)
                 }
             };
 
-            for hit in &file_hits {
+            for (hit, copies) in file_hits.iter().zip(&copies) {
                 if results.len() >= limit {
                     break;
                 }
                 total_matches += hit.total_match_count;
+                let also_in = if copies.is_empty() {
+                    String::new()
+                } else {
+                    format!("\n  also in: {}", copies.join(", "))
+                };
 
                 let term_info = if hit.total_terms > 1 {
                     format!(", {}/{} terms", hit.terms_matched, hit.total_terms)
@@ -722,24 +2157,38 @@ fn handle_tool_call(
 
                 if output_mode == "files_only" {
                     results.push(format!(
-                        "{}  ({}, score {:.0}{}, {} matches)",
+                        "{}  ({}, score {:.0}{}, {} matches){also_in}",
                         hit.display_path, hit.desc, hit.score, term_info, hit.total_match_count
                     ));
-                } else if context_lines == 0 {
+                } else if context == SnippetContext::Symbol {
+                    results.push(format!(
+                        "{}\n{}",
+                        file_header(hit, &term_info, &also_in),
+                        match &hit.lines {
+                            GrepLines::All(lines) => {
+                                symbol_context_lines(lines, &hit.match_indices, truncate)
+                            }
+                            // No enclosing symbols without the whole file
+                            GrepLines::Sparse { .. } => hit
+                                .match_indices
+                                .iter()
+                                .map(|&i| format!("  L{}: {}", i + 1, truncate(hit.lines.get(i))))
+                                .collect(),
+                        }
+                        .join("\n")
+                    ));
+                } else if context == SnippetContext::Lines(0) {
                     let file_lines: Vec<String> = hit
                         .match_indices
                         .iter()
-                        .map(|&i| format!("  L{}: {}", i + 1, truncate(&hit.lines[i])))
+                        .map(|&i| format!("  L{}: {}", i + 1, truncate(hit.lines.get(i))))
                         .collect();
                     results.push(format!(
-                        "{}  ({}, score {:.0}{})\n{}",
-                        hit.display_path,
-                        hit.desc,
-                        hit.score,
-                        term_info,
+                        "{}\n{}",
+                        file_header(hit, &term_info, &also_in),
                         file_lines.join("\n")
                     ));
-                } else {
+                } else if let SnippetContext::Lines(context_lines) = context {
                     let match_set: HashSet<usize> = hit.match_indices.iter().copied().collect();
                     let mut ranges: Vec<(usize, usize)> = Vec::new();
                     for &idx in &hit.match_indices {
@@ -767,28 +2216,29 @@ fn handle_tool_call(
                                 "  L{}{} {}",
                                 i + 1,
                                 sep,
-                                truncate(&hit.lines[i])
+                                truncate(hit.lines.get(i))
                             ));
                         }
                     }
                     results.push(format!(
-                        "{}  ({}, score {:.0}{})\n{}",
-                        hit.display_path,
-                        hit.desc,
-                        hit.score,
-                        term_info,
+                        "{}\n{}",
+                        file_header(hit, &term_info, &also_in),
                         file_output.join("\n")
                     ));
                 }
             }
 
             let query_time = start.elapsed().as_millis();
-            let header = format!(
-                "Found {} matches in {} files ({query_time}ms, ranked by relevance)\n\n",
-                total_matches,
-                results.len()
-            );
-            (format!("{header}{}", results.join("\n\n")), false)
+            let header = if profile.detailed_headers() {
+                format!(
+                    "Found {} matches in {} files ({query_time}ms, ranked by relevance)\n\n",
+                    total_matches,
+                    results.len()
+                )
+            } else {
+                format!("{} matches in {} files\n\n", total_matches, results.len())
+            };
+            (format!("{header}{}{notes}", results.join("\n\n")), false)
         }
 
         // =================================================================
@@ -799,41 +2249,78 @@ fn handle_tool_call(
             match action {
                 "files" => {
                     // Was cs_get_module_files
-                    let repo = match resolve_repo(state, &args) {
+                    let (repo, module) = match crate::namespace::resolve_module(
+                        state,
+                        args["module"].as_str().unwrap_or(""),
+                        args["repo"].as_str(),
+                    ) {
                         Ok(r) => r,
-                        Err(e) => return (format!("Error: {e}"), true),
+                        Err(e) => return tool_error(e),
                     };
-                    let module = args["module"].as_str().unwrap_or("");
+                    let label = module_label(state, repo, &module);
+                    let module = module.as_str();
                     let prefix_dot = format!("{module} > ");
                     let mut out = String::new();
                     let mut count = 0;
-                    for (cat, files) in &repo.manifest {
+                    for (cat, files) in repo.config.sort_order.sorted(&repo.manifest) {
                         if cat != module && !cat.starts_with(&prefix_dot) {
                             continue;
                         }
                         for f in files {
-                            out.push_str(&format!("{}  ({}, {} bytes)\n", f.path, f.desc, f.size));
+                            out.push_str(&format!("{}  ({}, {} bytes)", f.path, f.desc, f.size));
+                            if let Some(h) = repo.licenses.header(&f.path) {
+                                out.push_str(&format!("  license: {}", h.license));
+                            }
+                            out.push('\n');
                             count += 1;
                         }
                     }
                     if count == 0 {
                         (format!("No files found for module '{module}'"), true)
                     } else {
-                        (format!("{count} files in {module}\n\n{out}"), false)
+                        let docs = match repo.module_docs.get(module) {
+                            Some(docs) => format!(
+                                "Docs: {} ({})\n",
+                                docs.join(", "),
+                                module_docs_uri(&repo.name, module)
+                            ),
+                            None => String::new(),
+                        };
+                        let licenses = crate::licenses::format_summary(
+                            &repo.licenses.summarize(
+                                repo.manifest
+                                    .iter()
+                                    .filter(|(cat, _)| {
+                                        *cat == module || cat.starts_with(&prefix_dot)
+                                    })
+                                    .flat_map(|(_, files)| files.iter().map(|f| f.path.as_str())),
+                            ),
+                        );
+                        let licenses = if licenses.is_empty() {
+                            String::new()
+                        } else {
+                            format!("License: {licenses}\n")
+                        };
+                        (format!("{count} files in {label}\n{docs}{licenses}\n{out}"), false)
                     }
                 }
                 "deps" => {
                     // Was cs_get_deps
-                    let repo = match resolve_repo(state, &args) {
+                    let (repo, module) = match crate::namespace::resolve_module(
+                        state,
+                        args["module"].as_str().unwrap_or(""),
+                        args["repo"].as_str(),
+                    ) {
                         Ok(r) => r,
-                        Err(e) => return (format!("Error: {e}"), true),
+                        Err(e) => return tool_error(e),
                     };
-                    let module = args["module"].as_str().unwrap_or("");
+                    let label = module_label(state, repo, &module);
+                    let module = module.as_str();
                     match repo.deps.get(module) {
                         None => (format!("No dependency info found for '{module}'"), true),
                         Some(dep) => {
                             let mut out =
-                                format!("Module: {module}\nCategory: {}\n\n", dep.category_path);
+                                format!("Module: {label}\nCategory: {}\n\n", dep.category_path);
                             if !dep.public.is_empty() {
                                 out.push_str("Public dependencies:\n");
                                 for d in &dep.public {
@@ -850,11 +2337,136 @@ fn handle_tool_call(
                         }
                     }
                 }
-                _ => {
-                    // "list" (default) — was cs_list_modules
+                "graph" => {
+                    let repo = match resolve_repo(state, &args) {
+                        Ok(r) => r,
+                        Err(e) => return tool_error(e),
+                    };
+                    let depth = args["depth"]
+                        .as_u64()
+                        .map_or(crate::modgraph::DEFAULT_DEPTH, |d| d.clamp(1, 5) as usize);
+                    let prefix = args["prefix"].as_str().unwrap_or("");
+                    let limit = args["limit"].as_u64().unwrap_or(100).min(1000) as usize;
+                    let graph = crate::modgraph::build(repo, depth, prefix);
+                    match args["format"].as_str().unwrap_or("text") {
+                        "text" => (crate::modgraph::format_text(&graph, limit), false),
+                        "dot" => (crate::modgraph::to_dot(&graph), false),
+                        other => tool_error(format!(
+                            "Unknown format '{other}'. Expected 'text' or 'dot'"
+                        )),
+                    }
+                }
+                "entries" => {
+                    let repo = match resolve_repo(state, &args) {
+                        Ok(r) => r,
+                        Err(e) => return tool_error(e),
+                    };
+                    let prefix = args["prefix"].as_str().unwrap_or("");
+                    let limit = args["limit"].as_u64().unwrap_or(50).min(1000) as usize;
+                    let kind = args["kind"].as_str();
+
+                    let mut reach: HashMap<&str, usize> = HashMap::new();
+                    let mut by_kind: BTreeMap<EntryKind, Vec<(&EntryPoint, usize)>> =
+                        BTreeMap::new();
+                    for e in &repo.entry_points {
+                        if !entries::path_in(&e.path, prefix)
+                            || kind.is_some_and(|k| k != e.kind.name())
+                        {
+                            continue;
+                        }
+                        let r = *reach
+                            .entry(&e.path)
+                            .or_insert_with(|| entries::import_reach(&repo.import_graph, &e.path));
+                        by_kind.entry(e.kind).or_default().push((e, r));
+                    }
+
+                    let total: usize = by_kind.values().map(Vec::len).sum();
+                    let mut out = format!("{total} entry points\n");
+                    for (k, list) in &mut by_kind {
+                        list.sort_by(|a, b| {
+                            b.1.cmp(&a.1)
+                                .then(a.0.path.cmp(&b.0.path))
+                                .then(a.0.line.cmp(&b.0.line))
+                        });
+                        out.push_str(&format!("\n{} ({}):\n", k.name(), list.len()));
+                        for (e, r) in list.iter().take(limit) {
+                            out.push_str(&format!("  {}:{}  {}", e.path, e.line, e.detail));
+                            if *r > 0 {
+                                out.push_str(&format!("  (reaches {r} files)"));
+                            }
+                            out.push('\n');
+                        }
+                        if list.len() > limit {
+                            out.push_str(&format!("  ... and {} more\n", list.len() - limit));
+                        }
+                    }
+
+                    // Import-graph roots the patterns missed
+                    if kind.is_none() {
+                        let matched: HashSet<&str> =
+                            repo.entry_points.iter().map(|e| e.path.as_str()).collect();
+                        let roots: Vec<(String, usize)> =
+                            entries::import_roots(&repo.import_graph, &matched)
+                                .into_iter()
+                                .filter(|(p, _)| entries::path_in(p, prefix))
+                                .take(limit.min(10))
+                                .collect();
+                        if !roots.is_empty() {
+                            out.push_str("\nImport roots (imported by nothing):\n");
+                            for (p, r) in roots {
+                                out.push_str(&format!("  {p}  (reaches {r} files)\n"));
+                            }
+                        } else if total == 0 {
+                            out.push_str("\nNo entry points detected.\n");
+                        }
+                    }
+                    (out, false)
+                }
+                "features" => {
+                    let repo = match resolve_repo(state, &args) {
+                        Ok(r) => r,
+                        Err(e) => return tool_error(e),
+                    };
+                    let limit = args["limit"].as_u64().unwrap_or(50).min(1000) as usize;
+                    let map = crate::features::map_features(repo);
+                    (
+                        crate::features::format_feature_map(
+                            &map,
+                            args["feature"].as_str(),
+                            args["prefix"].as_str().unwrap_or(""),
+                            limit,
+                        ),
+                        false,
+                    )
+                }
+                "licenses" => {
                     let repo = match resolve_repo(state, &args) {
                         Ok(r) => r,
-                        Err(e) => return (format!("Error: {e}"), true),
+                        Err(e) => return tool_error(e),
+                    };
+                    let limit = args["limit"].as_u64().unwrap_or(50).min(1000) as usize;
+                    let prefix = args["prefix"].as_str().unwrap_or("");
+                    (crate::licenses::format_licenses(repo, prefix, limit), false)
+                }
+                _ => {
+                    // "list" (default) — was cs_list_modules
+                    // Without a repo selection in multi-repo mode, list every repo's
+                    // modules under qualified ids (`repo/module`) and flag collisions.
+                    let unified = args["repo"].is_null()
+                        && state.repos.len() > 1
+                        && state.default_repo.is_none();
+                    let repos: Vec<&RepoState> = if unified {
+                        state.repos.values().map(|r| &**r).collect()
+                    } else {
+                        match resolve_repo(state, &args) {
+                            Ok(r) => vec![r],
+                            Err(e) => return tool_error(e),
+                        }
+                    };
+                    let collisions = if unified {
+                        crate::namespace::module_collisions(state)
+                    } else {
+                        BTreeMap::new()
                     };
                     let limit = args["limit"].as_u64().unwrap_or(100).min(1000) as usize;
                     let prefix = args["prefix"].as_str();
@@ -862,26 +2474,66 @@ fn handle_tool_call(
                     let mut out = String::new();
                     let mut shown = 0usize;
                     let mut total = 0usize;
-                    for (cat, files) in &repo.manifest {
-                        if let Some(pfx) = prefix {
-                            if !cat.starts_with(pfx) {
-                                continue;
+                    for repo in &repos {
+                        for (cat, files) in repo.config.sort_order.sorted(&repo.manifest) {
+                            let id = if unified {
+                                crate::namespace::qualify(&repo.name, cat)
+                            } else {
+                                cat.clone()
+                            };
+                            if let Some(pfx) = prefix {
+                                if !cat.starts_with(pfx) && !id.starts_with(pfx) {
+                                    continue;
+                                }
+                            }
+                            total += 1;
+                            if shown < limit {
+                                out.push_str(&format!("{id}  ({} files)", files.len()));
+                                if let Some(docs) = repo.module_docs.get(cat) {
+                                    out.push_str(&format!("  docs: {}", docs.join(", ")));
+                                }
+                                let licenses =
+                                    repo.licenses.summarize(files.iter().map(|f| f.path.as_str()));
+                                if !licenses.is_empty() {
+                                    out.push_str(&format!(
+                                        "  license: {}",
+                                        crate::licenses::format_summary(&licenses)
+                                    ));
+                                }
+                                if let Some(owners) = collisions.get(cat) {
+                                    let others: Vec<&str> = owners
+                                        .iter()
+                                        .map(String::as_str)
+                                        .filter(|o| *o != repo.name)
+                                        .collect();
+                                    out.push_str(&format!("  [also in: {}]", others.join(", ")));
+                                }
+                                out.push('\n');
+                                shown += 1;
                             }
-                        }
-                        total += 1;
-                        if shown < limit {
-                            out.push_str(&format!("{cat}  ({} files)\n", files.len()));
-                            shown += 1;
                         }
                     }
+                    if unified && !collisions.is_empty() {
+                        out.push_str(&format!(
+                            "\n{} {} in several repos; pass qualified ids (repo/module) \
+                             to the files and deps actions.\n",
+                            collisions.len(),
+                            if collisions.len() == 1 { "name exists" } else { "names exist" }
+                        ));
+                    }
                     let truncated = if total > shown {
                         format!("\n... and {} more (use prefix filter to narrow)", total - shown)
                     } else {
                         String::new()
                     };
+                    let across = if unified {
+                        format!(" across {} repos", repos.len())
+                    } else {
+                        String::new()
+                    };
                     (
                         format!(
-                            "{total} modules{}\n\n{out}{truncated}",
+                            "{total} modules{}{across}\n\n{out}{truncated}",
                             if prefix.is_some() { " matching" } else { " total" }
                         ),
                         false,
@@ -895,64 +2547,101 @@ fn handle_tool_call(
         // =================================================================
         "cs_imports" => {
             let transitive = args["transitive"].as_bool().unwrap_or(false);
+            match args["action"].as_str().unwrap_or("imports") {
+                "imports" => {}
+                "violations" => {
+                    let repo = match resolve_repo(state, &args) {
+                        Ok(r) => r,
+                        Err(e) => return tool_error(e),
+                    };
+                    let limit = args["limit"].as_u64().unwrap_or(100).min(1000) as usize;
+                    let config = &repo.config.boundaries;
+                    let violations = crate::boundaries::check(config, &repo.import_graph);
+                    return (
+                        crate::boundaries::format_violations(
+                            config,
+                            &repo.import_graph,
+                            &violations,
+                            limit,
+                        ),
+                        false,
+                    );
+                }
+                "test_impact" => {
+                    let repo = match resolve_repo(state, &args) {
+                        Ok(r) => r,
+                        Err(e) => return tool_error(e),
+                    };
+                    let max_depth = args["max_depth"].as_u64().unwrap_or(20).min(50) as usize;
+                    let limit = args["limit"].as_u64().unwrap_or(50).min(1000) as usize;
+                    let path = args["path"].as_str().unwrap_or("");
+                    let (what, changed) = if !path.is_empty() {
+                        match import_targets(repo, path) {
+                            Ok(files) => (path.to_string(), files),
+                            Err(e) => return tool_error(e),
+                        }
+                    } else {
+                        let since = match args["since"].as_str().filter(|s| !s.is_empty()) {
+                            Some(since) => since.to_string(),
+                            None => {
+                                match crate::git::default_branch(&repo.root, &repo.config.git) {
+                                    Ok(b) => b,
+                                    Err(e) => {
+                                        return tool_error(format!(
+                                            "{e}, or pass 'since' or 'path'"
+                                        ))
+                                    }
+                                }
+                            }
+                        };
+                        match crate::git::changed_on_branch(&repo.root, &since) {
+                            Ok(files) => (format!("changes since {since}"), files),
+                            Err(e) => return tool_error(e),
+                        }
+                    };
+                    let impact = crate::testmap::test_impact(repo, &changed, max_depth);
+                    return (crate::testmap::format_test_impact(&impact, &what, limit), false);
+                }
+                other => {
+                    return tool_error(format!(
+                        "Unknown cs_imports action '{other}'. Use: imports, violations, test_impact"
+                    ))
+                }
+            }
             if transitive {
                 // Impact analysis (was cs_impact)
                 let repo = match resolve_repo(state, &args) {
                     Ok(r) => r,
-                    Err(e) => return (format!("Error: {e}"), true),
+                    Err(e) => return tool_error(e),
                 };
                 let path = args["path"].as_str().unwrap_or("");
                 let max_depth = args["max_depth"].as_u64().unwrap_or(5).min(20) as usize;
                 let file_limit = args["limit"].as_u64().unwrap_or(50).min(500) as usize;
 
                 if path.is_empty() {
-                    return ("Error: path is required".to_string(), true);
-                }
-
-                let mut visited: HashSet<String> = HashSet::new();
-                let mut queue: VecDeque<(String, usize)> = VecDeque::new();
-                let mut by_depth: BTreeMap<usize, Vec<String>> = BTreeMap::new();
-
-                visited.insert(path.to_string());
-                queue.push_back((path.to_string(), 0));
-
-                while let Some((current, depth)) = queue.pop_front() {
-                    if depth > 0 {
-                        by_depth.entry(depth).or_default().push(current.clone());
-                    }
-                    if depth >= max_depth {
-                        continue;
-                    }
-                    if let Some(dependents) = repo.import_graph.imported_by.get(&current) {
-                        for dep in dependents {
-                            if visited.insert(dep.clone()) {
-                                queue.push_back((dep.clone(), depth + 1));
-                            }
-                        }
-                    }
-                    for edge in &state.cross_repo_edges {
-                        if edge.to_repo == repo.name && edge.to_file == current {
-                            let key = format!("[{}] {}", edge.from_repo, edge.from_file);
-                            if visited.insert(key.clone()) {
-                                by_depth.entry(depth + 1).or_default().push(key);
-                            }
-                        }
-                    }
+                    return tool_error("path is required");
                 }
+                let targets = match import_targets(repo, path) {
+                    Ok(t) => t,
+                    Err(e) => return tool_error(e),
+                };
 
-                let total: usize = by_depth.values().map(|v| v.len()).sum();
+                let impact = crate::impact::analyze(state, repo, &targets, max_depth);
+                let total = impact.total();
                 if total == 0 {
-                    return (
-                        format!("No dependents found for '{path}'. This file is not imported by any other file."),
-                        false,
-                    );
+                    let what = if targets.len() == 1 {
+                        "This file is not imported by any other file".to_string()
+                    } else {
+                        format!("None of its {} files is imported from outside it", targets.len())
+                    };
+                    return (format!("No dependents found for '{path}'. {what}."), false);
                 }
 
-                let mut out = format!("Impact analysis for {path}\n\n");
-                let max_depth_found = *by_depth.keys().max().unwrap_or(&0);
+                let mut out = format!("Impact analysis for {path}{}\n\n", set_note(&targets));
+                let max_depth_found = impact.max_depth();
                 let mut shown = 0usize;
                 for depth in 1..=max_depth_found {
-                    if let Some(files) = by_depth.get(&depth) {
+                    if let Some(files) = impact.by_depth.get(&depth) {
                         let label = if depth == 1 { "direct dependents" } else { "" };
                         out.push_str(&format!(
                             "Depth {}{}: {} file{}\n",
@@ -963,7 +2652,16 @@ fn handle_tool_call(
                         ));
                         for f in files {
                             if shown < file_limit {
-                                out.push_str(&format!("  {f}\n"));
+                                let label = if depth == 1 {
+                                    impact.strength(f).label()
+                                } else {
+                                    String::new()
+                                };
+                                if label.is_empty() {
+                                    out.push_str(&format!("  {f}\n"));
+                                } else {
+                                    out.push_str(&format!("  {f}  ({label})\n"));
+                                }
                                 shown += 1;
                             }
                         }
@@ -986,39 +2684,79 @@ fn handle_tool_call(
                 // Direct imports (was cs_find_imports)
                 let repo = match resolve_repo(state, &args) {
                     Ok(r) => r,
-                    Err(e) => return (format!("Error: {e}"), true),
+                    Err(e) => return tool_error(e),
                 };
                 let path = args["path"].as_str().unwrap_or("");
                 let direction = args["direction"].as_str().unwrap_or("both");
+                if path.is_empty() {
+                    return tool_error("path is required");
+                }
 
-                let imports: Vec<String> = if direction == "both" || direction == "imports" {
-                    repo.import_graph.imports.get(path).cloned().unwrap_or_default()
-                } else {
-                    vec![]
-                };
-                let imported_by: Vec<String> = if direction == "both" || direction == "imported_by"
-                {
-                    repo.import_graph.imported_by.get(path).cloned().unwrap_or_default()
-                } else {
-                    vec![]
+                let targets = match import_targets(repo, path) {
+                    Ok(t) => t,
+                    Err(e) => return tool_error(e),
                 };
+                let members: HashSet<&str> = targets.iter().map(|t| t.as_str()).collect();
+                let want_imports = direction == "both" || direction == "imports";
+                let want_imported_by = direction == "both" || direction == "imported_by";
+
+                // Edges crossing the boundary of the selected files, merged per outside file,
+                // with the selected files each one touches
+                let graph = &repo.import_graph;
+                type Crossing<'t> = BTreeMap<String, (crate::types::ImportEdge, Vec<&'t str>)>;
+                let mut imports: Crossing = BTreeMap::new();
+                let mut imported_by: Crossing = BTreeMap::new();
+                for target in &targets {
+                    if want_imports {
+                        for dep in graph.imports.get(target).into_iter().flatten() {
+                            if members.contains(dep.as_str()) {
+                                continue;
+                            }
+                            let entry = imports.entry(dep.clone()).or_default();
+                            entry.0.merge(graph.edge(target, dep));
+                            entry.1.push(target);
+                        }
+                    }
+                    if want_imported_by {
+                        for dep in graph.imported_by.get(target).into_iter().flatten() {
+                            if members.contains(dep.as_str()) {
+                                continue;
+                            }
+                            let entry = imported_by.entry(dep.clone()).or_default();
+                            entry.0.merge(graph.edge(dep, target));
+                            entry.1.push(target);
+                        }
+                    }
+                }
+                // Strongest edges first (stable, so ties stay alphabetical)
+                let mut imports: Vec<_> = imports.into_iter().collect();
+                let mut imported_by: Vec<_> = imported_by.into_iter().collect();
+                for edges in [&mut imports, &mut imported_by] {
+                    edges.sort_by(|a, b| {
+                        crate::types::ImportEdge::strength_order(&(a.1).0, &(b.1).0)
+                    });
+                }
 
                 let mut cross_imports = Vec::new();
                 let mut cross_imported_by = Vec::new();
                 for edge in &state.cross_repo_edges {
                     if edge.from_repo == repo.name
-                        && edge.from_file == path
-                        && (direction == "both" || direction == "imports")
+                        && members.contains(edge.from_file.as_str())
+                        && want_imports
                     {
                         cross_imports.push(format!("[{}] {}", edge.to_repo, edge.to_file));
                     }
                     if edge.to_repo == repo.name
-                        && edge.to_file == path
-                        && (direction == "both" || direction == "imported_by")
+                        && members.contains(edge.to_file.as_str())
+                        && want_imported_by
                     {
                         cross_imported_by.push(format!("[{}] {}", edge.from_repo, edge.from_file));
                     }
                 }
+                cross_imports.sort();
+                cross_imports.dedup();
+                cross_imported_by.sort();
+                cross_imported_by.dedup();
 
                 if imports.is_empty()
                     && imported_by.is_empty()
@@ -1028,17 +2766,23 @@ fn handle_tool_call(
                     return (format!("No import relationships found for '{path}'"), false);
                 }
 
-                let mut out = format!("# {path}\n\n");
+                let line = |file: &str, edge: &crate::types::ImportEdge, via: &[&str]| {
+                    let desc = repo
+                        .all_files
+                        .iter()
+                        .find(|f| f.rel_path == file)
+                        .map(|f| f.desc.as_str())
+                        .unwrap_or("");
+                    let label = edge.label();
+                    let note = if label.is_empty() { String::new() } else { format!(" [{label}]") };
+                    let via = if targets.len() > 1 { via_note(via) } else { String::new() };
+                    format!("  {file}{note}  ({desc}){via}\n")
+                };
+                let mut out = format!("# {path}{}\n\n", set_note(&targets));
                 if !imports.is_empty() {
                     out.push_str(&format!("Imports ({} files):\n", imports.len()));
-                    for inc in &imports {
-                        let desc = repo
-                            .all_files
-                            .iter()
-                            .find(|f| f.rel_path == *inc)
-                            .map(|f| f.desc.as_str())
-                            .unwrap_or("");
-                        out.push_str(&format!("  {inc}  ({desc})\n"));
+                    for (file, (edge, via)) in &imports {
+                        out.push_str(&line(file, edge, via));
                     }
                     out.push('\n');
                 }
@@ -1051,14 +2795,8 @@ fn handle_tool_call(
                 }
                 if !imported_by.is_empty() {
                     out.push_str(&format!("Imported by ({} files):\n", imported_by.len()));
-                    for inc in &imported_by {
-                        let desc = repo
-                            .all_files
-                            .iter()
-                            .find(|f| f.rel_path == *inc)
-                            .map(|f| f.desc.as_str())
-                            .unwrap_or("");
-                        out.push_str(&format!("  {inc}  ({desc})\n"));
+                    for (file, (edge, via)) in &imported_by {
+                        out.push_str(&line(file, edge, via));
                     }
                 }
                 if !cross_imported_by.is_empty() {
@@ -1080,14 +2818,28 @@ fn handle_tool_call(
         "cs_search" => {
             let repos = resolve_repos_for_search(state, &args);
             if repos.is_empty() {
-                return ("Error: No matching repos found".to_string(), true);
+                return tool_error("No matching repos found");
             }
+            let (repos, scope_name) = match resolve_scope(state, &args, repos) {
+                Ok(r) => r,
+                Err(e) => return tool_error(e),
+            };
             let multi = repos.len() > 1;
 
             let raw_query = args["query"].as_str().unwrap_or("");
             if raw_query.is_empty() {
-                return ("Error: Query must not be empty".to_string(), true);
+                return tool_error("Query must not be empty");
             }
+            let profile = match crate::format::resolve_profile(&args, session) {
+                Ok(p) => p,
+                Err(e) => return tool_error(e),
+            };
+            let dir_limit = args["dirLimit"].as_u64().unwrap_or(3).min(20) as usize;
+            // Snippets are single lines, so only symbol context applies
+            let context = match SnippetContext::parse(&args["context"], 0) {
+                Ok(c) => c,
+                Err(e) => return tool_error(e),
+            };
             let file_limit =
                 args["fileLimit"].as_u64().unwrap_or(args["limit"].as_u64().unwrap_or(30)).min(100)
                     as usize;
@@ -1098,24 +2850,36 @@ fn handle_tool_call(
             let cat_filter = args["category"].as_str().map(|s| s.to_string());
             let path_filter = args["path"].as_str();
             let match_mode = args["match_mode"].as_str().unwrap_or("all");
+            let include_generated = args["include_generated"].as_bool().unwrap_or(false);
+
+            // `OR`, `-term`, and `!term` are operators unless the query is a phrase or regex
+            let ops = if matches!(match_mode, "exact" | "regex") {
+                crate::fuzzy::QueryOperators {
+                    terms: raw_query.split_whitespace().map(str::to_string).collect(),
+                    ..Default::default()
+                }
+            } else {
+                crate::fuzzy::parse_query_operators(raw_query)
+            };
+            if ops.terms.is_empty() {
+                return tool_error("Query needs at least one term besides exclusions");
+            }
+            let match_mode = if ops.any && match_mode == "all" { "any" } else { match_mode };
+            let positive_query = ops.terms.join(" ");
 
             let start = std::time::Instant::now();
 
             // Content grep pattern
-            let terms: Vec<&str> = raw_query.split_whitespace().collect();
+            let terms: Vec<&str> = ops.terms.iter().map(String::as_str).collect();
             let terms_lower: Vec<String> = terms.iter().map(|t| t.to_lowercase()).collect();
             let require_all_terms = match_mode == "all" && terms.len() > 1;
 
             let pattern = match match_mode {
-                "exact" => {
-                    RegexBuilder::new(&regex::escape(raw_query)).case_insensitive(true).build()
-                }
-                "regex" => RegexBuilder::new(raw_query).case_insensitive(true).build(),
-                _ => {
-                    let pattern_str =
-                        terms.iter().map(|t| regex::escape(t)).collect::<Vec<_>>().join("|");
-                    RegexBuilder::new(&pattern_str).case_insensitive(true).build()
-                }
+                "exact" => grep_regex(&regex::escape(raw_query)),
+                "regex" => grep_regex(raw_query),
+                _ => grep_regex(
+                    &terms.iter().map(|t| regex::escape(t)).collect::<Vec<_>>().join("|"),
+                ),
             };
 
             struct FindResult {
@@ -1125,6 +2889,13 @@ fn handle_tool_call(
                 grep_score: f64,
                 grep_count: usize,
                 top_match: Option<String>,
+                /// Signature enclosing `top_match`, as `L12: fn name(...)`, for
+                /// `context: "symbol"`.
+                top_match_symbol: Option<String>,
+                /// Best-matching definition in the file and its match strength in 0..=1,
+                /// scaled by the file's ranking boost.
+                definition: Option<crate::symbols::SymbolEntry>,
+                symbol_score: f64,
                 terms_matched: usize,
                 total_terms: usize,
             }
@@ -1135,22 +2906,34 @@ fn handle_tool_call(
 
             for repo in &repos {
                 let config = &repo.config;
+                let ranker = crate::ranking::Ranker::new(repo);
+                let scope = scope_name.as_deref().map(|n| &config.scopes[n]);
 
                 // 1. Fuzzy filename search
-                let query = crate::fuzzy::preprocess_search_query(raw_query);
-                let search_resp = run_search(
+                let query = crate::fuzzy::preprocess_search_query(&positive_query);
+                let search_resp = run_search_ranked(
                     &repo.search_files,
                     &repo.search_modules,
                     &query,
                     file_limit,
                     module_limit,
+                    &|f| ranker.boost(&f.path),
                 );
 
                 for m in search_resp.modules {
                     all_modules.push((repo, m));
                 }
 
+                let hidden = hidden_origins(repo, include_generated);
+
                 for f in &search_resp.files {
+                    if scope.is_some_and(|s| !s.matches(&f.path, config))
+                        || hidden.contains(f.path.as_str())
+                        || ops.excludes(&f.path)
+                        || !config.read_policy.permits(&f.path)
+                    {
+                        continue;
+                    }
                     if let Some(prefix) = path_filter {
                         if !f.path.starts_with(prefix) {
                             continue;
@@ -1177,20 +2960,82 @@ fn handle_tool_call(
                             grep_score: 0.0,
                             grep_count: 0,
                             top_match: None,
+                            top_match_symbol: None,
+                            definition: None,
+                            symbol_score: 0.0,
                             terms_matched: 0,
                             total_terms: terms_lower.len(),
                         },
                     );
                 }
 
+                // 1b. Definitions whose names match, the best one per file
+                let in_category: Option<HashSet<&str>> = cat_filter.as_ref().map(|cat| {
+                    repo.search_files
+                        .iter()
+                        .filter(|f| f.category.starts_with(cat.as_str()))
+                        .map(|f| f.path.as_str())
+                        .collect()
+                });
+                let symbol_hits =
+                    crate::symbols::search_symbols(&repo.symbols, &positive_query, |s| {
+                        let ext = s.path.rsplit_once('.').map_or("", |(_, e)| e);
+                        !s.is_heading()
+                            && !hidden.contains(s.path.as_str())
+                            && scope.is_none_or(|sc| sc.matches(&s.path, config))
+                            && path_filter.is_none_or(|prefix| s.path.starts_with(prefix))
+                            && ext_filter.as_ref().is_none_or(|exts| exts.contains(ext))
+                            && in_category
+                                .as_ref()
+                                .is_none_or(|paths| paths.contains(s.path.as_str()))
+                            && !ops.excludes(&s.path)
+                    });
+                let mut defining: HashSet<&str> = HashSet::new();
+                // Scattered-letter matches help an editor's symbol picker, not file ranking
+                for hit in symbol_hits.into_iter().filter(|h| h.score > 1) {
+                    let path = hit.symbol.path.as_str();
+                    if defining.len() >= file_limit || !defining.insert(path) {
+                        continue;
+                    }
+                    let key = repo_path(repo, path, multi);
+                    let entry = merged.entry(key.clone()).or_insert_with(|| FindResult {
+                        display_path: key,
+                        desc: repo
+                            .all_files
+                            .iter()
+                            .find(|f| f.rel_path == path)
+                            .map(|f| f.desc.clone())
+                            .unwrap_or_default(),
+                        name_score: 0.0,
+                        grep_score: 0.0,
+                        grep_count: 0,
+                        top_match: None,
+                        top_match_symbol: None,
+                        definition: None,
+                        symbol_score: 0.0,
+                        terms_matched: 0,
+                        total_terms: terms_lower.len(),
+                    });
+                    entry.symbol_score = f64::from(hit.score) / 4.0 * ranker.boost(path);
+                    entry.definition = Some(hit.symbol.clone());
+                }
+
                 // 2. Content grep
                 if let Ok(ref pattern) = pattern {
-                    let idf_weights: Vec<f64> =
-                        terms_lower.iter().map(|t| repo.term_doc_freq.idf(t)).collect();
+                    let query_terms = query_terms_for(config, match_mode, &terms_lower);
+                    let alias_pattern = crate::aliases::alias_pattern(&query_terms);
+                    let pattern = alias_pattern.as_ref().unwrap_or(pattern);
+                    let idf_weights = query_idf_weights(repo, &query_terms);
                     let candidates: Vec<&ScannedFile> = repo
                         .all_files
                         .iter()
+                        .filter(|f| f.is_searchable() && !hidden.contains(f.rel_path.as_str()))
+                        .filter(|f| !ops.excludes(&f.rel_path))
+                        .filter(|f| config.read_policy.permits(&f.rel_path))
                         .filter(|f| {
+                            if scope.is_some_and(|s| !s.matches(&f.rel_path, config)) {
+                                return false;
+                            }
                             if let Some(prefix) = path_filter {
                                 if !f.rel_path.starts_with(prefix) {
                                     return false;
@@ -1215,11 +3060,12 @@ fn handle_tool_call(
                     let grep_results: Vec<_> = candidates
                         .par_iter()
                         .filter_map(|file| {
-                            let content = fs::read_to_string(&file.abs_path).ok()?;
+                            let content = crate::encoding::read_to_string(&file.abs_path).ok()?;
                             let lines: Vec<&str> = content.lines().collect();
                             let total_lines = lines.len().max(1);
                             let mut match_count = 0usize;
                             let mut best_snippet: Option<String> = None;
+                            let mut best_line_idx = 0usize;
                             let mut best_snippet_term_count: usize = 0;
                             let mut first_match_line_idx = usize::MAX;
                             let mut terms_seen = std::collections::HashSet::new();
@@ -1227,38 +3073,50 @@ fn handle_tool_call(
                                 if !pattern.is_match(line) {
                                     continue;
                                 }
+                                if ops.excludes_line(line) {
+                                    continue;
+                                }
                                 let line_lower = line.to_lowercase();
-                                if require_all_terms
-                                    && !terms_lower.iter().all(|t| line_lower.contains(t.as_str()))
-                                {
+                                let line_terms: Vec<bool> = query_terms
+                                    .iter()
+                                    .map(|t| t.matches(line, &line_lower))
+                                    .collect();
+                                if require_all_terms && !line_terms.iter().all(|&m| m) {
+                                    continue;
+                                }
+                                if alias_pattern.is_some() && !line_terms.contains(&true) {
                                     continue;
                                 }
                                 match_count += 1;
                                 if first_match_line_idx == usize::MAX {
                                     first_match_line_idx = i;
                                 }
-                                let line_term_count = terms_lower
-                                    .iter()
-                                    .filter(|t| line_lower.contains(t.as_str()))
-                                    .count();
-                                for (ti, term) in terms_lower.iter().enumerate() {
-                                    if line_lower.contains(term.as_str()) {
-                                        terms_seen.insert(ti);
-                                    }
+                                let line_term_count = line_terms.iter().filter(|&&m| m).count();
+                                for (ti, _) in line_terms.iter().enumerate().filter(|(_, &m)| m) {
+                                    terms_seen.insert(ti);
                                 }
                                 if line_term_count > best_snippet_term_count {
                                     best_snippet_term_count = line_term_count;
-                                    let trimmed = if line.len() > 120 {
-                                        format!("{}...", &line[..line.floor_char_boundary(120)])
-                                    } else {
-                                        line.to_string()
-                                    };
-                                    best_snippet = Some(trimmed);
+                                    best_line_idx = i;
+                                    best_snippet = Some(crate::format::truncate_line(
+                                        line,
+                                        profile.snippet_len(),
+                                    ));
                                 }
                             }
                             if match_count == 0 {
                                 return None;
                             }
+                            let best_symbol = (context == SnippetContext::Symbol)
+                                .then(|| crate::stubs::enclosing_symbol(&lines, best_line_idx))
+                                .flatten()
+                                .map(|s| {
+                                    let sig = crate::format::truncate_line(
+                                        lines[s].trim(),
+                                        profile.snippet_len(),
+                                    );
+                                    format!("L{}: {sig}", s + 1)
+                                });
 
                             let filename = file
                                 .rel_path
@@ -1279,7 +3137,7 @@ fn handle_tool_call(
                                     first_match_line_idx
                                 },
                                 &idf_weights,
-                            );
+                            ) * ranker.boost(&file.rel_path);
 
                             let key = repo_path(repo, &file.rel_path, multi);
                             Some((
@@ -1288,13 +3146,21 @@ fn handle_tool_call(
                                 grep_score,
                                 match_count,
                                 best_snippet,
+                                best_symbol,
                                 terms_seen.len(),
                             ))
                         })
                         .collect();
 
-                    for (key, desc, grep_score, match_count, best_snippet, terms_matched) in
-                        grep_results
+                    for (
+                        key,
+                        desc,
+                        grep_score,
+                        match_count,
+                        best_snippet,
+                        best_symbol,
+                        terms_matched,
+                    ) in grep_results
                     {
                         let entry = merged.entry(key.clone()).or_insert_with(|| FindResult {
                             display_path: key,
@@ -1303,12 +3169,16 @@ fn handle_tool_call(
                             grep_score: 0.0,
                             grep_count: 0,
                             top_match: None,
+                            top_match_symbol: None,
+                            definition: None,
+                            symbol_score: 0.0,
                             terms_matched: 0,
                             total_terms: terms_lower.len(),
                         });
                         entry.grep_score = grep_score;
                         entry.grep_count = match_count;
                         entry.top_match = best_snippet;
+                        entry.top_match_symbol = best_symbol;
                         entry.terms_matched = terms_matched;
                     }
                 }
@@ -1321,13 +3191,17 @@ fn handle_tool_call(
             let max_name = ranked.iter().map(|r| r.name_score).fold(0.0f64, f64::max).max(1.0);
             let max_grep = ranked.iter().map(|r| r.grep_score).fold(0.0f64, f64::max).max(1.0);
 
+            // Defining the queried symbol counts as much as a perfect name and grep match
+            let named = |r: &FindResult| r.name_score > 0.0 || r.definition.is_some();
             ranked.sort_by(|a, b| {
-                let norm_a =
-                    (a.name_score / max_name) * name_w + (a.grep_score / max_grep) * grep_w;
-                let norm_b =
-                    (b.name_score / max_name) * name_w + (b.grep_score / max_grep) * grep_w;
-                let boost_a = if a.name_score > 0.0 && a.grep_count > 0 { 1.25 } else { 1.0 };
-                let boost_b = if b.name_score > 0.0 && b.grep_count > 0 { 1.25 } else { 1.0 };
+                let norm_a = (a.name_score / max_name) * name_w
+                    + (a.grep_score / max_grep) * grep_w
+                    + a.symbol_score;
+                let norm_b = (b.name_score / max_name) * name_w
+                    + (b.grep_score / max_grep) * grep_w
+                    + b.symbol_score;
+                let boost_a = if named(a) && a.grep_count > 0 { 1.25 } else { 1.0 };
+                let boost_b = if named(b) && b.grep_count > 0 { 1.25 } else { 1.0 };
                 (norm_b * boost_b)
                     .partial_cmp(&(norm_a * boost_a))
                     .unwrap_or(std::cmp::Ordering::Equal)
@@ -1341,11 +3215,13 @@ fn handle_tool_call(
             let has_semantic = {
                 let mut fused = false;
                 for repo in &repos {
+                    let ranker = crate::ranking::Ranker::new(repo);
+                    let hidden = hidden_origins(repo, include_generated);
                     let sem_guard = repo.semantic_index.read().unwrap();
                     if let Some(ref index) = *sem_guard {
                         let sem_limit = file_limit * 2;
                         if let Ok(sem_results) =
-                            crate::semantic::semantic_search(index, raw_query, sem_limit)
+                            crate::semantic::semantic_search(index, &positive_query, sem_limit)
                         {
                             if !sem_results.is_empty() {
                                 fused = true;
@@ -1368,6 +3244,15 @@ fn handle_tool_call(
                                     (usize, &SemanticSearchResult),
                                 > = sem_results
                                     .iter()
+                                    .filter(|sr| {
+                                        scope_name.as_deref().is_none_or(|n| {
+                                            repo.config.scopes[n]
+                                                .matches(&sr.file_path, &repo.config)
+                                        })
+                                    })
+                                    .filter(|sr| !hidden.contains(sr.file_path.as_str()))
+                                    .filter(|sr| !ops.excludes(&sr.file_path))
+                                    .filter(|sr| repo.config.read_policy.permits(&sr.file_path))
                                     .enumerate()
                                     .map(|(i, sr)| {
                                         (repo_path(repo, &sr.file_path, multi), (i + 1, sr))
@@ -1386,9 +3271,12 @@ fn handle_tool_call(
                                             .get(&path)
                                             .map(|(rank, _)| 1.0 / (RRF_K + *rank as f64))
                                             .unwrap_or(0.0);
+                                        // Keyword scores already carry the [ranking] boost
                                         let sem_rrf = sem_map
                                             .get(&path)
-                                            .map(|(rank, _)| 1.0 / (RRF_K + *rank as f64))
+                                            .map(|(rank, sr)| {
+                                                ranker.boost(&sr.file_path) / (RRF_K + *rank as f64)
+                                            })
                                             .unwrap_or(0.0);
                                         let rrf_score = kw_rrf + sem_rrf;
 
@@ -1404,6 +3292,11 @@ fn handle_tool_call(
                                                 grep_score: kw_result.grep_score,
                                                 grep_count: kw_result.grep_count,
                                                 top_match: kw_result.top_match.clone(),
+                                                top_match_symbol: kw_result
+                                                    .top_match_symbol
+                                                    .clone(),
+                                                definition: kw_result.definition.clone(),
+                                                symbol_score: kw_result.symbol_score,
                                                 terms_matched: kw_result.terms_matched,
                                                 total_terms: kw_result.total_terms,
                                             }
@@ -1436,6 +3329,9 @@ fn handle_tool_call(
                                                 grep_score: 0.0,
                                                 grep_count: 0,
                                                 top_match: Some(preview),
+                                                top_match_symbol: None,
+                                                definition: None,
+                                                symbol_score: 0.0,
                                                 terms_matched: 0,
                                                 total_terms: terms_lower.len(),
                                             }
@@ -1460,13 +3356,46 @@ fn handle_tool_call(
             #[cfg(not(feature = "semantic"))]
             let has_semantic = false;
 
+            let copies: Vec<Vec<String>> = if multi {
+                let paths: Vec<&str> = ranked.iter().map(|r| r.display_path.as_str()).collect();
+                let copies = collapse_identical_copies(&repos, &paths);
+                let mut keep = copies.iter().map(Option::is_some);
+                ranked.retain(|_| keep.next().unwrap_or(true));
+                copies.into_iter().flatten().collect()
+            } else {
+                vec![Vec::new(); ranked.len()]
+            };
+
+            // Directories are scored from the final file ranking, so they fuse with it too
+            let dir_hits = if dir_limit == 0 {
+                Vec::new()
+            } else {
+                let paths: Vec<&str> = ranked.iter().map(|r| r.display_path.as_str()).collect();
+                rank_directories(&paths, |dirs| directory_sizes(&repos, dirs, multi), dir_limit)
+            };
+
+            if ranked.is_empty() && all_modules.is_empty() {
+                let empty = format!("0 results for \"{raw_query}\"\n");
+                return spelling_fallback(
+                    state, name, &args, session, structured, &repos, raw_query, empty,
+                );
+            }
+
             let query_time = start.elapsed().as_millis();
-            let mut out = format!(
-                "Found {} results for \"{}\" ({query_time}ms{})\n\n",
-                ranked.len(),
-                raw_query,
-                if has_semantic { ", semantic+keyword" } else { "" }
-            );
+            let result_count = ranked.len() + dir_hits.len();
+            let mut out = if profile.detailed_headers() {
+                format!(
+                    "Found {} results for \"{}\" ({query_time}ms{})\n\n",
+                    result_count,
+                    raw_query,
+                    if has_semantic { ", semantic+keyword" } else { "" }
+                )
+            } else {
+                format!("{result_count} results for \"{raw_query}\"\n\n")
+            };
+
+            let mut structured_modules = Vec::new();
+            let mut structured_results = Vec::new();
 
             // Module results
             if !all_modules.is_empty() {
@@ -1481,12 +3410,23 @@ fn handle_tool_call(
                         "  {prefix}{} ({} files, score {:.1})\n",
                         m.id, m.file_count, m.score
                     ));
+                    structured_modules.push(serde_json::json!({
+                        "repo": repo.name,
+                        "id": m.id,
+                        "fileCount": m.file_count,
+                        "score": m.score,
+                    }));
                 }
                 out.push('\n');
             }
 
-            // File results
-            for r in &ranked {
+            // File results, with directories placed where their score falls
+            let mut dirs = dir_hits.iter().peekable();
+            for (rank, r) in ranked.iter().enumerate() {
+                while let Some(d) = dirs.next_if(|d| d.score > rank_score(rank)) {
+                    out.push_str(&format_dir_hit(d, profile));
+                    structured_results.push(dir_hit_json(d));
+                }
                 let has_name = r.name_score > 0.0;
                 let has_content = r.grep_count > 0;
 
@@ -1496,7 +3436,7 @@ fn handle_tool_call(
                 // - [both]: found by both semantic and keyword search
                 // - [keyword]: found by keyword/filename only
                 let source = if has_semantic {
-                    if !has_name && !has_content {
+                    if !has_name && !has_content && r.definition.is_none() {
                         "[semantic]"
                     } else {
                         "[both]"
@@ -1506,6 +3446,7 @@ fn handle_tool_call(
                         (true, true) => "name+content",
                         (true, false) => "name",
                         (false, true) => "content",
+                        (false, false) if r.definition.is_some() => "symbol",
                         (false, false) => "",
                     }
                 };
@@ -1522,12 +3463,65 @@ fn handle_tool_call(
                 } else {
                     format!(" [{}]", source)
                 };
-                out.push_str(&format!("  {} — {}{tag_str}\n", r.display_path, r.desc));
+                if !profile.detailed_headers() {
+                    out.push_str(&format!("  {}\n", r.display_path));
+                } else if profile.show_scores() {
+                    out.push_str(&format!(
+                        "  {} — {}{tag_str} (name {:.1}, grep {:.1})\n",
+                        r.display_path, r.desc, r.name_score, r.grep_score
+                    ));
+                } else {
+                    out.push_str(&format!("  {} — {}{tag_str}\n", r.display_path, r.desc));
+                }
+                if !copies[rank].is_empty() {
+                    out.push_str(&format!("    also in: {}\n", copies[rank].join(", ")));
+                }
+                if let Some(ref d) = r.definition {
+                    out.push_str(&format!("    defines L{}: {} {}\n", d.line, d.kind, d.name));
+                }
+                if let Some(ref symbol) = r.top_match_symbol {
+                    out.push_str(&format!("    in {symbol}\n"));
+                }
                 if let Some(ref line) = r.top_match {
                     out.push_str(&format!("    > {}\n", line.trim()));
                 }
+                let ranges = r
+                    .top_match
+                    .as_deref()
+                    .map(|m| snippet_match_ranges(m, &terms_lower))
+                    .unwrap_or_default();
+                structured_results.push(serde_json::json!({
+                    "kind": "file",
+                    "path": r.display_path,
+                    "desc": r.desc,
+                    "source": source.trim_matches(|c| c == '[' || c == ']'),
+                    "grepCount": r.grep_count,
+                    "termsMatched": r.terms_matched,
+                    "totalTerms": r.total_terms,
+                    "topMatch": r.top_match,
+                    "topMatchSymbol": r.top_match_symbol,
+                    "topMatchRanges": ranges,
+                    "definition": r.definition.as_ref().map(|d| serde_json::json!({
+                        "line": d.line,
+                        "kind": d.kind,
+                        "name": d.name,
+                        "parent": d.parent,
+                    })),
+                    "copies": copies[rank],
+                }));
             }
 
+            for d in dirs {
+                out.push_str(&format_dir_hit(d, profile));
+                structured_results.push(dir_hit_json(d));
+            }
+
+            *structured = Some(serde_json::json!({
+                "query": raw_query,
+                "semantic": has_semantic,
+                "modules": structured_modules,
+                "results": structured_results,
+            }));
             (out, false)
         }
 
@@ -1540,11 +3534,11 @@ fn handle_tool_call(
                 "blame" => {
                     let repo = match resolve_repo(state, &args) {
                         Ok(r) => r,
-                        Err(e) => return (format!("Error: {e}"), true),
+                        Err(e) => return tool_error(e),
                     };
                     let path = args["path"].as_str().unwrap_or("");
                     if path.is_empty() {
-                        return ("Error: 'path' is required".to_string(), true);
+                        return tool_error("'path' is required");
                     }
                     let start_line = args["start_line"].as_u64().map(|n| n as usize);
                     let end_line = args["end_line"].as_u64().map(|n| n as usize);
@@ -1561,7 +3555,8 @@ fn handle_tool_call(
                                 _ => String::new(),
                             };
                             let mut out = format!("# {path}{range_str}\n\n");
-                            let width = lines.last().map(|l| format!("{}", l.line).len()).unwrap_or(1);
+                            let width =
+                                lines.last().map(|l| format!("{}", l.line).len()).unwrap_or(1);
                             for bl in &lines {
                                 out.push_str(&format!(
                                     "{:>w$}: {} | {} | {} | {}\n",
@@ -1576,17 +3571,17 @@ fn handle_tool_call(
                             out.push_str(&format!("\n{} lines", lines.len()));
                             (out, false)
                         }
-                        Err(e) => (format!("Error: {e}"), true),
+                        Err(e) => tool_error(e),
                     }
                 }
                 "history" => {
                     let repo = match resolve_repo(state, &args) {
                         Ok(r) => r,
-                        Err(e) => return (format!("Error: {e}"), true),
+                        Err(e) => return tool_error(e),
                     };
                     let path = args["path"].as_str().unwrap_or("");
                     if path.is_empty() {
-                        return ("Error: 'path' is required".to_string(), true);
+                        return tool_error("'path' is required");
                     }
                     let limit = args["limit"].as_u64().unwrap_or(10).min(100) as usize;
 
@@ -1595,7 +3590,8 @@ fn handle_tool_call(
                             if commits.is_empty() {
                                 return (format!("No commit history found for '{path}'"), false);
                             }
-                            let mut out = format!("# {path} — {} recent commits\n\n", commits.len());
+                            let mut out =
+                                format!("# {path} — {} recent commits\n\n", commits.len());
                             for c in &commits {
                                 out.push_str(&format!(
                                     "{} | {} | {} | {}\n",
@@ -1616,45 +3612,184 @@ fn handle_tool_call(
                             }
                             (out, false)
                         }
-                        Err(e) => (format!("Error: {e}"), true),
+                        Err(e) => tool_error(e),
                     }
                 }
                 "changed" => {
                     let repo = match resolve_repo(state, &args) {
                         Ok(r) => r,
-                        Err(e) => return (format!("Error: {e}"), true),
+                        Err(e) => return tool_error(e),
                     };
                     let since = args["since"].as_str().unwrap_or("");
-                    if since.is_empty() {
-                        return ("Error: 'since' is required".to_string(), true);
+                    if !since.is_empty() {
+                        return match crate::git::changed_since(&repo.root, since) {
+                            Ok(files) if files.is_empty() => {
+                                (format!("No changes since '{since}'"), false)
+                            }
+                            Ok(files) => {
+                                let mut out =
+                                    format!("Files changed since {since}: {}\n\n", files.len());
+                                push_changed_files(&mut out, &files);
+                                (out, false)
+                            }
+                            Err(e) => tool_error(e),
+                        };
                     }
 
-                    match crate::git::changed_since(&repo.root, since) {
-                        Ok(files) => {
-                            if files.is_empty() {
-                                return (format!("No changes since '{since}'"), false);
+                    // No `since`: everything on this branch since it left the default branch
+                    let base = match crate::git::default_branch(&repo.root, &repo.config.git) {
+                        Ok(b) => b,
+                        Err(e) => return tool_error(format!("{e}, or pass 'since'")),
+                    };
+                    match crate::git::compare(&repo.root, &base, "HEAD", 0) {
+                        Ok(cmp) => {
+                            let mut out = String::new();
+                            if let Some(branch) = crate::git::current_branch(&repo.root) {
+                                if repo.config.git.is_protected(&branch, &base) {
+                                    out.push_str(&format!(
+                                        "Note: HEAD is on protected branch '{branch}'\n"
+                                    ));
+                                }
                             }
-                            let mut out = format!("Files changed since {since}: {}\n\n", files.len());
-                            let mut by_status: BTreeMap<String, Vec<&str>> = BTreeMap::new();
-                            for f in &files {
-                                by_status.entry(f.status.clone()).or_default().push(&f.path);
+                            if cmp.files.is_empty() {
+                                out.push_str(&format!(
+                                    "No changes since merge base {} with '{base}'",
+                                    cmp.merge_base
+                                ));
+                                return (out, false);
                             }
-                            for (status, paths) in &by_status {
-                                out.push_str(&format!("{} ({}):\n", status, paths.len()));
-                                for p in paths {
-                                    out.push_str(&format!("  {p}\n"));
+                            out.push_str(&format!(
+                                "Files changed since merge base {} with {base}: {}\n\n",
+                                cmp.merge_base,
+                                cmp.files.len()
+                            ));
+                            push_changed_files(&mut out, &cmp.files);
+                            (out, false)
+                        }
+                        Err(e) => tool_error(e),
+                    }
+                }
+                "compare" => {
+                    let repo = match resolve_repo(state, &args) {
+                        Ok(r) => r,
+                        Err(e) => return tool_error(e),
+                    };
+                    let (base, head) = match git_range(repo, &args) {
+                        Ok(range) => range,
+                        Err(e) => return tool_error(e),
+                    };
+                    let default = crate::git::default_branch(&repo.root, &repo.config.git);
+                    let limit = args["limit"].as_u64().unwrap_or(20).min(200) as usize;
+
+                    match crate::git::compare(&repo.root, &base, head, limit) {
+                        Ok(cmp) => {
+                            let default_name = default.as_deref().unwrap_or(&base);
+                            let head_name = ref_label(repo, head);
+                            let mut out = format!(
+                                "# {base}...{head_name} (merge base {})\n",
+                                cmp.merge_base
+                            );
+                            for name in [&base, &head_name] {
+                                if repo.config.git.is_protected(name, default_name) {
+                                    out.push_str(&format!("{name}: protected\n"));
                                 }
-                                out.push('\n');
+                            }
+                            out.push_str(&format!(
+                                "{} ahead, {} behind\n",
+                                cmp.ahead_count, cmp.behind_count
+                            ));
+                            if !cmp.ahead.is_empty() {
+                                out.push_str("\nCommits:\n");
+                                for c in &cmp.ahead {
+                                    out.push_str(&format!(
+                                        "  {} | {} | {} | {}\n",
+                                        c.hash, c.author, c.date, c.message
+                                    ));
+                                }
+                                if cmp.ahead_count > cmp.ahead.len() {
+                                    out.push_str(&format!(
+                                        "  ... {} more\n",
+                                        cmp.ahead_count - cmp.ahead.len()
+                                    ));
+                                }
+                            }
+                            if cmp.files.is_empty() {
+                                out.push_str("\nNo file changes");
+                            } else {
+                                out.push_str(&format!("\nFiles changed: {}\n\n", cmp.files.len()));
+                                push_changed_files(&mut out, &cmp.files);
+                            }
+                            (out, false)
+                        }
+                        Err(e) => tool_error(e),
+                    }
+                }
+                "diffstat" => {
+                    let repo = match resolve_repo(state, &args) {
+                        Ok(r) => r,
+                        Err(e) => return tool_error(e),
+                    };
+                    let (base, head) = match git_range(repo, &args) {
+                        Ok(range) => range,
+                        Err(e) => return tool_error(e),
+                    };
+                    let limit = args["limit"].as_u64().unwrap_or(20).min(200) as usize;
+
+                    match crate::git::diffstat(&repo.root, &base, head) {
+                        Ok(stat) => {
+                            let head_name = ref_label(repo, head);
+                            if stat.files.is_empty() {
+                                return (format!("No changes in {base}...{head_name}"), false);
+                            }
+                            let (added, removed) = stat
+                                .files
+                                .iter()
+                                .fold((0, 0), |(a, r), f| (a + f.added, r + f.removed));
+                            let file_count = stat.files.len();
+                            let modules = crate::git::by_module(stat.files, |path| {
+                                get_category_path(path, &repo.config).join(" > ")
+                            });
+                            let mut out = format!(
+                                "# {base}...{head_name} (merge base {})\n\
+                                 {file_count} files changed, +{added} -{removed}, {} modules\n\n",
+                                stat.merge_base,
+                                modules.len()
+                            );
+                            for m in modules.iter().take(limit) {
+                                out.push_str(&format!(
+                                    "{}  +{} -{} ({} files)\n",
+                                    module_label(state, repo, &m.module),
+                                    m.added,
+                                    m.removed,
+                                    m.files.len()
+                                ));
+                                for f in m.files.iter().take(5) {
+                                    let lines = if f.binary {
+                                        "binary".to_string()
+                                    } else {
+                                        format!("+{} -{}", f.added, f.removed)
+                                    };
+                                    out.push_str(&format!("  {} {} {lines}\n", f.status, f.path));
+                                }
+                                if m.files.len() > 5 {
+                                    out.push_str(&format!("  ... {} more\n", m.files.len() - 5));
+                                }
+                            }
+                            if modules.len() > limit {
+                                out.push_str(&format!(
+                                    "\n... {} more modules (raise 'limit')\n",
+                                    modules.len() - limit
+                                ));
                             }
                             (out, false)
                         }
-                        Err(e) => (format!("Error: {e}"), true),
+                        Err(e) => tool_error(e),
                     }
                 }
                 "hotspots" => {
                     let repo = match resolve_repo(state, &args) {
                         Ok(r) => r,
-                        Err(e) => return (format!("Error: {e}"), true),
+                        Err(e) => return tool_error(e),
                     };
                     let limit = args["limit"].as_u64().unwrap_or(20).min(200) as usize;
                     let days = args["days"].as_u64().unwrap_or(90).min(365) as usize;
@@ -1662,9 +3797,13 @@ fn handle_tool_call(
                     match crate::git::hot_files(&repo.root, limit, days) {
                         Ok(files) => {
                             if files.is_empty() {
-                                return (format!("No file changes found in the last {days} days"), false);
+                                return (
+                                    format!("No file changes found in the last {days} days"),
+                                    false,
+                                );
                             }
-                            let mut out = format!("Hot files (last {days} days, top {})\n\n", files.len());
+                            let mut out =
+                                format!("Hot files (last {days} days, top {})\n\n", files.len());
                             let max_commits = files.first().map(|f| f.commits).unwrap_or(1);
                             let width = format!("{}", max_commits).len();
                             for (i, f) in files.iter().enumerate() {
@@ -1678,143 +3817,289 @@ fn handle_tool_call(
                             }
                             (out, false)
                         }
-                        Err(e) => (format!("Error: {e}"), true),
+                        Err(e) => tool_error(e),
                     }
                 }
-                _ => (format!("Error: Unknown cs_git action '{action}'. Use: blame, history, changed, hotspots"), true),
+                _ => tool_error(format!(
+                    "Unknown cs_git action '{action}'. Use: blame, history, changed, compare, diffstat, hotspots"
+                )),
             }
         }
 
         // =================================================================
         // cs_status — merged status + session info
         // =================================================================
-        "cs_status" => {
-            let version = env!("CARGO_PKG_VERSION");
-            let repo_count = state.repos.len();
-            let mut out = format!(
-                "CodeScope v{version} — {repo_count} repositor{} indexed\n\n",
-                if repo_count == 1 { "y" } else { "ies" }
-            );
-
-            let mut total_files = 0usize;
-            for repo in state.repos.values() {
-                let file_count = repo.all_files.len();
-                total_files += file_count;
+        // =================================================================
+        // cs_conventions — formatter/linter/CI conventions
+        // =================================================================
+        "cs_tests" => {
+            let repo = match resolve_repo(state, &args) {
+                Ok(r) => r,
+                Err(e) => return tool_error(e),
+            };
+            let path = args["for"].as_str().unwrap_or("");
+            let limit = args["limit"].as_u64().unwrap_or(20).min(200) as usize;
+            if path.is_empty() {
+                return tool_error("'for' is required");
+            }
+            if !repo.all_files.iter().any(|f| f.rel_path == path) {
+                return tool_error(format!("File not indexed: {path}"));
+            }
 
-                out.push_str(&format!(
-                    "[{}] {}\n  Files: {} | Modules: {} | Import edges: {}\n",
-                    repo.name,
-                    repo.root.display(),
-                    file_count,
-                    repo.manifest.len(),
-                    repo.import_graph.imports.len(),
-                ));
+            let is_test = crate::testmap::is_test_file(path);
+            let links = if is_test {
+                crate::testmap::sources_for_test(repo, path)
+            } else {
+                crate::testmap::tests_for_source(repo, path, true)
+            };
+            let what = if is_test { "Sources covered by" } else { "Tests for" };
+            if links.is_empty() {
+                return (format!("{what} {path}: none found"), false);
+            }
+            let mut out = format!("{what} {path} ({} found)\n\n", links.len());
+            for link in links.iter().take(limit) {
+                out.push_str(&format!("  {}  [{}]\n", link.path, link.reasons()));
+            }
+            if links.len() > limit {
+                out.push_str(&format!("  ... and {} more\n", links.len() - limit));
+            }
+            (out, false)
+        }
 
-                // Language breakdown
-                let mut ext_counts: BTreeMap<String, usize> = BTreeMap::new();
-                for f in &repo.all_files {
-                    if !f.ext.is_empty() {
-                        *ext_counts.entry(f.ext.clone()).or_default() += 1;
-                    }
+        "cs_config_refs" => {
+            let repo = match resolve_repo(state, &args) {
+                Ok(r) => r,
+                Err(e) => return tool_error(e),
+            };
+            let needle = args["name"].as_str().map(str::to_lowercase);
+            let kind = args["kind"].as_str();
+            let prefix = args["prefix"].as_str().unwrap_or("");
+            let limit = args["limit"].as_u64().unwrap_or(50).min(1000) as usize;
+
+            let mut by_name: BTreeMap<(&str, &str), Vec<&crate::config_refs::ConfigRef>> =
+                BTreeMap::new();
+            for r in &repo.config_refs {
+                if needle.as_ref().is_some_and(|n| !r.name.to_lowercase().contains(n.as_str()))
+                    || kind.is_some_and(|k| k != r.kind.name())
+                    || !entries::path_in(&r.path, prefix)
+                {
+                    continue;
                 }
-                let mut sorted_exts: Vec<(String, usize)> = ext_counts.into_iter().collect();
-                sorted_exts.sort_by(|a, b| b.1.cmp(&a.1));
-                sorted_exts.truncate(8);
+                by_name.entry((r.name.as_str(), r.kind.name())).or_default().push(r);
+            }
+            if by_name.is_empty() {
+                return match &needle {
+                    Some(n) => (format!("No references to '{n}' found"), false),
+                    None => {
+                        ("No environment variable or config key references found".into(), false)
+                    }
+                };
+            }
 
-                let lang_str: Vec<String> = sorted_exts
-                    .iter()
-                    .map(|(ext, count)| {
-                        if *count >= 1000 {
-                            format!("{ext}({:.0}K)", *count as f64 / 1000.0)
-                        } else {
-                            format!("{ext}({count})")
-                        }
-                    })
-                    .collect();
-                if !lang_str.is_empty() {
-                    out.push_str(&format!("  Languages: {}\n", lang_str.join(" ")));
+            let total: usize = by_name.values().map(Vec::len).sum();
+            let mut out = format!("{} names, {total} references\n\n", by_name.len());
+            for ((name, kind), refs) in by_name.iter().take(limit) {
+                let defs = refs.iter().filter(|r| r.definition).count();
+                let reads = refs.len() - defs;
+                out.push_str(&format!("{name} ({kind}) — {reads} reads"));
+                if defs > 0 {
+                    out.push_str(&format!(", {defs} definitions"));
                 }
-                out.push_str(&format!("  Last scan: {}ms\n", repo.scan_time_ms));
-
-                #[cfg(feature = "semantic")]
-                {
-                    use std::sync::atomic::Ordering::Relaxed;
-                    let sp = &repo.semantic_progress;
-                    let status = sp.status_label();
-                    match sp.status.load(Relaxed) {
-                        0 => out.push_str("  Semantic: disabled\n"),
-                        1 => {
-                            out.push_str("  Semantic: extracting chunks...\n");
-                        }
-                        2 => {
-                            let done = sp.completed_batches.load(Relaxed);
-                            let total = sp.total_batches.load(Relaxed);
-                            let chunks = sp.total_chunks.load(Relaxed);
-                            let device = sp.device.read().unwrap();
-                            let pct = if total > 0 { done * 100 / total } else { 0 };
-                            out.push_str(&format!(
-                                "  Semantic: embedding on {device} — {done}/{total} batches ({pct}%), {chunks} chunks\n",
-                            ));
-                        }
-                        3 => {
-                            let chunks = sp.total_chunks.load(Relaxed);
-                            let device = sp.device.read().unwrap();
-                            out.push_str(&format!(
-                                "  Semantic: ready ({chunks} chunks, {device})\n",
-                            ));
-                        }
-                        4 => out.push_str("  Semantic: failed\n"),
-                        _ => out.push_str(&format!("  Semantic: {status}\n")),
+                out.push('\n');
+                // Listing every site is the point of a name lookup; the full table only
+                // needs the names.
+                if needle.is_some() {
+                    for r in refs {
+                        let what = if r.definition { "defined" } else { "read" };
+                        out.push_str(&format!("  {what:<8} {}:{}\n", r.path, r.line));
                     }
                 }
-
-                out.push('\n');
             }
-
-            if !state.cross_repo_edges.is_empty() {
+            if by_name.len() > limit {
                 out.push_str(&format!(
-                    "Cross-repo: {} import edges\n\n",
-                    state.cross_repo_edges.len()
+                    "\n... and {} more (use name to narrow)\n",
+                    by_name.len() - limit
                 ));
             }
+            (out, false)
+        }
 
-            out.push_str(&format!("Total: {} files across {} repo(s)", total_files, repo_count));
+        "cs_symbols" => {
+            let repo = match resolve_repo(state, &args) {
+                Ok(r) => r,
+                Err(e) => return tool_error(e),
+            };
+            let needle = args["name"].as_str().map(str::to_lowercase);
+            let kind = args["kind"].as_str();
+            let format = args["format"].as_str();
+            let prefix = args["prefix"].as_str().unwrap_or("");
+            let limit = args["limit"].as_u64().unwrap_or(50).min(1000) as usize;
+
+            let index = &repo.schemas;
+            let symbols: Vec<&crate::schemas::SchemaSymbol> = index
+                .symbols
+                .iter()
+                .filter(|s| {
+                    needle.as_ref().is_none_or(|n| s.name.to_lowercase().contains(n.as_str()))
+                        && kind.is_none_or(|k| k == s.kind)
+                        && format.is_none_or(|f| f == s.format.name())
+                        && entries::path_in(&s.path, prefix)
+                })
+                .collect();
+            if symbols.is_empty() {
+                return match &needle {
+                    Some(n) => (format!("No schema definitions matching '{n}'"), false),
+                    None if index.symbols.is_empty() => {
+                        ("No .proto, OpenAPI, or GraphQL schema definitions found".into(), false)
+                    }
+                    None => ("No schema definitions match the filters".into(), false),
+                };
+            }
 
-            // Append session info (was cs_session_info)
-            if let Some(ref s) = session {
-                let elapsed = s.started_at.elapsed();
-                let mins = elapsed.as_secs() / 60;
-                let secs = elapsed.as_secs() % 60;
+            let mut out = format!("{} definitions\n", symbols.len());
+            let mut current_path = "";
+            for s in symbols.iter().take(limit) {
+                if s.path != current_path {
+                    current_path = &s.path;
+                    out.push_str(&format!("\n{} ({})\n", s.path, s.format.name()));
+                }
+                let refs: Vec<&crate::schemas::SchemaRef> = index.refs_to(&s.name).collect();
+                let impls = refs.iter().filter(|r| r.implements).count();
+                let parent = s.parent.as_deref().map(|p| format!(" [{p}]")).unwrap_or_default();
                 out.push_str(&format!(
-                    "\n\nSession: {}m {}s, {} files read, ~{} tokens served",
-                    mins,
-                    secs,
-                    s.files_read.len(),
-                    s.total_tokens_served
+                    "  {} {}{parent} (line {}) — {impls} implementations, {} uses\n",
+                    s.kind,
+                    s.name,
+                    s.line,
+                    refs.len() - impls
                 ));
-                if !s.files_read.is_empty() {
-                    out.push_str("\nFiles read:\n");
-                    let mut sorted: Vec<(&String, &std::time::Instant)> =
-                        s.files_read.iter().collect();
-                    sorted.sort_by_key(|(_, t)| *t);
-                    for (path, _) in sorted {
-                        out.push_str(&format!("  {path}\n"));
+                // Like cs_config_refs, sites are only listed for a name lookup
+                if needle.is_some() {
+                    for r in refs.iter().take(limit) {
+                        let what = if r.implements { "implements" } else { "uses" };
+                        out.push_str(&format!("    {what:<10} {}:{}\n", r.path, r.line));
+                    }
+                    if refs.len() > limit {
+                        out.push_str(&format!("    ... and {} more\n", refs.len() - limit));
                     }
                 }
             }
+            if symbols.len() > limit {
+                out.push_str(&format!(
+                    "\n... and {} more (use name to narrow)\n",
+                    symbols.len() - limit
+                ));
+            }
+            (out, false)
+        }
+
+        "cs_conventions" => {
+            let repo = match resolve_repo(state, &args) {
+                Ok(r) => r,
+                Err(e) => return tool_error(e),
+            };
+            let report = crate::conventions::mine_conventions(repo);
+            (crate::conventions::format_conventions(&repo.name, &report), false)
+        }
+
+        "cs_duplicates" => {
+            let repo = match resolve_repo(state, &args) {
+                Ok(r) => r,
+                Err(e) => return tool_error(e),
+            };
+            let path = args["path"].as_str().unwrap_or("");
+            let min_lines = args["min_lines"]
+                .as_u64()
+                .map_or(crate::duplicates::DEFAULT_MIN_LINES, |n| n.min(u32::MAX as u64) as u32);
+            let limit = args["limit"].as_u64().unwrap_or(20).clamp(1, 500) as usize;
+            let report = crate::duplicates::find(repo, path, min_lines);
+            (crate::duplicates::format_report(&repo.name, &report, limit), false)
+        }
+
+        "cs_owners" => {
+            let repo = match resolve_repo(state, &args) {
+                Ok(r) => r,
+                Err(e) => return tool_error(e),
+            };
+            let codeowners = &repo.config.codeowners;
+            let Some(source) = &codeowners.source else {
+                return (format!("No CODEOWNERS file in {}", repo.name), false);
+            };
+            let limit = args["limit"].as_u64().unwrap_or(50).clamp(1, 500) as usize;
+            if let Some(owner) = args["owner"].as_str().filter(|o| !o.is_empty()) {
+                let files = crate::owners::files_owned_by(repo, owner);
+                if files.is_empty() {
+                    return (format!("No files owned by {owner} ({source})"), false);
+                }
+                let mut out = format!("{} files owned by {owner} ({source}):\n", files.len());
+                for f in files.iter().take(limit) {
+                    out.push_str(&format!("  {f}\n"));
+                }
+                if files.len() > limit {
+                    out.push_str(&format!("  ... and {} more\n", files.len() - limit));
+                }
+                return (out, false);
+            }
 
+            let path = args["path"].as_str().unwrap_or("");
+            let report = crate::owners::owners_report(repo, path);
+            if report.files == 0 {
+                return tool_error(format!("No indexed files under '{path}'"));
+            }
+            let target = if report.path.is_empty() { repo.name.as_str() } else { &report.path };
+            let mut out = format!("Owners of {target} ({source}, {} files):\n", report.files);
+            for o in report.owners.iter().take(limit) {
+                out.push_str(&format!("  {} — {} files\n", o.owner, o.files));
+            }
+            if report.owners.len() > limit {
+                out.push_str(&format!("  ... and {} more owners\n", report.owners.len() - limit));
+            }
+            if report.unowned > 0 {
+                out.push_str(&format!("  (unowned) — {} files\n", report.unowned));
+            }
+            for rule in &report.rules {
+                let section = rule.section.as_ref().map(|s| format!(" [{s}]")).unwrap_or_default();
+                let owners = if rule.owners.is_empty() {
+                    "(no owners)".to_string()
+                } else {
+                    rule.owners.join(" ")
+                };
+                out.push_str(&format!(
+                    "Rule{section}: {} {owners} (line {})\n",
+                    rule.pattern, rule.line
+                ));
+            }
             (out, false)
         }
 
+        "cs_status" => {
+            let detail = match args["detail"].as_str() {
+                Some(spec) => match crate::status::Detail::parse(spec) {
+                    Ok(d) => d,
+                    Err(e) => return tool_error(e),
+                },
+                None => crate::status::Detail::ALL,
+            };
+            let report = crate::status::collect(state, session.as_ref(), detail);
+            if args["format"].as_str() == Some("json") {
+                let json = serde_json::to_value(&report).unwrap_or_default();
+                let text = serde_json::to_string_pretty(&json).unwrap_or_default();
+                *structured = Some(json);
+                return (text, false);
+            }
+            (crate::status::format_status(&report), false)
+        }
+
         _ => (format!("Unknown tool: {name}"), true),
     }
 }
 
 // ---------------------------------------------------------------------------
-// Mutating tool handlers (need write lock)
+// Mutating tool handlers (scan off-lock, then publish a new generation)
 // ---------------------------------------------------------------------------
 
-fn handle_rescan(state: &mut ServerState, args: &serde_json::Value) -> (String, bool) {
+fn handle_rescan(shared: &SharedState, args: &serde_json::Value) -> (String, bool) {
+    let state = shared.snapshot();
     let target_repo = args.get("repo").and_then(|v| v.as_str());
     let tok = state.tokenizer.clone();
 
@@ -1822,49 +4107,113 @@ fn handle_rescan(state: &mut ServerState, args: &serde_json::Value) -> (String,
         Some(name) => {
             if state.repos.contains_key(name) {
                 vec![name.to_string()]
+            } else if let Some(pending) = state.pending.get(name) {
+                if !pending.is_queued() {
+                    pending.request();
+                    return (
+                        format!("Repo '{name}' is lazy and not indexed yet; its first scan has started."),
+                        false,
+                    );
+                }
+                return tool_error(format!(
+                    "Repo '{name}' is still being indexed for the first time"
+                ));
             } else {
-                return (format!("Error: Unknown repo '{name}'"), true);
+                return tool_error(format!("Unknown repo '{name}'"));
             }
         }
         None => state.repos.keys().cloned().collect(),
     };
 
-    let mut results = Vec::new();
+    // "default" clears the profile; without the argument each repo keeps its own
+    let requested_profile = args.get("profile").and_then(|v| v.as_str());
+    let profile_of = |repo: &RepoState| match requested_profile {
+        Some("default") => Ok(None),
+        Some(p) if crate::config_profiles(&repo.root).iter().any(|n| n == p) => {
+            Ok(Some(p.to_string()))
+        }
+        Some(p) => Err(format!("Repo '{}' has no [profile.{p}] in .codescope.toml", repo.name)),
+        None => Ok(repo.config.profile.clone()),
+    };
+    let mut profiles = Vec::new();
     for name in &repos_to_scan {
-        let root = state.repos[name].root.clone();
-        let new_state = crate::scan_repo(name, &root, &tok);
+        match profile_of(&state.repos[name]) {
+            Ok(p) => profiles.push(p),
+            Err(e) => return tool_error(e),
+        }
+    }
+
+    // Requests keep reading the current generation while the scans run
+    let incremental = args.get("incremental").and_then(|v| v.as_bool()).unwrap_or(false);
+    let mut results = Vec::new();
+    let mut scanned = Vec::new();
+    for (name, profile) in repos_to_scan.iter().zip(&profiles) {
+        let repo = &state.repos[name];
+        let mut full_reason = String::new();
+        if incremental && *profile == repo.config.profile {
+            let start = std::time::Instant::now();
+            match crate::watch::rescan_changed(repo) {
+                crate::watch::Incremental::Updated(new_state, (updated, removed, unchanged)) => {
+                    results.push(format!(
+                        "[{name}] Incrementally rescanned: {updated} updated, {removed} removed, \
+                         {unchanged} unchanged of {} files ({}ms)",
+                        new_state.all_files.len(),
+                        start.elapsed().as_millis(),
+                    ));
+                    scanned.push(*new_state);
+                    continue;
+                }
+                crate::watch::Incremental::Full(reason) => {
+                    full_reason = format!(" [full: {reason}]");
+                }
+            }
+        }
+        let root = repo.root.clone();
+        let progress = crate::types::ScanProgress::new();
+        let mut new_state =
+            crate::scan_repo_with_progress(name, &root, &tok, false, profile.as_deref(), &progress);
+        crate::renames::RenameMap::carry_over(repo, &mut new_state);
+        let label = profile.as_ref().map(|p| format!(" (profile {p})")).unwrap_or_default();
         results.push(format!(
-            "[{name}] Rescanned: {} files, {} modules, {} import edges ({}ms)",
+            "[{name}] Rescanned{label}: {} files, {} modules, {} import edges ({}ms){full_reason}",
             new_state.all_files.len(),
             new_state.manifest.len(),
             new_state.import_graph.imports.len(),
             new_state.scan_time_ms,
         ));
-        state.repos.insert(name.clone(), new_state);
+        scanned.push(new_state);
     }
+    drop(state);
 
-    // Rebuild cross-repo edges
-    state.cross_repo_edges = crate::scan::resolve_cross_repo_imports(&state.repos);
+    shared.update(|state| {
+        for new_state in scanned {
+            state.repos.insert(new_state.name.clone(), Arc::new(new_state));
+        }
+        // Rebuild cross-repo edges
+        state.cross_repo_edges = crate::scan::resolve_cross_repo_imports(&state.repos);
+    });
+    crate::memory::enforce_memory_limits(&shared.snapshot());
 
     (results.join("\n"), false)
 }
 
-fn handle_add_repo(state: &mut ServerState, args: &serde_json::Value) -> (String, bool) {
+fn handle_add_repo(shared: &SharedState, args: &serde_json::Value) -> (String, bool) {
+    let state = shared.snapshot();
     let name = match args["name"].as_str() {
         Some(n) => n.to_string(),
-        None => return ("Error: 'name' is required".to_string(), true),
+        None => return tool_error("'name' is required"),
     };
     let root_str = match args["root"].as_str() {
         Some(r) => r,
-        None => return ("Error: 'root' is required".to_string(), true),
+        None => return tool_error("'root' is required"),
     };
     let root = match std::path::PathBuf::from(root_str).canonicalize() {
         Ok(r) => r,
-        Err(e) => return (format!("Error: Path not found: {e}"), true),
+        Err(e) => return tool_error(format!("Path not found: {e}")),
     };
 
-    if state.repos.contains_key(&name) {
-        return (format!("Error: Repo '{name}' already exists. Use cs_rescan to update it."), true);
+    if state.repos.contains_key(&name) || state.pending.contains_key(&name) {
+        return tool_error(format!("Repo '{name}' already exists. Use cs_rescan to update it."));
     }
 
     let tok = state.tokenizer.clone();
@@ -1882,6 +4231,7 @@ fn handle_add_repo(state: &mut ServerState, args: &serde_json::Value) -> (String
     #[cfg(feature = "semantic")]
     let semantic_summary = if state.semantic_enabled {
         let files = new_state.all_files.clone();
+        let noise = new_state.config.noise.clone();
         let sem_handle = std::sync::Arc::clone(&new_state.semantic_index);
         let progress = std::sync::Arc::clone(&new_state.semantic_progress);
         let repo_root = root.clone();
@@ -1892,6 +4242,7 @@ fn handle_add_repo(state: &mut ServerState, args: &serde_json::Value) -> (String
             let sem_start = std::time::Instant::now();
             if let Some(idx) = crate::semantic::build_semantic_index(
                 &files,
+                &noise,
                 model.as_deref(),
                 &progress,
                 &repo_root,
@@ -1912,10 +4263,23 @@ fn handle_add_repo(state: &mut ServerState, args: &serde_json::Value) -> (String
     #[cfg(not(feature = "semantic"))]
     let semantic_summary = "";
 
-    state.repos.insert(name.clone(), new_state);
+    let added = shared.update(|state| {
+        // Another call may have added the same name while this one was scanning
+        if state.repos.contains_key(&name) || state.pending.contains_key(&name) {
+            return false;
+        }
+        state.repos.insert(name.clone(), Arc::new(new_state));
+        // Rebuild cross-repo edges
+        state.cross_repo_edges = crate::scan::resolve_cross_repo_imports(&state.repos);
+        true
+    });
+    if !added {
+        return tool_error(format!("Repo '{name}' already exists. Use cs_rescan to update it."));
+    }
+    crate::memory::enforce_memory_limits(&shared.snapshot());
 
     // Persist to global ~/.codescope/repos.toml so the repo survives server restarts
-    let persist_note = match crate::merge_global_repos_toml(&name, &root) {
+    let persist_note = match crate::merge_global_repos_toml(&name, &root, None) {
         Ok(()) => " Saved to ~/.codescope/repos.toml.",
         Err(e) => {
             tracing::warn!(repo = name.as_str(), error = %e, "Failed to persist repo to global config");
@@ -1923,9 +4287,6 @@ fn handle_add_repo(state: &mut ServerState, args: &serde_json::Value) -> (String
         }
     };
 
-    // Rebuild cross-repo edges
-    state.cross_repo_edges = crate::scan::resolve_cross_repo_imports(&state.repos);
-
     (format!("{summary}{semantic_summary}{persist_note}"), false)
 }
 
@@ -1950,104 +4311,155 @@ pub(crate) fn negotiate_version(client_version: &str) -> &'static str {
 // Shared JSON-RPC dispatch (used by both stdio and HTTP transports)
 // ---------------------------------------------------------------------------
 
+/// Instructions returned from `initialize`.
+const SERVER_INSTRUCTIONS: &str = "CodeScope — search, browse, and read source code. Start with cs_search for discovery (uses semantic search when available, keyword matching as fallback). Use cs_grep for exact pattern matching. Use cs_read to read files. Use cs_imports to trace dependencies. Use cs_tests to find the tests covering a file. Use cs_config_refs to find what reads an environment variable or config key. Use cs_symbols to find the code implementing or calling a proto, OpenAPI, or GraphQL definition. Use cs_git for history analysis. Use cs_conventions before writing code to match formatter, lint, and CI rules. Use cs_owners to find who owns a file or directory. Use cs_duplicates to find copies of a file before changing only one of them. A result cut at max_result_tokens ends with a cursor; pass it as cursor to the same tool for the rest. Module READMEs and docs are available as codescope://{repo}/module/{name}/docs resources, and parsed Cargo.toml, package.json, and go.mod files as codescope://{repo}/manifests.";
+
 /// Process a single JSON-RPC request and return the response.
 ///
 /// Returns `None` for notifications (no `id` field).
 /// The `initialized` flag is checked by the caller — this function assumes
 /// the request has already passed init enforcement.
 pub(crate) fn dispatch_jsonrpc(
-    state: &Arc<RwLock<ServerState>>,
+    state: &SharedState,
     msg: &serde_json::Value,
     session: &mut Option<SessionState>,
 ) -> Option<serde_json::Value> {
-    let method = msg["method"].as_str().unwrap_or("");
-    let id = msg.get("id").cloned();
+    let request: Request = match serde_json::from_value(msg.clone()) {
+        Ok(r) => r,
+        Err(e) => {
+            // Only a message that looks like a request gets an answer
+            let id = msg.get("id").cloned().and_then(|id| serde_json::from_value(id).ok());
+            let error =
+                ErrorObject::new(protocol::INVALID_REQUEST, format!("Invalid request: {e}"));
+            return id.map(|id| Response::error(Some(id), error).to_value());
+        }
+    };
 
-    // Notifications have no id and produce no response
-    if id.is_none() || method.starts_with("notifications/") {
+    // Notifications produce no response
+    if request.is_notification() {
         return None;
     }
+    let id = request.id.clone();
+    let response = match dispatch_request(state, &request, session) {
+        Ok(result) => Response::result(id, result),
+        Err(error) => Response::error(id, error),
+    };
+    Some(response.to_value())
+}
 
-    let response = match method {
+/// Result of one request, serialized, or the error to return instead.
+fn dispatch_request(
+    state: &SharedState,
+    request: &Request,
+    session: &mut Option<SessionState>,
+) -> Result<serde_json::Value, ErrorObject> {
+    let invalid = |e: String| ErrorObject::new(protocol::INVALID_PARAMS, e);
+    let result = match request.method.as_str() {
         "initialize" => {
-            let client_version = msg["params"]["protocolVersion"].as_str().unwrap_or("");
-            let negotiated = negotiate_version(client_version);
-            serde_json::json!({
-                "jsonrpc": "2.0",
-                "id": id,
-                "result": {
-                    "protocolVersion": negotiated,
-                    "capabilities": {
-                        "tools": { "listChanged": true }
-                    },
-                    "serverInfo": {
-                        "name": "codescope",
-                        "version": env!("CARGO_PKG_VERSION")
-                    },
-                    "instructions": "CodeScope — search, browse, and read source code. Start with cs_search for discovery (uses semantic search when available, keyword matching as fallback). Use cs_grep for exact pattern matching. Use cs_read to read files. Use cs_imports to trace dependencies. Use cs_git for history analysis."
-                }
+            let params: InitializeParams = request.params().map_err(invalid)?;
+            if let Some(s) = session.as_mut() {
+                s.output_profile = crate::format::initialize_profile(&params);
+            }
+            to_result(InitializeResult {
+                protocol_version: negotiate_version(&params.protocol_version).to_string(),
+                capabilities: ServerCapabilities {
+                    tools: Some(ToolsCapability { list_changed: true }),
+                    resources: Some(ResourcesCapability::default()),
+                    prompts: Some(PromptsCapability::default()),
+                },
+                server_info: Implementation {
+                    name: "codescope".to_string(),
+                    version: env!("CARGO_PKG_VERSION").to_string(),
+                },
+                instructions: Some(SERVER_INSTRUCTIONS.to_string()),
             })
         }
         "tools/list" => {
-            serde_json::json!({
-                "jsonrpc": "2.0",
-                "id": id,
-                "result": {
-                    "tools": tool_definitions()
-                }
-            })
+            let tools = listed_tools().as_array().cloned().unwrap_or_default();
+            to_result(ListToolsResult { tools })
         }
-        "tools/call" => {
-            let tool_name = msg["params"]["name"].as_str().unwrap_or("");
-            let arguments =
-                msg["params"].get("arguments").cloned().unwrap_or(serde_json::json!({}));
-
-            // Mutating tools need write lock
-            let (text, is_error) = match tool_name {
-                "cs_rescan" | "cs_add_repo" => {
-                    let mut s = state.write().unwrap();
-                    match tool_name {
-                        "cs_rescan" => handle_rescan(&mut s, &arguments),
-                        "cs_add_repo" => handle_add_repo(&mut s, &arguments),
-                        _ => unreachable!(),
-                    }
-                }
-                _ => {
-                    let s = state.read().unwrap();
-                    handle_tool_call(&s, tool_name, &arguments, session)
-                }
-            };
-
-            // Never set isError: true — it triggers Claude Code's sibling tool call
-            // cascade failure (all parallel calls get killed). Instead, prefix the
-            // error message so the LLM can still detect and recover from failures.
-            let content_text = if is_error { format!("\u{26a0} Error: {text}") } else { text };
-            serde_json::json!({
-                "jsonrpc": "2.0",
-                "id": id,
-                "result": {
-                    "content": [{ "type": "text", "text": content_text }],
-                    "isError": false
-                }
-            })
+        "tools/call" => to_result(call_tool(state, request.params().map_err(invalid)?, session)),
+        "resources/list" => to_result(resource_list(&state.snapshot())),
+        "resources/templates/list" => to_result(resource_templates()),
+        "resources/read" => {
+            let params: ReadResourceParams = request.params().map_err(invalid)?;
+            let result = read_resource(&state.snapshot(), &params.uri).map_err(|e| {
+                ErrorObject::new(protocol::RESOURCE_NOT_FOUND, e)
+                    .with_data(serde_json::json!({ "uri": params.uri }))
+            })?;
+            to_result(result)
         }
-        "ping" => {
-            serde_json::json!({
-                "jsonrpc": "2.0",
-                "id": id,
-                "result": {}
+        "prompts/list" => {
+            let snapshot = state.snapshot();
+            let prompts = available_prompts(&snapshot);
+            to_result(ListPromptsResult {
+                prompts: prompts.iter().map(|(name, (_, p))| p.to_prompt(name)).collect(),
             })
         }
+        "prompts/get" => {
+            let params: GetPromptParams = request.params().map_err(invalid)?;
+            to_result(get_prompt(&state.snapshot(), params).map_err(invalid)?)
+        }
+        "ping" => to_result(EmptyResult {}),
+        _ => return Err(ErrorObject::new(protocol::METHOD_NOT_FOUND, "Method not found")),
+    };
+    Ok(result)
+}
+
+fn to_result(result: impl serde::Serialize) -> serde_json::Value {
+    serde_json::to_value(result).unwrap_or(serde_json::Value::Null)
+}
+
+/// Run a `tools/call`.
+fn call_tool(
+    state: &SharedState,
+    params: CallToolParams,
+    session: &mut Option<SessionState>,
+) -> CallToolResult {
+    let CallToolParams { name: tool_name, arguments } = params;
+    let start = std::time::Instant::now();
+
+    // Mutating tools publish a new generation; everything else reads one snapshot
+    let (text, is_error, structured, generation) = match tool_name.as_str() {
+        "cs_rescan" | "cs_add_repo" => {
+            let (text, is_error) = match tool_name.as_str() {
+                "cs_rescan" => handle_rescan(state, &arguments),
+                "cs_add_repo" => handle_add_repo(state, &arguments),
+                _ => unreachable!(),
+            };
+            (text, is_error, None, state.snapshot().generation)
+        }
         _ => {
-            serde_json::json!({
-                "jsonrpc": "2.0",
-                "id": id,
-                "error": { "code": -32601, "message": "Method not found" }
-            })
+            let s = state.snapshot();
+            let (text, is_error, structured) =
+                handle_tool_call_structured(&s, &tool_name, &arguments, session);
+            (text, is_error, structured, s.generation)
         }
     };
+    if let Some(log) = &state.snapshot().access_log {
+        log.record(
+            "tool",
+            &tool_name,
+            start.elapsed().as_millis() as u64,
+            crate::access_log::tool_result_count(&text, structured.as_ref()),
+            is_error,
+            arguments["query"].as_str(),
+        );
+    }
 
-    Some(response)
+    // Never set isError: true — it triggers Claude Code's sibling tool call
+    // cascade failure (all parallel calls get killed). Instead, prefix the
+    // error message so the LLM can still detect and recover from failures.
+    let text =
+        if is_error { format!("\u{26a0} {}: {text}", crate::tr!("error-prefix")) } else { text };
+    let mut meta = serde_json::Map::new();
+    meta.insert(GENERATION_META.to_string(), serde_json::json!(generation));
+    CallToolResult {
+        content: vec![ContentBlock::Text { text }],
+        is_error: false,
+        structured_content: structured.filter(|_| !is_error),
+        meta,
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -2055,7 +4467,7 @@ pub(crate) fn dispatch_jsonrpc(
 // ---------------------------------------------------------------------------
 
 /// Run the MCP stdio server loop, reading JSON-RPC from stdin and writing responses to stdout.
-pub fn run_mcp(state: Arc<RwLock<ServerState>>) {
+pub fn run_mcp(state: Arc<SharedState>) {
     let stdin = io::stdin();
     let stdout = io::stdout();
     let reader = stdin.lock();
@@ -2063,7 +4475,7 @@ pub fn run_mcp(state: Arc<RwLock<ServerState>>) {
     let mut initialized = false;
 
     {
-        let s = state.read().unwrap();
+        let s = state.snapshot();
         let total_files: usize = s.repos.values().map(|r| r.all_files.len()).sum();
         let total_modules: usize = s.repos.values().map(|r| r.manifest.len()).sum();
         let repo_names: Vec<&str> = s.repos.keys().map(|k| k.as_str()).collect();
@@ -2088,13 +4500,10 @@ pub fn run_mcp(state: Arc<RwLock<ServerState>>) {
         let msg: serde_json::Value = match serde_json::from_str(&line) {
             Ok(v) => v,
             Err(_) => {
-                let err = serde_json::json!({
-                    "jsonrpc": "2.0",
-                    "id": null,
-                    "error": { "code": -32700, "message": "Parse error" }
-                });
+                let err =
+                    Response::error(None, ErrorObject::new(protocol::PARSE_ERROR, "Parse error"));
                 let mut out = stdout.lock();
-                let _ = writeln!(out, "{}", err);
+                let _ = writeln!(out, "{}", serde_json::to_string(&err).unwrap());
                 let _ = out.flush();
                 continue;
             }
@@ -2109,15 +4518,15 @@ pub fn run_mcp(state: Arc<RwLock<ServerState>>) {
 
         // Init ordering enforcement: reject non-init requests before initialize
         if !initialized && method != "initialize" && method != "ping" {
-            if let Some(id) = msg.get("id").cloned() {
-                let err = serde_json::json!({
-                    "jsonrpc": "2.0",
-                    "id": id,
-                    "error": {
-                        "code": -32002,
-                        "message": "Server not initialized. Send 'initialize' first."
-                    }
-                });
+            if let Some(id) = msg.get("id").cloned().and_then(|id| serde_json::from_value(id).ok())
+            {
+                let err = Response::error(
+                    Some(id),
+                    ErrorObject::new(
+                        protocol::NOT_INITIALIZED,
+                        "Server not initialized. Send 'initialize' first.",
+                    ),
+                );
                 let mut out = stdout.lock();
                 let _ = writeln!(out, "{}", serde_json::to_string(&err).unwrap());
                 let _ = out.flush();
@@ -2137,3 +4546,60 @@ pub fn run_mcp(state: Arc<RwLock<ServerState>>) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn listed_tools_are_versioned_and_legacy_names_shimmed() {
+        let tools = listed_tools();
+        for def in tools.as_array().unwrap() {
+            let name = def["name"].as_str().unwrap();
+            assert!(
+                TOOL_SCHEMA_VERSIONS.iter().any(|(n, _)| *n == name),
+                "{name} has no entry in TOOL_SCHEMA_VERSIONS"
+            );
+            assert!(def["_meta"][SCHEMA_VERSION_META].is_u64(), "{name} lacks a schema version");
+        }
+        let modules = tools.as_array().unwrap().iter().find(|t| t["name"] == "cs_modules").unwrap();
+        assert!(modules["_meta"][REPLACES_META]
+            .as_array()
+            .unwrap()
+            .contains(&"cs_get_deps".into()));
+
+        for legacy in LEGACY_TOOLS {
+            assert!(
+                tools.as_array().unwrap().iter().any(|t| t["name"] == legacy.replacement),
+                "{} forwards to unlisted tool {}",
+                legacy.name,
+                legacy.replacement
+            );
+        }
+        let (name, args) =
+            translate_legacy_tool("cs_impact", &serde_json::json!({ "path": "a.rs" }));
+        assert_eq!((name, args["transitive"].as_bool()), ("cs_imports", Some(true)));
+        assert_eq!(
+            legacy_tool("cs_list_modules").unwrap().hint(),
+            "cs_modules with action=\"list\""
+        );
+
+        let mut def = serde_json::json!({ "name": "cs_old", "description": "Does things." });
+        annotate_tool(&mut def, 3, Some("cs_new"));
+        assert_eq!(def["description"], "DEPRECATED — use cs_new instead. Does things.");
+        assert_eq!(def["_meta"][DEPRECATED_META]["replacement"], "cs_new");
+        assert_eq!(def["_meta"][SCHEMA_VERSION_META], 3);
+    }
+
+    #[test]
+    fn module_docs_uri_round_trips() {
+        let uri = module_docs_uri("my repo", "crates > 100% core");
+        assert_eq!(uri, "codescope://my%20repo/module/crates/100%25%20core/docs");
+        assert_eq!(
+            parse_module_docs_uri(&uri),
+            Some(("my repo".to_string(), "crates > 100% core".to_string()))
+        );
+        assert_eq!(parse_module_docs_uri("codescope://repo/module//docs"), None);
+        assert_eq!(parse_module_docs_uri("file:///etc/passwd"), None);
+    }
+}
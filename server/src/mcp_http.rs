@@ -16,6 +16,7 @@ use std::time::Instant;
 use uuid::Uuid;
 
 use crate::mcp::{dispatch_jsonrpc, negotiate_version};
+use crate::protocol::{self, ErrorObject, InitializeParams, Response as RpcResponse};
 use crate::types::*;
 
 const SESSION_HEADER: &str = "mcp-session-id";
@@ -38,12 +39,9 @@ pub async fn handle_mcp_post(
     let parsed: serde_json::Value = match serde_json::from_str(&body) {
         Ok(v) => v,
         Err(_) => {
-            let err = serde_json::json!({
-                "jsonrpc": "2.0",
-                "id": null,
-                "error": { "code": -32700, "message": "Parse error" }
-            });
-            return Ok(json_response(StatusCode::BAD_REQUEST, &err));
+            let err =
+                RpcResponse::error(None, ErrorObject::new(protocol::PARSE_ERROR, "Parse error"));
+            return Ok(json_response(StatusCode::BAD_REQUEST, &err.to_value()));
         }
     };
 
@@ -99,13 +97,16 @@ pub async fn handle_mcp_post(
         let method = req["method"].as_str().unwrap_or("");
 
         if method == "initialize" {
-            // Version negotiation
-            let client_version = req["params"]["protocolVersion"].as_str().unwrap_or("");
-            let negotiated = negotiate_version(client_version);
+            // Version negotiation. Malformed params still get a session; dispatch reports
+            // the error.
+            let params: InitializeParams =
+                serde_json::from_value(req["params"].clone()).unwrap_or_default();
+            let negotiated = negotiate_version(&params.protocol_version);
 
             // Create session
             let sid = Uuid::new_v4().to_string();
-            let session = McpSession::new(negotiated.to_string());
+            let mut session = McpSession::new(negotiated.to_string());
+            session.session_state.output_profile = crate::format::initialize_profile(&params);
             ctx.sessions.insert(sid.clone(), session);
             new_session_id = Some(sid);
 
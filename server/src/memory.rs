@@ -0,0 +1,392 @@
+//! Approximate memory accounting for per-repo index components, and enforcement of the
+//! `max_memory_mb` limit from `.codescope.toml` by evicting caches.
+//!
+//! The scanned index is measured once per scan and watcher update
+//! ([`RepoState::index_memory`]); only the stub cache and semantic vectors are counted on
+//! demand. The limit is checked after each scan, rescan, watcher update, and semantic
+//! build, never on the read path, so the stub cache that reads fill can run over it until
+//! the next check.
+//!
+//! Estimates count inline struct sizes plus owned heap buffers. Allocator overhead and
+//! hash table slack are ignored, so real RSS is typically somewhat higher.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::mem::size_of;
+use tracing::warn;
+
+use crate::config_refs::ConfigRef;
+use crate::entries::EntryPoint;
+use crate::licenses::{DirLicense, FileLicense};
+use crate::schemas::{SchemaRef, SchemaSymbol};
+use crate::symbols::SymbolEntry;
+use crate::types::*;
+
+// ---------------------------------------------------------------------------
+// Usage accounting
+// ---------------------------------------------------------------------------
+
+/// Approximate bytes held by each index component of a repo.
+#[derive(Clone, Copy, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryUsage {
+    /// Scanned file list, manifest, module docs, and dependency entries.
+    pub all_files: usize,
+    /// Fuzzy search entries for files and modules.
+    pub search_index: usize,
+    pub import_graph: usize,
+    pub term_doc_freq: usize,
+    pub stub_cache: usize,
+    /// In-RAM embedding vectors and chunk metadata (0 without the `semantic` feature).
+    pub semantic_vectors: usize,
+}
+
+impl MemoryUsage {
+    pub fn total(&self) -> usize {
+        self.all_files
+            + self.search_index
+            + self.import_graph
+            + self.term_doc_freq
+            + self.stub_cache
+            + self.semantic_vectors
+    }
+}
+
+impl std::ops::AddAssign for MemoryUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.all_files += other.all_files;
+        self.search_index += other.search_index;
+        self.import_graph += other.import_graph;
+        self.term_doc_freq += other.term_doc_freq;
+        self.stub_cache += other.stub_cache;
+        self.semantic_vectors += other.semantic_vectors;
+    }
+}
+
+/// Estimate memory held by every index component of a repo: the measured index plus the
+/// stub cache and semantic vectors as they are now.
+pub fn repo_memory_usage(repo: &RepoState) -> MemoryUsage {
+    MemoryUsage {
+        stub_cache: stub_cache_bytes(&repo.stub_cache),
+        semantic_vectors: semantic_bytes(repo),
+        ..repo.index_memory
+    }
+}
+
+/// Measure the scanned index of `repo` into [`RepoState::index_memory`]. Walks every
+/// file, search entry, and import edge; call it where those change, not per request.
+pub fn measure_index(repo: &mut RepoState) {
+    repo.index_memory = MemoryUsage {
+        all_files: files_bytes(repo),
+        search_index: search_index_bytes(repo),
+        import_graph: edge_map_bytes(&repo.import_graph.imports)
+            + edge_map_bytes(&repo.import_graph.imported_by)
+            + edge_meta_bytes(&repo.import_graph.edges),
+        term_doc_freq: repo.term_doc_freq.heap_bytes(),
+        stub_cache: 0,
+        semantic_vectors: 0,
+    };
+}
+
+/// Sum of [`repo_memory_usage`] across all indexed repos.
+pub fn total_memory_usage(state: &ServerState) -> MemoryUsage {
+    let mut total = MemoryUsage::default();
+    for repo in state.repos.values() {
+        total += repo_memory_usage(repo);
+    }
+    total
+}
+
+fn files_bytes(repo: &RepoState) -> usize {
+    let scanned: usize = repo
+        .all_files
+        .iter()
+        .map(|f| {
+            size_of::<ScannedFile>()
+                + f.rel_path.capacity()
+                + f.abs_path.as_os_str().len()
+                + f.desc.capacity()
+                + f.ext.capacity()
+        })
+        .sum();
+    let manifest: usize = repo
+        .manifest
+        .iter()
+        .map(|(cat, entries)| {
+            size_of::<String>()
+                + cat.capacity()
+                + entries
+                    .iter()
+                    .map(|e| size_of::<FileEntry>() + e.path.capacity() + e.desc.capacity())
+                    .sum::<usize>()
+        })
+        .sum();
+    let deps: usize = repo
+        .deps
+        .iter()
+        .map(|(name, d)| {
+            size_of::<DepEntry>()
+                + name.capacity()
+                + d.category_path.capacity()
+                + d.public
+                    .iter()
+                    .chain(&d.private)
+                    .map(|s| size_of::<String>() + s.capacity())
+                    .sum::<usize>()
+        })
+        .sum();
+    let docs: usize = edge_map_bytes(&repo.module_docs);
+    let entries: usize = repo
+        .entry_points
+        .iter()
+        .map(|e| size_of::<EntryPoint>() + e.path.capacity() + e.detail.capacity())
+        .sum();
+    let symbols: usize = repo
+        .symbols
+        .iter()
+        .map(|s| {
+            size_of::<SymbolEntry>()
+                + s.name.capacity()
+                + s.kind.capacity()
+                + s.path.capacity()
+                + s.parent.as_ref().map_or(0, String::capacity)
+        })
+        .sum();
+    let config_refs: usize = repo
+        .config_refs
+        .iter()
+        .map(|r| size_of::<ConfigRef>() + r.name.capacity() + r.path.capacity())
+        .sum();
+    let licenses: usize = repo
+        .licenses
+        .headers
+        .iter()
+        .map(|h| size_of::<FileLicense>() + h.path.capacity() + h.license.capacity())
+        .chain(repo.licenses.dirs.values().map(|d| {
+            size_of::<DirLicense>()
+                + d.dir.capacity()
+                + d.license.capacity()
+                + d.files.iter().map(|f| size_of::<String>() + f.capacity()).sum::<usize>()
+        }))
+        .sum();
+    let schemas: usize = repo
+        .schemas
+        .symbols
+        .iter()
+        .map(|s| {
+            size_of::<SchemaSymbol>()
+                + s.name.capacity()
+                + s.kind.capacity()
+                + s.path.capacity()
+                + s.parent.as_ref().map_or(0, String::capacity)
+        })
+        .chain(
+            repo.schemas
+                .refs
+                .iter()
+                .map(|r| size_of::<SchemaRef>() + r.symbol.capacity() + r.path.capacity()),
+        )
+        .sum();
+    let commit_times: usize =
+        repo.commit_times.keys().map(|k| size_of::<(String, i64)>() + k.capacity()).sum();
+    scanned
+        + manifest
+        + docs
+        + entries
+        + symbols
+        + config_refs
+        + licenses
+        + schemas
+        + commit_times
+        + deps
+}
+
+fn search_index_bytes(repo: &RepoState) -> usize {
+    let files: usize = repo
+        .search_files
+        .iter()
+        .map(|e| {
+            size_of::<SearchFileEntry>()
+                + e.path.capacity()
+                + e.path_lower.capacity()
+                + e.filename.capacity()
+                + e.filename_lower.capacity()
+                + e.dir.capacity()
+                + e.ext.capacity()
+                + e.desc.capacity()
+                + e.desc_lower.capacity()
+                + e.category.capacity()
+        })
+        .sum();
+    let modules: usize = repo
+        .search_modules
+        .iter()
+        .map(|m| {
+            size_of::<SearchModuleEntry>()
+                + m.id.capacity()
+                + m.id_lower.capacity()
+                + m.name.capacity()
+                + m.name_lower.capacity()
+        })
+        .sum();
+    files + modules
+}
+
+fn edge_map_bytes(map: &BTreeMap<String, Vec<String>>) -> usize {
+    map.iter()
+        .map(|(k, v)| {
+            size_of::<(String, Vec<String>)>()
+                + k.capacity()
+                + v.iter().map(|s| size_of::<String>() + s.capacity()).sum::<usize>()
+        })
+        .sum()
+}
+
+fn edge_meta_bytes(edges: &BTreeMap<String, BTreeMap<String, ImportEdge>>) -> usize {
+    edges
+        .iter()
+        .map(|(k, targets)| {
+            size_of::<(String, BTreeMap<String, ImportEdge>)>()
+                + k.capacity()
+                + targets
+                    .keys()
+                    .map(|t| size_of::<(String, ImportEdge)>() + t.capacity())
+                    .sum::<usize>()
+        })
+        .sum()
+}
+
+/// Bytes held by cached raw file contents and tier-1 stubs.
+pub fn stub_cache_bytes(cache: &StubCache) -> usize {
+    cache
+        .iter()
+        .map(|e| {
+            size_of::<((String, u64), CachedStub)>()
+                + e.key().0.capacity()
+                + e.value().raw.len()
+                + e.value().tier1.len()
+        })
+        .sum()
+}
+
+#[cfg(feature = "semantic")]
+fn semantic_bytes(repo: &RepoState) -> usize {
+    let guard = repo.semantic_index.read().unwrap();
+    let Some(idx) = guard.as_ref() else { return 0 };
+    idx.embeddings.capacity() * size_of::<f32>()
+        + idx
+            .chunk_meta
+            .iter()
+            .map(|c| size_of::<ChunkMeta>() + c.file_path.capacity() + c.snippet.capacity())
+            .sum::<usize>()
+}
+
+#[cfg(not(feature = "semantic"))]
+fn semantic_bytes(_repo: &RepoState) -> usize {
+    0
+}
+
+/// Human-readable byte count (e.g. "512 B", "3.4 KB", "1.2 MB").
+pub fn format_bytes(bytes: usize) -> String {
+    const KB: f64 = 1024.0;
+    let b = bytes as f64;
+    if b < KB {
+        format!("{bytes} B")
+    } else if b < KB * KB {
+        format!("{:.1} KB", b / KB)
+    } else if b < KB * KB * KB {
+        format!("{:.1} MB", b / (KB * KB))
+    } else {
+        format!("{:.2} GB", b / (KB * KB * KB))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Limit enforcement
+// ---------------------------------------------------------------------------
+
+/// Evict caches until the repo fits within its configured `max_memory_mb`.
+///
+/// The stub cache is cleared first (it is rebuilt lazily on demand). If that is not
+/// enough, the in-RAM semantic vectors are dropped and semantic search falls back to
+/// keyword matching for this repo. Returns a description of each eviction performed.
+pub fn enforce_memory_limit(repo: &RepoState) -> Vec<String> {
+    let Some(limit_mb) = repo.config.max_memory_mb else {
+        return Vec::new();
+    };
+    let limit = limit_mb.saturating_mul(1024 * 1024);
+    let mut usage = repo_memory_usage(repo);
+    let mut evicted = Vec::new();
+    if usage.total() <= limit {
+        return evicted;
+    }
+
+    if usage.stub_cache > 0 {
+        repo.stub_cache.clear();
+        evicted.push(format!("stub cache ({})", format_bytes(usage.stub_cache)));
+        usage.stub_cache = 0;
+    }
+
+    #[cfg(feature = "semantic")]
+    if usage.total() > limit && usage.semantic_vectors > 0 {
+        *repo.semantic_index.write().unwrap() = None;
+        repo.semantic_progress
+            .status
+            .store(SemanticProgress::EVICTED, std::sync::atomic::Ordering::Relaxed);
+        evicted.push(format!("semantic vectors ({})", format_bytes(usage.semantic_vectors)));
+        usage.semantic_vectors = 0;
+    }
+
+    if !evicted.is_empty() {
+        warn!(
+            repo = repo.name.as_str(),
+            limit_mb = limit_mb,
+            evicted = evicted.join(", ").as_str(),
+            "Memory limit exceeded — evicted caches"
+        );
+    }
+    if usage.total() > limit {
+        warn!(
+            repo = repo.name.as_str(),
+            limit_mb = limit_mb,
+            usage = format_bytes(usage.total()).as_str(),
+            "Memory limit still exceeded after eviction — consider narrowing scan_dirs"
+        );
+    }
+    evicted
+}
+
+/// Run [`enforce_memory_limit`] for every repo that has a limit configured.
+pub fn enforce_memory_limits(state: &ServerState) {
+    for repo in state.repos.values() {
+        enforce_memory_limit(repo);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn format_bytes_picks_unit() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(2048), "2.0 KB");
+        assert_eq!(format_bytes(3 * 1024 * 1024 / 2), "1.5 MB");
+    }
+
+    #[test]
+    fn stub_cache_bytes_counts_contents() {
+        let cache = StubCache::new();
+        assert_eq!(stub_cache_bytes(&cache), 0);
+        cache.insert(
+            ("a.rs".to_string(), 7),
+            CachedStub {
+                raw: Arc::from("x".repeat(1000)),
+                tier1: Arc::from("fn a()"),
+                fast_tokens: 2,
+            },
+        );
+        assert!(stub_cache_bytes(&cache) >= 1006);
+    }
+}
@@ -0,0 +1,168 @@
+//! Module-to-module dependency graph computed from file import edges.
+//!
+//! `cs_modules action=deps` reports what a module's manifest declares; this graph reports
+//! what its code actually imports. Each file belongs to the module given by its category
+//! path, cut to `depth` levels (`crates > parser` at depth 2, `crates` at depth 1), and
+//! every import edge between files of two different modules counts toward the edge between
+//! those modules. Imports within a module are counted per module but form no edge.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::scan::get_category_path;
+use crate::types::RepoState;
+
+/// Category levels per node when the caller doesn't say.
+pub const DEFAULT_DEPTH: usize = 1;
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ModuleNode {
+    pub name: String,
+    pub files: usize,
+    /// File imports that stay inside the module.
+    pub internal_imports: usize,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ModuleEdge {
+    pub from: String,
+    pub to: String,
+    /// File-to-file import edges from `from` into `to`.
+    pub imports: usize,
+    /// Files of `from` with at least one such import.
+    pub files: usize,
+    /// Named symbols imported across those edges.
+    pub symbols: u32,
+    /// `to` also imports `from`.
+    pub mutual: bool,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ModuleGraph {
+    pub depth: usize,
+    /// Modules with files, by name.
+    pub modules: Vec<ModuleNode>,
+    /// Most imports first.
+    pub edges: Vec<ModuleEdge>,
+}
+
+/// Module graph of `repo` at `depth` category levels. With a `prefix`, only modules whose
+/// name starts with it are kept, along with edges that touch one of them.
+pub fn build(repo: &RepoState, depth: usize, prefix: &str) -> ModuleGraph {
+    let depth = depth.max(1);
+    let module_of = |path: &str| -> String {
+        let mut parts = get_category_path(path, &repo.config);
+        parts.truncate(depth);
+        parts.join(" > ")
+    };
+    let mut file_module: BTreeMap<&str, String> = BTreeMap::new();
+    let mut nodes: BTreeMap<String, ModuleNode> = BTreeMap::new();
+    for f in &repo.all_files {
+        let module = module_of(&f.rel_path);
+        nodes
+            .entry(module.clone())
+            .or_insert_with(|| ModuleNode { name: module.clone(), files: 0, internal_imports: 0 })
+            .files += 1;
+        file_module.insert(&f.rel_path, module);
+    }
+
+    let mut edges: BTreeMap<(&str, &str), (usize, BTreeSet<&str>, u32)> = BTreeMap::new();
+    for (from_file, targets) in &repo.import_graph.imports {
+        let Some(from) = file_module.get(from_file.as_str()) else { continue };
+        for to_file in targets {
+            let Some(to) = file_module.get(to_file.as_str()) else { continue };
+            if from == to {
+                if let Some(node) = nodes.get_mut(from) {
+                    node.internal_imports += 1;
+                }
+                continue;
+            }
+            let symbols = repo
+                .import_graph
+                .edges
+                .get(from_file)
+                .and_then(|e| e.get(to_file))
+                .map_or(0, |e| e.symbols);
+            let edge = edges.entry((from.as_str(), to.as_str())).or_default();
+            edge.0 += 1;
+            edge.1.insert(from_file.as_str());
+            edge.2 += symbols;
+        }
+    }
+
+    let keep = |name: &str| name.starts_with(prefix);
+    let mut edges: Vec<ModuleEdge> = edges
+        .iter()
+        .filter(|((from, to), _)| keep(from) || keep(to))
+        .map(|((from, to), (imports, files, symbols))| ModuleEdge {
+            from: from.to_string(),
+            to: to.to_string(),
+            imports: *imports,
+            files: files.len(),
+            symbols: *symbols,
+            mutual: edges.contains_key(&(*to, *from)),
+        })
+        .collect();
+    edges.sort_by(|a, b| {
+        b.imports.cmp(&a.imports).then_with(|| (&a.from, &a.to).cmp(&(&b.from, &b.to)))
+    });
+    ModuleGraph { depth, modules: nodes.into_values().filter(|n| keep(&n.name)).collect(), edges }
+}
+
+/// `cs_modules action=graph` text output, listing up to `limit` edges.
+pub fn format_text(graph: &ModuleGraph, limit: usize) -> String {
+    if graph.edges.is_empty() {
+        return format!(
+            "No imports between modules ({} modules at depth {})",
+            graph.modules.len(),
+            graph.depth
+        );
+    }
+    let mut out = format!(
+        "{} modules, {} dependencies from file imports (depth {}):\n\n",
+        graph.modules.len(),
+        graph.edges.len(),
+        graph.depth
+    );
+    for e in graph.edges.iter().take(limit) {
+        out.push_str(&format!(
+            "{} -> {}  ({} imports from {} files",
+            e.from, e.to, e.imports, e.files
+        ));
+        if e.symbols > 0 {
+            out.push_str(&format!(", {} symbols", e.symbols));
+        }
+        out.push(')');
+        if e.mutual {
+            out.push_str("  [mutual]");
+        }
+        out.push('\n');
+    }
+    if graph.edges.len() > limit {
+        out.push_str(&format!("\n... and {} more\n", graph.edges.len() - limit));
+    }
+    out
+}
+
+/// Graphviz DOT rendering: one box per module labeled with its file count, one arrow per
+/// dependency labeled with its import count.
+pub fn to_dot(graph: &ModuleGraph) -> String {
+    let escape = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+    let mut out = String::from("digraph modules {\n  rankdir=LR;\n  node [shape=box];\n");
+    for m in &graph.modules {
+        let name = escape(&m.name);
+        out.push_str(&format!("  \"{name}\" [label=\"{name}\\n{} files\"];\n", m.files));
+    }
+    for e in &graph.edges {
+        out.push_str(&format!(
+            "  \"{}\" -> \"{}\" [label=\"{}\"];\n",
+            escape(&e.from),
+            escape(&e.to),
+            e.imports
+        ));
+    }
+    out.push_str("}\n");
+    out
+}
@@ -0,0 +1,204 @@
+//! Repo-qualified module ids for multi-repo mode.
+//!
+//! A module is named by its manifest category (`crates > parser`) or its package name from
+//! `deps` (`core`). With several repos indexed, the same name can exist in more than one;
+//! the qualified id `repo/module` (e.g. `payments/core`) is always unambiguous.
+//!
+//! Resolution policy, shared by `cs_modules`, budget reads, and the web UI:
+//! 1. A qualified id selects its repo.
+//! 2. Otherwise the explicit `repo` argument (or the single/default repo) is used.
+//! 3. Otherwise a name found in exactly one repo resolves there.
+//! 4. A name found in several repos is an error listing the qualified candidates.
+
+use std::collections::BTreeMap;
+
+use crate::types::{RepoState, ServerState};
+
+/// Separator between repo name and module in a qualified id.
+pub const QUALIFIER_SEP: char = '/';
+
+/// `repo/module`.
+pub fn qualify(repo: &str, module: &str) -> String {
+    format!("{repo}{QUALIFIER_SEP}{module}")
+}
+
+/// Split a qualified id into its repo and module. Only a registered repo name counts as a
+/// qualifier, so module names containing `/` (npm scopes, paths) are left intact.
+pub fn split_qualified<'a, 's>(
+    state: &'s ServerState,
+    id: &'a str,
+) -> Option<(&'s RepoState, &'a str)> {
+    let (repo, module) = id.split_once(QUALIFIER_SEP)?;
+    let repo = state.repos.get(repo)?;
+    (!module.is_empty()).then_some((repo, module))
+}
+
+/// Whether `module` names a manifest category (or a parent of one) or a dependency module.
+pub fn has_module(repo: &RepoState, module: &str) -> bool {
+    let child_prefix = format!("{module} > ");
+    repo.deps.contains_key(module)
+        || repo.manifest.keys().any(|cat| cat == module || cat.starts_with(&child_prefix))
+}
+
+/// Module names defined in more than one repo, with the repos defining each.
+pub fn module_collisions(state: &ServerState) -> BTreeMap<String, Vec<String>> {
+    let mut owners: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for repo in state.repos.values() {
+        let mut names: Vec<&String> = repo.manifest.keys().chain(repo.deps.keys()).collect();
+        names.sort();
+        names.dedup();
+        for name in names {
+            owners.entry(name.clone()).or_default().push(repo.name.clone());
+        }
+    }
+    owners.retain(|_, repos| repos.len() > 1);
+    owners
+}
+
+/// Resolve a plain or qualified module id to a repo and unqualified module name.
+///
+/// `repo_arg` is an explicit repo selection (the tool's `repo` argument).
+pub fn resolve_module<'s>(
+    state: &'s ServerState,
+    id: &str,
+    repo_arg: Option<&str>,
+) -> Result<(&'s RepoState, String), String> {
+    if let Some((repo, module)) = split_qualified(state, id) {
+        return Ok((repo, module.to_string()));
+    }
+    let unknown = |name: &str| {
+        let available: Vec<&str> = state.repos.keys().map(|k| k.as_str()).collect();
+        format!("Unknown repo '{name}'. Available: {}", available.join(", "))
+    };
+    if let Some(name) = repo_arg {
+        return state.repos.get(name).map(|r| (&**r, id.to_string())).ok_or_else(|| unknown(name));
+    }
+    if state.repos.len() == 1 || state.default_repo.is_some() {
+        return Ok((state.default_repo(), id.to_string()));
+    }
+    let owners: Vec<&RepoState> =
+        state.repos.values().map(|r| &**r).filter(|r| has_module(r, id)).collect();
+    match owners.as_slice() {
+        [repo] => Ok((repo, id.to_string())),
+        [] => Err(format!("Module '{id}' not found in any repo")),
+        _ => {
+            let candidates: Vec<String> = owners.iter().map(|r| qualify(&r.name, id)).collect();
+            Err(format!(
+                "Module '{id}' exists in {} repos. Use a qualified id: {}",
+                owners.len(),
+                candidates.join(", ")
+            ))
+        }
+    }
+}
+
+/// Repo for a batch of paths: the explicit `repo_arg`, else the `[repo]` prefix all paths
+/// share. Returns the repo (`None` when neither selects one, so the caller applies its own
+/// default) and the paths with prefixes removed.
+pub fn resolve_paths_repo<'s>(
+    state: &'s ServerState,
+    paths: &[String],
+    repo_arg: Option<&str>,
+) -> Result<(Option<&'s RepoState>, Vec<String>), String> {
+    let mut prefixed: Option<&str> = None;
+    let mut stripped = Vec::with_capacity(paths.len());
+    for p in paths {
+        let (name, rel) = crate::paths::split_repo_prefix(p);
+        if let Some(name) = name {
+            if prefixed.is_some_and(|n| n != name) {
+                return Err(format!(
+                    "Paths span several repos ({}, {name}); budget reads take one repo at a time",
+                    prefixed.unwrap_or_default()
+                ));
+            }
+            prefixed = Some(name);
+        }
+        stripped.push(rel.to_string());
+    }
+    let repo = match repo_arg.or(prefixed) {
+        Some(name) => Some(&**state.repos.get(name).ok_or_else(|| {
+            let available: Vec<&str> = state.repos.keys().map(|k| k.as_str()).collect();
+            format!("Unknown repo '{name}'. Available: {}", available.join(", "))
+        })?),
+        None => None,
+    };
+    Ok((repo, stripped))
+}
+
+// ---------------------------------------------------------------------------
+// Unified views for the web UI
+// ---------------------------------------------------------------------------
+
+/// File/module tree for `/api/tree`. A single repo is served as-is; with several repos,
+/// each repo becomes a top-level node and file paths carry a `[repo] ` prefix so
+/// `/api/file` resolves them against the right root.
+pub fn unified_tree(state: &ServerState) -> serde_json::Value {
+    if state.repos.len() == 1 {
+        let repo = state.default_repo();
+        return crate::scan::build_tree(&repo.manifest, repo.config.sort_order);
+    }
+    let mut root = serde_json::Map::new();
+    for repo in state.repos.values() {
+        let mut manifest = repo.manifest.clone();
+        for files in manifest.values_mut() {
+            for f in files {
+                f.path = format!("[{}] {}", repo.name, f.path);
+            }
+        }
+        root.insert(repo.name.clone(), crate::scan::build_tree(&manifest, repo.config.sort_order));
+    }
+    serde_json::Value::Object(root)
+}
+
+/// Manifest for `/api/manifest`, keyed by qualified module id in multi-repo mode.
+pub fn unified_manifest(state: &ServerState) -> serde_json::Value {
+    if state.repos.len() == 1 {
+        return serde_json::json!(state.default_repo().manifest);
+    }
+    let mut out = serde_json::Map::new();
+    for repo in state.repos.values() {
+        for (cat, files) in &repo.manifest {
+            let files: Vec<serde_json::Value> = files
+                .iter()
+                .map(|f| {
+                    serde_json::json!({
+                        "path": format!("[{}] {}", repo.name, f.path),
+                        "desc": f.desc,
+                        "size": f.size,
+                    })
+                })
+                .collect();
+            out.insert(qualify(&repo.name, cat), serde_json::Value::Array(files));
+        }
+    }
+    serde_json::Value::Object(out)
+}
+
+/// Dependency map for `/api/deps`, keyed by qualified module id in multi-repo mode.
+/// Dependency names inside each entry are qualified when the repo defines that module.
+pub fn unified_deps(state: &ServerState) -> serde_json::Value {
+    if state.repos.len() == 1 {
+        return serde_json::json!(state.default_repo().deps);
+    }
+    let mut out = serde_json::Map::new();
+    for repo in state.repos.values() {
+        let local = |d: &String| {
+            if repo.deps.contains_key(d) {
+                qualify(&repo.name, d)
+            } else {
+                d.clone()
+            }
+        };
+        for (name, dep) in &repo.deps {
+            out.insert(
+                qualify(&repo.name, name),
+                serde_json::json!({
+                    "public": dep.public.iter().map(local).collect::<Vec<_>>(),
+                    "private": dep.private.iter().map(local).collect::<Vec<_>>(),
+                    "categoryPath": qualify(&repo.name, &dep.category_path),
+                }),
+            );
+        }
+    }
+    serde_json::Value::Object(out)
+}
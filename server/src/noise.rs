@@ -0,0 +1,305 @@
+//! Graded noise scores for directories from `[noise]` in `.codescope.toml`.
+//!
+//! ```toml
+//! [noise]
+//! third_party = 1.0        # plain names match at any depth
+//! "**/fixtures" = 0.6
+//! "legacy/*" = 0.3         # paths and globs match from the project root
+//! ```
+//!
+//! A file's noise is the highest score of any directory above it, from 0 (no effect) up
+//! to 1. Search scores are multiplied by `1 - 0.9 * noise`, on top of the `[ranking]`
+//! boosts, so noisy files sink without disappearing. Files with noise of 0.5 or more are
+//! also left out of semantic embedding. Reads, `cs_grep` matching, and the file tree are
+//! not affected.
+//!
+//! [`detect`] proposes entries from scan statistics: directories of mostly minified files,
+//! directories whose code never imports or is imported from outside them, and huge
+//! directories that barely connect to the rest. `codescope doctor` prints the proposals;
+//! with `auto_noise = true` they apply at scan time to any file no `[noise]` entry matches.
+
+use globset::{GlobBuilder, GlobSetBuilder};
+use std::collections::HashMap;
+use tracing::warn;
+
+use crate::types::{ImportGraph, ScannedFile};
+
+/// Largest share of a score that noise can take away.
+const MAX_PENALTY: f64 = 0.9;
+
+/// Noise at or above which files are not embedded for semantic search.
+pub const EMBED_CUTOFF: f64 = 0.5;
+
+/// `[noise]` directory patterns and their scores.
+#[derive(Clone, Debug, Default)]
+pub struct NoiseConfig {
+    globs: Option<globset::GlobSet>,
+    /// Score of each glob in `globs`, by index.
+    scores: Vec<f64>,
+    /// Detected directories and their scores (`auto_noise`), for files no glob matches.
+    auto: Vec<(String, f64)>,
+}
+
+impl NoiseConfig {
+    /// Parse the `[noise]` table. Invalid patterns and scores outside 0–1 are skipped with
+    /// a warning.
+    pub fn from_table(table: &toml::Table) -> Self {
+        let mut builder = GlobSetBuilder::new();
+        let mut scores = Vec::new();
+        for (pattern, value) in table {
+            let score = value.as_float().or_else(|| value.as_integer().map(|i| i as f64));
+            let Some(score) = score.filter(|s| (0.0..=1.0).contains(s)) else {
+                warn!(
+                    pattern = pattern.as_str(),
+                    value = %value,
+                    "[noise] scores must be numbers from 0 to 1 — ignoring"
+                );
+                continue;
+            };
+            let trimmed = pattern.trim_start_matches("./").trim_end_matches('/');
+            let full = if trimmed.contains('/') || trimmed.starts_with("**") {
+                trimmed.to_string()
+            } else {
+                format!("**/{trimmed}")
+            };
+            match GlobBuilder::new(&full).literal_separator(true).build() {
+                Ok(glob) => {
+                    builder.add(glob);
+                    scores.push(score);
+                }
+                Err(e) => {
+                    warn!(
+                        pattern = pattern.as_str(),
+                        error = %e,
+                        "Invalid [noise] pattern — ignoring"
+                    );
+                }
+            }
+        }
+        let globs = if scores.is_empty() { None } else { builder.build().ok() };
+        Self { globs, scores, auto: Vec::new() }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.globs.is_none() && self.auto.is_empty()
+    }
+
+    /// Use detected `candidates` for files that no `[noise]` pattern matches.
+    pub fn set_auto(&mut self, candidates: &[NoiseCandidate]) {
+        self.auto = candidates.iter().map(|c| (c.dir.clone(), c.score)).collect();
+    }
+
+    /// Noise of a file: the highest score among the directories containing it, 0 if none.
+    /// A matching `[noise]` pattern wins over detected directories, even with a score of 0.
+    pub fn score(&self, rel_path: &str) -> f64 {
+        self.configured(rel_path).unwrap_or_else(|| {
+            self.auto
+                .iter()
+                .filter(|(dir, _)| {
+                    rel_path.strip_prefix(dir.as_str()).is_some_and(|r| r.starts_with('/'))
+                })
+                .fold(0.0, |score, (_, s)| score.max(*s))
+        })
+    }
+
+    /// Highest `[noise]` pattern score among the directories containing a file, `None`
+    /// when no pattern matches.
+    fn configured(&self, rel_path: &str) -> Option<f64> {
+        let globs = self.globs.as_ref()?;
+        let mut score: Option<f64> = None;
+        let mut matches = Vec::new();
+        for (i, _) in rel_path.match_indices('/') {
+            globs.matches_into(&rel_path[..i], &mut matches);
+            for &m in &matches {
+                score = Some(score.unwrap_or(0.0).max(self.scores[m]));
+            }
+        }
+        score
+    }
+
+    /// Search score multiplier for a file.
+    pub fn boost(&self, rel_path: &str) -> f64 {
+        1.0 - MAX_PENALTY * self.score(rel_path)
+    }
+
+    /// Whether semantic indexing should skip a file.
+    pub fn skips_embedding(&self, rel_path: &str) -> bool {
+        self.score(rel_path) >= EMBED_CUTOFF
+    }
+}
+
+/// Files a directory needs before it is judged at all.
+const MIN_FILES: usize = 5;
+/// Code files a directory needs before its lack of imports means anything.
+const MIN_DISCONNECTED_FILES: usize = 20;
+/// Files, and share of the repo, that make a directory huge.
+const HUGE_FILES: usize = 1000;
+const HUGE_SHARE: f64 = 0.25;
+/// Share of a huge directory's code files that import across its boundary, below which it
+/// counts as barely connected.
+const HUGE_CONNECTED_SHARE: f64 = 0.05;
+
+/// A directory that looks like noise, with the score to give it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NoiseCandidate {
+    pub dir: String,
+    pub files: usize,
+    pub score: f64,
+    /// Why, one short phrase per signal, e.g. `"9 of 10 files minified"`.
+    pub reasons: Vec<String>,
+}
+
+#[derive(Default)]
+struct DirStats {
+    files: usize,
+    minified: usize,
+    /// Per language: files, files with any import edge, and files with an import edge to
+    /// or from outside the directory.
+    langs: HashMap<&'static str, (usize, usize, usize)>,
+}
+
+/// Directories of `files` that look like noise, outermost first, skipping those that
+/// `existing` already has a pattern for. A directory nested in a candidate is only listed when it
+/// scores higher.
+///
+/// Code files count toward connectivity only in languages whose files import one another
+/// outside the directory too, so a self-contained subproject in another language (a web
+/// frontend next to a server) is not mistaken for noise.
+pub fn detect(
+    files: &[ScannedFile],
+    graph: &ImportGraph,
+    existing: &NoiseConfig,
+) -> Vec<NoiseCandidate> {
+    let mut linked: HashMap<&'static str, usize> = HashMap::new();
+    let mut stats: HashMap<&str, DirStats> = HashMap::new();
+    for f in files {
+        // Depth of the deepest directory that holds all of this file's import neighbours
+        let neighbours = graph
+            .imports
+            .get(&f.rel_path)
+            .into_iter()
+            .chain(graph.imported_by.get(&f.rel_path))
+            .flatten();
+        let mut shared: Option<usize> = None;
+        for other in neighbours {
+            let depth = common_dir_depth(&f.rel_path, other);
+            shared = Some(shared.map_or(depth, |s| s.min(depth)));
+        }
+        if let (Some(lang), Some(_)) = (f.lang, shared) {
+            *linked.entry(lang).or_default() += 1;
+        }
+        for (depth, (i, _)) in f.rel_path.match_indices('/').enumerate() {
+            let dir = stats.entry(&f.rel_path[..i]).or_default();
+            dir.files += 1;
+            if f.skip_content == Some("minified") {
+                dir.minified += 1;
+            }
+            if let Some(lang) = f.lang {
+                let counts = dir.langs.entry(lang).or_default();
+                counts.0 += 1;
+                if let Some(shared) = shared {
+                    counts.1 += 1;
+                    if shared <= depth {
+                        counts.2 += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    let total = files.len();
+    let mut found: Vec<NoiseCandidate> = Vec::new();
+    for (dir, s) in &stats {
+        if s.files < MIN_FILES || existing.configured(&format!("{dir}/")).is_some() {
+            continue;
+        }
+        let mut score: f64 = 0.0;
+        let mut reasons = Vec::new();
+        if s.minified * 2 >= s.files {
+            score = 1.0;
+            reasons.push(format!("{} of {} files minified", s.minified, s.files));
+        }
+        // Languages that also have linked files elsewhere in the repo
+        let (code, connected) = s
+            .langs
+            .iter()
+            .filter(|(lang, (_, inside, _))| linked.get(*lang).is_some_and(|all| all > inside))
+            .fold((0, 0), |(c, l), (_, (n, _, k))| (c + n, l + k));
+        if code >= MIN_DISCONNECTED_FILES && connected == 0 {
+            score = score.max(0.6);
+            reasons.push(format!("{code} code files, none imported from or importing outside"));
+        }
+        if s.files >= HUGE_FILES
+            && s.files as f64 >= HUGE_SHARE * total as f64
+            && code > 0
+            && (connected as f64) < HUGE_CONNECTED_SHARE * code as f64
+        {
+            score = score.max(0.8);
+            reasons.push(format!(
+                "{} of the repo's {total} files, {connected} of {code} code files connected",
+                s.files
+            ));
+        }
+        if score > 0.0 {
+            found.push(NoiseCandidate { dir: dir.to_string(), files: s.files, score, reasons });
+        }
+    }
+
+    found.sort_by(|a, b| a.dir.cmp(&b.dir));
+    let mut kept: Vec<NoiseCandidate> = Vec::new();
+    for c in found {
+        let outer = kept
+            .iter()
+            .filter(|k| c.dir.strip_prefix(k.dir.as_str()).is_some_and(|r| r.starts_with('/')))
+            .fold(0.0, |score: f64, k| score.max(k.score));
+        if c.score > outer {
+            kept.push(c);
+        }
+    }
+    kept.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.dir.cmp(&b.dir)));
+    kept
+}
+
+/// Number of leading directories two paths share.
+fn common_dir_depth(a: &str, b: &str) -> usize {
+    let (a, b) = (a.rsplit_once('/').map_or("", |p| p.0), b.rsplit_once('/').map_or("", |p| p.0));
+    a.split('/').zip(b.split('/')).take_while(|(x, y)| !x.is_empty() && x == y).count()
+}
+
+/// `[noise]` lines for `candidates`, ready to paste into `.codescope.toml`.
+pub fn format_toml(candidates: &[NoiseCandidate]) -> String {
+    let mut out = String::from("[noise]\n");
+    for c in candidates {
+        out.push_str(&format!("\"{}\" = {:.1}  # {}\n", c.dir, c.score, c.reasons.join("; ")));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn files_take_the_highest_score_of_their_directories() {
+        let table: toml::Table = toml::from_str(
+            r#"
+            third_party = 1.0
+            "legacy/*" = 0.3
+            "legacy/old" = 0.6
+            "**/fixtures" = 0.4
+            bad = 2
+            "#,
+        )
+        .unwrap();
+        let noise = NoiseConfig::from_table(&table);
+        assert_eq!(noise.score("src/main.rs"), 0.0);
+        assert_eq!(noise.score("libs/third_party/zlib/inflate.c"), 1.0);
+        assert_eq!(noise.score("legacy/api/handler.rs"), 0.3);
+        assert_eq!(noise.score("legacy/old/fixtures/a.json"), 0.6);
+        assert_eq!(noise.score("legacy/README.md"), 0.0, "only directories match");
+        assert_eq!(noise.score("bad/file.rs"), 0.0, "out-of-range score ignored");
+        assert!((noise.boost("legacy/api/handler.rs") - 0.73).abs() < 1e-9);
+        assert!(noise.skips_embedding("legacy/old/x.rs"));
+        assert!(!noise.skips_embedding("src/fixtures/x.rs"));
+    }
+}
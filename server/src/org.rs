@@ -0,0 +1,255 @@
+//! Polyrepo onboarding from an org file (`codescope init --org-file repos.yaml`).
+//!
+//! ```yaml
+//! workspace: ~/src/acme     # where checkouts live; default: the org file's directory
+//! group: acme               # group recorded in repos.toml for every repo below
+//! repos:
+//!   - https://github.com/acme/api.git
+//!   - url: git@github.com:acme/web.git
+//!     name: web-app          # default: the last path segment of the URL
+//!     branch: develop        # default: the remote's default branch
+//!     group: frontend
+//! ```
+//!
+//! Only this shape of YAML is read: top-level `key: value` pairs and a `repos` list whose
+//! items are URLs or `key: value` maps. A bare top-level list of repos works too. Each repo
+//! is cloned into `<workspace>/<name>` with the `git` command line, which brings the user's
+//! credential helpers and SSH keys along; an existing checkout is fast-forwarded instead.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// One repo of an org file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OrgRepo {
+    pub url: String,
+    pub name: String,
+    pub branch: Option<String>,
+    pub group: Option<String>,
+}
+
+/// A parsed org file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OrgFile {
+    /// `workspace` as written, before `~` and relative paths are resolved.
+    pub workspace: Option<String>,
+    pub repos: Vec<OrgRepo>,
+}
+
+/// What [`sync`] did to a checkout.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SyncOutcome {
+    Cloned,
+    /// An existing checkout was fast-forwarded (or was already up to date).
+    Updated,
+}
+
+/// Parse an org file. Errors name the offending line.
+pub fn parse(content: &str) -> Result<OrgFile, String> {
+    let mut file = OrgFile::default();
+    let mut group: Option<String> = None;
+    // Keys of the list item being read, with the line it started on
+    let mut items: Vec<(usize, Vec<(String, String)>)> = Vec::new();
+    let mut in_repos = false;
+
+    for (n, raw) in content.lines().enumerate() {
+        let n = n + 1;
+        let line = strip_comment(raw);
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed == "---" {
+            continue;
+        }
+        let indented = line.starts_with([' ', '\t']);
+        if let Some(item) = trimmed.strip_prefix("- ").or((trimmed == "-").then_some("")) {
+            if indented && !in_repos {
+                return Err(format!("line {n}: list item outside 'repos'"));
+            }
+            let item = item.trim();
+            let pairs = match key_value(item) {
+                Some((k, v)) => vec![(k.to_string(), v)],
+                None if item.is_empty() => Vec::new(),
+                None => vec![("url".to_string(), unquote(item))],
+            };
+            items.push((n, pairs));
+            continue;
+        }
+        let Some((key, value)) = key_value(trimmed) else {
+            return Err(format!("line {n}: expected 'key: value', got '{trimmed}'"));
+        };
+        if indented && !items.is_empty() {
+            items.last_mut().unwrap().1.push((key.to_string(), value));
+            continue;
+        }
+        if indented {
+            return Err(format!("line {n}: unexpected indented '{key}'"));
+        }
+        in_repos = false;
+        match key {
+            "workspace" => file.workspace = Some(value).filter(|v| !v.is_empty()),
+            "group" => group = Some(value).filter(|v| !v.is_empty()),
+            "repos" if value.is_empty() => in_repos = true,
+            "repos" => return Err(format!("line {n}: 'repos' must be a list")),
+            other => {
+                return Err(format!(
+                    "line {n}: unknown key '{other}'. Expected workspace, group, or repos"
+                ))
+            }
+        }
+    }
+
+    for (n, pairs) in items {
+        let mut repo =
+            OrgRepo { url: String::new(), name: String::new(), branch: None, group: None };
+        for (key, value) in pairs {
+            match key.as_str() {
+                "url" => repo.url = value,
+                "name" => repo.name = value,
+                "branch" => repo.branch = Some(value),
+                "group" => repo.group = Some(value),
+                other => {
+                    return Err(format!(
+                        "line {n}: unknown repo key '{other}'. Expected url, name, branch, or group"
+                    ))
+                }
+            }
+        }
+        if repo.url.is_empty() {
+            return Err(format!("line {n}: repo has no url"));
+        }
+        if repo.name.is_empty() {
+            repo.name = name_from_url(&repo.url)
+                .ok_or_else(|| format!("line {n}: no repo name in '{}'; add name:", repo.url))?;
+        }
+        if repo.name.contains(['/', '\\']) || repo.name.starts_with('.') {
+            return Err(format!("line {n}: invalid repo name '{}'", repo.name));
+        }
+        if repo.group.is_none() {
+            repo.group = group.clone();
+        }
+        if file.repos.iter().any(|r| r.name == repo.name) {
+            return Err(format!("line {n}: repo name '{}' is used twice", repo.name));
+        }
+        file.repos.push(repo);
+    }
+    Ok(file)
+}
+
+/// The checkout name a URL implies: its last path segment without `.git`.
+pub fn name_from_url(url: &str) -> Option<String> {
+    let last = url.trim_end_matches('/').rsplit(['/', ':', '\\']).next()?;
+    let name = last.strip_suffix(".git").unwrap_or(last);
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// Directory the checkouts go in: `workspace` from the org file, with `~` expanded and
+/// relative paths taken from the org file's directory, else that directory itself.
+pub fn workspace_dir(file: &OrgFile, org_file: &Path) -> PathBuf {
+    let base = org_file.parent().map(Path::to_path_buf).unwrap_or_default();
+    let Some(workspace) = &file.workspace else { return base };
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"));
+    match (workspace.strip_prefix("~/").or((workspace == "~").then_some("")), home) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => base.join(workspace),
+    }
+}
+
+/// Clone `repo` into `dir`, or fast-forward the checkout already there.
+pub fn sync(repo: &OrgRepo, dir: &Path) -> Result<SyncOutcome, String> {
+    if dir.join(".git").exists() {
+        git(Command::new("git").arg("-C").arg(dir).args(["pull", "--ff-only", "--quiet"]))?;
+        return Ok(SyncOutcome::Updated);
+    }
+    if dir.read_dir().is_ok_and(|mut entries| entries.next().is_some()) {
+        return Err(format!("{} exists and is not a git checkout", dir.display()));
+    }
+    let mut clone = Command::new("git");
+    clone.args(["clone", "--quiet"]);
+    if let Some(branch) = &repo.branch {
+        clone.args(["--branch", branch]);
+    }
+    git(clone.arg("--").arg(&repo.url).arg(dir))?;
+    Ok(SyncOutcome::Cloned)
+}
+
+/// Run a git command, turning a failure into its last line of stderr.
+fn git(cmd: &mut Command) -> Result<(), String> {
+    let output = cmd
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(std::process::Stdio::null())
+        .output()
+        .map_err(|e| format!("Failed to run git: {e}"))?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let reason = stderr.lines().rev().find(|l| !l.trim().is_empty()).unwrap_or("unknown error");
+    Err(reason.trim().to_string())
+}
+
+fn strip_comment(line: &str) -> &str {
+    if line.trim_start().starts_with('#') {
+        return "";
+    }
+    let mut quote = None;
+    let mut prev = ' ';
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '#') if prev.is_whitespace() => return &line[..i],
+            _ => {}
+        }
+        prev = c;
+    }
+    line
+}
+
+/// `key: value` with a plain identifier key. URLs such as `https://…` or
+/// `git@host:org/repo` are not pairs.
+fn key_value(s: &str) -> Option<(&str, String)> {
+    let (key, value) = match s.split_once(": ") {
+        Some(pair) => pair,
+        None => (s.strip_suffix(':')?, ""),
+    };
+    key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_').then(|| (key, unquote(value.trim())))
+}
+
+fn unquote(s: &str) -> String {
+    let s = s.trim();
+    for q in ['"', '\''] {
+        if let Some(inner) = s.strip_prefix(q).and_then(|s| s.strip_suffix(q)) {
+            return inner.to_string();
+        }
+    }
+    s.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn org_files_list_urls_or_maps_with_defaults() {
+        let file = parse(
+            "# team repos\nworkspace: ~/src/acme\ngroup: acme\nrepos:\n  - https://github.com/acme/api.git\n  - url: \"git@github.com:acme/web.git\"  # frontend\n    name: web-app\n    branch: develop\n    group: frontend\n  - git@github.com:acme/infra\n",
+        )
+        .unwrap();
+        assert_eq!(file.workspace.as_deref(), Some("~/src/acme"));
+        let names: Vec<(&str, Option<&str>)> =
+            file.repos.iter().map(|r| (r.name.as_str(), r.group.as_deref())).collect();
+        assert_eq!(
+            names,
+            [("api", Some("acme")), ("web-app", Some("frontend")), ("infra", Some("acme"))]
+        );
+        assert_eq!(file.repos[1].url, "git@github.com:acme/web.git");
+        assert_eq!(file.repos[1].branch.as_deref(), Some("develop"));
+
+        let bare = parse("- /srv/git/tools.git\n- ../shared/\n").unwrap();
+        assert_eq!(bare.repos[1].name, "shared");
+        assert_eq!(workspace_dir(&bare, Path::new("/work/repos.yaml")), Path::new("/work"));
+
+        assert!(parse("repos:\n  - url: x\n    tag: v1\n").unwrap_err().contains("line 2"));
+        assert!(parse("repo:\n  - x\n").unwrap_err().contains("unknown key 'repo'"));
+        assert!(parse("- a/x.git\n- b/x.git\n").unwrap_err().contains("used twice"));
+    }
+}
@@ -0,0 +1,280 @@
+//! Code owners from the repo's `CODEOWNERS` file.
+//!
+//! The first of `.github/CODEOWNERS`, `CODEOWNERS`, `docs/CODEOWNERS`, and
+//! `.gitlab/CODEOWNERS` is read at scan time, and each scanned file records its owners.
+//! Patterns follow GitHub's rules: `/` at the start anchors a pattern to the repo root, a
+//! pattern without a `/` (other than a trailing one) matches at any depth, a trailing `/`
+//! matches only directories, `*` stays within one path component, and `docs/*` covers the
+//! files directly in `docs/` but not deeper ones. The last matching rule wins, and a rule
+//! listing no owners leaves its files unowned.
+//!
+//! GitLab sections (`[Backend] @backend-team`) are understood too: each section is matched
+//! on its own, rules without owners take the section's default owners, and a file's
+//! owners are the union of the winning rule of every section.
+
+use globset::{GlobBuilder, GlobSetBuilder};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::types::RepoState;
+
+/// Where `CODEOWNERS` is looked for, in order.
+pub const CODEOWNERS_PATHS: &[&str] =
+    &[".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS", ".gitlab/CODEOWNERS"];
+
+/// One `CODEOWNERS` line.
+#[derive(Clone, Debug, Serialize)]
+pub struct OwnerRule {
+    pub pattern: String,
+    #[serde(serialize_with = "serialize_owners")]
+    pub owners: Arc<[String]>,
+    /// 1-based line in the `CODEOWNERS` file.
+    pub line: usize,
+    /// GitLab section the rule belongs to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub section: Option<String>,
+}
+
+fn serialize_owners<S: serde::Serializer>(owners: &Arc<[String]>, s: S) -> Result<S::Ok, S::Error> {
+    s.collect_seq(owners.iter())
+}
+
+/// Parsed `CODEOWNERS` rules with their compiled patterns.
+#[derive(Clone, Debug, Default)]
+pub struct CodeOwners {
+    /// The file the rules came from, relative to the repo root.
+    pub source: Option<String>,
+    pub rules: Vec<OwnerRule>,
+    globs: Option<globset::GlobSet>,
+    /// Rule index of each glob in `globs`.
+    glob_rules: Vec<usize>,
+}
+
+impl CodeOwners {
+    /// Read the repo's `CODEOWNERS`, if it has one.
+    pub fn load(root: &Path) -> Self {
+        for rel in CODEOWNERS_PATHS {
+            if let Ok(content) = std::fs::read_to_string(root.join(rel)) {
+                return Self::parse(rel, &content);
+            }
+        }
+        Self::default()
+    }
+
+    /// Parse `CODEOWNERS` content. Patterns that don't compile are skipped with a warning.
+    pub fn parse(source: &str, content: &str) -> Self {
+        let mut owners = Self { source: Some(source.to_string()), ..Self::default() };
+        let mut builder = GlobSetBuilder::new();
+        let mut section: Option<(String, Arc<[String]>)> = None;
+        for (i, line) in content.lines().enumerate() {
+            let line = line.split(" #").next().unwrap_or("").trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some((name, defaults)) = parse_section(line) {
+                section = Some((name, defaults));
+                continue;
+            }
+            let mut parts = line.split_whitespace();
+            let Some(pattern) = parts.next() else { continue };
+            let mut listed: Vec<String> = parts.map(String::from).collect();
+            if listed.is_empty() {
+                if let Some((_, defaults)) = &section {
+                    listed = defaults.to_vec();
+                }
+            }
+            let rule = owners.rules.len();
+            for glob in pattern_globs(pattern) {
+                match GlobBuilder::new(&glob).literal_separator(true).build() {
+                    Ok(g) => {
+                        builder.add(g);
+                        owners.glob_rules.push(rule);
+                    }
+                    Err(e) => {
+                        warn!(pattern, line = i + 1, error = %e, "Invalid CODEOWNERS pattern — ignoring");
+                    }
+                }
+            }
+            owners.rules.push(OwnerRule {
+                pattern: pattern.to_string(),
+                owners: listed.into(),
+                line: i + 1,
+                section: section.as_ref().map(|(name, _)| name.clone()),
+            });
+        }
+        owners.globs = builder.build().ok();
+        owners
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The winning rule for `rel_path` in each section (one entry without sections).
+    pub fn rules_for(&self, rel_path: &str) -> Vec<&OwnerRule> {
+        let Some(globs) = &self.globs else { return Vec::new() };
+        let mut winners: BTreeMap<Option<&str>, usize> = BTreeMap::new();
+        for m in globs.matches(rel_path) {
+            let rule = self.glob_rules[m];
+            let slot = winners.entry(self.rules[rule].section.as_deref()).or_insert(rule);
+            *slot = (*slot).max(rule);
+        }
+        winners.into_values().map(|r| &self.rules[r]).collect()
+    }
+
+    /// Owners of `rel_path`; `None` when no rule with owners matches it.
+    pub fn owners_of(&self, rel_path: &str) -> Option<Arc<[String]>> {
+        let rules = self.rules_for(rel_path);
+        match rules.as_slice() {
+            [] => None,
+            [rule] => (!rule.owners.is_empty()).then(|| Arc::clone(&rule.owners)),
+            _ => {
+                let mut all: Vec<String> = Vec::new();
+                for owner in rules.iter().flat_map(|r| r.owners.iter()) {
+                    if !all.contains(owner) {
+                        all.push(owner.clone());
+                    }
+                }
+                (!all.is_empty()).then(|| all.into())
+            }
+        }
+    }
+}
+
+/// `[Name] @default-owners`, optionally `^[Name]` (optional section) or `[Name][2]`
+/// (approval count).
+fn parse_section(line: &str) -> Option<(String, Arc<[String]>)> {
+    let rest = line.strip_prefix('^').unwrap_or(line).strip_prefix('[')?;
+    let (name, mut rest) = rest.split_once(']')?;
+    if let Some(after) = rest.strip_prefix('[') {
+        rest = after.split_once(']').map_or("", |(_, r)| r);
+    }
+    Some((name.trim().to_string(), rest.split_whitespace().map(String::from).collect()))
+}
+
+/// Globs (matched against repo-relative file paths) equivalent to a `CODEOWNERS` pattern.
+fn pattern_globs(pattern: &str) -> Vec<String> {
+    let anchored = pattern.starts_with('/');
+    let dir_only = pattern.ends_with('/');
+    let trimmed = pattern.trim_matches('/');
+    if trimmed.is_empty() {
+        return vec!["**".to_string()];
+    }
+    let base = if anchored || trimmed.contains('/') {
+        trimmed.to_string()
+    } else {
+        format!("**/{trimmed}")
+    };
+    if dir_only {
+        vec![format!("{base}/**")]
+    } else if trimmed.ends_with("/*") {
+        vec![base]
+    } else {
+        vec![format!("{base}/**"), base]
+    }
+}
+
+/// Owner of files under a path, with how many of them they own.
+#[derive(Serialize, Debug)]
+pub struct OwnerCount {
+    pub owner: String,
+    pub files: usize,
+}
+
+/// Ownership of a file or of every file under a directory.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OwnersReport {
+    /// `CODEOWNERS` file in use; `None` when the repo has none.
+    pub source: Option<String>,
+    pub path: String,
+    pub files: usize,
+    /// Owners by files owned, most first.
+    pub owners: Vec<OwnerCount>,
+    pub unowned: usize,
+    /// The deciding rules, for a single file.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<OwnerRule>,
+}
+
+/// Ownership of `path` (a file or directory; empty for the whole repo).
+pub fn owners_report(repo: &RepoState, path: &str) -> OwnersReport {
+    let path = path.trim_matches('/');
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    let (mut files, mut unowned) = (0, 0);
+    for f in repo.all_files.iter().filter(|f| crate::entries::path_in(&f.rel_path, path)) {
+        files += 1;
+        match &f.owners {
+            Some(owners) => {
+                for owner in owners.iter() {
+                    *counts.entry(owner).or_default() += 1;
+                }
+            }
+            None => unowned += 1,
+        }
+    }
+    let mut owners: Vec<OwnerCount> =
+        counts.into_iter().map(|(o, n)| OwnerCount { owner: o.to_string(), files: n }).collect();
+    owners.sort_by(|a, b| b.files.cmp(&a.files));
+    let codeowners = &repo.config.codeowners;
+    let is_file = repo.all_files.iter().any(|f| f.rel_path == path);
+    let rules = if is_file {
+        codeowners.rules_for(path).into_iter().cloned().collect()
+    } else {
+        Vec::new()
+    };
+    OwnersReport {
+        source: codeowners.source.clone(),
+        path: path.to_string(),
+        files,
+        owners,
+        unowned,
+        rules,
+    }
+}
+
+/// Files owned by `owner` (case-insensitive, with or without the leading `@`).
+pub fn files_owned_by<'a>(repo: &'a RepoState, owner: &str) -> Vec<&'a str> {
+    let wanted = owner.trim_start_matches('@').to_lowercase();
+    repo.all_files
+        .iter()
+        .filter(|f| {
+            f.owners.as_ref().is_some_and(|owners| {
+                owners.iter().any(|o| o.trim_start_matches('@').to_lowercase() == wanted)
+            })
+        })
+        .map(|f| f.rel_path.as_str())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn last_matching_rule_wins_per_section() {
+        let owners = CodeOwners::parse(
+            "CODEOWNERS",
+            "# default\n\
+             *       @org/core\n\
+             *.md    @docs\n\
+             /src/auth/ @alice @org/security\n\
+             docs/*  @writers\n\
+             /src/auth/vendor.rs\n\
+             [Frontend] @org/web\n\
+             web/\n",
+        );
+        let of = |p: &str| owners.owners_of(p).map(|o| o.join(" "));
+        assert_eq!(of("src/main.rs").as_deref(), Some("@org/core"));
+        assert_eq!(of("src/auth/login.rs").as_deref(), Some("@alice @org/security"));
+        assert_eq!(of("lib/src/auth/x.rs").as_deref(), Some("@org/core"), "anchored");
+        assert_eq!(of("src/README.md").as_deref(), Some("@docs"));
+        assert_eq!(of("docs/intro.md").as_deref(), Some("@writers"));
+        assert_eq!(of("docs/guides/setup.md").as_deref(), Some("@docs"), "docs/* is shallow");
+        assert_eq!(of("src/auth/vendor.rs"), None, "no owners unsets");
+        assert_eq!(of("web/app.ts").as_deref(), Some("@org/core @org/web"));
+    }
+}
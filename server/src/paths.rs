@@ -0,0 +1,213 @@
+//! Central path resolution for file reads from MCP tools, the HTTP API, and budget allocation.
+//!
+//! Accepts plain relative paths or the `[repo] path` form printed by multi-repo tool output,
+//! applies the repo's `read_allow` / `read_deny` policy from `.codescope.toml`, and
+//! canonicalizes symlinks so a read can never leave the root of the repo it resolved to.
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+use crate::types::{validate_path, ExternalDir, RepoState, ServerState};
+
+// ---------------------------------------------------------------------------
+// Read policy
+// ---------------------------------------------------------------------------
+
+/// Compiled allow/deny globs, matched against repo-relative paths.
+///
+/// An empty allow list permits everything not denied. Deny always wins over allow.
+#[derive(Clone, Default)]
+pub struct ReadPolicy {
+    allow: Option<GlobSet>,
+    deny: Option<GlobSet>,
+    /// Reads may follow symlinks inside the repo to targets outside it (`follow_symlinks`).
+    follow_symlinks: bool,
+    /// `scan_dirs` outside the root, whose files are read through their `../` paths.
+    external: Vec<ExternalDir>,
+}
+
+impl ReadPolicy {
+    /// Compile allow and deny patterns. Invalid globs are skipped with a warning.
+    pub fn new(allow: &[String], deny: &[String]) -> Self {
+        Self {
+            allow: compile_globs(allow, "read_allow"),
+            deny: compile_globs(deny, "read_deny"),
+            follow_symlinks: false,
+            external: Vec::new(),
+        }
+    }
+
+    /// Let reads follow symlinks out of the repo root, as the scan does with
+    /// `follow_symlinks = true`.
+    pub fn with_follow_symlinks(mut self, follow: bool) -> Self {
+        self.follow_symlinks = follow;
+        self
+    }
+
+    /// Let reads reach the files of `scan_dirs` outside the root.
+    pub fn with_external_dirs(mut self, dirs: &[ExternalDir]) -> Self {
+        self.external = dirs.to_vec();
+        self
+    }
+
+    /// Whether a repo-relative path may be read under this policy.
+    pub fn permits(&self, rel_path: &str) -> bool {
+        if self.deny.as_ref().is_some_and(|d| d.is_match(rel_path)) {
+            return false;
+        }
+        self.allow.as_ref().is_none_or(|a| a.is_match(rel_path))
+    }
+}
+
+fn compile_globs(patterns: &[String], key: &str) -> Option<GlobSet> {
+    if patterns.is_empty() {
+        return None;
+    }
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        match Glob::new(pattern) {
+            Ok(glob) => {
+                builder.add(glob);
+            }
+            Err(e) => warn!(key, pattern = pattern.as_str(), "Invalid glob — ignoring: {e}"),
+        }
+    }
+    builder.build().ok()
+}
+
+// ---------------------------------------------------------------------------
+// Resolution
+// ---------------------------------------------------------------------------
+
+/// A path resolved to a repo and a canonical on-disk location.
+pub struct ResolvedPath<'a> {
+    pub repo: &'a RepoState,
+    /// Path relative to the repo root, without any `[repo]` prefix.
+    pub rel_path: String,
+    pub abs_path: PathBuf,
+    /// The path asked for, when it was the old path of a moved file ([`crate::renames`]).
+    pub renamed_from: Option<String>,
+}
+
+impl ResolvedPath<'_> {
+    /// Redirect note for a read through an old path.
+    pub fn rename_note(&self) -> Option<String> {
+        let from = self.renamed_from.as_deref()?;
+        Some(crate::renames::note(from, &self.rel_path))
+    }
+}
+
+/// Split a `[repo] path` string into its repo name and path. Plain paths have no repo.
+pub fn split_repo_prefix(spec: &str) -> (Option<&str>, &str) {
+    if let Some(rest) = spec.strip_prefix('[') {
+        if let Some((name, path)) = rest.split_once(']') {
+            if !name.is_empty() {
+                return (Some(name), path.trim_start());
+            }
+        }
+    }
+    (None, spec)
+}
+
+/// Resolve a repo-relative path under `root`, enforcing `policy`.
+///
+/// The policy is checked against both the requested path and the canonical target, so a
+/// symlink cannot be used to reach a denied file. Targets outside the root are refused
+/// unless the policy follows symlinks or they are inside an external `scan_dirs` entry,
+/// which is then held to the same rules as the root.
+pub fn resolve_in_repo(
+    root: &Path,
+    policy: &ReadPolicy,
+    rel_path: &str,
+) -> Result<PathBuf, String> {
+    if !policy.permits(rel_path) {
+        return Err("Access denied by read policy".to_string());
+    }
+    if let Some((dir, rest)) = policy.external.iter().find_map(|d| Some((d, d.strip(rel_path)?))) {
+        let canonical = validate_path(&dir.path, rest).map_err(str::to_string)?;
+        let target = canonical.strip_prefix(&dir.path).unwrap_or(&canonical);
+        let target = format!("{}/{}", dir.prefix, target.to_string_lossy().replace('\\', "/"));
+        if !policy.permits(&target) {
+            return Err("Access denied by read policy".to_string());
+        }
+        return Ok(canonical);
+    }
+    let canonical = match validate_path(root, rel_path) {
+        Ok(c) => c,
+        // `..` and absolute paths are already rejected, so only a symlink inside the repo
+        // can lead outside it
+        Err("Path traversal detected") if policy.follow_symlinks => {
+            root.join(rel_path).canonicalize().map_err(|_| "File not found".to_string())?
+        }
+        Err(e) => return Err(e.to_string()),
+    };
+    let root_canonical = root.canonicalize().map_err(|_| "Root not found".to_string())?;
+    let target = canonical.strip_prefix(&root_canonical).unwrap_or(&canonical);
+    if !policy.permits(&target.to_string_lossy().replace('\\', "/")) {
+        return Err("Access denied by read policy".to_string());
+    }
+    Ok(canonical)
+}
+
+/// Resolve a plain or `[repo] path` string against the registered repos.
+///
+/// A `[repo]` prefix selects that repo; otherwise `fallback` is used. The result is always
+/// inside the selected repo's root and permitted by its read policy. A path that no longer
+/// exists but was moved in an earlier scan generation resolves to the file's current path.
+pub fn resolve<'a>(
+    state: &'a ServerState,
+    spec: &str,
+    fallback: &'a RepoState,
+) -> Result<ResolvedPath<'a>, String> {
+    let (repo_name, rel_path) = split_repo_prefix(spec);
+    let repo = match repo_name {
+        Some(name) => state.repos.get(name).ok_or_else(|| {
+            let available: Vec<&str> = state.repos.keys().map(|k| k.as_str()).collect();
+            format!("Unknown repo '{name}'. Available: {}", available.join(", "))
+        })?,
+        None => fallback,
+    };
+    let policy = &repo.config.read_policy;
+    match resolve_in_repo(&repo.root, policy, rel_path) {
+        Ok(abs_path) => {
+            Ok(ResolvedPath { repo, rel_path: rel_path.to_string(), abs_path, renamed_from: None })
+        }
+        Err(e) if e == "File not found" => {
+            let Some(current) = repo.renames.resolve(rel_path) else { return Err(e) };
+            let abs_path = resolve_in_repo(&repo.root, policy, current)?;
+            Ok(ResolvedPath {
+                repo,
+                rel_path: current.to_string(),
+                abs_path,
+                renamed_from: Some(rel_path.to_string()),
+            })
+        }
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_repo_prefix_handles_both_forms() {
+        assert_eq!(split_repo_prefix("[api] src/main.rs"), (Some("api"), "src/main.rs"));
+        assert_eq!(split_repo_prefix("src/main.rs"), (None, "src/main.rs"));
+        assert_eq!(split_repo_prefix("[] src/main.rs"), (None, "[] src/main.rs"));
+    }
+
+    #[test]
+    fn policy_deny_wins_over_allow() {
+        let policy = ReadPolicy::new(
+            &["src/**".to_string()],
+            &["**/*.pem".to_string(), "src/secrets/**".to_string()],
+        );
+        assert!(policy.permits("src/main.rs"));
+        assert!(!policy.permits("src/secrets/key.rs"));
+        assert!(!policy.permits("src/tls/cert.pem"));
+        assert!(!policy.permits("README.md"));
+        assert!(ReadPolicy::default().permits("anything/at/all"));
+    }
+}
@@ -0,0 +1,188 @@
+//! User-defined MCP prompts from `[prompts.<name>]` in `.codescope.toml` and from
+//! `~/.codescope/prompts/<name>.toml`.
+//!
+//! ```toml
+//! [prompts.explain]
+//! description = "Explain how a feature works"
+//! arguments = ["topic", "audience?"]     # a trailing `?` marks an optional argument
+//! template = """
+//! Explain how {{topic}} works in this codebase for {{audience}}.
+//!
+//! Relevant code:
+//! {{search:{{topic}}}}
+//!
+//! Conventions to follow:
+//! {{conventions}}
+//! """
+//! ```
+//!
+//! A file in `~/.codescope/prompts/` holds the keys of one prompt, named by the file stem.
+//! Arguments may also be tables (`{ name = "topic", description = "...", required = true }`).
+//!
+//! `prompts/list` offers every prompt. `prompts/get` first fills `{{argument}}` placeholders
+//! (missing optional ones become empty), then replaces `{{search:QUERY}}` with `cs_search`
+//! results and `{{conventions}}` with the `cs_conventions` report, both for the repo that
+//! defines the prompt (global prompts search every repo and use the default repo's
+//! conventions). A repo's prompt hides a global prompt of the same name.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use tracing::warn;
+
+use crate::protocol::{Prompt, PromptArgument};
+
+/// A prompt template and the arguments it takes.
+#[derive(Clone, Debug, Default)]
+pub struct PromptDef {
+    pub description: Option<String>,
+    pub arguments: Vec<PromptArgument>,
+    pub template: String,
+}
+
+impl PromptDef {
+    /// The `prompts/list` entry for this prompt.
+    pub fn to_prompt(&self, name: &str) -> Prompt {
+        Prompt {
+            name: name.to_string(),
+            description: self.description.clone(),
+            arguments: self.arguments.clone(),
+        }
+    }
+
+    /// Required arguments absent from `values`.
+    pub fn missing_arguments(&self, values: &BTreeMap<String, String>) -> Vec<&str> {
+        self.arguments
+            .iter()
+            .filter(|a| a.required && values.get(&a.name).is_none_or(|v| v.is_empty()))
+            .map(|a| a.name.as_str())
+            .collect()
+    }
+
+    /// The template with `{{argument}}` placeholders filled and directives resolved by
+    /// `resolve`, which gets `("search", query)` or `("conventions", "")` and returns the
+    /// replacement text.
+    pub fn render(
+        &self,
+        values: &BTreeMap<String, String>,
+        mut resolve: impl FnMut(&str, &str) -> String,
+    ) -> String {
+        let mut text = self.template.clone();
+        for arg in &self.arguments {
+            let value = values.get(&arg.name).map(String::as_str).unwrap_or("");
+            text = text.replace(&format!("{{{{{}}}}}", arg.name), value);
+        }
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text.as_str();
+        while let Some(start) = rest.find("{{") {
+            let Some(len) = rest[start..].find("}}") else { break };
+            let directive = rest[start + 2..start + len].trim();
+            out.push_str(&rest[..start]);
+            match directive.split_once(':') {
+                Some(("search", query)) => out.push_str(&resolve("search", query.trim())),
+                None if directive == "conventions" => out.push_str(&resolve("conventions", "")),
+                _ => out.push_str(&rest[start..start + len + 2]),
+            }
+            rest = &rest[start + len + 2..];
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+/// Parse the `[prompts]` table: one sub-table per prompt. Entries without a `template` are
+/// skipped with a warning.
+pub fn parse_prompts(table: &toml::Table) -> BTreeMap<String, PromptDef> {
+    let mut prompts = BTreeMap::new();
+    for (name, value) in table {
+        match value.as_table().and_then(parse_prompt) {
+            Some(prompt) => {
+                prompts.insert(name.clone(), prompt);
+            }
+            None => warn!(prompt = name.as_str(), "[prompts] entries need a template — ignoring"),
+        }
+    }
+    prompts
+}
+
+fn parse_prompt(table: &toml::Table) -> Option<PromptDef> {
+    let template = table.get("template")?.as_str()?.to_string();
+    let arguments = table
+        .get("arguments")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|arg| match arg {
+            toml::Value::String(spec) => {
+                let (name, required) = match spec.strip_suffix('?') {
+                    Some(name) => (name, false),
+                    None => (spec.as_str(), true),
+                };
+                Some(PromptArgument { name: name.to_string(), description: None, required })
+            }
+            toml::Value::Table(t) => Some(PromptArgument {
+                name: t.get("name")?.as_str()?.to_string(),
+                description: t.get("description").and_then(|d| d.as_str()).map(String::from),
+                required: t.get("required").and_then(|r| r.as_bool()).unwrap_or(true),
+            }),
+            _ => None,
+        })
+        .collect();
+    let description = table.get("description").and_then(|d| d.as_str()).map(String::from);
+    Some(PromptDef { description, arguments, template })
+}
+
+/// Prompts in `<dir>/*.toml`, one per file.
+pub fn load_dir(dir: &Path) -> BTreeMap<String, PromptDef> {
+    let mut prompts = BTreeMap::new();
+    let Ok(entries) = std::fs::read_dir(dir) else { return prompts };
+    for path in entries.flatten().map(|e| e.path()) {
+        if path.extension().and_then(|e| e.to_str()) != Some("toml") {
+            continue;
+        }
+        let Some(name) = path.file_stem().and_then(|s| s.to_str()) else { continue };
+        let table = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| content.parse::<toml::Table>().ok());
+        match table.as_ref().and_then(parse_prompt) {
+            Some(prompt) => {
+                prompts.insert(name.to_string(), prompt);
+            }
+            None => {
+                warn!(path = %path.display(), "Invalid prompt file (needs a template) — ignoring")
+            }
+        }
+    }
+    prompts
+}
+
+/// Global prompts from `~/.codescope/prompts/`.
+pub fn load_global() -> BTreeMap<String, PromptDef> {
+    crate::config_dir().map(|d| load_dir(&d.join("prompts"))).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arguments_fill_before_directives_resolve() {
+        let table: toml::Table = toml::from_str(
+            r#"
+            broken = { description = "no template" }
+
+            [explain]
+            arguments = ["topic", "audience?"]
+            template = "{{topic}} for {{audience}}|{{search:{{topic}} flow}}|{{conventions}}|{{x}}"
+            "#,
+        )
+        .unwrap();
+        let prompts = parse_prompts(&table);
+        assert_eq!(prompts.keys().collect::<Vec<_>>(), ["explain"]);
+        let explain = &prompts["explain"];
+        assert_eq!(explain.missing_arguments(&BTreeMap::new()), ["topic"]);
+
+        let values = BTreeMap::from([("topic".to_string(), "auth".to_string())]);
+        let text = explain.render(&values, |kind, arg| format!("<{kind}:{arg}>"));
+        assert_eq!(text, "auth for |<search:auth flow>|<conventions:>|{{x}}");
+    }
+}
@@ -0,0 +1,557 @@
+//! Typed MCP JSON-RPC messages.
+//!
+//! Requests are parsed into [`Request`] and then into the params struct for their method;
+//! results are built as structs and serialized into a [`Response`]. Only the fields the
+//! server reads or writes are typed. Unknown fields are ignored on input, and free-form
+//! payloads (tool arguments, tool schemas, structured content) stay `serde_json::Value`.
+//!
+//! Field names follow the MCP schema; Rust names are snake_case with `camelCase` renames.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+pub const JSONRPC_VERSION: &str = "2.0";
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+/// Server-defined: a request other than `initialize`/`ping` before initialization.
+pub const NOT_INITIALIZED: i64 = -32002;
+/// MCP: `resources/read` for a URI the server does not have.
+pub const RESOURCE_NOT_FOUND: i64 = -32002;
+
+// ---------------------------------------------------------------------------
+// Envelope
+// ---------------------------------------------------------------------------
+
+/// A JSON-RPC request id. `null` ids are not accepted; such a message is a notification.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RequestId {
+    Number(i64),
+    String(String),
+}
+
+/// An incoming request or notification. `params` is parsed per method with
+/// [`Request::params`].
+#[derive(Debug, Clone, Deserialize)]
+pub struct Request {
+    #[serde(default)]
+    pub jsonrpc: String,
+    /// `None` for notifications.
+    #[serde(default)]
+    pub id: Option<RequestId>,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+impl Request {
+    pub fn is_notification(&self) -> bool {
+        self.id.is_none() || self.method.starts_with("notifications/")
+    }
+
+    /// Params as `T`. Omitted params parse like an empty object.
+    pub fn params<T: DeserializeOwned>(&self) -> Result<T, String> {
+        let params =
+            if self.params.is_null() { Value::Object(Map::new()) } else { self.params.clone() };
+        serde_json::from_value(params).map_err(|e| format!("Invalid params: {e}"))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorObject {
+    pub code: i64,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl ErrorObject {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self { code, message: message.into(), data: None }
+    }
+
+    pub fn with_data(mut self, data: Value) -> Self {
+        self.data = Some(data);
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Outcome {
+    #[serde(rename = "result")]
+    Result(Value),
+    #[serde(rename = "error")]
+    Error(ErrorObject),
+}
+
+/// A response: `{"jsonrpc", "id", "result"}` or `{"jsonrpc", "id", "error"}`. The id is
+/// `None` (serialized as `null`) only when the request's id could not be read.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Response {
+    pub jsonrpc: String,
+    pub id: Option<RequestId>,
+    #[serde(flatten)]
+    pub outcome: Outcome,
+}
+
+impl Response {
+    pub fn result(id: Option<RequestId>, result: impl Serialize) -> Self {
+        let result = serde_json::to_value(result).unwrap_or(Value::Null);
+        Self { jsonrpc: JSONRPC_VERSION.to_string(), id, outcome: Outcome::Result(result) }
+    }
+
+    pub fn error(id: Option<RequestId>, error: ErrorObject) -> Self {
+        Self { jsonrpc: JSONRPC_VERSION.to_string(), id, outcome: Outcome::Error(error) }
+    }
+
+    pub fn to_value(&self) -> Value {
+        serde_json::to_value(self).unwrap_or(Value::Null)
+    }
+}
+
+/// An empty result object, e.g. for `ping`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct EmptyResult {}
+
+// ---------------------------------------------------------------------------
+// initialize
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Implementation {
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InitializeParams {
+    #[serde(default)]
+    pub protocol_version: String,
+    #[serde(default)]
+    pub capabilities: ClientCapabilities,
+    #[serde(default)]
+    pub client_info: Option<Implementation>,
+}
+
+/// Client capabilities. Only `experimental.codescope` is read; the standard capabilities
+/// are ignored, and some clients send them in non-standard shapes.
+#[derive(Debug, Default, Deserialize)]
+pub struct ClientCapabilities {
+    #[serde(default)]
+    pub experimental: ExperimentalCapabilities,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ExperimentalCapabilities {
+    #[serde(default)]
+    pub codescope: Option<CodescopeClientOptions>,
+}
+
+/// `capabilities.experimental.codescope` in `initialize`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CodescopeClientOptions {
+    /// Session default output profile (`compact`, `standard`, `verbose`).
+    #[serde(default)]
+    pub output_profile: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InitializeResult {
+    pub protocol_version: String,
+    pub capabilities: ServerCapabilities,
+    pub server_info: Implementation,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instructions: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ServerCapabilities {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tools: Option<ToolsCapability>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resources: Option<ResourcesCapability>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompts: Option<PromptsCapability>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolsCapability {
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub list_changed: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourcesCapability {
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub subscribe: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub list_changed: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptsCapability {
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub list_changed: bool,
+}
+
+// ---------------------------------------------------------------------------
+// tools
+// ---------------------------------------------------------------------------
+
+/// `tools/list` result. Tool definitions are built by the tool registry as JSON.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListToolsResult {
+    pub tools: Vec<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CallToolParams {
+    pub name: String,
+    /// Omitted arguments are an empty object.
+    #[serde(default = "empty_object")]
+    pub arguments: Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ContentBlock {
+    Text { text: String },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallToolResult {
+    pub content: Vec<ContentBlock>,
+    #[serde(default)]
+    pub is_error: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub structured_content: Option<Value>,
+    #[serde(rename = "_meta", default, skip_serializing_if = "Map::is_empty")]
+    pub meta: Map<String, Value>,
+}
+
+// ---------------------------------------------------------------------------
+// resources
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Resource {
+    pub uri: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListResourcesResult {
+    pub resources: Vec<Resource>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceTemplate {
+    pub uri_template: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListResourceTemplatesResult {
+    pub resource_templates: Vec<ResourceTemplate>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReadResourceParams {
+    pub uri: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceContents {
+    pub uri: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    pub text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReadResourceResult {
+    pub contents: Vec<ResourceContents>,
+}
+
+// ---------------------------------------------------------------------------
+// prompts
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptArgument {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Prompt {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub arguments: Vec<PromptArgument>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListPromptsResult {
+    pub prompts: Vec<Prompt>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GetPromptParams {
+    pub name: String,
+    #[serde(default)]
+    pub arguments: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PromptMessage {
+    pub role: String,
+    pub content: ContentBlock,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GetPromptResult {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub messages: Vec<PromptMessage>,
+}
+
+fn empty_object() -> Value {
+    Value::Object(Map::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn request(raw: &str) -> Request {
+        serde_json::from_str(raw).unwrap_or_else(|e| panic!("{e}: {raw}"))
+    }
+
+    // Payloads below are captured from real clients, trimmed only of whitespace.
+
+    #[test]
+    fn initialize_params_from_captured_clients() {
+        let claude = request(
+            r#"{"method":"initialize","params":{"protocolVersion":"2025-06-18","capabilities":{"roots":{}},"clientInfo":{"name":"claude-code","version":"1.0.98"}},"jsonrpc":"2.0","id":0}"#,
+        );
+        let inspector = request(
+            r#"{"jsonrpc":"2.0","id":0,"method":"initialize","params":{"protocolVersion":"2025-11-25","capabilities":{"sampling":{},"elicitation":{},"roots":{"listChanged":true}},"clientInfo":{"name":"inspector-client","version":"0.17.0"}}}"#,
+        );
+        // Cursor sends booleans where the schema has objects
+        let cursor = request(
+            r#"{"jsonrpc":"2.0","id":0,"method":"initialize","params":{"protocolVersion":"2025-06-18","capabilities":{"tools":true,"prompts":false,"resources":true,"logging":false,"roots":{"listChanged":false}},"clientInfo":{"name":"cursor-vscode","version":"1.0.0"}}}"#,
+        );
+        let codescope = request(
+            r#"{"jsonrpc":"2.0","id":"init-1","method":"initialize","params":{"protocolVersion":"2025-11-25","capabilities":{"experimental":{"codescope":{"outputProfile":"compact"}}},"clientInfo":{"name":"eval-harness","version":"0.3.0"}}}"#,
+        );
+
+        for (req, version, client) in [
+            (&claude, "2025-06-18", "claude-code"),
+            (&inspector, "2025-11-25", "inspector-client"),
+            (&cursor, "2025-06-18", "cursor-vscode"),
+            (&codescope, "2025-11-25", "eval-harness"),
+        ] {
+            assert_eq!(req.method, "initialize");
+            assert!(!req.is_notification());
+            let params: InitializeParams = req.params().unwrap();
+            assert_eq!(params.protocol_version, version);
+            assert_eq!(params.client_info.unwrap().name, client);
+        }
+        assert_eq!(claude.id, Some(RequestId::Number(0)));
+        assert_eq!(codescope.id, Some(RequestId::String("init-1".into())));
+        let params: InitializeParams = codescope.params().unwrap();
+        let options = params.capabilities.experimental.codescope.unwrap();
+        assert_eq!(options.output_profile.as_deref(), Some("compact"));
+        let params: InitializeParams = cursor.params().unwrap();
+        assert!(params.capabilities.experimental.codescope.is_none());
+    }
+
+    #[test]
+    fn notifications_have_no_id_or_notification_method() {
+        let initialized = request(r#"{"method":"notifications/initialized","jsonrpc":"2.0"}"#);
+        let cancelled = request(
+            r#"{"jsonrpc":"2.0","method":"notifications/cancelled","params":{"requestId":7,"reason":"Request timed out"}}"#,
+        );
+        let ping = request(r#"{"jsonrpc":"2.0","id":12,"method":"ping"}"#);
+        assert!(initialized.is_notification());
+        assert!(cancelled.is_notification());
+        assert!(!ping.is_notification());
+        assert!(ping.params.is_null());
+        assert!(ping.params::<EmptyResult>().is_ok());
+    }
+
+    #[test]
+    fn call_tool_params_from_captured_clients() {
+        let claude = request(
+            r#"{"method":"tools/call","params":{"name":"cs_search","arguments":{"query":"token budget","limit":5},"_meta":{"progressToken":3}},"jsonrpc":"2.0","id":3}"#,
+        );
+        let params: CallToolParams = claude.params().unwrap();
+        assert_eq!(params.name, "cs_search");
+        assert_eq!(params.arguments, json!({ "query": "token budget", "limit": 5 }));
+
+        let no_args = request(
+            r#"{"jsonrpc":"2.0","id":4,"method":"tools/call","params":{"name":"cs_status"}}"#,
+        );
+        let params: CallToolParams = no_args.params().unwrap();
+        assert_eq!(params.arguments, json!({}));
+
+        let no_name = request(r#"{"jsonrpc":"2.0","id":5,"method":"tools/call","params":{}}"#);
+        let err = no_name.params::<CallToolParams>().unwrap_err();
+        assert!(err.contains("name"), "{err}");
+    }
+
+    #[test]
+    fn resource_and_prompt_params_from_captured_clients() {
+        let read = request(
+            r#"{"jsonrpc":"2.0","id":9,"method":"resources/read","params":{"uri":"codescope://app/conventions"}}"#,
+        );
+        let params: ReadResourceParams = read.params().unwrap();
+        assert_eq!(params.uri, "codescope://app/conventions");
+        let list = request(r#"{"jsonrpc":"2.0","id":8,"method":"resources/list","params":{}}"#);
+        assert_eq!(list.method, "resources/list");
+
+        let get = request(
+            r#"{"jsonrpc":"2.0","id":10,"method":"prompts/get","params":{"name":"review","arguments":{"path":"src/lib.rs"}}}"#,
+        );
+        let params: GetPromptParams = get.params().unwrap();
+        assert_eq!(params.name, "review");
+        assert_eq!(params.arguments.get("path").map(String::as_str), Some("src/lib.rs"));
+    }
+
+    #[test]
+    fn results_serialize_to_schema_shape() {
+        let init = InitializeResult {
+            protocol_version: "2025-11-25".into(),
+            capabilities: ServerCapabilities {
+                tools: Some(ToolsCapability { list_changed: true }),
+                resources: Some(ResourcesCapability::default()),
+                prompts: None,
+            },
+            server_info: Implementation { name: "codescope".into(), version: "1.2.3".into() },
+            instructions: Some("Use cs_search.".into()),
+        };
+        let response = Response::result(Some(RequestId::Number(0)), &init).to_value();
+        assert_eq!(
+            response,
+            json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": {
+                    "protocolVersion": "2025-11-25",
+                    "capabilities": { "tools": { "listChanged": true }, "resources": {} },
+                    "serverInfo": { "name": "codescope", "version": "1.2.3" },
+                    "instructions": "Use cs_search."
+                }
+            })
+        );
+
+        let mut meta = Map::new();
+        meta.insert("codescope/generation".into(), json!(4));
+        let call = CallToolResult {
+            content: vec![ContentBlock::Text { text: "ok".into() }],
+            is_error: false,
+            structured_content: Some(json!({ "hits": [] })),
+            meta,
+        };
+        assert_eq!(
+            serde_json::to_value(&call).unwrap(),
+            json!({
+                "content": [{ "type": "text", "text": "ok" }],
+                "isError": false,
+                "structuredContent": { "hits": [] },
+                "_meta": { "codescope/generation": 4 }
+            })
+        );
+
+        let read = ReadResourceResult {
+            contents: vec![ResourceContents {
+                uri: "codescope://app/conventions".into(),
+                mime_type: Some("application/json".into()),
+                text: "{}".into(),
+            }],
+        };
+        assert_eq!(
+            serde_json::to_value(&read).unwrap(),
+            json!({ "contents": [{
+                "uri": "codescope://app/conventions", "mimeType": "application/json", "text": "{}"
+            }] })
+        );
+        let templates = ListResourceTemplatesResult {
+            resource_templates: vec![ResourceTemplate {
+                uri_template: "codescope://{repo}/conventions".into(),
+                name: "Conventions".into(),
+                description: None,
+                mime_type: None,
+            }],
+        };
+        assert_eq!(
+            serde_json::to_value(&templates).unwrap(),
+            json!({ "resourceTemplates": [{
+                "uriTemplate": "codescope://{repo}/conventions", "name": "Conventions"
+            }] })
+        );
+        assert_eq!(
+            serde_json::to_value(ListPromptsResult { prompts: vec![] }).unwrap(),
+            json!({ "prompts": [] })
+        );
+    }
+
+    #[test]
+    fn responses_round_trip() {
+        let ok = Response::result(Some(RequestId::String("a".into())), EmptyResult {});
+        assert_eq!(ok.to_value(), json!({ "jsonrpc": "2.0", "id": "a", "result": {} }));
+        let err = Response::error(
+            Some(RequestId::Number(9)),
+            ErrorObject::new(RESOURCE_NOT_FOUND, "Unknown repo 'x'")
+                .with_data(json!({ "uri": "codescope://x/conventions" })),
+        );
+        let value = err.to_value();
+        assert_eq!(
+            value,
+            json!({
+                "jsonrpc": "2.0",
+                "id": 9,
+                "error": {
+                    "code": -32002,
+                    "message": "Unknown repo 'x'",
+                    "data": { "uri": "codescope://x/conventions" }
+                }
+            })
+        );
+        assert_eq!(serde_json::from_value::<Response>(value).unwrap(), err);
+        let parse = Response::error(None, ErrorObject::new(PARSE_ERROR, "Parse error"));
+        assert_eq!(parse.to_value()["id"], Value::Null);
+    }
+}
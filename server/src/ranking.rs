@@ -0,0 +1,199 @@
+//! Search ranking boosts from `[ranking]` in `.codescope.toml`.
+//!
+//! ```toml
+//! [ranking]
+//! ext = { rs = 1.5, lock = 0.1 }
+//! path = { "src/" = 1.3, "examples/" = 0.5 }
+//! recency = 0.5                # up to +50% for files committed just now
+//! recency_half_life_days = 30
+//! ```
+//!
+//! A file's multiplier is its extension boost, times the boost of its longest matching
+//! path prefix, times `1 + recency * 0.5^(age / half_life)`, where age is the time since
+//! the last commit that touched the file (edits seen by the watcher count as now), times
+//! the file's [`crate::noise`] penalty. Fuzzy
+//! filename scores, grep relevance scores, and the semantic side of fused `cs_search`
+//! scores are all scaled by it, in the MCP tools and the HTTP API alike.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+/// Commits walked back from HEAD to find each file's last change.
+const RECENCY_MAX_COMMITS: usize = 5000;
+
+/// Default `recency_half_life_days`.
+const DEFAULT_HALF_LIFE_DAYS: f64 = 30.0;
+
+/// `[ranking]` settings from `.codescope.toml`.
+#[derive(Clone, Debug)]
+pub struct RankingConfig {
+    /// Extension (without dot) → multiplier.
+    pub ext: HashMap<String, f64>,
+    /// Path prefix → multiplier, longest prefix first.
+    pub path: Vec<(String, f64)>,
+    /// Maximum extra weight for a file committed just now; 0 disables recency.
+    pub recency: f64,
+    pub half_life_days: f64,
+}
+
+impl Default for RankingConfig {
+    fn default() -> Self {
+        Self {
+            ext: HashMap::new(),
+            path: Vec::new(),
+            recency: 0.0,
+            half_life_days: DEFAULT_HALF_LIFE_DAYS,
+        }
+    }
+}
+
+fn as_weight(key: &str, value: &toml::Value) -> Option<f64> {
+    let weight = value.as_float().or_else(|| value.as_integer().map(|i| i as f64));
+    match weight {
+        Some(w) if w >= 0.0 && w.is_finite() => Some(w),
+        _ => {
+            warn!(key, value = %value, "[ranking] weights must be non-negative numbers — ignoring");
+            None
+        }
+    }
+}
+
+impl RankingConfig {
+    /// Parse the `[ranking]` table. Invalid weights are skipped with a warning.
+    pub fn from_table(table: &toml::Table) -> Self {
+        let mut config = Self::default();
+        if let Some(exts) = table.get("ext").and_then(|v| v.as_table()) {
+            for (ext, value) in exts {
+                if let Some(w) = as_weight(ext, value) {
+                    config.ext.insert(ext.trim_start_matches('.').to_lowercase(), w);
+                }
+            }
+        }
+        if let Some(paths) = table.get("path").and_then(|v| v.as_table()) {
+            for (prefix, value) in paths {
+                if let Some(w) = as_weight(prefix, value) {
+                    config.path.push((prefix.trim_start_matches("./").to_string(), w));
+                }
+            }
+            config.path.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then(a.0.cmp(&b.0)));
+        }
+        if let Some(w) = table.get("recency").and_then(|v| as_weight("recency", v)) {
+            config.recency = w;
+        }
+        if let Some(days) =
+            table.get("recency_half_life_days").and_then(|v| as_weight("recency_half_life_days", v))
+        {
+            if days > 0.0 {
+                config.half_life_days = days;
+            }
+        }
+        config
+    }
+
+    /// Whether every file gets multiplier 1.
+    pub fn is_neutral(&self) -> bool {
+        self.ext.is_empty() && self.path.is_empty() && self.recency == 0.0
+    }
+
+    /// Extension and path-prefix multiplier for a file.
+    pub fn static_boost(&self, rel_path: &str) -> f64 {
+        let filename = rel_path.rsplit('/').next().unwrap_or(rel_path);
+        let ext_boost = match filename.rsplit_once('.') {
+            Some((stem, ext)) if !stem.is_empty() => {
+                self.ext.get(&ext.to_lowercase()).copied().unwrap_or(1.0)
+            }
+            _ => 1.0,
+        };
+        let path_boost = self
+            .path
+            .iter()
+            .find(|(prefix, _)| rel_path.starts_with(prefix.as_str()))
+            .map_or(1.0, |(_, w)| *w);
+        ext_boost * path_boost
+    }
+}
+
+/// Seconds since the Unix epoch.
+pub fn now_secs() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0)
+}
+
+/// Last commit time per file, when `[ranking] recency` is enabled. Empty otherwise or
+/// outside a git repository.
+pub fn scan_commit_times(root: &Path, config: &RankingConfig) -> HashMap<String, i64> {
+    if config.recency == 0.0 {
+        return HashMap::new();
+    }
+    crate::git::last_commit_times(root, RECENCY_MAX_COMMITS).unwrap_or_default()
+}
+
+/// Per-query ranking multipliers for one repo.
+pub struct Ranker<'a> {
+    config: &'a RankingConfig,
+    noise: &'a crate::noise::NoiseConfig,
+    commit_times: &'a HashMap<String, i64>,
+    now: i64,
+}
+
+impl<'a> Ranker<'a> {
+    pub fn new(repo: &'a crate::types::RepoState) -> Self {
+        Self {
+            config: &repo.config.ranking,
+            noise: &repo.config.noise,
+            commit_times: &repo.commit_times,
+            now: now_secs(),
+        }
+    }
+
+    /// Multiplier for a file's search score.
+    pub fn boost(&self, rel_path: &str) -> f64 {
+        if self.config.is_neutral() && self.noise.is_empty() {
+            return 1.0;
+        }
+        let mut boost = self.config.static_boost(rel_path) * self.noise.boost(rel_path);
+        if self.config.recency > 0.0 {
+            if let Some(&time) = self.commit_times.get(rel_path) {
+                let age_days = (self.now - time).max(0) as f64 / 86400.0;
+                boost *=
+                    1.0 + self.config.recency * 0.5f64.powf(age_days / self.config.half_life_days);
+            }
+        }
+        boost
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boosts_combine_extension_longest_prefix_and_recency() {
+        let table: toml::Table = toml::from_str(
+            r#"
+            ext = { rs = 2, ".lock" = 0.1, md = "high" }
+            path = { "src/" = 1.5, "src/gen/" = 0.5, "examples/" = 0.25 }
+            recency = 1.0
+            recency_half_life_days = 10
+            "#,
+        )
+        .unwrap();
+        let config = RankingConfig::from_table(&table);
+        assert_eq!(config.static_boost("src/main.rs"), 3.0);
+        assert_eq!(config.static_boost("src/gen/api.RS"), 1.0);
+        assert_eq!(config.static_boost("Cargo.lock"), 0.1);
+        assert_eq!(config.static_boost("README.md"), 1.0, "invalid weight ignored");
+
+        let now = now_secs();
+        let times = HashMap::from([
+            ("docs/new.txt".to_string(), now),
+            ("docs/old.txt".to_string(), now - 10 * 86400),
+        ]);
+        let noise = crate::noise::NoiseConfig::default();
+        let ranker = Ranker { config: &config, noise: &noise, commit_times: &times, now };
+        assert_eq!(ranker.boost("docs/new.txt"), 2.0);
+        assert_eq!(ranker.boost("docs/old.txt"), 1.5);
+        assert_eq!(ranker.boost("docs/untracked.txt"), 1.0);
+    }
+}
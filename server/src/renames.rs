@@ -0,0 +1,125 @@
+//! Renamed files across scan generations.
+//!
+//! When a new generation drops a path and adds another with the same content hash, the old
+//! path is recorded as an alias of the new one. A directory rename moves each file under it,
+//! so every file gets its own alias. `cs_read` and the HTTP file endpoints resolve an alias
+//! to the current path with a redirect note, which keeps paths an agent picked up earlier in
+//! a session (read receipts, `cs_imports` results, earlier search hits) usable after a move.
+//!
+//! Only unambiguous moves are recorded: a hash shared by several removed or several added
+//! files (empty `__init__.py` files, copied fixtures) is skipped.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::types::RepoState;
+
+/// Old path → current path of files moved since the repo was first scanned.
+#[derive(Clone, Debug, Default)]
+pub struct RenameMap {
+    aliases: HashMap<String, String>,
+}
+
+impl RenameMap {
+    /// Current path of a file that used to live at `rel_path`.
+    pub fn resolve(&self, rel_path: &str) -> Option<&str> {
+        self.aliases.get(rel_path).map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.aliases.is_empty()
+    }
+
+    /// Record the moves among `removed` and `added` files (path and content hash). Earlier
+    /// aliases of a moved file follow it, aliases of a path that exists again are dropped,
+    /// and so are aliases of files that were deleted. Returns the number of moves found.
+    pub fn record(&mut self, removed: &[(String, u64)], added: &[(String, u64)]) -> usize {
+        let empty = xxhash_rust::xxh3::xxh3_64(b"");
+        let mut by_hash: HashMap<u64, (Vec<&str>, Vec<&str>)> = HashMap::new();
+        for (path, hash) in removed {
+            by_hash.entry(*hash).or_default().0.push(path);
+        }
+        for (path, hash) in added {
+            by_hash.entry(*hash).or_default().1.push(path);
+        }
+        let moves: HashMap<&str, &str> = by_hash
+            .into_iter()
+            .filter(|(hash, _)| *hash != 0 && *hash != empty)
+            .filter_map(|(_, (from, to))| match (from.as_slice(), to.as_slice()) {
+                ([from], [to]) => Some((*from, *to)),
+                _ => None,
+            })
+            .collect();
+
+        for (path, _) in added {
+            self.aliases.remove(path);
+        }
+        self.aliases.retain(|_, current| {
+            if let Some(to) = moves.get(current.as_str()) {
+                *current = to.to_string();
+                true
+            } else {
+                !removed.iter().any(|(path, _)| path == current)
+            }
+        });
+        for (from, to) in &moves {
+            self.aliases.insert(from.to_string(), to.to_string());
+        }
+        moves.len()
+    }
+
+    /// Carry the aliases of `old` into `new`, a later generation of the same repo, adding
+    /// the moves between the two.
+    pub fn carry_over(old: &RepoState, new: &mut RepoState) {
+        let old_files: HashMap<&str, u64> =
+            old.all_files.iter().map(|f| (f.rel_path.as_str(), f.content_hash)).collect();
+        let new_paths: HashSet<&str> = new.all_files.iter().map(|f| f.rel_path.as_str()).collect();
+        let removed: Vec<(String, u64)> = old_files
+            .iter()
+            .filter(|(path, _)| !new_paths.contains(*path))
+            .map(|(path, hash)| (path.to_string(), *hash))
+            .collect();
+        let added: Vec<(String, u64)> = new
+            .all_files
+            .iter()
+            .filter(|f| !old_files.contains_key(f.rel_path.as_str()))
+            .map(|f| (f.rel_path.clone(), f.content_hash))
+            .collect();
+        let mut renames = old.renames.clone();
+        renames.record(&removed, &added);
+        new.renames = renames;
+    }
+}
+
+/// Redirect note for a read of `from` served from `to`.
+pub fn note(from: &str, to: &str) -> String {
+    format!("[renamed: {from} → {to}]")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(list: &[(&str, u64)]) -> Vec<(String, u64)> {
+        list.iter().map(|(p, h)| (p.to_string(), *h)).collect()
+    }
+
+    #[test]
+    fn moves_are_matched_by_hash_and_chained() {
+        let mut map = RenameMap::default();
+        let found = map.record(
+            &files(&[("src/a.rs", 1), ("src/b.rs", 2), ("src/x.rs", 7), ("src/y.rs", 7)]),
+            &files(&[("lib/a.rs", 1), ("lib/b.rs", 3), ("lib/x.rs", 7)]),
+        );
+        assert_eq!(found, 1, "only a.rs moved unambiguously");
+        assert_eq!(map.resolve("src/a.rs"), Some("lib/a.rs"));
+        assert_eq!(map.resolve("src/b.rs"), None);
+
+        map.record(&files(&[("lib/a.rs", 1)]), &files(&[("core/a.rs", 1)]));
+        assert_eq!(map.resolve("src/a.rs"), Some("core/a.rs"));
+        assert_eq!(map.resolve("lib/a.rs"), Some("core/a.rs"));
+
+        // The old path is back, and the file it pointed at is gone
+        map.record(&files(&[("core/a.rs", 1)]), &files(&[("src/a.rs", 9)]));
+        assert!(map.is_empty(), "{map:?}");
+    }
+}
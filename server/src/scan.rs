@@ -7,7 +7,8 @@ use crate::fuzzy::char_bitmask;
 use crate::types::*;
 use ignore::WalkBuilder;
 use rayon::prelude::*;
-use std::collections::{BTreeMap, HashSet};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::path::Path;
 use std::sync::Mutex;
@@ -82,11 +83,16 @@ pub fn describe(rel_path: &str) -> String {
 }
 
 /// Derive a category path (breadcrumb trail) from a file's directory, stripping noise dirs and scan prefixes.
+/// Files of a workspace member are categorized under the member's package name.
 pub fn get_category_path(rel_path: &str, config: &ScanConfig) -> Vec<String> {
+    let dir = rel_path.rsplit_once('/').map_or("", |(d, _)| d);
+    if let Some(parts) = workspace_category(dir, config) {
+        return parts;
+    }
     let mut parts: Vec<&str> = rel_path.split('/').collect();
 
     // Strip any matching scan_dirs prefix
-    for scan_dir in &config.scan_dirs {
+    for scan_dir in &config.scan_dir_prefixes {
         let prefix_parts: Vec<&str> = scan_dir.split('/').collect();
         if parts.len() > prefix_parts.len() && parts[..prefix_parts.len()] == prefix_parts[..] {
             parts = parts[prefix_parts.len()..].to_vec();
@@ -99,10 +105,10 @@ pub fn get_category_path(rel_path: &str, config: &ScanConfig) -> Vec<String> {
         parts.pop();
     }
 
-    // Filter out noise directories
+    // Filter out noise directories, and the `..` leading into external scan_dirs
     let filtered: Vec<String> = parts
         .into_iter()
-        .filter(|p| !config.noise_dirs.contains(*p))
+        .filter(|p| *p != ".." && !config.noise_dirs.contains(*p))
         .map(|s| s.to_string())
         .collect();
 
@@ -119,32 +125,324 @@ pub fn get_category_path(rel_path: &str, config: &ScanConfig) -> Vec<String> {
 // Binary file detection
 // ---------------------------------------------------------------------------
 
-/// Check if a file appears to be text by reading the first 8KB and looking for null bytes.
-fn is_text_file(path: &Path) -> bool {
-    let mut file = match fs::File::open(path) {
-        Ok(f) => f,
-        Err(_) => return false,
-    };
-    let mut buf = [0u8; 8192];
-    let n = match std::io::Read::read(&mut file, &mut buf) {
-        Ok(n) => n,
-        Err(_) => return false,
+/// Bytes read from the start of a file to classify its content.
+const SNIFF_BYTES: usize = 16 * 1024;
+
+/// Average line length, in bytes, above which a file counts as minified.
+const MINIFIED_AVG_LINE: usize = 300;
+
+/// Samples shorter than this are never called minified; a short one-liner is cheap to grep.
+const MINIFIED_MIN_SAMPLE: usize = 2048;
+
+/// Lines at the top of a file searched for a generated-code marker.
+const GENERATED_MARKER_LINES: usize = 20;
+
+/// Header comments that mark a file as generated (`// Code generated ... DO NOT EDIT.`,
+/// `@generated`, .NET's `<auto-generated>`).
+const GENERATED_MARKERS: &[&str] = &["@generated", "DO NOT EDIT", "<auto-generated"];
+
+/// File name endings of generated code: protobuf/gRPC output, `*_generated.*`, and the
+/// like.
+const GENERATED_SUFFIXES: &[&str] =
+    &[".pb.go", ".pb.cc", ".pb.h", "_pb2.py", "_pb2_grpc.py", ".g.dart", ".designer.cs"];
+
+/// Directory names holding vendored third-party code.
+const VENDORED_DIRS: &[&str] =
+    &["vendor", "vendored", "third_party", "third-party", "thirdparty", "3rdparty"];
+
+fn read_head(path: &Path) -> Option<Vec<u8>> {
+    let file = fs::File::open(path).ok()?;
+    let mut buf = Vec::with_capacity(SNIFF_BYTES);
+    std::io::Read::read_to_end(&mut std::io::Read::take(file, SNIFF_BYTES as u64), &mut buf)
+        .ok()?;
+    Some(buf)
+}
+
+/// Classify a file's content for [`ScannedFile::skip_content`]: `"binary"` when the first
+/// 16KB holds a NUL byte (UTF-16 text aside), `"minified"` for `.min.*` names or an
+/// average line length over 300 bytes. Unreadable files count as binary.
+pub fn sniff_content(path: &Path) -> Option<&'static str> {
+    classify_content(path, read_head(path).as_deref())
+}
+
+/// [`sniff_content`] and [`file_origin`] from one read of the file's head.
+fn sniff_file(path: &Path, rel_path: &str) -> (Option<&'static str>, Option<&'static str>) {
+    let head = read_head(path);
+    let skip_content = classify_content(path, head.as_deref());
+    let origin = file_origin(rel_path, head.as_deref().unwrap_or_default());
+    (skip_content, origin)
+}
+
+/// Classify a file for [`ScannedFile::origin`]: `"vendored"` under a vendor directory
+/// (`vendor/`, `third_party/`, ...), `"generated"` for generated-code names (`.pb.go`,
+/// `*_generated.rs`, ...) or a generated marker (`@generated`, `DO NOT EDIT`) in the first
+/// lines of `head`.
+pub fn file_origin(rel_path: &str, head: &[u8]) -> Option<&'static str> {
+    let (dirs, name) = rel_path.rsplit_once('/').unwrap_or(("", rel_path));
+    if dirs.split('/').any(|d| VENDORED_DIRS.contains(&d)) {
+        return Some("vendored");
+    }
+    let stem = name.rsplit_once('.').map_or(name, |(s, _)| s);
+    if GENERATED_SUFFIXES.iter().any(|s| name.ends_with(s))
+        || stem.ends_with("_generated")
+        || stem.ends_with(".generated")
+    {
+        return Some("generated");
+    }
+    let head = String::from_utf8_lossy(head);
+    head.lines()
+        .take(GENERATED_MARKER_LINES)
+        .any(|line| GENERATED_MARKERS.iter().any(|m| line.contains(m)))
+        .then_some("generated")
+}
+
+fn classify_content(path: &Path, head: Option<&[u8]>) -> Option<&'static str> {
+    let Some(buf) = head else { return Some("binary") };
+    if crate::encoding::has_utf16_bom(buf) {
+        return None;
+    }
+    if buf.contains(&0) {
+        return Some("binary");
+    }
+    let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    let stem = name.rsplit_once('.').map_or(name.as_ref(), |(s, _)| s);
+    if stem.ends_with(".min") {
+        return Some("minified");
+    }
+    let lines = buf.iter().filter(|&&b| b == b'\n').count() + 1;
+    (buf.len() >= MINIFIED_MIN_SAMPLE && buf.len() / lines > MINIFIED_AVG_LINE)
+        .then_some("minified")
+}
+
+/// xxh3 hash of a file's bytes, read in blocks; 0 when the file can't be read.
+pub fn hash_file(path: &Path) -> u64 {
+    digest_file(path).hash
+}
+
+/// What [`digest_file`] learns from one read of a file. All zero when it can't be read.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FileDigest {
+    /// xxh3 hash of the bytes.
+    pub hash: u64,
+    /// Newlines, plus a final line without one.
+    pub lines: u32,
+    /// xxh3 hash of the bytes other than ASCII whitespace, equal for files that differ only
+    /// in indentation, line endings, or blank lines.
+    pub normalized_hash: u64,
+}
+
+/// Hash and count the lines of a file, read in blocks.
+pub fn digest_file(path: &Path) -> FileDigest {
+    let Ok(mut file) = fs::File::open(path) else { return FileDigest::default() };
+    let mut hasher = xxhash_rust::xxh3::Xxh3::new();
+    let mut normalized = xxhash_rust::xxh3::Xxh3::new();
+    let mut buf = [0u8; 64 * 1024];
+    let mut kept = Vec::with_capacity(buf.len());
+    let mut lines: u32 = 0;
+    let mut last = b'\n';
+    loop {
+        match std::io::Read::read(&mut file, &mut buf) {
+            Ok(0) => {
+                return FileDigest {
+                    hash: hasher.digest(),
+                    lines: lines + u32::from(last != b'\n'),
+                    normalized_hash: normalized.digest(),
+                }
+            }
+            Ok(n) => {
+                let block = &buf[..n];
+                hasher.update(block);
+                lines = lines.saturating_add(block.iter().filter(|&&b| b == b'\n').count() as u32);
+                last = block[n - 1];
+                kept.clear();
+                kept.extend(block.iter().filter(|b| !b.is_ascii_whitespace()));
+                normalized.update(&kept);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(_) => return FileDigest::default(),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Glob entries in scan_dirs, skip_dirs, and extensions
+// ---------------------------------------------------------------------------
+
+/// Whether a config entry is a glob pattern rather than a literal name or path.
+pub fn is_glob(entry: &str) -> bool {
+    entry.contains(['*', '?', '[', '{'])
+}
+
+/// The components of a path pattern before its first wildcard component.
+pub fn literal_prefix(pattern: &str) -> String {
+    pattern.split('/').take_while(|c| !is_glob(c)).collect::<Vec<_>>().join("/")
+}
+
+/// Compile config globs in which `*` stops at `/`. Invalid ones are skipped with a warning.
+pub fn compile_config_globs(patterns: &[String], key: &str) -> Option<globset::GlobSet> {
+    if patterns.is_empty() {
+        return None;
+    }
+    let mut builder = globset::GlobSetBuilder::new();
+    for pattern in patterns {
+        match globset::GlobBuilder::new(pattern).literal_separator(true).build() {
+            Ok(glob) => {
+                builder.add(glob);
+            }
+            Err(e) => {
+                tracing::warn!(key, pattern = pattern.as_str(), "Invalid glob — ignoring: {e}")
+            }
+        }
+    }
+    builder.build().ok()
+}
+
+/// Expand glob entries of `scan_dirs` (`services/*/src`, `**/proto`) into the directories
+/// they match, leaving out hidden and skipped ones. Literal entries are kept as written.
+/// A directory inside another selected one is dropped so no file is scanned twice.
+pub fn expand_scan_dirs(config: &ScanConfig, entries: &[String]) -> Vec<String> {
+    let mut dirs = Vec::new();
+    for entry in entries {
+        let pattern = entry.trim_matches('/');
+        if !is_glob(pattern) {
+            dirs.push(pattern.to_string());
+            continue;
+        }
+        let Some(glob) = compile_config_globs(&[pattern.to_string()], "scan_dirs") else {
+            continue;
+        };
+        let max_depth =
+            if pattern.contains("**") { usize::MAX } else { pattern.split('/').count() };
+        let before = dirs.len();
+        collect_matching_dirs(config, &glob, "", max_depth, &mut dirs);
+        if dirs.len() == before {
+            tracing::warn!(pattern, "scan_dirs pattern matches no directory");
+        }
+    }
+    dirs.sort();
+    dirs.dedup();
+    let mut kept: Vec<String> = Vec::new();
+    for dir in dirs {
+        if !kept.iter().any(|k| k == "." || dir.starts_with(&format!("{k}/"))) {
+            kept.push(dir);
+        }
+    }
+    kept
+}
+
+/// Whether a `scan_dirs` entry may point outside the root: absolute, or starting with `..`.
+pub fn is_external_entry(entry: &str) -> bool {
+    Path::new(entry).is_absolute() || entry.split(['/', '\\']).next() == Some("..")
+}
+
+/// Resolve an absolute or `../` `scan_dirs` entry to the directory it names, with its path
+/// from the canonical root as the prefix (`.` when it is the root itself). `None`, with a
+/// warning, when it is not a directory. Entries that land inside the root get a plain
+/// relative prefix; callers treat those as ordinary `scan_dirs`.
+pub fn external_dir(root: &Path, entry: &str) -> Option<ExternalDir> {
+    let path = match root.join(entry).canonicalize() {
+        Ok(p) if p.is_dir() => p,
+        _ => {
+            tracing::warn!(entry, "scan_dirs entry outside the root is not a directory, skipping");
+            return None;
+        }
     };
-    !buf[..n].contains(&0)
+    let root = root.canonicalize().ok()?;
+    let (from, to): (Vec<_>, Vec<_>) = (root.components().collect(), path.components().collect());
+    if from.first() != to.first() {
+        tracing::warn!(entry, "scan_dirs entry is on another drive than the root, skipping");
+        return None;
+    }
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let mut parts = vec!["..".to_string(); from.len() - common];
+    parts.extend(to[common..].iter().map(|c| c.as_os_str().to_string_lossy().into_owned()));
+    let prefix = if parts.is_empty() { ".".to_string() } else { parts.join("/") };
+    Some(ExternalDir { prefix, path })
+}
+
+fn collect_matching_dirs(
+    config: &ScanConfig,
+    glob: &globset::GlobSet,
+    rel_dir: &str,
+    depth_left: usize,
+    out: &mut Vec<String>,
+) {
+    if depth_left == 0 {
+        return;
+    }
+    let Ok(entries) = fs::read_dir(config.root.join(rel_dir)) else { return };
+    for entry in entries.flatten() {
+        if !entry.file_type().is_ok_and(|t| t.is_dir()) {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let rel = if rel_dir.is_empty() { name.clone() } else { format!("{rel_dir}/{name}") };
+        if name.starts_with('.') || config.skips_dir(&rel) {
+            continue;
+        }
+        if glob.is_match(&rel) {
+            out.push(rel.clone());
+        }
+        collect_matching_dirs(config, glob, &rel, depth_left - 1, out);
+    }
+}
+
+/// Category of a directory inside a workspace member: the package name, then the
+/// directories below the member without noise dirs.
+fn workspace_category(rel_dir: &str, config: &ScanConfig) -> Option<Vec<String>> {
+    let member = crate::workspaces::member_of(&config.workspace_members, rel_dir)?;
+    let rest = rel_dir[member.dir.len()..].trim_start_matches('/');
+    let mut parts = vec![member.name.clone()];
+    parts.extend(
+        rest.split('/')
+            .filter(|p| !p.is_empty() && !config.noise_dirs.contains(*p))
+            .map(str::to_string),
+    );
+    parts.truncate(5);
+    Some(parts)
 }
 
 // ---------------------------------------------------------------------------
 // Parallel file walking helper
 // ---------------------------------------------------------------------------
 
-/// Collect files matching an extension filter using parallel directory walk.
+/// Symlinks the walk did not index through.
+#[derive(Debug, Default)]
+pub struct SymlinkReport {
+    /// Symlinks left alone because `follow_symlinks` is off, relative to the root.
+    pub skipped: Vec<String>,
+    /// Followed symlinks that lead back to one of their own ancestors.
+    pub cycles: Vec<String>,
+}
+
+/// The symlink of a loop error, looking through the path/depth wrappers `ignore` adds.
+fn loop_child(err: &ignore::Error) -> Option<&Path> {
+    match err {
+        ignore::Error::Loop { child, .. } => Some(child),
+        ignore::Error::WithPath { err, .. }
+        | ignore::Error::WithDepth { err, .. }
+        | ignore::Error::WithLineNumber { err, .. } => loop_child(err),
+        _ => None,
+    }
+}
+
+/// Collect files under `scan_dirs` with a parallel directory walk, skipping `skip_dirs`
+/// and, when `filter_extensions` is set, files that fail the `extensions` filter.
+/// Symlinks are followed only with `follow_symlinks`; the report lists the rest.
 fn walk_files_parallel(
-    project_root: &Path,
+    config: &ScanConfig,
     scan_dirs: &[String],
-    skip_dirs: &HashSet<String>,
-    ext_filter: Option<&HashSet<String>>,
-) -> Vec<(std::path::PathBuf, String)> {
+    filter_extensions: bool,
+    progress: &ScanProgress,
+) -> (Vec<(std::path::PathBuf, String)>, SymlinkReport) {
+    let project_root = config.root.as_path();
+    let respect_gitignore = config.respect_gitignore;
     let results: Mutex<Vec<(std::path::PathBuf, String)>> = Mutex::new(Vec::new());
+    let symlinks = Mutex::new(SymlinkReport::default());
+    let rel = |path: &Path| {
+        path.strip_prefix(project_root).unwrap_or(path).to_string_lossy().replace('\\', "/")
+    };
+    let filter = std::sync::Arc::new(config.clone());
 
     for scan_dir in scan_dirs {
         let dir = project_root.join(scan_dir);
@@ -153,17 +451,20 @@ fn walk_files_parallel(
             continue;
         }
 
-        let skip = skip_dirs.clone();
+        let filter = std::sync::Arc::clone(&filter);
         WalkBuilder::new(&dir)
             .hidden(true)
-            .git_ignore(false)
-            .git_global(false)
-            .git_exclude(false)
+            .ignore(respect_gitignore)
+            .git_ignore(respect_gitignore)
+            .git_global(respect_gitignore)
+            .git_exclude(respect_gitignore)
+            .require_git(false)
+            .follow_links(config.follow_symlinks)
             .threads(rayon::current_num_threads().min(12))
             .filter_entry(move |entry| {
                 if entry.file_type().is_some_and(|ft| ft.is_dir()) {
-                    let name = entry.file_name().to_string_lossy();
-                    return !skip.contains(name.as_ref());
+                    let rel = entry.path().strip_prefix(&filter.root).unwrap_or(entry.path());
+                    return !filter.skips_dir(&rel.to_string_lossy().replace('\\', "/"));
                 }
                 true
             })
@@ -172,34 +473,79 @@ fn walk_files_parallel(
                 Box::new(|entry| {
                     let entry = match entry {
                         Ok(e) => e,
-                        Err(_) => return ignore::WalkState::Continue,
+                        Err(err) => {
+                            if let Some(child) = loop_child(&err) {
+                                symlinks.lock().unwrap().cycles.push(rel(child));
+                            }
+                            return ignore::WalkState::Continue;
+                        }
                     };
+                    if entry.path_is_symlink() && !config.follow_symlinks {
+                        symlinks.lock().unwrap().skipped.push(rel(entry.path()));
+                        return ignore::WalkState::Continue;
+                    }
                     if !entry.file_type().is_some_and(|ft| ft.is_file()) {
                         return ignore::WalkState::Continue;
                     }
 
                     let abs_path = entry.path().to_path_buf();
-                    let ext_str = abs_path.extension().and_then(|e| e.to_str()).unwrap_or("");
-
-                    if let Some(exts) = ext_filter {
-                        if !exts.contains(ext_str) {
-                            return ignore::WalkState::Continue;
-                        }
+                    if filter_extensions
+                        && !config.matches_extensions(&entry.file_name().to_string_lossy())
+                    {
+                        return ignore::WalkState::Continue;
                     }
 
-                    let rel_path = abs_path
-                        .strip_prefix(project_root)
-                        .unwrap_or(&abs_path)
-                        .to_string_lossy()
-                        .replace('\\', "/");
-
+                    let rel_path = rel(&abs_path);
                     results.lock().unwrap().push((abs_path, rel_path));
+                    progress.file_walked();
                     ignore::WalkState::Continue
                 })
             });
     }
 
-    results.into_inner().unwrap()
+    let mut symlinks = symlinks.into_inner().unwrap();
+    symlinks.skipped.sort();
+    symlinks.cycles.sort();
+    (results.into_inner().unwrap(), symlinks)
+}
+
+/// Whether the walk would skip `rel_path` because of ignore rules, for paths the watcher
+/// sees outside a walk. Like the walk, deeper `.ignore`/`.gitignore` files override
+/// shallower ones, `.ignore` overrides `.gitignore` in the same directory, and
+/// `.git/info/exclude` and the global gitignore come last. Always false with
+/// `respect_gitignore = false`.
+pub fn is_ignored(config: &ScanConfig, rel_path: &str) -> bool {
+    use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+    if !config.respect_gitignore {
+        return false;
+    }
+    let abs_path = config.root.join(rel_path);
+    let is_dir = abs_path.is_dir();
+    let mut dirs = vec![config.root.clone()];
+    let parts: Vec<&str> = rel_path.split('/').collect();
+    for part in &parts[..parts.len().saturating_sub(1)] {
+        let next = dirs[dirs.len() - 1].join(part);
+        dirs.push(next);
+    }
+    let matchers = dirs
+        .iter()
+        .rev()
+        .flat_map(|d| [d.join(".ignore"), d.join(".gitignore")])
+        .filter(|f| f.is_file())
+        .chain(std::iter::once(config.root.join(".git/info/exclude")).filter(|f| f.is_file()))
+        .map(|f| Gitignore::new(f).0)
+        .chain(std::iter::once(GitignoreBuilder::new(&config.root).build_global().0));
+    for gi in matchers {
+        let m = gi.matched_path_or_any_parents(&abs_path, is_dir);
+        if m.is_ignore() {
+            return true;
+        }
+        if m.is_whitelist() {
+            return false;
+        }
+    }
+    false
 }
 
 // ---------------------------------------------------------------------------
@@ -208,43 +554,62 @@ fn walk_files_parallel(
 
 /// Walk the project directory tree and return all discovered files plus a category-keyed manifest.
 pub fn scan_files(config: &ScanConfig) -> (Vec<ScannedFile>, BTreeMap<String, Vec<FileEntry>>) {
+    let (all_files, category_files, _) = scan_files_with_symlinks(config);
+    (all_files, category_files)
+}
+
+/// [`scan_files`], also returning the symlinks the walk skipped or found to be cycles.
+pub fn scan_files_with_symlinks(
+    config: &ScanConfig,
+) -> (Vec<ScannedFile>, BTreeMap<String, Vec<FileEntry>>, SymlinkReport) {
+    scan_files_with_progress(config, &ScanProgress::new())
+}
+
+/// [`scan_files_with_symlinks`], counting each file found in `progress`.
+pub fn scan_files_with_progress(
+    config: &ScanConfig,
+    progress: &ScanProgress,
+) -> (Vec<ScannedFile>, BTreeMap<String, Vec<FileEntry>>, SymlinkReport) {
     // If scan_dirs is empty, scan root itself
     let scan_dirs: Vec<String> =
         if config.scan_dirs.is_empty() { vec![".".to_string()] } else { config.scan_dirs.clone() };
 
-    // Extension filter: None means scan all (with text check)
-    let ext_filter: Option<HashSet<String>> =
-        if config.extensions.is_empty() { None } else { Some(config.extensions.clone()) };
-
     // Parallel walk
-    let raw_files =
-        walk_files_parallel(&config.root, &scan_dirs, &config.skip_dirs, ext_filter.as_ref());
+    let (raw_files, symlinks) = walk_files_parallel(config, &scan_dirs, true, progress);
 
-    // If no extension filter, apply binary file check
-    let raw_files: Vec<(std::path::PathBuf, String)> = if ext_filter.is_none() {
-        raw_files.into_par_iter().filter(|(abs_path, _)| is_text_file(abs_path)).collect()
-    } else {
-        raw_files
-    };
-
-    // Process in parallel with rayon
+    // Process in parallel with rayon. Without an extension filter, binaries are left out
     let processed: Vec<(ScannedFile, String, FileEntry)> = raw_files
         .par_iter()
-        .map(|(abs_path, rel_path)| {
+        .filter_map(|(abs_path, rel_path)| {
+            let (skip_content, origin) = sniff_file(abs_path, rel_path);
+            if skip_content == Some("binary") && !config.has_extension_filter() {
+                return None;
+            }
             let size = fs::metadata(abs_path).map(|m| m.len()).unwrap_or(0);
             let desc = describe(rel_path);
             let cat_parts = get_category_path(rel_path, config);
             let cat_key = cat_parts.join(" > ");
             let ext = abs_path.extension().and_then(|e| e.to_str()).unwrap_or("").to_string();
 
+            let lang = crate::lang::detect_file(&config.languages, rel_path, abs_path);
+            let encoding = crate::encoding::detect_file(abs_path);
+            let digest = digest_file(abs_path);
             let scanned = ScannedFile {
                 rel_path: rel_path.clone(),
                 abs_path: abs_path.clone(),
                 desc: desc.clone(),
                 ext,
+                lang,
+                encoding,
+                skip_content,
+                origin,
+                owners: config.codeowners.owners_of(rel_path),
+                content_hash: digest.hash,
+                lines: digest.lines,
+                normalized_hash: digest.normalized_hash,
             };
             let entry = FileEntry { path: rel_path.clone(), desc, size };
-            (scanned, cat_key, entry)
+            Some((scanned, cat_key, entry))
         })
         .collect();
 
@@ -257,17 +622,18 @@ pub fn scan_files(config: &ScanConfig) -> (Vec<ScannedFile>, BTreeMap<String, Ve
     }
 
     for files in category_files.values_mut() {
-        files.sort_by(|a, b| a.path.cmp(&b.path));
+        files.sort_by(|a, b| config.sort_order.compare(&a.path, &b.path));
     }
 
-    (all_files, category_files)
+    (all_files, category_files, symlinks)
 }
 
 // ---------------------------------------------------------------------------
 // Incremental update helpers (used by file watcher)
 // ---------------------------------------------------------------------------
 
-/// Process a single file into a ScannedFile. Returns None if the file is binary or doesn't match filters.
+/// Process a single file into a ScannedFile. Returns None if the file doesn't match filters,
+/// or is binary when there is no extension filter.
 pub fn process_single_file(
     config: &ScanConfig,
     abs_path: &Path,
@@ -277,18 +643,31 @@ pub fn process_single_file(
         return None;
     }
     let ext = abs_path.extension().and_then(|e| e.to_str()).unwrap_or("").to_string();
-    if !config.extensions.is_empty() && !config.extensions.contains(&ext) {
+    let file_name = rel_path.rsplit('/').next().unwrap_or(rel_path);
+    if !config.matches_extensions(file_name) {
         return None;
     }
-    if config.extensions.is_empty() && !is_text_file(abs_path) {
+    let (skip_content, origin) = sniff_file(abs_path, rel_path);
+    if skip_content == Some("binary") && !config.has_extension_filter() {
         return None;
     }
     let desc = describe(rel_path);
+    let lang = crate::lang::detect_file(&config.languages, rel_path, abs_path);
+    let encoding = crate::encoding::detect_file(abs_path);
+    let digest = digest_file(abs_path);
     Some(ScannedFile {
         rel_path: rel_path.to_string(),
         abs_path: abs_path.to_path_buf(),
         desc,
         ext,
+        lang,
+        encoding,
+        skip_content,
+        origin,
+        owners: config.codeowners.owners_of(rel_path),
+        content_hash: digest.hash,
+        lines: digest.lines,
+        normalized_hash: digest.normalized_hash,
     })
 }
 
@@ -304,11 +683,9 @@ pub fn update_manifest_entry(
     // Add to correct category
     let cat_key = get_category_path(&file.rel_path, config).join(" > ");
     let size = fs::metadata(&file.abs_path).map(|m| m.len()).unwrap_or(0);
-    manifest.entry(cat_key).or_default().push(FileEntry {
-        path: file.rel_path.clone(),
-        desc: file.desc.clone(),
-        size,
-    });
+    let files = manifest.entry(cat_key).or_default();
+    let at = files.partition_point(|f| config.sort_order.compare(&f.path, &file.rel_path).is_lt());
+    files.insert(at, FileEntry { path: file.rel_path.clone(), desc: file.desc.clone(), size });
 }
 
 /// Remove a file from the manifest by its relative path.
@@ -325,28 +702,26 @@ pub fn remove_manifest_entry(manifest: &mut BTreeMap<String, Vec<FileEntry>>, re
     }
 }
 
-/// Remove all import edges for a file and re-add them based on current content.
+/// Remove the import edges out of a file and re-add them based on its current content,
+/// resolved against `all_files`. Edges into the file are kept.
 pub fn update_import_edges_for_file(
     graph: &mut ImportGraph,
     file: &ScannedFile,
-    _all_files: &[ScannedFile],
+    all_files: &[ScannedFile],
 ) {
     let rel = &file.rel_path;
 
-    // Remove old outgoing edges
+    // Remove old outgoing edges, here and on the imported files' side
     graph.imports.remove(rel);
-
-    // Remove old incoming edges (this file as an import target)
-    for (_src, targets) in graph.imported_by.iter_mut() {
-        targets.retain(|t| t != rel);
+    graph.edges.remove(rel);
+    for importers in graph.imported_by.values_mut() {
+        importers.retain(|t| t != rel);
     }
-    // Remove this file from imported_by as a source
-    graph.imported_by.remove(rel);
+    graph.imported_by.retain(|_, importers| !importers.is_empty());
 
     // Re-parse this file's imports and update both directions
-    // We re-scan just this one file by creating a temporary single-file slice
     let single = [file.clone()];
-    let new_graph = scan_imports(&single);
+    let new_graph = scan_imports_from(&single, all_files);
 
     // Merge new edges into existing graph
     for (src, targets) in new_graph.imports {
@@ -355,6 +730,7 @@ pub fn update_import_edges_for_file(
             graph.imported_by.entry(target.clone()).or_default().push(src.clone());
         }
     }
+    graph.edges.extend(new_graph.edges);
 }
 
 // ---------------------------------------------------------------------------
@@ -362,8 +738,14 @@ pub fn update_import_edges_for_file(
 // ---------------------------------------------------------------------------
 
 /// Build a nested JSON tree from the flat category manifest for the tree API endpoint.
-pub fn build_tree(manifest: &BTreeMap<String, Vec<FileEntry>>) -> serde_json::Value {
+/// JSON objects keep no key order, so the root's `_sort` key tells the web UI how to order
+/// module names; `_files` lists are already in that order.
+pub fn build_tree(
+    manifest: &BTreeMap<String, Vec<FileEntry>>,
+    order: crate::collate::SortOrder,
+) -> serde_json::Value {
     let mut root = serde_json::Map::new();
+    root.insert("_sort".to_string(), serde_json::json!(order.as_str()));
 
     for (cat_key, files) in manifest {
         let parts: Vec<&str> = cat_key.split(" > ").collect();
@@ -722,12 +1104,12 @@ pub fn scan_deps(config: &ScanConfig) -> BTreeMap<String, DepEntry> {
         if config.scan_dirs.is_empty() { vec![".".to_string()] } else { config.scan_dirs.clone() };
 
     // Walk all files — no ext filter, scanners decide what they match
-    let raw_files = walk_files_parallel(&config.root, &scan_dirs, &config.skip_dirs, None);
+    let (raw_files, _) = walk_files_parallel(config, &scan_dirs, false, &ScanProgress::new());
 
     // Process matching files in parallel
     let entries: Vec<(String, DepEntry)> = raw_files
         .par_iter()
-        .filter_map(|(abs_path, _rel_path)| {
+        .filter_map(|(abs_path, rel_path)| {
             // Find the first scanner that matches this file
             let scanner = scanners.iter().find(|s| s.matches(abs_path))?;
             let module_name = scanner.module_name(abs_path)?;
@@ -741,10 +1123,16 @@ pub fn scan_deps(config: &ScanConfig) -> BTreeMap<String, DepEntry> {
                 .to_string_lossy()
                 .replace('\\', "/");
 
+            if let Some(parts) = workspace_category(&rel_dir, config) {
+                let category_path = parts.join(" > ");
+                let manifest = rel_path.clone();
+                return Some((module_name, DepEntry { public, private, category_path, manifest }));
+            }
+
             let cat_parts: Vec<&str> = rel_dir.split('/').collect();
             // Strip scan_dirs prefix
             let mut filtered_parts = cat_parts.clone();
-            for scan_dir in &config.scan_dirs {
+            for scan_dir in &config.scan_dir_prefixes {
                 let prefix_parts: Vec<&str> = scan_dir.split('/').collect();
                 if filtered_parts.len() > prefix_parts.len()
                     && filtered_parts[..prefix_parts.len()] == prefix_parts[..]
@@ -757,13 +1145,164 @@ pub fn scan_deps(config: &ScanConfig) -> BTreeMap<String, DepEntry> {
                 filtered_parts.into_iter().filter(|p| !config.noise_dirs.contains(*p)).collect();
             let category_path = filtered.join(" > ");
 
-            Some((module_name, DepEntry { public, private, category_path }))
+            let manifest = rel_path.clone();
+            Some((module_name, DepEntry { public, private, category_path, manifest }))
         })
         .collect();
 
     entries.into_iter().collect()
 }
 
+// ---------------------------------------------------------------------------
+// File statistics
+// ---------------------------------------------------------------------------
+
+/// Size totals of a group of files. Lines count only files content search reads, so
+/// binaries and minified bundles don't inflate them.
+#[derive(Serialize, Debug, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StatTotals {
+    pub files: usize,
+    pub lines: u64,
+    pub bytes: u64,
+    pub avg_bytes: u64,
+}
+
+/// Totals for one extension, language, or module.
+#[derive(Serialize, Debug, Clone)]
+pub struct StatGroup {
+    pub name: String,
+    #[serde(flatten)]
+    pub totals: StatTotals,
+}
+
+/// A module's totals with its language breakdown.
+#[derive(Serialize, Debug, Clone)]
+pub struct ModuleStats {
+    pub module: String,
+    #[serde(flatten)]
+    pub totals: StatTotals,
+    pub languages: Vec<StatGroup>,
+}
+
+/// Codebase profile of a repo: totals, then per extension, language, and module, each
+/// sorted by lines, most first.
+#[derive(Serialize, Debug, Clone)]
+pub struct RepoStats {
+    #[serde(flatten)]
+    pub totals: StatTotals,
+    pub extensions: Vec<StatGroup>,
+    pub languages: Vec<StatGroup>,
+    pub modules: Vec<ModuleStats>,
+}
+
+impl StatTotals {
+    fn add(&mut self, file: &ScannedFile, bytes: u64) {
+        self.files += 1;
+        if file.is_searchable() {
+            self.lines += u64::from(file.lines);
+        }
+        self.bytes += bytes;
+        self.avg_bytes = self.bytes / self.files as u64;
+    }
+}
+
+fn stat_groups(groups: BTreeMap<&str, StatTotals>) -> Vec<StatGroup> {
+    let mut groups: Vec<StatGroup> = groups
+        .into_iter()
+        .map(|(name, totals)| StatGroup { name: name.to_string(), totals })
+        .collect();
+    groups.sort_by(|a, b| {
+        b.totals.lines.cmp(&a.totals.lines).then(b.totals.files.cmp(&a.totals.files))
+    });
+    groups
+}
+
+/// Files without a detected language are grouped as `other`, files without an extension as
+/// `(none)`. Sizes come from the manifest, so they are as of the last scan or watcher update.
+pub fn compute_stats(repo: &RepoState) -> RepoStats {
+    let files: HashMap<&str, &ScannedFile> =
+        repo.all_files.iter().map(|f| (f.rel_path.as_str(), f)).collect();
+    let mut totals = StatTotals::default();
+    let mut extensions: BTreeMap<&str, StatTotals> = BTreeMap::new();
+    let mut languages: BTreeMap<&str, StatTotals> = BTreeMap::new();
+    let mut modules = Vec::new();
+    for (module, entries) in &repo.manifest {
+        let mut module_totals = StatTotals::default();
+        let mut module_languages: BTreeMap<&str, StatTotals> = BTreeMap::new();
+        for entry in entries {
+            let Some(file) = files.get(entry.path.as_str()) else { continue };
+            let ext = if file.ext.is_empty() { "(none)" } else { file.ext.as_str() };
+            let lang = file.lang.unwrap_or("other");
+            totals.add(file, entry.size);
+            extensions.entry(ext).or_default().add(file, entry.size);
+            languages.entry(lang).or_default().add(file, entry.size);
+            module_totals.add(file, entry.size);
+            module_languages.entry(lang).or_default().add(file, entry.size);
+        }
+        modules.push(ModuleStats {
+            module: module.clone(),
+            totals: module_totals,
+            languages: stat_groups(module_languages),
+        });
+    }
+    modules.sort_by(|a, b| b.totals.lines.cmp(&a.totals.lines).then(a.module.cmp(&b.module)));
+    RepoStats {
+        totals,
+        extensions: stat_groups(extensions),
+        languages: stat_groups(languages),
+        modules,
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Module docs
+// ---------------------------------------------------------------------------
+
+/// Maximum doc files recorded per module.
+const MAX_DOCS_PER_MODULE: usize = 10;
+
+/// Directory names whose prose files document the parent module.
+const DOC_DIR_NAMES: &[&str] = &["docs", "doc", "documentation"];
+
+/// Extensions treated as prose when found in a docs directory.
+const DOC_EXTENSIONS: &[&str] = &["md", "markdown", "rst", "adoc", "txt"];
+
+/// Collect human-written docs per module: README files in the module itself, plus prose
+/// files in a `docs/` child module. READMEs come first; paths are repo-relative.
+pub fn collect_module_docs(
+    manifest: &BTreeMap<String, Vec<FileEntry>>,
+) -> BTreeMap<String, Vec<String>> {
+    let mut docs: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (category, entries) in manifest {
+        for entry in entries {
+            let filename = entry.path.rsplit('/').next().unwrap_or(&entry.path).to_lowercase();
+            if filename.split('.').next() == Some("readme") {
+                docs.entry(category.clone()).or_default().push(entry.path.clone());
+            }
+        }
+    }
+    for (category, entries) in manifest {
+        let Some((parent, last)) = category.rsplit_once(" > ") else { continue };
+        if !DOC_DIR_NAMES.contains(&last.to_lowercase().as_str()) {
+            continue;
+        }
+        for entry in entries {
+            let ext = entry.path.rsplit_once('.').map(|(_, e)| e.to_lowercase());
+            if ext.is_some_and(|e| DOC_EXTENSIONS.contains(&e.as_str())) {
+                let list = docs.entry(parent.to_string()).or_default();
+                if !list.contains(&entry.path) {
+                    list.push(entry.path.clone());
+                }
+            }
+        }
+    }
+    for list in docs.values_mut() {
+        list.truncate(MAX_DOCS_PER_MODULE);
+    }
+    docs
+}
+
 // ---------------------------------------------------------------------------
 // Search index
 // ---------------------------------------------------------------------------
@@ -838,38 +1377,37 @@ pub fn build_search_index(
 // Term document frequency — for IDF-weighted search scoring
 // ---------------------------------------------------------------------------
 
+/// Distinct lowercase terms of a file, as counted by [`crate::types::TermDocFreq`]. Empty
+/// for files over 256 KB (likely generated/binary) and unreadable ones.
+pub fn file_terms(f: &ScannedFile) -> std::collections::HashSet<String> {
+    if f.abs_path.metadata().map(|m| m.len()).unwrap_or(0) > 256 * 1024 {
+        return Default::default();
+    }
+    let Ok(content) = crate::encoding::read_to_string(&f.abs_path) else {
+        return Default::default();
+    };
+    content
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|s| s.len() >= 2)
+        .map(|s| s.to_lowercase())
+        .collect()
+}
+
 /// Build per-term document frequency index across all files in the repo.
 /// Used to compute IDF weights at query time for better search relevance.
 pub fn build_term_doc_freq(all_files: &[ScannedFile]) -> crate::types::TermDocFreq {
-    use std::collections::{HashMap, HashSet};
-
-    // Collect per-file unique terms in parallel
-    let per_file_terms: Vec<HashSet<String>> = all_files
-        .par_iter()
-        .filter_map(|f| {
-            // Skip large files (likely generated/binary)
-            if f.abs_path.metadata().map(|m| m.len()).unwrap_or(0) > 256 * 1024 {
-                return None;
-            }
-            let content = std::fs::read_to_string(&f.abs_path).ok()?;
-            let terms: HashSet<String> = content
-                .split(|c: char| !c.is_alphanumeric() && c != '_')
-                .filter(|s| s.len() >= 2)
-                .map(|s| s.to_lowercase())
-                .collect();
-            Some(terms)
-        })
-        .collect();
+    let mut tdf = crate::types::TermDocFreq::new();
+    add_term_docs(&mut tdf, all_files);
+    tdf
+}
 
-    let total_docs = all_files.len();
-    let mut freq: HashMap<String, usize> = HashMap::new();
-    for terms in &per_file_terms {
-        for term in terms {
-            *freq.entry(term.clone()).or_insert(0) += 1;
-        }
+/// Count `files` into `tdf`, replacing any earlier counts for them. Terms are collected in
+/// parallel and interned sequentially.
+pub fn add_term_docs(tdf: &mut crate::types::TermDocFreq, files: &[ScannedFile]) {
+    let per_file_terms: Vec<_> = files.par_iter().map(file_terms).collect();
+    for (f, terms) in files.iter().zip(per_file_terms) {
+        tdf.add_doc(&f.rel_path, terms);
     }
-
-    crate::types::TermDocFreq { total_docs, freq }
 }
 
 // ---------------------------------------------------------------------------
@@ -907,6 +1445,28 @@ fn import_exts_powershell() -> HashSet<&'static str> {
 
 /// Parse import/include directives across all files and build a bidirectional import graph.
 pub fn scan_imports(all_files: &[ScannedFile]) -> ImportGraph {
+    scan_imports_with_progress(all_files, &ScanProgress::new())
+}
+
+/// [`scan_imports`], counting each parsed file in `progress`.
+pub fn scan_imports_with_progress(
+    all_files: &[ScannedFile],
+    progress: &ScanProgress,
+) -> ImportGraph {
+    resolve_imports(all_files, all_files, progress)
+}
+
+/// Parse import directives in `sources` only, resolving them against every file in
+/// `all_files`. The graph holds just the edges out of `sources`.
+pub fn scan_imports_from(sources: &[ScannedFile], all_files: &[ScannedFile]) -> ImportGraph {
+    resolve_imports(sources, all_files, &ScanProgress::new())
+}
+
+fn resolve_imports(
+    sources: &[ScannedFile],
+    all_files: &[ScannedFile],
+    progress: &ScanProgress,
+) -> ImportGraph {
     let cpp_exts = import_exts_cpp();
     let py_exts = import_exts_python();
     let js_exts = import_exts_js();
@@ -924,7 +1484,9 @@ pub fn scan_imports(all_files: &[ScannedFile]) -> ImportGraph {
             .unwrap();
     let rust_import_re =
         regex::Regex::new(r#"(?:use\s+(?:crate|super)::([\w]+)|mod\s+([\w]+)\s*;)"#).unwrap();
-    let go_import_re = regex::Regex::new(r#"import\s+(?:\(\s*)?(?:"([^"]+)")"#).unwrap();
+    // `import "x"`, `import alias "x"`, or a parenthesized block of either
+    let go_import_re =
+        regex::Regex::new(r#"\bimport\s*(?:\(([^)]*)\)|(?:[\w.]+\s+)?"([^"]+)")"#).unwrap();
     let cs_using_re = regex::Regex::new(r#"(?m)^using\s+(?:static\s+)?([\w.]+)\s*;"#).unwrap();
     // PowerShell: dot-source (. .\file.ps1) and Import-Module
     let ps_dotsource_re =
@@ -932,6 +1494,22 @@ pub fn scan_imports(all_files: &[ScannedFile]) -> ImportGraph {
     let ps_import_re =
         regex::Regex::new(r#"(?mi)Import-Module\s+['".]?\.?[\\/]?([^\s'";\)]+)"#).unwrap();
     let cs_namespace_re = regex::Regex::new(r#"(?m)^(?:namespace\s+([\w.]+))"#).unwrap();
+    // Dynamic imports: a complete literal, a literal prefix (concatenation, f-string, or
+    // template), or a variable
+    let py_dynamic_re = regex::Regex::new(
+        r#"\b(?:import_module|__import__)\s*\(\s*(?:(f?)["']([^"'{]*)(\{?)[^"']*["']\s*(\+?)|([A-Za-z_]\w*)\s*[,)])"#,
+    )
+    .unwrap();
+    let js_dynamic_re = regex::Regex::new(
+        r#"\b(import|require)\s*\(\s*(?:["']([^"']*)["']\s*(\+?)|`([^`$]*)(\$\{)?[^`]*`|([A-Za-z_$][\w$]*)\s*\))"#,
+    )
+    .unwrap();
+    let assignment_re = regex::Regex::new(
+        r#"(?m)^\s*(?:(?:export\s+)?(?:const|let|var)\s+)?([A-Za-z_$][\w$]*)\s*=\s*["']([^"'\n]+)["']"#,
+    )
+    .unwrap();
+    let bazel_deps_re = regex::Regex::new(r#"\b(?:runtime_)?deps\s*=\s*\[([^\]]*)\]"#).unwrap();
+    let string_re = regex::Regex::new(r#""([^"]+)""#).unwrap();
 
     // Build a lookup: filename (without ext) → Vec<rel_path> for resolving imports
     let mut filename_to_paths: BTreeMap<String, Vec<String>> = BTreeMap::new();
@@ -955,7 +1533,7 @@ pub fn scan_imports(all_files: &[ScannedFile]) -> ImportGraph {
         let ns_pairs: Vec<(String, String)> = cs_files
             .par_iter()
             .filter_map(|f| {
-                let content = fs::read_to_string(&f.abs_path).ok()?;
+                let content = crate::encoding::read_to_string(&f.abs_path).ok()?;
                 let ns = cs_namespace_re
                     .captures(&content)
                     .and_then(|cap| cap.get(1))
@@ -997,12 +1575,52 @@ pub fn scan_imports(all_files: &[ScannedFile]) -> ImportGraph {
         None
     };
 
+    let plain = ImportEdge::plain(ImportConfidence::High);
+    let dynamic = ImportEdge::plain(ImportConfidence::Medium);
+    let guessed = ImportEdge::plain(ImportConfidence::Low);
+
+    // Files from scan_dirs outside the project root don't lead back to it
+    let root = sources.iter().find(|f| !f.rel_path.starts_with("../")).and_then(repo_root);
+    let source_dirs = |exts: &HashSet<&str>| {
+        let sources = sources.iter().filter(|f| exts.contains(f.ext.as_str()));
+        sources.map(|f| f.rel_path.rsplit_once('/').map_or("", |(d, _)| d)).collect::<Vec<_>>()
+    };
+
+    // tsconfig `paths`/`baseUrl` aliases for the directories of JS/TS sources
+    let indexed: HashSet<&str> = all_files.iter().map(|f| f.rel_path.as_str()).collect();
+    let ts_configs = match &root {
+        Some(root) => crate::tsconfig::TsConfigs::load(root, source_dirs(&js_exts)),
+        None => crate::tsconfig::TsConfigs::load(Path::new(""), []),
+    };
+
+    // Go module paths from the `go.mod` files above Go sources
+    let go_modules = match &root {
+        Some(root) => go_modules(root, source_dirs(&go_exts)),
+        None => Vec::new(),
+    };
+
+    // Files by parent directory, for computed imports matched by their literal prefix
+    let mut files_by_dir: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    // Bazel package → its BUILD file
+    let mut build_files: BTreeMap<&str, &str> = BTreeMap::new();
+    for f in all_files {
+        let (dir, name) = f.rel_path.rsplit_once('/').unwrap_or(("", &f.rel_path));
+        files_by_dir.entry(dir).or_default().push(&f.rel_path);
+        if name == "BUILD.bazel" || (name == "BUILD" && !build_files.contains_key(dir)) {
+            build_files.insert(dir, &f.rel_path);
+        }
+    }
+
     // Parse imports in parallel
-    let pairs: Vec<(String, Vec<String>)> = all_files
+    let pairs: Vec<(String, BTreeMap<String, ImportEdge>)> = sources
         .par_iter()
         .filter_map(|f| {
+            progress.import_resolved();
             let ext = f.ext.as_str();
-            let has_patterns = cpp_exts.contains(ext)
+            let (dir, file_name) = f.rel_path.rsplit_once('/').unwrap_or(("", &f.rel_path));
+            let is_build = file_name == "BUILD" || file_name == "BUILD.bazel";
+            let has_patterns = is_build
+                || cpp_exts.contains(ext)
                 || py_exts.contains(ext)
                 || js_exts.contains(ext)
                 || rust_exts.contains(ext)
@@ -1013,13 +1631,13 @@ pub fn scan_imports(all_files: &[ScannedFile]) -> ImportGraph {
                 return None;
             }
 
-            let content = fs::read_to_string(&f.abs_path).ok()?;
+            let content = crate::encoding::read_to_string(&f.abs_path).ok()?;
             let mut resolved = Vec::new();
 
             if cpp_exts.contains(ext) {
                 for cap in include_re.captures_iter(&content) {
                     if let Some(path) = resolve_import(&cap[1]) {
-                        resolved.push(path);
+                        resolved.push((path, plain));
                     }
                 }
             }
@@ -1030,7 +1648,115 @@ pub fn scan_imports(all_files: &[ScannedFile]) -> ImportGraph {
                         cap.get(1).or_else(|| cap.get(2)).map(|m| m.as_str()).unwrap_or("");
                     if !import_str.is_empty() {
                         if let Some(path) = resolve_import(import_str) {
-                            resolved.push(path);
+                            let edge = if cap.get(1).is_some() {
+                                py_from_import_edge(&content[cap.get(0).unwrap().end()..])
+                            } else {
+                                plain
+                            };
+                            resolved.push((path, edge));
+                        }
+                    }
+                }
+            }
+
+            // An aliased JS/TS specifier, then the usual filename lookup
+            let aliases = ts_configs.for_dir(dir);
+            let resolve_js = |spec: &str| {
+                aliases.and_then(|a| a.resolve(spec, &indexed)).or_else(|| resolve_import(spec))
+            };
+
+            // A variable's string value, when the file assigns it a literal
+            let assigned = |name: &str| {
+                assignment_re
+                    .captures_iter(&content)
+                    .find(|c| &c[1] == name)
+                    .map(|c| c.get(2).unwrap().as_str())
+            };
+
+            if py_exts.contains(ext) {
+                for cap in py_dynamic_re.captures_iter(&content) {
+                    if let Some(var) = cap.get(5) {
+                        if let Some(path) = assigned(var.as_str()).and_then(&resolve_import) {
+                            resolved.push((path, guessed));
+                        }
+                        continue;
+                    }
+                    let literal = &cap[2];
+                    let computed = (!cap[1].is_empty() && !cap[3].is_empty()) || !cap[4].is_empty();
+                    if !computed {
+                        if let Some(path) = resolve_import(literal) {
+                            resolved.push((path, dynamic));
+                        }
+                        continue;
+                    }
+                    // `import_module(f"plugins.{name}")`: modules in the `plugins` package
+                    let Some((package, stem)) = literal.trim_start_matches('.').rsplit_once('.')
+                    else {
+                        continue;
+                    };
+                    let package = package.replace('.', "/");
+                    let dirs = files_by_dir
+                        .iter()
+                        .filter(|(d, _)| **d == package || d.ends_with(&format!("/{package}")));
+                    let targets = dirs.flat_map(|(_, files)| files.iter()).filter(|p| {
+                        p.rsplit('/')
+                            .next()
+                            .is_some_and(|n| n.starts_with(stem) && n.ends_with(".py"))
+                    });
+                    push_prefix_targets(&mut resolved, targets, &f.rel_path, guessed);
+                }
+            }
+
+            if js_exts.contains(ext) {
+                for cap in js_dynamic_re.captures_iter(&content) {
+                    let literal = if let Some(var) = cap.get(6) {
+                        match assigned(var.as_str()) {
+                            Some(value) => value,
+                            None => continue,
+                        }
+                    } else {
+                        cap.get(2).or_else(|| cap.get(4)).map_or("", |m| m.as_str())
+                    };
+                    let computed =
+                        cap.get(3).is_some_and(|m| !m.is_empty()) || cap.get(5).is_some();
+                    if !computed {
+                        // `require('x')` is a static import, found above
+                        if &cap[1] == "require" && cap.get(2).is_some() {
+                            continue;
+                        }
+                        let edge = if cap.get(6).is_some() { guessed } else { dynamic };
+                        if let Some(path) = resolve_js(literal) {
+                            resolved.push((path, edge));
+                        }
+                        continue;
+                    }
+                    // `require('./locales/' + lang)`: files in `locales` next to the importer
+                    if !literal.starts_with('.') {
+                        continue;
+                    }
+                    let (prefix_dir, stem) = literal.rsplit_once('/').unwrap_or(("", literal));
+                    let Some(target_dir) = join_relative(dir, prefix_dir) else { continue };
+                    let targets =
+                        files_by_dir.get(target_dir.as_str()).into_iter().flatten().filter(|p| {
+                            let name = p.rsplit('/').next().unwrap_or(p);
+                            let ext = name.rsplit_once('.').map_or("", |(_, e)| e);
+                            name.starts_with(stem) && (js_exts.contains(ext) || ext == "json")
+                        });
+                    push_prefix_targets(&mut resolved, targets, &f.rel_path, guessed);
+                }
+            }
+
+            if is_build {
+                // `deps = ["//pkg/util:lib", ":local"]` → the BUILD file of `pkg/util`
+                for cap in bazel_deps_re.captures_iter(&content) {
+                    for label in string_re.captures_iter(&cap[1]) {
+                        let Some(package) = label[1].strip_prefix("//") else { continue };
+                        let package = package.split(':').next().unwrap_or(package);
+                        if package == dir {
+                            continue;
+                        }
+                        if let Some(build) = build_files.get(package) {
+                            resolved.push((build.to_string(), dynamic));
                         }
                     }
                 }
@@ -1040,15 +1766,20 @@ pub fn scan_imports(all_files: &[ScannedFile]) -> ImportGraph {
                 for cap in js_import_re.captures_iter(&content) {
                     let import_str =
                         cap.get(1).or_else(|| cap.get(2)).map(|m| m.as_str()).unwrap_or("");
+                    let edge = if cap.get(1).is_some() {
+                        js_import_edge(&content[..cap.get(0).unwrap().start()])
+                    } else {
+                        plain
+                    };
                     if !import_str.is_empty() && !import_str.starts_with('.') {
-                        // Skip relative imports for now, they need path resolution
-                        if let Some(path) = resolve_import(import_str) {
-                            resolved.push(path);
+                        // Bare or tsconfig-aliased specifier
+                        if let Some(path) = resolve_js(import_str) {
+                            resolved.push((path, edge));
                         }
                     } else if !import_str.is_empty() {
                         // Relative import — try resolving the last component
                         if let Some(path) = resolve_import(import_str) {
-                            resolved.push(path);
+                            resolved.push((path, edge));
                         }
                     }
                 }
@@ -1060,18 +1791,40 @@ pub fn scan_imports(all_files: &[ScannedFile]) -> ImportGraph {
                         cap.get(1).or_else(|| cap.get(2)).map(|m| m.as_str()).unwrap_or("");
                     if !import_str.is_empty() {
                         if let Some(path) = resolve_import(import_str) {
-                            resolved.push(path);
+                            let edge = if cap.get(1).is_some() {
+                                rust_use_edge(&content[cap.get(0).unwrap().end()..])
+                            } else {
+                                plain
+                            };
+                            resolved.push((path, edge));
                         }
                     }
                 }
             }
 
             if go_exts.contains(ext) {
-                for cap in go_import_re.captures_iter(&content) {
-                    if let Some(m) = cap.get(1) {
-                        if let Some(path) = resolve_import(m.as_str()) {
-                            resolved.push(path);
+                let specs = go_import_re.captures_iter(&content).flat_map(|cap| match cap.get(1) {
+                    Some(block) => string_re
+                        .captures_iter(block.as_str())
+                        .map(|c| c.get(1).unwrap().as_str())
+                        .collect(),
+                    None => vec![cap.get(2).unwrap().as_str()],
+                });
+                for spec in specs {
+                    if go_modules.is_empty() {
+                        if let Some(path) = resolve_import(spec) {
+                            resolved.push((path, plain));
                         }
+                        continue;
+                    }
+                    // A package is a directory: link every non-test file in it. Paths outside
+                    // the repo's modules are the standard library or dependencies.
+                    let Some(dir) = go_package_dir(&go_modules, spec) else { continue };
+                    let package = files_by_dir.get(dir.as_str()).into_iter().flatten();
+                    for path in package.filter(|p| {
+                        p.ends_with(".go") && !p.ends_with("_test.go") && **p != f.rel_path
+                    }) {
+                        resolved.push((path.to_string(), plain));
                     }
                 }
             }
@@ -1087,7 +1840,7 @@ pub fn scan_imports(all_files: &[ScannedFile]) -> ImportGraph {
                     if let Some(files) = namespace_to_files.get(ns) {
                         for file in files {
                             if file != &f.rel_path {
-                                resolved.push(file.clone());
+                                resolved.push((file.clone(), plain));
                             }
                         }
                         continue;
@@ -1098,7 +1851,7 @@ pub fn scan_imports(all_files: &[ScannedFile]) -> ImportGraph {
                         if full_ns.starts_with(&prefix) || full_ns == ns {
                             for file in files {
                                 if file != &f.rel_path {
-                                    resolved.push(file.clone());
+                                    resolved.push((file.clone(), plain));
                                 }
                             }
                         }
@@ -1106,7 +1859,7 @@ pub fn scan_imports(all_files: &[ScannedFile]) -> ImportGraph {
                     // Fallback: resolve by last component (filename-based)
                     if let Some(path) = resolve_import(ns) {
                         if path != f.rel_path {
-                            resolved.push(path);
+                            resolved.push((path, plain));
                         }
                     }
                 }
@@ -1116,13 +1869,13 @@ pub fn scan_imports(all_files: &[ScannedFile]) -> ImportGraph {
                 // Dot-source: . .\helpers.ps1, . "$PSScriptRoot\utils.ps1"
                 for cap in ps_dotsource_re.captures_iter(&content) {
                     if let Some(path) = resolve_import(&cap[1]) {
-                        resolved.push(path);
+                        resolved.push((path, plain));
                     }
                 }
                 // Import-Module .\MyModule or Import-Module MyModule
                 for cap in ps_import_re.captures_iter(&content) {
                     if let Some(path) = resolve_import(&cap[1]) {
-                        resolved.push(path);
+                        resolved.push((path, plain));
                     }
                 }
             }
@@ -1130,30 +1883,179 @@ pub fn scan_imports(all_files: &[ScannedFile]) -> ImportGraph {
             if resolved.is_empty() {
                 None
             } else {
-                resolved.sort();
-                resolved.dedup();
-                Some((f.rel_path.clone(), resolved))
+                let mut edges: BTreeMap<String, ImportEdge> = BTreeMap::new();
+                for (path, edge) in resolved {
+                    edges.entry(path).or_default().merge(edge);
+                }
+                Some((f.rel_path.clone(), edges))
             }
         })
         .collect();
 
-    // Build bidirectional graph
-    let mut imports: BTreeMap<String, Vec<String>> = BTreeMap::new();
-    let mut imported_by: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    ImportGraph::from_edges(pairs.into_iter().collect())
+}
+
+/// The repo root a scanned file was found under.
+fn repo_root(file: &ScannedFile) -> Option<std::path::PathBuf> {
+    let mut root = file.abs_path.as_path();
+    for _ in file.rel_path.split('/') {
+        root = root.parent()?;
+    }
+    Some(root.to_path_buf())
+}
 
-    for (file, deps) in pairs {
-        for dep in &deps {
-            imported_by.entry(dep.clone()).or_default().push(file.clone());
+/// `(module path, directory)` of every `go.mod` at or above `dirs`, longest path first.
+fn go_modules<'a>(root: &Path, dirs: impl IntoIterator<Item = &'a str>) -> Vec<(String, String)> {
+    let mut checked: HashSet<&str> = HashSet::new();
+    let mut modules = Vec::new();
+    for dir in dirs {
+        let mut current = Some(dir);
+        while let Some(d) = current.filter(|d| checked.insert(d)) {
+            if let Ok(content) = std::fs::read_to_string(root.join(d).join("go.mod")) {
+                let module = content.lines().find_map(|l| l.trim().strip_prefix("module "));
+                if let Some(module) = module.filter(|m| !m.trim().is_empty()) {
+                    let module = module.split("//").next().unwrap_or("").trim().trim_matches('"');
+                    modules.push((module.to_string(), d.to_string()));
+                }
+            }
+            current = (!d.is_empty()).then(|| d.rsplit_once('/').map_or("", |(p, _)| p));
         }
-        imports.insert(file, deps);
     }
+    modules.sort_by_key(|(module, _)| std::cmp::Reverse(module.len()));
+    modules
+}
 
-    // Sort imported_by lists for consistent output
-    for list in imported_by.values_mut() {
-        list.sort();
+/// Repo directory of the Go package `import_path`, if it belongs to one of `modules`.
+fn go_package_dir(modules: &[(String, String)], import_path: &str) -> Option<String> {
+    modules.iter().find_map(|(module, dir)| {
+        let rest = import_path.strip_prefix(module.as_str())?;
+        let rest = if rest.is_empty() { "" } else { rest.strip_prefix('/')? };
+        Some(match (dir.is_empty(), rest.is_empty()) {
+            (true, _) => rest.to_string(),
+            (false, true) => dir.clone(),
+            (false, false) => format!("{dir}/{rest}"),
+        })
+    })
+}
+
+/// Most files a computed import may be linked to; a broader prefix says too little.
+const MAX_PREFIX_TARGETS: usize = 32;
+
+/// Link an importer to every file a computed import's literal prefix matches, unless
+/// there are more than [`MAX_PREFIX_TARGETS`].
+fn push_prefix_targets<'a>(
+    resolved: &mut Vec<(String, ImportEdge)>,
+    targets: impl Iterator<Item = &'a &'a str>,
+    importer: &str,
+    edge: ImportEdge,
+) {
+    let targets: Vec<&str> = targets.copied().filter(|p| *p != importer).collect();
+    if targets.len() <= MAX_PREFIX_TARGETS {
+        resolved.extend(targets.into_iter().map(|p| (p.to_string(), edge)));
+    }
+}
+
+/// `base/rel` with `.` and `..` components resolved; `None` when it leaves the root.
+fn join_relative(base: &str, rel: &str) -> Option<String> {
+    let mut parts: Vec<&str> = base.split('/').filter(|p| !p.is_empty()).collect();
+    for part in rel.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            p => parts.push(p),
+        }
+    }
+    Some(parts.join("/"))
+}
+
+/// Edge for a JS/TS `import`/`export ... from`, given the source up to `from`.
+/// Counts named specifiers plus default and namespace bindings.
+fn js_import_edge(before_from: &str) -> ImportEdge {
+    let mut edge = ImportEdge::plain(ImportConfidence::High);
+    let Some(start) = before_from.rfind("import").max(before_from.rfind("export")) else {
+        return edge;
+    };
+    let clause = before_from[start + "import".len()..].trim();
+    if clause.contains(';') {
+        return edge;
     }
+    let (type_clause, clause) = match clause.strip_prefix("type ") {
+        Some(rest) => (true, rest.trim()),
+        None => (false, clause),
+    };
+    let mut all_type = true;
+    let bindings = match (clause.find('{'), clause.rfind('}')) {
+        (Some(open), Some(close)) if open < close => {
+            for item in clause[open + 1..close].split(',').map(str::trim).filter(|s| !s.is_empty())
+            {
+                edge.symbols += 1;
+                all_type &= item.starts_with("type ");
+            }
+            clause[..open].trim().trim_end_matches(',').trim()
+        }
+        _ => clause,
+    };
+    if !bindings.is_empty() {
+        // A default import or `* as ns`
+        edge.symbols += 1;
+        all_type = false;
+    }
+    edge.type_only = type_clause || (edge.symbols > 0 && all_type);
+    edge
+}
 
-    ImportGraph { imports, imported_by }
+/// Edge for a Python `from x import ...`, given the source after `import`.
+fn py_from_import_edge(rest: &str) -> ImportEdge {
+    let rest = rest.trim_start_matches([' ', '\t']);
+    let names = match rest.strip_prefix('(') {
+        Some(inner) => inner.split(')').next().unwrap_or(""),
+        None => rest.lines().next().unwrap_or("").split('#').next().unwrap_or(""),
+    };
+    let symbols = names
+        .split(',')
+        .map(|n| n.trim().trim_end_matches('\\').trim())
+        .filter(|n| !n.is_empty() && *n != "*")
+        .count();
+    ImportEdge { symbols: symbols as u32, ..ImportEdge::plain(ImportConfidence::High) }
+}
+
+/// Edge for a Rust `use crate::module...;`, given the source after the module name.
+/// `::{A, B}` counts two symbols, `::Item` one, and the bare module none.
+fn rust_use_edge(rest: &str) -> ImportEdge {
+    let path = rest.split(';').next().unwrap_or("").trim();
+    let mut edge = ImportEdge::plain(ImportConfidence::High);
+    let Some(tail) = path.strip_prefix("::") else {
+        return edge;
+    };
+    let tail = tail.trim();
+    if let Some(group) = tail.strip_prefix('{').and_then(|g| g.strip_suffix('}')) {
+        let mut depth = 0;
+        let mut item = String::new();
+        let mut items = Vec::new();
+        for c in group.chars() {
+            match c {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                ',' if depth == 0 => {
+                    items.push(std::mem::take(&mut item));
+                    continue;
+                }
+                _ => {}
+            }
+            item.push(c);
+        }
+        items.push(item);
+        edge.symbols = items
+            .iter()
+            .map(|i| i.trim())
+            .filter(|i| !i.is_empty() && *i != "self" && *i != "*")
+            .count() as u32;
+    } else if tail != "*" {
+        edge.symbols = 1;
+    }
+    edge
 }
 
 // ---------------------------------------------------------------------------
@@ -1163,7 +2065,7 @@ pub fn scan_imports(all_files: &[ScannedFile]) -> ImportGraph {
 /// Resolve imports that cross repository boundaries.
 /// For each repo, unresolved filenames are matched against other repos' files.
 pub fn resolve_cross_repo_imports(
-    repos: &std::collections::BTreeMap<String, crate::types::RepoState>,
+    repos: &std::collections::BTreeMap<String, std::sync::Arc<crate::types::RepoState>>,
 ) -> Vec<crate::types::CrossRepoEdge> {
     if repos.len() < 2 {
         return Vec::new();
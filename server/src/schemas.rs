@@ -0,0 +1,497 @@
+//! Schema definitions and their cross-references, indexed during scan for `cs_symbols`.
+//!
+//! Protocol Buffers (`message`, `enum`, `service`, `rpc`), OpenAPI/Swagger documents
+//! (`operationId`s and component schemas), and GraphQL SDL (types plus Query, Mutation,
+//! and Subscription fields) are parsed line by line. Source files are then searched for
+//! each name in its snake_case, camelCase, and PascalCase spellings; a hit right after a
+//! definition keyword (`fn`, `def`, `func`, `class`, `impl ... for`, ...) counts as an
+//! implementation, anything else as a use. Generated stubs (`*_pb2.py`, `*.pb.go`, ...)
+//! are skipped, since they mention every name.
+
+use rayon::prelude::*;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::types::ScannedFile;
+
+/// Files larger than this are skipped (likely generated or bundled).
+const MAX_SCHEMA_SCAN_BYTES: u64 = 512 * 1024;
+
+/// Names shorter than this are not cross-referenced; they match too much.
+const MIN_REF_NAME_LEN: usize = 4;
+
+/// Extensions searched for references to schema names.
+const CODE_EXTS: &[&str] = &[
+    "rs", "go", "py", "js", "mjs", "cjs", "jsx", "ts", "tsx", "java", "kt", "scala", "cs", "rb",
+    "php", "swift", "dart", "c", "cc", "cpp", "h", "hpp", "vue", "svelte",
+];
+
+/// Generated-code filename suffixes, skipped when searching for references.
+const GENERATED_SUFFIXES: &[&str] = &[
+    "_pb2.py",
+    "_pb2_grpc.py",
+    "_pb2.pyi",
+    ".pb.go",
+    "_grpc.pb.go",
+    ".pb.gw.go",
+    "_pb.js",
+    "_pb.d.ts",
+    "_grpc_pb.js",
+    ".pb.ts",
+    ".pb.h",
+    ".pb.cc",
+    ".generated.ts",
+    ".graphql.ts",
+];
+
+/// Schema language a symbol was defined in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SchemaFormat {
+    Proto,
+    OpenApi,
+    GraphQl,
+}
+
+impl SchemaFormat {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Proto => "proto",
+            Self::OpenApi => "openapi",
+            Self::GraphQl => "graphql",
+        }
+    }
+}
+
+/// A message, service, operation, or type defined in a schema file.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SchemaSymbol {
+    pub name: String,
+    /// `message`, `enum`, `service`, `rpc`, `operation`, `schema`, `type`, `input`,
+    /// `interface`, `union`, `scalar`, `query`, `mutation`, or `subscription`.
+    pub kind: String,
+    pub format: SchemaFormat,
+    pub path: String,
+    /// 1-based line number.
+    pub line: usize,
+    /// Enclosing service for an rpc, `METHOD /path` for an operation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+}
+
+/// A source line that mentions a schema symbol.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SchemaRef {
+    pub symbol: String,
+    pub path: String,
+    /// 1-based line number.
+    pub line: usize,
+    /// The line defines a function, method, or type with the symbol's name.
+    pub implements: bool,
+}
+
+/// Schema symbols and the code that references them.
+#[derive(Clone, Default)]
+pub struct SchemaIndex {
+    /// Sorted by path, then line.
+    pub symbols: Vec<SchemaSymbol>,
+    /// Sorted by symbol, then path and line.
+    pub refs: Vec<SchemaRef>,
+}
+
+impl SchemaIndex {
+    /// References to `symbol`.
+    pub fn refs_to<'a>(&'a self, symbol: &'a str) -> impl Iterator<Item = &'a SchemaRef> + 'a {
+        let start = self.refs.partition_point(|r| r.symbol.as_str() < symbol);
+        self.refs[start..].iter().take_while(move |r| r.symbol == symbol)
+    }
+}
+
+/// Schema format of a file from its extension, or for YAML/JSON, an `openapi`/`swagger`
+/// key in its content.
+pub fn schema_format(ext: &str, content: &str) -> Option<SchemaFormat> {
+    match ext {
+        "proto" => Some(SchemaFormat::Proto),
+        "graphql" | "gql" | "graphqls" => Some(SchemaFormat::GraphQl),
+        "yaml" | "yml" | "json" => {
+            static OPENAPI: OnceLock<Regex> = OnceLock::new();
+            let re = OPENAPI.get_or_init(|| {
+                Regex::new(r#"(?m)^\s*"?(?:openapi|swagger)"?\s*:\s*["']?\d"#).unwrap()
+            });
+            let head = &content[..content.floor_char_boundary(4096)];
+            re.is_match(head).then_some(SchemaFormat::OpenApi)
+        }
+        _ => None,
+    }
+}
+
+/// Whether a file could hold schema definitions (decided by content for YAML/JSON).
+pub fn is_schema_candidate(ext: &str) -> bool {
+    matches!(ext, "proto" | "graphql" | "gql" | "graphqls" | "yaml" | "yml" | "json")
+}
+
+/// Parse the symbols a schema file defines.
+pub fn parse_schema(rel_path: &str, format: SchemaFormat, content: &str) -> Vec<SchemaSymbol> {
+    let symbol = |name: &str, kind: &str, line: usize, parent: Option<String>| SchemaSymbol {
+        name: name.to_string(),
+        kind: kind.to_string(),
+        format,
+        path: rel_path.to_string(),
+        line,
+        parent,
+    };
+    let mut out = Vec::new();
+    match format {
+        SchemaFormat::Proto => {
+            static DEF: OnceLock<Regex> = OnceLock::new();
+            static RPC: OnceLock<Regex> = OnceLock::new();
+            let def =
+                DEF.get_or_init(|| Regex::new(r"^\s*(message|enum|service)\s+(\w+)").unwrap());
+            let rpc = RPC.get_or_init(|| Regex::new(r"^\s*rpc\s+(\w+)\s*\(").unwrap());
+            let mut service: Option<String> = None;
+            for (i, line) in content.lines().enumerate() {
+                if let Some(c) = def.captures(line) {
+                    if &c[1] == "service" {
+                        service = Some(c[2].to_string());
+                    }
+                    out.push(symbol(&c[2], &c[1], i + 1, None));
+                } else if let Some(c) = rpc.captures(line) {
+                    out.push(symbol(&c[1], "rpc", i + 1, service.clone()));
+                }
+            }
+        }
+        SchemaFormat::GraphQl => {
+            static DEF: OnceLock<Regex> = OnceLock::new();
+            static FIELD: OnceLock<Regex> = OnceLock::new();
+            let def = DEF.get_or_init(|| {
+                Regex::new(r"^\s*(?:extend\s+)?(type|input|interface|enum|union|scalar)\s+(\w+)")
+                    .unwrap()
+            });
+            let field = FIELD.get_or_init(|| Regex::new(r"^\s*(\w+)\s*[(:]").unwrap());
+            // Root operation type whose block we are in
+            let mut root: Option<&'static str> = None;
+            for (i, line) in content.lines().enumerate() {
+                if let Some(c) = def.captures(line) {
+                    root = match &c[2] {
+                        "Query" => Some("query"),
+                        "Mutation" => Some("mutation"),
+                        "Subscription" => Some("subscription"),
+                        name => {
+                            out.push(symbol(name, &c[1], i + 1, None));
+                            None
+                        }
+                    };
+                } else if line.trim_start().starts_with('}') {
+                    root = None;
+                } else if let (Some(kind), Some(c)) = (root, field.captures(line)) {
+                    out.push(symbol(&c[1], kind, i + 1, None));
+                }
+            }
+        }
+        SchemaFormat::OpenApi => parse_openapi(content, &mut out, &symbol),
+    }
+    out
+}
+
+/// Builds a symbol from name, kind, line, and parent, for the file being parsed.
+type SymbolCtor<'a> = dyn Fn(&str, &str, usize, Option<String>) -> SchemaSymbol + 'a;
+
+/// `operationId`s (with their `METHOD /path`) and component/definition schema names.
+fn parse_openapi(content: &str, out: &mut Vec<SchemaSymbol>, symbol: &SymbolCtor) {
+    static OP_ID: OnceLock<Regex> = OnceLock::new();
+    static PATH_KEY: OnceLock<Regex> = OnceLock::new();
+    static METHOD_KEY: OnceLock<Regex> = OnceLock::new();
+    static KEY: OnceLock<Regex> = OnceLock::new();
+    let op_id =
+        OP_ID.get_or_init(|| Regex::new(r#"^\s*"?operationId"?\s*:\s*["']?([\w.-]+)"#).unwrap());
+    let path_key = PATH_KEY.get_or_init(|| Regex::new(r#"^\s*["']?(/[^"':]*)["']?\s*:"#).unwrap());
+    let method_key = METHOD_KEY.get_or_init(|| {
+        Regex::new(r#"^\s*"?(get|put|post|delete|patch|head|options|trace)"?\s*:"#).unwrap()
+    });
+    let key = KEY.get_or_init(|| Regex::new(r#"^(\s*)"?([\w.-]+)"?\s*:"#).unwrap());
+
+    let mut path: Option<String> = None;
+    let mut method: Option<String> = None;
+    // Indent of the `schemas:`/`definitions:` key, and of its first child
+    let mut schemas_indent: Option<usize> = None;
+    let mut child_indent: Option<usize> = None;
+    for (i, line) in content.lines().enumerate() {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        if let Some(c) = path_key.captures(line) {
+            path = Some(c[1].to_string());
+            method = None;
+        } else if let Some(c) = method_key.captures(line) {
+            method = Some(c[1].to_uppercase());
+        }
+        if let Some(c) = op_id.captures(line) {
+            let parent = match (&method, &path) {
+                (Some(m), Some(p)) => Some(format!("{m} {p}")),
+                _ => None,
+            };
+            out.push(symbol(&c[1], "operation", i + 1, parent));
+        }
+
+        let Some(c) = key.captures(line) else { continue };
+        let indent = c[1].len();
+        match schemas_indent {
+            Some(parent) if indent <= parent => {
+                schemas_indent = None;
+                child_indent = None;
+            }
+            Some(_) => {
+                let child = *child_indent.get_or_insert(indent);
+                if indent == child {
+                    out.push(symbol(&c[2], "schema", i + 1, None));
+                }
+                continue;
+            }
+            None => {}
+        }
+        if matches!(&c[2], "schemas" | "definitions") {
+            schemas_indent = Some(indent);
+        }
+    }
+}
+
+/// Name spellings searched for in code: as written, snake_case, camelCase, PascalCase.
+fn spellings(name: &str) -> Vec<String> {
+    let mut words: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let chars: Vec<char> = name.chars().collect();
+    for (i, &c) in chars.iter().enumerate() {
+        if c == '_' || c == '-' || c == '.' {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            continue;
+        }
+        let boundary = c.is_uppercase()
+            && i > 0
+            && (chars[i - 1].is_lowercase()
+                || chars.get(i + 1).is_some_and(|n| n.is_lowercase())
+                    && chars[i - 1].is_uppercase());
+        if boundary && !current.is_empty() {
+            words.push(std::mem::take(&mut current));
+        }
+        current.extend(c.to_lowercase());
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    let capitalize = |w: &str| {
+        let mut cs = w.chars();
+        cs.next().map(|f| f.to_uppercase().chain(cs).collect::<String>()).unwrap_or_default()
+    };
+    let pascal: String = words.iter().map(|w| capitalize(w)).collect();
+    let camel = words
+        .iter()
+        .enumerate()
+        .map(|(i, w)| if i == 0 { w.clone() } else { capitalize(w) })
+        .collect();
+    let mut out = vec![name.to_string(), words.join("_"), camel, pascal];
+    out.retain(|s| {
+        s.len() >= MIN_REF_NAME_LEN && s.chars().all(|c| c.is_alphanumeric() || c == '_')
+    });
+    out.sort();
+    out.dedup();
+    out
+}
+
+/// Compiled search for every symbol spelling.
+pub struct RefMatcher {
+    re: Regex,
+    by_spelling: HashMap<String, Vec<String>>,
+}
+
+impl RefMatcher {
+    /// `None` when no symbol has a searchable name.
+    pub fn new(symbols: &[SchemaSymbol]) -> Option<Self> {
+        let mut by_spelling: HashMap<String, Vec<String>> = HashMap::new();
+        for s in symbols {
+            for spelling in spellings(&s.name) {
+                let names = by_spelling.entry(spelling).or_default();
+                if !names.contains(&s.name) {
+                    names.push(s.name.clone());
+                }
+            }
+        }
+        if by_spelling.is_empty() {
+            return None;
+        }
+        let mut alternatives: Vec<&str> = by_spelling.keys().map(String::as_str).collect();
+        // Longest first, so a name is not shadowed by a prefix of it
+        alternatives.sort_by(|a, b| b.len().cmp(&a.len()).then(a.cmp(b)));
+        let pattern = format!(r"\b(?:{})\b", alternatives.join("|"));
+        let re = RegexBuilder::new(&pattern).size_limit(64 << 20).build().ok()?;
+        Some(Self { re, by_spelling })
+    }
+
+    /// References in one file's content.
+    pub fn detect(&self, rel_path: &str, content: &str) -> Vec<SchemaRef> {
+        static IMPL: OnceLock<Regex> = OnceLock::new();
+        let impl_re = IMPL.get_or_init(|| {
+            Regex::new(
+                r"(?:\b(?:fn|def|function|class|struct|interface|trait|enum|type|record|async)|\bfunc(?:\s*\([^)]*\))?|\bimpl(?:<[^>]*>)?|(?:public|private|protected|override|static|async|void|Task(?:<[^>]*>)?)\s+[\w<>\[\]]+)\s+$",
+            )
+            .unwrap()
+        });
+        let mut out = Vec::new();
+        for (i, line) in content.lines().enumerate() {
+            let mut seen: Vec<&str> = Vec::new();
+            for m in self.re.find_iter(line) {
+                let implements = impl_re.is_match(&line[..m.start()]);
+                for symbol in self.by_spelling.get(m.as_str()).into_iter().flatten() {
+                    if seen.contains(&symbol.as_str()) {
+                        continue;
+                    }
+                    seen.push(symbol);
+                    out.push(SchemaRef {
+                        symbol: symbol.clone(),
+                        path: rel_path.to_string(),
+                        line: i + 1,
+                        implements,
+                    });
+                }
+            }
+        }
+        out
+    }
+}
+
+fn readable(file: &ScannedFile) -> Option<String> {
+    if file.abs_path.metadata().map(|m| m.len()).unwrap_or(0) > MAX_SCHEMA_SCAN_BYTES {
+        return None;
+    }
+    crate::encoding::read_to_string(&file.abs_path).ok()
+}
+
+/// Symbols defined by one file, if it is a schema.
+pub fn detect_file_symbols(file: &ScannedFile) -> Vec<SchemaSymbol> {
+    if !is_schema_candidate(&file.ext) {
+        return Vec::new();
+    }
+    let Some(content) = readable(file) else { return Vec::new() };
+    match schema_format(&file.ext, &content) {
+        Some(format) => parse_schema(&file.rel_path, format, &content),
+        None => Vec::new(),
+    }
+}
+
+/// References in one source file. Non-code and generated files yield nothing.
+pub fn detect_file_refs(matcher: &RefMatcher, file: &ScannedFile) -> Vec<SchemaRef> {
+    if !CODE_EXTS.contains(&file.ext.as_str())
+        || GENERATED_SUFFIXES.iter().any(|s| file.rel_path.ends_with(s))
+    {
+        return Vec::new();
+    }
+    match readable(file) {
+        Some(content) => matcher.detect(&file.rel_path, &content),
+        None => Vec::new(),
+    }
+}
+
+/// References across `files` to the given symbols, unsorted.
+pub fn scan_refs(symbols: &[SchemaSymbol], files: &[ScannedFile]) -> Vec<SchemaRef> {
+    match RefMatcher::new(symbols) {
+        Some(matcher) => {
+            files.par_iter().flat_map_iter(|f| detect_file_refs(&matcher, f)).collect()
+        }
+        None => Vec::new(),
+    }
+}
+
+/// Parse every schema file, then cross-reference the rest of the repo.
+pub fn scan_schemas(all_files: &[ScannedFile]) -> SchemaIndex {
+    let mut symbols: Vec<SchemaSymbol> =
+        all_files.par_iter().flat_map_iter(detect_file_symbols).collect();
+    sort_symbols(&mut symbols);
+    let mut refs = scan_refs(&symbols, all_files);
+    sort_refs(&mut refs);
+    SchemaIndex { symbols, refs }
+}
+
+/// Canonical ordering of the symbol table.
+pub fn sort_symbols(symbols: &mut [SchemaSymbol]) {
+    symbols.sort_by(|a, b| a.path.cmp(&b.path).then(a.line.cmp(&b.line)));
+}
+
+/// Canonical ordering of the reference table.
+pub fn sort_refs(refs: &mut [SchemaRef]) {
+    refs.sort_by(|a, b| {
+        a.symbol.cmp(&b.symbol).then(a.path.cmp(&b.path)).then(a.line.cmp(&b.line))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(symbols: &[SchemaSymbol]) -> Vec<(&str, &str, Option<&str>)> {
+        symbols.iter().map(|s| (s.name.as_str(), s.kind.as_str(), s.parent.as_deref())).collect()
+    }
+
+    #[test]
+    fn parses_proto_graphql_and_openapi_definitions() {
+        let proto = "syntax = \"proto3\";\nmessage GetUserRequest {\n  string id = 1;\n}\n\
+                     service UserService {\n  rpc GetUser(GetUserRequest) returns (User);\n}\n";
+        assert_eq!(
+            kinds(&parse_schema("api/user.proto", SchemaFormat::Proto, proto)),
+            vec![
+                ("GetUserRequest", "message", None),
+                ("UserService", "service", None),
+                ("GetUser", "rpc", Some("UserService")),
+            ]
+        );
+
+        let gql =
+            "type User {\n  id: ID!\n}\ntype Query {\n  user(id: ID!): User\n  users: [User]\n}\n\
+                   input NewUser {\n  name: String\n}\n";
+        assert_eq!(
+            kinds(&parse_schema("schema.graphql", SchemaFormat::GraphQl, gql)),
+            vec![
+                ("User", "type", None),
+                ("user", "query", None),
+                ("users", "query", None),
+                ("NewUser", "input", None),
+            ]
+        );
+
+        let yaml = "openapi: 3.0.0\npaths:\n  /users/{id}:\n    get:\n      operationId: getUser\n\
+                    components:\n  schemas:\n    User:\n      type: object\n      properties:\n\
+                    \x20       name:\n          type: string\n    Error:\n      type: object\n";
+        assert_eq!(schema_format("yaml", yaml), Some(SchemaFormat::OpenApi));
+        assert_eq!(
+            kinds(&parse_schema("openapi.yaml", SchemaFormat::OpenApi, yaml)),
+            vec![
+                ("getUser", "operation", Some("GET /users/{id}")),
+                ("User", "schema", None),
+                ("Error", "schema", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn references_match_name_spellings_and_flag_implementations() {
+        assert_eq!(spellings("GetUser"), vec!["GetUser", "getUser", "get_user"]);
+
+        let symbols = parse_schema(
+            "api/user.proto",
+            SchemaFormat::Proto,
+            "service UserService {\n  rpc GetUser(Req) returns (Resp);\n}\n",
+        );
+        let matcher = RefMatcher::new(&symbols).unwrap();
+        let go =
+            "func (s *server) GetUser(ctx context.Context) {}\nresp, err := client.GetUser(ctx)\n";
+        let refs = matcher.detect("server/user.go", go);
+        let found: Vec<(&str, usize, bool)> =
+            refs.iter().map(|r| (r.symbol.as_str(), r.line, r.implements)).collect();
+        assert_eq!(found, vec![("GetUser", 1, true), ("GetUser", 2, false)]);
+
+        let py = "def get_user(self, request, context):\n    pass\n";
+        assert!(matcher.detect("svc.py", py)[0].implements);
+    }
+}
@@ -0,0 +1,140 @@
+//! Named search scopes from `[scopes.<name>]` in `.codescope.toml`.
+//!
+//! ```toml
+//! [scopes.backend]
+//! path = ["server/", "crates/"]
+//! ext = ["rs"]
+//!
+//! [scopes.ui]
+//! path = "src/components"
+//! category = "src > components"
+//! ```
+//!
+//! `scope: "backend"` on `cs_search` and `cs_grep` keeps files under any of the scope's
+//! paths, with one of its extensions, and in its module category (each only when set), on
+//! top of explicit `path`/`ext`/`category` filters. `codescope --scope NAME` makes a scope
+//! the default for calls that name none.
+
+use std::collections::BTreeMap;
+use tracing::warn;
+
+use crate::types::ScanConfig;
+
+/// A saved combination of search filters.
+#[derive(Clone, Debug, Default)]
+pub struct Scope {
+    /// Path prefixes; a file matches if it is under any of them.
+    pub paths: Vec<String>,
+    /// Extensions without the dot, lowercase.
+    pub exts: Vec<String>,
+    /// Module category prefix (`server > src`).
+    pub category: Option<String>,
+}
+
+/// A string or array of strings, as `path` and `ext` accept.
+fn strings(value: Option<&toml::Value>) -> Vec<String> {
+    match value {
+        Some(toml::Value::String(s)) => vec![s.clone()],
+        Some(toml::Value::Array(a)) => {
+            a.iter().filter_map(|v| v.as_str()).map(str::to_string).collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+impl Scope {
+    /// Whether a file passes the scope's filters.
+    pub fn matches(&self, rel_path: &str, config: &ScanConfig) -> bool {
+        if !self.paths.is_empty() && !self.paths.iter().any(|p| rel_path.starts_with(p.as_str())) {
+            return false;
+        }
+        if !self.exts.is_empty() {
+            let filename = rel_path.rsplit('/').next().unwrap_or(rel_path);
+            let ext = match filename.rsplit_once('.') {
+                Some((stem, ext)) if !stem.is_empty() => ext.to_lowercase(),
+                _ => String::new(),
+            };
+            if !self.exts.contains(&ext) {
+                return false;
+            }
+        }
+        match &self.category {
+            Some(cat) => crate::scan::get_category_path(rel_path, config)
+                .join(" > ")
+                .starts_with(cat.as_str()),
+            None => true,
+        }
+    }
+
+    /// One-line summary for error messages and `cs_status`.
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if !self.paths.is_empty() {
+            parts.push(format!("path {}", self.paths.join(", ")));
+        }
+        if !self.exts.is_empty() {
+            parts.push(format!("ext {}", self.exts.join(", ")));
+        }
+        if let Some(cat) = &self.category {
+            parts.push(format!("category {cat}"));
+        }
+        if parts.is_empty() {
+            "everything".to_string()
+        } else {
+            parts.join("; ")
+        }
+    }
+}
+
+/// Parse the `[scopes]` table: one sub-table per scope. Entries that are not tables are
+/// skipped with a warning.
+pub fn parse_scopes(table: &toml::Table) -> BTreeMap<String, Scope> {
+    let mut scopes = BTreeMap::new();
+    for (name, value) in table {
+        let Some(t) = value.as_table() else {
+            warn!(scope = name.as_str(), "[scopes] entries must be tables — ignoring");
+            continue;
+        };
+        let scope = Scope {
+            paths: strings(t.get("path"))
+                .into_iter()
+                .map(|p| p.trim_start_matches("./").to_string())
+                .collect(),
+            exts: strings(t.get("ext"))
+                .into_iter()
+                .map(|e| e.trim().trim_start_matches('.').to_lowercase())
+                .collect(),
+            category: t.get("category").and_then(|v| v.as_str()).map(str::to_string),
+        };
+        scopes.insert(name.clone(), scope);
+    }
+    scopes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scopes_match_any_path_and_listed_extensions() {
+        let table: toml::Table = toml::from_str(
+            r#"
+            backend = { path = ["server/", "./crates/"], ext = [".rs", "TOML"] }
+            docs = { path = "docs" }
+            broken = "server/"
+            "#,
+        )
+        .unwrap();
+        let scopes = parse_scopes(&table);
+        assert_eq!(scopes.keys().collect::<Vec<_>>(), ["backend", "docs"]);
+
+        let config = ScanConfig::new(std::path::PathBuf::from("."));
+        let backend = &scopes["backend"];
+        assert!(backend.matches("server/src/main.rs", &config));
+        assert!(backend.matches("crates/core/Cargo.toml", &config));
+        assert!(!backend.matches("server/web/app.ts", &config));
+        assert!(!backend.matches("tools/gen.rs", &config));
+        assert!(scopes["docs"].matches("docs/guide.md", &config));
+        assert_eq!(backend.describe(), "path server/, crates/; ext rs, toml");
+    }
+}
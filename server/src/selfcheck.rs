@@ -0,0 +1,165 @@
+//! Startup self-check (`codescope --check`): a deployment smoke test.
+//!
+//! After the configured repos are scanned, each one gets a small battery of queries through
+//! the same tool handlers agents call: a `cs_search` for a file's name and a `cs_grep` for
+//! a word taken from it (both limited to that file's path), a `cs_imports` lookup of a known
+//! import edge, and a budgeted `cs_read`. With semantic search enabled, the repo's embedding
+//! cache must also load. The report is printed as JSON and the process exits non-zero when
+//! any check fails.
+
+use serde::Serialize;
+use serde_json::json;
+use std::time::Instant;
+
+use crate::types::{RepoState, ScannedFile, ServerState, SessionState};
+
+/// Token budget for the `budget` check's read.
+const BUDGET_TOKENS: u64 = 2000;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Fail,
+    /// Nothing to check, e.g. a repo without import edges.
+    Skip,
+}
+
+/// One query of the battery.
+#[derive(Serialize, Debug)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub ms: u64,
+    pub detail: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct RepoCheck {
+    pub repo: String,
+    pub files: usize,
+    pub checks: Vec<Check>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CheckReport {
+    /// No check failed and every configured repo was indexed.
+    pub ok: bool,
+    pub repos: Vec<RepoCheck>,
+    /// Configured repos that were not indexed.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub missing: Vec<String>,
+}
+
+/// Run the battery against every indexed repo of `state`.
+pub fn run(state: &ServerState, semantic: bool) -> CheckReport {
+    let repos: Vec<RepoCheck> = state
+        .repos
+        .values()
+        .map(|repo| RepoCheck {
+            repo: repo.name.clone(),
+            files: repo.all_files.len(),
+            checks: check_repo(state, repo, semantic),
+        })
+        .collect();
+    let missing: Vec<String> = state.pending.keys().cloned().collect();
+    let failed = repos.iter().flat_map(|r| &r.checks).any(|c| c.status == CheckStatus::Fail);
+    CheckReport { ok: !failed && missing.is_empty(), repos, missing }
+}
+
+fn check_repo(state: &ServerState, repo: &RepoState, semantic: bool) -> Vec<Check> {
+    let mut checks = Vec::new();
+    let sample = repo.all_files.iter().find(|f| f.skip_content.is_none() && f.origin.is_none());
+    checks.push(timed("scan", || match sample {
+        Some(_) => (CheckStatus::Pass, format!("{} files indexed", repo.all_files.len())),
+        None => (CheckStatus::Fail, "no searchable files indexed".to_string()),
+    }));
+    let Some(sample) = sample else { return checks };
+
+    checks.push(timed("search", || {
+        let stem = file_stem(&sample.rel_path);
+        let args = json!({ "query": stem, "path": sample.rel_path, "repo": repo.name });
+        expect_path(call(state, "cs_search", args), &sample.rel_path)
+    }));
+    checks.push(timed("grep", || match grep_word(sample) {
+        Some(word) => {
+            let args = json!({ "query": word, "path": sample.rel_path, "repo": repo.name });
+            expect_path(call(state, "cs_grep", args), &sample.rel_path)
+        }
+        None => (CheckStatus::Skip, format!("no word to grep for in {}", sample.rel_path)),
+    }));
+    checks.push(timed("imports", || {
+        let edge =
+            repo.import_graph.imports.iter().find_map(|(from, to)| Some((from, to.first()?)));
+        match edge {
+            Some((from, to)) => {
+                let args = json!({ "path": to, "repo": repo.name });
+                expect_path(call(state, "cs_imports", args), from)
+            }
+            None => (CheckStatus::Skip, "no import edges".to_string()),
+        }
+    }));
+    checks.push(timed("budget", || {
+        let args =
+            json!({ "paths": [sample.rel_path], "budget": BUDGET_TOKENS, "repo": repo.name });
+        expect_path(call(state, "cs_read", args), &sample.rel_path)
+    }));
+    if semantic {
+        checks.push(timed("semantic_cache", || semantic_cache(state, repo)));
+    }
+    checks
+}
+
+fn timed(name: &'static str, f: impl FnOnce() -> (CheckStatus, String)) -> Check {
+    let start = Instant::now();
+    let (status, detail) = f();
+    Check { name, status, ms: start.elapsed().as_millis() as u64, detail }
+}
+
+fn call(state: &ServerState, tool: &str, args: serde_json::Value) -> (String, bool) {
+    let mut session = Some(SessionState::new());
+    crate::mcp::handle_tool_call(state, tool, &args, &mut session)
+}
+
+/// Pass when the tool succeeded and its output mentions `path`.
+fn expect_path((out, is_error): (String, bool), path: &str) -> (CheckStatus, String) {
+    if is_error {
+        (CheckStatus::Fail, first_line(&out))
+    } else if out.contains(path) {
+        (CheckStatus::Pass, format!("found {path}"))
+    } else {
+        (CheckStatus::Fail, format!("{path} missing from results: {}", first_line(&out)))
+    }
+}
+
+fn first_line(text: &str) -> String {
+    text.lines().next().unwrap_or("").to_string()
+}
+
+fn file_stem(rel_path: &str) -> &str {
+    let name = rel_path.rsplit('/').next().unwrap_or(rel_path);
+    name.split('.').next().filter(|s| !s.is_empty()).unwrap_or(name)
+}
+
+/// First identifier of four or more characters in the file.
+fn grep_word(file: &ScannedFile) -> Option<String> {
+    let content = crate::encoding::read_to_string(&file.abs_path).ok()?;
+    content
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .find(|w| w.len() >= 4 && w.starts_with(|c: char| c.is_alphabetic()))
+        .map(String::from)
+}
+
+#[cfg(feature = "semantic")]
+fn semantic_cache(state: &ServerState, repo: &RepoState) -> (CheckStatus, String) {
+    match crate::semantic::check_cache(&repo.root, state.semantic_model.as_deref()) {
+        Ok(0) => (CheckStatus::Skip, "no embedding cache yet".to_string()),
+        Ok(files) => (CheckStatus::Pass, format!("{files} files cached")),
+        Err(e) => (CheckStatus::Fail, e),
+    }
+}
+
+#[cfg(not(feature = "semantic"))]
+fn semantic_cache(_state: &ServerState, _repo: &RepoState) -> (CheckStatus, String) {
+    (CheckStatus::Skip, "built without semantic search".to_string())
+}
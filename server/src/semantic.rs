@@ -85,48 +85,53 @@ struct Chunk {
 struct FileChunks {
     rel_path: String,
     file_size: u64,
-    mtime_secs: i64,
+    content_hash: u64,
     chunks: Vec<Chunk>,
 }
 
-/// File extensions worth embedding — source code that produces meaningful stubs.
+/// File extensions worth embedding — source code that produces meaningful stubs, including
+/// languages with a registered extractor.
 fn is_embeddable_ext(ext: &str) -> bool {
-    matches!(
-        ext,
-        "h" | "hpp"
-            | "hxx"
-            | "cpp"
-            | "cxx"
-            | "cc"
-            | "c"
-            | "cs"
-            | "java"
-            | "kt"
-            | "scala"
-            | "rs"
-            | "go"
-            | "js"
-            | "ts"
-            | "jsx"
-            | "tsx"
-            | "mjs"
-            | "cjs"
-            | "swift"
-            | "usf"
-            | "ush"
-            | "hlsl"
-            | "glsl"
-            | "vert"
-            | "frag"
-            | "comp"
-            | "wgsl"
-            | "py"
-            | "rb"
-            | "d"
-            | "ps1"
-            | "psm1"
-            | "psd1"
-    )
+    crate::stubs::registered_ext(ext).is_some()
+        || matches!(
+            ext,
+            "h" | "hpp"
+                | "hxx"
+                | "cpp"
+                | "cxx"
+                | "cc"
+                | "c"
+                | "cs"
+                | "java"
+                | "kt"
+                | "scala"
+                | "rs"
+                | "go"
+                | "js"
+                | "ts"
+                | "jsx"
+                | "tsx"
+                | "mjs"
+                | "cjs"
+                | "swift"
+                | "usf"
+                | "ush"
+                | "hlsl"
+                | "glsl"
+                | "vert"
+                | "frag"
+                | "comp"
+                | "wgsl"
+                | "py"
+                | "rb"
+                | "php"
+                | "ex"
+                | "exs"
+                | "d"
+                | "ps1"
+                | "psm1"
+                | "psd1"
+        )
 }
 
 /// Directories to skip during semantic indexing.
@@ -207,27 +212,29 @@ fn path_context(rel_path: &str) -> String {
     }
 }
 
-fn extract_chunks_by_file(files: &[ScannedFile], max_chunk_chars: usize) -> Vec<FileChunks> {
+fn extract_chunks_by_file(
+    files: &[ScannedFile],
+    noise: &crate::noise::NoiseConfig,
+    max_chunk_chars: usize,
+) -> Vec<FileChunks> {
     use rayon::prelude::*;
 
     files
         .par_iter()
-        .filter(|file| is_embeddable_ext(&file.ext))
+        .filter(|file| file.is_searchable())
+        .filter(|file| !noise.skips_embedding(&file.rel_path))
+        .filter(|file| is_embeddable_ext(crate::lang::file_stub_ext(file.lang, &file.rel_path)))
         .filter(|file| !skip_for_semantic(&file.rel_path))
         .filter_map(|file| {
             let meta = std::fs::metadata(&file.abs_path).ok()?;
             if meta.len() > MAX_FILE_SIZE {
                 return None;
             }
-            let mtime_secs = meta
-                .modified()
-                .ok()
-                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                .map(|d| d.as_secs() as i64)
-                .unwrap_or(0);
-
-            let content = std::fs::read_to_string(&file.abs_path).ok()?;
-            let stubs = extract_stubs(&content, &file.ext);
+            let bytes = std::fs::read(&file.abs_path).ok()?;
+            let content_hash = xxhash_rust::xxh3::xxh3_64(&bytes);
+            let (content, _) = crate::encoding::decode(bytes).ok()?;
+            let stubs =
+                extract_stubs(&content, crate::lang::file_stub_ext(file.lang, &file.rel_path));
             if stubs.trim().is_empty() {
                 return None;
             }
@@ -250,7 +257,7 @@ fn extract_chunks_by_file(files: &[ScannedFile], max_chunk_chars: usize) -> Vec<
             Some(FileChunks {
                 rel_path: file.rel_path.clone(),
                 file_size: meta.len(),
-                mtime_secs,
+                content_hash,
                 chunks,
             })
         })
@@ -428,18 +435,18 @@ fn encode_batch(
 //
 // Binary format (append-safe):
 //   Header:  magic[4] + version[2] + dim[2] + model_name_len[2] + model_name
-//   Entry*:  path_len[4] + path + file_size[8] + mtime[8] + n_chunks[4]
+//   Entry*:  path_len[4] + path + file_size[8] + content_hash[8] + n_chunks[4]
 //            + per chunk: start_line[4] + snippet_len[2] + snippet + embedding[dim*4]
 //
 // Later entries for the same path supersede earlier ones (HashMap insert).
 
 const CACHE_MAGIC: &[u8; 4] = b"CSEM";
-const CACHE_VERSION: u16 = 2; // bumped: chunks now include path context + doc comments
+const CACHE_VERSION: u16 = 3; // bumped: entries keyed by content hash instead of mtime
 
 /// Cached embeddings for one source file.
 struct CachedFile {
     file_size: u64,
-    mtime_secs: i64,
+    content_hash: u64,
     chunks: Vec<(ChunkMeta, Vec<f32>)>,
 }
 
@@ -538,7 +545,10 @@ fn write_cache_meta(repo_root: &Path, model: &str, chunks: usize) {
             "chunks": chunks,
             "built": chrono_now_iso(),
         });
-        let _ = std::fs::write(&meta_path, serde_json::to_string_pretty(&meta).unwrap_or_default());
+        let _ = crate::atomic::write(
+            &meta_path,
+            serde_json::to_string_pretty(&meta).unwrap_or_default(),
+        );
     }
 }
 
@@ -550,17 +560,38 @@ fn chrono_now_iso() -> String {
     format!("{secs}")
 }
 
+/// Files with cached embeddings for `repo_root` under `model_name`; 0 when the repo has
+/// no cache yet. Errors when a cache file exists but nothing in it loads (corrupt, an older
+/// cache version, or built with another model).
+pub fn check_cache(repo_root: &Path, model_name: Option<&str>) -> Result<usize, String> {
+    let dim = resolve_model(model_name).dim;
+    let stored_model = model_name.unwrap_or("minilm");
+    let Some(path) =
+        [cache_path(repo_root), legacy_cache_path(repo_root)].into_iter().find(|p| p.exists())
+    else {
+        return Ok(0);
+    };
+    match load_cache(&path, dim, stored_model).len() {
+        0 => Err(format!("{} does not load for model {stored_model}", path.display())),
+        files => Ok(files),
+    }
+}
+
 fn load_cache(
     path: &Path,
     expected_dim: usize,
     expected_model: &str,
 ) -> HashMap<String, CachedFile> {
     let mut map = HashMap::new();
-    let file = match std::fs::File::open(path) {
-        Ok(f) => f,
-        Err(_) => return map,
+    let mut r = match crate::crypt::open(path) {
+        Ok(r) => r,
+        Err(e) => {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                tracing::warn!(error = %e, "Cannot read embedding cache");
+            }
+            return map;
+        }
     };
-    let mut r = std::io::BufReader::new(file);
 
     // Header
     let mut magic = [0u8; 4];
@@ -614,7 +645,7 @@ fn load_cache(
         if r.read_exact(&mut buf8).is_err() {
             break;
         }
-        let mtime_secs = i64::from_le_bytes(buf8);
+        let content_hash = u64::from_le_bytes(buf8);
 
         if r.read_exact(&mut buf4).is_err() {
             break;
@@ -659,7 +690,7 @@ fn load_cache(
             break; // truncated entry — stop reading, keep what we have
         }
         // Later entries for same path overwrite earlier ones
-        map.insert(rel_path, CachedFile { file_size, mtime_secs, chunks });
+        map.insert(rel_path, CachedFile { file_size, content_hash, chunks });
     }
 
     map
@@ -675,18 +706,25 @@ fn write_cache_header(w: &mut impl IoWrite, dim: usize, model_name: &str) -> std
     Ok(())
 }
 
+/// Replace the previous cache file with the one just written.
+fn commit_cache(w: &mut crate::atomic::AtomicFile) {
+    if let Err(e) = w.commit() {
+        tracing::warn!(error = %e, "Failed to write embedding cache");
+    }
+}
+
 fn write_cache_entry(
     w: &mut impl IoWrite,
     rel_path: &str,
     file_size: u64,
-    mtime_secs: i64,
+    content_hash: u64,
     chunks: &[(ChunkMeta, Vec<f32>)],
 ) -> std::io::Result<()> {
     let path_bytes = rel_path.as_bytes();
     w.write_all(&(path_bytes.len() as u32).to_le_bytes())?;
     w.write_all(path_bytes)?;
     w.write_all(&file_size.to_le_bytes())?;
-    w.write_all(&mtime_secs.to_le_bytes())?;
+    w.write_all(&content_hash.to_le_bytes())?;
     w.write_all(&(chunks.len() as u32).to_le_bytes())?;
     for (meta, emb) in chunks {
         w.write_all(&(meta.start_line as u32).to_le_bytes())?;
@@ -720,11 +758,13 @@ fn make_snippet(text: &str) -> String {
 /// Build a semantic index from scanned files.
 ///
 /// Loads per-file cache from `.codescope/semantic.cache`. Files with matching
-/// (size, mtime) use cached embeddings. Only changed/new files are embedded.
+/// (size, content hash) use cached embeddings, so touched but unedited files are not
+/// re-embedded. Only changed/new files are embedded.
 /// Cache entries are written progressively — if interrupted, completed files
-/// survive for the next startup.
+/// survive for the next startup. Files that `[noise]` scores at 0.5 or more are skipped.
 pub fn build_semantic_index(
     files: &[ScannedFile],
+    noise: &crate::noise::NoiseConfig,
     model_name: Option<&str>,
     progress: &crate::types::SemanticProgress,
     repo_root: &Path,
@@ -734,7 +774,7 @@ pub fn build_semantic_index(
     // Phase 1: Extract chunks grouped by file
     progress.status.store(1, Relaxed);
     let model_config = resolve_model(model_name);
-    let file_chunks = extract_chunks_by_file(files, model_config.max_chunk_chars);
+    let file_chunks = extract_chunks_by_file(files, noise, model_config.max_chunk_chars);
 
     let total_chunks: usize = file_chunks.iter().map(|fc| fc.chunks.len()).sum();
     if total_chunks == 0 {
@@ -780,7 +820,7 @@ pub fn build_semantic_index(
 
     for fc in &file_chunks {
         if let Some(entry) = cache.get(&fc.rel_path) {
-            if entry.file_size == fc.file_size && entry.mtime_secs == fc.mtime_secs {
+            if entry.file_size == fc.file_size && entry.content_hash == fc.content_hash {
                 for (meta, emb) in &entry.chunks {
                     cached_embs.extend_from_slice(emb);
                     cached_meta.push(ChunkMeta {
@@ -804,34 +844,35 @@ pub fn build_semantic_index(
         "Embedding cache status"
     );
 
-    // Phase 3: Open cache file for progressive writes
-    // Write header + all cache-hit entries first, then append as workers complete files.
-    let cache_writer = {
-        if let Some(parent) = cp.parent() {
-            let _ = std::fs::create_dir_all(parent);
-        }
-        match std::fs::File::create(&cp) {
-            Ok(f) => {
-                let mut w = std::io::BufWriter::new(f);
+    // Phase 3: Open the replacement cache file
+    // Write header + all cache-hit entries first, then the newly embedded files once the
+    // workers finish. It replaces the old cache only when complete.
+    // Another server holding the repo lock owns the cache; use it without writing it
+    let write_cache = crate::locks::is_writer(repo_root);
+    let mut cache_writer = if !write_cache {
+        None
+    } else {
+        match crate::atomic::AtomicFile::create_cache(&cp) {
+            Ok(mut w) => {
                 if write_cache_header(&mut w, model_config.dim, stored_model).is_err() {
                     tracing::warn!("Failed to write embedding cache header");
                 }
                 // Write cache-hit entries
                 for fc in &file_chunks {
                     if let Some(entry) = cache.get(&fc.rel_path) {
-                        if entry.file_size == fc.file_size && entry.mtime_secs == fc.mtime_secs {
+                        if entry.file_size == fc.file_size && entry.content_hash == fc.content_hash
+                        {
                             let _ = write_cache_entry(
                                 &mut w,
                                 &fc.rel_path,
                                 entry.file_size,
-                                entry.mtime_secs,
+                                entry.content_hash,
                                 &entry.chunks,
                             );
                         }
                     }
                 }
-                let _ = w.flush();
-                Some(std::sync::Mutex::new(w))
+                Some(w)
             }
             Err(e) => {
                 tracing::warn!(error = %e, "Cannot write embedding cache");
@@ -843,9 +884,14 @@ pub fn build_semantic_index(
 
     // Fast path: everything cached
     if to_embed.is_empty() {
+        if let Some(w) = cache_writer.as_mut() {
+            commit_cache(w);
+        }
         progress.status.store(3, Relaxed);
         tracing::info!(chunks = cache_hits, "Semantic index fully cached, loaded instantly");
-        write_cache_meta(repo_root, stored_model, cached_meta.len());
+        if write_cache {
+            write_cache_meta(repo_root, stored_model, cached_meta.len());
+        }
         return Some(SemanticIndex {
             embeddings: cached_embs,
             chunk_meta: cached_meta,
@@ -896,7 +942,6 @@ pub fn build_semantic_index(
 
     let batch_counter = std::sync::atomic::AtomicUsize::new(0);
     let model_config = &model_config;
-    let cache_writer = &cache_writer;
     let to_embed_ref = &to_embed;
 
     // Per-file result accumulator: (embeddings, complete?)
@@ -978,22 +1023,14 @@ pub fn build_semantic_index(
     });
 
     // Write cache entries for all embedded files
-    if let Some(ref writer) = cache_writer {
-        if let Ok(mut w) = writer.lock() {
-            for (fi, fc) in to_embed.iter().enumerate() {
-                let results = file_results[fi].lock().unwrap();
-                if results.len() == fc.chunks.len() {
-                    let _ = write_cache_entry(
-                        &mut *w,
-                        &fc.rel_path,
-                        fc.file_size,
-                        fc.mtime_secs,
-                        &results,
-                    );
-                }
+    if let Some(w) = cache_writer.as_mut() {
+        for (fi, fc) in to_embed.iter().enumerate() {
+            let results = file_results[fi].lock().unwrap();
+            if results.len() == fc.chunks.len() {
+                let _ = write_cache_entry(w, &fc.rel_path, fc.file_size, fc.content_hash, &results);
             }
-            let _ = w.flush();
         }
+        commit_cache(w);
     }
 
     let results = worker_results;
@@ -1021,7 +1058,9 @@ pub fn build_semantic_index(
     );
 
     // Write meta.json alongside the cache for debugging
-    write_cache_meta(repo_root, stored_model, chunk_meta.len());
+    if write_cache {
+        write_cache_meta(repo_root, stored_model, chunk_meta.len());
+    }
 
     Some(SemanticIndex {
         embeddings: all_embeddings,
@@ -0,0 +1,73 @@
+//! Did-you-mean corrections for search queries.
+//!
+//! When `cs_search` or `cs_grep` finds nothing, each query word the index has never seen
+//! is matched against the repo vocabulary (the terms counted by
+//! [`crate::types::TermDocFreq`]) by edit distance. The closest term wins, ties going to
+//! the one more files contain. The tools then retry with the corrected query, or with
+//! `autocorrect: false` only suggest it.
+
+use std::collections::HashMap;
+
+use crate::types::RepoState;
+
+/// Words shorter than this are never corrected; too many short terms are one edit apart.
+const MIN_WORD_LEN: usize = 4;
+
+/// Maximum edits for a correction of a word of `len` bytes.
+fn max_distance(len: usize) -> usize {
+    if len <= 5 {
+        1
+    } else {
+        2
+    }
+}
+
+/// Closest vocabulary term for a word no indexed file contains, if any is near enough.
+fn correct_word(repos: &[&RepoState], word: &str) -> Option<String> {
+    if word.len() < MIN_WORD_LEN || !word.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return None;
+    }
+    let word = word.to_lowercase();
+    if repos.iter().any(|r| r.term_doc_freq.doc_freq(&word) > 0) {
+        return None;
+    }
+    let max = max_distance(word.len());
+    // term → (distance, files containing it across repos)
+    let mut candidates: HashMap<&str, (usize, usize)> = HashMap::new();
+    for repo in repos {
+        for (term, df) in repo.term_doc_freq.terms() {
+            if term.len().abs_diff(word.len()) > max {
+                continue;
+            }
+            if let Some(entry) = candidates.get_mut(term) {
+                entry.1 += df;
+                continue;
+            }
+            let dist = crate::edit_distance(&word, term);
+            if dist <= max {
+                candidates.insert(term, (dist, df));
+            }
+        }
+    }
+    candidates
+        .into_iter()
+        .min_by(|a, b| a.1 .0.cmp(&b.1 .0).then(b.1 .1.cmp(&a.1 .1)).then(a.0.cmp(b.0)))
+        .map(|(term, _)| term.to_string())
+}
+
+/// The query with every unknown word replaced by its closest known term, or `None` when
+/// no word needs (or has) a correction.
+pub fn correct_query(repos: &[&RepoState], query: &str) -> Option<String> {
+    let mut corrected = false;
+    let words: Vec<String> = query
+        .split_whitespace()
+        .map(|w| match correct_word(repos, w) {
+            Some(c) => {
+                corrected = true;
+                c
+            }
+            None => w.to_string(),
+        })
+        .collect();
+    corrected.then(|| words.join(" "))
+}
@@ -0,0 +1,648 @@
+//! Typed server status behind `cs_status` and `/health`.
+//!
+//! [`collect`] gathers the components asked for: `repos` (index sizes, languages, scan
+//! timings, scopes, and repos still indexing), `memory`, `semantic`, `session`, and
+//! `watch`. `cs_status` renders the report as text with [`format_status`], or returns it
+//! as JSON with `format=json`; `/health` serves the same structures, so both always
+//! report the same numbers.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::memory::{format_bytes, MemoryUsage};
+use crate::types::{ScanStage, ServerState, SessionState};
+
+/// Report components, in output order.
+pub const COMPONENTS: &[&str] = &["repos", "memory", "semantic", "session", "watch"];
+
+/// The components a report includes.
+#[derive(Clone, Copy, Debug)]
+pub struct Detail {
+    pub repos: bool,
+    pub memory: bool,
+    pub semantic: bool,
+    pub session: bool,
+    pub watch: bool,
+}
+
+impl Detail {
+    pub const ALL: Detail =
+        Detail { repos: true, memory: true, semantic: true, session: true, watch: true };
+
+    /// Parse a `detail` argument: one component or several separated by `,` or `|`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut detail =
+            Detail { repos: false, memory: false, semantic: false, session: false, watch: false };
+        for part in spec.split([',', '|']).map(str::trim).filter(|p| !p.is_empty()) {
+            match part {
+                "repos" => detail.repos = true,
+                "memory" => detail.memory = true,
+                "semantic" => detail.semantic = true,
+                "session" => detail.session = true,
+                "watch" => detail.watch = true,
+                other => {
+                    return Err(format!(
+                        "Unknown status detail '{other}'. Use: {}",
+                        COMPONENTS.join(", ")
+                    ))
+                }
+            }
+        }
+        Ok(detail)
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusReport {
+    pub version: &'static str,
+    pub generation: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repos: Option<ReposStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory: Option<MemoryStatus>,
+    /// Semantic index state per repo; empty when built without the `semantic` feature.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub semantic: Option<BTreeMap<String, SemanticStatus>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session: Option<SessionStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub watch: Option<WatchStatus>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ReposStatus {
+    pub indexed: Vec<RepoStatus>,
+    /// Repos whose initial scan is running, queued, or waiting for a first request.
+    pub indexing: Vec<PendingStatus>,
+    /// Share of the startup queue already indexed, 0–100.
+    pub percent_done: usize,
+    pub total_files: usize,
+    pub cross_repo_edges: usize,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RepoStatus {
+    pub name: String,
+    pub root: String,
+    /// `[profile.<name>]` the repo was scanned with.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// `CODESCOPE_<KEY>` environment variables that override `.codescope.toml`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub env_overrides: Vec<String>,
+    pub files: usize,
+    /// Lines of the files content search reads ([`crate::scan::compute_stats`]).
+    pub lines: u64,
+    pub modules: usize,
+    pub import_edges: usize,
+    /// File count per language, most common first.
+    pub languages: Vec<LanguageCount>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspace: Option<WorkspaceStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub codeowners: Option<CodeOwnersStatus>,
+    pub binary_files: usize,
+    pub minified_files: usize,
+    /// Files `cs_search`/`cs_grep` skip unless `include_generated` is set.
+    pub generated_files: usize,
+    pub vendored_files: usize,
+    pub scan: ScanStatus,
+    /// The server that writes this repo's caches, when it is not this one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub caches_written_by: Option<String>,
+    /// Scope name → description, with the default scope flagged.
+    pub scopes: Vec<ScopeStatus>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct LanguageCount {
+    pub language: &'static str,
+    pub files: usize,
+}
+
+#[derive(Serialize, Debug)]
+pub struct WorkspaceStatus {
+    pub members: usize,
+    /// Workspace kinds present (`cargo`, `npm`, `pnpm`, `go`).
+    pub kinds: Vec<&'static str>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CodeOwnersStatus {
+    /// The `CODEOWNERS` file in use.
+    pub source: String,
+    pub rules: usize,
+    /// Files no rule assigns an owner.
+    pub unowned: usize,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanStatus {
+    pub total_ms: u64,
+    pub stages: Vec<ScanStage>,
+    /// `walk 120ms, imports 340ms, ...`.
+    #[serde(skip)]
+    summary: String,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ScopeStatus {
+    pub name: String,
+    pub default: bool,
+    pub description: String,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingStatus {
+    pub name: String,
+    pub root: String,
+    /// `scanning`, `queued`, `lazy`, or `unavailable` (root directory missing).
+    pub state: &'static str,
+    /// Place in the startup queue, for queued repos.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<usize>,
+    /// Stage and file counts of a running scan.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub progress: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryStatus {
+    pub total_bytes: usize,
+    pub repos: BTreeMap<String, RepoMemory>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RepoMemory {
+    pub total_bytes: usize,
+    pub limit_mb: Option<usize>,
+    pub components: MemoryUsage,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticStatus {
+    /// `idle`, `extracting chunks`, `embedding`, `ready`, `failed`, or `evicted`.
+    pub status: &'static str,
+    pub device: String,
+    pub chunks: usize,
+    pub completed_batches: usize,
+    pub total_batches: usize,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionStatus {
+    pub elapsed_secs: u64,
+    /// Paths read, oldest first.
+    pub files_read: Vec<String>,
+    pub tokens_served: usize,
+    pub output_profile: &'static str,
+}
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchStatus {
+    /// Whether the file watcher is running.
+    pub active: bool,
+    /// Debounced batches of changes processed.
+    pub batches: usize,
+    pub updated: usize,
+    pub removed: usize,
+    /// Files whose events left their content unchanged.
+    pub unchanged: usize,
+    /// Seconds since the last batch, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_change_secs_ago: Option<u64>,
+}
+
+/// Gather the components of `detail` from a state snapshot and, for `session`, the
+/// caller's session.
+pub fn collect(
+    state: &ServerState,
+    session: Option<&SessionState>,
+    detail: Detail,
+) -> StatusReport {
+    StatusReport {
+        version: env!("CARGO_PKG_VERSION"),
+        generation: state.generation,
+        repos: detail.repos.then(|| repos_status(state)),
+        memory: detail.memory.then(|| memory_status(state)),
+        semantic: detail.semantic.then(|| semantic_status(state)),
+        session: if detail.session { session.map(session_status) } else { None },
+        watch: detail.watch.then(|| state.watch.status()),
+    }
+}
+
+/// Indexed and still-indexing repos.
+pub fn repos_status(state: &ServerState) -> ReposStatus {
+    let indexed = state.repos.values().map(|repo| {
+        let mut lang_counts: BTreeMap<&'static str, usize> = BTreeMap::new();
+        let mut lines = 0;
+        for f in &repo.all_files {
+            if f.is_searchable() {
+                lines += u64::from(f.lines);
+            }
+            if let Some(lang) = f.lang {
+                *lang_counts.entry(lang).or_default() += 1;
+            }
+        }
+        let mut languages: Vec<LanguageCount> = lang_counts
+            .into_iter()
+            .map(|(language, files)| LanguageCount { language, files })
+            .collect();
+        languages.sort_by(|a, b| b.files.cmp(&a.files));
+
+        let members = &repo.config.workspace_members;
+        let workspace = (!members.is_empty()).then(|| {
+            let mut kinds: Vec<&'static str> = Vec::new();
+            for m in members {
+                if !kinds.contains(&m.kind) {
+                    kinds.push(m.kind);
+                }
+            }
+            WorkspaceStatus { members: members.len(), kinds }
+        });
+        let codeowners = repo.config.codeowners.source.as_ref().map(|source| CodeOwnersStatus {
+            source: source.clone(),
+            rules: repo.config.codeowners.rules.len(),
+            unowned: repo.all_files.iter().filter(|f| f.owners.is_none()).count(),
+        });
+        let skipped = |kind| repo.all_files.iter().filter(|f| f.skip_content == Some(kind)).count();
+        let origin = |kind| repo.all_files.iter().filter(|f| f.origin == Some(kind)).count();
+        let caches_written_by = (!crate::locks::is_writer(&repo.root)).then(|| {
+            match crate::locks::writer_pid(&repo.root) {
+                Some(pid) => format!("server pid {pid}"),
+                None => "another server".to_string(),
+            }
+        });
+        let scopes = repo
+            .config
+            .scopes
+            .iter()
+            .map(|(name, scope)| ScopeStatus {
+                name: name.clone(),
+                default: state.default_scope.as_deref() == Some(name),
+                description: scope.describe(),
+            })
+            .collect();
+        RepoStatus {
+            name: repo.name.clone(),
+            root: repo.root.display().to_string(),
+            profile: repo.config.profile.clone(),
+            env_overrides: repo
+                .config
+                .env_overrides
+                .iter()
+                .map(|k| crate::config_env_var(k))
+                .collect(),
+            files: repo.all_files.len(),
+            lines,
+            modules: repo.manifest.len(),
+            import_edges: repo.import_graph.imports.len(),
+            languages,
+            workspace,
+            codeowners,
+            binary_files: skipped("binary"),
+            minified_files: skipped("minified"),
+            generated_files: origin("generated"),
+            vendored_files: origin("vendored"),
+            scan: ScanStatus {
+                total_ms: repo.scan_time_ms,
+                stages: repo.scan_profile.stages.clone(),
+                summary: repo.scan_profile.summary(),
+            },
+            caches_written_by,
+            scopes,
+        }
+    });
+    let indexed: Vec<RepoStatus> = indexed.collect();
+
+    let mut pending: Vec<&crate::indexing::PendingRepo> =
+        state.pending.values().map(|p| &**p).collect();
+    pending.sort_by_key(|p| (!p.is_queued(), !p.is_requested(), p.priority));
+    let indexing = pending
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let (state, position, progress) = if p.is_missing() {
+                ("unavailable", None, None)
+            } else if !p.is_queued() {
+                ("lazy", None, None)
+            } else if i == 0 || p.lazy {
+                ("scanning", None, p.progress.summary())
+            } else {
+                ("queued", Some(i), None)
+            };
+            PendingStatus {
+                name: p.name.clone(),
+                root: p.root.display().to_string(),
+                state,
+                position,
+                progress,
+            }
+        })
+        .collect();
+
+    ReposStatus {
+        total_files: indexed.iter().map(|r| r.files).sum(),
+        indexed,
+        indexing,
+        percent_done: crate::indexing::percent_done(state),
+        cross_repo_edges: state.cross_repo_edges.len(),
+    }
+}
+
+/// Approximate index memory per repo.
+pub fn memory_status(state: &ServerState) -> MemoryStatus {
+    let repos: BTreeMap<String, RepoMemory> = state
+        .repos
+        .values()
+        .map(|r| {
+            let usage = crate::memory::repo_memory_usage(r);
+            let memory = RepoMemory {
+                total_bytes: usage.total(),
+                limit_mb: r.config.max_memory_mb,
+                components: usage,
+            };
+            (r.name.clone(), memory)
+        })
+        .collect();
+    MemoryStatus { total_bytes: repos.values().map(|m| m.total_bytes).sum(), repos }
+}
+
+/// Semantic index state per repo.
+#[cfg(feature = "semantic")]
+pub fn semantic_status(state: &ServerState) -> BTreeMap<String, SemanticStatus> {
+    use std::sync::atomic::Ordering::Relaxed;
+    state
+        .repos
+        .values()
+        .map(|r| {
+            let sp = &r.semantic_progress;
+            let status = SemanticStatus {
+                status: sp.status_label(),
+                device: sp.device.read().unwrap().clone(),
+                chunks: sp.total_chunks.load(Relaxed),
+                completed_batches: sp.completed_batches.load(Relaxed),
+                total_batches: sp.total_batches.load(Relaxed),
+            };
+            (r.name.clone(), status)
+        })
+        .collect()
+}
+
+#[cfg(not(feature = "semantic"))]
+pub fn semantic_status(_state: &ServerState) -> BTreeMap<String, SemanticStatus> {
+    BTreeMap::new()
+}
+
+fn session_status(s: &SessionState) -> SessionStatus {
+    let mut sorted: Vec<(&String, &std::time::Instant)> = s.files_read.iter().collect();
+    sorted.sort_by_key(|(_, t)| *t);
+    SessionStatus {
+        elapsed_secs: s.started_at.elapsed().as_secs(),
+        files_read: sorted.into_iter().map(|(p, _)| p.clone()).collect(),
+        tokens_served: s.total_tokens_served,
+        output_profile: s.output_profile.name(),
+    }
+}
+
+/// Render a report as the `cs_status` text.
+pub fn format_status(report: &StatusReport) -> String {
+    let mut out = String::new();
+    let (no_memory, no_semantic) = (BTreeMap::new(), BTreeMap::new());
+    let memory = report.memory.as_ref().map(|m| &m.repos).unwrap_or(&no_memory);
+    let semantic = report.semantic.as_ref().unwrap_or(&no_semantic);
+    let fmt = format_bytes;
+
+    if let Some(repos) = &report.repos {
+        let repo_count = repos.indexed.len();
+        out.push_str(&format!(
+            "CodeScope v{} — {repo_count} repositor{} indexed",
+            report.version,
+            if repo_count == 1 { "y" } else { "ies" }
+        ));
+        let count = |state: &str| repos.indexing.iter().filter(|p| p.state == state).count();
+        let (lazy, unavailable) = (count("lazy"), count("unavailable"));
+        let indexing = repos.indexing.len() - lazy - unavailable;
+        if indexing > 0 {
+            out.push_str(&format!(", {indexing} indexing ({}% done)", repos.percent_done));
+        }
+        if lazy > 0 {
+            out.push_str(&format!(", {lazy} lazy"));
+        }
+        if unavailable > 0 {
+            out.push_str(&format!(", {unavailable} unavailable"));
+        }
+        out.push_str("\n\n");
+    } else {
+        out.push_str(&format!("CodeScope v{}\n\n", report.version));
+    }
+
+    let names: Vec<&str> = match &report.repos {
+        Some(repos) => repos.indexed.iter().map(|r| r.name.as_str()).collect(),
+        None => memory.keys().chain(semantic.keys()).map(String::as_str).collect(),
+    };
+    let mut seen = Vec::new();
+    for name in names {
+        if seen.contains(&name) {
+            continue;
+        }
+        seen.push(name);
+        let repo = report.repos.as_ref().and_then(|r| r.indexed.iter().find(|r| r.name == name));
+        match repo {
+            Some(repo) => {
+                let profile =
+                    repo.profile.as_ref().map(|p| format!(" (profile {p})")).unwrap_or_default();
+                out.push_str(&format!(
+                    "[{}] {}{}\n  Files: {} | Lines: {} | Modules: {} | Import edges: {}\n",
+                    repo.name,
+                    repo.root,
+                    profile,
+                    repo.files,
+                    repo.lines,
+                    repo.modules,
+                    repo.import_edges,
+                ));
+                let lang_str: Vec<String> = repo
+                    .languages
+                    .iter()
+                    .take(8)
+                    .map(|l| {
+                        if l.files >= 1000 {
+                            format!("{}({:.0}K)", l.language, l.files as f64 / 1000.0)
+                        } else {
+                            format!("{}({})", l.language, l.files)
+                        }
+                    })
+                    .collect();
+                if !lang_str.is_empty() {
+                    out.push_str(&format!("  Languages: {}\n", lang_str.join(" ")));
+                }
+                if !repo.env_overrides.is_empty() {
+                    out.push_str(&format!(
+                        "  Config from environment: {}\n",
+                        repo.env_overrides.join(", ")
+                    ));
+                }
+                if let Some(ws) = &repo.workspace {
+                    out.push_str(&format!(
+                        "  Workspace: {} members ({})\n",
+                        ws.members,
+                        ws.kinds.join(", ")
+                    ));
+                }
+                if let Some(co) = &repo.codeowners {
+                    out.push_str(&format!(
+                        "  Code owners: {} rules from {}, {} files unowned\n",
+                        co.rules, co.source, co.unowned
+                    ));
+                }
+                if repo.binary_files + repo.minified_files > 0 {
+                    out.push_str(&format!(
+                        "  Not searched: {} binary, {} minified\n",
+                        repo.binary_files, repo.minified_files
+                    ));
+                }
+                if repo.generated_files + repo.vendored_files > 0 {
+                    out.push_str(&format!(
+                        "  Hidden from search: {} generated, {} vendored (include_generated)\n",
+                        repo.generated_files, repo.vendored_files
+                    ));
+                }
+                out.push_str(&format!("  Last scan: {}ms", repo.scan.total_ms));
+                if !repo.scan.stages.is_empty() {
+                    out.push_str(&format!(" ({})", repo.scan.summary));
+                }
+                out.push('\n');
+            }
+            None => out.push_str(&format!("[{name}]\n")),
+        }
+
+        if let Some(mem) = memory.get(name) {
+            let c = &mem.components;
+            out.push_str(&format!(
+                "  Memory: ~{} (files {}, search {}, imports {}, idf {}, stubs {}, vectors {})",
+                fmt(mem.total_bytes),
+                fmt(c.all_files),
+                fmt(c.search_index),
+                fmt(c.import_graph),
+                fmt(c.term_doc_freq),
+                fmt(c.stub_cache),
+                fmt(c.semantic_vectors),
+            ));
+            if let Some(limit) = mem.limit_mb {
+                out.push_str(&format!(" / limit {limit} MB"));
+            }
+            out.push('\n');
+        }
+
+        if let Some(repo) = repo {
+            if let Some(holder) = &repo.caches_written_by {
+                out.push_str(&format!("  Caches: read-only ({holder} writes them)\n"));
+            }
+            for scope in &repo.scopes {
+                let default = if scope.default { " (default)" } else { "" };
+                out.push_str(&format!("  Scope {}{default}: {}\n", scope.name, scope.description));
+            }
+        }
+
+        if let Some(sem) = semantic.get(name) {
+            out.push_str(&format_semantic(sem));
+        }
+        out.push('\n');
+    }
+
+    if let Some(repos) = &report.repos {
+        for p in &repos.indexing {
+            let position = match (p.state, p.position, &p.progress) {
+                ("lazy", ..) => "lazy (scanned when a tool names it)".to_string(),
+                ("unavailable", ..) => {
+                    "unavailable (root not found; indexed once it reappears)".to_string()
+                }
+                (_, Some(i), _) => format!("queued #{i}"),
+                (_, None, Some(stage)) => format!("scanning ({stage})"),
+                _ => "scanning".to_string(),
+            };
+            out.push_str(&format!("[{}] {}\n  Indexing: {position}\n\n", p.name, p.root));
+        }
+        if repos.cross_repo_edges > 0 {
+            out.push_str(&format!("Cross-repo: {} import edges\n\n", repos.cross_repo_edges));
+        }
+        out.push_str(&format!(
+            "Total: {} files across {} repo(s)",
+            repos.total_files,
+            repos.indexed.len()
+        ));
+        if let Some(memory) = &report.memory {
+            out.push_str(&format!(", ~{} indexed in memory", fmt(memory.total_bytes)));
+        }
+    } else if let Some(memory) = &report.memory {
+        out.push_str(&format!("Total: ~{} indexed in memory", fmt(memory.total_bytes)));
+    }
+
+    if let Some(watch) = &report.watch {
+        if !out.ends_with("\n\n") {
+            out.push_str("\n\n");
+        }
+        if watch.active {
+            out.push_str(&format!(
+                "Watch: active, {} batches ({} updated, {} removed, {} unchanged)",
+                watch.batches, watch.updated, watch.removed, watch.unchanged
+            ));
+            if let Some(ago) = watch.last_change_secs_ago {
+                out.push_str(&format!(", last change {ago}s ago"));
+            }
+        } else {
+            out.push_str("Watch: off");
+        }
+    }
+
+    if let Some(s) = &report.session {
+        if !out.ends_with("\n\n") {
+            out.push_str("\n\n");
+        }
+        out.push_str(&format!(
+            "Session: {}m {}s, {} files read, ~{} tokens served, {} output profile",
+            s.elapsed_secs / 60,
+            s.elapsed_secs % 60,
+            s.files_read.len(),
+            s.tokens_served,
+            s.output_profile
+        ));
+        if !s.files_read.is_empty() {
+            out.push_str("\nFiles read:\n");
+            for path in &s.files_read {
+                out.push_str(&format!("  {path}\n"));
+            }
+        }
+    }
+    out.trim_end_matches('\n').to_string()
+}
+
+fn format_semantic(sem: &SemanticStatus) -> String {
+    match sem.status {
+        "idle" => "  Semantic: disabled\n".to_string(),
+        "extracting chunks" => "  Semantic: extracting chunks...\n".to_string(),
+        "embedding" => {
+            let (done, total) = (sem.completed_batches, sem.total_batches);
+            let pct = if total > 0 { done * 100 / total } else { 0 };
+            format!(
+                "  Semantic: embedding on {} — {done}/{total} batches ({pct}%), {} chunks\n",
+                sem.device, sem.chunks
+            )
+        }
+        "ready" => format!("  Semantic: ready ({} chunks, {})\n", sem.chunks, sem.device),
+        "evicted" => "  Semantic: evicted (max_memory_mb exceeded)\n".to_string(),
+        status => format!("  Semantic: {status}\n"),
+    }
+}
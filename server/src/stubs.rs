@@ -1,8 +1,17 @@
 //! Language-aware stub extraction that strips function/method bodies while preserving
 //! signatures, imports, type definitions, and structural declarations.
 //!
-//! Supports brace-based languages (C-family, Rust, Go, JS/TS, PowerShell, shaders),
-//! indent-based languages (Python, Ruby), and config files (JSON, YAML, TOML, XML, INI).
+//! Supports brace-based languages (C-family, Java and C# with annotations, attributes, and
+//! records, Kotlin data classes and extension functions, Swift protocols, extensions, and
+//! computed properties, Rust, Go, JS/TS, PHP, PowerShell, shaders),
+//! indent-based languages (Python), `end`-delimited languages (Ruby, Elixir), Jupyter
+//! notebooks (cell headers and code-cell signatures), SQL DDL (`CREATE TABLE`/`VIEW`/`FUNCTION` signatures),
+//! documents (Markdown and AsciiDoc heading trees), and config files (key paths of JSON,
+//! YAML, and TOML; XML, INI). Other languages can be plugged in with [`register_extractor`].
+
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::sync::{Arc, OnceLock, RwLock};
 
 // ---------------------------------------------------------------------------
 // Language family classification
@@ -12,8 +21,12 @@
 pub enum LanguageFamily {
     BraceBased,
     IndentBased,
+    EndBased,
     ConfigIni,
     ConfigStructured,
+    Notebook,
+    Sql,
+    Markup,
     Unknown,
 }
 
@@ -23,38 +36,548 @@ pub fn classify_language(ext: &str) -> LanguageFamily {
         // Brace-based languages
         "h" | "hpp" | "hxx" | "cpp" | "cxx" | "cc" | "c" | "cs" | "java" | "kt" | "scala"
         | "rs" | "go" | "js" | "ts" | "jsx" | "tsx" | "mjs" | "cjs" | "swift" | "usf" | "ush"
-        | "hlsl" | "glsl" | "vert" | "frag" | "comp" | "wgsl" | "d" | "ps1" | "psm1" | "psd1" => {
-            LanguageFamily::BraceBased
-        }
+        | "hlsl" | "glsl" | "vert" | "frag" | "comp" | "wgsl" | "d" | "ps1" | "psm1" | "psd1"
+        | "php" => LanguageFamily::BraceBased,
         // Indent-based languages
-        "py" | "rb" => LanguageFamily::IndentBased,
+        "py" => LanguageFamily::IndentBased,
+        // Blocks closed by `end`
+        "rb" | "ex" | "exs" => LanguageFamily::EndBased,
         // INI/CFG config
         "ini" | "cfg" | "conf" => LanguageFamily::ConfigIni,
         // Structured config (JSON, YAML, TOML, XML)
         "json" | "yaml" | "yml" | "toml" | "xml" => LanguageFamily::ConfigStructured,
+        // Jupyter notebooks (JSON cells)
+        "ipynb" => LanguageFamily::Notebook,
+        // SQL DDL and routines
+        "sql" => LanguageFamily::Sql,
+        // Documentation with headings
+        "md" | "markdown" | "mdx" | "adoc" | "asciidoc" => LanguageFamily::Markup,
         // Unknown
         _ => LanguageFamily::Unknown,
     }
 }
 
+// ---------------------------------------------------------------------------
+// Extractor plugins
+// ---------------------------------------------------------------------------
+
+/// Stubs and outlines for a language the built-in families don't cover, such as an
+/// in-house DSL. Plugged in with [`register_extractor`].
+pub trait StubExtractor: Send + Sync {
+    /// Signatures, declarations, and imports of `content`, without bodies.
+    fn extract_stubs(&self, content: &str) -> String;
+
+    /// Definitions of `content` with their line ranges, as for `cs_read mode=outline`.
+    /// Default: none.
+    fn extract_outline(&self, _content: &str) -> Vec<OutlineEntry> {
+        Vec::new()
+    }
+}
+
+/// Registered extractors by lowercase extension. Keys are leaked once per extension, so
+/// they can serve as `&'static` language names.
+static EXTRACTORS: RwLock<BTreeMap<&'static str, Arc<dyn StubExtractor>>> =
+    RwLock::new(BTreeMap::new());
+
+/// Use `extractor` for files with extension `ext` (without the dot, case-insensitive) in
+/// place of the built-in strategy. Such files are detected as language `ext` unless a
+/// built-in language claims the extension, in which case the extractor still replaces
+/// that language's stubs for files with this extension only: one registered for `tsx`
+/// handles `.tsx` files and leaves `.ts` ones to the TypeScript strategy. Register before
+/// scanning; registering an extension again replaces its extractor.
+pub fn register_extractor(ext: &str, extractor: Box<dyn StubExtractor>) {
+    let ext = ext.trim_start_matches('.').to_lowercase();
+    let mut extractors = EXTRACTORS.write().unwrap_or_else(|e| e.into_inner());
+    let key = match extractors.keys().find(|k| **k == ext) {
+        Some(&known) => known,
+        None => Box::leak(ext.into_boxed_str()),
+    };
+    extractors.insert(key, Arc::from(extractor));
+}
+
+/// Remove the extractor registered for `ext`, so its files go back to the built-in
+/// strategy. Returns whether one was registered.
+pub fn unregister_extractor(ext: &str) -> bool {
+    let ext = ext.trim_start_matches('.').to_lowercase();
+    let mut extractors = EXTRACTORS.write().unwrap_or_else(|e| e.into_inner());
+    extractors.remove(ext.as_str()).is_some()
+}
+
+/// The extractor registered for `ext`.
+fn registered_extractor(ext: &str) -> Option<Arc<dyn StubExtractor>> {
+    let extractors = EXTRACTORS.read().unwrap_or_else(|e| e.into_inner());
+    extractors.get(ext).cloned()
+}
+
+/// `ext` as a `&'static str` when an extractor is registered for it.
+pub fn registered_ext(ext: &str) -> Option<&'static str> {
+    let extractors = EXTRACTORS.read().unwrap_or_else(|e| e.into_inner());
+    extractors.get_key_value(ext).map(|(k, _)| *k)
+}
+
 // ---------------------------------------------------------------------------
 // Main stub extraction entry point
 // ---------------------------------------------------------------------------
 
 /// Extract structural stubs from source code by language family.
 /// Keeps: imports, macros, class/struct/enum/namespace declarations,
-/// function signatures, member variables, type aliases.
+/// function signatures, member variables, type aliases, and the doc comment or docstring
+/// of each.
 /// Replaces: function/method bodies with `{ /* ... */ }`
+///
+/// An extractor registered for `ext` ([`register_extractor`]) takes precedence.
 pub fn extract_stubs(content: &str, ext: &str) -> String {
+    if let Some(extractor) = registered_extractor(ext) {
+        return extractor.extract_stubs(content);
+    }
     match classify_language(ext) {
         LanguageFamily::ConfigIni => stub_ini(content),
         LanguageFamily::IndentBased => stub_python(content),
+        LanguageFamily::EndBased => stub_end_based(content, EndSyntax::for_ext(ext)),
         LanguageFamily::ConfigStructured => stub_structured(content, ext),
+        LanguageFamily::Notebook => stub_notebook(content),
+        LanguageFamily::Sql => stub_sql(content),
+        LanguageFamily::Markup => stub_markup(content, ext),
         LanguageFamily::Unknown => stub_fallback(content),
         LanguageFamily::BraceBased => stub_brace_based(content),
     }
 }
 
+/// Extract stubs from a repo file, choosing the strategy from its detected language
+/// ([`crate::lang`]) rather than its raw extension, so `.h` files holding C++ or
+/// extensionless scripts get the right treatment. Without `include_docs`, comments and
+/// docstrings are left out (see [`strip_docs`]); with [`Visibility::Public`], private
+/// symbols are (see [`public_only`]).
+pub fn extract_file_stubs(
+    languages: &crate::lang::LanguageOverrides,
+    rel_path: &str,
+    content: &str,
+    options: StubOptions,
+) -> String {
+    let lang = crate::lang::detect(languages, rel_path, content);
+    let ext = crate::lang::file_stub_ext(lang, rel_path);
+    let mut stubs = extract_stubs(content, ext);
+    if options.visibility == Visibility::Public {
+        stubs = public_only(&stubs, ext);
+    }
+    if options.include_docs {
+        stubs
+    } else {
+        strip_docs(&stubs, ext)
+    }
+}
+
+/// `stubs` without comment lines and docstrings: `//` and `/* */` comments in brace-based
+/// languages, `#` comments and docstrings in Python, `#` comments and `@doc`/`@moduledoc`
+/// attributes in Ruby and Elixir. Other languages are returned as they are.
+pub fn strip_docs(stubs: &str, ext: &str) -> String {
+    let family = classify_language(ext);
+    if !matches!(
+        family,
+        LanguageFamily::BraceBased | LanguageFamily::IndentBased | LanguageFamily::EndBased
+    ) {
+        return stubs.to_string();
+    }
+    let mut out = String::with_capacity(stubs.len());
+    // Closing marker of the comment or docstring being skipped
+    let mut until: Option<&str> = None;
+    for (n, line) in stubs.lines().enumerate() {
+        let trimmed = line.trim();
+        if let Some(end) = until {
+            if trimmed.contains(end) {
+                until = None;
+            }
+            continue;
+        }
+        let skip = match family {
+            LanguageFamily::BraceBased => {
+                if trimmed.starts_with("/*") && !trimmed.contains("*/") {
+                    until = Some("*/");
+                }
+                trimmed.starts_with("//")
+                    || trimmed.starts_with("/*") && trimmed.ends_with("*/")
+                    || until.is_some()
+            }
+            LanguageFamily::IndentBased => {
+                let doc = trimmed.trim_start_matches(['r', 'R', 'u', 'U']);
+                if let Some(q) = ["\"\"\"", "'''"].into_iter().find(|q| doc.starts_with(q)) {
+                    if !(doc.len() >= 6 && doc[3..].contains(q)) {
+                        until = Some(q);
+                    }
+                    true
+                } else {
+                    trimmed.starts_with('#') && !(n == 0 && trimmed.starts_with("#!"))
+                }
+            }
+            LanguageFamily::EndBased => {
+                let attr = ["@doc", "@moduledoc", "@typedoc"]
+                    .iter()
+                    .any(|a| trimmed.strip_prefix(a).is_some_and(|r| r.starts_with([' ', '('])));
+                if attr && trimmed.matches("\"\"\"").count() == 1 {
+                    until = Some("\"\"\"");
+                } else if line.starts_with("=begin") {
+                    until = Some("=end");
+                }
+                attr || until.is_some()
+                    || trimmed.starts_with('#') && !(n == 0 && trimmed.starts_with("#!"))
+            }
+            _ => false,
+        };
+        if !skip {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Which symbols stubs keep (`visibility` on `cs_read`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Visibility {
+    #[default]
+    All,
+    /// Only the public surface of the file (see [`public_only`]).
+    Public,
+}
+
+impl Visibility {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "all" => Ok(Self::All),
+            "public" => Ok(Self::Public),
+            other => Err(format!("Unknown visibility '{other}'. Expected 'all' or 'public'")),
+        }
+    }
+}
+
+/// How [`extract_file_stubs`] trims stubs beyond the language's own rules.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StubOptions {
+    /// Keep doc comments and docstrings.
+    pub include_docs: bool,
+    pub visibility: Visibility,
+}
+
+impl Default for StubOptions {
+    fn default() -> Self {
+        Self { include_docs: true, visibility: Visibility::All }
+    }
+}
+
+/// How a language marks what is private.
+#[derive(Clone, Copy, PartialEq)]
+enum VisibilityRules {
+    /// Items without `pub`, or with `pub(crate)` and the like.
+    Rust,
+    /// Java and C#: members without `public` or `protected`.
+    ExplicitPublic,
+    /// Kotlin, Swift, Scala, PHP: `private`, `internal`, `fileprivate`.
+    Modifiers,
+    /// JS/TS: unexported top-level declarations, `private` and `#name` members.
+    Exports,
+    /// Lowercase names.
+    Go,
+    /// `_name`.
+    Python,
+    /// `private` sections and `private def`.
+    Ruby,
+    /// `defp` and `@doc false`.
+    Elixir,
+    /// `private:` and `protected:` sections; `class` members are private until one opens.
+    Cpp,
+}
+
+impl VisibilityRules {
+    fn for_ext(ext: &str) -> Option<Self> {
+        Some(match ext {
+            "rs" => Self::Rust,
+            "java" | "cs" => Self::ExplicitPublic,
+            "kt" | "kts" | "swift" | "scala" | "php" => Self::Modifiers,
+            "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" => Self::Exports,
+            "go" => Self::Go,
+            "py" => Self::Python,
+            "rb" => Self::Ruby,
+            "ex" | "exs" => Self::Elixir,
+            "cpp" | "cxx" | "cc" | "hpp" | "hxx" => Self::Cpp,
+            _ => return None,
+        })
+    }
+}
+
+/// Leading words of lines that are never hidden: imports and namespace declarations.
+const ALWAYS_PUBLIC_WORDS: &[&str] = &[
+    "use",
+    "import",
+    "from",
+    "package",
+    "namespace",
+    "using",
+    "require",
+    "require_relative",
+    "include",
+    "extend",
+    "alias",
+    "extern",
+    "#include",
+    "export",
+    "module.exports",
+];
+
+/// `stubs` without the symbols a caller outside the file's module can't use: private and
+/// internal items, members, and fields, each with the comments and attributes above it.
+/// What counts as private follows each language's rules (`pub` in Rust, `public` in Java
+/// and C#, exports in JS/TS, capitalization in Go, `_name` in Python, `defp` in Elixir,
+/// `private` sections in Ruby and C++). Members of interfaces, traits, trait impls, and
+/// enums follow their type. Other languages are returned as they are.
+pub fn public_only(stubs: &str, ext: &str) -> String {
+    let Some(rules) = VisibilityRules::for_ext(ext) else { return stubs.to_string() };
+    let by_indent =
+        matches!(rules, VisibilityRules::Python | VisibilityRules::Ruby | VisibilityRules::Elixir);
+    let indent = |l: &str| l.len() - l.trim_start().len();
+    let lines: Vec<&str> = stubs.lines().collect();
+    let exports = rules == VisibilityRules::Exports
+        && lines.iter().any(|l| l.starts_with("export ") || l.starts_with("module.exports"));
+    let mut hidden = vec![false; lines.len()];
+    // Open `private`/`public` sections: indent of their label and whether they hide
+    let mut sections: Vec<(usize, bool)> = Vec::new();
+
+    for i in 0..lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim();
+        if trimmed.is_empty() || is_chunk_prefix(trimmed, by_indent) {
+            continue;
+        }
+        let ind = indent(line);
+        let closer = trimmed.starts_with(['}', ')', ']']) || trimmed == "end";
+        sections.retain(|&(at, _)| ind > at || ind == at && !closer);
+        if closer || hidden[i] {
+            continue;
+        }
+        // The header of the enclosing block: the nearest less-indented line above
+        let header = (0..i)
+            .rev()
+            .map(|j| lines[j])
+            .find(|l| {
+                let t = l.trim();
+                !t.is_empty() && indent(l) < ind && !is_chunk_prefix(t, by_indent)
+            })
+            .map(str::trim);
+        let first = trimmed.split_whitespace().next().unwrap_or("");
+        if ALWAYS_PUBLIC_WORDS.contains(&first) && !(exports && first == "export") {
+            continue;
+        }
+        if let Some(label) = section_label(trimmed, rules) {
+            sections.retain(|&(at, _)| at < ind);
+            sections.push((ind, label));
+            // Ruby's bare `public` has nothing left to separate
+            hidden[i] = label || rules == VisibilityRules::Ruby;
+            continue;
+        }
+        let hide = if sections.last().is_some_and(|&(_, hides)| hides) {
+            true
+        } else {
+            is_private(trimmed, header, rules, exports)
+                || rules == VisibilityRules::Elixir && documented_false(&lines, i)
+        };
+        if !hide {
+            if rules == VisibilityRules::Cpp && header.is_none_or(|h| !h.ends_with('{')) {
+                // A class defaults to private until its first `public:`
+                if trimmed.ends_with('{') && word_at(trimmed, "class") {
+                    sections.push((ind + 1, true));
+                }
+            }
+            continue;
+        }
+        // Up to the definition's own closing line, not that of the block around it
+        let end = (i + 1..=definition_end(&lines, i))
+            .find(|&j| !lines[j].trim().is_empty() && indent(lines[j]) < ind)
+            .map_or(definition_end(&lines, i), |j| j - 1);
+        hidden[i..=end].iter_mut().for_each(|h| *h = true);
+        for j in (0..i).rev() {
+            let t = lines[j].trim();
+            if t.is_empty() || !is_chunk_prefix(t, by_indent) || indent(lines[j]) != ind {
+                break;
+            }
+            hidden[j] = true;
+        }
+    }
+
+    // Rust `impl Foo {` blocks left with no public methods
+    if rules == VisibilityRules::Rust {
+        for i in 0..lines.len() {
+            let trimmed = lines[i].trim();
+            if hidden[i] || !trimmed.starts_with("impl") || !trimmed.ends_with('{') {
+                continue;
+            }
+            let end = definition_end(&lines, i);
+            let empty = (i + 1..end).all(|j| hidden[j] || lines[j].trim().is_empty());
+            if empty && end > i {
+                hidden[i..=end].iter_mut().for_each(|h| *h = true);
+                for j in (0..i).rev() {
+                    if !lines[j].trim().starts_with("#[") {
+                        break;
+                    }
+                    hidden[j] = true;
+                }
+            }
+        }
+    }
+
+    // Blank lines are kept between the lines that remain, never before a closing line
+    let mut out = String::with_capacity(stubs.len());
+    let mut blank = false;
+    for (line, hide) in lines.iter().zip(hidden) {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            blank = !out.is_empty();
+            continue;
+        }
+        if hide {
+            continue;
+        }
+        if blank && !trimmed.starts_with(['}', ')', ']']) && trimmed != "end" {
+            out.push('\n');
+        }
+        blank = false;
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+/// Whether `word` appears as a whole word in `s`.
+fn word_at(s: &str, word: &str) -> bool {
+    s.split(|c: char| !c.is_alphanumeric() && c != '_' && c != '#').any(|w| w == word)
+}
+
+/// `Some(hides)` for a line that opens a visibility section: `private`/`public` in Ruby,
+/// `private:`/`public:` in C++.
+fn section_label(trimmed: &str, rules: VisibilityRules) -> Option<bool> {
+    match (rules, trimmed) {
+        (VisibilityRules::Ruby, "private" | "protected") => Some(true),
+        (VisibilityRules::Ruby, "public") => Some(false),
+        (VisibilityRules::Cpp, "private:" | "protected:") => Some(true),
+        (VisibilityRules::Cpp, "public:") => Some(false),
+        _ => None,
+    }
+}
+
+/// Whether the declaration on `trimmed`, inside the block opened by `header`, is private.
+fn is_private(trimmed: &str, header: Option<&str>, rules: VisibilityRules, exports: bool) -> bool {
+    // Lines inside a function signature or an initializer rather than a type body
+    let in_type = header.is_none_or(|h| {
+        definition_label(h).is_some_and(|l| !l.starts_with("fn "))
+            || h.starts_with("mod ")
+            || h.starts_with("pub mod ")
+            || h.contains(" object ")
+            || h.starts_with("object ")
+            || h.ends_with("struct {")
+            || h.ends_with("interface {")
+    });
+    if !in_type {
+        return false;
+    }
+    // Members of these follow the visibility of the type itself
+    let follows_type = header.is_some_and(|h| {
+        ["trait", "interface", "enum", "@interface", "protocol"].iter().any(|w| word_at(h, w))
+            || rules == VisibilityRules::Rust && h.starts_with("impl") && word_at(h, "for")
+    });
+    let name = || {
+        definition_label(trimmed)
+            .and_then(|l| l.split_once(' ').map(|(_, n)| n.to_string()))
+            .unwrap_or_else(|| {
+                let head = trimmed.split(['=', ':', '(', '{', ';']).next().unwrap_or("");
+                head.split_whitespace().last().unwrap_or("").to_string()
+            })
+    };
+    match rules {
+        VisibilityRules::Rust => {
+            if follows_type || trimmed.starts_with("impl") || trimmed.starts_with("macro_rules!") {
+                return false;
+            }
+            let visible = trimmed.strip_prefix("pub").is_some_and(|rest| {
+                rest.starts_with(' ')
+                    || rest.starts_with('(')
+                        && !rest.starts_with("(crate)")
+                        && !rest.starts_with("(super)")
+                        && !rest.starts_with("(self)")
+                        && !rest.starts_with("(in ")
+            });
+            !visible
+        }
+        VisibilityRules::ExplicitPublic => {
+            if follows_type {
+                return false;
+            }
+            let head = trimmed.split(['(', '=', '{']).next().unwrap_or(trimmed);
+            !word_at(head, "public") && !word_at(head, "protected")
+        }
+        VisibilityRules::Modifiers => {
+            let head = trimmed.split(['(', '=', '{']).next().unwrap_or(trimmed);
+            ["private", "internal", "fileprivate"].iter().any(|w| word_at(head, w))
+        }
+        VisibilityRules::Exports => {
+            let head = trimmed.split(['(', '=', '{']).next().unwrap_or(trimmed);
+            if word_at(head, "private") || trimmed.starts_with('#') {
+                return true;
+            }
+            let declaration = [
+                "function",
+                "async",
+                "class",
+                "const",
+                "let",
+                "var",
+                "interface",
+                "type",
+                "enum",
+                "abstract",
+                "declare",
+            ];
+            header.is_none()
+                && exports
+                && !trimmed.starts_with("export")
+                && declaration.contains(&trimmed.split_whitespace().next().unwrap_or(""))
+        }
+        VisibilityRules::Go => {
+            let name = match trimmed.split_whitespace().next() {
+                Some("func") => name(),
+                Some("type" | "var" | "const") => {
+                    trimmed.split_whitespace().nth(1).unwrap_or("").to_string()
+                }
+                Some(field) if header.is_some() => field.to_string(),
+                _ => return false,
+            };
+            name.starts_with(|c: char| c.is_lowercase() || c == '_')
+        }
+        VisibilityRules::Python => {
+            let name = name();
+            let name = name.rsplit('.').next().unwrap_or("");
+            name.starts_with('_') && !(name.starts_with("__") && name.ends_with("__"))
+        }
+        VisibilityRules::Ruby => {
+            trimmed.starts_with("private ")
+                || trimmed.starts_with("protected ")
+                || trimmed.starts_with("private_class_method ")
+        }
+        VisibilityRules::Elixir => {
+            let first = trimmed.split([' ', '(']).next().unwrap_or("");
+            matches!(first, "defp" | "defmacrop" | "defguardp")
+        }
+        VisibilityRules::Cpp => false,
+    }
+}
+
+/// Whether the Elixir definition at `lines[idx]` is hidden from docs with `@doc false`.
+fn documented_false(lines: &[&str], idx: usize) -> bool {
+    lines[..idx]
+        .iter()
+        .rev()
+        .map(|l| l.trim())
+        .take_while(|t| t.starts_with('@'))
+        .any(|t| t == "@doc false")
+}
+
 // ---------------------------------------------------------------------------
 // Brace-based stub extraction (C/C++, Java, C#, Rust, Go, JS/TS, etc.)
 // ---------------------------------------------------------------------------
@@ -132,7 +655,7 @@ fn stub_brace_based(content: &str) -> String {
             || trimmed.starts_with("mod ")
             || trimmed.starts_with("extern ")
             || trimmed.starts_with("package ")
-            || is_annotation_or_macro(trimmed)
+            || is_annotation_or_macro(trimmed) && !strip_annotations(trimmed).contains('{')
         {
             out.push_str(line);
             out.push('\n');
@@ -144,7 +667,7 @@ fn stub_brace_based(content: &str) -> String {
         let has_close = trimmed.contains('}');
 
         if has_open {
-            let is_structural = is_structural_scope(trimmed, &lines, i);
+            let is_structural = is_structural_scope(strip_annotations(trimmed), &lines, i);
 
             if is_structural {
                 out.push_str(line);
@@ -164,7 +687,12 @@ fn stub_brace_based(content: &str) -> String {
                 }
             } else {
                 let sig = line_before_brace(line);
-                out.push_str(sig);
+                // A brace on its own line (Allman style) joins the signature above it
+                if sig.trim().is_empty() && out.ends_with('\n') && !out.ends_with("\n\n") {
+                    out.pop();
+                } else {
+                    out.push_str(sig);
+                }
                 out.push_str(" { /* ... */ }\n");
 
                 if has_close && line.rfind('}').unwrap_or(0) > line.find('{').unwrap_or(0) {
@@ -277,9 +805,54 @@ pub fn is_annotation_or_macro(line: &str) -> bool {
     false
 }
 
+/// The declaration after a Java/Kotlin/Swift annotation on the same line
+/// (`@JvmStatic fun create() {`, `@ViewBuilder var body: some View {`), or the line itself.
+fn strip_annotations(line: &str) -> &str {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| Regex::new(r"^(?:@[\w.]+(?:\([^)]*\))?\s+)+").unwrap());
+    match re.find(line) {
+        Some(m) if m.end() < line.len() => &line[m.end()..],
+        _ => line,
+    }
+}
+
+/// A C#, Java, Kotlin, or Swift type declaration, with or without its opening brace:
+/// modifiers, then `class`, `struct`, `interface`, `enum`, `record` (`record struct`),
+/// `@interface`, `namespace`, `object`, `protocol`, `extension`, or `actor` and a name.
+/// A Kotlin `companion object` may go without one.
+fn type_declaration_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r"^(?:(?:public|private|protected|internal|fileprivate|open|static|sealed|abstract|partial|readonly|unsafe|final|new|file|ref|strictfp|non-sealed|data|value|inner|annotation|companion|fun|indirect|expect|actual)\s+)*(?:class|struct|interface|enum|record(?:\s+(?:struct|class))?|@interface|namespace|object|protocol|extension|actor)(?:\s+[A-Za-z_]|\s*[{:]|$)",
+        )
+        .unwrap()
+    })
+}
+
+/// A Swift computed property or Kotlin property with a getter, up to its brace:
+/// `var body: some View`, `override val size: Int`. A Kotlin `by lazy` delegate counts too.
+fn property_accessor_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"^(?:[a-z]+\s+)*(?:var|val|let)\s+[\w.]+\s*(?::\s*[^=\s][^=]*|.*\bby\s+lazy)$")
+            .unwrap()
+    })
+}
+
 fn is_structural_scope(line: &str, lines: &[&str], idx: usize) -> bool {
+    // Java `throws` clauses and C# generic constraints (`where T : class`) follow a
+    // method's parameter list
+    let sig = line_before_brace(line);
+    if sig.contains(") throws ") || sig.contains(") where ") {
+        return false;
+    }
+
     let check = |s: &str| -> bool {
         let t = s.trim();
+        if type_declaration_re().is_match(t) {
+            return true;
+        }
         if t.starts_with("class ")
             || t.starts_with("struct ")
             || t.starts_with("namespace ")
@@ -311,6 +884,19 @@ fn is_structural_scope(line: &str, lines: &[&str], idx: usize) -> bool {
         return true;
     }
 
+    // Closing line of a parameter list spread over several lines: a Kotlin primary
+    // constructor (`data class Page(` ... `) {`) or a multi-line signature
+    let parens = |s: &str| s.matches(')').count() as i32 - s.matches('(').count() as i32;
+    let mut unclosed = parens(sig);
+    let mut j = idx;
+    while unclosed > 0 && j > 0 && idx - j < 50 {
+        j -= 1;
+        unclosed += parens(lines[j]);
+        if unclosed <= 0 && check(strip_annotations(lines[j].trim())) {
+            return true;
+        }
+    }
+
     let trimmed = line.trim();
     if trimmed == "{" || trimmed.starts_with("{ ") || trimmed == "{}" {
         let mut j = idx.saturating_sub(1);
@@ -356,15 +942,41 @@ fn is_structural_scope(line: &str, lines: &[&str], idx: usize) -> bool {
     if before.contains(']') && before.contains('(') {
         return false;
     }
+    if before.ends_with("->") || before.ends_with("=>") {
+        return false;
+    }
+
+    // Java static initializer and record compact constructor (`public Point {`)
+    static COMPACT_CTOR: OnceLock<Regex> = OnceLock::new();
+    let compact_ctor = COMPACT_CTOR
+        .get_or_init(|| Regex::new(r"^(?:public|protected|private)\s+[A-Z]\w*$").unwrap());
+    if matches!(before.as_str(), "static" | "init" | "deinit") || compact_ctor.is_match(&before) {
+        return false;
+    }
+
+    // Swift computed properties and Kotlin getters, but not a protocol's `{ get set }`
+    if property_accessor_re().is_match(&before) {
+        let accessors = line[before.len()..].trim();
+        let requirement = accessors.ends_with('}')
+            && accessors
+                .trim_matches(|c| c == '{' || c == '}')
+                .split_whitespace()
+                .all(|w| matches!(w, "get" | "set" | "async" | "throws"));
+        return requirement;
+    }
 
     // Check for function keywords that indicate a non-structural scope
     let trimmed_before = before.trim();
     if trimmed_before.starts_with("fn ")
         || trimmed_before.starts_with("func ")
+        || trimmed_before.starts_with("fun ")
         || trimmed_before.starts_with("function ")
+        || trimmed_before.starts_with("subscript")
         || trimmed_before.contains(" fn ")
         || trimmed_before.contains(" func ")
+        || trimmed_before.contains(" fun ")
         || trimmed_before.contains(" function ")
+        || trimmed_before.contains(" subscript")
     {
         return false;
     }
@@ -436,10 +1048,6 @@ fn stub_python(content: &str) -> String {
             if !trimmed.is_empty() && indent <= body_indent {
                 skip_body = false;
             } else {
-                if trimmed.starts_with("\"\"\"") || trimmed.starts_with("'''") {
-                    out.push_str(line);
-                    out.push('\n');
-                }
                 i += 1;
                 continue;
             }
@@ -457,8 +1065,28 @@ fn stub_python(content: &str) -> String {
             || trimmed.starts_with("def ")
             || trimmed.starts_with("async def ")
         {
-            out.push_str(line);
-            out.push('\n');
+            // The signature, through the last line of a parameter list spread over lines
+            let mut depth = 0;
+            loop {
+                out.push_str(lines[i]);
+                out.push('\n');
+                depth += lines[i].matches(['(', '[']).count() as i32
+                    - lines[i].matches([')', ']']).count() as i32;
+                if depth <= 0 || i + 1 == lines.len() {
+                    break;
+                }
+                i += 1;
+            }
+            // The docstring, if the body starts with one
+            if let Some(start) = (i + 1..lines.len()).find(|&j| !lines[j].trim().is_empty()) {
+                if let Some(end) = docstring_end(&lines, start) {
+                    for doc in &lines[start..=end] {
+                        out.push_str(doc);
+                        out.push('\n');
+                    }
+                    i = end;
+                }
+            }
             if trimmed.starts_with("def ") || trimmed.starts_with("async def ") {
                 body_indent = indent;
                 skip_body = true;
@@ -475,121 +1103,921 @@ fn stub_python(content: &str) -> String {
     out
 }
 
+/// Last line of the docstring opening on line `start`, if that line opens one.
+fn docstring_end(lines: &[&str], start: usize) -> Option<usize> {
+    let trimmed = lines[start].trim().trim_start_matches(['r', 'R', 'u', 'U']);
+    let quote = ["\"\"\"", "'''"].into_iter().find(|q| trimmed.starts_with(q))?;
+    if trimmed.len() >= 6 && trimmed[3..].contains(quote) {
+        return Some(start);
+    }
+    (start + 1..lines.len()).find(|&j| lines[j].contains(quote))
+}
+
+// ---------------------------------------------------------------------------
+// `end`-delimited stub extraction (Ruby, Elixir)
+// ---------------------------------------------------------------------------
+
+/// Block keywords of a language whose blocks close with `end`.
+struct EndSyntax {
+    elixir: bool,
+    /// Blocks kept open, their contents stubbed in turn.
+    scopes: &'static [&'static str],
+    /// Blocks whose bodies are collapsed to `... end`.
+    defs: &'static [&'static str],
+}
+
+impl EndSyntax {
+    const RUBY: Self = Self { elixir: false, scopes: &["class", "module"], defs: &["def"] };
+    const ELIXIR: Self = Self {
+        elixir: true,
+        scopes: &["defmodule", "defprotocol", "defimpl"],
+        defs: &["def", "defp", "defmacro", "defmacrop", "defguard", "defguardp"],
+    };
+
+    fn for_ext(ext: &str) -> &'static Self {
+        if matches!(ext, "ex" | "exs") {
+            &Self::ELIXIR
+        } else {
+            &Self::RUBY
+        }
+    }
+}
+
+/// Ruby and Elixir: classes, modules, and `defmodule`s stay open; method and function
+/// bodies collapse to `def name(args) ... end`. Any other block (`do` blocks, `if`,
+/// `case`) stays open when it holds a nested block, as an RSpec `describe` or a Rails
+/// `class_methods do` does, and collapses otherwise, as a single `it` or `test` does.
+/// Statements between definitions (`attr_reader`, `include`, `use`, `@moduledoc`,
+/// `has_many`) are kept.
+fn stub_end_based(content: &str, syntax: &EndSyntax) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let ends = end_blocks(&lines, syntax);
+    let mut out = String::with_capacity(content.len() / 3);
+    stub_end_scope(&lines, &ends, 0..lines.len(), syntax, &mut out);
+    out
+}
+
+fn stub_end_scope(
+    lines: &[&str],
+    ends: &[Option<usize>],
+    range: std::ops::Range<usize>,
+    syntax: &EndSyntax,
+    out: &mut String,
+) {
+    // An Elixir function head spread over lines: its `do` comes on a later line
+    let mut def_head = false;
+    let mut i = range.start;
+    while i < range.end {
+        let line = lines[i];
+        let code = strip_hash_comment(line.trim());
+        let first = code.split(|c: char| !c.is_alphanumeric() && c != '_').next().unwrap_or("");
+        match ends[i] {
+            Some(end) if end < range.end => {
+                let is_def = syntax.defs.contains(&first) || std::mem::take(&mut def_head);
+                let nested = (i + 1..end).any(|j| ends[j].is_some());
+                if syntax.scopes.contains(&first) || !is_def && nested {
+                    out.push_str(line);
+                    out.push('\n');
+                    stub_end_scope(lines, ends, i + 1..end, syntax, out);
+                    out.push_str(lines[end]);
+                    out.push('\n');
+                } else {
+                    // Keep a parameter list that runs over several lines
+                    let parens =
+                        |l: &str| l.matches('(').count() as i32 - l.matches(')').count() as i32;
+                    let (mut head, mut open) = (i, parens(line));
+                    while open > 0 && head + 1 < end {
+                        head += 1;
+                        open += parens(lines[head]);
+                    }
+                    for l in &lines[i..head] {
+                        out.push_str(l);
+                        out.push('\n');
+                    }
+                    out.push_str(lines[head].trim_end());
+                    out.push_str(" ... end\n");
+                }
+                i = end + 1;
+                continue;
+            }
+            _ => {}
+        }
+        if syntax.elixir && syntax.defs.contains(&first) && !code.contains("do:") {
+            def_head = true;
+        } else if code.is_empty() {
+            def_head = false;
+        }
+        out.push_str(line);
+        out.push('\n');
+        i += 1;
+    }
+}
+
+/// For each line that opens a block, the index of the line with its `end`. Heredocs,
+/// `=begin` comments, and Elixir `\"\"\"` strings are skipped.
+fn end_blocks(lines: &[&str], syntax: &EndSyntax) -> Vec<Option<usize>> {
+    static RUBY_HEREDOC: OnceLock<Regex> = OnceLock::new();
+    let heredoc =
+        RUBY_HEREDOC.get_or_init(|| Regex::new(r#"<<[~-]?['"]?([A-Z_][A-Z0-9_]*)\b"#).unwrap());
+    let mut ends = vec![None; lines.len()];
+    let mut open: Vec<usize> = Vec::new();
+    let mut terminator: Option<String> = None;
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        if let Some(t) = &terminator {
+            if trimmed.starts_with(t.as_str()) {
+                terminator = None;
+            }
+            continue;
+        }
+        if !syntax.elixir && line.starts_with("=begin") {
+            terminator = Some("=end".to_string());
+            continue;
+        }
+        let code = strip_hash_comment(trimmed);
+        terminator = if syntax.elixir {
+            ["\"\"\"", "'''"].into_iter().find(|q| code.matches(q).count() == 1).map(String::from)
+        } else {
+            heredoc.captures(code).map(|c| c[1].to_string())
+        };
+        if is_end(code) {
+            if let Some(start) = open.pop() {
+                ends[start] = Some(i);
+            }
+        }
+        if opens_end_block(code, syntax) {
+            open.push(i);
+        }
+    }
+    ends
+}
+
+fn is_end(code: &str) -> bool {
+    code.strip_prefix("end")
+        .is_some_and(|rest| !rest.starts_with(|c: char| c.is_alphanumeric() || c == '_'))
+}
+
+fn opens_end_block(code: &str, syntax: &EndSyntax) -> bool {
+    static RUBY_OPENER: OnceLock<Regex> = OnceLock::new();
+    static RUBY_ENDLESS_DEF: OnceLock<Regex> = OnceLock::new();
+    static DO_BLOCK: OnceLock<Regex> = OnceLock::new();
+    static ELIXIR_FN: OnceLock<Regex> = OnceLock::new();
+    let do_block = DO_BLOCK.get_or_init(|| Regex::new(r"(?:^|[\s)])do(?:\s*\|[^|]*\|)?$").unwrap());
+    if syntax.elixir {
+        let multiline_fn = ELIXIR_FN.get_or_init(|| Regex::new(r"\bfn\b.*->$").unwrap());
+        return do_block.is_match(code) || multiline_fn.is_match(code);
+    }
+    // One-liners: `def empty?; end`, `class Error < StandardError; end`
+    if code.ends_with(" end") || code.ends_with(";end") {
+        return false;
+    }
+    let opener = RUBY_OPENER.get_or_init(|| {
+        Regex::new(
+            r"^(?:class|module|def|if|unless|while|until|case|begin|for)\b|(?:=|\|\||&&|\breturn)\s*(?:if|unless|case|begin|while|until)\b",
+        )
+        .unwrap()
+    });
+    let endless_def = RUBY_ENDLESS_DEF
+        .get_or_init(|| Regex::new(r"^def\s+[\w.?!]+(?:\([^)]*\))?\s*=[^=~>]").unwrap());
+    (opener.is_match(code) && !endless_def.is_match(code)) || do_block.is_match(code)
+}
+
+/// `code` without a trailing `#` comment, ignoring `#` inside quotes.
+fn strip_hash_comment(code: &str) -> &str {
+    let mut quote = None;
+    let mut prev = ' ';
+    for (i, c) in code.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q && prev != '\\' => quote = None,
+            (None, '#') if prev.is_whitespace() => return code[..i].trim_end(),
+            _ => {}
+        }
+        prev = c;
+    }
+    code
+}
+
 // ---------------------------------------------------------------------------
 // Structured config stub extraction (JSON, YAML, TOML, XML)
 // ---------------------------------------------------------------------------
 
+/// JSON, YAML, and TOML become key-path outlines ([`key_paths`]); XML keeps its first lines.
+/// A file that does not parse falls back to its first lines too.
 fn stub_structured(content: &str, ext: &str) -> String {
-    match ext {
-        "json" => stub_json(content),
-        "yaml" | "yml" => stub_yaml(content),
-        "toml" => stub_toml(content),
-        "xml" => stub_xml(content),
-        _ => stub_fallback(content),
-    }
-}
-
-/// JSON: return keys to depth 2
-fn stub_json(content: &str) -> String {
-    // Simple approach: parse with serde_json, extract top-level structure
-    match serde_json::from_str::<serde_json::Value>(content) {
-        Ok(val) => {
-            let mut out = String::new();
-            format_json_depth(&val, &mut out, 0, 2);
-            out
-        }
-        Err(_) => stub_fallback(content),
-    }
-}
-
-fn format_json_depth(val: &serde_json::Value, out: &mut String, depth: usize, max_depth: usize) {
-    let indent = "  ".repeat(depth);
-    match val {
-        serde_json::Value::Object(map) => {
-            out.push_str("{\n");
-            for (i, (key, value)) in map.iter().enumerate() {
-                out.push_str(&"  ".repeat(depth + 1));
-                out.push_str(&format!("\"{key}\": "));
-                if depth + 1 >= max_depth {
-                    match value {
-                        serde_json::Value::Object(_) => out.push_str("{...}"),
-                        serde_json::Value::Array(a) => {
-                            out.push_str(&format!("[...{} items]", a.len()))
-                        }
-                        _ => out.push_str(&value.to_string()),
+    let value = match ext {
+        "json" => serde_json::from_str(content).ok(),
+        "toml" => toml::from_str(content).ok(),
+        "yaml" | "yml" => {
+            let docs = yaml_documents(content);
+            if docs.is_empty() {
+                return stub_fallback(content);
+            }
+            let outlines: Vec<String> = docs.iter().map(key_paths).collect();
+            return outlines.join("---\n");
+        }
+        "xml" => return stub_xml(content),
+        _ => None,
+    };
+    match value {
+        Some(value) => key_paths(&value),
+        None => stub_fallback(content),
+    }
+}
+
+/// A config value with its keys in file order.
+enum ConfigNode {
+    Null,
+    /// A number, boolean, or date, as written.
+    Literal(String),
+    String(String),
+    List(Vec<ConfigNode>),
+    Map(Vec<(String, ConfigNode)>),
+}
+
+impl ConfigNode {
+    /// The value as shown after `=`: strings quoted, long values cut.
+    fn display(&self) -> String {
+        let text = match self {
+            Self::Null => "null".to_string(),
+            Self::Literal(s) => s.clone(),
+            Self::String(s) => serde_json::to_string(s).unwrap_or_default(),
+            Self::List(items) if items.is_empty() => "[]".to_string(),
+            Self::List(items) => {
+                let items: Vec<String> = items.iter().map(Self::display).collect();
+                format!("[{}]", items.join(", "))
+            }
+            Self::Map(entries) if entries.is_empty() => "{}".to_string(),
+            Self::Map(entries) => format!("{{{} keys}}", entries.len()),
+        };
+        if text.chars().count() <= KEY_PATH_VALUE_CHARS {
+            return text;
+        }
+        match self {
+            Self::List(items) => format!("[...{} items]", items.len()),
+            _ => format!("{}...", text.chars().take(KEY_PATH_VALUE_CHARS).collect::<String>()),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for ConfigNode {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct NodeVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for NodeVisitor {
+            type Value = ConfigNode;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a config value")
+            }
+            fn visit_unit<E>(self) -> Result<ConfigNode, E> {
+                Ok(ConfigNode::Null)
+            }
+            fn visit_bool<E>(self, v: bool) -> Result<ConfigNode, E> {
+                Ok(ConfigNode::Literal(v.to_string()))
+            }
+            fn visit_i64<E>(self, v: i64) -> Result<ConfigNode, E> {
+                Ok(ConfigNode::Literal(v.to_string()))
+            }
+            fn visit_u64<E>(self, v: u64) -> Result<ConfigNode, E> {
+                Ok(ConfigNode::Literal(v.to_string()))
+            }
+            fn visit_f64<E>(self, v: f64) -> Result<ConfigNode, E> {
+                Ok(ConfigNode::Literal(v.to_string()))
+            }
+            fn visit_str<E>(self, v: &str) -> Result<ConfigNode, E> {
+                Ok(ConfigNode::String(v.to_string()))
+            }
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> Result<ConfigNode, A::Error> {
+                let mut items = Vec::new();
+                while let Some(item) = seq.next_element()? {
+                    items.push(item);
+                }
+                Ok(ConfigNode::List(items))
+            }
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> Result<ConfigNode, A::Error> {
+                let mut entries: Vec<(String, ConfigNode)> = Vec::new();
+                while let Some((key, value)) = map.next_entry::<String, ConfigNode>()? {
+                    entries.push((key, value));
+                }
+                // TOML dates arrive as a one-key map
+                if let [(key, ConfigNode::String(date))] = entries.as_slice() {
+                    if key == "$__toml_private_datetime" {
+                        return Ok(ConfigNode::Literal(date.clone()));
                     }
+                }
+                Ok(ConfigNode::Map(entries))
+            }
+        }
+
+        deserializer.deserialize_any(NodeVisitor)
+    }
+}
+
+/// Nesting levels listed before objects are summarized by their key count.
+const KEY_PATH_DEPTH: usize = 5;
+/// Keys listed per object; the rest are counted.
+const KEY_PATH_KEYS: usize = 40;
+/// Longest value shown, in chars.
+const KEY_PATH_VALUE_CHARS: usize = 60;
+
+/// One `path = value` line per leaf of a config file, in file order: `server.port = 8080`,
+/// `services[].name = "api"`. Lists of objects are outlined from their first item after
+/// a count; lists of scalars are shown inline; deep objects and long values are summarized.
+fn key_paths(value: &ConfigNode) -> String {
+    let mut out = String::new();
+    key_paths_into(value, "", 0, &mut out);
+    out
+}
+
+fn key_paths_into(value: &ConfigNode, path: &str, depth: usize, out: &mut String) {
+    let first_map = match value {
+        ConfigNode::List(items) => {
+            items.iter().find(|v| matches!(v, ConfigNode::Map(e) if !e.is_empty()))
+        }
+        _ => None,
+    };
+    match value {
+        ConfigNode::Map(entries) if !entries.is_empty() && depth < KEY_PATH_DEPTH => {
+            for (key, child) in entries.iter().take(KEY_PATH_KEYS) {
+                let plain = !key.is_empty()
+                    && key.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '$'));
+                let key = if plain {
+                    key.clone()
                 } else {
-                    format_json_depth(value, out, depth + 1, max_depth);
+                    serde_json::to_string(key).unwrap_or_default()
+                };
+                let child_path = if path.is_empty() { key } else { format!("{path}.{key}") };
+                key_paths_into(child, &child_path, depth + 1, out);
+            }
+            if entries.len() > KEY_PATH_KEYS {
+                let prefix = if path.is_empty() { String::new() } else { format!("{path}.") };
+                let more = entries.len() - KEY_PATH_KEYS;
+                out.push_str(&format!("{prefix}... ({more} more keys)\n"));
+            }
+        }
+        ConfigNode::List(items) if first_map.is_some() && depth < KEY_PATH_DEPTH => {
+            out.push_str(&format!("{path}[] ({} items)\n", items.len()));
+            key_paths_into(first_map.unwrap(), &format!("{path}[]"), depth + 1, out);
+        }
+        _ => {
+            let path = if path.is_empty() { "." } else { path };
+            out.push_str(&format!("{path} = {}\n", value.display()));
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// YAML block structure, for key-path outlines
+// ---------------------------------------------------------------------------
+
+/// The documents of a YAML file, read from its block structure: mappings, sequences, and
+/// scalars. Flow collections and block scalars are kept as their text, and anchors, tags,
+/// and multi-line plain scalars are not interpreted; enough for an outline.
+fn yaml_documents(content: &str) -> Vec<ConfigNode> {
+    let mut docs = Vec::new();
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    let mut block_scalar: Option<usize> = None;
+    for line in content.lines().chain(std::iter::once("---")) {
+        let indent = line.len() - line.trim_start_matches(' ').len();
+        let trimmed = line.trim();
+        if let Some(parent) = block_scalar {
+            if trimmed.is_empty() || indent > parent {
+                continue;
+            }
+            block_scalar = None;
+        }
+        if trimmed == "---" || trimmed.starts_with("--- ") || trimmed == "..." {
+            if !lines.is_empty() {
+                let (mut i, indent) = (0, lines[0].0);
+                docs.push(yaml_block(&mut lines, &mut i, indent));
+                lines.clear();
+            }
+            continue;
+        }
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with('%') {
+            continue;
+        }
+        let code = strip_yaml_comment(trimmed);
+        if code.ends_with(['|', '>']) || code.ends_with("|-") || code.ends_with(">-") {
+            block_scalar = Some(indent);
+        }
+        lines.push((indent, code.to_string()));
+    }
+    docs
+}
+
+/// A YAML line without its `# comment`, outside quotes.
+fn strip_yaml_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut prev = ' ';
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '#') if prev.is_whitespace() => return line[..i].trim_end(),
+            _ => {}
+        }
+        prev = c;
+    }
+    line
+}
+
+fn is_yaml_item(text: &str) -> bool {
+    text == "-" || text.starts_with("- ")
+}
+
+/// The mapping, sequence, or scalar whose lines start at `lines[*i]` with `indent`.
+fn yaml_block(lines: &mut [(usize, String)], i: &mut usize, indent: usize) -> ConfigNode {
+    let Some((_, first)) = lines.get(*i) else { return ConfigNode::Null };
+    if is_yaml_item(first) {
+        let mut items = Vec::new();
+        while let Some((at, text)) = lines.get(*i).cloned() {
+            if at != indent || !is_yaml_item(&text) {
+                break;
+            }
+            let rest = text[1..].trim_start();
+            if rest.is_empty() {
+                *i += 1;
+                items.push(yaml_child(lines, i, indent));
+            } else if yaml_key(rest).is_some() {
+                // `- key: value` opens a mapping at the column after the dash
+                let column = indent + (text.len() - rest.len());
+                lines[*i] = (column, rest.to_string());
+                items.push(yaml_block(lines, i, column));
+            } else {
+                *i += 1;
+                items.push(yaml_scalar(rest));
+            }
+        }
+        return ConfigNode::List(items);
+    }
+    if yaml_key(first).is_none() {
+        *i += 1;
+        return yaml_scalar(first);
+    }
+    let mut entries = Vec::new();
+    while let Some((at, text)) = lines.get(*i).cloned() {
+        if at > indent {
+            // Continuation of a multi-line scalar
+            *i += 1;
+            continue;
+        }
+        let Some((key, value)) = yaml_key(&text).filter(|_| at == indent) else { break };
+        *i += 1;
+        let value = if !value.is_empty() {
+            yaml_scalar(value)
+        } else if lines.get(*i).is_some_and(|(a, t)| *a == indent && is_yaml_item(t)) {
+            // Sequences may sit at their key's own indent
+            yaml_block(lines, i, indent)
+        } else {
+            yaml_child(lines, i, indent)
+        };
+        entries.push((key, value));
+    }
+    ConfigNode::Map(entries)
+}
+
+/// The block nested under the line before `lines[*i]`, or null when there is none.
+fn yaml_child(lines: &mut [(usize, String)], i: &mut usize, parent: usize) -> ConfigNode {
+    match lines.get(*i) {
+        Some(&(at, _)) if at > parent => yaml_block(lines, i, at),
+        _ => ConfigNode::Null,
+    }
+}
+
+/// The key and the rest of a `key: value` line, without quotes around the key.
+fn yaml_key(line: &str) -> Option<(String, &str)> {
+    let (key, value) = if let Some(q) = line.chars().next().filter(|c| matches!(c, '"' | '\'')) {
+        let end = line[1..].find(q)? + 1;
+        (line[1..end].to_string(), line[end + 1..].strip_prefix(':')?)
+    } else {
+        let colon = line.find(": ").or_else(|| line.strip_suffix(':').map(str::len))?;
+        let key = &line[..colon];
+        if key.starts_with(['[', '{']) || key.contains(['"', '\'']) {
+            return None;
+        }
+        (key.trim_end().to_string(), &line[colon + 1..])
+    };
+    if !value.is_empty() && !value.starts_with(' ') {
+        return None;
+    }
+    Some((key, value.trim()))
+}
+
+/// A plain or quoted YAML scalar: numbers, booleans, null, and flow collections are
+/// literals, everything else a string.
+fn yaml_scalar(text: &str) -> ConfigNode {
+    let text = text.trim();
+    for q in ['"', '\''] {
+        if let Some(inner) = text.strip_prefix(q).and_then(|t| t.strip_suffix(q)) {
+            return ConfigNode::String(inner.to_string());
+        }
+    }
+    match text {
+        "" | "~" | "null" | "Null" | "NULL" => ConfigNode::Null,
+        "true" | "True" | "TRUE" | "false" | "False" | "FALSE" => {
+            ConfigNode::Literal(text.to_lowercase())
+        }
+        _ if text.parse::<f64>().is_ok_and(f64::is_finite) => ConfigNode::Literal(text.to_string()),
+        // Flow collections and block scalar indicators, as written
+        _ if text.starts_with(['[', '{', '|', '>']) => ConfigNode::Literal(text.to_string()),
+        _ => ConfigNode::String(text.to_string()),
+    }
+}
+
+/// XML: return first 100 lines
+fn stub_xml(content: &str) -> String {
+    let lines: Vec<&str> = content.lines().take(100).collect();
+    let mut out = lines.join("\n");
+    if content.lines().count() > 100 {
+        out.push_str("\n<!-- ... (truncated) -->\n");
+    } else {
+        out.push('\n');
+    }
+    out
+}
+
+// ---------------------------------------------------------------------------
+// Jupyter notebook stub extraction
+// ---------------------------------------------------------------------------
+
+/// Cell source: a string or an array of line strings.
+fn notebook_source(cell: &serde_json::Value) -> String {
+    match &cell["source"] {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Array(lines) => lines.iter().filter_map(|l| l.as_str()).collect(),
+        _ => String::new(),
+    }
+}
+
+/// Notebook: a `# %% [n] kind` header per cell (jupytext percent format), markdown
+/// headings, and code-cell imports and class/function signatures. Non-Python kernels
+/// get their language's stubs via `language_info.file_extension`.
+fn stub_notebook(content: &str) -> String {
+    let nb: serde_json::Value = match serde_json::from_str(content) {
+        Ok(v) => v,
+        Err(_) => return stub_fallback(content),
+    };
+    let Some(cells) = nb["cells"].as_array() else {
+        return stub_fallback(content);
+    };
+    let ext = nb["metadata"]["language_info"]["file_extension"]
+        .as_str()
+        .map(|e| e.trim_start_matches('.'))
+        .unwrap_or("py");
+    let python = classify_language(ext) == LanguageFamily::IndentBased;
+
+    let mut out = String::new();
+    for (i, cell) in cells.iter().enumerate() {
+        let kind = cell["cell_type"].as_str().unwrap_or("code");
+        let source = notebook_source(cell);
+        let line_count = source.lines().count();
+        out.push_str(&format!("# %% [{i}] {kind} ({line_count} lines)\n"));
+        match kind {
+            "markdown" => {
+                for line in source.lines().filter(|l| l.starts_with('#')) {
+                    out.push_str("# ");
+                    out.push_str(line);
+                    out.push('\n');
                 }
-                if i < map.len() - 1 {
-                    out.push(',');
+            }
+            "code" if python => {
+                for line in stub_python(&source).lines() {
+                    let trimmed = line.trim_start();
+                    if trimmed.starts_with("import ")
+                        || trimmed.starts_with("from ")
+                        || trimmed.starts_with('@')
+                        || trimmed.starts_with("class ")
+                        || trimmed.starts_with("def ")
+                        || trimmed.starts_with("async def ")
+                        || trimmed == "..."
+                    {
+                        out.push_str(line);
+                        out.push('\n');
+                    }
                 }
-                out.push('\n');
             }
-            out.push_str(&indent);
-            out.push('}');
+            "code" => out.push_str(&extract_stubs(&source, ext)),
+            _ => {}
         }
-        serde_json::Value::Array(arr) => {
-            out.push_str(&format!("[...{} items]", arr.len()));
-        }
-        _ => out.push_str(&val.to_string()),
     }
+    out
 }
 
-/// YAML: return top-level keys and their immediate children
-fn stub_yaml(content: &str) -> String {
+fn extract_toc_notebook(content: &str) -> String {
     let mut out = String::new();
-    for line in content.lines() {
-        // Top-level lines (no leading whitespace) or first-level children (2-space indent)
-        if (!line.starts_with(' ') && !line.starts_with('\t'))
-            || (line.starts_with("  ") && !line.starts_with("    "))
-            || (line.starts_with('\t') && !line.starts_with("\t\t"))
+    for line in stub_notebook(content).lines() {
+        let trimmed = line.trim();
+        if let Some(heading) = trimmed.strip_prefix("# #") {
+            out.push('#');
+            out.push_str(heading);
+            out.push('\n');
+        } else if trimmed.starts_with("class ")
+            || trimmed.starts_with("def ")
+            || trimmed.starts_with("async def ")
         {
-            out.push_str(line);
+            out.push_str(trimmed.split(':').next().unwrap_or(trimmed));
             out.push('\n');
         }
     }
     out
 }
 
-/// TOML: return section headers + first 5 keys per section
-fn stub_toml(content: &str) -> String {
+// ---------------------------------------------------------------------------
+// SQL stub extraction
+// ---------------------------------------------------------------------------
+
+/// Split SQL into statements at top-level `;`, skipping quoted strings, `--` and `/* */`
+/// comments, and `$tag$` dollar-quoted bodies. Each statement keeps its leading comments.
+fn split_sql_statements(content: &str) -> Vec<&str> {
+    let bytes = content.as_bytes();
+    let mut statements = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\'' | b'"' | b'`' => {
+                let quote = bytes[i];
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    i += 1;
+                }
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i += 2;
+                while i + 1 < bytes.len() && !(bytes[i] == b'*' && bytes[i + 1] == b'/') {
+                    i += 1;
+                }
+                i += 1;
+            }
+            b'$' => {
+                // `$tag$ ... $tag$`; a lone `$1` parameter has no closing `$`
+                let tag_len = bytes[i + 1..]
+                    .iter()
+                    .position(|&b| !(b.is_ascii_alphanumeric() || b == b'_'))
+                    .filter(|&n| bytes.get(i + 1 + n) == Some(&b'$'));
+                if let Some(n) = tag_len {
+                    let tag = &content[i..i + n + 2];
+                    match content[i + n + 2..].find(tag) {
+                        Some(end) => i += n + 2 + end + tag.len() - 1,
+                        None => i = bytes.len(),
+                    }
+                }
+            }
+            b';' => {
+                statements.push(&content[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    if !content[start.min(content.len())..].trim().is_empty() {
+        statements.push(&content[start..]);
+    }
+    statements
+}
+
+/// Leading `--` and `/* */` comments of a statement, and the statement text after them.
+fn split_sql_comments(statement: &str) -> (Vec<&str>, &str) {
+    let mut comments = Vec::new();
+    let mut rest = statement.trim_start();
+    loop {
+        let len = if rest.starts_with("--") {
+            rest.find('\n').unwrap_or(rest.len())
+        } else if rest.starts_with("/*") {
+            rest.find("*/").map_or(rest.len(), |end| end + 2)
+        } else {
+            break;
+        };
+        comments.push(&rest[..len]);
+        rest = rest[len..].trim_start();
+    }
+    (comments, rest)
+}
+
+fn sql_create_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r"(?i)^(?:CREATE|ALTER)\s+(?:OR\s+REPLACE\s+)?(?:(?:GLOBAL|LOCAL|TEMP|TEMPORARY|UNLOGGED|MATERIALIZED|UNIQUE|DEFINER\s*=\s*\S+)\s+)*(TABLE|VIEW|FUNCTION|PROCEDURE|TRIGGER|INDEX|TYPE|DOMAIN|SCHEMA|SEQUENCE|EXTENSION)\b",
+        )
+        .unwrap()
+    })
+}
+
+/// SQL: `CREATE TABLE` statements with their columns; view, function, and procedure
+/// signatures with bodies replaced by `...`; other DDL as is. Queries and data statements
+/// are dropped and counted.
+fn stub_sql(content: &str) -> String {
+    static BODY: OnceLock<Regex> = OnceLock::new();
+    let body = BODY.get_or_init(|| {
+        Regex::new(r"(?is)\$\w*\$|\bBEGIN\b|\b(?:AS|IS)\b\s*(?:'|\n|$)|\bRETURN\b\s").unwrap()
+    });
+    static VIEW_AS: OnceLock<Regex> = OnceLock::new();
+    let view_as = VIEW_AS.get_or_init(|| Regex::new(r"(?i)\bAS\b").unwrap());
+
     let mut out = String::new();
-    let mut entries_in_section = 0;
-    let max_entries = 5;
+    let mut skipped = 0;
+    for statement in split_sql_statements(content) {
+        let (comments, stmt) = split_sql_comments(statement);
+        let stmt = stmt.trim_end();
+        if stmt.is_empty() {
+            continue;
+        }
+        let Some(kind) = sql_create_re().captures(stmt).map(|c| c[1].to_uppercase()) else {
+            skipped += 1;
+            continue;
+        };
+        for comment in comments {
+            out.push_str(comment.trim_end());
+            out.push('\n');
+        }
+        // Cut at the body: `AS SELECT` for views and CTAS tables, the routine body for
+        // functions and procedures
+        let cut = match kind.as_str() {
+            "VIEW" => view_as.find(stmt).map(|m| m.end()),
+            // `CREATE TABLE t AS SELECT`, not `GENERATED ALWAYS AS` inside the columns
+            "TABLE" => view_as
+                .find(stmt)
+                .filter(|m| stmt.find('(').is_none_or(|open| m.start() < open))
+                .map(|m| m.end()),
+            "FUNCTION" | "PROCEDURE" | "TRIGGER" => {
+                body.find_at(stmt, matching_paren_end(stmt)).map(|m| m.start())
+            }
+            _ => None,
+        };
+        match cut {
+            Some(end) => {
+                out.push_str(stmt[..end].trim_end());
+                out.push_str(" ...;\n");
+            }
+            None => {
+                out.push_str(stmt);
+                out.push_str(";\n");
+            }
+        }
+        out.push('\n');
+    }
+    if skipped > 0 {
+        out.push_str(&format!("-- ... {skipped} other statements\n"));
+    }
+    out
+}
 
-    for line in content.lines() {
-        let trimmed = line.trim();
-        if trimmed.is_empty() || trimmed.starts_with('#') {
-            out.push_str(line);
+/// Byte offset just past the parenthesis closing the first `(` in `stmt` (0 if none).
+fn matching_paren_end(stmt: &str) -> usize {
+    let Some(open) = stmt.find('(') else { return 0 };
+    let mut depth = 0;
+    for (i, c) in stmt[open..].char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return open + i + 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    stmt.len()
+}
+
+fn extract_toc_sql(content: &str) -> String {
+    let mut out = String::new();
+    for statement in split_sql_statements(content) {
+        let (_, stmt) = split_sql_comments(statement);
+        if sql_create_re().is_match(stmt) {
+            let first = stmt.lines().next().unwrap_or(stmt).trim();
+            out.push_str(first.trim_end_matches('(').trim_end());
             out.push('\n');
+        }
+    }
+    out
+}
+
+// ---------------------------------------------------------------------------
+// Markup stub extraction (Markdown, AsciiDoc)
+// ---------------------------------------------------------------------------
+
+/// A heading of a Markdown or AsciiDoc file: level 1 for the title.
+struct Heading {
+    line: usize,
+    level: usize,
+    text: String,
+}
+
+/// Headings of a Markdown (ATX `#` and setext underlined) or AsciiDoc (`=`) document,
+/// skipping fenced and delimited blocks and YAML front matter.
+fn markup_headings(lines: &[&str], ext: &str) -> Vec<Heading> {
+    let asciidoc = matches!(ext, "adoc" | "asciidoc");
+    let marker = if asciidoc { '=' } else { '#' };
+    let mut headings = Vec::new();
+    let mut fence: Option<String> = None;
+    let mut start = 0;
+    if lines.first().is_some_and(|l| l.trim_end() == "---") {
+        start = lines.iter().skip(1).position(|l| l.trim_end() == "---").map_or(0, |p| p + 2);
+    }
+    for (i, line) in lines.iter().enumerate().skip(start) {
+        let trimmed = line.trim();
+        if let Some(open) = &fence {
+            if trimmed.starts_with(open.as_str()) {
+                fence = None;
+            }
             continue;
         }
-        if trimmed.starts_with('[') {
-            entries_in_section = 0;
-            out.push_str(line);
-            out.push('\n');
+        let fenced = if asciidoc {
+            ["----", "....", "++++", "````"].iter().find(|d| trimmed == **d)
+        } else {
+            ["```", "~~~"].iter().find(|d| trimmed.starts_with(**d))
+        };
+        if let Some(delimiter) = fenced {
+            fence = Some(delimiter.to_string());
             continue;
         }
-        entries_in_section += 1;
-        if entries_in_section <= max_entries {
-            out.push_str(line);
-            out.push('\n');
-        } else if entries_in_section == max_entries + 1 {
-            out.push_str("# ... (more entries)\n");
+        // Headings are not indented as code
+        if line.starts_with("    ") || line.starts_with('\t') {
+            continue;
+        }
+        let level = trimmed.chars().take_while(|&c| c == marker).count();
+        let rest = &trimmed[level..];
+        if (1..=6).contains(&level) && (rest.is_empty() || rest.starts_with(' ')) {
+            let text = rest.trim().trim_end_matches(marker).trim();
+            if !text.is_empty() {
+                headings.push(Heading { line: i, level, text: text.to_string() });
+            }
+            continue;
+        }
+        // Setext: a line of text underlined with `===` or `---`
+        if !asciidoc && i > start && !trimmed.is_empty() {
+            let underline = if trimmed.chars().all(|c| c == '=') {
+                1
+            } else if trimmed.chars().all(|c| c == '-') && trimmed.len() >= 2 {
+                2
+            } else {
+                0
+            };
+            let above = lines[i - 1].trim();
+            let is_text = !above.is_empty()
+                && !above.starts_with(['#', '-', '*', '>', '|'])
+                && headings.last().is_none_or(|h: &Heading| h.line != i - 1);
+            if underline > 0 && is_text && !line.starts_with(' ') {
+                headings.push(Heading { line: i - 1, level: underline, text: above.to_string() });
+            }
+        }
+    }
+    headings
+}
+
+/// Markdown/AsciiDoc: front matter keys, then the heading tree with each section's
+/// length. Documents without headings keep their first lines.
+fn stub_markup(content: &str, ext: &str) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let headings = markup_headings(&lines, ext);
+    let mut out = String::new();
+    if lines.first().is_some_and(|l| l.trim_end() == "---") {
+        if let Some(end) = lines.iter().skip(1).position(|l| l.trim_end() == "---") {
+            let front = lines[1..=end].join("\n");
+            if let Some(value) = yaml_documents(&front).first() {
+                out.push_str("---\n");
+                out.push_str(&key_paths(value));
+                out.push_str("---\n");
+            }
         }
     }
+    if headings.is_empty() {
+        return if out.is_empty() { stub_fallback(content) } else { out };
+    }
+    let marker = if matches!(ext, "adoc" | "asciidoc") { "=" } else { "#" };
+    for (i, h) in headings.iter().enumerate() {
+        let end = headings[i + 1..]
+            .iter()
+            .find(|next| next.level <= h.level)
+            .map_or(lines.len(), |next| next.line);
+        out.push_str(&format!("{} {}  ({} lines)\n", marker.repeat(h.level), h.text, end - h.line));
+    }
     out
 }
 
-/// XML: return first 100 lines
-fn stub_xml(content: &str) -> String {
-    let lines: Vec<&str> = content.lines().take(100).collect();
-    let mut out = lines.join("\n");
-    if content.lines().count() > 100 {
-        out.push_str("\n<!-- ... (truncated) -->\n");
-    } else {
-        out.push('\n');
+fn extract_toc_markup(content: &str, ext: &str) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let marker = if matches!(ext, "adoc" | "asciidoc") { "=" } else { "#" };
+    let mut out = String::new();
+    for h in markup_headings(&lines, ext) {
+        out.push_str(&format!("{} {}\n", marker.repeat(h.level), h.text));
     }
     out
 }
@@ -669,6 +2097,10 @@ pub fn extract_tier3(content: &str, ext: &str) -> String {
     match classify_language(ext) {
         LanguageFamily::ConfigIni => return extract_toc_ini(content),
         LanguageFamily::IndentBased => return extract_toc_python(content),
+        LanguageFamily::EndBased => return extract_toc_end_based(content, EndSyntax::for_ext(ext)),
+        LanguageFamily::Notebook => return extract_toc_notebook(content),
+        LanguageFamily::Sql => return extract_toc_sql(content),
+        LanguageFamily::Markup => return extract_toc_markup(content, ext),
         _ => {}
     }
 
@@ -784,6 +2216,21 @@ fn extract_toc_python(content: &str) -> String {
     out
 }
 
+fn extract_toc_end_based(content: &str, syntax: &EndSyntax) -> String {
+    let mut out = String::new();
+    for line in content.lines() {
+        let trimmed = line.trim();
+        let first = trimmed.split_whitespace().next().unwrap_or("");
+        if syntax.scopes.contains(&first) || syntax.defs.contains(&first) {
+            let indent = line.len() - line.trim_start().len();
+            out.push_str(&" ".repeat(indent));
+            out.push_str(strip_hash_comment(trimmed).trim_end_matches(" do"));
+            out.push('\n');
+        }
+    }
+    out
+}
+
 // ---------------------------------------------------------------------------
 // Block-level parsing for intra-file budget pruning
 // ---------------------------------------------------------------------------
@@ -1206,6 +2653,408 @@ fn block_function_name(line: &str) -> String {
     String::new()
 }
 
+// ---------------------------------------------------------------------------
+// Enclosing symbols for search snippets
+// ---------------------------------------------------------------------------
+
+/// Leading words of lines that open a block but never define a symbol.
+const NON_DEFINITION_WORDS: &[&str] = &[
+    "if", "else", "elif", "for", "foreach", "while", "loop", "do", "switch", "match", "case",
+    "when", "try", "catch", "except", "finally", "with", "return", "yield", "await", "throw",
+    "new", "delete", "let", "var", "go", "defer", "select", "unless", "until", "using", "lock",
+];
+
+/// Whether a trimmed line is the signature of a function, method, or type.
+fn is_definition(trimmed: &str) -> bool {
+    static KEYWORD: OnceLock<Regex> = OnceLock::new();
+    static C_LIKE: OnceLock<Regex> = OnceLock::new();
+    static METHOD: OnceLock<Regex> = OnceLock::new();
+    static ARROW: OnceLock<Regex> = OnceLock::new();
+
+    if trimmed.starts_with("//")
+        || trimmed.starts_with('#')
+        || trimmed.starts_with('*')
+        || trimmed.starts_with("/*")
+        || trimmed.starts_with('@')
+    {
+        return false;
+    }
+    let arrow = ARROW.get_or_init(|| {
+        Regex::new(
+            r"^(?:export\s+)?(?:const|let|var)\s+\w+\s*=\s*(?:async\s*)?(?:\([^)]*\)|\w+)\s*=>",
+        )
+        .unwrap()
+    });
+    if arrow.is_match(trimmed) {
+        return true;
+    }
+    let first_word = trimmed.split(|c: char| !c.is_alphanumeric() && c != '_').next();
+    if first_word.is_some_and(|w| NON_DEFINITION_WORDS.contains(&w)) {
+        return false;
+    }
+    // Declarations come before any `=` or body
+    let head = &trimmed[..trimmed.find(['=', '{']).unwrap_or(trimmed.len())];
+    let keyword = KEYWORD.get_or_init(|| {
+        Regex::new(
+            r"\b(?:fn|def|defp|defmodule|func|function|fun|sub|proc|class|struct|enum|union|trait|interface|protocol|extension|impl|module|namespace|object|record)(?:\s+[\w$<(]|[<(])",
+        )
+        .unwrap()
+    });
+    if keyword.is_match(head) {
+        return true;
+    }
+    if trimmed.contains("=>") || trimmed.ends_with(';') {
+        return false;
+    }
+    // `int main(`, `void Foo::bar(...) const {`: a return type before the name
+    let c_like = C_LIKE.get_or_init(|| {
+        Regex::new(r"^[\w:<>,*&~\[\]]+(?:\s+[\w:<>,*&~\[\]]+)+\s*\([^;|]*$").unwrap()
+    });
+    // `handleClick(e) {`: no return type, so the parameters must close on the line
+    let method =
+        METHOD.get_or_init(|| Regex::new(r"^[A-Za-z_$][\w$]*\s*\([^;|(){}]*\)\s*\{$").unwrap());
+    c_like.is_match(trimmed) || method.is_match(trimmed)
+}
+
+/// Index of the signature line of the function, method, or type that encloses `lines[idx]`:
+/// the nearest less-indented line above it that is a definition. `None` for top-level lines
+/// and for lines that are themselves definitions.
+///
+/// Indentation-based, so it works across brace and indent languages without a parser.
+pub fn enclosing_symbol<S: AsRef<str>>(lines: &[S], idx: usize) -> Option<usize> {
+    let indent = |l: &str| l.len() - l.trim_start().len();
+    let line = lines.get(idx)?.as_ref();
+    if is_definition(line.trim()) {
+        return None;
+    }
+    let mut threshold = indent(line);
+    for j in (0..idx).rev() {
+        let l = lines[j].as_ref();
+        let trimmed = l.trim();
+        if trimmed.is_empty() || indent(l) >= threshold {
+            continue;
+        }
+        if is_definition(trimmed) {
+            return Some(j);
+        }
+        // A closing `) -> T {` or lone `{` line sits at the same indent as its signature
+        threshold =
+            if trimmed.starts_with([')', ']', '}', '{']) { indent(l) + 1 } else { indent(l) };
+        if threshold == 0 {
+            return None;
+        }
+    }
+    None
+}
+
+/// A definition found by [`symbol_marks`], with 0-based line indexes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolMark {
+    pub start: usize,
+    /// Last line of the definition: its closing brace, or the end of its indented body.
+    pub end: usize,
+    /// Kind and name, such as `fn run_search` or `class Parser`.
+    pub label: String,
+}
+
+/// Every function, method, and type definition in `lines`, in order.
+pub fn symbol_marks<S: AsRef<str>>(lines: &[S]) -> Vec<SymbolMark> {
+    (0..lines.len())
+        .filter_map(|idx| {
+            let label = definition_label(lines[idx].as_ref().trim())?;
+            Some(SymbolMark { start: idx, end: definition_end(lines, idx), label })
+        })
+        .collect()
+}
+
+/// One definition found by [`extract_outline`], with 1-based line numbers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutlineEntry {
+    /// `fn`, `class`, `struct`, `impl`, and so on, as in [`SymbolMark::label`].
+    pub kind: String,
+    pub name: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    /// Number of definitions enclosing this one: 1 for the methods of a class.
+    pub depth: usize,
+}
+
+/// Every function, method, and type of a source file with its line range, each nested
+/// definition after the one enclosing it; for Markdown and AsciiDoc, every section. Empty
+/// for config files, notebooks, and SQL, which have no definitions to outline. An extractor
+/// registered for `ext` takes precedence.
+pub fn extract_outline(content: &str, ext: &str) -> Vec<OutlineEntry> {
+    if let Some(extractor) = registered_extractor(ext) {
+        return extractor.extract_outline(content);
+    }
+    if matches!(
+        classify_language(ext),
+        LanguageFamily::ConfigIni
+            | LanguageFamily::ConfigStructured
+            | LanguageFamily::Notebook
+            | LanguageFamily::Sql
+    ) {
+        return Vec::new();
+    }
+    let lines: Vec<&str> = content.lines().collect();
+    if classify_language(ext) == LanguageFamily::Markup {
+        return markup_outline(&lines, ext);
+    }
+    // Last lines of the definitions enclosing the current one
+    let mut open: Vec<usize> = Vec::new();
+    symbol_marks(&lines)
+        .into_iter()
+        .map(|mark| {
+            while open.last().is_some_and(|&end| end < mark.start) {
+                open.pop();
+            }
+            let depth = open.len();
+            open.push(mark.end);
+            let (kind, name) = mark.label.split_once(' ').unwrap_or(("fn", &mark.label));
+            OutlineEntry {
+                kind: kind.to_string(),
+                name: name.to_string(),
+                start_line: mark.start + 1,
+                end_line: mark.end + 1,
+                depth,
+            }
+        })
+        .collect()
+}
+
+/// Sections of a document: each heading runs to the next one of its level or higher.
+fn markup_outline(lines: &[&str], ext: &str) -> Vec<OutlineEntry> {
+    let headings = markup_headings(lines, ext);
+    let marker = if matches!(ext, "adoc" | "asciidoc") { "=" } else { "#" };
+    let mut enclosing: Vec<usize> = Vec::new();
+    headings
+        .iter()
+        .enumerate()
+        .map(|(i, h)| {
+            while enclosing.last().is_some_and(|&level| level >= h.level) {
+                enclosing.pop();
+            }
+            let depth = enclosing.len();
+            enclosing.push(h.level);
+            let end = headings[i + 1..]
+                .iter()
+                .find(|next| next.level <= h.level)
+                .map_or(lines.len(), |next| next.line);
+            OutlineEntry {
+                kind: marker.repeat(h.level),
+                name: h.text.clone(),
+                start_line: h.line + 1,
+                end_line: end.max(h.line + 1),
+                depth,
+            }
+        })
+        .collect()
+}
+
+/// `kind name` for a definition line, or `None` when it is not one.
+fn definition_label(trimmed: &str) -> Option<String> {
+    static NAMED: OnceLock<Regex> = OnceLock::new();
+    static CALLABLE: OnceLock<Regex> = OnceLock::new();
+
+    if !is_definition(trimmed) {
+        return None;
+    }
+    let named = NAMED.get_or_init(|| {
+        Regex::new(
+            r"\b(fn|def|defp|defmodule|func|function|fun|sub|proc|class|struct|enum|union|trait|interface|protocol|extension|impl|module|namespace|object|record)\b(?:<[^>]*>)?\s*(?:(?:struct|class)\s+)?(?:\([^)]*\)\s*)?([A-Za-z_$][\w$.:]*)",
+        )
+        .unwrap()
+    });
+    if let Some(cap) = named.captures(trimmed) {
+        let kind = match &cap[1] {
+            "def" | "defp" | "func" | "function" | "fun" | "sub" | "proc" => "fn",
+            kind => kind,
+        };
+        return Some(format!("{kind} {}", cap[2].trim_end_matches([':', '.'])));
+    }
+    // Arrow functions, C-like functions, and methods: the name before the parameters
+    let callable = CALLABLE
+        .get_or_init(|| Regex::new(r"([A-Za-z_$~][\w$:~]*)\s*(?:=\s*(?:async\s*)?)?\(").unwrap());
+    callable.captures(trimmed).map(|cap| format!("fn {}", &cap[1]))
+}
+
+/// Index of the last line of the definition starting at `lines[idx]`.
+fn definition_end<S: AsRef<str>>(lines: &[S], idx: usize) -> usize {
+    let indent = |l: &str| l.len() - l.trim_start().len();
+    let base = indent(lines[idx].as_ref());
+    let mut last = idx;
+    for (j, line) in lines.iter().enumerate().skip(idx + 1) {
+        let l = line.as_ref();
+        let trimmed = l.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if indent(l) > base || trimmed == "{" {
+            last = j;
+            continue;
+        }
+        if trimmed.starts_with('}') || trimmed == "end" {
+            last = j;
+        } else if trimmed.starts_with([')', ']']) {
+            // The close of a multi-line signature, with the body still to come
+            last = j;
+            if trimmed.ends_with(['{', ':']) {
+                continue;
+            }
+        }
+        break;
+    }
+    last
+}
+
+// ---------------------------------------------------------------------------
+// Definition chunks for symbol-level budget ordering
+// ---------------------------------------------------------------------------
+
+/// One definition of a stubbed file, with the comments, attributes, and decorators above
+/// it, as placed by `ordering=interleaved` budget reads.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StubChunk {
+    /// `kind name` of the definition (`fn parse`, `class Cache`); empty for imports,
+    /// constants, and the headers of enclosing types.
+    pub label: String,
+    pub text: String,
+}
+
+/// Split stubs into definition chunks. The split happens at the outermost nesting level
+/// that holds two or more definitions, so the methods of a file's only class (or a Ruby
+/// module's only class) become chunks rather than the class as a whole; the lines of the
+/// levels above it (imports, the `class Foo {` header) form unnamed chunks, and the
+/// closing lines join the chunk before them. Consecutive unnamed statements stay together.
+/// Notebook stubs split at their cell headers.
+pub fn definition_chunks(stubs: &str, ext: &str) -> Vec<StubChunk> {
+    let family = classify_language(ext);
+    if family == LanguageFamily::Notebook {
+        return notebook_chunks(stubs);
+    }
+    let by_indent = matches!(family, LanguageFamily::IndentBased | LanguageFamily::EndBased);
+    let lines: Vec<&str> = stubs.lines().collect();
+
+    // Nesting level and open-bracket state before each line
+    let mut levels = Vec::with_capacity(lines.len());
+    let (mut braces, mut brackets) = (0i32, 0i32);
+    for line in &lines {
+        let trimmed = line.trim();
+        let level = if by_indent { line.len() - line.trim_start().len() } else { braces as usize };
+        levels.push((level, brackets > 0));
+        if trimmed.is_empty() || is_chunk_prefix(trimmed, by_indent) {
+            continue;
+        }
+        for c in trimmed.chars() {
+            match c {
+                '{' if !by_indent => braces += 1,
+                '}' if !by_indent => braces = (braces - 1).max(0),
+                '(' | '[' | '{' => brackets += 1,
+                ')' | ']' | '}' => brackets = (brackets - 1).max(0),
+                _ => {}
+            }
+        }
+    }
+    let mut defs_per_level: std::collections::BTreeMap<usize, usize> = Default::default();
+    for (line, &(level, continued)) in lines.iter().zip(&levels) {
+        if !continued && definition_label(line.trim()).is_some() {
+            *defs_per_level.entry(level).or_default() += 1;
+        }
+    }
+    let split = defs_per_level.iter().find(|(_, &n)| n >= 2).map_or(0, |(&level, _)| level);
+
+    let mut chunks: Vec<StubChunk> = Vec::new();
+    let mut pending = String::new();
+    // The last statement at the split level is unfinished (no `;`, `}`, or `{` yet)
+    let mut open = false;
+    for (line, &(level, continued)) in lines.iter().zip(&levels) {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            pending.push_str(line);
+            pending.push('\n');
+            continue;
+        }
+        let closer = trimmed.starts_with(['}', ')', ']'])
+            || by_indent
+                && trimmed
+                    .split_whitespace()
+                    .next()
+                    .is_some_and(|w| BLOCK_CONTINUATIONS.contains(&w));
+        let label = if continued || level != split || closer {
+            None
+        } else if is_chunk_prefix(trimmed, by_indent) {
+            pending.push_str(line);
+            pending.push('\n');
+            continue;
+        } else {
+            definition_label(trimmed)
+        };
+        let starts = !continued
+            && !closer
+            && (level < split || level == split && (label.is_some() || !open));
+        if starts || chunks.is_empty() {
+            let label = label.unwrap_or_default();
+            if !label.is_empty() || chunks.last().is_none_or(|c| !c.label.is_empty()) {
+                chunks.push(StubChunk { label, text: String::new() });
+            }
+        }
+        let chunk = chunks.last_mut().expect("a chunk was just started");
+        chunk.text.push_str(&pending);
+        chunk.text.push_str(line);
+        chunk.text.push('\n');
+        pending.clear();
+        if !by_indent {
+            open = !trimmed.ends_with([';', '}', '{', ',']);
+        }
+    }
+    match chunks.last_mut() {
+        Some(last) => last.text.push_str(&pending),
+        None if !pending.is_empty() => {
+            chunks.push(StubChunk { label: String::new(), text: pending })
+        }
+        None => {}
+    }
+    for chunk in &mut chunks {
+        let blank = chunk.text.len() - chunk.text.trim_start_matches(['\n', '\r']).len();
+        chunk.text.drain(..blank);
+    }
+    chunks
+}
+
+/// Leading words of lines that continue a block in Python, Ruby, and Elixir.
+const BLOCK_CONTINUATIONS: &[&str] =
+    &["end", "else", "else:", "elif", "elsif", "except", "except:", "finally:", "rescue", "ensure"];
+
+/// Comment, attribute, and decorator lines, which belong to the definition below them.
+fn is_chunk_prefix(trimmed: &str, by_indent: bool) -> bool {
+    if by_indent {
+        return trimmed.starts_with(['#', '@']);
+    }
+    trimmed.starts_with("//")
+        || trimmed.starts_with("/*")
+        || trimmed.starts_with('*')
+        || trimmed.starts_with("#[")
+        || trimmed.starts_with("#![")
+        || trimmed.starts_with('@')
+        || trimmed.starts_with('[') && trimmed.ends_with(']')
+}
+
+/// Notebook stubs, one chunk per `# %% [n]` cell header.
+fn notebook_chunks(stubs: &str) -> Vec<StubChunk> {
+    let mut chunks: Vec<StubChunk> = Vec::new();
+    for line in stubs.lines() {
+        let cell = line.strip_prefix("# %% [").and_then(|rest| rest.split_once(']'));
+        if cell.is_some() || chunks.is_empty() {
+            let label = cell.map(|(n, _)| format!("cell {n}")).unwrap_or_default();
+            chunks.push(StubChunk { label, text: String::new() });
+        }
+        let chunk = chunks.last_mut().expect("a chunk was just started");
+        chunk.text.push_str(line);
+        chunk.text.push('\n');
+    }
+    chunks
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1284,6 +3133,240 @@ mod tests {
         assert!(stubs.contains("int x"), "Member should be preserved, got:\n{stubs}");
     }
 
+    #[test]
+    fn java_stubs_keep_types_annotations_and_signatures() {
+        let input = "@Service\npublic class Orders implements Api {\n    @Transactional(readOnly = true)\n    public List<Order> search(\n            String q) throws IOException {\n        return repo.find(q);\n    }\n    static {\n        init();\n    }\n}\npublic record Dto(long id, String name) {\n    public Dto {\n        requireNonNull(name);\n    }\n}\npublic @interface Audited {\n    String value() default \"\";\n}\n";
+        let stubs = extract_stubs(input, "java");
+        for kept in [
+            "@Service",
+            "public class Orders implements Api {",
+            "@Transactional(readOnly = true)",
+            "String q) throws IOException { /* ... */ }",
+            "public record Dto(long id, String name) {",
+            "public Dto { /* ... */ }",
+            "String value() default \"\";",
+        ] {
+            assert!(stubs.contains(kept), "missing {kept:?}:\n{stubs}");
+        }
+        for body in ["repo.find", "init()", "requireNonNull"] {
+            assert!(!stubs.contains(body), "body {body:?} should be collapsed:\n{stubs}");
+        }
+    }
+
+    #[test]
+    fn kotlin_stubs_keep_data_classes_and_collapse_functions() {
+        let input = "data class Page<T>(\n    val items: List<T>,\n) : Paged {\n    fun isLast(): Boolean {\n        return next == null\n    }\n}\nclass Repo(private val api: Api) {\n    val cache by lazy {\n        mutableMapOf<Long, User>()\n    }\n    suspend fun load(id: Long): User {\n        return api.fetch(id)\n    }\n    @JvmStatic fun create(): Repo {\n        return Repo(Api())\n    }\n    companion object {\n        const val TAG = \"repo\"\n    }\n    init {\n        warmUp()\n    }\n}\nfun String.slugify(): String {\n    return lowercase()\n}\n";
+        let stubs = extract_stubs(input, "kt");
+        for kept in [
+            ") : Paged {",
+            "    fun isLast(): Boolean { /* ... */ }",
+            "    val cache by lazy { /* ... */ }",
+            "    suspend fun load(id: Long): User { /* ... */ }",
+            "    @JvmStatic fun create(): Repo { /* ... */ }",
+            "    companion object {",
+            "const val TAG = \"repo\"",
+            "    init { /* ... */ }",
+            "fun String.slugify(): String { /* ... */ }",
+        ] {
+            assert!(stubs.contains(kept), "missing {kept:?}:\n{stubs}");
+        }
+        for body in
+            ["next == null", "mutableMapOf", "api.fetch", "Repo(Api())", "warmUp", "lowercase"]
+        {
+            assert!(!stubs.contains(body), "body {body:?} should be collapsed:\n{stubs}");
+        }
+    }
+
+    #[test]
+    fn stubs_keep_docstrings_unless_docs_are_stripped() {
+        let input = "class Cache:\n    '''A cache.'''\n\n    def get(self, key,\n            default=None):\n        \"\"\"Get a key.\n\n        Falls back to default.\n        \"\"\"\n        # look it up\n        return self.d.get(key, default)\n";
+        let stubs = extract_stubs(input, "py");
+        assert!(
+            stubs.contains("            default=None):\n        \"\"\"Get a key.\n\n        Falls back to default.\n        \"\"\"\n        ...\n"),
+            "{stubs}"
+        );
+        assert!(stubs.contains("    '''A cache.'''") && !stubs.contains("look it up"), "{stubs}");
+        assert_eq!(
+            strip_docs(&stubs, "py"),
+            "class Cache:\n\n    def get(self, key,\n            default=None):\n        ...\n"
+        );
+
+        let rust = "/// Parses.\n///\n/// Fails on junk.\npub fn parse(s: &str) -> u32 {\n    0\n}\n/**\n * Kept open.\n */\npub struct Foo;\n";
+        let stubs = extract_stubs(rust, "rs");
+        assert!(stubs.contains("/// Fails on junk.\npub fn parse"), "{stubs}");
+        assert_eq!(
+            strip_docs(&stubs, "rs"),
+            "pub fn parse(s: &str) -> u32 { /* ... */ }\npub struct Foo;\n"
+        );
+    }
+
+    #[test]
+    fn public_stubs_leave_out_private_symbols() {
+        let rust = "use std::io;\n\n/// Config.\npub struct Config {\n    pub name: String,\n    secret: String,\n}\n\nimpl Config {\n    pub fn new() -> Self {\n        todo!()\n    }\n    fn helper(&self) {}\n}\n\nimpl Helper {\n    fn a() {}\n}\n\nimpl Default for Config {\n    fn default() -> Self { todo!() }\n}\n\n#[derive(Debug)]\npub(crate) enum Private {\n    A,\n}\n";
+        assert_eq!(
+            public_only(&extract_stubs(rust, "rs"), "rs"),
+            "use std::io;\n\n/// Config.\npub struct Config {\n    pub name: String,\n}\n\nimpl Config {\n    pub fn new() -> Self { /* ... */ }\n}\n\nimpl Default for Config {\n    fn default() -> Self { /* ... */ }\n}\n"
+        );
+
+        let ts = "export class Api {\n  private cache: Map<string, string>;\n  #secret = 1;\n  get(id: string): string {\n    return id;\n  }\n}\n\nfunction helper() {\n  return 2;\n}\n";
+        assert_eq!(
+            public_only(&extract_stubs(ts, "ts"), "ts"),
+            "export class Api {\n  get(id: string): string { /* ... */ }\n}\n"
+        );
+
+        let go = "type Server struct {\n\tAddr string\n\tconn int\n}\n\nfunc (s *Server) dial() {\n}\n\nfunc New() *Server {\n\treturn nil\n}\n";
+        assert_eq!(
+            public_only(&extract_stubs(go, "go"), "go"),
+            "type Server struct {\n\tAddr string\n}\n\nfunc New() *Server { /* ... */ }\n"
+        );
+
+        let py = "class Cache:\n    def __init__(self):\n        pass\n    def _evict(self):\n        pass\ndef _helper():\n    pass\n";
+        assert_eq!(
+            public_only(&extract_stubs(py, "py"), "py"),
+            "class Cache:\n    def __init__(self):\n        ...\n"
+        );
+
+        let ruby = "class Store\n  def get(k)\n    1\n  end\n\n  private\n\n  def load\n    1\n  end\nend\n";
+        assert_eq!(
+            public_only(&extract_stubs(ruby, "rb"), "rb"),
+            "class Store\n  def get(k) ... end\nend\n"
+        );
+
+        let cpp = "class Parser {\n    int state;\npublic:\n    void parse();\nprivate:\n    void advance();\n};\n";
+        assert_eq!(
+            public_only(&extract_stubs(cpp, "cpp"), "cpp"),
+            "class Parser {\npublic:\n    void parse();\n};\n"
+        );
+        assert_eq!(
+            Visibility::parse("internal").unwrap_err(),
+            "Unknown visibility 'internal'. Expected 'all' or 'public'"
+        );
+    }
+
+    #[test]
+    fn chunks_split_the_only_class_into_its_methods() {
+        let stubs = "import java.util.List;\n\n@Service\npublic class Users {\n    private Repo repo;\n\n    /** Finds one. */\n    public User find(long id) { /* ... */ }\n\n    public List<User> all(\n        int page) { /* ... */ }\n}\n";
+        let chunks = definition_chunks(stubs, "java");
+        let labels: Vec<&str> = chunks.iter().map(|c| c.label.as_str()).collect();
+        assert_eq!(labels, ["", "fn find", "fn all"]);
+        assert!(chunks[0].text.ends_with("public class Users {\n    private Repo repo;\n"));
+        assert_eq!(
+            chunks[1].text,
+            "    /** Finds one. */\n    public User find(long id) { /* ... */ }\n"
+        );
+        assert!(chunks[2].text.ends_with("int page) { /* ... */ }\n}\n"));
+
+        let cells = definition_chunks(
+            "# %% [0] markdown (2 lines)\n# Title\n# %% [1] code (9 lines)\nimport os\n",
+            "ipynb",
+        );
+        assert_eq!(
+            cells.iter().map(|c| c.label.as_str()).collect::<Vec<_>>(),
+            ["cell 0", "cell 1"]
+        );
+    }
+
+    #[test]
+    fn ruby_stubs_keep_classes_and_collapse_methods() {
+        let input = "module Billing\n  class Invoice < ApplicationRecord\n    has_many :lines\n    SQL = <<~SQL\n      SELECT 1\n      end\n    SQL\n\n    def self.overdue(limit = 10)\n      where(\"due_at < ?\", Time.now)\n    end\n\n    def total_for(currency:,\n                  rounding: :half_up)\n      lines.sum { |l| l.amount(currency) }\n    end\n\n    def empty?; end\n    def paid? = status == \"paid\"\n\n    private\n\n    def recalc\n      if lines.any?\n        @total = lines.map(&:amount).sum\n      end\n    end\n  end\nend\n\nRSpec.describe Invoice do\n  it \"sums lines\" do\n    expect(subject.total).to eq(0)\n  end\nend\n";
+        let stubs = extract_stubs(input, "rb");
+        for kept in [
+            "module Billing\n  class Invoice < ApplicationRecord\n    has_many :lines",
+            "    def self.overdue(limit = 10) ... end",
+            "    def total_for(currency:,\n                  rounding: :half_up) ... end",
+            "    def empty?; end\n    def paid? = status == \"paid\"",
+            "    private\n\n    def recalc ... end\n  end\nend",
+            "RSpec.describe Invoice do\n  it \"sums lines\" do ... end\nend",
+        ] {
+            assert!(stubs.contains(kept), "missing {kept:?}:\n{stubs}");
+        }
+        for body in ["Time.now", "l.amount", "@total", "expect("] {
+            assert!(!stubs.contains(body), "body {body:?} should be collapsed:\n{stubs}");
+        }
+    }
+
+    #[test]
+    fn elixir_stubs_keep_modules_and_collapse_functions() {
+        let input = "defmodule MyApp.Accounts do\n  @moduledoc \"\"\"\n  Accounts.\n  def fake do\n  \"\"\"\n  alias MyApp.Repo\n\n  def get_user!(id), do: Repo.get!(User, id)\n\n  def list_users(opts \\\\ []) do\n    Repo.all(User)\n  end\n\n  defp normalize(%{email: email} = attrs)\n       when is_binary(email) do\n    Map.put(attrs, :email, String.downcase(email))\n  end\n\n  def handle(msg) do\n    Enum.map(msg, fn x ->\n      x + 1\n    end)\n  end\nend\n";
+        let stubs = extract_stubs(input, "ex");
+        for kept in [
+            "defmodule MyApp.Accounts do\n  @moduledoc",
+            "  alias MyApp.Repo",
+            "  def get_user!(id), do: Repo.get!(User, id)",
+            "  def list_users(opts \\\\ []) do ... end",
+            "  defp normalize(%{email: email} = attrs)\n       when is_binary(email) do ... end",
+            "  def handle(msg) do ... end\nend",
+        ] {
+            assert!(stubs.contains(kept), "missing {kept:?}:\n{stubs}");
+        }
+        for body in ["Repo.all", "String.downcase", "x + 1"] {
+            assert!(!stubs.contains(body), "body {body:?} should be collapsed:\n{stubs}");
+        }
+    }
+
+    #[test]
+    fn php_stubs_keep_classes_and_collapse_methods() {
+        let input = "<?php\nnamespace App\\Http;\n\nfinal class UserController extends Controller\n{\n    private const LIMIT = 10;\n\n    public function index(Request $request): JsonResponse\n    {\n        return response()->json([]);\n    }\n\n    abstract protected function authorize(): bool;\n}\n\nfunction helper(array $items): array {\n    return array_map(fn ($i) => $i * 2, $items);\n}\n";
+        let stubs = extract_stubs(input, "php");
+        for kept in [
+            "namespace App\\Http;",
+            "final class UserController extends Controller\n{",
+            "    private const LIMIT = 10;",
+            "    public function index(Request $request): JsonResponse { /* ... */ }",
+            "    abstract protected function authorize(): bool;",
+            "function helper(array $items): array { /* ... */ }",
+        ] {
+            assert!(stubs.contains(kept), "missing {kept:?}:\n{stubs}");
+        }
+        assert!(!stubs.contains("response()") && !stubs.contains("array_map"), "{stubs}");
+    }
+
+    #[test]
+    fn swift_stubs_keep_protocols_extensions_and_property_wrappers() {
+        let input = "protocol Cache {\n    var name: String { get set }\n    func value(for key: Key) -> Data?\n}\n@propertyWrapper\nstruct Clamped<Value: Comparable> {\n    var wrappedValue: Value {\n        get { value }\n        set { value = min(newValue, upper) }\n    }\n    init(wrappedValue: Value) {\n        self.value = wrappedValue\n    }\n}\nextension String {\n    var isBlank: Bool {\n        trimmingCharacters(in: .whitespaces).isEmpty\n    }\n    func truncated(to length: Int) -> String {\n        String(prefix(length))\n    }\n}\nstruct ContentView: View {\n    @ViewBuilder var body: some View {\n        Text(\"hi\")\n    }\n    deinit {\n        cancel()\n    }\n}\n";
+        let stubs = extract_stubs(input, "swift");
+        for kept in [
+            "protocol Cache {",
+            "    var name: String { get set }",
+            "@propertyWrapper\nstruct Clamped<Value: Comparable> {",
+            "    var wrappedValue: Value { /* ... */ }",
+            "    init(wrappedValue: Value) { /* ... */ }",
+            "extension String {",
+            "    var isBlank: Bool { /* ... */ }",
+            "    func truncated(to length: Int) -> String { /* ... */ }",
+            "    @ViewBuilder var body: some View { /* ... */ }",
+            "    deinit { /* ... */ }",
+        ] {
+            assert!(stubs.contains(kept), "missing {kept:?}:\n{stubs}");
+        }
+        for body in
+            ["min(newValue", "self.value", "whitespaces", "prefix(length)", "Text(", "cancel"]
+        {
+            assert!(!stubs.contains(body), "body {body:?} should be collapsed:\n{stubs}");
+        }
+    }
+
+    #[test]
+    fn csharp_stubs_handle_allman_braces_attributes_and_records() {
+        let input = "namespace Acme\n{\n    [ApiController]\n    public sealed class Orders : ControllerBase\n    {\n        public int Count { get; set; }\n        [HttpGet(\"{id}\")]\n        public async Task<Order> Get(int id)\n        {\n            return await repo.Find(id);\n        }\n        public T As<T>() where T : class\n        {\n            return default;\n        }\n    }\n    public record struct Point(int X, int Y)\n    {\n        public double Length() { return 0; }\n    }\n}\n";
+        let stubs = extract_stubs(input, "cs");
+        for kept in [
+            "    public sealed class Orders : ControllerBase\n    {\n",
+            "public int Count { get; set; }",
+            "[HttpGet(\"{id}\")]",
+            "public async Task<Order> Get(int id) { /* ... */ }",
+            "public T As<T>() where T : class { /* ... */ }",
+            "    public record struct Point(int X, int Y)\n    {\n",
+            "public double Length() { /* ... */ }",
+        ] {
+            assert!(stubs.contains(kept), "missing {kept:?}:\n{stubs}");
+        }
+        assert!(!stubs.contains("repo.Find") && !stubs.contains("return default"), "{stubs}");
+        let marks: Vec<String> =
+            symbol_marks(&input.lines().collect::<Vec<_>>()).into_iter().map(|m| m.label).collect();
+        assert!(marks.contains(&"record Point".to_string()), "{marks:?}");
+    }
+
     #[test]
     fn test_constructor_init_list_not_structural() {
         let input = "class Foo {\n\tFoo()\n\t\t: bar(1)\n\t\t, baz(2)\n\t{\n\t\tDoStuff();\n\t}\n\tint bar;\n\tint baz;\n};";
@@ -1294,4 +3377,338 @@ mod tests {
         );
         assert!(stubs.contains("int bar"), "Member should be preserved, got:\n{stubs}");
     }
+
+    #[test]
+    fn notebook_stubs_keep_cell_headers_and_signatures() {
+        let nb = r##"{
+            "cells": [
+                { "cell_type": "markdown", "source": ["# Churn model\n", "Some prose.\n"] },
+                { "cell_type": "code", "source": "import pandas as pd\ndf = pd.read_csv('x.csv')\n" },
+                { "cell_type": "code", "source": ["def score(row):\n", "    return row.total * 2\n"] }
+            ],
+            "metadata": { "language_info": { "name": "python", "file_extension": ".py" } }
+        }"##;
+        let stubs = extract_stubs(nb, "ipynb");
+        assert!(stubs.contains("# %% [0] markdown (2 lines)\n# # Churn model\n"), "{stubs}");
+        assert!(stubs.contains("# %% [1] code (2 lines)\nimport pandas as pd\n"), "{stubs}");
+        assert!(stubs.contains("def score(row):\n    ...\n"), "{stubs}");
+        assert!(!stubs.contains("read_csv") && !stubs.contains("row.total"), "{stubs}");
+        assert_eq!(extract_tier3(nb, "ipynb"), "# Churn model\ndef score(row)\n");
+    }
+
+    #[test]
+    fn sql_stubs_keep_ddl_signatures() {
+        let sql = "-- Accounts\nCREATE TABLE users (\n  id serial PRIMARY KEY,\n  name text NOT NULL\n);\n\
+                   INSERT INTO users (name) VALUES ('a;b');\n\
+                   CREATE OR REPLACE VIEW active_users AS\n  SELECT * FROM users WHERE active;\n\
+                   CREATE FUNCTION add_one(x integer) RETURNS integer AS $$\n  SELECT x + 1;\n$$ LANGUAGE sql;\n\
+                   CREATE INDEX users_name ON users (name);\n";
+        let stubs = extract_stubs(sql, "sql");
+        assert!(stubs.contains("-- Accounts\nCREATE TABLE users (\n  id serial"), "{stubs}");
+        assert!(stubs.contains("CREATE OR REPLACE VIEW active_users AS ...;"), "{stubs}");
+        assert!(
+            stubs.contains("CREATE FUNCTION add_one(x integer) RETURNS integer AS ...;"),
+            "{stubs}"
+        );
+        assert!(stubs.contains("CREATE INDEX users_name ON users (name);"), "{stubs}");
+        assert!(stubs.ends_with("-- ... 1 other statements\n"), "{stubs}");
+        assert!(!stubs.contains("x + 1") && !stubs.contains("WHERE active"), "{stubs}");
+        assert_eq!(
+            extract_tier3(sql, "sql"),
+            "CREATE TABLE users\nCREATE OR REPLACE VIEW active_users AS\n\
+             CREATE FUNCTION add_one(x integer) RETURNS integer AS $$\nCREATE INDEX users_name ON users (name)\n"
+        );
+    }
+
+    #[test]
+    fn document_stubs_outline_headings() {
+        let md = "---\ntitle: Guide\ntags: [a, b]\n---\n# Guide\n\nIntro.\n\n## Install\n\n```sh\n# not a heading\n```\n\nUsage\n-----\nRun it.\n";
+        assert_eq!(
+            extract_stubs(md, "md"),
+            "---\ntitle = \"Guide\"\ntags = [a, b]\n---\n# Guide  (13 lines)\n## Install  (6 lines)\n## Usage  (3 lines)\n"
+        );
+        assert_eq!(extract_tier3(md, "md"), "# Guide\n## Install\n## Usage\n");
+        let outline: Vec<(String, usize, usize, usize)> = extract_outline(md, "md")
+            .into_iter()
+            .map(|e| (e.name, e.start_line, e.end_line, e.depth))
+            .collect();
+        assert_eq!(
+            outline,
+            [("Guide".into(), 5, 17, 0), ("Install".into(), 9, 14, 1), ("Usage".into(), 15, 17, 1)]
+        );
+
+        let adoc = "= Manual\n\n== Setup\n----\n= not a heading\n----\n=== Linux\n";
+        assert_eq!(
+            extract_stubs(adoc, "adoc"),
+            "= Manual  (7 lines)\n== Setup  (5 lines)\n=== Linux  (1 lines)\n"
+        );
+        assert_eq!(extract_stubs("Just prose.\n", "md"), "Just prose.\n");
+    }
+
+    #[test]
+    fn config_stubs_list_key_paths() {
+        let yaml = "# deploy\nserver:\n  port: 8080\n  host: \"0.0.0.0\"  # all interfaces\nservices:\n  - name: api\n    replicas: 2\n  - name: web\nscript: |\n  echo: hi\nflags: [x, y]\n---\nkind: Secret\n";
+        assert_eq!(
+            extract_stubs(yaml, "yaml"),
+            "server.port = 8080\nserver.host = \"0.0.0.0\"\nservices[] (2 items)\nservices[].name = \"api\"\n\
+             services[].replicas = 2\nscript = |\nflags = [x, y]\n---\nkind = \"Secret\"\n"
+        );
+        let toml = "[package]\nname = \"demo\"\nkeywords = [\"a\", \"b\"]\n\n[dependencies.serde]\nversion = \"1\"\n";
+        assert_eq!(
+            extract_stubs(toml, "toml"),
+            "package.name = \"demo\"\npackage.keywords = [\"a\", \"b\"]\ndependencies.serde.version = \"1\"\n"
+        );
+        let json = r#"{"scripts": {"build": "tsc"}, "a.b": {}, "deep": {"l1": {"l2": {"l3": {"l4": {"x": 1}}}}}}"#;
+        assert_eq!(
+            extract_stubs(json, "json"),
+            "scripts.build = \"tsc\"\n\"a.b\" = {}\ndeep.l1.l2.l3.l4 = {1 keys}\n"
+        );
+        // Unparseable files keep their first lines
+        assert_eq!(extract_stubs("{ broken", "json"), "{ broken\n");
+    }
+
+    /// Extensions covering every [`LanguageFamily`] plus the structured sub-formats.
+    const PROPTEST_EXTS: &[&str] = &[
+        "rs", "cpp", "h", "ts", "cs", "go", "py", "rb", "ini", "json", "yaml", "toml", "xml",
+        "ipynb", "sql", "md", "adoc", "txt",
+    ];
+
+    /// Code-shaped fragments that exercise brace/indent/comment/string handling.
+    fn code_fragment() -> impl proptest::strategy::Strategy<Value = String> {
+        use proptest::prelude::*;
+        prop_oneof![
+            Just("{".to_string()),
+            Just("}".to_string()),
+            Just("\n".to_string()),
+            Just("    ".to_string()),
+            Just("\t".to_string()),
+            Just("fn f() ".to_string()),
+            Just("class C : public B ".to_string()),
+            Just("def f(x):".to_string()),
+            Just("#include \"a.h\"".to_string()),
+            Just("#if X".to_string()),
+            Just("/*".to_string()),
+            Just("*/".to_string()),
+            Just("//".to_string()),
+            Just("\"".to_string()),
+            Just("'".to_string()),
+            Just("\"\"\"".to_string()),
+            Just("[section]".to_string()),
+            Just("key = value".to_string()),
+            Just("<tag attr=\"1\">".to_string()),
+            Just("- item:".to_string()),
+            Just("@decorator".to_string()),
+            Just("CREATE FUNCTION f() AS $$".to_string()),
+            Just("$$;".to_string()),
+            Just("{\"cells\": [".to_string()),
+            Just("é漢🦀".to_string()),
+            "\\PC{0,6}",
+        ]
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn extract_stubs_never_panics_on_arbitrary_utf8(
+            content in "\\PC{0,400}",
+            ext in proptest::sample::select(PROPTEST_EXTS),
+        ) {
+            let _ = extract_stubs(&content, ext);
+        }
+
+        #[test]
+        fn stub_pipeline_never_panics_on_code_shapes(
+            fragments in proptest::collection::vec(code_fragment(), 0..80),
+            ext in proptest::sample::select(PROPTEST_EXTS),
+        ) {
+            let content = fragments.concat();
+            let tier1 = extract_stubs(&content, ext);
+            let _ = extract_tier2(&tier1);
+            let _ = extract_tier3(&content, ext);
+            let _ = parse_blocks(&tier1, ext);
+        }
+    }
+
+    #[test]
+    fn symbol_marks_label_definitions_with_their_extent() {
+        let rust = [
+            "pub struct Config;",
+            "",
+            "impl Config {",
+            "    pub fn run_search(",
+            "        query: &str,",
+            "    ) -> usize {",
+            "        query.len()",
+            "    }",
+            "}",
+        ];
+        let marks = symbol_marks(&rust);
+        let summary: Vec<(usize, usize, &str)> =
+            marks.iter().map(|m| (m.start, m.end, m.label.as_str())).collect();
+        assert_eq!(
+            summary,
+            [(0, 0, "struct Config"), (2, 8, "impl Config"), (3, 7, "fn run_search")]
+        );
+
+        let python = ["class Parser:", "    def parse(self):", "        pass", "", "x = 1"];
+        let marks = symbol_marks(&python);
+        assert_eq!((marks[0].end, marks[0].label.as_str()), (2, "class Parser"));
+        assert_eq!((marks[1].end, marks[1].label.as_str()), (2, "fn parse"));
+
+        let go = ["func (s *Server) Run(ctx context.Context) error {", "\treturn nil", "}"];
+        assert_eq!(symbol_marks(&go)[0].label, "fn Run");
+        let ruby = ["class Store", "  def get(k)", "    @h[k]", "  end", "end"];
+        let outline = extract_outline(&ruby.join("\n"), "rb");
+        let summary: Vec<(&str, &str, usize, usize, usize)> = outline
+            .iter()
+            .map(|e| (e.kind.as_str(), e.name.as_str(), e.start_line, e.end_line, e.depth))
+            .collect();
+        assert_eq!(summary, [("class", "Store", 1, 5, 0), ("fn", "get", 2, 4, 1)]);
+        let c = ["static int parse_header(const char *buf)", "{", "    return 0;", "}"];
+        assert_eq!(
+            symbol_marks(&c),
+            [SymbolMark { start: 0, end: 3, label: "fn parse_header".into() }]
+        );
+    }
+
+    #[test]
+    fn enclosing_symbol_finds_signature_above_match() {
+        let rust = [
+            "impl Server {",
+            "    pub(crate) fn dispatch(",
+            "        &self,",
+            "        msg: &Value,",
+            "    ) -> Option<Value> {",
+            "        match msg {",
+            "            Some(x) => {",
+            "                handle(x);",
+            "            }",
+            "        }",
+            "    }",
+            "}",
+        ];
+        assert_eq!(enclosing_symbol(&rust, 7), Some(1));
+        assert_eq!(enclosing_symbol(&rust, 5), Some(1));
+        assert_eq!(enclosing_symbol(&rust, 1), None, "a signature is its own context");
+        assert_eq!(enclosing_symbol(&rust, 11), None);
+
+        let python =
+            ["class Cache:", "    def get(self, key):", "        if key:", "            return 1"];
+        assert_eq!(enclosing_symbol(&python, 3), Some(1));
+
+        let c = [
+            "static int",
+            "int main(int argc, char **argv)",
+            "{",
+            "    if (argc) {",
+            "        run();",
+            "    }",
+            "}",
+        ];
+        assert_eq!(enclosing_symbol(&c, 4), Some(1));
+
+        let js = [
+            "let load = async (id) => {",
+            "  const object = { id };",
+            "  items.forEach((item) => {",
+            "    render(item);",
+            "  });",
+            "};",
+        ];
+        assert_eq!(enclosing_symbol(&js, 3), Some(0));
+    }
+
+    /// `rule name:` headers of a rules DSL, each running to the next blank line.
+    struct RulesExtractor;
+
+    impl StubExtractor for RulesExtractor {
+        fn extract_stubs(&self, content: &str) -> String {
+            let rules = content.lines().filter(|l| l.starts_with("rule "));
+            rules.map(|l| format!("{l} ...\n")).collect()
+        }
+
+        fn extract_outline(&self, content: &str) -> Vec<OutlineEntry> {
+            let lines: Vec<&str> = content.lines().collect();
+            let headers = lines.iter().enumerate().filter_map(|(i, l)| {
+                let name = l.strip_prefix("rule ")?.trim_end_matches(':');
+                Some((i, name))
+            });
+            headers
+                .map(|(i, name)| {
+                    let end =
+                        (i..lines.len()).find(|&j| lines[j].is_empty()).unwrap_or(lines.len());
+                    OutlineEntry {
+                        kind: "rule".into(),
+                        name: name.into(),
+                        start_line: i + 1,
+                        end_line: end,
+                        depth: 0,
+                    }
+                })
+                .collect()
+        }
+    }
+
+    /// [`RulesExtractor`] registered for an extension until dropped, so the global registry
+    /// is restored for other tests even when an assertion fails.
+    struct Registered(&'static str);
+
+    impl Registered {
+        fn new(ext: &'static str) -> Self {
+            register_extractor(ext, Box::new(RulesExtractor));
+            Self(ext)
+        }
+    }
+
+    impl Drop for Registered {
+        fn drop(&mut self) {
+            unregister_extractor(self.0);
+        }
+    }
+
+    #[test]
+    fn registered_extractors_stub_and_outline_their_extension() {
+        let content = "rule deny_all:\n  match *\n  drop\n\nrule allow_web:\n  match :443\n";
+        assert!(!extract_stubs(content, "stubrules").contains("rule deny_all ..."));
+        assert_eq!(crate::lang::detect(&Default::default(), "fw/edge.stubrules", ""), None);
+
+        let _registered = Registered::new(".StubRules");
+        assert_eq!(
+            extract_stubs(content, "stubrules"),
+            "rule deny_all: ...\nrule allow_web: ...\n"
+        );
+        let outline: Vec<(String, usize, usize)> = extract_outline(content, "stubrules")
+            .into_iter()
+            .map(|e| (e.name, e.start_line, e.end_line))
+            .collect();
+        assert_eq!(outline, [("deny_all".to_string(), 1, 3), ("allow_web".to_string(), 5, 6)]);
+
+        // Files with the extension are detected as that language, so repo reads use it too
+        let lang = crate::lang::detect(&Default::default(), "fw/edge.stubrules", "");
+        assert_eq!(lang, Some("stubrules"));
+        assert_eq!(crate::lang::stub_ext(lang), "stubrules");
+        let options = StubOptions::default();
+        let stubs = extract_file_stubs(&Default::default(), "fw/edge.stubrules", content, options);
+        assert!(stubs.starts_with("rule deny_all: ..."), "{stubs}");
+
+        drop(_registered);
+        assert!(!extract_stubs(content, "stubrules").contains("rule deny_all: ..."));
+        assert_eq!(crate::lang::detect(&Default::default(), "fw/edge.stubrules", ""), None);
+        assert!(!unregister_extractor("stubrules"));
+    }
+
+    #[test]
+    fn extractors_for_a_claimed_extension_apply_to_that_extension_only() {
+        // `pyw` files are Python, whose stub extension is `py`
+        let content = "rule deny_all:\n  drop\n";
+        let _registered = Registered::new("pyw");
+        let lang = crate::lang::detect(&Default::default(), "tools/gui.pyw", "");
+        assert_eq!(lang, Some("python"));
+        assert_eq!(crate::lang::file_stub_ext(lang, "tools/gui.pyw"), "pyw");
+        assert_eq!(crate::lang::file_stub_ext(lang, "tools/cli.py"), "py");
+        let options = StubOptions::default();
+        let stubs = extract_file_stubs(&Default::default(), "tools/gui.pyw", content, options);
+        assert_eq!(stubs, "rule deny_all: ...\n");
+        let stubs = extract_file_stubs(&Default::default(), "tools/cli.py", content, options);
+        assert!(!stubs.contains("rule deny_all: ..."), "{stubs}");
+    }
 }
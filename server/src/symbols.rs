@@ -0,0 +1,265 @@
+//! Symbol index: the functions, methods, types, and document sections each file defines,
+//! so `cs_search` can answer a query like `allocate_budget` with the defining line rather
+//! than only files whose names or contents match. The LSP server answers
+//! `workspace/symbol`, `textDocument/definition`, and `textDocument/references` from the
+//! same index, ranked the same way.
+//!
+//! Symbols come from [`crate::stubs::extract_outline`] and are collected once per file
+//! during scan (and again for files the watcher re-indexes), skipping files the repo's
+//! read policy denies. Matching ignores case and the `_`/`-` separators, so
+//! `allocate_budget`, `allocateBudget`, and `AllocateBudget` all find each other.
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::stubs::{classify_language, LanguageFamily};
+use crate::types::{ScanConfig, ScannedFile};
+
+/// Files longer than this are skipped (likely generated or bundled).
+const MAX_SYMBOL_SCAN_LINES: u32 = 20_000;
+
+/// A column on a definition's line, counted in both LSP position encodings.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Column {
+    /// UTF-8 bytes.
+    pub utf8: u32,
+    /// UTF-16 code units.
+    pub utf16: u32,
+}
+
+impl Column {
+    /// Column of byte offset `byte` in `line`.
+    fn at(line: &str, byte: usize) -> Self {
+        let prefix = &line[..byte.min(line.len())];
+        Self { utf8: prefix.len() as u32, utf16: prefix.encode_utf16().count() as u32 }
+    }
+}
+
+/// One definition found in a file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SymbolEntry {
+    pub name: String,
+    /// `fn`, `class`, `struct`, `impl`, `##`, and so on, as in
+    /// [`crate::stubs::OutlineEntry`].
+    pub kind: String,
+    pub path: String,
+    /// 1-based first and last line of the definition.
+    pub line: usize,
+    pub end_line: usize,
+    /// Nesting depth; 0 at the top level.
+    pub depth: usize,
+    /// Enclosing definition, as `class Foo`, for methods and nested types.
+    pub parent: Option<String>,
+    /// Where the name starts and ends on the first line.
+    pub name_start: Column,
+    pub name_end: Column,
+    /// End of the last line.
+    pub end: Column,
+}
+
+impl SymbolEntry {
+    /// Markdown and AsciiDoc sections, which are never the definition of an identifier.
+    pub fn is_heading(&self) -> bool {
+        self.kind.starts_with(['#', '='])
+    }
+}
+
+/// A symbol matching a query, best first from [`search_symbols`].
+#[derive(Debug)]
+pub struct SymbolHit<'a> {
+    pub symbol: &'a SymbolEntry,
+    /// 4 for a whole-name match, 3 for a prefix, 2 when the name contains every term, 1
+    /// when the query's letters appear in the name in order.
+    pub score: u8,
+}
+
+impl SymbolHit<'_> {
+    /// Best first: higher score, then shorter name, then path and line.
+    pub fn rank(&self, other: &Self) -> std::cmp::Ordering {
+        other
+            .score
+            .cmp(&self.score)
+            .then(self.symbol.name.len().cmp(&other.symbol.name.len()))
+            .then(self.symbol.path.cmp(&other.symbol.path))
+            .then(self.symbol.line.cmp(&other.symbol.line))
+    }
+}
+
+/// Last segment of a qualified name: `bar` for `Foo::bar` and `Foo.bar`.
+pub fn simple_name(name: &str) -> &str {
+    name.rsplit(['.', ':']).next().unwrap_or(name)
+}
+
+/// Whether a file goes into the index: readable under `config`'s read policy, and text in a
+/// language with definitions or sections to outline.
+fn indexed(file: &ScannedFile, config: &ScanConfig) -> bool {
+    file.is_searchable()
+        && file.lines <= MAX_SYMBOL_SCAN_LINES
+        && config.read_policy.permits(&file.rel_path)
+        && !matches!(
+            classify_language(crate::lang::file_stub_ext(file.lang, &file.rel_path)),
+            LanguageFamily::ConfigIni
+                | LanguageFamily::ConfigStructured
+                | LanguageFamily::Notebook
+                | LanguageFamily::Sql
+        )
+}
+
+/// Read and outline one file. Files the read policy denies, files without searchable
+/// content, and config formats yield nothing.
+pub fn detect_file_symbols(file: &ScannedFile, config: &ScanConfig) -> Vec<SymbolEntry> {
+    if !indexed(file, config) {
+        return Vec::new();
+    }
+    let Ok(content) = crate::encoding::read_to_string(&file.abs_path) else {
+        return Vec::new();
+    };
+    outline(&content, crate::lang::file_stub_ext(file.lang, &file.rel_path), &file.rel_path)
+}
+
+/// The outline of `content` as symbols of `path`, each after the one enclosing it.
+pub fn outline(content: &str, ext: &str, path: &str) -> Vec<SymbolEntry> {
+    let lines: Vec<&str> = content.lines().collect();
+    // Labels of the definitions enclosing the current one, by depth
+    let mut parents: Vec<String> = Vec::new();
+    crate::stubs::extract_outline(content, ext)
+        .into_iter()
+        .map(|entry| {
+            parents.truncate(entry.depth);
+            let parent = parents.last().cloned();
+            parents.push(format!("{} {}", entry.kind, entry.name));
+            let first = entry.start_line.max(1);
+            let last = entry.end_line.max(first);
+            let line = lines.get(first - 1).copied().unwrap_or("");
+            let end_line = lines.get(last - 1).copied().unwrap_or("");
+            let heading = entry.kind.starts_with(['#', '=']);
+            let name = if heading { entry.name.as_str() } else { simple_name(&entry.name) };
+            let name_at = if heading {
+                line.find(name)
+            } else {
+                crate::lsp::word_occurrences(line, name).next()
+            };
+            let name_at = name_at.unwrap_or(line.len() - line.trim_start().len());
+            SymbolEntry {
+                name_start: Column::at(line, name_at),
+                name_end: Column::at(line, name_at + name.len()),
+                end: Column::at(end_line, end_line.len()),
+                name: entry.name,
+                kind: entry.kind,
+                path: path.to_string(),
+                line: first,
+                end_line: last,
+                depth: entry.depth,
+                parent,
+            }
+        })
+        .collect()
+}
+
+/// Collect symbols across all scanned files, sorted by path and line.
+pub fn scan_symbols(all_files: &[ScannedFile], config: &ScanConfig) -> Vec<SymbolEntry> {
+    let mut symbols: Vec<SymbolEntry> =
+        all_files.par_iter().flat_map_iter(|f| detect_file_symbols(f, config)).collect();
+    sort_symbols(&mut symbols);
+    symbols
+}
+
+pub fn sort_symbols(symbols: &mut [SymbolEntry]) {
+    symbols.sort_by(|a, b| a.path.cmp(&b.path).then(a.line.cmp(&b.line)));
+}
+
+/// Lowercase `s` without `_` and `-`, the form names are compared in.
+fn normalize(s: &str) -> String {
+    s.chars().filter(|c| *c != '_' && *c != '-').flat_map(char::to_lowercase).collect()
+}
+
+/// Symbols whose name matches `query`, best first (see [`SymbolHit::rank`]): whole-name
+/// matches, then prefixes, then names containing every whitespace-separated term, then
+/// names holding the query's letters in order. Qualified names (`Foo::bar`) match on their
+/// last segment too.
+pub fn search_symbols<'a>(
+    symbols: &'a [SymbolEntry],
+    query: &str,
+    keep: impl Fn(&SymbolEntry) -> bool + Sync,
+) -> Vec<SymbolHit<'a>> {
+    let terms: Vec<String> =
+        query.split_whitespace().map(normalize).filter(|t| !t.is_empty()).collect();
+    if terms.is_empty() {
+        return Vec::new();
+    }
+    let joined = terms.concat();
+    let mut hits: Vec<SymbolHit> = symbols
+        .par_iter()
+        .filter_map(|symbol| {
+            let name = normalize(&symbol.name);
+            let simple = normalize(simple_name(&symbol.name));
+            let score = if name == joined || simple == joined {
+                4
+            } else if name.starts_with(&joined) || simple.starts_with(&joined) {
+                3
+            } else if terms.iter().all(|t| name.contains(t.as_str())) {
+                2
+            } else if is_subsequence(&joined, &name) {
+                1
+            } else {
+                return None;
+            };
+            keep(symbol).then_some(SymbolHit { symbol, score })
+        })
+        .collect();
+    hits.sort_by(|a, b| a.rank(b));
+    hits
+}
+
+/// Whether the characters of `needle` appear in `haystack` in order.
+fn is_subsequence(needle: &str, haystack: &str) -> bool {
+    let mut rest = haystack.chars();
+    needle.chars().all(|n| rest.any(|c| c == n))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbol(name: &str, path: &str, line: usize) -> SymbolEntry {
+        SymbolEntry {
+            name: name.to_string(),
+            kind: "fn".to_string(),
+            path: path.to_string(),
+            line,
+            end_line: line,
+            depth: 0,
+            parent: None,
+            name_start: Column::default(),
+            name_end: Column::default(),
+            end: Column::default(),
+        }
+    }
+
+    #[test]
+    fn names_match_across_case_and_separators_best_first() {
+        let symbols = vec![
+            symbol("allocate_budget_for_tokens", "a.rs", 10),
+            symbol("reallocate_budget", "b.rs", 3),
+            symbol("AllocateBudget", "c.ts", 7),
+            symbol("Planner::allocate_budget", "d.rs", 20),
+            symbol("allocate", "e.rs", 1),
+        ];
+        let hits = search_symbols(&symbols, "allocate_budget", |_| true);
+        let found: Vec<(&str, u8)> =
+            hits.iter().map(|h| (h.symbol.path.as_str(), h.score)).collect();
+        assert_eq!(found, vec![("c.ts", 4), ("d.rs", 4), ("a.rs", 3), ("b.rs", 2)]);
+
+        let hits = search_symbols(&symbols, "budget tokens", |_| true);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].score, 2);
+        assert_eq!(hits[0].symbol.line, 10);
+
+        let hits = search_symbols(&symbols, "alcbdg", |_| true);
+        assert!(hits.iter().all(|h| h.score == 1) && hits.len() == 4, "{hits:?}");
+
+        let hits = search_symbols(&symbols, "allocate", |s| s.path != "e.rs");
+        assert!(hits.iter().all(|h| h.symbol.path != "e.rs"));
+        assert!(search_symbols(&symbols, "  ", |_| true).is_empty());
+    }
+}
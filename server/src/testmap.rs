@@ -0,0 +1,376 @@
+//! Test-to-source mapping for `cs_tests` and the "Related tests" footer of `cs_read`.
+//!
+//! Three signals link a test file to a source file: naming conventions (`scan_test.go`,
+//! `test_scan.py`, `Scan.spec.ts`, `tests/scan.rs`), import edges from the test to the
+//! source, and references in the test to symbols declared in the source's stubs. Rust
+//! files with an inline `#[cfg(test)]` module count as their own tests.
+//!
+//! [`test_impact`] selects the tests to run for a change: every test that depends on a
+//! changed file through the import graph, plus the tests named after (or inline in) each
+//! source file the change reaches.
+
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+use crate::stubs::extract_stubs;
+use crate::types::RepoState;
+
+/// Test files read when checking symbol references.
+const MAX_TEST_FILES_SCANNED: usize = 500;
+
+/// Files larger than this are not read for symbol references.
+const MAX_TEST_FILE_BYTES: u64 = 256 * 1024;
+
+/// Source symbols considered per file.
+const MAX_SOURCE_SYMBOLS: usize = 200;
+
+/// Referenced symbols listed per match.
+const MAX_LISTED_REFS: usize = 5;
+
+/// Names too generic to link a test to a particular source file.
+const GENERIC_SYMBOLS: &[&str] = &[
+    "main", "new", "init", "test", "tests", "setup", "teardown", "default", "from", "into",
+    "clone", "drop", "self", "this", "config", "options", "error", "result", "value", "data",
+    "name", "path", "type", "index", "get", "set", "run", "start", "stop", "build", "create",
+    "update", "delete", "handle", "handler", "parse", "load", "save", "read", "write",
+];
+
+/// Directory names that hold tests.
+const TEST_DIRS: &[&str] = &["test", "tests", "__tests__", "spec", "specs", "testing"];
+
+/// Why a test file was linked to a source file.
+#[derive(Debug, Default, Clone)]
+pub struct TestLink {
+    pub path: String,
+    /// File names match after stripping test affixes.
+    pub by_name: bool,
+    /// The test imports the source file.
+    pub by_import: bool,
+    /// Source symbols the test references, up to [`MAX_LISTED_REFS`].
+    pub refs: Vec<String>,
+    /// Total source symbols the test references.
+    pub ref_count: usize,
+    /// The source file carries its own inline test module.
+    pub inline: bool,
+}
+
+impl TestLink {
+    fn score(&self) -> usize {
+        usize::from(self.inline) * 10
+            + usize::from(self.by_name) * 4
+            + usize::from(self.by_import) * 4
+            + self.ref_count.min(4)
+    }
+
+    /// Comma-separated reasons, e.g. `name, imports, refs: parse_config, Scanner`.
+    pub fn reasons(&self) -> String {
+        let mut parts = Vec::new();
+        if self.inline {
+            parts.push("inline #[cfg(test)]".to_string());
+        }
+        if self.by_name {
+            parts.push("name".to_string());
+        }
+        if self.by_import {
+            parts.push("imports".to_string());
+        }
+        if self.ref_count > 0 {
+            let more = self.ref_count.saturating_sub(self.refs.len());
+            let suffix = if more > 0 { format!(" +{more}") } else { String::new() };
+            parts.push(format!("refs: {}{suffix}", self.refs.join(", ")));
+        }
+        parts.join(", ")
+    }
+}
+
+/// Whether a repo-relative path looks like a test file.
+pub fn is_test_file(rel_path: &str) -> bool {
+    let lower = rel_path.to_lowercase();
+    let mut parts: Vec<&str> = lower.split('/').collect();
+    let filename = parts.pop().unwrap_or("");
+    if parts.iter().any(|p| TEST_DIRS.contains(p)) {
+        return true;
+    }
+    let stem = filename.split_once('.').map(|(s, _)| s).unwrap_or(filename);
+    filename.contains(".test.")
+        || filename.contains(".spec.")
+        || stem.starts_with("test_")
+        || stem.ends_with("_test")
+        || stem.ends_with("_tests")
+        || stem.ends_with("_spec")
+        || has_pascal_test_suffix(rel_path)
+}
+
+/// `FooTest.java` / `FooTests.cs` — a capitalized `Test`/`Tests` suffix after a word.
+fn has_pascal_test_suffix(rel_path: &str) -> bool {
+    let filename = rel_path.rsplit('/').next().unwrap_or(rel_path);
+    let stem = filename.split_once('.').map(|(s, _)| s).unwrap_or(filename);
+    let base = stem.strip_suffix("Tests").or_else(|| stem.strip_suffix("Test"));
+    base.is_some_and(|b| !b.is_empty() && !b.ends_with('_'))
+}
+
+/// File stem with test affixes removed and case folded: `test_scan.py`, `ScanTest.java`,
+/// and `scan.spec.ts` all normalize to `scan`.
+fn subject_stem(rel_path: &str) -> String {
+    let filename = rel_path.rsplit('/').next().unwrap_or(rel_path);
+    let stem = filename.split_once('.').map(|(s, _)| s).unwrap_or(filename);
+    let stem = stem.strip_prefix("test_").unwrap_or(stem);
+    let stem = ["_tests", "_test", "_spec", "Tests", "Test"]
+        .iter()
+        .find_map(|suffix| stem.strip_suffix(suffix).filter(|s| !s.is_empty()))
+        .unwrap_or(stem);
+    stem.to_lowercase().replace(['-', '_'], "")
+}
+
+/// Symbols declared in a source file, taken from its stubs.
+fn declared_symbols(content: &str, ext: &str) -> Vec<String> {
+    let decl_re = Regex::new(
+        r"\b(?:fn|def|func|function|class|struct|enum|trait|interface|type|record|module)\s+(?:\([^)]*\)\s*)?([A-Za-z_]\w*)",
+    )
+    .unwrap();
+    let stubs = extract_stubs(content, ext);
+    let mut seen = HashSet::new();
+    decl_re
+        .captures_iter(&stubs)
+        .map(|c| c[1].to_string())
+        .filter(|s| s.len() >= 4 && !GENERIC_SYMBOLS.contains(&s.to_lowercase().as_str()))
+        .filter(|s| seen.insert(s.clone()))
+        .take(MAX_SOURCE_SYMBOLS)
+        .collect()
+}
+
+/// Test files linked to `source`, best first.
+///
+/// `with_refs` enables the symbol-reference signal, which reads every candidate test file;
+/// without it only names and import edges are used, which costs no I/O beyond the source.
+pub fn tests_for_source(repo: &RepoState, source: &str, with_refs: bool) -> Vec<TestLink> {
+    let ext = source.rsplit_once('.').map(|(_, e)| e).unwrap_or("");
+    let content = crate::encoding::read_to_string(repo.root.join(source)).unwrap_or_default();
+    let lang = crate::lang::detect(&repo.config.languages, source, &content);
+    let stub_ext = crate::lang::file_stub_ext(lang, source);
+    let subject = subject_stem(source);
+    let symbols = if with_refs { declared_symbols(&content, stub_ext) } else { Vec::new() };
+    let importers: HashSet<&str> = repo
+        .import_graph
+        .imported_by
+        .get(source)
+        .map(|v| v.iter().map(String::as_str).collect())
+        .unwrap_or_default();
+
+    let mut links = Vec::new();
+    if ext == "rs" && content.contains("#[cfg(test)]") {
+        links.push(TestLink { path: source.to_string(), inline: true, ..Default::default() });
+    }
+
+    let mut scanned = 0usize;
+    for f in &repo.all_files {
+        if f.rel_path == source || !is_test_file(&f.rel_path) {
+            continue;
+        }
+        let mut link = TestLink {
+            path: f.rel_path.clone(),
+            by_name: subject_stem(&f.rel_path) == subject,
+            by_import: importers.contains(f.rel_path.as_str()),
+            ..Default::default()
+        };
+        if !symbols.is_empty()
+            && scanned < MAX_TEST_FILES_SCANNED
+            && f.abs_path.metadata().map(|m| m.len()).unwrap_or(0) <= MAX_TEST_FILE_BYTES
+        {
+            scanned += 1;
+            if let Ok(test_content) = crate::encoding::read_to_string(&f.abs_path) {
+                let words: HashSet<&str> =
+                    test_content.split(|c: char| !c.is_alphanumeric() && c != '_').collect();
+                let hits: Vec<&String> =
+                    symbols.iter().filter(|s| words.contains(s.as_str())).collect();
+                link.ref_count = hits.len();
+                link.refs = hits.into_iter().take(MAX_LISTED_REFS).cloned().collect();
+            }
+        }
+        // A lone reference is too weak to report on its own.
+        if link.by_name || link.by_import || link.ref_count >= 2 {
+            links.push(link);
+        }
+    }
+
+    links.sort_by(|a, b| b.score().cmp(&a.score()).then(a.path.cmp(&b.path)));
+    links
+}
+
+/// Source files a test file covers, by name and import edges, best first.
+pub fn sources_for_test(repo: &RepoState, test: &str) -> Vec<TestLink> {
+    let subject = subject_stem(test);
+    let imports: HashSet<&str> = repo
+        .import_graph
+        .imports
+        .get(test)
+        .map(|v| v.iter().map(String::as_str).collect())
+        .unwrap_or_default();
+    let mut links: Vec<TestLink> = repo
+        .all_files
+        .iter()
+        .filter(|f| f.rel_path != test && !is_test_file(&f.rel_path))
+        .filter_map(|f| {
+            let link = TestLink {
+                path: f.rel_path.clone(),
+                by_name: subject_stem(&f.rel_path) == subject,
+                by_import: imports.contains(f.rel_path.as_str()),
+                ..Default::default()
+            };
+            (link.by_name || link.by_import).then_some(link)
+        })
+        .collect();
+    links.sort_by(|a, b| b.score().cmp(&a.score()).then(a.path.cmp(&b.path)));
+    links
+}
+
+/// A test selected by [`test_impact`].
+#[derive(Debug, Clone)]
+pub struct ImpactedTest {
+    pub path: String,
+    /// Why it was selected, e.g. `changed`, `imports src/scan.rs`.
+    pub reason: String,
+}
+
+/// Files reached by a change and the tests to run for it.
+#[derive(Debug, Default)]
+pub struct TestImpact {
+    /// Changed files in the index.
+    pub changed: Vec<String>,
+    /// Changed files that are not indexed, so nothing is traced from them.
+    pub unindexed: Vec<String>,
+    /// Non-test files that changed or depend on a changed file, nearest first.
+    pub sources: Vec<String>,
+    /// The tests to run, by path.
+    pub tests: Vec<ImpactedTest>,
+}
+
+/// Tests affected by changes to `changed`, following importers up to `max_depth` levels.
+///
+/// A test is selected when it changed, when it depends on a changed file, or when it is
+/// linked by name to (or inline in) an affected source file.
+pub fn test_impact(repo: &RepoState, changed: &[String], max_depth: usize) -> TestImpact {
+    let indexed: HashSet<&str> = repo.all_files.iter().map(|f| f.rel_path.as_str()).collect();
+    let mut impact = TestImpact::default();
+    for path in changed {
+        if indexed.contains(path.as_str()) {
+            impact.changed.push(path.clone());
+        } else {
+            impact.unindexed.push(path.clone());
+        }
+    }
+
+    // Breadth-first over importers, so each file keeps its nearest reason
+    let mut reasons: BTreeMap<String, String> = BTreeMap::new();
+    let mut visited: HashSet<&str> = HashSet::new();
+    let mut queue: VecDeque<(&str, usize)> = VecDeque::new();
+    for path in &impact.changed {
+        if visited.insert(path) {
+            queue.push_back((path, 0));
+        }
+    }
+    let mut affected = Vec::new();
+    while let Some((current, depth)) = queue.pop_front() {
+        affected.push(current);
+        if is_test_file(current) && depth == 0 {
+            reasons.insert(current.to_string(), "changed".to_string());
+        }
+        if depth >= max_depth {
+            continue;
+        }
+        for dep in repo.import_graph.imported_by.get(current).into_iter().flatten() {
+            if visited.insert(dep) {
+                if is_test_file(dep) {
+                    reasons.entry(dep.clone()).or_insert_with(|| format!("imports {current}"));
+                }
+                queue.push_back((dep, depth + 1));
+            }
+        }
+    }
+
+    let mut tests_by_subject: HashMap<String, Vec<&str>> = HashMap::new();
+    for f in repo.all_files.iter().filter(|f| is_test_file(&f.rel_path)) {
+        tests_by_subject.entry(subject_stem(&f.rel_path)).or_default().push(&f.rel_path);
+    }
+    for source in affected.into_iter().filter(|p| !is_test_file(p)) {
+        impact.sources.push(source.to_string());
+        for test in tests_by_subject.get(&subject_stem(source)).into_iter().flatten() {
+            reasons.entry(test.to_string()).or_insert_with(|| format!("name matches {source}"));
+        }
+        if source.ends_with(".rs") {
+            let content = crate::encoding::read_to_string(repo.root.join(source));
+            if content.is_ok_and(|c| c.contains("#[cfg(test)]")) {
+                reasons.entry(source.to_string()).or_insert_with(|| "inline tests".to_string());
+            }
+        }
+    }
+    impact.tests =
+        reasons.into_iter().map(|(path, reason)| ImpactedTest { path, reason }).collect();
+    impact
+}
+
+/// Report of a [`test_impact`] result; at most `limit` affected sources are listed.
+pub fn format_test_impact(impact: &TestImpact, what: &str, limit: usize) -> String {
+    let plural = |n: usize| if n == 1 { "" } else { "s" };
+    let mut out = format!(
+        "Test impact for {what}: {} changed file{}, {} affected source file{}\n",
+        impact.changed.len(),
+        plural(impact.changed.len()),
+        impact.sources.len(),
+        plural(impact.sources.len())
+    );
+    if !impact.unindexed.is_empty() {
+        out.push_str(&format!(
+            "Not indexed (not traced): {}\n",
+            impact.unindexed.iter().take(10).cloned().collect::<Vec<_>>().join(", ")
+        ));
+    }
+    out.push('\n');
+    if impact.tests.is_empty() {
+        out.push_str("No tests affected.\n");
+    } else {
+        out.push_str(&format!("Tests to run ({}):\n", impact.tests.len()));
+        for test in &impact.tests {
+            out.push_str(&format!("  {}  ({})\n", test.path, test.reason));
+        }
+    }
+    if !impact.sources.is_empty() {
+        out.push_str(&format!("\nAffected sources ({}):\n", impact.sources.len()));
+        for source in impact.sources.iter().take(limit) {
+            out.push_str(&format!("  {source}\n"));
+        }
+        if impact.sources.len() > limit {
+            out.push_str(&format!("  ... {} more\n", impact.sources.len() - limit));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_detection_and_subject_stems() {
+        for path in [
+            "tests/fixtures.rs",
+            "pkg/scan_test.go",
+            "app/test_scan.py",
+            "src/Scan.spec.ts",
+            "src/components/__tests__/Tree.tsx",
+            "src/main/java/ScanTest.java",
+            "Scan.Tests/ScanTests.cs",
+        ] {
+            assert!(is_test_file(path), "{path} should be a test file");
+        }
+        for path in ["src/scan.rs", "src/contest.rs", "src/latest.py", "src/attest.go"] {
+            assert!(!is_test_file(path), "{path} should not be a test file");
+        }
+
+        assert_eq!(subject_stem("app/test_scan.py"), "scan");
+        assert_eq!(subject_stem("pkg/scan_test.go"), "scan");
+        assert_eq!(subject_stem("src/ScanTest.java"), "scan");
+        assert_eq!(subject_stem("src/Scan.spec.ts"), "scan");
+        assert_eq!(subject_stem("src/scan.rs"), "scan");
+    }
+}
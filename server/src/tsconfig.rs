@@ -0,0 +1,304 @@
+//! TypeScript path aliases from `tsconfig.json` for import resolution.
+//!
+//! Each JS/TS file uses the nearest `tsconfig.json` (or `jsconfig.json`) at or above its
+//! directory, within the repo. `compilerOptions.paths` and `baseUrl` are merged along the
+//! `extends` chain, the child winning, with each resolved against the config that sets it:
+//! `baseUrl` against its own directory, and `paths` against the effective `baseUrl` or,
+//! without one, the directory of the config defining `paths`. Relative `extends` and
+//! packages installed in the repo's `node_modules` are followed. Config files may contain
+//! comments and trailing commas.
+//!
+//! A non-relative import is tried against the `paths` pattern with the longest prefix
+//! before `*`, then against `baseUrl`; each candidate is probed with the usual extensions
+//! and `index` files. Only files in the index count as hits.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Config file names, in lookup order.
+const CONFIG_NAMES: &[&str] = &["tsconfig.json", "jsconfig.json"];
+
+/// Deepest `extends` chain followed.
+const MAX_EXTENDS: usize = 8;
+
+/// Suffixes probed after an alias target, in TypeScript's order.
+const PROBE_SUFFIXES: &[&str] = &[
+    "",
+    ".ts",
+    ".tsx",
+    ".d.ts",
+    ".js",
+    ".jsx",
+    ".mjs",
+    ".cjs",
+    "/index.ts",
+    "/index.tsx",
+    "/index.js",
+    "/index.jsx",
+];
+
+/// Effective `baseUrl` and `paths` of one config, relative to the repo root.
+#[derive(Debug, Default)]
+pub struct TsPaths {
+    base_url: Option<String>,
+    /// Pattern → targets with `*` kept, longest prefix before `*` first.
+    paths: Vec<(String, Vec<String>)>,
+}
+
+impl TsPaths {
+    /// The indexed file an aliased import specifier refers to.
+    pub fn resolve(&self, spec: &str, files: &HashSet<&str>) -> Option<String> {
+        if spec.starts_with('.') || spec.starts_with('/') {
+            return None;
+        }
+        for (pattern, targets) in &self.paths {
+            let Some(star) = match_pattern(pattern, spec) else { continue };
+            for target in targets {
+                if let Some(hit) = probe(&target.replacen('*', star, 1), files) {
+                    return Some(hit);
+                }
+            }
+            // TypeScript stops at the first matching pattern
+            return None;
+        }
+        let base = self.base_url.as_deref()?;
+        probe(&join(base, spec), files)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.base_url.is_none() && self.paths.is_empty()
+    }
+}
+
+/// The part of `spec` matched by `*`, or `""` for an exact pattern.
+fn match_pattern<'a>(pattern: &str, spec: &'a str) -> Option<&'a str> {
+    match pattern.split_once('*') {
+        Some((prefix, suffix)) => {
+            spec.strip_prefix(prefix)?.strip_suffix(suffix).filter(|rest| !rest.is_empty())
+        }
+        None => (pattern == spec).then_some(""),
+    }
+}
+
+fn probe(candidate: &str, files: &HashSet<&str>) -> Option<String> {
+    let candidate = normalize(candidate)?;
+    PROBE_SUFFIXES.iter().map(|s| format!("{candidate}{s}")).find(|p| files.contains(p.as_str()))
+}
+
+fn join(dir: &str, rel: &str) -> String {
+    if dir.is_empty() {
+        rel.to_string()
+    } else {
+        format!("{dir}/{rel}")
+    }
+}
+
+/// Resolve `.` and `..` components; `None` when the path leaves the root.
+fn normalize(path: &str) -> Option<String> {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            p => parts.push(p),
+        }
+    }
+    Some(parts.join("/"))
+}
+
+/// Nearest-config lookup for the directories of a repo's JS/TS files.
+pub struct TsConfigs {
+    by_dir: HashMap<String, Option<Arc<TsPaths>>>,
+}
+
+impl TsConfigs {
+    /// Load the configs governing `dirs` (relative to `root`).
+    pub fn load<'a>(root: &Path, dirs: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut by_dir: HashMap<String, Option<Arc<TsPaths>>> = HashMap::new();
+        let mut by_config: HashMap<PathBuf, Option<Arc<TsPaths>>> = HashMap::new();
+        for dir in dirs {
+            if by_dir.contains_key(dir) {
+                continue;
+            }
+            let mut found = None;
+            let mut current = Some(dir);
+            while let Some(d) = current {
+                if let Some(known) = by_dir.get(d) {
+                    found = known.clone();
+                    break;
+                }
+                let config =
+                    CONFIG_NAMES.iter().map(|n| root.join(d).join(n)).find(|p| p.is_file());
+                if let Some(config) = config {
+                    found = by_config
+                        .entry(config.clone())
+                        .or_insert_with(|| {
+                            let paths = load_chain(root, &config);
+                            (!paths.is_empty()).then(|| Arc::new(paths))
+                        })
+                        .clone();
+                    break;
+                }
+                current = (!d.is_empty()).then(|| d.rsplit_once('/').map_or("", |(p, _)| p));
+            }
+            by_dir.insert(dir.to_string(), found);
+        }
+        Self { by_dir }
+    }
+
+    /// Aliases for files in `dir`, if any config defines them.
+    pub fn for_dir(&self, dir: &str) -> Option<&TsPaths> {
+        self.by_dir.get(dir)?.as_deref()
+    }
+}
+
+/// Merge `baseUrl` and `paths` along the `extends` chain of `config`.
+fn load_chain(root: &Path, config: &Path) -> TsPaths {
+    let mut chain = Vec::new();
+    let mut next = Some(config.to_path_buf());
+    while let Some(path) = next.take() {
+        if chain.len() >= MAX_EXTENDS || chain.iter().any(|(p, _): &(PathBuf, _)| *p == path) {
+            break;
+        }
+        let Some(json) = read_jsonc(&path) else { break };
+        next = json
+            .get("extends")
+            .and_then(|e| e.as_str())
+            .and_then(|e| resolve_extends(root, &path, e));
+        chain.push((path, json));
+    }
+
+    let rel_dir = |path: &Path| -> Option<String> {
+        let dir = path.parent()?.strip_prefix(root).ok()?;
+        Some(dir.to_string_lossy().replace('\\', "/"))
+    };
+    let mut base_url: Option<String> = None;
+    let mut paths: Option<(String, &serde_json::Map<String, serde_json::Value>)> = None;
+    // Child first: the first config setting an option wins
+    for (path, json) in &chain {
+        let options = json.get("compilerOptions");
+        let Some(dir) = rel_dir(path) else { continue };
+        if base_url.is_none() {
+            if let Some(base) = options.and_then(|o| o.get("baseUrl")).and_then(|b| b.as_str()) {
+                base_url = normalize(&join(&dir, base));
+            }
+        }
+        if paths.is_none() {
+            if let Some(p) = options.and_then(|o| o.get("paths")).and_then(|p| p.as_object()) {
+                paths = Some((dir, p));
+            }
+        }
+    }
+
+    let mut result = TsPaths { base_url: base_url.clone(), paths: Vec::new() };
+    if let Some((paths_dir, map)) = paths {
+        let base = base_url.unwrap_or(paths_dir);
+        for (pattern, targets) in map {
+            let targets: Vec<String> = targets
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|t| t.as_str())
+                .map(|t| join(&base, t))
+                .collect();
+            result.paths.push((pattern.clone(), targets));
+        }
+        let prefix_len = |p: &str| p.split('*').next().unwrap_or(p).len();
+        result.paths.sort_by(|a, b| prefix_len(&b.0).cmp(&prefix_len(&a.0)));
+    }
+    result
+}
+
+fn resolve_extends(root: &Path, from: &Path, extends: &str) -> Option<PathBuf> {
+    let with_json = |p: PathBuf| if p.extension().is_some() { p } else { p.with_extension("json") };
+    let path = if extends.starts_with('.') {
+        with_json(from.parent()?.join(extends))
+    } else {
+        let package = root.join("node_modules").join(extends);
+        if package.is_dir() {
+            package.join("tsconfig.json")
+        } else {
+            with_json(package)
+        }
+    };
+    path.is_file().then_some(path)
+}
+
+fn read_jsonc(path: &Path) -> Option<serde_json::Value> {
+    let content = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&strip_jsonc(&content)).ok()
+}
+
+/// Drop `//` and `/* */` comments and trailing commas outside strings.
+fn strip_jsonc(src: &str) -> String {
+    let mut out = String::with_capacity(src.len());
+    let mut chars = src.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            if c == '\\' {
+                out.extend(chars.next());
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                out.push(c);
+            }
+            ('/', Some('/')) => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        out.push('\n');
+                        break;
+                    }
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut prev = ' ';
+                for c in chars.by_ref() {
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+            }
+            ('}' | ']', _) => {
+                let trimmed = out.trim_end().len();
+                if out[..trimmed].ends_with(',') {
+                    out.truncate(trimmed - 1);
+                }
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jsonc_comments_and_trailing_commas_are_dropped() {
+        let src = r#"{
+            // line comment
+            "compilerOptions": { /* block */ "baseUrl": ".", "paths": { "@/*": ["src/*",], }, },
+            "note": "keep // this, and /* this */",
+        }"#;
+        let json: serde_json::Value = serde_json::from_str(&strip_jsonc(src)).unwrap();
+        assert_eq!(json["compilerOptions"]["paths"]["@/*"][0], "src/*");
+        assert_eq!(json["note"], "keep // this, and /* this */");
+        assert_eq!(match_pattern("@app/*", "@app/util/log"), Some("util/log"));
+        assert_eq!(match_pattern("~/lib", "~/lib"), Some(""));
+        assert_eq!(match_pattern("@app/*", "@app/"), None);
+    }
+}
@@ -3,12 +3,14 @@
 //! MCP transport types, and path validation utilities.
 
 use dashmap::DashMap;
-use serde::Serialize;
-use std::collections::{BTreeMap, HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Instant;
 
+pub use codescope_client::api::ImportConfidence;
+
 // ---------------------------------------------------------------------------
 // Session state (per MCP connection, tracks what the agent has already read)
 // ---------------------------------------------------------------------------
@@ -18,11 +20,43 @@ pub struct SessionState {
     pub files_read: HashMap<String, Instant>,
     pub total_tokens_served: usize,
     pub started_at: Instant,
+    /// Default output profile, chosen by the client at `initialize`.
+    pub output_profile: crate::format::OutputProfile,
+    /// Last full read of each file, keyed by `[repo] path`.
+    read_receipts: HashMap<String, ReadReceipt>,
+    /// Bytes of content held by `read_receipts`.
+    receipt_bytes: usize,
+    /// Unsent remainders of results cut at `max_result_tokens`, oldest first, by cursor.
+    continuations: VecDeque<(String, String)>,
+    next_cursor: u64,
+}
+
+/// Cut results whose remainder a session keeps; older ones expire.
+pub const MAX_CONTINUATIONS: usize = 16;
+
+/// Most file content a session keeps for diffing re-reads; past it only hashes are kept.
+const MAX_RECEIPT_BYTES: usize = 16 * 1024 * 1024;
+
+/// What a session was last served of a whole file.
+pub struct ReadReceipt {
+    pub hash: u64,
+    /// The content served, unless the session's budget for kept content ran out.
+    pub content: Option<Arc<str>>,
+    pub at: Instant,
 }
 
 impl Default for SessionState {
     fn default() -> Self {
-        Self { files_read: HashMap::new(), total_tokens_served: 0, started_at: Instant::now() }
+        Self {
+            files_read: HashMap::new(),
+            total_tokens_served: 0,
+            started_at: Instant::now(),
+            output_profile: crate::format::OutputProfile::default(),
+            read_receipts: HashMap::new(),
+            receipt_bytes: 0,
+            continuations: VecDeque::new(),
+            next_cursor: 0,
+        }
     }
 }
 
@@ -39,11 +73,53 @@ impl SessionState {
     pub fn seen_paths(&self) -> HashSet<String> {
         self.files_read.keys().cloned().collect()
     }
+
+    /// The last full read of `key` in this session.
+    pub fn receipt(&self, key: &str) -> Option<&ReadReceipt> {
+        self.read_receipts.get(key)
+    }
+
+    /// Remember that `content` of `key` was served in full.
+    pub fn record_receipt(&mut self, key: &str, content: &str) {
+        if let Some(old) = self.read_receipts.remove(key).and_then(|r| r.content) {
+            self.receipt_bytes -= old.len();
+        }
+        let keep = self.receipt_bytes + content.len() <= MAX_RECEIPT_BYTES;
+        if keep {
+            self.receipt_bytes += content.len();
+        }
+        let receipt = ReadReceipt {
+            hash: crate::handoff::content_hash(content.as_bytes()),
+            content: keep.then(|| Arc::from(content)),
+            at: Instant::now(),
+        };
+        self.read_receipts.insert(key.to_string(), receipt);
+    }
+
+    /// Keep the unsent `rest` of a result and return the cursor that fetches it.
+    pub fn stash_continuation(&mut self, rest: String) -> String {
+        self.next_cursor += 1;
+        let cursor = format!("c{}", self.next_cursor);
+        if self.continuations.len() == MAX_CONTINUATIONS {
+            self.continuations.pop_front();
+        }
+        self.continuations.push_back((cursor.clone(), rest));
+        cursor
+    }
+
+    /// The remainder stashed under `cursor`, which is used up by fetching it.
+    pub fn take_continuation(&mut self, cursor: &str) -> Option<String> {
+        let i = self.continuations.iter().position(|(c, _)| c == cursor)?;
+        self.continuations.remove(i).map(|(_, rest)| rest)
+    }
 }
 
 /// Maximum file size (in bytes) that will be read into memory.
 pub const MAX_FILE_READ: usize = 512 * 1024;
 
+/// Default `max_grep_file_size`: larger files are skipped by `cs_grep` and `/api/grep`.
+pub const DEFAULT_MAX_GREP_FILE_SIZE: u64 = 256 * 1024 * 1024;
+
 // ---------------------------------------------------------------------------
 // Scan configuration — replaces hardcoded constants
 // ---------------------------------------------------------------------------
@@ -52,25 +128,110 @@ pub const MAX_FILE_READ: usize = 512 * 1024;
 #[derive(Clone)]
 pub struct ScanConfig {
     pub root: PathBuf,
-    /// Directories to scan (relative to root). Empty = scan root itself.
+    /// `[profile.<name>]` applied over the top-level settings, if any.
+    pub profile: Option<String>,
+    /// Directories to scan (relative to root), with glob entries expanded when the config
+    /// is loaded. Empty = scan root itself.
     pub scan_dirs: Vec<String>,
+    /// Leading paths stripped from category paths: each literal `scan_dirs` entry, and the
+    /// part of a glob entry before its first wildcard component.
+    pub scan_dir_prefixes: Vec<String>,
+    /// `scan_dirs` entries outside the root (`../shared-lib`, absolute paths). They are
+    /// also listed in `scan_dirs`, by their path relative to the root.
+    pub external_dirs: Vec<ExternalDir>,
     /// Directory names to skip during walk.
     pub skip_dirs: HashSet<String>,
-    /// File extensions to include. Empty = all text files.
+    /// `skip_dirs` entries that are globs or contain a `/`, matched against directory paths
+    /// relative to root. Entries without a `/` match at any depth.
+    pub skip_globs: Option<globset::GlobSet>,
+    /// File extensions to include. Empty (with no `extension_globs`) = all text files.
     pub extensions: HashSet<String>,
-    /// Directory names to collapse/strip from category paths.
+    /// `extensions` entries that are globs (`*.gen.ts`), matched against file names.
+    pub extension_globs: Option<globset::GlobSet>,
+    /// Directory names to collapse/strip from category paths. Ranking is unaffected; see
+    /// `noise`.
     pub noise_dirs: HashSet<String>,
+    /// Score directories that look like noise from scan statistics (`auto_noise`), for
+    /// files no `[noise]` pattern matches.
+    pub auto_noise: bool,
+    /// Honor `.gitignore`, `.ignore`, `.git/info/exclude`, and the global gitignore during
+    /// the walk and in the watcher (`respect_gitignore`, default true).
+    pub respect_gitignore: bool,
+    /// Descend into symlinked directories and index symlinked files (`follow_symlinks`,
+    /// default false). Symlinks that loop back to an ancestor are skipped.
+    pub follow_symlinks: bool,
+    /// Approximate memory ceiling for this repo's indexes. Exceeding it evicts caches.
+    pub max_memory_mb: Option<usize>,
+    /// Files larger than this many bytes are left out of grep (`max_grep_file_size`).
+    pub max_grep_file_size: u64,
+    /// Tool results over this many tokens are cut and continued by cursor
+    /// (`max_result_tokens`). `None` sends results whole.
+    pub max_result_tokens: Option<usize>,
+    /// Order of module and file listings (`sort_order`): natural by default.
+    pub sort_order: crate::collate::SortOrder,
+    /// Allow/deny globs applied to every file read (`read_allow` / `read_deny`).
+    pub read_policy: crate::paths::ReadPolicy,
+    /// `[languages]` overrides for language detection.
+    pub languages: crate::lang::LanguageOverrides,
+    /// `[redact]` secret masking for file reads.
+    pub redact: crate::redact::RedactConfig,
+    /// `[git]` default comparison branch and protected refs.
+    pub git: crate::git::GitConfig,
+    /// `[boundaries]` layers and denied imports between them.
+    pub boundaries: crate::boundaries::BoundaryConfig,
+    /// `[ranking]` search score multipliers.
+    pub ranking: crate::ranking::RankingConfig,
+    /// `[scopes.<name>]` saved search filters.
+    pub scopes: BTreeMap<String, crate::scopes::Scope>,
+    /// `[prompts.<name>]` MCP prompt templates.
+    pub prompts: BTreeMap<String, crate::prompts::PromptDef>,
+    /// `[aliases]` interchangeable query words.
+    pub aliases: crate::aliases::AliasConfig,
+    /// `[noise]` directory scores for ranking and semantic embedding.
+    pub noise: crate::noise::NoiseConfig,
+    /// Cargo, npm/yarn/pnpm, and Go workspace members, each a top-level module.
+    pub workspace_members: Vec<crate::workspaces::WorkspaceMember>,
+    /// Rules from the repo's `CODEOWNERS` file.
+    pub codeowners: crate::owners::CodeOwners,
+    /// Keys set by `CODESCOPE_<KEY>` environment variables, which override the file.
+    pub env_overrides: Vec<String>,
     /// Embedding model name for semantic search (e.g. "minilm", "codebert", or a HuggingFace ID).
     #[cfg(feature = "semantic")]
     pub semantic_model: Option<String>,
 }
 
+/// A directory outside the project root scanned as part of the repo. Its files' paths
+/// start with `prefix`, so `root.join(rel_path)` still locates them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExternalDir {
+    /// Path from the project root, e.g. `../shared-lib`.
+    pub prefix: String,
+    /// Canonical location.
+    pub path: PathBuf,
+}
+
+impl ExternalDir {
+    /// `rel_path` without this directory's prefix, if it lies inside it.
+    pub fn strip<'a>(&self, rel_path: &'a str) -> Option<&'a str> {
+        rel_path.strip_prefix(self.prefix.as_str())?.strip_prefix('/')
+    }
+
+    fn rel_path_of(&self, abs_path: &Path) -> Option<String> {
+        let rest = abs_path.strip_prefix(&self.path).ok()?.to_string_lossy().replace('\\', "/");
+        Some(if rest.is_empty() { self.prefix.clone() } else { format!("{}/{rest}", self.prefix) })
+    }
+}
+
 impl ScanConfig {
     pub fn new(root: PathBuf) -> Self {
         Self {
             root,
+            profile: None,
             scan_dirs: Vec::new(),
+            scan_dir_prefixes: Vec::new(),
+            external_dirs: Vec::new(),
             extensions: HashSet::new(),
+            extension_globs: None,
             skip_dirs: [
                 ".git",
                 "node_modules",
@@ -84,14 +245,77 @@ impl ScanConfig {
             .iter()
             .map(|s| s.to_string())
             .collect(),
+            skip_globs: None,
             noise_dirs: ["Private", "Public", "Internal", "Source", "Src", "Include", "src", "lib"]
                 .iter()
                 .map(|s| s.to_string())
                 .collect(),
+            auto_noise: false,
+            respect_gitignore: true,
+            follow_symlinks: false,
+            max_memory_mb: None,
+            max_grep_file_size: DEFAULT_MAX_GREP_FILE_SIZE,
+            max_result_tokens: None,
+            sort_order: Default::default(),
+            read_policy: crate::paths::ReadPolicy::default(),
+            languages: crate::lang::LanguageOverrides::default(),
+            redact: crate::redact::RedactConfig::default(),
+            git: crate::git::GitConfig::default(),
+            boundaries: crate::boundaries::BoundaryConfig::default(),
+            ranking: crate::ranking::RankingConfig::default(),
+            scopes: BTreeMap::new(),
+            prompts: BTreeMap::new(),
+            aliases: crate::aliases::AliasConfig::default(),
+            noise: crate::noise::NoiseConfig::default(),
+            workspace_members: Vec::new(),
+            codeowners: crate::owners::CodeOwners::default(),
+            env_overrides: Vec::new(),
             #[cfg(feature = "semantic")]
             semantic_model: None,
         }
     }
+
+    /// Path relative to the root of `abs_path`, which is under the root or one of the
+    /// external `scan_dirs`.
+    pub fn rel_path_of(&self, abs_path: &Path) -> Option<String> {
+        if let Ok(rel) = abs_path.strip_prefix(&self.root) {
+            return Some(rel.to_string_lossy().replace('\\', "/"));
+        }
+        self.external_dirs.iter().find_map(|d| d.rel_path_of(abs_path))
+    }
+
+    /// Whether `rel_path` lies under one of `scan_dirs` (always, when they are empty).
+    pub fn in_scan_dirs(&self, rel_path: &str) -> bool {
+        self.scan_dirs.is_empty()
+            || self.scan_dirs.iter().any(|d| d == "." || crate::entries::path_in(rel_path, d))
+    }
+
+    /// Whether the walk skips the directory at `rel_dir` (relative to root) by `skip_dirs`.
+    pub fn skips_dir(&self, rel_dir: &str) -> bool {
+        let name = rel_dir.rsplit('/').next().unwrap_or(rel_dir);
+        self.skip_dirs.contains(name)
+            || self.skip_globs.as_ref().is_some_and(|g| g.is_match(rel_dir))
+    }
+
+    /// Whether any directory containing `rel_path` is skipped by `skip_dirs`.
+    pub fn in_skipped_dir(&self, rel_path: &str) -> bool {
+        rel_path.match_indices('/').any(|(i, _)| self.skips_dir(&rel_path[..i]))
+    }
+
+    /// Whether `extensions` restricts which files are scanned.
+    pub fn has_extension_filter(&self) -> bool {
+        !self.extensions.is_empty() || self.extension_globs.is_some()
+    }
+
+    /// Whether a file name passes the `extensions` filter. True when there is none.
+    pub fn matches_extensions(&self, file_name: &str) -> bool {
+        if !self.has_extension_filter() {
+            return true;
+        }
+        let ext = Path::new(file_name).extension().and_then(|e| e.to_str()).unwrap_or("");
+        self.extensions.contains(ext)
+            || self.extension_globs.as_ref().is_some_and(|g| g.is_match(file_name))
+    }
 }
 
 impl Default for ScanConfig {
@@ -119,6 +343,8 @@ pub struct DepEntry {
     pub private: Vec<String>,
     #[serde(rename = "categoryPath")]
     pub category_path: String,
+    /// Manifest the entry was read from, relative to the repo root.
+    pub manifest: String,
 }
 
 /// Metadata for a file discovered during the directory scan.
@@ -128,6 +354,34 @@ pub struct ScannedFile {
     pub abs_path: PathBuf,
     pub desc: String,
     pub ext: String,
+    /// Detected language ([`crate::lang`]), `None` when unrecognized.
+    pub lang: Option<&'static str>,
+    /// Source encoding when not UTF-8 (e.g. `Shift_JIS`); reads transcode through
+    /// [`crate::encoding`].
+    pub encoding: Option<&'static str>,
+    /// Why content searches pass over this file: `"binary"` or `"minified"`. Such files stay
+    /// listed and readable; grep, content search, and semantic chunking skip them.
+    pub skip_content: Option<&'static str>,
+    /// `"generated"` or `"vendored"` ([`crate::scan::file_origin`]). `cs_search` and `cs_grep`
+    /// leave such files out unless called with `include_generated`.
+    pub origin: Option<&'static str>,
+    /// Owners from `CODEOWNERS` ([`crate::owners`]), shared by the files of one rule.
+    pub owners: Option<Arc<[String]>>,
+    /// xxh3 hash of the file's bytes when it was indexed ([`crate::scan::hash_file`]), so a
+    /// touched but unchanged file can be told apart from an edited one.
+    pub content_hash: u64,
+    /// Line count, read along with the hash ([`crate::scan::digest_file`]).
+    pub lines: u32,
+    /// Hash of the content without whitespace, matching reformatted copies
+    /// ([`crate::duplicates`]).
+    pub normalized_hash: u64,
+}
+
+impl ScannedFile {
+    /// Whether grep, content search, and semantic chunking should read this file.
+    pub fn is_searchable(&self) -> bool {
+        self.skip_content.is_none()
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -173,11 +427,106 @@ pub struct SearchModuleEntry {
 // ---------------------------------------------------------------------------
 
 /// Bidirectional import/include graph mapping files to their dependencies and dependents.
+#[derive(Clone)]
 pub struct ImportGraph {
     /// file -> files it imports (resolved to rel_paths)
     pub imports: BTreeMap<String, Vec<String>>,
     /// file -> files that import it
     pub imported_by: BTreeMap<String, Vec<String>>,
+    /// file -> imported file -> how it is imported
+    pub edges: BTreeMap<String, BTreeMap<String, ImportEdge>>,
+}
+
+/// How strongly one file depends on another it imports.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportEdge {
+    /// Named symbols imported (`{ a, b }`, `from x import a, b`, `use x::{A, B}`).
+    /// Zero when only whole modules are imported.
+    pub symbols: u32,
+    /// Import statements that resolve to the target.
+    pub statements: u32,
+    /// Every statement is type-only (`import type`, or only `type` specifiers).
+    pub type_only: bool,
+    /// How sure the scanner is that the dependency exists; the best of its statements.
+    #[serde(default)]
+    pub confidence: ImportConfidence,
+}
+
+impl ImportEdge {
+    /// One plain import statement found with `confidence`.
+    pub fn plain(confidence: ImportConfidence) -> Self {
+        Self { symbols: 0, statements: 1, type_only: false, confidence }
+    }
+
+    /// Named symbols, or statements for whole-module imports.
+    pub fn weight(&self) -> u32 {
+        self.symbols.max(self.statements).max(1)
+    }
+
+    /// Fold another statement importing the same target into this edge.
+    pub fn merge(&mut self, other: ImportEdge) {
+        self.type_only = (self.statements == 0 || self.type_only) && other.type_only;
+        self.confidence = if self.statements == 0 {
+            other.confidence
+        } else {
+            self.confidence.max(other.confidence)
+        };
+        self.symbols += other.symbols;
+        self.statements += other.statements;
+    }
+
+    /// Order for listing dependents: value imports before type-only, then heavier first.
+    pub fn strength_order(a: &ImportEdge, b: &ImportEdge) -> std::cmp::Ordering {
+        a.type_only.cmp(&b.type_only).then(b.weight().cmp(&a.weight()))
+    }
+
+    /// Short description such as `3 symbols, type-only`, or empty for a plain import.
+    pub fn label(&self) -> String {
+        let mut parts = Vec::new();
+        if self.symbols > 0 {
+            parts.push(format!(
+                "{} symbol{}",
+                self.symbols,
+                if self.symbols == 1 { "" } else { "s" }
+            ));
+        }
+        if self.type_only {
+            parts.push("type-only".to_string());
+        }
+        match self.confidence {
+            ImportConfidence::Low => parts.push("low confidence".to_string()),
+            ImportConfidence::Medium => parts.push("medium confidence".to_string()),
+            ImportConfidence::High => {}
+        }
+        parts.join(", ")
+    }
+}
+
+impl ImportGraph {
+    /// Build both adjacency maps from per-importer edges.
+    pub fn from_edges(edges: BTreeMap<String, BTreeMap<String, ImportEdge>>) -> Self {
+        let mut imports: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        let mut imported_by: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (file, targets) in &edges {
+            for dep in targets.keys() {
+                imported_by.entry(dep.clone()).or_default().push(file.clone());
+            }
+            imports.insert(file.clone(), targets.keys().cloned().collect());
+        }
+        for list in imported_by.values_mut() {
+            list.sort();
+        }
+        Self { imports, imported_by, edges }
+    }
+
+    /// Metadata for `from` importing `to`; a single plain import when unrecorded.
+    pub fn edge(&self, from: &str, to: &str) -> ImportEdge {
+        self.edges
+            .get(from)
+            .and_then(|targets| targets.get(to))
+            .copied()
+            .unwrap_or(ImportEdge::plain(ImportConfidence::High))
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -185,19 +534,25 @@ pub struct ImportGraph {
 // ---------------------------------------------------------------------------
 
 /// Cached stub data for a single file. Shared via Arc to avoid clones.
+#[derive(Clone)]
 pub struct CachedStub {
     pub raw: Arc<str>,
     pub tier1: Arc<str>,
     pub fast_tokens: usize,
 }
 
+/// Stubs by path and the content hash the index holds for the file
+/// ([`ScannedFile::content_hash`]): once an edit is indexed, entries for the old content
+/// no longer match, even where the watcher has not evicted them yet.
+pub type StubCache = DashMap<(String, u64), CachedStub>;
+
 // ---------------------------------------------------------------------------
 // Semantic search types (feature-gated)
 // ---------------------------------------------------------------------------
 
 #[cfg(feature = "semantic")]
 pub struct SemanticProgress {
-    pub status: std::sync::atomic::AtomicU8, // 0=idle, 1=extracting, 2=embedding, 3=ready, 4=failed
+    pub status: std::sync::atomic::AtomicU8, // 0=idle, 1=extracting, 2=embedding, 3=ready, 4=failed, 5=evicted
     pub total_chunks: std::sync::atomic::AtomicUsize,
     pub total_batches: std::sync::atomic::AtomicUsize,
     pub completed_batches: std::sync::atomic::AtomicUsize,
@@ -219,6 +574,9 @@ impl Default for SemanticProgress {
 
 #[cfg(feature = "semantic")]
 impl SemanticProgress {
+    /// Status value set when vectors were dropped to honor `max_memory_mb`.
+    pub const EVICTED: u8 = 5;
+
     pub fn new() -> Self {
         Self::default()
     }
@@ -230,6 +588,7 @@ impl SemanticProgress {
             2 => "embedding",
             3 => "ready",
             4 => "failed",
+            5 => "evicted",
             _ => "unknown",
         }
     }
@@ -261,39 +620,328 @@ pub struct ChunkMeta {
 // ---------------------------------------------------------------------------
 
 /// Per-term document frequency index for IDF-weighted search scoring.
-#[derive(Default)]
+///
+/// Each counted file keeps its distinct terms as interned ids, so files can be added,
+/// replaced, or removed (by the watcher and warm starts) without recounting the corpus.
+/// Terms whose count drops to zero keep their id until the next full scan.
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct TermDocFreq {
-    pub total_docs: usize,
-    pub freq: HashMap<String, usize>,
+    /// Term → id into `counts`.
+    ids: HashMap<String, u32>,
+    /// Number of files containing each term.
+    counts: Vec<usize>,
+    /// rel_path → ids of the file's distinct terms; empty for files too large to count.
+    docs: HashMap<String, Box<[u32]>>,
 }
 
 impl TermDocFreq {
     pub fn new() -> Self {
-        Self { total_docs: 0, freq: HashMap::new() }
+        Self::default()
+    }
+
+    pub fn total_docs(&self) -> usize {
+        self.docs.len()
+    }
+
+    /// Distinct terms ever counted, including ones no file contains any more.
+    pub fn term_count(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Terms at least one file contains, with their document counts.
+    pub fn terms(&self) -> impl Iterator<Item = (&str, usize)> + '_ {
+        self.ids
+            .iter()
+            .map(|(term, &id)| (term.as_str(), self.counts[id as usize]))
+            .filter(|&(_, df)| df > 0)
+    }
+
+    /// Number of files containing `term`.
+    pub fn doc_freq(&self, term: &str) -> usize {
+        self.ids.get(term).map_or(0, |&id| self.counts[id as usize])
+    }
+
+    /// Count a file's terms, replacing its previous terms if it was already counted.
+    pub fn add_doc(&mut self, path: &str, terms: impl IntoIterator<Item = String>) {
+        self.remove_doc(path);
+        let mut ids: Vec<u32> = terms
+            .into_iter()
+            .map(|term| {
+                let next = self.counts.len() as u32;
+                let id = *self.ids.entry(term).or_insert(next);
+                if id == next {
+                    self.counts.push(0);
+                }
+                id
+            })
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        for &id in &ids {
+            self.counts[id as usize] += 1;
+        }
+        self.docs.insert(path.to_string(), ids.into_boxed_slice());
+    }
+
+    /// Take back a file's counts. Returns whether it was counted.
+    pub fn remove_doc(&mut self, path: &str) -> bool {
+        let Some(ids) = self.docs.remove(path) else { return false };
+        for &id in ids.iter() {
+            self.counts[id as usize] -= 1;
+        }
+        true
+    }
+
+    /// Keep only files for which `keep` returns true.
+    pub fn retain_docs(&mut self, mut keep: impl FnMut(&str) -> bool) {
+        let dropped: Vec<String> = self.docs.keys().filter(|p| !keep(p)).cloned().collect();
+        for path in dropped {
+            self.remove_doc(&path);
+        }
+    }
+
+    /// Approximate heap bytes, for [`crate::memory`].
+    pub fn heap_bytes(&self) -> usize {
+        let ids: usize =
+            self.ids.keys().map(|k| std::mem::size_of::<(String, u32)>() + k.capacity()).sum();
+        let docs: usize = self
+            .docs
+            .iter()
+            .map(|(p, t)| std::mem::size_of::<(String, Box<[u32]>)>() + p.capacity() + t.len() * 4)
+            .sum();
+        ids + self.counts.capacity() * std::mem::size_of::<usize>() + docs
     }
 
     /// IDF with Laplace smoothing: ln((N+1)/(df+1)) + 1.
     /// Unknown terms default to df=total_docs (IDF ~1.0).
     pub fn idf(&self, term: &str) -> f64 {
-        let df = self.freq.get(term).copied().unwrap_or(self.total_docs);
-        (((self.total_docs as f64 + 1.0) / (df as f64 + 1.0)).ln() + 1.0).max(1.0)
+        let total_docs = self.total_docs();
+        let df = match self.doc_freq(term) {
+            0 => total_docs,
+            df => df,
+        };
+        (((total_docs as f64 + 1.0) / (df as f64 + 1.0)).ln() + 1.0).max(1.0)
+    }
+}
+
+/// One timed stage of a repo scan, with offsets relative to the start of the scan.
+#[derive(Clone, Debug, Serialize)]
+pub struct ScanStage {
+    pub name: &'static str,
+    #[serde(rename = "startUs")]
+    pub start_us: u64,
+    #[serde(rename = "durationUs")]
+    pub duration_us: u64,
+}
+
+/// Wall-clock time spent in each stage of a repo scan (walk, index, imports, tdf, deps, ...),
+/// in the order the stages ran.
+#[derive(Clone, Debug, Serialize)]
+pub struct ScanProfile {
+    #[serde(skip)]
+    origin: Instant,
+    pub stages: Vec<ScanStage>,
+}
+
+impl Default for ScanProfile {
+    fn default() -> Self {
+        Self { origin: Instant::now(), stages: Vec::new() }
+    }
+}
+
+impl ScanProfile {
+    /// Start a profile; stage offsets are measured from now.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `f` as the stage `name` and record how long it took.
+    pub fn stage<T>(&mut self, name: &'static str, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let out = f();
+        self.stages.push(ScanStage {
+            name,
+            start_us: start.duration_since(self.origin).as_micros() as u64,
+            duration_us: start.elapsed().as_micros() as u64,
+        });
+        out
+    }
+
+    /// [`stage`](Self::stage), also announcing the stage to `progress`.
+    pub fn tracked_stage<T>(
+        &mut self,
+        progress: &ScanProgress,
+        name: &'static str,
+        f: impl FnOnce() -> T,
+    ) -> T {
+        progress.start_phase(name);
+        self.stage(name, f)
+    }
+
+    /// Time from the start of the profile to the end of the last stage.
+    pub fn total_us(&self) -> u64 {
+        self.stages.iter().map(|s| s.start_us + s.duration_us).max().unwrap_or(0)
+    }
+
+    /// `walk 120ms, imports 340ms, ...` for status output.
+    pub fn summary(&self) -> String {
+        let parts: Vec<String> =
+            self.stages.iter().map(|s| format!("{} {}ms", s.name, s.duration_us / 1000)).collect();
+        parts.join(", ")
+    }
+}
+
+/// What a running scan reports to a [`ScanProgress`] callback.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScanEvent {
+    /// A stage started (`walk`, `imports`, ... as named in [`ScanProfile`]).
+    Phase(&'static str),
+    /// Files found by the directory walk so far.
+    FilesWalked(usize),
+    /// Files whose imports have been parsed, out of all indexed files.
+    ImportsResolved { done: usize, total: usize },
+    /// The scan finished.
+    Done,
+}
+
+/// Live progress of one repo scan, readable from other threads while it runs.
+///
+/// The counters are always updated. A callback, if set, runs on the scanning threads at
+/// each phase change and every [`ScanProgress::STEP`] files; forward events to a channel
+/// when the receiver must not block the scan.
+#[derive(Default)]
+pub struct ScanProgress {
+    phase: std::sync::RwLock<&'static str>,
+    files_walked: std::sync::atomic::AtomicUsize,
+    files_total: std::sync::atomic::AtomicUsize,
+    imports_resolved: std::sync::atomic::AtomicUsize,
+    callback: Option<Box<dyn Fn(ScanEvent) + Send + Sync>>,
+}
+
+impl ScanProgress {
+    /// Files between two counter events.
+    pub const STEP: usize = 1000;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Progress that also calls `callback` with each [`ScanEvent`].
+    pub fn with_callback(callback: impl Fn(ScanEvent) + Send + Sync + 'static) -> Self {
+        Self { callback: Some(Box::new(callback)), ..Self::default() }
+    }
+
+    fn emit(&self, event: ScanEvent) {
+        if let Some(callback) = &self.callback {
+            callback(event);
+        }
+    }
+
+    pub fn start_phase(&self, name: &'static str) {
+        *self.phase.write().unwrap() = name;
+        self.emit(ScanEvent::Phase(name));
+    }
+
+    /// The running stage, `done` once finished, or empty before the scan starts.
+    pub fn phase(&self) -> &'static str {
+        *self.phase.read().unwrap()
+    }
+
+    pub fn file_walked(&self) {
+        let n = self.files_walked.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1;
+        if n.is_multiple_of(Self::STEP) {
+            self.emit(ScanEvent::FilesWalked(n));
+        }
+    }
+
+    /// Record how many files the walk kept, once it is done.
+    pub fn set_files_total(&self, total: usize) {
+        self.files_total.store(total, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    pub fn import_resolved(&self) {
+        let done = self.imports_resolved.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1;
+        let total = self.files_total();
+        if done.is_multiple_of(Self::STEP) || done == total {
+            self.emit(ScanEvent::ImportsResolved { done, total });
+        }
+    }
+
+    pub fn finish(&self) {
+        *self.phase.write().unwrap() = "done";
+        self.emit(ScanEvent::Done);
+    }
+
+    pub fn files_walked(&self) -> usize {
+        self.files_walked.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    pub fn files_total(&self) -> usize {
+        self.files_total.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    pub fn imports_resolved(&self) -> usize {
+        self.imports_resolved.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// `imports, 3000 of 12345 files` for status output; `None` before the scan starts.
+    pub fn summary(&self) -> Option<String> {
+        match self.phase() {
+            "" => None,
+            "walk" => Some(format!("walk, {} files found", self.files_walked())),
+            "imports" => Some(format!(
+                "imports, {} of {} files",
+                self.imports_resolved(),
+                self.files_total()
+            )),
+            phase => Some(format!("{phase}, {} files", self.files_total())),
+        }
     }
 }
 
 /// Complete indexed state for a single repository, including files, deps, search index, and caches.
+///
+/// Held behind an `Arc` in [`ServerState`] and treated as immutable once published;
+/// incremental updates copy it first if a snapshot still references it.
+#[derive(Clone)]
 pub struct RepoState {
     pub name: String,
     pub root: PathBuf,
     pub config: ScanConfig,
     pub all_files: Vec<ScannedFile>,
     pub manifest: BTreeMap<String, Vec<FileEntry>>,
+    /// README and docs-directory files per module (manifest category), READMEs first.
+    pub module_docs: BTreeMap<String, Vec<String>>,
+    /// Detected program entry points, sorted by path and line.
+    pub entry_points: Vec<crate::entries::EntryPoint>,
+    /// Functions, methods, and types defined per file, sorted by path and line.
+    pub symbols: Vec<crate::symbols::SymbolEntry>,
+    /// Environment variable and config key references, sorted by name, path, and line.
+    pub config_refs: Vec<crate::config_refs::ConfigRef>,
+    /// License files per directory and license headers per file.
+    pub licenses: crate::licenses::LicenseIndex,
+    /// Proto, OpenAPI, and GraphQL definitions and the code referencing them.
+    pub schemas: crate::schemas::SchemaIndex,
+    /// Last commit time (Unix seconds) per file, for `[ranking] recency`. Empty when off.
+    pub commit_times: std::collections::HashMap<String, i64>,
     pub deps: BTreeMap<String, DepEntry>,
     pub search_files: Vec<SearchFileEntry>,
     pub search_modules: Vec<SearchModuleEntry>,
     pub import_graph: ImportGraph,
-    pub stub_cache: DashMap<String, CachedStub>,
+    pub stub_cache: StubCache,
     pub term_doc_freq: TermDocFreq,
+    /// Approximate bytes of the scanned index, stub cache and semantic vectors aside;
+    /// measured by [`crate::memory::measure_index`] when a scan or the watcher changes it.
+    pub index_memory: crate::memory::MemoryUsage,
     pub scan_time_ms: u64,
+    pub scan_profile: ScanProfile,
+    /// When the last full or incremental rescan started (Unix seconds): files modified
+    /// since may be out of date. Used by `cs_rescan incremental=true`.
+    pub synced_at: i64,
+    /// Git HEAD commit at `synced_at`; `None` outside a git repository.
+    pub synced_head: Option<String>,
+    /// Paths of files moved since the first scan, mapped to where they are now.
+    pub renames: crate::renames::RenameMap,
     #[cfg(feature = "semantic")]
     pub semantic_index: std::sync::Arc<std::sync::RwLock<Option<SemanticIndex>>>,
     #[cfg(feature = "semantic")]
@@ -305,6 +953,7 @@ pub struct RepoState {
 // ---------------------------------------------------------------------------
 
 /// An import edge that crosses repository boundaries (file in repo A imports file in repo B).
+#[derive(Clone)]
 pub struct CrossRepoEdge {
     pub from_repo: String,
     pub from_file: String,
@@ -317,11 +966,28 @@ pub struct CrossRepoEdge {
 // ---------------------------------------------------------------------------
 
 /// Unified server state holding all indexed repos, shared by both MCP and HTTP modes.
+///
+/// One value is one index generation. Requests read it through a [`SharedState`]
+/// snapshot; it is never mutated while a snapshot of it is alive.
+#[derive(Clone)]
 pub struct ServerState {
-    pub repos: BTreeMap<String, RepoState>,
+    pub repos: BTreeMap<String, Arc<RepoState>>,
+    /// Registered repos whose initial scan is still queued or running.
+    pub pending: BTreeMap<String, Arc<crate::indexing::PendingRepo>>,
+    /// Every repo registered at startup, in scan order.
+    pub registered: Vec<crate::indexing::RepoSpec>,
     pub default_repo: Option<String>,
+    /// Scope applied to `cs_search`/`cs_grep` calls that name none (`--scope`).
+    pub default_scope: Option<String>,
+    /// Index generation, bumped by every [`SharedState::update`]. Reported in responses
+    /// so results can be tied to the index they were computed from.
+    pub generation: u64,
     pub cross_repo_edges: Vec<CrossRepoEdge>,
     pub tokenizer: Arc<dyn crate::tokenizer::Tokenizer>,
+    /// File watcher activity, shared by every generation.
+    pub watch: Arc<crate::watch::WatchStats>,
+    /// Sampled request log (`--access-log`), shared by every generation.
+    pub access_log: Option<Arc<crate::access_log::AccessLog>>,
     #[cfg(feature = "semantic")]
     pub semantic_enabled: bool,
     #[cfg(feature = "semantic")]
@@ -342,6 +1008,39 @@ impl ServerState {
     }
 }
 
+/// Handle to the current [`ServerState`] generation, shared by all transports.
+///
+/// Each request takes one [`snapshot`](Self::snapshot) and works against it, so a rescan
+/// or watcher update that lands mid-request cannot mix old and new indexes in one
+/// response. Writers publish a new generation through [`update`](Self::update); the
+/// state (and each repo in it) is copied only if a live snapshot still shares it.
+pub struct SharedState {
+    current: std::sync::RwLock<Arc<ServerState>>,
+}
+
+impl SharedState {
+    pub fn new(state: ServerState) -> Self {
+        Self { current: std::sync::RwLock::new(Arc::new(state)) }
+    }
+
+    /// The current generation. The lock is held only long enough to clone the `Arc`.
+    pub fn snapshot(&self) -> Arc<ServerState> {
+        self.current.read().expect("state lock poisoned").clone()
+    }
+
+    /// Apply `f` to the state and publish the result as the next generation.
+    ///
+    /// Writers are serialized, so keep slow work (scanning) outside `f` and only swap
+    /// the results in here.
+    pub fn update<R>(&self, f: impl FnOnce(&mut ServerState) -> R) -> R {
+        let mut current = self.current.write().expect("state lock poisoned");
+        let state = Arc::make_mut(&mut current);
+        let out = f(state);
+        state.generation += 1;
+        out
+    }
+}
+
 // ---------------------------------------------------------------------------
 // MCP transport types (session management, OAuth config)
 // ---------------------------------------------------------------------------
@@ -382,7 +1081,7 @@ pub type SessionStore = DashMap<String, McpSession>;
 /// Axum state for MCP HTTP transport routes.
 #[derive(Clone)]
 pub struct McpAppContext {
-    pub state: Arc<std::sync::RwLock<ServerState>>,
+    pub state: Arc<SharedState>,
     pub sessions: Arc<SessionStore>,
     pub config: Arc<McpConfig>,
 }
@@ -401,7 +1100,7 @@ pub struct HttpCache {
 /// Axum application state combining the shared server state with the HTTP JSON cache.
 #[derive(Clone)]
 pub struct AppContext {
-    pub state: Arc<std::sync::RwLock<ServerState>>,
+    pub state: Arc<SharedState>,
     pub cache: Arc<HttpCache>,
     /// Server start time for uptime reporting via `/health`.
     pub start_time: std::time::Instant,
@@ -477,6 +1176,295 @@ pub fn grep_relevance_score(
     base * (0.3 + 0.7 * coverage_factor)
 }
 
+/// Compiled-program size cap for grep patterns. Patterns with huge repetition counts
+/// (`(\w{1000}){1000}`) fail to compile instead of exhausting memory.
+pub const GREP_REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+/// Lazy DFA cache per grep pattern; when it fills up, matching falls back to a slower engine.
+pub const GREP_DFA_SIZE_LIMIT: usize = 2 << 20;
+
+/// Default wall-clock budget of one grep call (`timeout_ms`).
+pub const GREP_DEFAULT_TIMEOUT_MS: u64 = 10_000;
+
+/// Upper bound on `timeout_ms`.
+pub const GREP_MAX_TIMEOUT_MS: u64 = 60_000;
+
+/// Default number of files one grep call scans (`max_files`); later candidates are skipped.
+pub const GREP_DEFAULT_MAX_FILES: usize = 50_000;
+
+/// Case-insensitive grep regex with the size limits above.
+pub fn grep_regex(pattern: &str) -> Result<regex::Regex, regex::Error> {
+    regex::RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .size_limit(GREP_REGEX_SIZE_LIMIT)
+        .dfa_size_limit(GREP_DFA_SIZE_LIMIT)
+        .build()
+}
+
+/// Wall-clock deadline of one grep call, shared by the files it scans in parallel.
+pub struct GrepDeadline {
+    at: Instant,
+    expired: std::sync::atomic::AtomicBool,
+}
+
+impl GrepDeadline {
+    pub fn after(timeout: std::time::Duration) -> Self {
+        Self { at: Instant::now() + timeout, expired: Default::default() }
+    }
+
+    /// Whether the deadline has passed. Once it has, files not yet started are skipped and
+    /// files being scanned stop matching, so the call returns what it found so far.
+    pub fn expired(&self) -> bool {
+        use std::sync::atomic::Ordering::Relaxed;
+        if self.expired.load(Relaxed) {
+            return true;
+        }
+        let expired = Instant::now() >= self.at;
+        if expired {
+            self.expired.store(true, Relaxed);
+        }
+        expired
+    }
+
+    /// Whether any check hit the deadline, i.e. the results are partial.
+    pub fn timed_out(&self) -> bool {
+        self.expired.load(std::sync::atomic::Ordering::Relaxed)
+    }
+}
+
+/// How grep hits are aggregated for `group_by`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GrepGroupBy {
+    /// Module category path (`crates > parser`).
+    Module,
+    /// Parent directory.
+    Dir,
+}
+
+impl GrepGroupBy {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "module" => Ok(Self::Module),
+            "dir" => Ok(Self::Dir),
+            other => Err(format!("Unknown group_by '{other}'. Expected 'module' or 'dir'")),
+        }
+    }
+
+    /// Plural noun for result headers.
+    pub fn noun(self) -> &'static str {
+        match self {
+            Self::Module => "modules",
+            Self::Dir => "directories",
+        }
+    }
+
+    /// Group key of a repo-relative path.
+    pub fn key(self, rel_path: &str, config: &ScanConfig) -> String {
+        match self {
+            Self::Module => crate::scan::get_category_path(rel_path, config).join(" > "),
+            Self::Dir => match rel_path.rsplit_once('/') {
+                Some((dir, _)) => dir.to_string(),
+                None => ".".to_string(),
+            },
+        }
+    }
+}
+
+/// Context shown around matches in `cs_grep` and `cs_search` snippets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SnippetContext {
+    /// Raw lines before and after each match.
+    Lines(usize),
+    /// The signature line of the enclosing function, method, or type.
+    Symbol,
+}
+
+impl SnippetContext {
+    /// The `context` argument: a line count (capped at 10) or `"symbol"`. `default_lines`
+    /// applies when it is absent.
+    pub fn parse(arg: &serde_json::Value, default_lines: usize) -> Result<Self, String> {
+        match arg {
+            serde_json::Value::Null => Ok(Self::Lines(default_lines)),
+            serde_json::Value::String(s) if s == "symbol" => Ok(Self::Symbol),
+            v => v.as_u64().map(|n| Self::Lines(n.min(10) as usize)).ok_or_else(|| {
+                format!("Invalid context {v}. Expected a line count (0-10) or 'symbol'")
+            }),
+        }
+    }
+}
+
+/// Grep hits aggregated under one module or directory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrepGroup {
+    pub key: String,
+    pub files: usize,
+    pub matches: usize,
+    /// Index of the group's best-scoring hit in the input.
+    pub top: usize,
+}
+
+/// Aggregate `(group key, score, match count)` hits into groups ordered by match count,
+/// then key. Each group's `top` is its highest-scoring hit. Shared by HTTP API and MCP grep.
+pub fn group_grep_hits<'a>(
+    hits: impl IntoIterator<Item = (&'a str, f64, usize)>,
+) -> Vec<GrepGroup> {
+    let mut groups: Vec<GrepGroup> = Vec::new();
+    let mut best: Vec<f64> = Vec::new();
+    let mut index: HashMap<&str, usize> = HashMap::new();
+    for (i, (key, score, matches)) in hits.into_iter().enumerate() {
+        let g = *index.entry(key).or_insert_with(|| {
+            groups.push(GrepGroup { key: key.to_string(), files: 0, matches: 0, top: i });
+            best.push(f64::NEG_INFINITY);
+            groups.len() - 1
+        });
+        groups[g].files += 1;
+        groups[g].matches += matches;
+        if score > best[g] {
+            best[g] = score;
+            groups[g].top = i;
+        }
+    }
+    groups.sort_by(|a, b| b.matches.cmp(&a.matches).then_with(|| a.key.cmp(&b.key)));
+    groups
+}
+
+/// Grep output lines for `context: "symbol"`: each run of matches inside the same function
+/// or type is shown under that symbol's signature (`L12|`), and runs are separated by `---`.
+pub fn symbol_context_lines<S: AsRef<str>>(
+    lines: &[S],
+    matches: &[usize],
+    truncate: impl Fn(&str) -> String,
+) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut shown: Option<usize> = None;
+    let mut current: Option<Option<usize>> = None;
+    for &idx in matches {
+        let symbol = crate::stubs::enclosing_symbol(lines, idx);
+        // A match on the signature line itself already introduces its symbol
+        let continues = current == Some(symbol) || (symbol.is_some() && symbol == shown);
+        if !continues {
+            if current.is_some() {
+                out.push("  ---".to_string());
+            }
+            if let Some(s) = symbol {
+                out.push(format!("  L{}| {}", s + 1, truncate(lines[s].as_ref())));
+            }
+        }
+        current = Some(symbol);
+        out.push(format!("  L{}: {}", idx + 1, truncate(lines[idx].as_ref())));
+        shown = Some(idx);
+    }
+    out
+}
+
+/// Rank offset for directory scores, as in reciprocal rank fusion.
+const DIR_RANK_K: f64 = 60.0;
+
+/// Ranked files a directory needs under it to be listed.
+const MIN_DIR_HITS: usize = 2;
+
+/// Score of the file at `rank` in a ranked result list, the unit directory scores add up.
+pub fn rank_score(rank: usize) -> f64 {
+    1.0 / (DIR_RANK_K + rank as f64)
+}
+
+/// A directory listed among `cs_search` results.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirHit {
+    /// Display path without trailing slash, `[repo] `-prefixed like its files.
+    pub path: String,
+    pub score: f64,
+    /// Ranked files under the directory.
+    pub matching: usize,
+    /// Indexed files under the directory.
+    pub files: usize,
+}
+
+/// Directories that contain several of the `ranked` result paths, best first.
+///
+/// A directory's score is the sum of [`rank_score`] over the results under it, scaled by
+/// the square root of the share of its files that matched, so a folder that is mostly
+/// hits outranks one with a few hits among hundreds. An ancestor holding no more hits than
+/// one of its listed subdirectories is dropped. `dir_sizes` returns the indexed file count
+/// of each candidate directory.
+pub fn rank_directories(
+    ranked: &[&str],
+    dir_sizes: impl FnOnce(&[String]) -> Vec<usize>,
+    limit: usize,
+) -> Vec<DirHit> {
+    let mut dirs: BTreeMap<String, (f64, usize)> = BTreeMap::new();
+    for (rank, path) in ranked.iter().enumerate() {
+        let (_, rel) = crate::paths::split_repo_prefix(path);
+        let prefix = &path[..path.len() - rel.len()];
+        for (i, _) in rel.match_indices('/') {
+            let entry = dirs.entry(format!("{prefix}{}", &rel[..i])).or_default();
+            entry.0 += rank_score(rank);
+            entry.1 += 1;
+        }
+    }
+    dirs.retain(|_, (_, hits)| *hits >= MIN_DIR_HITS);
+    let candidates: Vec<String> = dirs
+        .iter()
+        .filter(|(dir, (_, hits))| {
+            let child_prefix = format!("{dir}/");
+            !dirs.iter().any(|(d, (_, h))| d.starts_with(&child_prefix) && h == hits)
+        })
+        .map(|(dir, _)| dir.clone())
+        .collect();
+
+    let sizes = dir_sizes(&candidates);
+    let mut hits: Vec<DirHit> = candidates
+        .into_iter()
+        .zip(sizes)
+        .map(|(path, files)| {
+            let (sum, matching) = dirs[&path];
+            let coverage = matching as f64 / files.max(matching) as f64;
+            DirHit { path, score: sum * coverage.sqrt(), matching, files: files.max(matching) }
+        })
+        .collect();
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.path.cmp(&b.path)));
+    hits.truncate(limit);
+    hits
+}
+
+/// One occurrence of a query term inside a result snippet, for highlighting.
+///
+/// Byte offsets index the UTF-8 snippet; char offsets count Unicode scalar values, which
+/// is what JavaScript string slicing needs for non-ASCII snippets.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchRange {
+    pub term: String,
+    pub byte_start: usize,
+    pub byte_end: usize,
+    pub char_start: usize,
+    pub char_end: usize,
+}
+
+/// Case-insensitive occurrences of each term in `snippet`, sorted by position.
+/// Shared by HTTP API and MCP search handlers.
+pub fn snippet_match_ranges(snippet: &str, terms: &[String]) -> Vec<MatchRange> {
+    let mut ranges = Vec::new();
+    for term in terms.iter().filter(|t| !t.is_empty()) {
+        let Ok(re) = regex::RegexBuilder::new(&regex::escape(term)).case_insensitive(true).build()
+        else {
+            continue;
+        };
+        for m in re.find_iter(snippet) {
+            let char_start = snippet[..m.start()].chars().count();
+            ranges.push(MatchRange {
+                term: term.clone(),
+                byte_start: m.start(),
+                byte_end: m.end(),
+                char_start,
+                char_end: char_start + m.as_str().chars().count(),
+            });
+        }
+    }
+    ranges.sort_by_key(|r| (r.byte_start, r.byte_end));
+    ranges
+}
+
 // ---------------------------------------------------------------------------
 // Path validation
 // ---------------------------------------------------------------------------
@@ -500,6 +1488,91 @@ mod tests {
     use super::*;
     use std::path::Path;
 
+    #[test]
+    fn directories_rank_by_child_scores_and_coverage() {
+        let ranked = [
+            "services/payments/webhooks/stripe.rs",
+            "services/payments/webhooks/paypal.rs",
+            "docs/webhooks.md",
+            "services/payments/webhooks/mod.rs",
+            "src/big/a.rs",
+            "src/big/b.rs",
+        ];
+        let sizes = |dirs: &[String]| {
+            dirs.iter().map(|d| if d == "src/big" { 400 } else { 4 }).collect::<Vec<_>>()
+        };
+        let hits = rank_directories(&ranked, sizes, 5);
+        let paths: Vec<&str> = hits.iter().map(|h| h.path.as_str()).collect();
+        // `services` and `services/payments` hold the same three hits as `webhooks`
+        assert_eq!(paths, vec!["services/payments/webhooks", "src/big"]);
+        assert_eq!((hits[0].matching, hits[0].files), (3, 4));
+        assert!(hits[0].score > rank_score(0), "a mostly-matching folder tops its files");
+        assert!(hits[1].score < rank_score(ranked.len()), "sparse hits rank below every file");
+
+        let multi = ["[api] src/a.rs", "[api] src/b.rs", "[web] src/c.rs"];
+        let hits = rank_directories(&multi, |d| vec![2; d.len()], 5);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].path, "[api] src");
+    }
+
+    #[test]
+    fn snippet_match_ranges_report_byte_and_char_offsets() {
+        let ranges = snippet_match_ranges(
+            "// café Parser: parse(input)",
+            &["parse".to_string(), "café".to_string()],
+        );
+        let spans: Vec<(&str, usize, usize, usize, usize)> = ranges
+            .iter()
+            .map(|r| (r.term.as_str(), r.byte_start, r.byte_end, r.char_start, r.char_end))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![("café", 3, 8, 3, 7), ("parse", 9, 14, 8, 13), ("parse", 17, 22, 16, 21)]
+        );
+    }
+
+    #[test]
+    fn grep_hits_group_by_key_with_best_hit_on_top() {
+        let groups = group_grep_hits([
+            ("src/scan", 9.0, 2),
+            ("src/mcp", 7.0, 5),
+            ("src/scan", 12.0, 4),
+            (".", 1.0, 1),
+        ]);
+        let summary: Vec<(&str, usize, usize, usize)> =
+            groups.iter().map(|g| (g.key.as_str(), g.files, g.matches, g.top)).collect();
+        assert_eq!(summary, vec![("src/scan", 2, 6, 2), ("src/mcp", 1, 5, 1), (".", 1, 1, 3)]);
+
+        let config = ScanConfig::new(PathBuf::from("/tmp"));
+        assert_eq!(GrepGroupBy::Dir.key("src/scan.rs", &config), "src");
+        assert_eq!(GrepGroupBy::Dir.key("README.md", &config), ".");
+        assert!(GrepGroupBy::parse("file").is_err());
+    }
+
+    #[test]
+    fn term_doc_freq_updates_match_a_fresh_count() {
+        let terms = |s: &str| s.split(' ').map(str::to_string).collect::<Vec<_>>();
+        let mut tdf = TermDocFreq::new();
+        tdf.add_doc("a.rs", terms("parse token parse"));
+        tdf.add_doc("b.rs", terms("parse render"));
+        tdf.add_doc("c.rs", terms("render"));
+        assert_eq!((tdf.total_docs(), tdf.doc_freq("parse"), tdf.doc_freq("render")), (3, 2, 2));
+
+        // Edit b.rs, delete c.rs
+        tdf.add_doc("b.rs", terms("token"));
+        assert!(tdf.remove_doc("c.rs"));
+        assert!(!tdf.remove_doc("c.rs"));
+
+        let mut fresh = TermDocFreq::new();
+        fresh.add_doc("a.rs", terms("parse token"));
+        fresh.add_doc("b.rs", terms("token"));
+        for term in ["parse", "token", "render", "missing"] {
+            assert_eq!(tdf.doc_freq(term), fresh.doc_freq(term), "{term}");
+            assert_eq!(tdf.idf(term), fresh.idf(term), "{term}");
+        }
+        assert_eq!(tdf.idf("render"), 1.0, "a term no file contains counts as unknown");
+    }
+
     #[test]
     fn validate_path_rejects_traversal() {
         let root = Path::new("/tmp");
@@ -524,18 +1597,6 @@ mod tests {
         assert_eq!(result.unwrap_err(), "Invalid path");
     }
 
-    #[test]
-    fn validate_path_accepts_valid_relative() {
-        // Use a path that actually exists on the filesystem
-        let root = Path::new("/tmp");
-        // Create a temp file so canonicalize succeeds
-        let test_file = root.join("codescope_test_validate.txt");
-        std::fs::write(&test_file, "test").ok();
-        let result = validate_path(root, "codescope_test_validate.txt");
-        assert!(result.is_ok(), "valid relative path should succeed: {:?}", result);
-        std::fs::remove_file(&test_file).ok();
-    }
-
     #[test]
     fn grep_relevance_score_more_matches_higher() {
         let terms = vec!["foo".to_string()];
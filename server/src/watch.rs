@@ -2,25 +2,71 @@
 //!
 //! Watches all indexed repo roots for file changes and incrementally updates
 //! the search index, manifest, and import graph without requiring a full rescan.
+//! `cs_rescan incremental=true` reuses the same update for the files git status and
+//! modification times point at, for changes the watcher missed.
 
+use crate::config_refs::{detect_file_config_refs, sort_config_refs, ConfigRefDetector};
+use crate::entries::{detect_file_entry_points, EntryDetector};
+use crate::licenses::{detect_file_license, is_license_file, scan_license_dirs, sort_headers};
 use crate::scan::{
-    build_search_index, process_single_file, remove_manifest_entry, update_import_edges_for_file,
-    update_manifest_entry,
+    build_search_index, collect_module_docs, file_terms, is_ignored, process_single_file,
+    remove_manifest_entry, update_import_edges_for_file, update_manifest_entry,
 };
-use crate::types::ServerState;
+use crate::schemas::{
+    detect_file_refs, detect_file_symbols, is_schema_candidate, scan_schemas, sort_refs, RefMatcher,
+};
+use crate::types::{ScanConfig, SharedState};
 use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicUsize, Ordering::Relaxed};
 use std::sync::mpsc;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 /// Debounce window: wait this long after the last event before processing.
 const DEBOUNCE_MS: u64 = 500;
 
-/// Start a file watcher on all indexed repo roots. Returns the watcher handle
-/// (must be kept alive — dropping it stops the watcher).
-pub fn start_watcher(state: Arc<RwLock<ServerState>>) -> Option<RecommendedWatcher> {
+/// Running totals of the watcher's work, reported by `cs_status` and `/health`.
+#[derive(Debug, Default)]
+pub struct WatchStats {
+    active: AtomicBool,
+    batches: AtomicUsize,
+    updated: AtomicUsize,
+    removed: AtomicUsize,
+    unchanged: AtomicUsize,
+    /// Unix seconds of the last processed batch, 0 before the first.
+    last_batch: AtomicI64,
+    /// The running watcher, kept here so [`rewatch`] can add roots that come back.
+    handle: Mutex<Option<RecommendedWatcher>>,
+}
+
+impl WatchStats {
+    fn record(&self, updated: usize, removed: usize, unchanged: usize) {
+        self.batches.fetch_add(1, Relaxed);
+        self.updated.fetch_add(updated, Relaxed);
+        self.removed.fetch_add(removed, Relaxed);
+        self.unchanged.fetch_add(unchanged, Relaxed);
+        self.last_batch.store(crate::ranking::now_secs(), Relaxed);
+    }
+
+    pub fn status(&self) -> crate::status::WatchStatus {
+        let last = self.last_batch.load(Relaxed);
+        crate::status::WatchStatus {
+            active: self.active.load(Relaxed),
+            batches: self.batches.load(Relaxed),
+            updated: self.updated.load(Relaxed),
+            removed: self.removed.load(Relaxed),
+            unchanged: self.unchanged.load(Relaxed),
+            last_change_secs_ago: (last > 0)
+                .then(|| (crate::ranking::now_secs() - last).max(0) as u64),
+        }
+    }
+}
+
+/// Start a file watcher on all indexed repo roots. The watcher lives in the state's
+/// [`WatchStats`] for the rest of the process. Returns false if it could not be created.
+pub fn start_watcher(state: Arc<SharedState>) -> bool {
     let (tx, rx) = mpsc::channel::<Event>();
 
     let mut watcher = match RecommendedWatcher::new(
@@ -34,33 +80,58 @@ pub fn start_watcher(state: Arc<RwLock<ServerState>>) -> Option<RecommendedWatch
         Ok(w) => w,
         Err(e) => {
             tracing::warn!(error = %e, "Failed to create file watcher");
-            return None;
+            return false;
         }
     };
 
-    // Watch all repo roots
+    // Watch all repo roots, including repos still waiting for their initial scan, and the
+    // scan_dirs outside them
     {
-        let s = state.read().unwrap();
-        for repo in s.repos.values() {
-            if let Err(e) = watcher.watch(&repo.root, RecursiveMode::Recursive) {
-                tracing::warn!(root = %repo.root.display(), error = %e, "Failed to watch directory");
+        let s = state.snapshot();
+        let external =
+            s.repos.values().flat_map(|r| r.config.external_dirs.iter().map(|d| &d.path));
+        let roots = s.repos.values().map(|r| &r.root).chain(s.pending.values().map(|p| &p.root));
+        let roots = roots.chain(external);
+        for root in roots {
+            if let Err(e) = watcher.watch(root, RecursiveMode::Recursive) {
+                tracing::warn!(root = %root.display(), error = %e, "Failed to watch directory");
             } else {
-                tracing::debug!(root = %repo.root.display(), "Watching for changes");
+                tracing::debug!(root = %root.display(), "Watching for changes");
             }
         }
     }
 
+    let stats = Arc::clone(&state.snapshot().watch);
+    *stats.handle.lock().unwrap_or_else(|e| e.into_inner()) = Some(watcher);
+    stats.active.store(true, Relaxed);
+
     // Spawn debounce processor thread
     let state_clone = Arc::clone(&state);
     std::thread::spawn(move || {
         debounce_loop(rx, state_clone);
     });
 
-    Some(watcher)
+    true
+}
+
+/// Watch `root` again after it reappears. A root that was missing at startup, or deleted
+/// since, has no live watch. Does nothing before [`start_watcher`] has run.
+pub fn rewatch(state: &crate::types::ServerState, root: &Path) {
+    let mut handle = state.watch.handle.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(watcher) = handle.as_mut() else {
+        return;
+    };
+    // Drop any stale watch left from before the root went away
+    let _ = watcher.unwatch(root);
+    if let Err(e) = watcher.watch(root, RecursiveMode::Recursive) {
+        tracing::warn!(root = %root.display(), error = %e, "Failed to watch directory");
+    } else {
+        tracing::debug!(root = %root.display(), "Watching for changes");
+    }
 }
 
 /// Collect file events and process them after a debounce period of quiet.
-fn debounce_loop(rx: mpsc::Receiver<Event>, state: Arc<RwLock<ServerState>>) {
+fn debounce_loop(rx: mpsc::Receiver<Event>, state: Arc<SharedState>) {
     let mut pending: HashMap<PathBuf, Instant> = HashMap::new();
 
     loop {
@@ -105,121 +176,326 @@ fn debounce_loop(rx: mpsc::Receiver<Event>, state: Arc<RwLock<ServerState>>) {
 }
 
 /// Process a batch of changed file paths, updating indexes incrementally.
-fn process_changes(paths: &[PathBuf], state: &Arc<RwLock<ServerState>>) {
-    // Read state to determine which repo owns each path and gather configs
-    let s = state.read().unwrap();
-
-    // Group paths by repo
-    let mut repo_changes: HashMap<String, Vec<PathBuf>> = HashMap::new();
+///
+/// The updated repos are published as one new index generation; requests already
+/// holding a snapshot keep the previous one.
+fn process_changes(paths: &[PathBuf], state: &SharedState) {
+    // Group relevant paths by repo against the current generation
+    let s = state.snapshot();
+    let mut repo_changes: HashMap<String, Vec<(PathBuf, String)>> = HashMap::new();
     for path in paths {
-        for repo in s.repos.values() {
-            if path.starts_with(&repo.root) {
-                repo_changes.entry(repo.name.clone()).or_default().push(path.clone());
-                break;
-            }
+        let Some((repo, rel_path)) =
+            s.repos.values().find_map(|r| Some((r, r.config.rel_path_of(path)?)))
+        else {
+            continue;
+        };
+        if tracks(&repo.config, path, &rel_path) {
+            repo_changes.entry(repo.name.clone()).or_default().push((path.clone(), rel_path));
         }
     }
-    drop(s); // release read lock
+    drop(s);
 
     if repo_changes.is_empty() {
         return;
     }
 
-    // Process each repo's changes
-    let mut state_w = state.write().unwrap();
+    // Re-index each repo's files on a copy, outside the write lock, then swap the copies
+    // in. A repo replaced meanwhile (by a rescan) is re-indexed again from the new one.
+    let mut totals = (0, 0, 0);
+    while !repo_changes.is_empty() {
+        let s = state.snapshot();
+        let updated: Vec<_> = repo_changes
+            .iter()
+            .filter_map(|(repo_name, changed_paths)| {
+                let base = s.repos.get(repo_name)?.clone();
+                let mut repo = (*base).clone();
+                let counts = apply_changes(&mut repo, changed_paths);
+                Some((base, repo, counts))
+            })
+            .collect();
+        let swapped = state.update(|state_w| {
+            let mut swapped = Vec::new();
+            for (base, repo, counts) in updated {
+                let Some(current) = state_w.repos.get_mut(&repo.name) else { continue };
+                if Arc::ptr_eq(current, &base) {
+                    swapped.push((repo.name.clone(), repo.all_files.len(), counts));
+                    *current = Arc::new(repo);
+                }
+            }
+            swapped
+        });
+        for (repo_name, total, (changed_count, removed_count, unchanged_count)) in &swapped {
+            totals =
+                (totals.0 + changed_count, totals.1 + removed_count, totals.2 + unchanged_count);
+            if *changed_count > 0 || *removed_count > 0 {
+                tracing::info!(
+                    repo = repo_name.as_str(),
+                    updated = changed_count,
+                    removed = removed_count,
+                    unchanged = unchanged_count,
+                    total,
+                    "File watcher re-indexed"
+                );
+            }
+            repo_changes.remove(repo_name);
+        }
+        // Repos unregistered meanwhile have nothing left to update
+        repo_changes.retain(|name, _| s.repos.contains_key(name));
+    }
+    let s = state.snapshot();
+    s.watch.record(totals.0, totals.1, totals.2);
+    if totals.0 > 0 || totals.1 > 0 {
+        crate::memory::enforce_memory_limits(&s);
+    }
+}
 
-    for (repo_name, changed_paths) in &repo_changes {
-        let repo = match state_w.repos.get_mut(repo_name) {
-            Some(r) => r,
-            None => continue,
-        };
+/// Whether a changed path belongs in the repo's index: a file under `scan_dirs`, outside
+/// `skip_dirs`, and not excluded by ignore files. Deleted files count, so their entries
+/// are dropped.
+fn tracks(config: &ScanConfig, path: &Path, rel_path: &str) -> bool {
+    config.in_scan_dirs(rel_path)
+        && !config.in_skipped_dir(rel_path)
+        && !is_ignored(config, rel_path)
+        && !path.is_dir()
+}
+
+/// Re-index `changed_paths` (absolute and repo-relative) in `repo`: files that exist are
+/// re-processed unless their content is unchanged, the others removed. Returns the
+/// (updated, removed, unchanged) counts.
+fn apply_changes(
+    repo: &mut crate::types::RepoState,
+    changed_paths: &[(PathBuf, String)],
+) -> (usize, usize, usize) {
+    let entry_detector = EntryDetector::new();
+    let config_ref_detector = ConfigRefDetector::new();
+    let mut changed_count = 0usize;
+    let mut removed_count = 0usize;
+    let mut unchanged_count = 0usize;
+    // Paths and content hashes of removed and newly indexed files, to detect moves
+    let mut removed: Vec<(String, u64)> = Vec::new();
+    let mut added: Vec<(String, u64)> = Vec::new();
+    let licenses_changed =
+        changed_paths.iter().any(|(_, rel)| is_license_file(rel.rsplit('/').next().unwrap_or(rel)));
+    if licenses_changed {
+        repo.licenses.dirs = scan_license_dirs(&repo.config, &repo.all_files);
+    }
+    let mut schemas_changed = false;
+    let mut schema_matcher = None;
 
-        let mut changed_count = 0usize;
-        let mut removed_count = 0usize;
+    for (abs_path, rel_path) in changed_paths {
+        let rel_path = rel_path.as_str();
+        if abs_path.exists() {
+            // File created or modified
+            match process_single_file(&repo.config, abs_path, rel_path) {
+                Some(scanned) => {
+                    let existing = repo.all_files.iter().position(|f| f.rel_path == rel_path);
+                    // A touch or save without edits leaves every index as it is
+                    if existing
+                        .is_some_and(|pos| repo.all_files[pos].content_hash == scanned.content_hash)
+                    {
+                        unchanged_count += 1;
+                        continue;
+                    }
 
-        for abs_path in changed_paths {
-            let rel_path = match abs_path.strip_prefix(&repo.root) {
-                Ok(r) => r.to_string_lossy().replace('\\', "/"),
-                Err(_) => continue,
-            };
+                    // Update all_files, dropping the stub cached for the previous content
+                    if let Some(pos) = existing {
+                        let old_hash = repo.all_files[pos].content_hash;
+                        repo.stub_cache.remove(&(rel_path.to_string(), old_hash));
+                        repo.all_files[pos] = scanned.clone();
+                    } else {
+                        repo.all_files.push(scanned.clone());
+                        added.push((rel_path.to_string(), scanned.content_hash));
+                    }
 
-            // Skip files in skip_dirs
-            let parts: Vec<&str> = rel_path.split('/').collect();
-            if parts.iter().any(|p| repo.config.skip_dirs.contains(*p)) {
-                continue;
-            }
+                    // Update manifest
+                    update_manifest_entry(&mut repo.manifest, &scanned, &repo.config);
 
-            // Skip directories and non-existent paths (for remove events)
-            if abs_path.is_dir() {
-                continue;
-            }
+                    // Update import graph
+                    update_import_edges_for_file(&mut repo.import_graph, &scanned, &repo.all_files);
 
-            if abs_path.exists() {
-                // File created or modified
-                match process_single_file(&repo.config, abs_path, &rel_path) {
-                    Some(scanned) => {
-                        // Update all_files
-                        if let Some(pos) =
-                            repo.all_files.iter().position(|f| f.rel_path == rel_path)
-                        {
-                            repo.all_files[pos] = scanned.clone();
-                        } else {
-                            repo.all_files.push(scanned.clone());
-                        }
+                    // Update entry points
+                    repo.entry_points.retain(|e| e.path != rel_path);
+                    repo.entry_points.extend(detect_file_entry_points(&entry_detector, &scanned));
+                    repo.entry_points.sort_by(|a, b| a.path.cmp(&b.path).then(a.line.cmp(&b.line)));
+
+                    // Update symbols
+                    repo.symbols.retain(|s| s.path != rel_path);
+                    let symbols = crate::symbols::detect_file_symbols(&scanned, &repo.config);
+                    repo.symbols.extend(symbols);
+                    crate::symbols::sort_symbols(&mut repo.symbols);
 
-                        // Update manifest
-                        update_manifest_entry(&mut repo.manifest, &scanned, &repo.config);
+                    // Update config references
+                    repo.config_refs.retain(|r| r.path != rel_path);
+                    repo.config_refs
+                        .extend(detect_file_config_refs(&config_ref_detector, &scanned));
+                    sort_config_refs(&mut repo.config_refs);
 
-                        // Invalidate stub cache
-                        repo.stub_cache.remove(&rel_path);
+                    // Update license header
+                    repo.licenses.headers.retain(|h| h.path != rel_path);
+                    repo.licenses.headers.extend(detect_file_license(&scanned));
+                    sort_headers(&mut repo.licenses.headers);
 
-                        // Update import graph
-                        update_import_edges_for_file(
-                            &mut repo.import_graph,
-                            &scanned,
-                            &repo.all_files,
-                        );
+                    // Recount the file's terms for IDF weights
+                    repo.term_doc_freq.add_doc(rel_path, file_terms(&scanned));
+
+                    // A watched edit is the newest change to the file
+                    if repo.config.ranking.recency > 0.0 {
+                        repo.commit_times.insert(rel_path.to_string(), crate::ranking::now_secs());
+                    }
 
-                        changed_count += 1;
+                    // Update schema references; an edited schema rebuilds the index
+                    if is_schema_candidate(&scanned.ext)
+                        && (repo.schemas.symbols.iter().any(|s| s.path == rel_path)
+                            || !detect_file_symbols(&scanned).is_empty())
+                    {
+                        schemas_changed = true;
+                    } else if !schemas_changed {
+                        let matcher = schema_matcher
+                            .get_or_insert_with(|| RefMatcher::new(&repo.schemas.symbols));
+                        repo.schemas.refs.retain(|r| r.path != rel_path);
+                        if let Some(matcher) = matcher {
+                            repo.schemas.refs.extend(detect_file_refs(matcher, &scanned));
+                            sort_refs(&mut repo.schemas.refs);
+                        }
                     }
-                    None => {
-                        // File doesn't match filters — treat as removal if it was indexed
-                        remove_file_from_repo(repo, &rel_path);
+
+                    changed_count += 1;
+                }
+                None => {
+                    // File doesn't match filters — treat as removal if it was indexed
+                    if let Some(hash) = remove_file_from_repo(repo, rel_path) {
+                        removed.push((rel_path.to_string(), hash));
                         removed_count += 1;
                     }
                 }
-            } else {
-                // File deleted
-                remove_file_from_repo(repo, &rel_path);
+            }
+        } else {
+            // File deleted
+            if let Some(hash) = remove_file_from_repo(repo, rel_path) {
+                removed.push((rel_path.to_string(), hash));
                 removed_count += 1;
             }
         }
+    }
+
+    if schemas_changed {
+        repo.schemas = scan_schemas(&repo.all_files);
+    }
+    repo.renames.record(&removed, &added);
 
-        if changed_count > 0 || removed_count > 0 {
-            // Rebuild search index (fast — just bitmask computation)
-            let (search_files, search_modules) = build_search_index(&repo.manifest);
-            repo.search_files = search_files;
-            repo.search_modules = search_modules;
-
-            tracing::info!(
-                repo = repo_name.as_str(),
-                updated = changed_count,
-                removed = removed_count,
-                total = repo.all_files.len(),
-                "File watcher re-indexed"
-            );
+    if changed_count > 0 || removed_count > 0 {
+        // Rebuild search index (fast — just bitmask computation)
+        let (search_files, search_modules) = build_search_index(&repo.manifest);
+        repo.search_files = search_files;
+        repo.search_modules = search_modules;
+        repo.module_docs = collect_module_docs(&repo.manifest);
+        crate::memory::measure_index(repo);
+    }
+    (changed_count, removed_count, unchanged_count)
+}
+
+// ---------------------------------------------------------------------------
+// Incremental rescan (`cs_rescan incremental=true`)
+// ---------------------------------------------------------------------------
+
+/// File names whose change needs a full rescan: they shape the config, dependencies,
+/// workspace members, code owners, or import resolution of the whole repo.
+const FULL_RESCAN_FILES: &[&str] = &[
+    ".codescope.toml",
+    "CODEOWNERS",
+    "Cargo.toml",
+    "package.json",
+    "pnpm-workspace.yaml",
+    "go.mod",
+    "go.work",
+    "tsconfig.json",
+];
+
+/// Result of [`rescan_changed`].
+pub enum Incremental {
+    /// The repo with its changed files re-indexed, and the (updated, removed, unchanged)
+    /// counts.
+    Updated(Box<crate::types::RepoState>, (usize, usize, usize)),
+    /// The changes need a full rescan, for this reason.
+    Full(String),
+}
+
+/// Bring `repo` up to date by re-indexing only the files that may have changed since its
+/// last sync: what `git status` reports (untracked files included), files changed between
+/// the synced HEAD and the current one, and indexed files modified since the sync or gone.
+/// Repos outside git, and changes to the files in [`FULL_RESCAN_FILES`], need a full scan.
+pub fn rescan_changed(repo: &crate::types::RepoState) -> Incremental {
+    let synced_at = crate::ranking::now_secs();
+    if repo.synced_head.is_none() {
+        return Incremental::Full("not a git repository".to_string());
+    }
+    let head = crate::git::head_commit(&repo.root);
+    let git_paths = match crate::git::changed_paths(&repo.root, repo.synced_head.as_deref()) {
+        Ok(paths) => paths,
+        Err(e) => return Incremental::Full(e),
+    };
+
+    let modified_since_sync = |path: &Path| {
+        let modified = path.metadata().and_then(|m| m.modified()).ok();
+        let secs = modified.and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok());
+        secs.is_none_or(|d| d.as_secs() as i64 >= repo.synced_at)
+    };
+    let stale = repo.all_files.iter().map(|f| &f.abs_path).filter(|p| modified_since_sync(p));
+    let config_file = repo.root.join(".codescope.toml");
+    let config_file =
+        (config_file.exists() && modified_since_sync(&config_file)).then_some(config_file);
+
+    // Keyed by relative path, so a file found both ways is processed once
+    let mut candidates: BTreeMap<String, PathBuf> = BTreeMap::new();
+    for path in git_paths.into_iter().chain(stale.cloned()).chain(config_file) {
+        if let Some(rel_path) = repo.config.rel_path_of(&path) {
+            candidates.insert(rel_path, path);
         }
     }
+    let full = candidates
+        .keys()
+        .find(|rel| FULL_RESCAN_FILES.contains(&rel.rsplit('/').next().unwrap_or(rel)));
+    if let Some(rel_path) = full {
+        return Incremental::Full(format!("{rel_path} changed"));
+    }
+
+    let changes: Vec<(PathBuf, String)> = candidates
+        .into_iter()
+        .filter(|(rel_path, path)| tracks(&repo.config, path, rel_path))
+        .map(|(rel_path, path)| (path, rel_path))
+        .collect();
+    let mut updated = repo.clone();
+    let counts = apply_changes(&mut updated, &changes);
+    updated.synced_at = synced_at;
+    updated.synced_head = head;
+    Incremental::Updated(Box::new(updated), counts)
 }
 
-/// Remove a file from all repo indexes.
-fn remove_file_from_repo(repo: &mut crate::types::RepoState, rel_path: &str) {
+/// Remove a file from all repo indexes. Returns its content hash, or `None` if it was not
+/// indexed.
+fn remove_file_from_repo(repo: &mut crate::types::RepoState, rel_path: &str) -> Option<u64> {
+    let hash = repo.all_files.iter().find(|f| f.rel_path == rel_path)?.content_hash;
     repo.all_files.retain(|f| f.rel_path != rel_path);
     remove_manifest_entry(&mut repo.manifest, rel_path);
-    repo.stub_cache.remove(rel_path);
+    repo.stub_cache.remove(&(rel_path.to_string(), hash));
+    repo.entry_points.retain(|e| e.path != rel_path);
+    repo.symbols.retain(|s| s.path != rel_path);
+    repo.commit_times.remove(rel_path);
+    repo.term_doc_freq.remove_doc(rel_path);
+    repo.config_refs.retain(|r| r.path != rel_path);
+    repo.licenses.headers.retain(|h| h.path != rel_path);
+    let schemas = &mut repo.schemas;
+    schemas.refs.retain(|r| r.path != rel_path);
+    if schemas.symbols.iter().any(|s| s.path == rel_path) {
+        schemas.symbols.retain(|s| s.path != rel_path);
+        let defined: HashSet<&str> = schemas.symbols.iter().map(|s| s.name.as_str()).collect();
+        schemas.refs.retain(|r| defined.contains(r.symbol.as_str()));
+    }
     repo.import_graph.imports.remove(rel_path);
+    repo.import_graph.edges.remove(rel_path);
     for targets in repo.import_graph.imported_by.values_mut() {
         targets.retain(|t| t != rel_path);
     }
     repo.import_graph.imported_by.remove(rel_path);
+    Some(hash)
 }
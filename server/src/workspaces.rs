@@ -0,0 +1,234 @@
+//! Workspace member detection for monorepos.
+//!
+//! Members come from the workspace files at the repo root: `[workspace] members` (minus
+//! `exclude`) in `Cargo.toml`, `workspaces` in `package.json` (array or `{ packages }`),
+//! `packages` in `pnpm-workspace.yaml` (with `!pattern` exclusions), and `use` directives
+//! in `go.work`. Glob patterns are expanded against the directories on disk. Each member
+//! is named by its own manifest (`[package] name`, `"name"`, or the last component of the
+//! `go.mod` module path), falling back to its directory.
+//!
+//! The scan makes every member a top-level module: files under `crates/parser/src/lexer/`
+//! are categorized `parser > lexer` rather than by raw directory nesting, and the member's
+//! dependency entry gets the same category.
+
+use globset::{GlobBuilder, GlobSetBuilder};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// Directories never searched for members.
+const SKIP: &[&str] = &["node_modules", "target", "dist", "build", "vendor"];
+
+/// Deepest directory a `**` member pattern is expanded to.
+const MAX_GLOB_DEPTH: usize = 6;
+
+/// A package of a Cargo, npm/yarn/pnpm, or Go workspace.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WorkspaceMember {
+    /// Directory relative to the repo root.
+    pub dir: String,
+    /// Package name from the member's manifest.
+    pub name: String,
+    /// `cargo`, `npm`, `pnpm`, or `go`.
+    pub kind: &'static str,
+}
+
+/// Members of every workspace declared at `root`, sorted by directory.
+pub fn detect(root: &Path) -> Vec<WorkspaceMember> {
+    let mut members = Vec::new();
+    if let Some((include, exclude)) = cargo_patterns(root) {
+        add_members(root, "cargo", &include, &exclude, &mut members);
+    }
+    if let Some((include, exclude)) = pnpm_patterns(root) {
+        add_members(root, "pnpm", &include, &exclude, &mut members);
+    } else if let Some(include) = npm_patterns(root) {
+        add_members(root, "npm", &include, &[], &mut members);
+    }
+    if let Some(include) = go_work_dirs(root) {
+        add_members(root, "go", &include, &[], &mut members);
+    }
+    members.sort_by(|a, b| a.dir.cmp(&b.dir));
+    members.dedup_by(|a, b| a.dir == b.dir);
+    // Members sharing a package name would merge into one module; name them by directory
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for m in &members {
+        *counts.entry(m.name.clone()).or_default() += 1;
+    }
+    for m in &mut members {
+        if counts[&m.name] > 1 {
+            m.name = m.dir.clone();
+        }
+    }
+    members
+}
+
+/// The member containing `rel_path` (a file or directory), the innermost when nested.
+pub fn member_of<'a>(
+    members: &'a [WorkspaceMember],
+    rel_path: &str,
+) -> Option<&'a WorkspaceMember> {
+    members
+        .iter()
+        .filter(|m| {
+            rel_path == m.dir
+                || rel_path.strip_prefix(m.dir.as_str()).is_some_and(|rest| rest.starts_with('/'))
+        })
+        .max_by_key(|m| m.dir.len())
+}
+
+fn cargo_patterns(root: &Path) -> Option<(Vec<String>, Vec<String>)> {
+    let table: toml::Table = std::fs::read_to_string(root.join("Cargo.toml")).ok()?.parse().ok()?;
+    let workspace = table.get("workspace")?.as_table()?;
+    let list = |key: &str| -> Vec<String> {
+        workspace
+            .get(key)
+            .and_then(|v| v.as_array())
+            .map(|a| a.iter().filter_map(|v| v.as_str().map(String::from)).collect())
+            .unwrap_or_default()
+    };
+    Some((list("members"), list("exclude")))
+}
+
+fn npm_patterns(root: &Path) -> Option<Vec<String>> {
+    let content = std::fs::read_to_string(root.join("package.json")).ok()?;
+    let data: serde_json::Value = serde_json::from_str(&content).ok()?;
+    let workspaces = data.get("workspaces")?;
+    let list = workspaces.as_array().or_else(|| workspaces.get("packages")?.as_array())?;
+    Some(list.iter().filter_map(|v| v.as_str().map(String::from)).collect())
+}
+
+fn pnpm_patterns(root: &Path) -> Option<(Vec<String>, Vec<String>)> {
+    let content = std::fs::read_to_string(root.join("pnpm-workspace.yaml")).ok()?;
+    let (mut include, mut exclude) = (Vec::new(), Vec::new());
+    let mut in_packages = false;
+    for line in content.lines() {
+        if !line.starts_with([' ', '\t', '-']) {
+            in_packages = line.trim_end() == "packages:";
+            continue;
+        }
+        let Some(item) = line.trim().strip_prefix('-').filter(|_| in_packages) else { continue };
+        let item = item.trim().trim_matches(['\'', '"']);
+        match item.strip_prefix('!') {
+            Some(negated) => exclude.push(negated.to_string()),
+            None => include.push(item.to_string()),
+        }
+    }
+    Some((include, exclude))
+}
+
+fn go_work_dirs(root: &Path) -> Option<Vec<String>> {
+    let content = std::fs::read_to_string(root.join("go.work")).ok()?;
+    let mut dirs = Vec::new();
+    let mut in_use_block = false;
+    for line in content.lines() {
+        let line = line.split("//").next().unwrap_or("").trim();
+        if line == "use (" {
+            in_use_block = true;
+        } else if in_use_block && line == ")" {
+            in_use_block = false;
+        } else if let Some(dir) = line.strip_prefix("use ").or(in_use_block.then_some(line)) {
+            if !dir.is_empty() {
+                dirs.push(dir.trim().to_string());
+            }
+        }
+    }
+    Some(dirs)
+}
+
+/// Expand member patterns into directories and name each one.
+fn add_members(
+    root: &Path,
+    kind: &'static str,
+    include: &[String],
+    exclude: &[String],
+    out: &mut Vec<WorkspaceMember>,
+) {
+    let clean = |p: &str| p.trim().trim_start_matches("./").trim_end_matches('/').to_string();
+    let exclude: BTreeSet<String> =
+        expand(root, &exclude.iter().map(|p| clean(p)).collect::<Vec<_>>());
+    let include: Vec<String> = include.iter().map(|p| clean(p)).collect();
+    for dir in expand(root, &include) {
+        if dir.is_empty() || dir == "." || exclude.contains(&dir) {
+            continue;
+        }
+        if let Some(name) = member_name(&root.join(&dir), kind) {
+            out.push(WorkspaceMember { dir, name, kind });
+        }
+    }
+}
+
+/// Directories matching literal or glob patterns.
+fn expand(root: &Path, patterns: &[String]) -> BTreeSet<String> {
+    let mut dirs = BTreeSet::new();
+    let mut globs = GlobSetBuilder::new();
+    let mut max_depth = 0;
+    for pattern in patterns.iter().filter(|p| !p.is_empty()) {
+        if !crate::scan::is_glob(pattern) {
+            if root.join(pattern).is_dir() {
+                dirs.insert(pattern.clone());
+            }
+            continue;
+        }
+        if let Ok(glob) = GlobBuilder::new(pattern).literal_separator(true).build() {
+            globs.add(glob);
+            let depth =
+                if pattern.contains("**") { MAX_GLOB_DEPTH } else { pattern.split('/').count() };
+            max_depth = max_depth.max(depth);
+        }
+    }
+    if let Ok(globs) = globs.build() {
+        if max_depth > 0 {
+            collect_dirs(root, "", &globs, max_depth, &mut dirs);
+        }
+    }
+    dirs
+}
+
+fn collect_dirs(
+    root: &Path,
+    rel_dir: &str,
+    globs: &globset::GlobSet,
+    depth_left: usize,
+    out: &mut BTreeSet<String>,
+) {
+    if depth_left == 0 {
+        return;
+    }
+    let Ok(entries) = std::fs::read_dir(root.join(rel_dir)) else { return };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !entry.file_type().is_ok_and(|t| t.is_dir())
+            || name.starts_with('.')
+            || SKIP.contains(&name.as_str())
+        {
+            continue;
+        }
+        let rel = if rel_dir.is_empty() { name } else { format!("{rel_dir}/{name}") };
+        if globs.is_match(&rel) {
+            out.insert(rel.clone());
+        }
+        collect_dirs(root, &rel, globs, depth_left - 1, out);
+    }
+}
+
+/// Package name from a member's manifest; `None` when it has no manifest of its kind.
+fn member_name(dir: &Path, kind: &str) -> Option<String> {
+    let dir_name = || dir.file_name().map(|n| n.to_string_lossy().into_owned());
+    match kind {
+        "cargo" => {
+            let table: toml::Table =
+                std::fs::read_to_string(dir.join("Cargo.toml")).ok()?.parse().ok()?;
+            let name = table.get("package").and_then(|p| p.get("name")).and_then(|n| n.as_str());
+            name.map(String::from).or_else(dir_name)
+        }
+        "go" => {
+            let content = std::fs::read_to_string(dir.join("go.mod")).ok()?;
+            let module = content.lines().find_map(|l| l.trim().strip_prefix("module "));
+            module.and_then(|m| m.trim().rsplit('/').next()).map(String::from).or_else(dir_name)
+        }
+        _ => {
+            let content = std::fs::read_to_string(dir.join("package.json")).ok()?;
+            let data: serde_json::Value = serde_json::from_str(&content).ok()?;
+            data.get("name").and_then(|n| n.as_str()).map(String::from).or_else(dir_name)
+        }
+    }
+}
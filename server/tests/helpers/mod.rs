@@ -0,0 +1,338 @@
+//! Programmatic fixture builder for integration tests.
+//!
+//! Builds a throwaway repository on disk from code — files, import edges, symbols, and
+//! commit history — so tests for new tools don't need checked-in fixture trees. Content,
+//! author, and commit timestamps are fixed, so every build of the same fixture is identical.
+//!
+//! ```ignore
+//! mod helpers;
+//! use helpers::FixtureBuilder;
+//!
+//! let fx = FixtureBuilder::new()
+//!     .module("src/util.rs", &[], &["parse_config"])
+//!     .module("src/main.rs", &["src/util.rs"], &["main"])
+//!     .commit("initial import")
+//!     .build();
+//! let state = fx.state();
+//! let (out, is_error) = fx.call(&state, "cs_grep", serde_json::json!({"query": "parse_config"}));
+//! ```
+
+// Each integration test binary compiles this module separately and uses a subset of it.
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use codescope_server::types::{RepoState, ServerState, SessionState};
+use git2::{Repository, Signature, Time};
+
+/// Fixed epoch for the first fixture commit (2024-01-01T00:00:00Z).
+const BASE_COMMIT_TIME: i64 = 1_704_067_200;
+
+/// Spacing between consecutive fixture commits (one day).
+const COMMIT_INTERVAL_SECS: i64 = 86_400;
+
+static FIXTURE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+enum Step {
+    Write { path: String, content: String },
+    Remove { path: String },
+    Commit { message: String, author: String },
+    Branch { name: String },
+}
+
+/// Declarative builder for an on-disk test repository.
+///
+/// Steps are applied in order: files written before a [`commit`](Self::commit) are part of
+/// that commit, so history is built by interleaving file and commit calls.
+pub struct FixtureBuilder {
+    steps: Vec<Step>,
+}
+
+impl FixtureBuilder {
+    pub fn new() -> Self {
+        Self { steps: Vec::new() }
+    }
+
+    /// Write a file with literal content.
+    pub fn file(mut self, path: &str, content: &str) -> Self {
+        self.steps.push(Step::Write { path: path.to_string(), content: content.to_string() });
+        self
+    }
+
+    /// Delete a previously written file (use before a commit to record the removal).
+    pub fn remove(mut self, path: &str) -> Self {
+        self.steps.push(Step::Remove { path: path.to_string() });
+        self
+    }
+
+    /// Write a source file that imports `imports` (paths of other fixture files) and defines
+    /// one function per entry in `symbols`, using syntax for the file's extension.
+    pub fn module(self, path: &str, imports: &[&str], symbols: &[&str]) -> Self {
+        let content = module_source(path, imports, symbols);
+        self.file(path, &content)
+    }
+
+    /// Write `n` generated modules `{dir}/gen_{i}.{ext}`, each defining `gen_symbol_{i}`
+    /// and importing its predecessor, forming a chain.
+    pub fn generated(mut self, n: usize, dir: &str, ext: &str) -> Self {
+        for i in 0..n {
+            let path = format!("{dir}/gen_{i}.{ext}");
+            let prev = format!("{dir}/gen_{}.{ext}", i.wrapping_sub(1));
+            let imports: Vec<&str> = if i > 0 { vec![prev.as_str()] } else { Vec::new() };
+            let symbol = format!("gen_symbol_{i}");
+            self = self.module(&path, &imports, &[symbol.as_str()]);
+        }
+        self
+    }
+
+    /// Write a `.codescope.toml` at the fixture root.
+    pub fn config(self, toml: &str) -> Self {
+        self.file(".codescope.toml", toml)
+    }
+
+    /// Commit everything written so far as the default fixture author.
+    pub fn commit(self, message: &str) -> Self {
+        self.commit_as("Fixture Author", message)
+    }
+
+    /// Commit everything written so far with a specific author name.
+    pub fn commit_as(mut self, author: &str, message: &str) -> Self {
+        self.steps.push(Step::Commit { message: message.to_string(), author: author.to_string() });
+        self
+    }
+
+    /// Create branch `name` at the current commit and make it HEAD, so later commits land
+    /// on it. Files on disk are left as they are.
+    pub fn branch(mut self, name: &str) -> Self {
+        self.steps.push(Step::Branch { name: name.to_string() });
+        self
+    }
+
+    /// Materialize the fixture in a fresh temporary directory.
+    ///
+    /// # Panics
+    /// Panics on any I/O or git failure — fixtures are test-only.
+    pub fn build(self) -> Fixture {
+        let n = FIXTURE_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let root =
+            std::env::temp_dir().join(format!("codescope-fixture-{}-{n}", std::process::id()));
+        if root.exists() {
+            std::fs::remove_dir_all(&root).unwrap();
+        }
+        std::fs::create_dir_all(&root).unwrap();
+
+        let mut repo: Option<Repository> = None;
+        let mut commit_index = 0i64;
+        for step in self.steps {
+            match step {
+                Step::Write { path, content } => {
+                    let full = root.join(&path);
+                    if let Some(parent) = full.parent() {
+                        std::fs::create_dir_all(parent).unwrap();
+                    }
+                    std::fs::write(full, content).unwrap();
+                }
+                Step::Remove { path } => {
+                    std::fs::remove_file(root.join(path)).unwrap();
+                }
+                Step::Commit { message, author } => {
+                    let repo = repo.get_or_insert_with(|| Repository::init(&root).unwrap());
+                    let time = BASE_COMMIT_TIME + commit_index * COMMIT_INTERVAL_SECS;
+                    commit_all(repo, &author, &message, time);
+                    commit_index += 1;
+                }
+                Step::Branch { name } => {
+                    let repo = repo.as_ref().expect("branch() needs a commit first");
+                    let head = repo.head().unwrap().peel_to_commit().unwrap();
+                    repo.branch(&name, &head, false).unwrap();
+                    repo.set_head(&format!("refs/heads/{name}")).unwrap();
+                }
+            }
+        }
+
+        let root = root.canonicalize().unwrap();
+        Fixture { root }
+    }
+}
+
+impl Default for FixtureBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A materialized fixture repository. The directory is removed on drop.
+pub struct Fixture {
+    pub root: PathBuf,
+}
+
+impl Fixture {
+    /// Absolute path of a file inside the fixture.
+    pub fn path(&self, rel: &str) -> PathBuf {
+        self.root.join(rel)
+    }
+
+    /// Scan the fixture into a [`RepoState`] named `name`.
+    pub fn scan(&self, name: &str) -> RepoState {
+        let tok = codescope_server::tokenizer::create_tokenizer("bytes-estimate");
+        codescope_server::scan_repo(name, &self.root, &tok)
+    }
+
+    /// Scan the fixture into a single-repo [`ServerState`] (repo name `fixture`).
+    pub fn state(&self) -> ServerState {
+        let repo = self.scan("fixture");
+        let mut repos = std::collections::BTreeMap::new();
+        repos.insert(repo.name.clone(), std::sync::Arc::new(repo));
+        let cross_repo_edges = codescope_server::scan::resolve_cross_repo_imports(&repos);
+        ServerState {
+            repos,
+            pending: std::collections::BTreeMap::new(),
+            registered: Vec::new(),
+            default_repo: Some("fixture".to_string()),
+            default_scope: None,
+            generation: 0,
+            cross_repo_edges,
+            tokenizer: codescope_server::tokenizer::create_tokenizer("bytes-estimate"),
+            watch: Default::default(),
+            access_log: None,
+            #[cfg(feature = "semantic")]
+            semantic_enabled: false,
+            #[cfg(feature = "semantic")]
+            semantic_model: None,
+        }
+    }
+
+    /// Invoke an MCP tool against `state` with a fresh session. Returns `(text, is_error)`.
+    pub fn call(&self, state: &ServerState, tool: &str, args: serde_json::Value) -> (String, bool) {
+        let mut session = Some(SessionState::new());
+        codescope_server::mcp::handle_tool_call(state, tool, &args, &mut session)
+    }
+}
+
+/// Scan several fixtures into one multi-repo [`ServerState`] with no default repo, as
+/// `--repo a=... --repo b=...` does.
+pub fn multi_state(fixtures: &[(&str, &Fixture)]) -> ServerState {
+    let mut repos = std::collections::BTreeMap::new();
+    for (name, fx) in fixtures {
+        repos.insert(name.to_string(), std::sync::Arc::new(fx.scan(name)));
+    }
+    let cross_repo_edges = codescope_server::scan::resolve_cross_repo_imports(&repos);
+    ServerState {
+        repos,
+        pending: std::collections::BTreeMap::new(),
+        registered: Vec::new(),
+        default_repo: None,
+        default_scope: None,
+        generation: 0,
+        cross_repo_edges,
+        tokenizer: codescope_server::tokenizer::create_tokenizer("bytes-estimate"),
+        watch: Default::default(),
+        access_log: None,
+        #[cfg(feature = "semantic")]
+        semantic_enabled: false,
+        #[cfg(feature = "semantic")]
+        semantic_model: None,
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.root);
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Source generation
+// ---------------------------------------------------------------------------
+
+fn stem(path: &str) -> &str {
+    let filename = path.rsplit('/').next().unwrap_or(path);
+    filename.rsplit_once('.').map(|(s, _)| s).unwrap_or(filename)
+}
+
+/// Render a source file in the syntax implied by `path`'s extension.
+fn module_source(path: &str, imports: &[&str], symbols: &[&str]) -> String {
+    let ext = path.rsplit_once('.').map(|(_, e)| e).unwrap_or("");
+    let mut out = String::new();
+    match ext {
+        "rs" => {
+            for imp in imports {
+                out.push_str(&format!("use crate::{};\n", stem(imp)));
+            }
+            out.push('\n');
+            for sym in symbols {
+                out.push_str(&format!("pub fn {sym}() -> usize {{\n    {}\n}}\n\n", sym.len()));
+            }
+        }
+        "py" => {
+            for imp in imports {
+                out.push_str(&format!("from {} import *\n", stem(imp)));
+            }
+            out.push('\n');
+            for sym in symbols {
+                out.push_str(&format!("def {sym}():\n    return {}\n\n", sym.len()));
+            }
+        }
+        "ts" | "tsx" | "js" | "jsx" | "mjs" => {
+            for imp in imports {
+                out.push_str(&format!("import * as {0} from './{0}';\n", stem(imp)));
+            }
+            out.push('\n');
+            for sym in symbols {
+                out.push_str(&format!(
+                    "export function {sym}() {{\n  return {};\n}}\n\n",
+                    sym.len()
+                ));
+            }
+        }
+        "go" => {
+            out.push_str("package main\n\n");
+            for imp in imports {
+                out.push_str(&format!(
+                    "import \"{}\"\n",
+                    imp.rsplit_once('.').map(|(s, _)| s).unwrap_or(imp)
+                ));
+            }
+            out.push('\n');
+            for sym in symbols {
+                out.push_str(&format!("func {sym}() int {{\n\treturn {}\n}}\n\n", sym.len()));
+            }
+        }
+        "c" | "cc" | "cpp" | "h" | "hpp" => {
+            for imp in imports {
+                let filename = imp.rsplit('/').next().unwrap_or(imp);
+                out.push_str(&format!("#include \"{filename}\"\n"));
+            }
+            out.push('\n');
+            for sym in symbols {
+                out.push_str(&format!("int {sym}(void) {{\n    return {};\n}}\n\n", sym.len()));
+            }
+        }
+        _ => {
+            for imp in imports {
+                out.push_str(&format!("# import {imp}\n"));
+            }
+            for sym in symbols {
+                out.push_str(&format!("{sym}\n"));
+            }
+        }
+    }
+    out
+}
+
+fn commit_all(repo: &Repository, author: &str, message: &str, time: i64) {
+    let mut index = repo.index().unwrap();
+    index.add_all(["*"], git2::IndexAddOption::DEFAULT, None).unwrap();
+    // Stage deletions too, so `remove` + `commit` records the removal.
+    index.update_all(["*"], None).unwrap();
+    index.write().unwrap();
+    let tree_id = index.write_tree().unwrap();
+    let tree = repo.find_tree(tree_id).unwrap();
+
+    let email = format!("{}@fixture.test", author.to_lowercase().replace(' ', "."));
+    let sig = Signature::new(author, &email, &Time::new(time, 0)).unwrap();
+    let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+    let parents: Vec<&git2::Commit> = parent.iter().collect();
+    repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents).unwrap();
+}
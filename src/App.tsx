@@ -196,7 +196,7 @@ export default function App() {
     if (!tree) return;
     const topLevel = new Set<string>();
     for (const key of Object.keys(tree)) {
-      if (key !== '_files') topLevel.add(key);
+      if (key !== '_files' && key !== '_sort') topLevel.add(key);
     }
     setExpanded(topLevel);
   }, [tree]);
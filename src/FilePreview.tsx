@@ -58,6 +58,18 @@ export default function FilePreview({ path, manifest, selected, onClose, onToggl
                   <span className="preview-truncated">truncated to 512KB</span>
                 </>
               )}
+              {data.encoding && (
+                <>
+                  <span className="preview-dot"> · </span>
+                  <span>{data.encoding}</span>
+                </>
+              )}
+              {data.redacted > 0 && (
+                <>
+                  <span className="preview-dot"> · </span>
+                  <span className="preview-truncated">{data.redacted} secrets redacted</span>
+                </>
+              )}
             </>
           )}
         </div>
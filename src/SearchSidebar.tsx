@@ -1,7 +1,7 @@
 import { useState, useEffect, useRef, useMemo, useCallback } from 'react';
 import { useVirtualizer } from '@tanstack/react-virtual';
 import { FileIcon } from './icons';
-import { HighlightedText, EMPTY_FIND } from './search-utils';
+import { HighlightedText, EMPTY_FIND, rangeIndices } from './search-utils';
 import { getExtColor } from './colors';
 import type { FindResponse, FindResult } from './types';
 import './styles/sidebar.css';
@@ -263,7 +263,15 @@ export default function SearchSidebar({
                   {item.topMatch && (
                     <div className="search-card-snippet">
                       {item.topMatchLine && <span className="snippet-linenum">{item.topMatchLine}</span>}
-                      <span className="snippet-text">{item.topMatch.trim()}</span>
+                      <span className="snippet-text">
+                        <HighlightedText
+                          text={item.topMatch.trim()}
+                          indices={rangeIndices(
+                            item.topMatchRanges ?? [],
+                            item.topMatch.length - item.topMatch.trimStart().length,
+                          )}
+                        />
+                      </span>
                     </div>
                   )}
                 </div>
@@ -63,7 +63,7 @@ function computeStats(manifest: Manifest, tree: TreeNode | null): Stats | null {
   let moduleCount = 0;
   if (tree) {
     for (const key of Object.keys(tree)) {
-      if (key !== '_files') moduleCount++;
+      if (key !== '_files' && key !== '_sort') moduleCount++;
     }
   }
 
@@ -1,10 +1,10 @@
 import { useRef, useMemo, useCallback, useState, useEffect } from 'react';
 import { useVirtualizer } from '@tanstack/react-virtual';
 import { FileIcon } from './icons';
-import { getExt, getFilename } from './utils';
+import { compareNames, getExt, getFilename } from './utils';
 import { estimateTokens, formatTokenCount } from './tokenCount';
 import { copyToClipboard, buildSmartContext, buildFullContents, buildPathsOnly } from './copyLogic';
-import type { TreeNode, FlatTreeRow, Manifest } from './types';
+import type { TreeNode, FlatTreeRow, Manifest, SortOrder } from './types';
 import './styles/sidebar.css';
 
 const MODEL_LIMITS: Record<string, { name: string; tokens: number }> = {
@@ -37,18 +37,26 @@ interface NodeMeta {
   fileCount: number;
 }
 
-function buildMetaCache(node: TreeNode, prefix: string, cache: Map<string, NodeMeta>): number {
+function buildMetaCache(
+  node: TreeNode,
+  prefix: string,
+  cache: Map<string, NodeMeta>,
+  inheritedOrder: SortOrder = 'natural',
+): number {
   let fileCount = (node._files as any[] || []).length;
   const children: NodeMeta['children'] = [];
+  // Each repo's root carries its sort_order; descendants inherit it
+  const order = typeof node._sort === 'string' ? node._sort : inheritedOrder;
+  const compare = compareNames(order);
 
   const entries = Object.entries(node)
     .filter(([k, v]) => k !== '_files' && typeof v === 'object' && !Array.isArray(v))
-    .sort(([a], [b]) => a.localeCompare(b));
+    .sort(([a], [b]) => compare(a, b));
 
   for (const [name, child] of entries) {
     const childNode = child as TreeNode;
     const id = prefix ? `${prefix} > ${name}` : name;
-    const childCount = buildMetaCache(childNode, id, cache);
+    const childCount = buildMetaCache(childNode, id, cache, order);
     fileCount += childCount;
     const hasChildren = Object.keys(childNode).some(k => k !== '_files' && typeof childNode[k] === 'object' && !Array.isArray(childNode[k]));
     children.push({ name, id, node: childNode, hasChildren });
@@ -1,4 +1,4 @@
-import type { FindResponse } from './types';
+import type { FindResponse, MatchRange } from './types';
 
 export const EMPTY_FIND: FindResponse = { results: [], queryTime: 0, extCounts: {}, catCounts: {} };
 
@@ -25,3 +25,12 @@ export function HighlightedText({ text, indices }: { text: string; indices: numb
   }
   return <>{parts}</>;
 }
+
+/** Expand snippet match ranges into per-character indices, shifted by `offset`. */
+export function rangeIndices(ranges: MatchRange[], offset = 0): number[] {
+  const indices: number[] = [];
+  for (const r of ranges) {
+    for (let i = r.charStart; i < r.charEnd; i++) indices.push(i - offset);
+  }
+  return indices;
+}
@@ -4,8 +4,12 @@ export interface FileEntry {
   size: number;
 }
 
+/** Listing order set by `sort_order` in .codescope.toml. */
+export type SortOrder = 'natural' | 'bytes';
+
+/** Module tree from /api/tree: child modules by name, `_files`, and `_sort` on a repo's root. */
 export interface TreeNode {
-  [key: string]: TreeNode | FileEntry[];
+  [key: string]: TreeNode | FileEntry[] | SortOrder;
 }
 
 export type Manifest = Record<string, FileEntry[]>;
@@ -64,6 +68,8 @@ export interface FileContentResponse {
   size: number;
   path: string;
   truncated: boolean;
+  redacted: number;
+  encoding: string | null;
 }
 
 // Content search (grep)
@@ -101,9 +107,19 @@ export interface FindResult {
   grepCount: number;
   topMatch: string | null;
   topMatchLine: number | null;
+  topMatchRanges: MatchRange[];
   filenameIndices: number[];
 }
 
+// Occurrence of a query term in a snippet (byte offsets for UTF-8, char offsets for JS)
+export interface MatchRange {
+  term: string;
+  byteStart: number;
+  byteEnd: number;
+  charStart: number;
+  charEnd: number;
+}
+
 export interface FindResponse {
   results: FindResult[];
   queryTime: number;
@@ -125,11 +141,21 @@ export type CopyMode = 'paths' | 'contents' | 'headers' | 'context';
 export interface ContextFileEntry {
   content: string;
   tier: number;
+  tierName: string;
   tokens: number;
+  chars: number;
   importance: number;
   order: number;
 }
 
+export interface TierSummary {
+  tier: number;
+  name: string;
+  files: number;
+  tokens: number;
+  chars: number;
+}
+
 export interface ContextSummary {
   totalTokens: number;
   totalChars: number;
@@ -137,6 +163,7 @@ export interface ContextSummary {
   unit: 'tokens' | 'chars';
   tierCounts: Record<string, number>;
   totalFiles: number;
+  tiers: TierSummary[];
 }
 
 export interface ContextResponse {
@@ -1,4 +1,13 @@
-import type { Manifest } from './types';
+import type { Manifest, SortOrder } from './types';
+
+const naturalCollator = new Intl.Collator(undefined, { numeric: true, sensitivity: 'base' });
+
+/** Name comparator for a listing order. Natural ties fall back to code units, like the server. */
+export function compareNames(order: SortOrder): (a: string, b: string) => number {
+  const bytes = (a: string, b: string) => (a < b ? -1 : a > b ? 1 : 0);
+  if (order === 'bytes') return bytes;
+  return (a, b) => naturalCollator.compare(a, b) || bytes(a, b);
+}
 
 /** Get all file paths belonging to a module (including child modules). */
 export function getFilesForModule(moduleId: string, manifest: Manifest): string[] {